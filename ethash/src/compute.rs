@@ -97,6 +97,16 @@ impl Light {
 		light_new(block_number)
 	}
 
+	/// Create an empty light cache which can't be used for computation, only to test caching.
+	#[cfg(test)]
+	pub fn fake(block_number: u64) -> Light {
+		Light {
+			block_number: block_number,
+			cache: Vec::new(),
+			seed_compute: Mutex::new(SeedHashCompute::new()),
+		}
+	}
+
 	/// Calculate the light boundary data
 	/// `header_hash` - The header hash to pack into the mix
 	/// `nonce` - The nonce to pack into the mix
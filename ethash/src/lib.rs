@@ -23,10 +23,15 @@ extern crate log;
 mod compute;
 
 use std::mem;
+use std::thread;
 use compute::Light;
 pub use compute::{ETHASH_EPOCH_LENGTH, H256, ProofOfWork, SeedHashCompute, quick_get_difficulty};
 
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Default number of blocks before epoch transition at which the light cache of the next epoch is prepared.
+pub const ETHASH_PRECOMPUTE_THRESHOLD: u64 = 1000;
 
 struct LightCache {
 	recent_epoch: Option<u64>,
@@ -35,21 +40,135 @@ struct LightCache {
 	prev: Option<Arc<Light>>,
 }
 
+impl LightCache {
+	/// Returns cached light of given epoch, making it the most recent one.
+	fn get(&mut self, epoch: u64) -> Option<Arc<Light>> {
+		match (self.recent_epoch, self.prev_epoch) {
+			(Some(e), _) if e == epoch => self.recent.clone(),
+			(_, Some(e)) if e == epoch => {
+				mem::swap(&mut self.prev_epoch, &mut self.recent_epoch);
+				mem::swap(&mut self.prev, &mut self.recent);
+				self.recent.clone()
+			},
+			_ => None,
+		}
+	}
+
+	fn contains(&self, epoch: u64) -> bool {
+		self.recent_epoch == Some(epoch) || self.prev_epoch == Some(epoch)
+	}
+
+	fn insert(&mut self, epoch: u64, light: Arc<Light>) {
+		self.prev_epoch = mem::replace(&mut self.recent_epoch, Some(epoch));
+		self.prev = mem::replace(&mut self.recent, Some(light));
+	}
+}
+
+/// Loads light cache of the epoch of `block_number` from file or generates it.
+fn load_light(block_number: u64) -> Arc<Light> {
+	match Light::from_file(block_number) {
+		Ok(light) => Arc::new(light),
+		Err(e) => {
+			debug!("Light cache file not found for {}:{}", block_number, e);
+			let light = Light::new(block_number);
+			if let Err(e) = light.to_file() {
+				warn!("Light cache file write error: {}", e);
+			}
+			Arc::new(light)
+		}
+	}
+}
+
+/// Loads light cache of the epoch of given block.
+type LightLoader = Arc<Fn(u64) -> Arc<Light> + Send + Sync>;
+
 /// Light/Full cache manager.
 pub struct EthashManager {
-	cache: Mutex<LightCache>,
+	cache: Arc<Mutex<LightCache>>,
+	precompute_threshold: u64,
+	precomputing: Arc<AtomicBool>,
+	epoch_length: u64,
+	loader: LightLoader,
 }
 
 impl EthashManager {
 	/// Create a new new instance of ethash manager
 	pub fn new() -> EthashManager {
+		EthashManager::with_precompute_threshold(ETHASH_PRECOMPUTE_THRESHOLD)
+	}
+
+	/// Create a new instance of ethash manager preparing light cache of the next epoch
+	/// when `threshold` or less blocks are left until the epoch transition.
+	pub fn with_precompute_threshold(threshold: u64) -> EthashManager {
+		EthashManager::with_epochs(ETHASH_EPOCH_LENGTH, threshold, Arc::new(load_light))
+	}
+
+	fn with_epochs(epoch_length: u64, threshold: u64, loader: LightLoader) -> EthashManager {
 		EthashManager {
-			cache: Mutex::new(LightCache {
+			cache: Arc::new(Mutex::new(LightCache {
 				recent_epoch: None,
 				recent: None,
 				prev_epoch: None,
 				prev: None,
-			}),
+			})),
+			precompute_threshold: threshold,
+			precomputing: Arc::new(AtomicBool::new(false)),
+			epoch_length: epoch_length,
+			loader: loader,
+		}
+	}
+
+	/// Checks if light cache of the epoch of `block_number` is already available.
+	pub fn is_epoch_ready(&self, block_number: u64) -> bool {
+		self.cache.lock().unwrap().contains(block_number / self.epoch_length)
+	}
+
+	/// Notes that block `block_number` is being processed.
+	/// Starts preparing light cache of the next epoch in background if the transition is close.
+	/// Returns handle of the preparing thread if it was started.
+	pub fn note_block(&self, block_number: u64) -> Option<thread::JoinHandle<()>> {
+		let next_epoch_start = (block_number / self.epoch_length + 1) * self.epoch_length;
+		if next_epoch_start - block_number > self.precompute_threshold || self.is_epoch_ready(next_epoch_start) {
+			return None;
+		}
+		if self.precomputing.compare_and_swap(false, true, Ordering::SeqCst) {
+			return None;
+		}
+
+		let cache = self.cache.clone();
+		let precomputing = self.precomputing.clone();
+		let loader = self.loader.clone();
+		let epoch = next_epoch_start / self.epoch_length;
+		let spawned = thread::Builder::new().name("ethash-precompute".into()).spawn(move || {
+			trace!("Preparing light cache for block {}", next_epoch_start);
+			let light = loader(next_epoch_start);
+			let mut cache = cache.lock().unwrap();
+			if !cache.contains(epoch) {
+				cache.insert(epoch, light);
+			}
+			precomputing.store(false, Ordering::SeqCst);
+		});
+		match spawned {
+			Ok(handle) => Some(handle),
+			Err(e) => {
+				warn!("Unable to spawn light cache precomputation thread: {}", e);
+				self.precomputing.store(false, Ordering::SeqCst);
+				None
+			}
+		}
+	}
+
+	/// Returns light cache of the epoch of `block_number`, loading it if it's not cached.
+	fn light(&self, block_number: u64) -> Arc<Light> {
+		let epoch = block_number / self.epoch_length;
+		let mut lights = self.cache.lock().unwrap();
+		match lights.get(epoch) {
+			Some(light) => light,
+			None => {
+				let light = (self.loader)(block_number);
+				lights.insert(epoch, light.clone());
+				light
+			}
 		}
 	}
 
@@ -59,46 +178,7 @@ impl EthashManager {
 	/// `header_hash` - The header hash to pack into the mix
 	/// `nonce` - The nonce to pack into the mix
 	pub fn compute_light(&self, block_number: u64, header_hash: &H256, nonce: u64) -> ProofOfWork {
-		let epoch = block_number / ETHASH_EPOCH_LENGTH;
-		let light = {
-			let mut lights = self.cache.lock().unwrap();
-			let light = match lights.recent_epoch.clone() {
-				Some(ref e) if *e == epoch => lights.recent.clone(),
-				_ => match lights.prev_epoch.clone() {
-					Some(e) if e == epoch => {
-						// swap
-						let t = lights.prev_epoch;
-						lights.prev_epoch = lights.recent_epoch;
-						lights.recent_epoch = t;
-						let t = lights.prev.clone();
-						lights.prev = lights.recent.clone();
-						lights.recent = t;
-						lights.recent.clone()
-					}
-					_ => None,
-				},
-			};
-			match light {
-				None => {
-					let light = match Light::from_file(block_number) {
-						Ok(light) => Arc::new(light),
-						Err(e) => {
-							debug!("Light cache file not found for {}:{}", block_number, e);
-							let light = Light::new(block_number);
-							if let Err(e) = light.to_file() {
-								warn!("Light cache file write error: {}", e);
-							}
-							Arc::new(light)
-						}
-					};
-					lights.prev_epoch = mem::replace(&mut lights.recent_epoch, Some(epoch));
-					lights.prev = mem::replace(&mut lights.recent, Some(light.clone()));
-					light
-				}
-				Some(light) => light,
-			}
-		};
-		light.compute(header_hash, nonce)
+		self.light(block_number).compute(header_hash, nonce)
 	}
 }
 
@@ -117,3 +197,30 @@ fn test_lru() {
 	assert_eq!(ethash.cache.lock().unwrap().recent_epoch.unwrap(), 2);
 	assert_eq!(ethash.cache.lock().unwrap().prev_epoch.unwrap(), 0);
 }
+
+#[test]
+fn test_precompute_next_epoch() {
+	use std::sync::atomic::AtomicUsize;
+
+	// given
+	let loads = Arc::new(AtomicUsize::new(0));
+	let counter = loads.clone();
+	let ethash = EthashManager::with_epochs(10, 3, Arc::new(move |block_number| {
+		counter.fetch_add(1, Ordering::SeqCst);
+		Arc::new(Light::fake(block_number))
+	}));
+	ethash.light(5);
+
+	// when
+	assert!(ethash.note_block(5).is_none(), "Next epoch shouldn't be prepared before the threshold");
+	assert!(!ethash.is_epoch_ready(10));
+	ethash.note_block(7).expect("Next epoch should be prepared").join().unwrap();
+
+	// then
+	assert!(ethash.is_epoch_ready(10));
+	assert_eq!(loads.load(Ordering::SeqCst), 2);
+	ethash.light(9);
+	ethash.light(10);
+	assert_eq!(loads.load(Ordering::SeqCst), 2, "Light cache shouldn't be recomputed at the transition");
+	assert!(ethash.note_block(8).is_none());
+}
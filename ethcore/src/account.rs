@@ -205,10 +205,13 @@ impl Account {
 	}
 
 	/// Commit the `storage_overlay` to the backing DB and update `storage_root`.
-	pub fn commit_storage(&mut self, db: &mut AccountDBMut) {
+	/// Returns `true` if any storage entry was written.
+	pub fn commit_storage(&mut self, db: &mut AccountDBMut) -> bool {
+		let mut changed = false;
 		let mut t = SecTrieDBMut::from_existing(db, &mut self.storage_root);
 		for (k, &mut (ref mut f, ref mut v)) in self.storage_overlay.borrow_mut().iter_mut() {
 			if f == &Filth::Dirty {
+				changed = true;
 				// cast key and value to trait type,
 				// so we can call overloaded `to_bytes` method
 				match v.is_zero() {
@@ -218,17 +221,23 @@ impl Account {
 				*f = Filth::Clean;
 			}
 		}
+		changed
 	}
 
 	/// Commit any unsaved code. `code_hash` will always return the hash of the `code_cache` after this.
-	pub fn commit_code(&mut self, db: &mut AccountDBMut) {
+	/// Returns `true` if the code was written.
+	pub fn commit_code(&mut self, db: &mut AccountDBMut) -> bool {
 		trace!("Commiting code of {:?} - {:?}, {:?}", self, self.code_hash.is_none(), self.code_cache.is_empty());
 		match (self.code_hash.is_none(), self.code_cache.is_empty()) {
-			(true, true) => self.code_hash = Some(SHA3_EMPTY),
+			(true, true) => {
+				self.code_hash = Some(SHA3_EMPTY);
+				false
+			},
 			(true, false) => {
 				self.code_hash = Some(db.insert(&self.code_cache));
+				true
 			},
-			(false, _) => {},
+			(false, _) => false,
 		}
 	}
 
@@ -17,7 +17,6 @@
 //! Diff between two accounts.
 
 use util::*;
-#[cfg(test)]
 use pod_account::*;
 
 #[derive(Debug,Clone,PartialEq,Eq)]
@@ -66,7 +65,6 @@ impl AccountDiff {
 		}
 	}
 
-	#[cfg(test)]
 	/// Determine difference between two optionally existant `Account`s. Returns None
 	/// if they are the same.
 	pub fn diff_pod(pre: Option<&PodAccount>, post: Option<&PodAccount>) -> Option<AccountDiff> {
@@ -108,6 +106,55 @@ impl AccountDiff {
 	}
 }
 
+fn append_diff<T>(s: &mut RlpStream, diff: &Diff<T>) where T: Eq + RlpEncodable {
+	match *diff {
+		Diff::Same => { s.begin_list(1).append(&0u32); },
+		Diff::Born(ref x) => { s.begin_list(2).append(&1u32).append(x); },
+		Diff::Changed(ref pre, ref post) => { s.begin_list(3).append(&2u32).append(pre).append(post); },
+		Diff::Died(ref x) => { s.begin_list(2).append(&3u32).append(x); },
+	}
+}
+
+fn decode_diff<T>(d: &UntrustedRlp) -> Result<Diff<T>, DecoderError> where T: Eq + RlpDecodable {
+	match try!(d.val_at::<u32>(0)) {
+		0 => Ok(Diff::Same),
+		1 => Ok(Diff::Born(try!(d.val_at(1)))),
+		2 => Ok(Diff::Changed(try!(d.val_at(1)), try!(d.val_at(2)))),
+		3 => Ok(Diff::Died(try!(d.val_at(1)))),
+		_ => Err(DecoderError::Custom("Unknown diff type.")),
+	}
+}
+
+impl Encodable for AccountDiff {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(4);
+		append_diff(s, &self.balance);
+		append_diff(s, &self.nonce);
+		append_diff(s, &self.code);
+		s.begin_list(self.storage.len());
+		for (k, v) in &self.storage {
+			s.begin_list(2).append(k);
+			append_diff(s, v);
+		}
+	}
+}
+
+impl Decodable for AccountDiff {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let d = decoder.as_rlp();
+		let mut storage = BTreeMap::new();
+		for item in try!(d.at(3)).iter() {
+			storage.insert(try!(item.val_at(0)), try!(decode_diff(&try!(item.at(1)))));
+		}
+		Ok(AccountDiff {
+			balance: try!(decode_diff(&try!(d.at(0)))),
+			nonce: try!(decode_diff(&try!(d.at(1)))),
+			code: try!(decode_diff(&try!(d.at(2)))),
+			storage: storage,
+		})
+	}
+}
+
 // TODO: refactor into something nicer.
 fn interpreted_hash(u: &H256) -> String {
 	if u <= &H256::from(0xffffffff) {
@@ -22,6 +22,7 @@ use block::*;
 use spec::{CommonParams, Spec};
 use engine::*;
 use evm::Schedule;
+use gas_limit::GasLimitTarget;
 use ethjson;
 
 /// `BasicAuthority` params.
@@ -80,17 +81,9 @@ impl Engine for BasicAuthority {
 		Schedule::new_homestead()
 	}
 
-	fn populate_from_parent(&self, header: &mut Header, parent: &Header, gas_floor_target: U256) {
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header, gas_limit_target: GasLimitTarget) {
 		header.difficulty = parent.difficulty;
-		header.gas_limit = {
-			let gas_limit = parent.gas_limit;
-			let bound_divisor = self.our_params.gas_limit_bound_divisor;
-			if gas_limit < gas_floor_target {
-				min(gas_floor_target, gas_limit + gas_limit / bound_divisor - x!(1))
-			} else {
-				max(gas_floor_target, gas_limit - gas_limit / bound_divisor + x!(1))
-			}
-		};
+		header.gas_limit = gas_limit_target.child_gas_limit(parent.gas_limit, self.our_params.gas_limit_bound_divisor, self.params().min_gas_limit);
 		header.note_dirty();
 //		info!("ethash: populate_from_parent #{}: difficulty={} and gas_limit={}", header.number, header.difficulty, header.gas_limit);
 	}
@@ -197,6 +190,7 @@ mod tests {
 	use common::*;
 	use block::*;
 	use tests::helpers::*;
+	use gas_limit::GasLimitTarget;
 	use util::keys::{TestAccountProvider, TestAccount};
 
 	#[test]
@@ -278,7 +272,7 @@ mod tests {
 		spec.ensure_db_good(db.as_hashdb_mut());
 		let last_hashes = vec![genesis_header.hash()];
 		let vm_factory = Default::default();
-		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, addr.clone(), x!(3141562), vec![]);
+		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, addr.clone(), GasLimitTarget::fixed(x!(3141562)), vec![]);
 		let b = b.close_and_lock();
 		let seal = engine.generate_seal(b.block(), Some(&tap)).unwrap();
 
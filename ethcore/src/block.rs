@@ -24,6 +24,7 @@ use state::*;
 use verification::PreverifiedBlock;
 use trace::Trace;
 use evm::Factory as EvmFactory;
+use gas_limit::GasLimitTarget;
 
 /// A block, encoded as it is on the block chain.
 #[derive(Default, Debug, Clone)]
@@ -228,7 +229,7 @@ pub struct SealedBlock {
 impl<'x> OpenBlock<'x> {
 	#[cfg_attr(feature="dev", allow(too_many_arguments))]
 	/// Create a new `OpenBlock` ready for transaction pushing.
-	pub fn new(engine: &'x Engine, vm_factory: &'x EvmFactory, tracing: bool, db: Box<JournalDB>, parent: &Header, last_hashes: LastHashes, author: Address, gas_floor_target: U256, extra_data: Bytes) -> Self {
+	pub fn new(engine: &'x Engine, vm_factory: &'x EvmFactory, tracing: bool, db: Box<JournalDB>, parent: &Header, last_hashes: LastHashes, author: Address, gas_limit_target: GasLimitTarget, extra_data: Bytes) -> Self {
 		let mut r = OpenBlock {
 			block: ExecutedBlock::new(State::from_existing(db, parent.state_root().clone(), engine.account_start_nonce()), tracing),
 			engine: engine,
@@ -243,7 +244,7 @@ impl<'x> OpenBlock<'x> {
 		r.block.base.header.extra_data = extra_data;
 		r.block.base.header.note_dirty();
 
-		engine.populate_from_parent(&mut r.block.base.header, parent, gas_floor_target);
+		engine.populate_from_parent(&mut r.block.base.header, parent, gas_limit_target);
 		engine.on_new_block(&mut r.block);
 		r
 	}
@@ -281,8 +282,23 @@ impl<'x> OpenBlock<'x> {
 		if self.block.base.uncles.len() + 1 > self.engine.maximum_uncle_count() {
 			return Err(BlockError::TooManyUncles(OutOfBounds{min: None, max: Some(self.engine.maximum_uncle_count()), found: self.block.base.uncles.len() + 1}));
 		}
-		// TODO: check number
-		// TODO: check not a direct ancestor (use last_hashes for that)
+
+		let number = self.block.base.header.number;
+		let uncle_number = valid_uncle_header.number;
+		let depth = if number > uncle_number { number - uncle_number } else { 0 };
+		let max_age = self.engine.maximum_uncle_age() as u64;
+		let bounds = |found| OutOfBounds { min: Some(number.saturating_sub(max_age)), max: Some(number.saturating_sub(1)), found: found };
+		if depth > max_age {
+			return Err(BlockError::UncleTooOld(bounds(uncle_number)));
+		} else if depth < 1 {
+			return Err(BlockError::UncleIsBrother(bounds(uncle_number)));
+		}
+
+		let hash = valid_uncle_header.hash();
+		if self.last_hashes.contains(&hash) || self.block.base.uncles.iter().any(|u| u.hash() == hash) {
+			return Err(BlockError::UncleInChain(hash));
+		}
+
 		self.block.base.uncles.push(valid_uncle_header);
 		Ok(())
 	}
@@ -311,8 +327,22 @@ impl<'x> OpenBlock<'x> {
 
 		let env_info = self.env_info();
 //		info!("env_info says gas_used={}", env_info.gas_used);
+		// Never let the block go over its gas limit, regardless of what the caller selected.
+		if env_info.gas_used + t.gas > env_info.gas_limit {
+			return Err(From::from(ExecutionError::BlockGasLimitReached {
+				gas_limit: env_info.gas_limit,
+				gas_used: env_info.gas_used,
+				gas: t.gas,
+			}));
+		}
+
 		match self.block.state.apply(&env_info, self.engine, self.vm_factory, &t, self.block.traces.is_some()) {
 			Ok(outcome) => {
+				debug_assert!(outcome.receipt.gas_used <= env_info.gas_limit, "Cumulative gas used exceeds block gas limit");
+				if outcome.receipt.gas_used > env_info.gas_limit {
+					warn!(target: "miner", "Block gas limit exceeded after pushing transaction {:?}: {} > {}",
+						t.hash(), outcome.receipt.gas_used, env_info.gas_limit);
+				}
 				self.block.transactions_set.insert(h.unwrap_or_else(||t.hash()));
 				self.block.base.transactions.push(t);
 				let t = outcome.trace;
@@ -324,6 +354,74 @@ impl<'x> OpenBlock<'x> {
 		}
 	}
 
+	/// Push transactions into the block, either all of them (in given order) or none.
+	///
+	/// Fails without executing anything if transactions don't fit into remaining block gas.
+	/// If any transaction fails, the block is reverted to the state before the first one was pushed.
+	pub fn push_transactions_atomically(&mut self, transactions: Vec<SignedTransaction>) -> Result<(), Error> {
+		let env_info = self.env_info();
+		let gas = transactions.iter().fold(U256::zero(), |sum, t| sum + t.gas);
+		if env_info.gas_used + gas > env_info.gas_limit {
+			return Err(From::from(ExecutionError::BlockGasLimitReached {
+				gas_limit: env_info.gas_limit,
+				gas_used: env_info.gas_used,
+				gas: gas,
+			}));
+		}
+
+		let checkpoint = self.block.clone();
+		for t in transactions {
+			let result = self.push_transaction(t, None).map(|_| ());
+			if let Err(e) = result {
+				self.block = checkpoint;
+				return Err(e);
+			}
+		}
+		Ok(())
+	}
+
+	/// Push transactions into the block one by one, skipping those which can't be included.
+	///
+	/// Once a transaction doesn't fit into remaining block gas, later transactions of the same sender
+	/// are skipped without being executed (they would leave a nonce gap).
+	/// Returns hashes of invalid transactions.
+	pub fn push_transactions(&mut self, transactions: Vec<SignedTransaction>) -> HashSet<H256> {
+		let block_number = self.block.base.header.number();
+		let min_tx_gas = U256::from(self.engine.schedule(&self.env_info()).tx_gas);
+		let mut invalid_transactions = HashSet::new();
+		let mut skipped_senders = HashSet::new();
+
+		for tx in transactions {
+			let hash = tx.hash();
+			let sender = tx.sender().ok();
+			if sender.as_ref().map_or(false, |s| skipped_senders.contains(s)) {
+				trace!(target: "miner", "Skipping transaction of a sender with previous transaction not included: {:?}", hash);
+				continue;
+			}
+			match self.push_transaction(tx, None) {
+				Err(Error::Execution(ExecutionError::BlockGasLimitReached { gas_limit, gas_used, .. })) => {
+					trace!(target: "miner", "Skipping adding transaction to block because of gas limit: {:?}", hash);
+					if let Some(sender) = sender {
+						skipped_senders.insert(sender);
+					}
+					// Exit early if gas left is smaller then min_tx_gas
+					if gas_limit - gas_used < min_tx_gas {
+						break;
+					}
+				},
+				Err(Error::Transaction(TransactionError::AlreadyImported)) => {},	// already have transaction - ignore
+				Err(e) => {
+					invalid_transactions.insert(hash);
+					trace!(target: "miner",
+						   "Error adding transaction to block: number={}. transaction_hash={:?}, Error: {:?}",
+						   block_number, hash, e);
+				},
+				Ok(_) => {},
+			}
+		}
+		invalid_transactions
+	}
+
 	/// Turn this into a `ClosedBlock`. A `BlockChain` must be provided in order to figure out the uncles.
 	pub fn close(self) -> ClosedBlock {
 		let mut s = self;
@@ -469,7 +567,7 @@ pub fn enact(header: &Header, transactions: &[SignedTransaction], uncles: &[Head
 		}
 	}
 
-	let mut b = OpenBlock::new(engine, vm_factory, tracing, db, parent, last_hashes, header.author().clone(), x!(3141562), header.extra_data().clone());
+	let mut b = OpenBlock::new(engine, vm_factory, tracing, db, parent, last_hashes, header.author().clone(), GasLimitTarget::fixed(x!(3141562)), header.extra_data().clone());
 	b.set_difficulty(*header.difficulty());
 	b.set_gas_limit(*header.gas_limit());
 	b.set_timestamp(header.timestamp());
@@ -502,6 +600,7 @@ mod tests {
 	use tests::helpers::*;
 	use super::*;
 	use common::*;
+	use gas_limit::GasLimitTarget;
 
 	#[test]
 	fn open_block() {
@@ -514,11 +613,116 @@ mod tests {
 		spec.ensure_db_good(db.as_hashdb_mut());
 		let last_hashes = vec![genesis_header.hash()];
 		let vm_factory = Default::default();
-		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), x!(3141562), vec![]);
+		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
 		let b = b.close_and_lock();
 		let _ = b.seal(engine.deref(), vec![]);
 	}
 
+	#[test]
+	fn should_not_push_transaction_exceeding_remaining_block_gas() {
+		use spec::*;
+		let spec = Spec::new_test();
+		let engine = &spec.engine;
+		let genesis_header = spec.genesis_header();
+		let mut db_result = get_temp_journal_db();
+		let mut db = db_result.take();
+		spec.ensure_db_good(db.as_hashdb_mut());
+		let last_hashes = vec![genesis_header.hash()];
+		let vm_factory = Default::default();
+		let mut b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
+		b.set_gas_limit(x!(50_000));
+
+		let keypair = KeyPair::create().unwrap();
+		let nonce = b.block.state.nonce(&keypair.address());
+		let tx = |nonce: U256| Transaction {
+			action: Action::Call(Address::zero()),
+			value: U256::zero(),
+			data: vec![],
+			gas: x!(30_000),
+			gas_price: U256::zero(),
+			nonce: nonce,
+		}.sign(&keypair.secret());
+
+		// first one fits, but second one would go over the limit
+		assert!(b.push_transaction(tx(nonce), None).is_ok());
+		match b.push_transaction(tx(nonce + U256::one()), None) {
+			Err(Error::Execution(ExecutionError::BlockGasLimitReached { gas_limit, gas, .. })) => {
+				assert_eq!(gas_limit, x!(50_000));
+				assert_eq!(gas, x!(30_000));
+			},
+			_ => panic!("Expected BlockGasLimitReached error."),
+		}
+
+		let b = b.close();
+		assert_eq!(b.transactions().len(), 1);
+		assert!(b.header().gas_used() <= b.header().gas_limit());
+	}
+
+	#[test]
+	fn should_push_all_or_none_of_transactions_atomically() {
+		use spec::*;
+		let spec = Spec::new_test();
+		let engine = &spec.engine;
+		let genesis_header = spec.genesis_header();
+		let mut db_result = get_temp_journal_db();
+		let mut db = db_result.take();
+		spec.ensure_db_good(db.as_hashdb_mut());
+		let last_hashes = vec![genesis_header.hash()];
+		let vm_factory = Default::default();
+		let mut b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
+		b.set_gas_limit(x!(100_000));
+
+		let keypair = KeyPair::create().unwrap();
+		let nonce = b.block.state.nonce(&keypair.address());
+		let tx = |nonce: U256, gas: u64| Transaction {
+			action: Action::Call(Address::zero()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(gas),
+			gas_price: U256::zero(),
+			nonce: nonce,
+		}.sign(&keypair.secret());
+
+		// second transaction has invalid nonce, so the first one is reverted as well
+		assert!(b.push_transactions_atomically(vec![tx(nonce, 30_000), tx(nonce + x!(2), 30_000)]).is_err());
+		assert_eq!(b.block.base.transactions.len(), 0);
+		assert_eq!(b.block.state.nonce(&keypair.address()), nonce);
+
+		// transactions don't fit into the block
+		match b.push_transactions_atomically(vec![tx(nonce, 60_000), tx(nonce + U256::one(), 60_000)]) {
+			Err(Error::Execution(ExecutionError::BlockGasLimitReached { gas, .. })) => assert_eq!(gas, x!(120_000)),
+			_ => panic!("Expected BlockGasLimitReached error."),
+		}
+
+		assert!(b.push_transactions_atomically(vec![tx(nonce, 30_000), tx(nonce + U256::one(), 30_000)]).is_ok());
+		let b = b.close();
+		assert_eq!(b.transactions().len(), 2);
+	}
+
+	#[test]
+	fn should_reject_invalid_uncles() {
+		use spec::*;
+		let spec = Spec::new_test();
+		let engine = &spec.engine;
+		let genesis_header = spec.genesis_header();
+		let mut db_result = get_temp_journal_db();
+		let mut db = db_result.take();
+		spec.ensure_db_good(db.as_hashdb_mut());
+		let last_hashes = vec![genesis_header.hash()];
+		let vm_factory = Default::default();
+		let mut b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
+		let mut uncle = Header::new();
+		uncle.extra_data = b"uncle".to_vec();
+		let mut brother = uncle.clone();
+		brother.number = 1;
+
+		assert_eq!(b.push_uncle(brother), Err(BlockError::UncleIsBrother(OutOfBounds { min: Some(0), max: Some(0), found: 1 })));
+		assert_eq!(b.push_uncle(genesis_header.clone()), Err(BlockError::UncleInChain(genesis_header.hash())));
+		assert!(b.push_uncle(uncle.clone()).is_ok());
+		assert_eq!(b.push_uncle(uncle.clone()), Err(BlockError::UncleInChain(uncle.hash())));
+		assert_eq!(b.uncles().len(), 1);
+	}
+
 	#[test]
 	fn enact_block() {
 		use spec::*;
@@ -530,7 +734,7 @@ mod tests {
 		let mut db = db_result.take();
 		spec.ensure_db_good(db.as_hashdb_mut());
 		let vm_factory = Default::default();
-		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, vec![genesis_header.hash()], Address::zero(), x!(3141562), vec![]).close_and_lock().seal(engine.deref(), vec![]).unwrap();
+		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, vec![genesis_header.hash()], Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]).close_and_lock().seal(engine.deref(), vec![]).unwrap();
 		let orig_bytes = b.rlp_bytes();
 		let orig_db = b.drain();
 
@@ -557,7 +761,7 @@ mod tests {
 		let mut db = db_result.take();
 		spec.ensure_db_good(db.as_hashdb_mut());
 		let vm_factory = Default::default();
-		let mut open_block = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, vec![genesis_header.hash()], Address::zero(), x!(3141562), vec![]);
+		let mut open_block = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, vec![genesis_header.hash()], Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
 		let mut uncle1_header = Header::new();
 		uncle1_header.extra_data = b"uncle1".to_vec();
 		let mut uncle2_header = Header::new();
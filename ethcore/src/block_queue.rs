@@ -17,7 +17,7 @@
 //! A queue of blocks. Sits between network or other I/O and the `BlockChain`.
 //! Sorts them ready for blockchain insertion.
 use std::thread::{JoinHandle, self};
-use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
 use util::*;
 use verification::*;
 use error::*;
@@ -94,6 +94,7 @@ impl BlockQueueInfo {
 pub struct BlockQueue {
 	panic_handler: Arc<PanicHandler>,
 	engine: Arc<Box<Engine>>,
+	check_seal: bool,
 	more_to_verify: Arc<Condvar>,
 	verification: Arc<Verification>,
 	verifiers: Vec<JoinHandle<()>>,
@@ -102,7 +103,7 @@ pub struct BlockQueue {
 	empty: Arc<Condvar>,
 	processing: RwLock<HashSet<H256>>,
 	max_queue_size: usize,
-	max_mem_use: usize,
+	max_mem_use: AtomicUsize,
 }
 
 struct UnverifiedBlock {
@@ -148,8 +149,8 @@ struct Verification {
 }
 
 impl BlockQueue {
-	/// Creates a new queue instance.
-	pub fn new(config: BlockQueueConfig, engine: Arc<Box<Engine>>, message_channel: IoChannel<NetSyncMessage>) -> BlockQueue {
+	/// Creates a new queue instance. Seals of the blocks are verified only if `check_seal` is set.
+	pub fn new(config: BlockQueueConfig, engine: Arc<Box<Engine>>, check_seal: bool, message_channel: IoChannel<NetSyncMessage>) -> BlockQueue {
 		let verification = Arc::new(Verification {
 			unverified: Mutex::new(VecDeque::new()),
 			verified: Mutex::new(VecDeque::new()),
@@ -181,7 +182,7 @@ impl BlockQueue {
 				.name(format!("Verifier #{}", i))
 				.spawn(move || {
 					panic_handler.catch_panic(move || {
-						BlockQueue::verify(verification, engine, more_to_verify, ready_signal, deleting, empty)
+						BlockQueue::verify(verification, engine, check_seal, more_to_verify, ready_signal, deleting, empty)
 					}).unwrap()
 				})
 				.expect("Error starting block verification thread")
@@ -189,6 +190,7 @@ impl BlockQueue {
 		}
 		BlockQueue {
 			engine: engine,
+			check_seal: check_seal,
 			panic_handler: panic_handler,
 			ready_signal: ready_signal.clone(),
 			more_to_verify: more_to_verify.clone(),
@@ -198,11 +200,11 @@ impl BlockQueue {
 			processing: RwLock::new(HashSet::new()),
 			empty: empty.clone(),
 			max_queue_size: max(config.max_queue_size, MIN_QUEUE_LIMIT),
-			max_mem_use: max(config.max_mem_use, MIN_MEM_LIMIT),
+			max_mem_use: AtomicUsize::new(max(config.max_mem_use, MIN_MEM_LIMIT)),
 		}
 	}
 
-	fn verify(verification: Arc<Verification>, engine: Arc<Box<Engine>>, wait: Arc<Condvar>, ready: Arc<QueueSignal>, deleting: Arc<AtomicBool>, empty: Arc<Condvar>) {
+	fn verify(verification: Arc<Verification>, engine: Arc<Box<Engine>>, check_seal: bool, wait: Arc<Condvar>, ready: Arc<QueueSignal>, deleting: Arc<AtomicBool>, empty: Arc<Condvar>) {
 		while !deleting.load(AtomicOrdering::Acquire) {
 			{
 				let mut unverified = verification.unverified.lock().unwrap();
@@ -232,7 +234,7 @@ impl BlockQueue {
 			};
 
 			let block_hash = block.header.hash();
-			match verify_block_unordered(block.header, block.bytes, engine.deref().deref()) {
+			match verify_block_unordered(block.header, block.bytes, engine.deref().deref(), check_seal) {
 				Ok(verified) => {
 					let mut verifying = verification.verifying.lock().unwrap();
 					for e in verifying.iter_mut() {
@@ -325,7 +327,7 @@ impl BlockQueue {
 			}
 		}
 
-		match verify_block_basic(&header, &bytes, self.engine.deref().deref()) {
+		match verify_block_basic(&header, &bytes, self.engine.deref().deref(), self.check_seal) {
 			Ok(()) => {
 				self.processing.write().unwrap().insert(h.clone());
 				self.verification.unverified.lock().unwrap().push_back(UnverifiedBlock { header: header, bytes: bytes });
@@ -413,7 +415,7 @@ impl BlockQueue {
 			verifying_queue_size: verifying_len,
 			verified_queue_size: verified_len,
 			max_queue_size: self.max_queue_size,
-			max_mem_use: self.max_mem_use,
+			max_mem_use: self.max_mem_use.load(AtomicOrdering::Relaxed),
 			mem_used:
 				unverified_bytes
 				+ verifying_bytes
@@ -423,6 +425,12 @@ impl BlockQueue {
 		}
 	}
 
+	/// Sets maximal memory used by the queue (it's never lower than `MIN_MEM_LIMIT`).
+	/// The queue reports it's full when the limit is reached.
+	pub fn set_max_mem_use(&self, max_mem_use: usize) {
+		self.max_mem_use.store(max(max_mem_use, MIN_MEM_LIMIT), AtomicOrdering::Relaxed);
+	}
+
 	/// Optimise memory footprint of the heap fields.
 	pub fn collect_garbage(&self) {
 		{
@@ -465,7 +473,7 @@ mod tests {
 	fn get_test_queue() -> BlockQueue {
 		let spec = get_test_spec();
 		let engine = spec.engine;
-		BlockQueue::new(BlockQueueConfig::default(), Arc::new(engine), IoChannel::disconnected())
+		BlockQueue::new(BlockQueueConfig::default(), Arc::new(engine), true, IoChannel::disconnected())
 	}
 
 	#[test]
@@ -473,7 +481,7 @@ mod tests {
 		// TODO better test
 		let spec = Spec::new_test();
 		let engine = spec.engine;
-		let _ = BlockQueue::new(BlockQueueConfig::default(), Arc::new(engine), IoChannel::disconnected());
+		let _ = BlockQueue::new(BlockQueueConfig::default(), Arc::new(engine), true, IoChannel::disconnected());
 	}
 
 	#[test]
@@ -536,7 +544,7 @@ mod tests {
 		let engine = spec.engine;
 		let mut config = BlockQueueConfig::default();
 		config.max_mem_use = super::MIN_MEM_LIMIT;  // empty queue uses about 15000
-		let queue = BlockQueue::new(config, Arc::new(engine), IoChannel::disconnected());
+		let queue = BlockQueue::new(config, Arc::new(engine), true, IoChannel::disconnected());
 		assert!(!queue.queue_info().is_full());
 		let mut blocks = get_good_dummy_block_seq(50);
 		for b in blocks.drain(..) {
@@ -42,6 +42,8 @@ pub struct BlockChainConfig {
 	pub pref_cache_size: usize,
 	/// Maximum cache size in bytes.
 	pub max_cache_size: usize,
+	/// Index uncles by hash, so that block which included them can be found.
+	pub index_uncles: bool,
 }
 
 impl Default for BlockChainConfig {
@@ -49,6 +51,7 @@ impl Default for BlockChainConfig {
 		BlockChainConfig {
 			pref_cache_size: 1 << 14,
 			max_cache_size: 1 << 20,
+			index_uncles: false,
 		}
 	}
 }
@@ -154,6 +157,7 @@ pub struct BlockChain {
 	block_details: RwLock<HashMap<H256, BlockDetails>>,
 	block_hashes: RwLock<HashMap<BlockNumber, H256>>,
 	transaction_addresses: RwLock<HashMap<H256, TransactionAddress>>,
+	uncle_addresses: RwLock<HashMap<H256, UncleAddress>>,
 	block_logs: RwLock<HashMap<H256, BlockLogBlooms>>,
 	blocks_blooms: RwLock<HashMap<H256, BlocksBlooms>>,
 	block_receipts: RwLock<HashMap<H256, BlockReceipts>>,
@@ -166,6 +170,8 @@ pub struct BlockChain {
 	// blooms indexing
 	bloom_indexer: BloomIndexer,
 
+	index_uncles: bool,
+
 	insert_lock: Mutex<()>
 }
 
@@ -257,16 +263,16 @@ impl<'a> Iterator for AncestryIter<'a> {
 
 impl BlockChain {
 	/// Create new instance of blockchain from given Genesis
-	pub fn new(config: BlockChainConfig, genesis: &[u8], path: &Path) -> BlockChain {
+	pub fn new(config: BlockChainConfig, genesis: &[u8], path: &Path, db_config: DatabaseConfig) -> BlockChain {
 		// open extras db
 		let mut extras_path = path.to_path_buf();
 		extras_path.push("extras");
-		let extras_db = Database::open_default(extras_path.to_str().unwrap()).unwrap();
+		let extras_db = Database::open(&db_config, extras_path.to_str().unwrap()).unwrap();
 
 		// open blocks db
 		let mut blocks_path = path.to_path_buf();
 		blocks_path.push("blocks");
-		let blocks_db = Database::open_default(blocks_path.to_str().unwrap()).unwrap();
+		let blocks_db = Database::open(&db_config, blocks_path.to_str().unwrap()).unwrap();
 
 		let mut cache_man = CacheManager{cache_usage: VecDeque::new(), in_use: HashSet::new()};
 		(0..COLLECTION_QUEUE_SIZE).foreach(|_| cache_man.cache_usage.push_back(HashSet::new()));
@@ -279,6 +285,7 @@ impl BlockChain {
 			block_details: RwLock::new(HashMap::new()),
 			block_hashes: RwLock::new(HashMap::new()),
 			transaction_addresses: RwLock::new(HashMap::new()),
+			uncle_addresses: RwLock::new(HashMap::new()),
 			block_logs: RwLock::new(HashMap::new()),
 			blocks_blooms: RwLock::new(HashMap::new()),
 			block_receipts: RwLock::new(HashMap::new()),
@@ -286,6 +293,7 @@ impl BlockChain {
 			blocks_db: blocks_db,
 			cache_man: RwLock::new(cache_man),
 			bloom_indexer: BloomIndexer::new(BLOOM_INDEX_SIZE, BLOOM_LEVELS),
+			index_uncles: config.index_uncles,
 			insert_lock: Mutex::new(()),
 		};
 
@@ -441,12 +449,16 @@ impl BlockChain {
 		self.blocks_db.put(&hash, &bytes).unwrap();
 
 		let info = self.block_info(bytes);
+		let uncles_addresses = self.prepare_uncle_addresses_update(bytes, &info);
+		let retracted_uncles = self.prepare_retracted_uncles_update(&info, &uncles_addresses);
 
 		self.apply_update(ExtrasUpdate {
 			block_hashes: self.prepare_block_hashes_update(bytes, &info),
 			block_details: self.prepare_block_details_update(bytes, &info),
 			block_receipts: self.prepare_block_receipts_update(receipts, &info),
 			transactions_addresses: self.prepare_transaction_addresses_update(bytes, &info),
+			uncles_addresses: uncles_addresses,
+			retracted_uncles: retracted_uncles,
 			blocks_blooms: self.prepare_block_blooms_update(bytes, &info),
 			info: info.clone(),
 		});
@@ -454,6 +466,63 @@ impl BlockChain {
 		ImportRoute::from(info)
 	}
 
+	/// Inserts the block without its ancestors and makes it the best block.
+	/// Used once state of the block is imported from an external source, so that sync continues from it.
+	/// Blocks between genesis and the pivot remain unknown.
+	pub fn insert_pivot_block(&self, bytes: &[u8], total_difficulty: U256) {
+		let block = BlockView::new(bytes);
+		let header = block.header_view();
+		let hash = header.sha3();
+		let number = header.number();
+
+		let _lock = self.insert_lock.lock();
+		self.blocks_db.put(&hash, bytes).unwrap();
+
+		let details = BlockDetails {
+			number: number,
+			total_difficulty: total_difficulty,
+			parent: header.parent_hash(),
+			children: vec![]
+		};
+
+		let batch = DBTransaction::new();
+		batch.write(&hash, &details);
+		batch.write(&number, &hash);
+		batch.put(b"best", &hash).unwrap();
+
+		self.block_details.write().unwrap().remove(&hash);
+		self.block_hashes.write().unwrap().remove(&number);
+		self.extras_db.write(batch).unwrap();
+		*self.best_block.write().unwrap() = BestBlock {
+			hash: hash,
+			number: number,
+			total_difficulty: total_difficulty,
+		};
+	}
+
+	/// Returns checkpoint of an interrupted state import (if any).
+	pub fn state_import_checkpoint(&self) -> Option<Bytes> {
+		self.extras_db.get(b"state_import").unwrap().map(|c| c.to_vec())
+	}
+
+	/// Stores checkpoint of state import. `None` marks the import as finished.
+	pub fn set_state_import_checkpoint(&self, checkpoint: Option<&[u8]>) {
+		match checkpoint {
+			Some(checkpoint) => self.extras_db.put(b"state_import", checkpoint).unwrap(),
+			None => self.extras_db.delete(b"state_import").unwrap(),
+		}
+	}
+
+	/// Returns true if the state database keeps preimages of account addresses since genesis.
+	pub fn tracks_preimages(&self) -> bool {
+		self.extras_db.get(b"preimages").unwrap().is_some()
+	}
+
+	/// Marks the state database as keeping preimages of account addresses since genesis.
+	pub fn set_tracks_preimages(&self) {
+		self.extras_db.put(b"preimages", &[1]).unwrap();
+	}
+
 	/// Applies extras update.
 	fn apply_update(&self, update: ExtrasUpdate) {
 		let batch = DBTransaction::new();
@@ -483,6 +552,7 @@ impl BlockChain {
 			let mut best_block = self.best_block.write().unwrap();
 			let mut write_hashes = self.block_hashes.write().unwrap();
 			let mut write_txs = self.transaction_addresses.write().unwrap();
+			let mut write_uncles = self.uncle_addresses.write().unwrap();
 
 			// update best block
 			match update.info.location {
@@ -498,6 +568,8 @@ impl BlockChain {
 
 			batch.extend_with_cache(&mut write_hashes, update.block_hashes, CacheUpdatePolicy::Remove);
 			batch.extend_with_cache(&mut write_txs, update.transactions_addresses, CacheUpdatePolicy::Remove);
+			batch.delete_with_cache(&mut write_uncles, update.retracted_uncles);
+			batch.extend_with_cache(&mut write_uncles, update.uncles_addresses, CacheUpdatePolicy::Remove);
 
 			// update extras database
 			self.extras_db.write(batch).unwrap();
@@ -516,6 +588,36 @@ impl BlockChain {
 		}
 	}
 
+	/// Returns hash of the latest common ancestor of `a` and `b` (it's one of them if the other is its descendant).
+	/// Depth of the blocks is equalized using their numbers first, so only the forked parts are walked twice.
+	/// Returns `None` if any of the blocks is unknown.
+	pub fn common_ancestor(&self, a: &H256, b: &H256) -> Option<H256> {
+		let (mut a, mut b) = (a.clone(), b.clone());
+		let (mut a_details, mut b_details) = match (self.block_details(&a), self.block_details(&b)) {
+			(Some(a_details), Some(b_details)) => (a_details, b_details),
+			_ => return None,
+		};
+
+		while a_details.number > b_details.number {
+			a = a_details.parent.clone();
+			a_details = self.block_details(&a).expect("Parent of known block is known; qed");
+		}
+
+		while b_details.number > a_details.number {
+			b = b_details.parent.clone();
+			b_details = self.block_details(&b).expect("Parent of known block is known; qed");
+		}
+
+		while a != b {
+			a = a_details.parent.clone();
+			a_details = self.block_details(&a).expect("Parent of known block is known; qed");
+			b = b_details.parent.clone();
+			b_details = self.block_details(&b).expect("Parent of known block is known; qed");
+		}
+
+		Some(a)
+	}
+
 	/// Given a block's `parent`, find every block header which represents a valid possible uncle.
 	pub fn find_uncle_headers(&self, parent: &H256, uncle_generations: usize) -> Option<Vec<Header>> {
 		self.find_uncle_hashes(parent, uncle_generations).map(|v| v.into_iter().filter_map(|h| self.block_header(&h)).collect())
@@ -657,6 +759,91 @@ impl BlockChain {
 			})
 	}
 
+	/// This function returns modified uncle addresses (if uncles are indexed).
+	/// Only uncles of blocks which become part of canon chain are indexed.
+	fn prepare_uncle_addresses_update(&self, block_bytes: &[u8], info: &BlockInfo) -> HashMap<H256, UncleAddress> {
+		let mut uncle_addresses = HashMap::new();
+		if !self.index_uncles {
+			return uncle_addresses;
+		}
+
+		{
+			let mut insert = |block_hash: &H256, uncle_hashes: Vec<H256>| {
+				for (i, uncle_hash) in uncle_hashes.into_iter().enumerate() {
+					uncle_addresses.insert(uncle_hash, UncleAddress {
+						block_hash: block_hash.clone(),
+						index: i
+					});
+				}
+			};
+
+			match info.location {
+				BlockLocation::Branch => (),
+				BlockLocation::CanonChain => {
+					insert(&info.hash, BlockView::new(block_bytes).uncle_hashes());
+				},
+				BlockLocation::BranchBecomingCanonChain(ref data) => {
+					for hash in &data.enacted {
+						insert(hash, self.uncle_hashes(hash).expect("Enacted block must be in the database."));
+					}
+					insert(&info.hash, BlockView::new(block_bytes).uncle_hashes());
+				}
+			}
+		}
+
+		uncle_addresses
+	}
+
+	/// This function returns uncles of retracted blocks, which should be removed from the index
+	/// (unless they are included again by one of the enacted blocks).
+	fn prepare_retracted_uncles_update(&self, info: &BlockInfo, enacted: &HashMap<H256, UncleAddress>) -> Vec<H256> {
+		if !self.index_uncles {
+			return vec![];
+		}
+
+		match info.location {
+			BlockLocation::BranchBecomingCanonChain(ref data) => data.retracted.iter()
+				.flat_map(|hash| self.uncle_hashes(hash).expect("Retracted block must be in the database.").into_iter())
+				.filter(|uncle_hash| !enacted.contains_key(uncle_hash))
+				.collect(),
+			_ => vec![],
+		}
+	}
+
+	/// Indexes uncles of canon blocks from `from` to `to` (inclusive).
+	/// Used to fill the index for blocks imported before uncles indexing was enabled.
+	/// Returns number of indexed uncles.
+	pub fn backfill_uncle_addresses(&self, from: BlockNumber, to: BlockNumber) -> usize {
+		let _lock = self.insert_lock.lock();
+		let batch = DBTransaction::new();
+		let mut indexed = HashMap::new();
+		for number in from..(to + 1) {
+			let hash = match self.block_hash(number) {
+				Some(hash) => hash,
+				None => break,
+			};
+			let uncles = self.uncle_hashes(&hash).expect("Canon block hash must refer to known block.");
+			for (i, uncle_hash) in uncles.into_iter().enumerate() {
+				indexed.insert(uncle_hash, UncleAddress {
+					block_hash: hash.clone(),
+					index: i,
+				});
+			}
+		}
+
+		let count = indexed.len();
+		let mut write_uncles = self.uncle_addresses.write().unwrap();
+		batch.extend_with_cache(&mut write_uncles, indexed, CacheUpdatePolicy::Remove);
+		self.extras_db.write(batch).unwrap();
+		count
+	}
+
+	/// Returns address of uncle with given hash within the block which included it.
+	/// Works only for uncles which were indexed (see `BlockChainConfig::index_uncles`).
+	pub fn uncle_address(&self, hash: &H256) -> Option<UncleAddress> {
+		self.query_extras(hash, &self.uncle_addresses)
+	}
+
 	/// This functions returns modified blocks blooms.
 	///
 	/// To accelerate blooms lookups, blomms are stored in multiple
@@ -742,12 +929,35 @@ impl BlockChain {
 		self.extras_db.read_with_cache(cache, hash)
 	}
 
+	/// Get raw data of given blocks, `None` for unknown ones.
+	/// Blocks which are not cached are read from a single database snapshot and are not cached.
+	pub fn blocks(&self, hashes: &[H256]) -> Vec<Option<Bytes>> {
+		let cache = self.blocks.read().unwrap();
+		let snapshot = self.blocks_db.snapshot();
+		hashes.iter().map(|hash| match cache.get(hash) {
+			Some(bytes) => Some(bytes.clone()),
+			None => snapshot.get(hash).expect("Low level database error. Some issue with disk?").map(|bytes| bytes.to_vec()),
+		}).collect()
+	}
+
+	/// Get receipts of given blocks, `None` for unknown ones.
+	/// Receipts which are not cached are read from a single database snapshot and are not cached.
+	pub fn block_receipts_list(&self, hashes: &[H256]) -> Vec<Option<BlockReceipts>> {
+		let cache = self.block_receipts.read().unwrap();
+		let snapshot = self.extras_db.snapshot();
+		hashes.iter().map(|hash| match cache.get(hash) {
+			Some(receipts) => Some(receipts.clone()),
+			None => snapshot.read::<BlockReceipts, _>(hash),
+		}).collect()
+	}
+
 	/// Get current cache size.
 	pub fn cache_size(&self) -> CacheSize {
 		CacheSize {
 			blocks: self.blocks.read().unwrap().heap_size_of_children(),
 			block_details: self.block_details.read().unwrap().heap_size_of_children(),
 			transaction_addresses: self.transaction_addresses.read().unwrap().heap_size_of_children(),
+			uncle_addresses: self.uncle_addresses.read().unwrap().heap_size_of_children(),
 			block_logs: self.block_logs.read().unwrap().heap_size_of_children(),
 			blocks_blooms: self.blocks_blooms.read().unwrap().heap_size_of_children(),
 			block_receipts: self.block_receipts.read().unwrap().heap_size_of_children()
@@ -779,6 +989,7 @@ impl BlockChain {
 				let mut block_details = self.block_details.write().unwrap();
 				let mut block_hashes = self.block_hashes.write().unwrap();
 				let mut transaction_addresses = self.transaction_addresses.write().unwrap();
+				let mut uncle_addresses = self.uncle_addresses.write().unwrap();
 				let mut block_logs = self.block_logs.write().unwrap();
 				let mut blocks_blooms = self.blocks_blooms.write().unwrap();
 				let mut block_receipts = self.block_receipts.write().unwrap();
@@ -790,6 +1001,7 @@ impl BlockChain {
 						CacheID::Block(h) => { blocks.remove(&h); },
 						CacheID::Extras(ExtrasIndex::BlockDetails, h) => { block_details.remove(&h); },
 						CacheID::Extras(ExtrasIndex::TransactionAddress, h) => { transaction_addresses.remove(&h); },
+						CacheID::Extras(ExtrasIndex::UncleAddress, h) => { uncle_addresses.remove(&h); },
 						CacheID::Extras(ExtrasIndex::BlockLogBlooms, h) => { block_logs.remove(&h); },
 						CacheID::Extras(ExtrasIndex::BlocksBlooms, h) => { blocks_blooms.remove(&h); },
 						CacheID::Extras(ExtrasIndex::BlockReceipts, h) => { block_receipts.remove(&h); },
@@ -806,6 +1018,7 @@ impl BlockChain {
 				block_details.shrink_to_fit();
  				block_hashes.shrink_to_fit();
  				transaction_addresses.shrink_to_fit();
+ 				uncle_addresses.shrink_to_fit();
  				block_logs.shrink_to_fit();
  				blocks_blooms.shrink_to_fit();
  				block_receipts.shrink_to_fit();
@@ -824,11 +1037,16 @@ mod tests {
 	use rustc_serialize::hex::FromHex;
 	use util::hash::*;
 	use util::sha3::Hashable;
+	use util::numbers::U256;
+	use util::kvdb::DatabaseConfig;
 	use blockchain::{BlockProvider, BlockChain, BlockChainConfig, ImportRoute};
 	use tests::helpers::*;
 	use devtools::*;
 	use blockchain::generator::{ChainGenerator, ChainIterator, BlockFinalizer};
 	use views::BlockView;
+	use header::Header;
+	use extras::UncleAddress;
+	use util::rlp::{RlpStream, Stream, View};
 
 	#[test]
 	fn basic_blockchain_insert() {
@@ -840,7 +1058,7 @@ mod tests {
 		let first_hash = BlockView::new(&first).header_view().sha3();
 
 		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 
 		assert_eq!(bc.genesis_hash(), genesis_hash.clone());
 		assert_eq!(bc.best_block_number(), 0);
@@ -860,6 +1078,32 @@ mod tests {
 		assert_eq!(bc.block_hash(2), None);
 	}
 
+	#[test]
+	fn should_insert_pivot_block_without_ancestors() {
+		// given
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let blocks = (0..3).map(|_| canon_chain.generate(&mut finalizer).unwrap()).collect::<Vec<_>>();
+		let pivot_hash = BlockView::new(&blocks[1]).header_view().sha3();
+		let last_hash = BlockView::new(&blocks[2]).header_view().sha3();
+
+		let temp = RandomTempPath::new();
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
+
+		// when
+		bc.insert_pivot_block(&blocks[1], U256::from(1000));
+		bc.insert_block(&blocks[2], vec![]);
+
+		// then
+		assert_eq!(bc.block_hash(1), None);
+		assert_eq!(bc.block_hash(2), Some(pivot_hash.clone()));
+		assert_eq!(bc.block_hash(3), Some(last_hash.clone()));
+		assert_eq!(bc.best_block_hash(), last_hash.clone());
+		assert_eq!(bc.block_details(&pivot_hash).unwrap().children, vec![last_hash]);
+		assert_eq!(bc.best_block_total_difficulty(), U256::from(1000) + BlockView::new(&blocks[2]).header_view().difficulty());
+	}
+
 	#[test]
 	fn check_ancestry_iter() {
 		let mut canon_chain = ChainGenerator::default();
@@ -868,7 +1112,7 @@ mod tests {
 		let genesis_hash = BlockView::new(&genesis).header_view().sha3();
 
 		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 
 		let mut block_hashes = vec![genesis_hash.clone()];
 		for _ in 0..10 {
@@ -882,6 +1126,35 @@ mod tests {
 		assert_eq!(bc.ancestry_iter(block_hashes[0].clone()).unwrap().collect::<Vec<_>>(), block_hashes)
 	}
 
+	#[test]
+	fn check_common_ancestor() {
+		// given
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let b1 = canon_chain.generate(&mut finalizer).unwrap();
+		let b2 = canon_chain.generate(&mut finalizer).unwrap();
+		let mut fork_chain = canon_chain.fork(1);
+		let mut fork_finalizer = finalizer.fork();
+		let b3b = fork_chain.generate(&mut fork_finalizer).unwrap();
+		let b4b = fork_chain.generate(&mut fork_finalizer).unwrap();
+		let b3a = canon_chain.generate(&mut finalizer).unwrap();
+		let hash = |block: &[u8]| BlockView::new(block).header_view().sha3();
+
+		let temp = RandomTempPath::new();
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
+		for block in &[&b1, &b2, &b3a, &b3b, &b4b] {
+			bc.insert_block(block, vec![]);
+		}
+
+		// then
+		assert_eq!(bc.common_ancestor(&hash(&b3a), &hash(&b4b)), Some(hash(&b2)));
+		assert_eq!(bc.common_ancestor(&hash(&b4b), &hash(&b3a)), Some(hash(&b2)));
+		assert_eq!(bc.common_ancestor(&hash(&b1), &hash(&b4b)), Some(hash(&b1)));
+		assert_eq!(bc.common_ancestor(&hash(&b3a), &hash(&b3a)), Some(hash(&b3a)));
+		assert_eq!(bc.common_ancestor(&hash(&genesis), &H256::from(42)), None);
+	}
+
 	#[test]
 	#[cfg_attr(feature="dev", allow(cyclomatic_complexity))]
 	fn test_find_uncles() {
@@ -900,7 +1173,7 @@ mod tests {
 		let b5a = canon_chain.generate(&mut finalizer).unwrap();
 
 		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 		bc.insert_block(&b1a, vec![]);
 		bc.insert_block(&b1b, vec![]);
 		bc.insert_block(&b2a, vec![]);
@@ -920,6 +1193,59 @@ mod tests {
 		// TODO: insert block that already includes one of them as an uncle to check it's not allowed.
 	}
 
+	/// Replaces uncles of given block with `uncle`. Hash of the block stays the same.
+	fn with_uncle(block: &[u8], uncle: &Header) -> Vec<u8> {
+		let view = BlockView::new(block);
+		let mut stream = RlpStream::new_list(3);
+		stream.append_raw(view.rlp().at(0).as_raw(), 1);
+		stream.append_raw(view.rlp().at(1).as_raw(), 1);
+		stream.begin_list(1);
+		stream.append(uncle);
+		stream.out()
+	}
+
+	#[test]
+	fn indexes_uncles_of_canon_blocks_only() {
+		// given
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let b1 = canon_chain.generate(&mut finalizer).unwrap();
+		let mut fork_chain = canon_chain.fork(1);
+		let mut fork_finalizer = finalizer.fork();
+		let b2b = fork_chain.generate(&mut fork_finalizer).unwrap();
+		let b3b = fork_chain.generate(&mut fork_finalizer).unwrap();
+		let b2a = canon_chain.generate(&mut finalizer).unwrap();
+		let hash = |block: &[u8]| BlockView::new(block).header_view().sha3();
+
+		let mut uncle_a = Header::default();
+		uncle_a.number = 1;
+		uncle_a.extra_data = vec![1];
+		let mut uncle_b = Header::default();
+		uncle_b.number = 1;
+		uncle_b.extra_data = vec![2];
+		let b2a = with_uncle(&b2a, &uncle_a);
+		let b2b = with_uncle(&b2b, &uncle_b);
+
+		let temp = RandomTempPath::new();
+		let config = BlockChainConfig { index_uncles: true, ..BlockChainConfig::default() };
+		let bc = BlockChain::new(config, &genesis, temp.as_path(), DatabaseConfig::default());
+		bc.insert_block(&b1, vec![]);
+		bc.insert_block(&b2a, vec![]);
+		bc.insert_block(&b2b, vec![]);
+		assert_eq!(bc.best_block_hash(), hash(&b2a));
+		assert_eq!(bc.uncle_address(&uncle_a.hash()), Some(UncleAddress { block_hash: hash(&b2a), index: 0 }));
+		assert_eq!(bc.uncle_address(&uncle_b.hash()), None);
+
+		// when
+		bc.insert_block(&b3b, vec![]);
+
+		// then
+		assert_eq!(bc.best_block_hash(), hash(&b3b));
+		assert_eq!(bc.uncle_address(&uncle_a.hash()), None);
+		assert_eq!(bc.uncle_address(&uncle_b.hash()), Some(UncleAddress { block_hash: hash(&b2b), index: 0 }));
+	}
+
 	#[test]
 	#[cfg_attr(feature="dev", allow(cyclomatic_complexity))]
 	fn test_small_fork() {
@@ -941,7 +1267,7 @@ mod tests {
 		let best_block_hash = b3a_hash.clone();
 
 		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 		let ir1 = bc.insert_block(&b1, vec![]);
 		let ir2 = bc.insert_block(&b2, vec![]);
 		let ir3b = bc.insert_block(&b3b, vec![]);
@@ -1046,14 +1372,14 @@ mod tests {
 
 		let temp = RandomTempPath::new();
 		{
-			let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+			let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 			assert_eq!(bc.best_block_hash(), genesis_hash);
 			bc.insert_block(&first, vec![]);
 			assert_eq!(bc.best_block_hash(), first_hash);
 		}
 
 		{
-			let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+			let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 			assert_eq!(bc.best_block_hash(), first_hash);
 		}
 	}
@@ -1106,7 +1432,7 @@ mod tests {
 		let b1_hash = H256::from_str("f53f268d23a71e85c7d6d83a9504298712b84c1a2ba220441c86eeda0bf0b6e3").unwrap();
 
 		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 		bc.insert_block(&b1, vec![]);
 
 		let transactions = bc.transactions(&b1_hash).unwrap();
@@ -1137,7 +1463,7 @@ mod tests {
 		let b2a = canon_chain.with_bloom(bloom_ba.clone()).generate(&mut finalizer).unwrap();
 
 		let temp = RandomTempPath::new();
-		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path());
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), DatabaseConfig::default());
 
 		let blocks_b1 = bc.blocks_with_bloom(&bloom_b1, 0, 5);
 		let blocks_b2 = bc.blocks_with_bloom(&bloom_b2, 0, 5);
@@ -1183,4 +1509,17 @@ mod tests {
 		assert_eq!(blocks_b2, vec![2]);
 		assert_eq!(blocks_ba, vec![3]);
 	}
+	#[test]
+	fn opens_databases_with_given_config() {
+		let mut canon_chain = ChainGenerator::default();
+		let mut finalizer = BlockFinalizer::default();
+		let genesis = canon_chain.generate(&mut finalizer).unwrap();
+		let temp = RandomTempPath::new();
+		let db_config = DatabaseConfig { cache_size: Some(64), ..DatabaseConfig::default() };
+
+		let bc = BlockChain::new(BlockChainConfig::default(), &genesis, temp.as_path(), db_config.clone());
+
+		assert_eq!(bc.blocks_db.config(), &db_config);
+		assert_eq!(bc.extras_db.config(), &db_config);
+	}
 }
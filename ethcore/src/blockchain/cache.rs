@@ -23,6 +23,8 @@ pub struct CacheSize {
 	pub block_details: usize,
 	/// Transaction addresses cache size.
 	pub transaction_addresses: usize,
+	/// Uncle addresses cache size.
+	pub uncle_addresses: usize,
 	/// Logs cache size.
 	pub block_logs: usize,
 	/// Blooms cache size.
@@ -33,5 +35,5 @@ pub struct CacheSize {
 
 impl CacheSize {
 	/// Total amount used by the cache.
-	pub fn total(&self) -> usize { self.blocks + self.block_details + self.transaction_addresses + self.block_logs + self.blocks_blooms }
+	pub fn total(&self) -> usize { self.blocks + self.block_details + self.transaction_addresses + self.uncle_addresses + self.block_logs + self.blocks_blooms }
 }
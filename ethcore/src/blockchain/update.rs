@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use util::numbers::H256;
 use header::BlockNumber;
 use blockchain::block_info::BlockInfo;
-use extras::{BlockDetails, BlockReceipts, TransactionAddress, BlocksBlooms};
+use extras::{BlockDetails, BlockReceipts, TransactionAddress, UncleAddress, BlocksBlooms};
 
 /// Block extras update info.
 pub struct ExtrasUpdate {
@@ -16,6 +16,10 @@ pub struct ExtrasUpdate {
 	pub block_receipts: HashMap<H256, BlockReceipts>,
 	/// Modified transaction addresses.
 	pub transactions_addresses: HashMap<H256, TransactionAddress>,
+	/// Modified uncle addresses.
+	pub uncles_addresses: HashMap<H256, UncleAddress>,
+	/// Uncles which are no longer included in canon chain.
+	pub retracted_uncles: Vec<H256>,
 	/// Modified blocks blooms.
 	pub blocks_blooms: HashMap<H256, BlocksBlooms>,
 }
@@ -20,13 +20,21 @@ use crypto::ripemd160::Ripemd160;
 use crypto::digest::Digest;
 use ethjson;
 
+/// Error returned by built-in which can't process given input.
+#[derive(Debug, PartialEq)]
+pub struct BuiltinError(pub &'static str);
+
+/// Built-in implementation. Takes the input and the output to fill.
+/// Output should be left untouched when execution fails.
+pub type BuiltinExec = Box<Fn(&[u8], &mut [u8]) -> Result<(), BuiltinError>>;
+
 /// Definition of a contract whose implementation is built-in.
 pub struct Builtin {
 	/// The gas cost of running this built-in for the given size of input data.
 	pub cost: Box<Fn(usize) -> U256>,	// TODO: U256 should be bignum.
 	/// Run this built-in function with the input being the first argument and the output
 	/// being placed into the second.
-	pub execute: Box<Fn(&[u8], &mut [u8])>,
+	pub execute: BuiltinExec,
 }
 
 // Rust does not mark closurer that do not capture as Sync
@@ -42,7 +50,7 @@ impl fmt::Debug for Builtin {
 
 impl Builtin {
 	/// Create a new object from components.
-	pub fn new(cost: Box<Fn(usize) -> U256>, execute: Box<Fn(&[u8], &mut [u8])>) -> Builtin {
+	pub fn new(cost: Box<Fn(usize) -> U256>, execute: BuiltinExec) -> Builtin {
 		Builtin {cost: cost, execute: execute}
 	}
 
@@ -59,7 +67,7 @@ impl Builtin {
 	pub fn cost(&self, s: usize) -> U256 { (*self.cost)(s) }
 
 	/// Simple forwarder for execute.
-	pub fn execute(&self, input: &[u8], output: &mut[u8]) { (*self.execute)(input, output); }
+	pub fn execute(&self, input: &[u8], output: &mut[u8]) -> Result<(), BuiltinError> { (*self.execute)(input, output) }
 }
 
 impl From<ethjson::spec::Builtin> for Builtin {
@@ -83,13 +91,16 @@ pub fn copy_to(src: &[u8], dest: &mut[u8]) {
 
 /// Create a new builtin executor according to `name`.
 /// TODO: turn in to a factory with dynamic registration.
-pub fn new_builtin_exec(name: &str) -> Box<Fn(&[u8], &mut [u8])> {
+pub fn new_builtin_exec(name: &str) -> BuiltinExec {
 	match name {
 		"identity" => Box::new(move|input: &[u8], output: &mut[u8]| {
 			for i in 0..min(input.len(), output.len()) {
 				output[i] = input[i];
 			}
+			Ok(())
 		}),
+		// NOTE: Input shorter than expected is padded with zeros and invalid signature
+		// results in empty output, so the execution never fails.
 		"ecrecover" => Box::new(move|input: &[u8], output: &mut[u8]| {
 			#[repr(packed)]
 			#[derive(Debug)]
@@ -113,6 +124,7 @@ pub fn new_builtin_exec(name: &str) -> Box<Fn(&[u8], &mut [u8])> {
 					}
 				}
 			}
+			Ok(())
 		}),
 		"sha256" => Box::new(move|input: &[u8], output: &mut[u8]| {
 			let mut sha = Sha256::new();
@@ -124,6 +136,7 @@ pub fn new_builtin_exec(name: &str) -> Box<Fn(&[u8], &mut [u8])> {
 				sha.result(ret.as_slice_mut());
 				copy_to(&ret, output);
 			}
+			Ok(())
 		}),
 		"ripemd160" => Box::new(move|input: &[u8], output: &mut[u8]| {
 			let mut sha = Ripemd160::new();
@@ -131,6 +144,7 @@ pub fn new_builtin_exec(name: &str) -> Box<Fn(&[u8], &mut [u8])> {
 			let mut ret = H256::new();
 			sha.result(&mut ret.as_slice_mut()[12..32]);
 			copy_to(&ret, output);
+			Ok(())
 		}),
 		_ => {
 			panic!("invalid builtin name {}", name);
@@ -144,15 +158,15 @@ fn identity() {
 	let i = [0u8, 1, 2, 3];
 
 	let mut o2 = [255u8; 2];
-	f(&i[..], &mut o2[..]);
+	f(&i[..], &mut o2[..]).unwrap();
 	assert_eq!(i[0..2], o2);
 
 	let mut o4 = [255u8; 4];
-	f(&i[..], &mut o4[..]);
+	f(&i[..], &mut o4[..]).unwrap();
 	assert_eq!(i, o4);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(i, o8[..4]);
 	assert_eq!([255u8; 4], o8[4..]);
 }
@@ -164,15 +178,15 @@ fn sha256() {
 	let i = [0u8; 0];
 
 	let mut o = [255u8; 32];
-	f(&i[..], &mut o[..]);
+	f(&i[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap())[..]);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(&o8[..], &(FromHex::from_hex("e3b0c44298fc1c14").unwrap())[..]);
 
 	let mut o34 = [255u8; 34];
-	f(&i[..], &mut o34[..]);
+	f(&i[..], &mut o34[..]).unwrap();
 	assert_eq!(&o34[..], &(FromHex::from_hex("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855ffff").unwrap())[..]);
 }
 
@@ -183,15 +197,15 @@ fn ripemd160() {
 	let i = [0u8; 0];
 
 	let mut o = [255u8; 32];
-	f(&i[..], &mut o[..]);
+	f(&i[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31").unwrap())[..]);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(&o8[..], &(FromHex::from_hex("0000000000000000").unwrap())[..]);
 
 	let mut o34 = [255u8; 34];
-	f(&i[..], &mut o34[..]);
+	f(&i[..], &mut o34[..]).unwrap();
 	assert_eq!(&o34[..], &(FromHex::from_hex("0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31ffff").unwrap())[..]);
 }
 
@@ -210,46 +224,46 @@ fn ecrecover() {
 	let i = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03").unwrap();
 
 	let mut o = [255u8; 32];
-	f(&i[..], &mut o[..]);
+	f(&i[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("000000000000000000000000c08b5542d177ac6686946920409741463a15dddb").unwrap())[..]);
 
 	let mut o8 = [255u8; 8];
-	f(&i[..], &mut o8[..]);
+	f(&i[..], &mut o8[..]).unwrap();
 	assert_eq!(&o8[..], &(FromHex::from_hex("0000000000000000").unwrap())[..]);
 
 	let mut o34 = [255u8; 34];
-	f(&i[..], &mut o34[..]);
+	f(&i[..], &mut o34[..]).unwrap();
 	assert_eq!(&o34[..], &(FromHex::from_hex("000000000000000000000000c08b5542d177ac6686946920409741463a15dddbffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001a650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b000000000000000000000000000000000000000000000000000000000000001b0000000000000000000000000000000000000000000000000000000000000000").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000001b").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001bffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff000000000000000000000000000000000000000000000000000000000000001b").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	let i_bad = FromHex::from_hex("47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b000000000000000000000000000000000000000000000000000000000000001bffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);
 
 	// TODO: Should this (corrupted version of the above) fail rather than returning some address?
 /*	let i_bad = FromHex::from_hex("48173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad000000000000000000000000000000000000000000000000000000000000001b650acf9d3f5f0a2c799776a1254355d5f4061762a237396a99a0e0e3fc2bcd6729514a0dacb2e623ac4abd157cb18163ff942280db4d5caad66ddf941ba12e03").unwrap();
 	let mut o = [255u8; 32];
-	f(&i_bad[..], &mut o[..]);
+	f(&i_bad[..], &mut o[..]).unwrap();
 	assert_eq!(&o[..], &(FromHex::from_hex("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff").unwrap())[..]);*/
 }
 
@@ -269,7 +283,7 @@ fn from_named_linear() {
 
 	let i = [0u8, 1, 2, 3];
 	let mut o = [255u8; 4];
-	(*b.execute)(&i[..], &mut o[..]);
+	(*b.execute)(&i[..], &mut o[..]).unwrap();
 	assert_eq!(i, o);
 }
 
@@ -290,6 +304,6 @@ fn from_json() {
 
 	let i = [0u8, 1, 2, 3];
 	let mut o = [255u8; 4];
-	(*b.execute)(&i[..], &mut o[..]);
+	(*b.execute)(&i[..], &mut o[..]).unwrap();
 	assert_eq!(i, o);
 }
@@ -16,8 +16,8 @@
 
 //! Blockchain database client.
 
-use std::marker::PhantomData;
 use std::path::PathBuf;
+use std::time::Instant;
 use util::*;
 use util::panics::*;
 use views::BlockView;
@@ -31,17 +31,22 @@ use service::{NetSyncMessage, SyncMessage};
 use env_info::LastHashes;
 use verification::*;
 use block::*;
+use gas_limit::GasLimitTarget;
 use transaction::{LocalizedTransaction, SignedTransaction, Action};
 use extras::TransactionAddress;
 use filter::Filter;
 use log_entry::LocalizedLogEntry;
 use block_queue::{BlockQueue, BlockQueueInfo};
 use blockchain::{BlockChain, BlockProvider, TreeRoute, ImportRoute};
-use client::{BlockID, TransactionID, UncleID, TraceId, ClientConfig, BlockChainClient, TraceFilter};
+use client::{BlockID, TransactionID, UncleID, TraceId, ClientConfig, BlockChainClient, TraceFilter, StateStats, AncestryIter};
+use client::state_stats;
+use client::{MemoryBudget, BudgetedCache, BudgetAssignment};
+use client::state_export::{self, StateManifest, StateReader, ImportCheckpoint, StateTransferError};
 use client::Error as ClientError;
 use env_info::EnvInfo;
-use executive::{Executive, Executed, TransactOptions, contract_address};
+use executive::{Executive, Executed, TransactOptions, MAX_CALL_OUTPUT_SIZE, contract_address};
 use receipt::LocalizedReceipt;
+use uncle_info::UncleInfo;
 pub use blockchain::CacheSize as BlockChainCacheSize;
 use trace::{TraceDB, ImportRequest as TraceImportRequest, LocalizedTrace, Database as TraceDatabase};
 use trace;
@@ -66,6 +71,8 @@ pub struct ClientReport {
 	pub gas_processed: U256,
 	/// Memory used by state DB
 	pub state_db_mem: usize,
+	/// Memory assigned to and used by caches sharing the memory budget.
+	pub memory_budget: Vec<BudgetAssignment>,
 }
 
 impl ClientReport {
@@ -79,20 +86,24 @@ impl ClientReport {
 
 /// Blockchain database client backed by a persistent database. Owns and manages a blockchain and a block queue.
 /// Call `import_block()` to import a block asynchronously; `flush_queue()` flushes the queue.
-pub struct Client<V = CanonVerifier> where V: Verifier {
+pub struct Client {
 	chain: Arc<BlockChain>,
 	tracedb: Arc<TraceDB<BlockChain>>,
 	engine: Arc<Box<Engine>>,
 	state_db: Mutex<Box<JournalDB>>,
-	block_queue: BlockQueue,
+	block_queue: Arc<BlockQueue>,
+	memory_budget: Option<Mutex<MemoryBudget>>,
 	report: RwLock<ClientReport>,
 	import_lock: Mutex<()>,
 	panic_handler: Arc<PanicHandler>,
-	verifier: PhantomData<V>,
+	verifier: Box<Verifier>,
 	vm_factory: Arc<EvmFactory>,
+	state_stats: Mutex<HashMap<H256, StateStats>>,
 }
 
 const HISTORY: u64 = 1200;
+/// Number of exact state statistics results kept in cache.
+const STATE_STATS_CACHE_SIZE: usize = 4;
 // DO NOT TOUCH THIS ANY MORE UNLESS YOU REALLY KNOW WHAT YOU'RE DOING.
 // Altering it will force a blanket DB update for *all* JournalDB-derived
 //   databases.
@@ -100,13 +111,6 @@ const HISTORY: u64 = 1200;
 // of which you actually want force an upgrade.
 const CLIENT_DB_VER_STR: &'static str = "5.3";
 
-impl Client<CanonVerifier> {
-	/// Create a new client with given spec and DB path.
-	pub fn new(config: ClientConfig, spec: Spec, path: &Path, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client>, ClientError> {
-		Client::<CanonVerifier>::new_with_verifier(config, spec, path, message_channel)
-	}
-}
-
 /// Get the path for the databases given the root path and information on the databases.
 pub fn get_db_path(path: &Path, pruning: journaldb::Algorithm, genesis_hash: H256) -> PathBuf {
 	let mut dir = path.to_path_buf();
@@ -124,25 +128,56 @@ pub fn append_path(path: &Path, item: &str) -> String {
 	p.to_str().unwrap().to_owned()
 }
 
-impl<V> Client<V> where V: Verifier {
-	///  Create a new client with given spec and DB path and custom verifier.
-	pub fn new_with_verifier(config: ClientConfig, spec: Spec, path: &Path, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client<V>>, ClientError> {
+/// Extract block body (uncles and transactions) RLP from raw block data.
+fn block_body_rlp(block: &[u8]) -> Bytes {
+	let rlp = Rlp::new(block);
+	let mut body = RlpStream::new_list(2);
+	body.append_raw(rlp.at(1).as_raw(), 1);
+	body.append_raw(rlp.at(2).as_raw(), 1);
+	body.out()
+}
+
+impl Client {
+	/// Create a new client with given spec and DB path.
+	///
+	/// Verifiers other than `VerifierType::Canon` are accepted only if `config.trusted_import` is set.
+	pub fn new(config: ClientConfig, spec: Spec, path: &Path, message_channel: IoChannel<NetSyncMessage> ) -> Result<Arc<Client>, ClientError> {
+		if config.verifier_type != VerifierType::Canon && !config.trusted_import {
+			return Err(ClientError::UntrustedVerifier(config.verifier_type));
+		}
+
 		let path = get_db_path(path, config.pruning, spec.genesis_header().hash());
 		let gb = spec.genesis_block();
-		let chain = Arc::new(BlockChain::new(config.blockchain, &gb, &path));
-		let tracedb = Arc::new(try!(TraceDB::new(config.tracing, &path, chain.clone())));
+		let db_config = config.database_config();
+		let chain = Arc::new(BlockChain::new(config.blockchain, &gb, &path, db_config.clone()));
+		let tracedb = match config.tracing.reindex {
+			true => try!(TraceDB::reindex(config.tracing, &path, chain.clone())),
+			false => try!(TraceDB::new(config.tracing, &path, chain.clone())),
+		};
+		let tracedb = Arc::new(tracedb);
 
-		let mut state_db = journaldb::new(&append_path(&path, "state"), config.pruning);
+		let mut state_db = journaldb::new(&append_path(&path, "state"), config.pruning, db_config);
 
-		if state_db.is_empty() && spec.ensure_db_good(state_db.as_hashdb_mut()) {
-			state_db.commit(0, &spec.genesis_header().hash(), None).expect("Error commiting genesis state to state DB");
+		if state_db.is_empty() {
+			if spec.ensure_db_good(state_db.as_hashdb_mut()) {
+				state_db.commit(0, &spec.genesis_header().hash(), None).expect("Error commiting genesis state to state DB");
+			}
+			chain.set_tracks_preimages();
 		}
 
 		let engine = Arc::new(spec.engine);
 
-		let block_queue = BlockQueue::new(config.queue, engine.clone(), message_channel);
+		let block_queue = Arc::new(BlockQueue::new(config.queue, engine.clone(), config.verifier_type.verifying_seal(), message_channel));
 		let panic_handler = PanicHandler::new_in_arc();
-		panic_handler.forward_from(&block_queue);
+		panic_handler.forward_from(&*block_queue);
+
+		let memory_budget = config.memory_budget.map(|config| {
+			let mut budget = MemoryBudget::new(config);
+			budget.register(BudgetedCache::Blockchain, chain.clone());
+			budget.register(BudgetedCache::Traces, tracedb.clone());
+			budget.register(BudgetedCache::BlockQueue, block_queue.clone());
+			Mutex::new(budget)
+		});
 
 		let client = Client {
 			chain: chain,
@@ -150,11 +185,13 @@ impl<V> Client<V> where V: Verifier {
 			engine: engine,
 			state_db: Mutex::new(state_db),
 			block_queue: block_queue,
+			memory_budget: memory_budget,
 			report: RwLock::new(Default::default()),
 			import_lock: Mutex::new(()),
 			panic_handler: panic_handler,
-			verifier: PhantomData,
+			verifier: ::verification::new(config.verifier_type),
 			vm_factory: Arc::new(EvmFactory::new(config.vm_type)),
+			state_stats: Mutex::new(HashMap::new()),
 		};
 
 		Ok(Arc::new(client))
@@ -180,7 +217,25 @@ impl<V> Client<V> where V: Verifier {
 		last_hashes
 	}
 
-	fn check_and_close_block(&self, block: &PreverifiedBlock) -> Result<LockedBlock, ()> {
+	/// Returns environment of the latest block and a copy of its state in which sender of `t` has maximal balance.
+	fn call_env(&self) -> (EnvInfo, State) {
+		let header = self.block_header(BlockID::Latest).unwrap();
+		let view = HeaderView::new(&header);
+		let last_hashes = self.build_last_hashes(view.hash());
+		let env_info = EnvInfo {
+			number: view.number(),
+			author: view.author(),
+			timestamp: view.timestamp(),
+			difficulty: view.difficulty(),
+			last_hashes: last_hashes,
+			gas_used: U256::zero(),
+			gas_limit: view.gas_limit(),
+		};
+		// that's just a copy of the state.
+		(env_info, self.state())
+	}
+
+	fn check_and_close_block(&self, block: &PreverifiedBlock, verifier: &Verifier) -> Result<LockedBlock, Error> {
 		let engine = self.engine.deref().deref();
 		let header = &block.header;
 
@@ -188,21 +243,21 @@ impl<V> Client<V> where V: Verifier {
 		let best_block_number = self.chain.best_block_number();
 		if best_block_number >= HISTORY && header.number() <= best_block_number - HISTORY {
 			warn!(target: "client", "Block import failed for #{} ({})\nBlock is ancient (current best block: #{}).", header.number(), header.hash(), best_block_number);
-			return Err(());
+			return Err(x!(BlockError::RidiculousNumber(OutOfBounds { min: Some(best_block_number - HISTORY + 1), max: None, found: header.number() })));
 		}
 
 		// Verify Block Family
-		let verify_family_result = V::verify_block_family(&header, &block.bytes, engine, self.chain.deref());
+		let verify_family_result = verifier.verify_block_family(&header, &block.bytes, engine, self.chain.deref());
 		if let Err(e) = verify_family_result {
 			warn!(target: "client", "Stage 3 block verification failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
-			return Err(());
+			return Err(e);
 		};
 
 		// Check if Parent is in chain
 		let chain_has_parent = self.chain.block_header(&header.parent_hash);
 		if let None = chain_has_parent {
 			warn!(target: "client", "Block import failed for #{} ({}): Parent not found ({}) ", header.number(), header.hash(), header.parent_hash);
-			return Err(());
+			return Err(x!(BlockError::UnknownParent(header.parent_hash.clone())));
 		};
 
 		// Enact Verified Block
@@ -213,14 +268,14 @@ impl<V> Client<V> where V: Verifier {
 		let enact_result = enact_verified(&block, engine, self.tracedb.tracing_enabled(), db, &parent, last_hashes, &self.vm_factory);
 		if let Err(e) = enact_result {
 			warn!(target: "client", "Block import failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
-			return Err(());
+			return Err(e);
 		};
 
 		// Final Verification
 		let locked_block = enact_result.unwrap();
-		if let Err(e) = V::verify_block_final(&header, locked_block.block().header()) {
+		if let Err(e) = verifier.verify_block_final(&header, locked_block.block().header()) {
 			warn!(target: "client", "Stage 4 block verification failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
-			return Err(());
+			return Err(e);
 		}
 
 		Ok(locked_block)
@@ -252,6 +307,66 @@ impl<V> Client<V> where V: Verifier {
 		(map_to_vec(enacted), map_to_vec(retracted))
 	}
 
+	/// Commits state of the closed block and inserts the block into the chain.
+	fn commit_block(&self, closed_block: LockedBlock, block: &PreverifiedBlock) -> ImportRoute {
+		let header = &block.header;
+
+		// Are we committing an era?
+		let ancient = if header.number() >= HISTORY {
+			let n = header.number() - HISTORY;
+			// Blocks preceding imported state are not known.
+			self.chain.block_hash(n).map(|hash| (n, hash))
+		} else {
+			None
+		};
+
+		// Commit results
+		let receipts = closed_block.block().receipts().clone();
+		let traces = From::from(closed_block.block().traces().clone().unwrap_or_else(Vec::new));
+
+		closed_block.drain()
+			.commit(header.number(), &header.hash(), ancient)
+			.expect("State DB commit failed.");
+
+		// And update the chain after commit to prevent race conditions
+		// (when something is in chain but you are not able to fetch details)
+		let route = self.chain.insert_block(&block.bytes, receipts);
+		self.tracedb.import(TraceImportRequest {
+			traces: traces,
+			block_hash: header.hash(),
+			block_number: header.number(),
+			enacted: route.enacted.clone(),
+			retracted: route.retracted.len()
+		});
+
+		self.report.write().unwrap().accrue_block(&block);
+		trace!(target: "client", "Imported #{} ({})", header.number(), header.hash());
+		route
+	}
+
+	/// Imports a block from a trusted source (e.g. a local export) synchronously, bypassing the block queue.
+	///
+	/// Seals of the block and its uncles are not verified, but the block still has to extend a known block
+	/// and its state root has to match the state after execution. No chain notifications are sent.
+	pub fn import_trusted_block(&self, bytes: Bytes) -> ImportResult {
+		let _import_lock = self.import_lock.lock();
+		let header = BlockView::new(&bytes).header();
+		let hash = header.hash();
+		if self.chain.is_known(&hash) {
+			return Err(x!(ImportError::AlreadyInChain));
+		}
+		if !self.chain.is_known(&header.parent_hash) {
+			return Err(x!(BlockError::UnknownParent(header.parent_hash.clone())));
+		}
+
+		let engine = self.engine.deref().deref();
+		try!(verify_block_basic(&header, &bytes, engine, false));
+		let block = try!(verify_block_unordered(header, bytes, engine, false));
+		let closed_block = try!(self.check_and_close_block(&block, &CanonVerifier));
+		self.commit_block(closed_block, &block);
+		Ok(hash)
+	}
+
 	/// This is triggered by a message coming from a block queue when the block is ready for insertion
 	pub fn import_verified_blocks(&self, io: &IoChannel<NetSyncMessage>) -> usize {
 		let max_blocks_to_import = 128;
@@ -272,45 +387,15 @@ impl<V> Client<V> where V: Verifier {
 				invalid_blocks.insert(header.hash());
 				continue;
 			}
-			let closed_block = self.check_and_close_block(&block);
+			let closed_block = self.check_and_close_block(&block, &*self.verifier);
 			if let Err(_) = closed_block {
 				invalid_blocks.insert(header.hash());
 				break;
 			}
 			imported_blocks.push(header.hash());
 
-			// Are we committing an era?
-			let ancient = if header.number() >= HISTORY {
-				let n = header.number() - HISTORY;
-				Some((n, self.chain.block_hash(n).unwrap()))
-			} else {
-				None
-			};
-
-			// Commit results
-			let closed_block = closed_block.unwrap();
-			let receipts = closed_block.block().receipts().clone();
-			let traces = From::from(closed_block.block().traces().clone().unwrap_or_else(Vec::new));
-
-			closed_block.drain()
-				.commit(header.number(), &header.hash(), ancient)
-				.expect("State DB commit failed.");
-
-			// And update the chain after commit to prevent race conditions
-			// (when something is in chain but you are not able to fetch details)
-			let route = self.chain.insert_block(&block.bytes, receipts);
-			self.tracedb.import(TraceImportRequest {
-				traces: traces,
-				block_hash: header.hash(),
-				block_number: header.number(),
-				enacted: route.enacted.clone(),
-				retracted: route.retracted.len()
-			});
-
+			let route = self.commit_block(closed_block.unwrap(), &block);
 			import_results.push(route);
-
-			self.report.write().unwrap().accrue_block(&block);
-			trace!(target: "client", "Imported #{} ({})", header.number(), header.hash());
 		}
 
 		let imported = imported_blocks.len();
@@ -351,6 +436,85 @@ impl<V> Client<V> where V: Verifier {
 		State::from_existing(self.state_db.lock().unwrap().boxed_clone(), HeaderView::new(&self.best_block_header()).state_root(), self.engine.account_start_nonce())
 	}
 
+	/// Writes state of given block to `writer` in a portable format. Returns number of exported accounts.
+	/// `progress` is called with number of exported accounts; returning `false` cancels the export.
+	pub fn export_state(&self, at: BlockID, writer: &mut Write, progress: &mut FnMut(u64) -> bool) -> Result<u64, StateTransferError> {
+		if !self.chain.tracks_preimages() {
+			return Err(StateTransferError::PreimagesUnavailable);
+		}
+		let hash = try!(Self::block_hash(&self.chain, at).ok_or(StateTransferError::UnknownBlock));
+		let block = try!(self.chain.block(&hash).ok_or(StateTransferError::UnknownBlock));
+		let details = try!(self.chain.block_details(&hash).ok_or(StateTransferError::UnknownBlock));
+		let manifest = StateManifest {
+			state_root: BlockView::new(&block).header_view().state_root(),
+			block: block,
+			total_difficulty: details.total_difficulty,
+		};
+		let db = self.state_db.lock().unwrap().boxed_clone();
+		state_export::export(db.as_hashdb(), &manifest, writer, state_export::DEFAULT_CHUNK_SIZE, progress)
+	}
+
+	/// Imports state written by `export_state` and makes its block the best block, so that sync continues from it.
+	///
+	/// Only a client without any imported blocks accepts the state. Progress is stored after every chunk,
+	/// an interrupted import is reported by `state_import_checkpoint` and resumed by importing the same export again.
+	/// `progress` is called with number of imported accounts; returning `false` cancels the import.
+	pub fn import_state(&self, reader: &mut Read, expected_root: &H256, progress: &mut FnMut(u64) -> bool) -> Result<(), StateTransferError> {
+		let _import_lock = self.import_lock.lock();
+		let mut reader = try!(StateReader::new(reader));
+		let manifest = reader.manifest().clone();
+		let (hash, number) = {
+			let header = BlockView::new(&manifest.block).header_view();
+			if manifest.state_root != *expected_root || header.state_root() != *expected_root {
+				return Err(StateTransferError::RootMismatch { expected: expected_root.clone(), got: manifest.state_root });
+			}
+			(header.sha3(), header.number())
+		};
+
+		let mut checkpoint = match self.state_import_checkpoint() {
+			Some(ref checkpoint) if checkpoint.block_hash == hash => checkpoint.clone(),
+			None if self.chain.best_block_number() == 0 => ImportCheckpoint::new(hash.clone()),
+			_ => return Err(StateTransferError::NotFresh),
+		};
+		if checkpoint.next_chunk > 0 {
+			info!(target: "client", "Resuming state import of #{} from chunk {}", number, checkpoint.next_chunk);
+		}
+
+		let mut index = 0;
+		while let Some(chunk) = try!(reader.next_chunk()) {
+			if index >= checkpoint.next_chunk {
+				let mut state_db = self.state_db.lock().unwrap();
+				try!(state_export::import_chunk(state_db.as_hashdb_mut(), &chunk, &mut checkpoint));
+				state_db.commit(number, &hash, None).expect("State DB commit failed.");
+				checkpoint.next_chunk += 1;
+				self.chain.set_state_import_checkpoint(Some(&encode(&checkpoint)));
+				if !progress(checkpoint.accounts) {
+					return Err(StateTransferError::Cancelled);
+				}
+			}
+			index += 1;
+		}
+
+		if checkpoint.pending_storage.is_some() || checkpoint.state_root != *expected_root {
+			return Err(StateTransferError::RootMismatch { expected: expected_root.clone(), got: checkpoint.state_root });
+		}
+		self.chain.insert_pivot_block(&manifest.block, manifest.total_difficulty);
+		self.chain.set_state_import_checkpoint(None);
+		info!(target: "client", "Imported state of #{} ({}), {} accounts", number, hash, checkpoint.accounts);
+		Ok(())
+	}
+
+	/// Returns progress of an interrupted state import.
+	pub fn state_import_checkpoint(&self) -> Option<ImportCheckpoint> {
+		self.chain.state_import_checkpoint().map(|checkpoint| decode(&checkpoint))
+	}
+
+	/// Indexes uncles of canon blocks in range `from..to` (inclusive).
+	/// Returns number of indexed uncles. See `BlockChain::backfill_uncle_addresses`.
+	pub fn backfill_uncle_index(&self, from: BlockNumber, to: BlockNumber) -> usize {
+		self.chain.backfill_uncle_addresses(from, to)
+	}
+
 	/// Get info on the cache.
 	pub fn blockchain_cache_info(&self) -> BlockChainCacheSize {
 		self.chain.cache_size()
@@ -360,12 +524,19 @@ impl<V> Client<V> where V: Verifier {
 	pub fn report(&self) -> ClientReport {
 		let mut report = self.report.read().unwrap().clone();
 		report.state_db_mem = self.state_db.lock().unwrap().mem_used();
+		if let Some(ref budget) = self.memory_budget {
+			report.memory_budget = budget.lock().unwrap().assignments();
+		}
 		report
 	}
 
 	/// Tick the client.
 	pub fn tick(&self) {
+		if let Some(ref budget) = self.memory_budget {
+			budget.lock().unwrap().rebalance();
+		}
 		self.chain.collect_garbage();
+		self.tracedb.collect_garbage();
 		self.block_queue.collect_garbage();
 	}
 
@@ -404,32 +575,23 @@ impl<V> Client<V> where V: Verifier {
 	}
 }
 
-impl<V> BlockChainClient for Client<V> where V: Verifier {
-	fn call(&self, t: &SignedTransaction) -> Result<Executed, ExecutionError> {
-		let header = self.block_header(BlockID::Latest).unwrap();
-		let view = HeaderView::new(&header);
-		let last_hashes = self.build_last_hashes(view.hash());
-		let env_info = EnvInfo {
-			number: view.number(),
-			author: view.author(),
-			timestamp: view.timestamp(),
-			difficulty: view.difficulty(),
-			last_hashes: last_hashes,
-			gas_used: U256::zero(),
-			gas_limit: U256::max_value(),
-		};
-		// that's just a copy of the state.
-		let mut state = self.state();
-		let sender = try!(t.sender().map_err(|e| {
-			let message = format!("Transaction malformed: {:?}", e);
-			ExecutionError::TransactionMalformed(message)
-		}));
-		let balance = state.balance(&sender);
-		// give the sender max balance
-		state.sub_balance(&sender, &balance);
-		state.add_balance(&sender, &U256::max_value());
-		let options = TransactOptions { tracing: false, check_nonce: false };
-		Executive::new(&mut state, &env_info, self.engine.deref().deref(), &self.vm_factory).transact(t, options)
+impl BlockChainClient for Client {
+	fn call_with_env(&self, t: &SignedTransaction, alter_env: &Fn(&mut EnvInfo), static_call: bool, deadline: Option<Instant>) -> Result<Executed, ExecutionError> {
+		let (mut env_info, mut state) = self.call_env();
+		env_info.gas_limit = U256::max_value();
+		alter_env(&mut env_info);
+		let options = TransactOptions { tracing: false, vm_tracing: false, check_nonce: false, charge_fees: false, diffing: false, static_call: static_call, max_output_size: Some(MAX_CALL_OUTPUT_SIZE) };
+		Executive::new(&mut state, &env_info, self.engine.deref().deref(), &self.vm_factory)
+			.with_deadline(deadline)
+			.transact(t, options)
+	}
+
+	fn estimate_gas(&self, t: &SignedTransaction, deadline: Option<Instant>) -> Result<U256, ExecutionError> {
+		let (env_info, mut state) = self.call_env();
+		let options = TransactOptions { tracing: false, vm_tracing: false, check_nonce: false, charge_fees: false, diffing: false, static_call: false, max_output_size: Some(MAX_CALL_OUTPUT_SIZE) };
+		Executive::new(&mut state, &env_info, self.engine.deref().deref(), &self.vm_factory)
+			.with_deadline(deadline)
+			.estimate_gas(t, options)
 	}
 
 	// TODO [todr] Should be moved to miner crate eventually.
@@ -442,61 +604,40 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 	}
 
 	// TODO [todr] Should be moved to miner crate eventually.
-	fn prepare_sealing(&self, author: Address, gas_floor_target: U256, extra_data: Bytes, transactions: Vec<SignedTransaction>)
+	fn prepare_sealing(&self, author: Address, gas_limit_target: GasLimitTarget, extra_data: Bytes, include_uncles: bool, transactions: Vec<SignedTransaction>)
 		-> (Option<ClosedBlock>, HashSet<H256>) {
 		let engine = self.engine.deref().deref();
 		let h = self.chain.best_block_hash();
-		let mut invalid_transactions = HashSet::new();
 
 		let mut b = OpenBlock::new(
 			engine,
 			&self.vm_factory,
 			false,	// TODO: this will need to be parameterised once we want to do immediate mining insertion.
 			self.state_db.lock().unwrap().boxed_clone(),
-			match self.chain.block_header(&h) { Some(ref x) => x, None => { return (None, invalid_transactions) } },
+			match self.chain.block_header(&h) { Some(ref x) => x, None => { return (None, HashSet::new()) } },
 			self.build_last_hashes(h.clone()),
 			author,
-			gas_floor_target,
+			gas_limit_target,
 			extra_data,
 		);
 
 		// Add uncles
-		self.chain
-			.find_uncle_headers(&h, engine.maximum_uncle_age())
-			.unwrap()
-			.into_iter()
-			.take(engine.maximum_uncle_count())
-			.foreach(|h| {
-				b.push_uncle(h).unwrap();
-			});
-
-		// Add transactions
-		let block_number = b.block().header().number();
-		let min_tx_gas = U256::from(self.engine.schedule(&b.env_info()).tx_gas);
-
-		for tx in transactions {
-			// Push transaction to block
-			let hash = tx.hash();
-			let import = b.push_transaction(tx, None);
-
-			match import {
-				Err(Error::Execution(ExecutionError::BlockGasLimitReached { gas_limit, gas_used, .. })) => {
-					trace!(target: "miner", "Skipping adding transaction to block because of gas limit: {:?}", hash);
-					// Exit early if gas left is smaller then min_tx_gas
-					if gas_limit - gas_used < min_tx_gas {
-						break;
-					}
-				},
-				Err(e) => {
-					invalid_transactions.insert(hash);
-					trace!(target: "miner",
-						   "Error adding transaction to block: number={}. transaction_hash={:?}, Error: {:?}",
-						   block_number, hash, e);
-				},
-				_ => {}
+		if include_uncles {
+			let uncles = self.chain.find_uncle_headers(&h, engine.maximum_uncle_age()).unwrap_or_else(Vec::new);
+			for uncle in uncles {
+				if b.uncles().len() >= engine.maximum_uncle_count() {
+					break;
+				}
+				let hash = uncle.hash();
+				if let Err(e) = b.push_uncle(uncle) {
+					debug!(target: "miner", "Skipping invalid uncle {:?}: {:?}", hash, e);
+				}
 			}
 		}
 
+		// Add transactions
+		let invalid_transactions = b.push_transactions(transactions);
+
 		// And close
 		let b = b.close();
 		trace!(target: "miner", "Sealing: number={}, hash={}, diff={}",
@@ -513,16 +654,14 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 
 	fn block_body(&self, id: BlockID) -> Option<Bytes> {
 		Self::block_hash(&self.chain, id).and_then(|hash| {
-			self.chain.block(&hash).map(|bytes| {
-				let rlp = Rlp::new(&bytes);
-				let mut body = RlpStream::new_list(2);
-				body.append_raw(rlp.at(1).as_raw(), 1);
-				body.append_raw(rlp.at(2).as_raw(), 1);
-				body.out()
-			})
+			self.chain.block(&hash).map(|bytes| block_body_rlp(&bytes))
 		})
 	}
 
+	fn block_bodies(&self, hashes: &[H256]) -> Vec<Option<Bytes>> {
+		self.chain.blocks(hashes).into_iter().map(|block| block.map(|bytes| block_body_rlp(&bytes))).collect()
+	}
+
 	fn block(&self, id: BlockID) -> Option<Bytes> {
 		Self::block_hash(&self.chain, id).and_then(|hash| {
 			self.chain.block(&hash)
@@ -570,6 +709,27 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 		self.block(id.0).and_then(|block| BlockView::new(&block).uncle_at(index))
 	}
 
+	fn uncle_extra_info(&self, id: UncleID) -> Option<UncleInfo> {
+		let index = id.1;
+		self.block(id.0).and_then(|block| {
+			let view = BlockView::new(&block);
+			let nephew = view.header_view();
+			view.uncle_at(index).map(|uncle| UncleInfo {
+				hash: uncle.hash(),
+				number: uncle.number(),
+				author: uncle.author().clone(),
+				nephew_hash: nephew.sha3(),
+				nephew_number: nephew.number(),
+				index: index,
+				reward: self.engine.uncle_reward(&uncle, nephew.number()),
+			})
+		})
+	}
+
+	fn uncle_position(&self, hash: &H256) -> Option<UncleID> {
+		self.chain.uncle_address(hash).map(|address| UncleID(BlockID::Hash(address.block_hash), address.index))
+	}
+
 	fn transaction_receipt(&self, id: TransactionID) -> Option<LocalizedReceipt> {
 		self.transaction_address(id).and_then(|address| {
 			let t = self.chain.block(&address.block_hash)
@@ -622,6 +782,16 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 		}
 	}
 
+	fn ancestry_iter(&self, from: BlockID) -> Option<AncestryIter> {
+		Self::block_hash(&self.chain, from)
+			.and_then(|hash| self.chain.ancestry_iter(hash))
+			.map(|iter| Box::new(iter) as AncestryIter)
+	}
+
+	fn common_ancestor(&self, a: H256, b: H256) -> Option<H256> {
+		self.chain.common_ancestor(&a, &b)
+	}
+
 	fn find_uncles(&self, hash: &H256) -> Option<Vec<H256>> {
 		self.chain.find_uncle_hashes(hash, self.engine.maximum_uncle_age())
 	}
@@ -634,6 +804,10 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 		self.chain.block_receipts(hash).map(|receipts| rlp::encode(&receipts).to_vec())
 	}
 
+	fn block_receipts_list(&self, hashes: &[H256]) -> Vec<Option<Bytes>> {
+		self.chain.block_receipts_list(hashes).into_iter().map(|receipts| receipts.map(|receipts| rlp::encode(&receipts).to_vec())).collect()
+	}
+
 	fn import_block(&self, bytes: Bytes) -> ImportResult {
 		{
 			let header = BlockView::new(&bytes).header_view();
@@ -757,6 +931,37 @@ impl<V> BlockChainClient for Client<V> where V: Verifier {
 	fn last_hashes(&self) -> LastHashes {
 		self.build_last_hashes(self.chain.best_block_hash())
 	}
+
+	fn chain_id(&self) -> Option<u64> {
+		self.engine.params().chain_id
+	}
+
+	fn state_stats(&self, id: BlockID, sample: Option<f64>, progress: &mut FnMut(u64) -> bool) -> Option<StateStats> {
+		let root = match self.block_header(id) {
+			Some(header) => HeaderView::new(&header).state_root(),
+			None => return None,
+		};
+		if let Some(stats) = self.state_stats.lock().unwrap().get(&root) {
+			return Some(stats.clone());
+		}
+
+		let db = self.state_db.lock().unwrap().boxed_clone();
+		if !db.as_hashdb().exists(&root) {
+			// State was pruned.
+			return None;
+		}
+		let stats = state_stats::collect(db.as_hashdb(), &root, sample, progress);
+		if let Some(ref stats) = stats {
+			if stats.is_exact() {
+				let mut cache = self.state_stats.lock().unwrap();
+				if cache.len() >= STATE_STATS_CACHE_SIZE {
+					cache.clear();
+				}
+				cache.insert(root, stats.clone());
+			}
+		}
+		stats
+	}
 }
 
 impl MayPanic for Client {
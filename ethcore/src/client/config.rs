@@ -18,10 +18,14 @@ pub use block_queue::BlockQueueConfig;
 pub use blockchain::BlockChainConfig;
 pub use trace::{Config as TraceConfig, Switch};
 pub use evm::VMType;
+pub use verification::VerifierType;
+pub use util::kvdb::CompactionProfile;
 use util::journaldb;
+use util::kvdb::DatabaseConfig;
+use client::MemoryBudgetConfig;
 
 /// Client configuration. Includes configs for all sub-systems.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct ClientConfig {
 	/// Block queue configuration.
 	pub queue: BlockQueueConfig,
@@ -35,4 +39,73 @@ pub struct ClientConfig {
 	pub pruning: journaldb::Algorithm,
 	/// The name of the client instance.
 	pub name: String,
+	/// Total memory budget shared by caches. Overrides cache sizes of blockchain, traces and block queue.
+	pub memory_budget: Option<MemoryBudgetConfig>,
+	/// Block cache size of each of the state, blocks and extras databases (in MB).
+	pub db_cache_size: Option<usize>,
+	/// Compaction profile of the databases.
+	pub db_compaction: CompactionProfile,
+	/// Use write-ahead log for the databases.
+	pub db_wal: bool,
+	/// Type of block verifier used by the block queue and the import.
+	pub verifier_type: VerifierType,
+	/// Client is used only for import of trusted blocks (e.g. from a local file), never for live sync.
+	/// Required by verifiers other than `VerifierType::Canon`.
+	pub trusted_import: bool,
+}
+
+impl Default for ClientConfig {
+	fn default() -> Self {
+		ClientConfig {
+			queue: Default::default(),
+			blockchain: Default::default(),
+			tracing: Default::default(),
+			vm_type: Default::default(),
+			pruning: Default::default(),
+			name: Default::default(),
+			memory_budget: None,
+			db_cache_size: None,
+			db_compaction: CompactionProfile::default(),
+			db_wal: true,
+			verifier_type: VerifierType::default(),
+			trusted_import: false,
+		}
+	}
+}
+
+impl ClientConfig {
+	/// Returns configuration of the client databases.
+	pub fn database_config(&self) -> DatabaseConfig {
+		DatabaseConfig {
+			prefix_size: None,
+			cache_size: self.db_cache_size,
+			compaction: self.db_compaction,
+			wal: self.db_wal,
+			columns: None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ClientConfig, CompactionProfile};
+
+	#[test]
+	fn database_config_follows_client_config() {
+		let default = ClientConfig::default().database_config();
+		assert_eq!(default.cache_size, None);
+		assert_eq!(default.compaction, CompactionProfile::SSD);
+		assert!(default.wal);
+
+		let config = ClientConfig {
+			db_cache_size: Some(128),
+			db_compaction: CompactionProfile::HDD,
+			db_wal: false,
+			..ClientConfig::default()
+		};
+		let db_config = config.database_config();
+		assert_eq!(db_config.cache_size, Some(128));
+		assert_eq!(db_config.compaction, CompactionProfile::HDD);
+		assert!(!db_config.wal);
+	}
 }
@@ -1,4 +1,5 @@
 use trace::Error as TraceError;
+use verification::VerifierType;
 use std::fmt::{Display, Formatter, Error as FmtError};
 
 /// Client configuration errors.
@@ -6,6 +7,8 @@ use std::fmt::{Display, Formatter, Error as FmtError};
 pub enum Error {
 	/// TraceDB configuration error.
 	Trace(TraceError),
+	/// Verifier skipping seal or state verification requested for a client which is not trusted.
+	UntrustedVerifier(VerifierType),
 }
 
 impl From<TraceError> for Error {
@@ -17,7 +20,8 @@ impl From<TraceError> for Error {
 impl Display for Error {
 	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
 		match *self {
-			Error::Trace(ref err) => write!(f, "{}", err)
+			Error::Trace(ref err) => write!(f, "{}", err),
+			Error::UntrustedVerifier(ref verifier) => write!(f, "{:?} verifier can only be used for trusted import", verifier),
 		}
 	}
 }
@@ -0,0 +1,404 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Memory budget shared by caches of the client.
+//!
+//! Single total budget is split between participating caches according to configured weights.
+//! Usage of caches is polled periodically and part of the free space of idle caches is moved
+//! to caches under pressure (e.g. block queue during initial sync), so the sum of assignments
+//! never exceeds the total.
+
+use std::cmp;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use blockchain::BlockChain;
+use block_queue::BlockQueue;
+use trace::{TraceDB, DatabaseExtras};
+
+/// Percentage of assigned memory garbage collected caches prefer to use, they are trimmed above it.
+const PREFERRED_PERCENT: usize = 75;
+/// Percentage of assigned memory above which cache is considered to be under pressure.
+/// Garbage collected caches hardly ever grow over their preferred size, so it can't be higher.
+const PRESSURE_THRESHOLD: usize = PREFERRED_PERCENT;
+/// Cache never gets less than its initial share divided by this number.
+const MIN_SHARE_DIVISOR: usize = 4;
+
+/// Cache participating in the memory budget.
+pub trait BudgetParticipant: Send + Sync {
+	/// Returns number of bytes currently used by the cache.
+	fn memory_used(&self) -> usize;
+
+	/// Limits memory used by the cache to `bytes`.
+	fn set_memory_budget(&self, bytes: usize);
+}
+
+impl BudgetParticipant for BlockChain {
+	fn memory_used(&self) -> usize {
+		self.cache_size().total()
+	}
+
+	fn set_memory_budget(&self, bytes: usize) {
+		self.configure_cache(bytes / 100 * PREFERRED_PERCENT, bytes);
+	}
+}
+
+impl<T> BudgetParticipant for TraceDB<T> where T: DatabaseExtras + Send + Sync {
+	fn memory_used(&self) -> usize {
+		self.cache_size()
+	}
+
+	fn set_memory_budget(&self, bytes: usize) {
+		self.configure_cache(bytes / 100 * PREFERRED_PERCENT, bytes);
+	}
+}
+
+impl BudgetParticipant for BlockQueue {
+	fn memory_used(&self) -> usize {
+		self.queue_info().mem_used
+	}
+
+	fn set_memory_budget(&self, bytes: usize) {
+		self.set_max_mem_use(bytes);
+	}
+}
+
+/// Identifies cache participating in the memory budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BudgetedCache {
+	/// Blockchain (extras and blocks) cache.
+	Blockchain,
+	/// Cache of traces and trace blooms.
+	Traces,
+	/// Queue of blocks being verified.
+	BlockQueue,
+}
+
+/// Memory budget configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryBudgetConfig {
+	/// Total number of bytes shared by all caches.
+	pub total: usize,
+	/// Relative weights used to split the budget initially (caches without weight get `1`).
+	pub weights: BTreeMap<BudgetedCache, u32>,
+}
+
+impl MemoryBudgetConfig {
+	/// Creates configuration with given total budget and default weights:
+	/// half of the budget goes to the blockchain cache, quarter to the trace cache and quarter to the block queue.
+	pub fn new(total: usize) -> Self {
+		let mut weights = BTreeMap::new();
+		weights.insert(BudgetedCache::Blockchain, 2);
+		weights.insert(BudgetedCache::Traces, 1);
+		weights.insert(BudgetedCache::BlockQueue, 1);
+		MemoryBudgetConfig {
+			total: total,
+			weights: weights,
+		}
+	}
+}
+
+/// Memory assigned to single cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetAssignment {
+	/// Cache the memory is assigned to.
+	pub cache: BudgetedCache,
+	/// Number of bytes assigned.
+	pub assigned: usize,
+	/// Number of bytes used (as of last rebalance).
+	pub used: usize,
+}
+
+struct Participant {
+	cache: Arc<BudgetParticipant>,
+	weight: usize,
+	min: usize,
+	assigned: usize,
+	used: usize,
+}
+
+/// Splits total memory budget between registered caches.
+pub struct MemoryBudget {
+	config: MemoryBudgetConfig,
+	participants: BTreeMap<BudgetedCache, Participant>,
+}
+
+impl MemoryBudget {
+	/// Creates coordinator of given budget without any participants.
+	pub fn new(config: MemoryBudgetConfig) -> Self {
+		MemoryBudget {
+			config: config,
+			participants: BTreeMap::new(),
+		}
+	}
+
+	/// Returns total budget in bytes.
+	pub fn total(&self) -> usize {
+		self.config.total
+	}
+
+	/// Registers cache and splits the budget again between all participants.
+	pub fn register(&mut self, id: BudgetedCache, cache: Arc<BudgetParticipant>) {
+		let weight = self.config.weights.get(&id).cloned().unwrap_or(1) as usize;
+		self.participants.insert(id, Participant {
+			cache: cache,
+			weight: weight,
+			min: 0,
+			assigned: 0,
+			used: 0,
+		});
+		self.allocate();
+	}
+
+	/// Assigns every participant its share according to weights.
+	fn allocate(&mut self) {
+		let total = self.config.total;
+		let weights = self.participants.values().fold(0, |sum, p| sum + p.weight);
+		for p in self.participants.values_mut() {
+			let share = match weights {
+				0 => 0,
+				_ => (total as u64 * p.weight as u64 / weights as u64) as usize,
+			};
+			p.min = share / MIN_SHARE_DIVISOR;
+			p.assigned = share;
+			p.cache.set_memory_budget(share);
+		}
+	}
+
+	/// Polls usage of all participants and moves memory from idle caches to caches under pressure.
+	/// Idle caches give up half of their free space, but never go below quarter of their initial share.
+	/// Returns `true` if assignments have changed.
+	pub fn rebalance(&mut self) -> bool {
+		for p in self.participants.values_mut() {
+			p.used = p.cache.memory_used();
+		}
+
+		let pressured = self.participants.iter()
+			.filter(|&(_, p)| p.used * 100 >= p.assigned * PRESSURE_THRESHOLD)
+			.map(|(id, _)| *id)
+			.collect::<Vec<_>>();
+		let pressured_weights = pressured.iter().fold(0, |sum, id| sum + self.participants[id].weight);
+		if pressured_weights == 0 {
+			return false;
+		}
+
+		let mut freed = 0;
+		for (id, p) in self.participants.iter_mut() {
+			if !pressured.contains(id) {
+				let spare = cmp::min((p.assigned - p.used) / 2, p.assigned.saturating_sub(p.min));
+				p.assigned -= spare;
+				freed += spare;
+			}
+		}
+		if freed == 0 {
+			return false;
+		}
+
+		for (id, p) in self.participants.iter_mut() {
+			if pressured.contains(id) {
+				// Remainder of the division is left unassigned.
+				p.assigned += (freed as u64 * p.weight as u64 / pressured_weights as u64) as usize;
+			}
+			p.cache.set_memory_budget(p.assigned);
+		}
+		trace!(target: "client", "Memory budget rebalanced: {:?}", self.assignments());
+		true
+	}
+
+	/// Returns current assignments and usage of all participants.
+	pub fn assignments(&self) -> Vec<BudgetAssignment> {
+		self.participants.iter().map(|(id, p)| BudgetAssignment {
+			cache: *id,
+			assigned: p.assigned,
+			used: p.used,
+		}).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use super::*;
+	use super::PREFERRED_PERCENT;
+
+	#[derive(Default)]
+	struct TestCache {
+		used: AtomicUsize,
+		budget: AtomicUsize,
+	}
+
+	impl TestCache {
+		fn use_memory(&self, bytes: usize) {
+			self.used.store(bytes, Ordering::Relaxed);
+		}
+
+		fn budget(&self) -> usize {
+			self.budget.load(Ordering::Relaxed)
+		}
+	}
+
+	impl BudgetParticipant for TestCache {
+		fn memory_used(&self) -> usize {
+			self.used.load(Ordering::Relaxed)
+		}
+
+		fn set_memory_budget(&self, bytes: usize) {
+			self.budget.store(bytes, Ordering::Relaxed);
+		}
+	}
+
+	fn budget_with_caches(total: usize) -> (MemoryBudget, Arc<TestCache>, Arc<TestCache>) {
+		let mut config = MemoryBudgetConfig::new(total);
+		config.weights.insert(BudgetedCache::Blockchain, 1);
+		config.weights.insert(BudgetedCache::BlockQueue, 3);
+		let mut budget = MemoryBudget::new(config);
+		let chain = Arc::new(TestCache::default());
+		let queue = Arc::new(TestCache::default());
+		budget.register(BudgetedCache::Blockchain, chain.clone());
+		budget.register(BudgetedCache::BlockQueue, queue.clone());
+		(budget, chain, queue)
+	}
+
+	fn assigned_total(budget: &MemoryBudget) -> usize {
+		budget.assignments().iter().fold(0, |sum, a| sum + a.assigned)
+	}
+
+	#[test]
+	fn should_split_budget_according_to_weights() {
+		// given
+		let (budget, chain, queue) = budget_with_caches(4000);
+
+		// then
+		assert_eq!(chain.budget(), 1000);
+		assert_eq!(queue.budget(), 3000);
+		assert_eq!(budget.assignments(), vec![
+			BudgetAssignment { cache: BudgetedCache::Blockchain, assigned: 1000, used: 0 },
+			BudgetAssignment { cache: BudgetedCache::BlockQueue, assigned: 3000, used: 0 },
+		]);
+	}
+
+	#[test]
+	fn should_split_budget_between_all_caches_by_default() {
+		// given
+		let mut budget = MemoryBudget::new(MemoryBudgetConfig::new(4000));
+		let caches = (0..3).map(|_| Arc::new(TestCache::default())).collect::<Vec<_>>();
+
+		// when
+		budget.register(BudgetedCache::Blockchain, caches[0].clone());
+		budget.register(BudgetedCache::Traces, caches[1].clone());
+		budget.register(BudgetedCache::BlockQueue, caches[2].clone());
+
+		// then
+		assert_eq!(caches.iter().map(|cache| cache.budget()).collect::<Vec<_>>(), vec![2000, 1000, 1000]);
+		assert_eq!(assigned_total(&budget), 4000);
+	}
+
+	#[test]
+	fn should_consider_cache_at_preferred_size_under_pressure() {
+		// given
+		let (mut budget, chain, queue) = budget_with_caches(4000);
+		queue.use_memory(0);
+
+		// when
+		chain.use_memory(1000 / 100 * PREFERRED_PERCENT);
+		let changed = budget.rebalance();
+
+		// then
+		assert!(changed);
+		assert!(chain.budget() > 1000);
+	}
+
+	#[test]
+	fn should_not_rebalance_without_pressure() {
+		// given
+		let (mut budget, chain, queue) = budget_with_caches(4000);
+		chain.use_memory(500);
+		queue.use_memory(2000);
+
+		// when
+		let changed = budget.rebalance();
+
+		// then
+		assert!(!changed);
+		assert_eq!(chain.budget(), 1000);
+		assert_eq!(queue.budget(), 3000);
+	}
+
+	#[test]
+	fn should_move_memory_to_cache_under_pressure() {
+		// given
+		let (mut budget, chain, queue) = budget_with_caches(4000);
+
+		// when
+		chain.use_memory(200);
+		queue.use_memory(2900);
+		let changed = budget.rebalance();
+
+		// then
+		assert!(changed);
+		assert_eq!(chain.budget(), 600);
+		assert_eq!(queue.budget(), 3400);
+
+		// when
+		chain.use_memory(600);
+		queue.use_memory(0);
+		budget.rebalance();
+
+		// then
+		assert_eq!(chain.budget(), 2300);
+		assert_eq!(queue.budget(), 1700);
+		assert_eq!(assigned_total(&budget), 4000);
+	}
+
+	#[test]
+	fn should_keep_minimal_share_of_idle_cache() {
+		// given
+		let (mut budget, chain, queue) = budget_with_caches(4000);
+		chain.use_memory(0);
+		queue.use_memory(3000);
+
+		// when
+		for _ in 0..10 {
+			budget.rebalance();
+			queue.use_memory(queue.budget());
+		}
+
+		// then
+		assert_eq!(chain.budget(), 250);
+		assert_eq!(queue.budget(), 3750);
+	}
+
+	#[test]
+	fn should_never_exceed_total_budget() {
+		// given
+		let (mut budget, chain, queue) = budget_with_caches(1001);
+
+		// when
+		for i in 0..20 {
+			let (busy, idle) = match i % 3 {
+				0 => (&chain, &queue),
+				_ => (&queue, &chain),
+			};
+			busy.use_memory(busy.budget() * 2);
+			idle.use_memory(idle.budget() / 3);
+			budget.rebalance();
+
+			// then
+			assert!(assigned_total(&budget) <= 1001);
+			assert_eq!(chain.budget() + queue.budget(), assigned_total(&budget));
+		}
+	}
+}
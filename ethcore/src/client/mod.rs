@@ -21,17 +21,24 @@ mod config;
 mod error;
 mod test_client;
 mod trace;
+mod state_stats;
+mod state_export;
+mod memory_budget;
 
 pub use self::client::*;
-pub use self::config::{ClientConfig, BlockQueueConfig, BlockChainConfig, Switch, VMType};
+pub use self::config::{ClientConfig, BlockQueueConfig, BlockChainConfig, Switch, VMType, CompactionProfile, VerifierType};
 pub use self::error::Error;
 pub use types::ids::*;
 pub use self::test_client::{TestBlockChainClient, EachBlockWith};
 pub use self::trace::Filter as TraceFilter;
-pub use executive::{Executed, Executive, TransactOptions};
+pub use self::state_stats::{StateStats, AccountStorage};
+pub use self::state_export::{StateTransferError, StateManifest, ImportCheckpoint};
+pub use self::memory_budget::{MemoryBudget, MemoryBudgetConfig, BudgetParticipant, BudgetedCache, BudgetAssignment};
+pub use executive::{Executed, Executive, TransactOptions, MAX_CALL_OUTPUT_SIZE};
 pub use env_info::{LastHashes, EnvInfo};
 
 use std::collections::HashSet;
+use std::time::Instant;
 use util::bytes::Bytes;
 use util::hash::{Address, H256, H2048};
 use util::numbers::U256;
@@ -44,8 +51,13 @@ use log_entry::LocalizedLogEntry;
 use filter::Filter;
 use error::{ImportResult, ExecutionError};
 use receipt::LocalizedReceipt;
+use uncle_info::UncleInfo;
 use trace::LocalizedTrace;
 use evm::Factory as EvmFactory;
+use gas_limit::GasLimitTarget;
+
+/// Iterator over hashes of a block and all its ancestors (towards genesis).
+pub type AncestryIter<'a> = Box<Iterator<Item = H256> + 'a>;
 
 /// Blockchain database client. Owns and manages a blockchain and a block queue.
 pub trait BlockChainClient : Sync + Send {
@@ -56,6 +68,11 @@ pub trait BlockChainClient : Sync + Send {
 	/// Block body is an RLP list of two items: uncles and transactions.
 	fn block_body(&self, id: BlockID) -> Option<Bytes>;
 
+	/// Get raw block bodies data for given block header hashes.
+	/// Returned vector has an entry for every requested hash, in the same order,
+	/// with `None` for unknown blocks.
+	fn block_bodies(&self, hashes: &[H256]) -> Vec<Option<Bytes>>;
+
 	/// Get raw block data by block header hash.
 	fn block(&self, id: BlockID) -> Option<Bytes>;
 
@@ -86,6 +103,13 @@ pub trait BlockChainClient : Sync + Send {
 	/// Get uncle with given id.
 	fn uncle(&self, id: UncleID) -> Option<Header>;
 
+	/// Get uncle with given id together with the block it was included in and the reward paid for it.
+	fn uncle_extra_info(&self, id: UncleID) -> Option<UncleInfo>;
+
+	/// Get position of uncle with given hash in the block it was included in.
+	/// Requires uncles index to be enabled (see `BlockChainConfig::index_uncles`).
+	fn uncle_position(&self, hash: &H256) -> Option<UncleID>;
+
 	/// Get transaction receipt with given hash.
 	fn transaction_receipt(&self, id: TransactionID) -> Option<LocalizedReceipt>;
 
@@ -93,6 +117,14 @@ pub trait BlockChainClient : Sync + Send {
 	/// See `BlockChain::tree_route`.
 	fn tree_route(&self, from: &H256, to: &H256) -> Option<TreeRoute>;
 
+	/// Get lazy iterator over hashes of block `from` and its ancestors.
+	/// Returns `None` if the block is unknown.
+	fn ancestry_iter(&self, from: BlockID) -> Option<AncestryIter>;
+
+	/// Get hash of the latest common ancestor of blocks `a` and `b`.
+	/// Returns `None` if any of the blocks is unknown.
+	fn common_ancestor(&self, a: H256, b: H256) -> Option<H256>;
+
 	/// Get all possible uncle hashes for a block.
 	fn find_uncles(&self, hash: &H256) -> Option<Vec<H256>>;
 
@@ -102,6 +134,11 @@ pub trait BlockChainClient : Sync + Send {
 	/// Get raw block receipts data by block header hash.
 	fn block_receipts(&self, hash: &H256) -> Option<Bytes>;
 
+	/// Get raw block receipts data for given block header hashes.
+	/// Returned vector has an entry for every requested hash, in the same order,
+	/// with `None` for unknown blocks.
+	fn block_receipts_list(&self, hashes: &[H256]) -> Vec<Option<Bytes>>;
+
 	/// Import a block into the blockchain.
 	fn import_block(&self, bytes: Bytes) -> ImportResult;
 
@@ -128,7 +165,9 @@ pub trait BlockChainClient : Sync + Send {
 
 	// TODO [todr] Should be moved to miner crate eventually.
 	/// Returns ClosedBlock prepared for sealing.
-	fn prepare_sealing(&self, author: Address, gas_floor_target: U256, extra_data: Bytes, transactions: Vec<SignedTransaction>)
+	/// Valid uncles (siblings of recent ancestors) are included only if `include_uncles` is set.
+	/// Gas limit of the block follows `gas_limit_target` within the bounds allowed by the engine.
+	fn prepare_sealing(&self, author: Address, gas_limit_target: GasLimitTarget, extra_data: Bytes, include_uncles: bool, transactions: Vec<SignedTransaction>)
 		-> (Option<ClosedBlock>, HashSet<H256>);
 
 	// TODO [todr] Should be moved to miner crate eventually.
@@ -136,7 +175,19 @@ pub trait BlockChainClient : Sync + Send {
 	fn try_seal(&self, block: LockedBlock, seal: Vec<Bytes>) -> Result<SealedBlock, LockedBlock>;
 
 	/// Makes a non-persistent transaction call.
-	fn call(&self, t: &SignedTransaction) -> Result<Executed, ExecutionError>;
+	/// Execution is aborted with `ExecutionError::Timeout` if it doesn't finish before `deadline`.
+	fn call(&self, t: &SignedTransaction, deadline: Option<Instant>) -> Result<Executed, ExecutionError> {
+		self.call_with_env(t, &|_| {}, false, deadline)
+	}
+
+	/// Makes a non-persistent transaction call with environment of the latest block altered by `alter_env`.
+	/// If `static_call` is set, any attempt to modify the state aborts execution with an exception.
+	/// Execution is aborted with `ExecutionError::Timeout` if it doesn't finish before `deadline`.
+	fn call_with_env(&self, t: &SignedTransaction, alter_env: &Fn(&mut EnvInfo), static_call: bool, deadline: Option<Instant>) -> Result<Executed, ExecutionError>;
+
+	/// Estimates minimal gas required to execute transaction on top of the latest block.
+	/// Estimation is aborted with `ExecutionError::Timeout` if it doesn't finish before `deadline`.
+	fn estimate_gas(&self, t: &SignedTransaction, deadline: Option<Instant>) -> Result<U256, ExecutionError>;
 
 	/// Returns EvmFactory.
 	fn vm_factory(&self) -> &EvmFactory;
@@ -155,5 +206,13 @@ pub trait BlockChainClient : Sync + Send {
 
 	/// Get last hashes starting from best block.
 	fn last_hashes(&self) -> LastHashes;
+
+	/// Returns id of the chain used to sign transactions (if the chain has one).
+	fn chain_id(&self) -> Option<u64>;
+
+	/// Walks state trie at given block and collects statistics of accounts.
+	/// This is expensive, it reads the whole state (or `sample` fraction of it) from the database.
+	/// `progress` is called with number of visited accounts; returning `false` cancels the walk.
+	fn state_stats(&self, id: BlockID, sample: Option<f64>, progress: &mut FnMut(u64) -> bool) -> Option<StateStats>;
 }
 
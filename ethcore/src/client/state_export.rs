@@ -0,0 +1,615 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Portable export of the state at a single block, used to provision new nodes of the same chain.
+//!
+//! The export is a sequence of records. Every record is a 4-byte big-endian payload length, the payload
+//! and `sha3` of the payload. Zero length marks the end of the export.
+//!
+//! The first record is the manifest: `[version, block, total_difficulty, state_root]`.
+//! Every following record is a chunk: a list of items, each one of
+//! - `[0, address, [[key, value], ...]]` - storage entries of an account which follows later,
+//! - `[1, address_hash, account, code, address]` - an account; `address` is empty for accounts without storage and code.
+//!
+//! Storage of a single account may be split across many chunks, so the size of a chunk is bounded by the
+//! chunk size plus the size of a single account entry.
+//!
+//! Storage and code are keyed by the account address in the database, while the state trie holds only
+//! hashes of addresses. Addresses are looked up by their hash, the state database keeps preimages of addresses
+//! of all accounts with storage or code (see `State::commit_into`). Databases created before the preimages
+//! were kept can't be exported and have to be resynced.
+
+use std::io;
+use common::*;
+use account_db::{AccountDB, AccountDBMut};
+
+/// Version of the export format.
+const FORMAT_VERSION: u32 = 1;
+/// Default upper bound of the chunk size in bytes.
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+/// Maximal size of a record accepted when reading an export.
+const MAX_RECORD_SIZE: usize = 64 * 1024 * 1024;
+
+const STORAGE_ITEM: u8 = 0;
+const ACCOUNT_ITEM: u8 = 1;
+
+/// Errors of state export and import.
+#[derive(Debug)]
+pub enum StateTransferError {
+	/// I/O error.
+	Io(io::Error),
+	/// Malformed data.
+	Decoder(DecoderError),
+	/// Block is not known.
+	UnknownBlock,
+	/// State of the block is not available in the database (e.g. it was pruned).
+	StateUnavailable,
+	/// Preimage of the address of an account with storage or code is not in the database.
+	UnknownAddress(H256),
+	/// Database was created before preimages of addresses were kept, a resync is required.
+	PreimagesUnavailable,
+	/// Export format version is not supported.
+	UnsupportedVersion(u32),
+	/// Chunk with given index does not match its checksum.
+	CorruptedChunk(usize),
+	/// Account with given address hash is inconsistent with its storage or code.
+	InvalidAccount(H256),
+	/// State root does not match the expected one.
+	RootMismatch {
+		/// Expected state root.
+		expected: H256,
+		/// State root of the export.
+		got: H256,
+	},
+	/// Database already contains blocks other than genesis, or another import is in progress.
+	NotFresh,
+	/// Export or import was cancelled.
+	Cancelled,
+}
+
+impl From<io::Error> for StateTransferError {
+	fn from(err: io::Error) -> Self {
+		StateTransferError::Io(err)
+	}
+}
+
+impl From<DecoderError> for StateTransferError {
+	fn from(err: DecoderError) -> Self {
+		StateTransferError::Decoder(err)
+	}
+}
+
+impl fmt::Display for StateTransferError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			StateTransferError::Io(ref err) => write!(f, "I/O error: {}", err),
+			StateTransferError::Decoder(ref err) => write!(f, "Malformed export: {:?}", err),
+			StateTransferError::UnknownBlock => write!(f, "Unknown block"),
+			StateTransferError::StateUnavailable => write!(f, "State of the block is not available"),
+			StateTransferError::UnknownAddress(ref hash) => write!(f, "Unable to recover address of account {}", hash),
+			StateTransferError::PreimagesUnavailable => write!(f, "Database does not keep addresses of accounts, resync is required to export state"),
+			StateTransferError::UnsupportedVersion(version) => write!(f, "Unsupported export version {}", version),
+			StateTransferError::CorruptedChunk(index) => write!(f, "Chunk {} is corrupted", index),
+			StateTransferError::InvalidAccount(ref hash) => write!(f, "Account {} is invalid", hash),
+			StateTransferError::RootMismatch { ref expected, ref got } => write!(f, "State root mismatch: expected {}, got {}", expected, got),
+			StateTransferError::NotFresh => write!(f, "Database is not fresh"),
+			StateTransferError::Cancelled => write!(f, "Cancelled"),
+		}
+	}
+}
+
+/// Describes the exported state.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateManifest {
+	/// RLP of the block the state belongs to.
+	pub block: Bytes,
+	/// Total difficulty of the block.
+	pub total_difficulty: U256,
+	/// State root of the block.
+	pub state_root: H256,
+}
+
+impl StateManifest {
+	fn to_rlp(&self) -> Bytes {
+		let mut s = RlpStream::new_list(4);
+		s.append(&FORMAT_VERSION);
+		s.append_raw(&self.block, 1);
+		s.append(&self.total_difficulty);
+		s.append(&self.state_root);
+		s.out()
+	}
+
+	fn from_rlp(bytes: &[u8]) -> Result<Self, StateTransferError> {
+		let rlp = UntrustedRlp::new(bytes);
+		let version: u32 = try!(rlp.val_at(0));
+		if version != FORMAT_VERSION {
+			return Err(StateTransferError::UnsupportedVersion(version));
+		}
+		let block = try!(rlp.at(1));
+		// Make sure the header is well-formed before it is used through views.
+		let _header: Header = try!(block.val_at(0));
+		Ok(StateManifest {
+			block: block.as_raw().to_vec(),
+			total_difficulty: try!(rlp.val_at(2)),
+			state_root: try!(rlp.val_at(3)),
+		})
+	}
+}
+
+/// Progress of state import, stored after every imported chunk so that an interrupted import can be resumed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportCheckpoint {
+	/// Hash of the imported block.
+	pub block_hash: H256,
+	/// Index of the next chunk to import.
+	pub next_chunk: usize,
+	/// Number of imported accounts.
+	pub accounts: u64,
+	/// Root of the state trie built so far.
+	pub state_root: H256,
+	/// Account which storage is partially imported and its storage root built so far.
+	pub pending_storage: Option<(Address, H256)>,
+}
+
+impl ImportCheckpoint {
+	/// Creates checkpoint of an import which has not started yet.
+	pub fn new(block_hash: H256) -> Self {
+		ImportCheckpoint {
+			block_hash: block_hash,
+			next_chunk: 0,
+			accounts: 0,
+			state_root: SHA3_NULL_RLP,
+			pending_storage: None,
+		}
+	}
+}
+
+impl Encodable for ImportCheckpoint {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(5);
+		s.append(&self.block_hash);
+		s.append(&(self.next_chunk as u64));
+		s.append(&self.accounts);
+		s.append(&self.state_root);
+		match self.pending_storage {
+			Some((ref address, ref root)) => s.begin_list(2).append(address).append(root),
+			None => s.begin_list(0),
+		};
+	}
+}
+
+impl Decodable for ImportCheckpoint {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let d = decoder.as_rlp();
+		let pending = try!(d.at(4));
+		let next_chunk: u64 = try!(d.val_at(1));
+		Ok(ImportCheckpoint {
+			block_hash: try!(d.val_at(0)),
+			next_chunk: next_chunk as usize,
+			accounts: try!(d.val_at(2)),
+			state_root: try!(d.val_at(3)),
+			pending_storage: match pending.item_count() {
+				0 => None,
+				_ => Some((try!(pending.val_at(0)), try!(pending.val_at(1)))),
+			},
+		})
+	}
+}
+
+fn write_record(writer: &mut Write, payload: &[u8]) -> Result<(), StateTransferError> {
+	let len = payload.len() as u32;
+	try!(writer.write_all(&[(len >> 24) as u8, (len >> 16) as u8, (len >> 8) as u8, len as u8]));
+	try!(writer.write_all(payload));
+	try!(writer.write_all(&payload.sha3()));
+	Ok(())
+}
+
+/// Reads next record. Returns `None` at the end of the export and `Err(CorruptedChunk)` if the checksum does not match.
+fn read_record(reader: &mut Read, index: usize) -> Result<Option<Bytes>, StateTransferError> {
+	let mut len = [0u8; 4];
+	try!(reader.read_exact(&mut len));
+	let len = len.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+	if len == 0 {
+		return Ok(None);
+	}
+	if len > MAX_RECORD_SIZE {
+		return Err(StateTransferError::CorruptedChunk(index));
+	}
+
+	let mut payload = vec![0u8; len];
+	try!(reader.read_exact(&mut payload));
+	let mut checksum = H256::new();
+	try!(reader.read_exact(&mut *checksum));
+	if payload.sha3() != checksum {
+		return Err(StateTransferError::CorruptedChunk(index));
+	}
+	Ok(Some(payload))
+}
+
+/// Returns address of an account with storage or code, given the hash of the address (key of the state trie).
+pub fn account_address(db: &HashDB, address_hash: &H256) -> Option<Address> {
+	match db.lookup(address_hash) {
+		Some(preimage) if preimage.len() == 20 => Some(Address::from_slice(preimage)),
+		_ => None,
+	}
+}
+
+/// Collects items into chunks and writes them out once they are full.
+struct ChunkWriter<'a> {
+	writer: &'a mut Write,
+	items: Vec<Bytes>,
+	size: usize,
+	limit: usize,
+}
+
+impl<'a> ChunkWriter<'a> {
+	fn push(&mut self, item: Bytes) -> Result<(), StateTransferError> {
+		self.size += item.len();
+		self.items.push(item);
+		if self.size >= self.limit {
+			try!(self.flush());
+		}
+		Ok(())
+	}
+
+	fn remaining(&self) -> usize {
+		self.limit.saturating_sub(self.size)
+	}
+
+	fn flush(&mut self) -> Result<(), StateTransferError> {
+		if self.items.is_empty() {
+			return Ok(());
+		}
+		let mut s = RlpStream::new_list(self.items.len());
+		for item in self.items.drain(..) {
+			s.append_raw(&item, 1);
+		}
+		self.size = 0;
+		write_record(self.writer, &s.out())
+	}
+}
+
+fn storage_item(address: &Address, entries: &[(Bytes, Bytes)]) -> Bytes {
+	let mut s = RlpStream::new_list(3);
+	s.append(&STORAGE_ITEM);
+	s.append(address);
+	s.begin_list(entries.len());
+	for &(ref key, ref value) in entries {
+		s.begin_list(2).append(key).append(value);
+	}
+	s.out()
+}
+
+/// Writes state with root `manifest.state_root` stored in `db`. Returns number of exported accounts.
+///
+/// `progress` is called with number of exported accounts after every full chunk; returning `false` cancels the export.
+pub fn export(db: &HashDB, manifest: &StateManifest, writer: &mut Write, chunk_size: usize, progress: &mut FnMut(u64) -> bool) -> Result<u64, StateTransferError> {
+	if !db.exists(&manifest.state_root) {
+		return Err(StateTransferError::StateUnavailable);
+	}
+	try!(write_record(writer, &manifest.to_rlp()));
+
+	let mut chunks = ChunkWriter {
+		writer: writer,
+		items: Vec::new(),
+		size: 0,
+		limit: chunk_size,
+	};
+	let mut exported = 0u64;
+	let trie = TrieDB::new(db, &manifest.state_root);
+	for (address_hash, account) in trie.iter() {
+		let address_hash = H256::from_slice(&address_hash);
+		let rlp = Rlp::new(account);
+		let storage_root: H256 = rlp.val_at(2);
+		let code_hash: H256 = rlp.val_at(3);
+
+		let mut code = Bytes::new();
+		let address = match storage_root != SHA3_NULL_RLP || code_hash != SHA3_EMPTY {
+			true => Some(try!(account_address(db, &address_hash).ok_or(StateTransferError::UnknownAddress(address_hash.clone())))),
+			false => None,
+		};
+		if let Some(ref address) = address {
+			let account_db = AccountDB::new(db, address);
+			let mut entries = Vec::new();
+			let mut entries_size = 0;
+			for (key, value) in TrieDB::new(&account_db, &storage_root).iter() {
+				entries_size += key.len() + value.len();
+				entries.push((key, value.to_vec()));
+				if entries_size >= chunks.remaining() {
+					try!(chunks.push(storage_item(address, &entries)));
+					entries.clear();
+					entries_size = 0;
+				}
+			}
+			if !entries.is_empty() {
+				try!(chunks.push(storage_item(address, &entries)));
+			}
+			if code_hash != SHA3_EMPTY {
+				code = try!(account_db.lookup(&code_hash).ok_or(StateTransferError::InvalidAccount(address_hash.clone()))).to_vec();
+			}
+		}
+
+		let mut s = RlpStream::new_list(5);
+		s.append(&ACCOUNT_ITEM);
+		s.append(&address_hash);
+		s.append_raw(account, 1);
+		s.append(&code);
+		match address {
+			Some(ref address) => s.append(address),
+			None => s.append_empty_data(),
+		};
+		let full = chunks.size + s.as_raw().len() >= chunks.limit;
+		try!(chunks.push(s.out()));
+		exported += 1;
+
+		if full && !progress(exported) {
+			trace!(target: "client", "State export cancelled after {} accounts.", exported);
+			return Err(StateTransferError::Cancelled);
+		}
+	}
+	try!(chunks.flush());
+	try!(chunks.writer.write_all(&[0u8; 4]));
+	progress(exported);
+	Ok(exported)
+}
+
+/// Reader of exported state.
+pub struct StateReader<'a> {
+	reader: &'a mut Read,
+	manifest: StateManifest,
+	next_chunk: usize,
+}
+
+impl<'a> StateReader<'a> {
+	/// Reads and validates the manifest.
+	pub fn new(reader: &'a mut Read) -> Result<Self, StateTransferError> {
+		let manifest = match try!(read_record(reader, 0)) {
+			Some(manifest) => try!(StateManifest::from_rlp(&manifest)),
+			None => return Err(StateTransferError::Decoder(DecoderError::RlpIsTooShort)),
+		};
+		Ok(StateReader {
+			reader: reader,
+			manifest: manifest,
+			next_chunk: 0,
+		})
+	}
+
+	/// Manifest of the export.
+	pub fn manifest(&self) -> &StateManifest {
+		&self.manifest
+	}
+
+	/// Reads next chunk and verifies its checksum. Returns `None` after the last chunk.
+	pub fn next_chunk(&mut self) -> Result<Option<Bytes>, StateTransferError> {
+		let chunk = try!(read_record(self.reader, self.next_chunk));
+		self.next_chunk += 1;
+		Ok(chunk)
+	}
+}
+
+fn open_trie<'db>(db: &'db mut HashDB, root: &'db mut H256) -> TrieDBMut<'db> {
+	match *root == SHA3_NULL_RLP {
+		true => TrieDBMut::new(db, root),
+		false => TrieDBMut::from_existing(db, root),
+	}
+}
+
+/// Imports a single chunk into `db` and updates `checkpoint`. Nothing is committed.
+pub fn import_chunk(db: &mut HashDB, chunk: &[u8], checkpoint: &mut ImportCheckpoint) -> Result<(), StateTransferError> {
+	let invalid = |hash: &H256| StateTransferError::InvalidAccount(hash.clone());
+	for item in UntrustedRlp::new(chunk).iter() {
+		let kind: u8 = try!(item.val_at(0));
+		match kind {
+			STORAGE_ITEM => {
+				let address: Address = try!(item.val_at(1));
+				let mut root = match checkpoint.pending_storage.take() {
+					Some((ref pending, ref root)) if *pending == address => root.clone(),
+					Some(_) => return Err(invalid(&address.sha3())),
+					None => SHA3_NULL_RLP,
+				};
+				{
+					let mut account_db = AccountDBMut::new(db, &address);
+					let mut trie = open_trie(&mut account_db, &mut root);
+					for entry in try!(item.at(2)).iter() {
+						let key: Bytes = try!(entry.val_at(0));
+						let value: Bytes = try!(entry.val_at(1));
+						trie.insert(&key, &value);
+					}
+				}
+				checkpoint.pending_storage = Some((address, root));
+			},
+			ACCOUNT_ITEM => {
+				let address_hash: H256 = try!(item.val_at(1));
+				let account = try!(item.at(2));
+				let storage_root: H256 = try!(account.val_at(2));
+				let code_hash: H256 = try!(account.val_at(3));
+				let code: Bytes = try!(item.val_at(3));
+				let address: Bytes = try!(item.val_at(4));
+
+				let built_root = match checkpoint.pending_storage.take() {
+					Some((pending, root)) => match address.len() == 20 && Address::from_slice(&address) == pending {
+						true => root,
+						false => return Err(invalid(&address_hash)),
+					},
+					None => SHA3_NULL_RLP,
+				};
+				if built_root != storage_root {
+					return Err(invalid(&address_hash));
+				}
+				match address.len() {
+					0 if code_hash == SHA3_EMPTY && storage_root == SHA3_NULL_RLP => {},
+					20 => {
+						let address = Address::from_slice(&address);
+						if address.sha3() != address_hash {
+							return Err(invalid(&address_hash));
+						}
+						if code_hash != SHA3_EMPTY && AccountDBMut::new(db, &address).insert(&code) != code_hash {
+							return Err(invalid(&address_hash));
+						}
+						db.insert(address.as_slice());
+					},
+					_ => return Err(invalid(&address_hash)),
+				}
+
+				open_trie(db, &mut checkpoint.state_root).insert(&address_hash, account.as_raw());
+				checkpoint.accounts += 1;
+			},
+			_ => return Err(StateTransferError::Decoder(DecoderError::Custom("Unknown item kind"))),
+		}
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use tests::helpers::*;
+	use state::State;
+	use super::*;
+
+	/// Creates state with `n` accounts. Every 4th account is a contract with storage.
+	fn prepare_state(n: u64) -> (GuardedTempResult<State>, H256) {
+		let mut state_result = get_temp_state();
+		let root = {
+			let state = state_result.reference_mut();
+			for i in 1..(n + 1) {
+				let address = Address::from(i);
+				if i % 4 == 0 {
+					state.new_contract(&address, U256::from(i));
+					state.init_code(&address, vec![0x60, 0x00, 0x60, i as u8]);
+					for key in 0..i {
+						state.set_storage(&address, H256::from(&U256::from(key)), H256::from(&U256::from(i)));
+					}
+				} else {
+					state.add_balance(&address, &U256::from(i));
+				}
+			}
+			state.commit();
+			state.root().clone()
+		};
+		(state_result, root)
+	}
+
+	fn manifest(state_root: &H256) -> StateManifest {
+		StateManifest {
+			block: get_good_dummy_block(),
+			total_difficulty: U256::from(1000),
+			state_root: state_root.clone(),
+		}
+	}
+
+	fn import_all(db: &mut HashDB, data: &[u8]) -> Result<ImportCheckpoint, StateTransferError> {
+		let mut reader = data;
+		let mut reader = try!(StateReader::new(&mut reader));
+		let mut checkpoint = ImportCheckpoint::new(H256::new());
+		while let Some(chunk) = try!(reader.next_chunk()) {
+			try!(import_chunk(db, &chunk, &mut checkpoint));
+			checkpoint.next_chunk += 1;
+		}
+		Ok(checkpoint)
+	}
+
+	#[test]
+	fn should_export_and_import_state() {
+		// given
+		let (mut state_result, root) = prepare_state(40);
+		let (_, db) = state_result.take().drop();
+		let mut data = Vec::new();
+		let exported = export(db.as_hashdb(), &manifest(&root), &mut data, 256, &mut |_| true).unwrap();
+
+		// when
+		let mut fresh = get_temp_journal_db();
+		let checkpoint = import_all(fresh.reference_mut().as_hashdb_mut(), &data).unwrap();
+
+		// then
+		assert_eq!(exported, 40);
+		assert_eq!(checkpoint.accounts, 40);
+		assert!(checkpoint.next_chunk > 1);
+		assert_eq!(checkpoint.state_root, root);
+		let mut exported_again = Vec::new();
+		export(fresh.reference().as_hashdb(), &manifest(&root), &mut exported_again, 256, &mut |_| true).unwrap();
+		assert_eq!(exported_again, data);
+		let state = State::from_existing(fresh.take(), root, U256::zero());
+		assert_eq!(state.balance(&Address::from(3)), U256::from(3));
+		assert_eq!(state.code(&Address::from(8)), Some(vec![0x60, 0x00, 0x60, 8]));
+		assert_eq!(state.storage_at(&Address::from(12), &H256::from(&U256::from(11))), H256::from(&U256::from(12)));
+	}
+
+	#[test]
+	fn should_resume_import_from_checkpoint() {
+		// given
+		let (mut state_result, root) = prepare_state(40);
+		let (_, db) = state_result.take().drop();
+		let mut data = Vec::new();
+		export(db.as_hashdb(), &manifest(&root), &mut data, 256, &mut |_| true).unwrap();
+		let mut fresh = get_temp_journal_db();
+		let mut checkpoint = ImportCheckpoint::new(H256::new());
+		{
+			let mut reader = &data[..];
+			let mut reader = StateReader::new(&mut reader).unwrap();
+			for _ in 0..3 {
+				let chunk = reader.next_chunk().unwrap().unwrap();
+				import_chunk(fresh.reference_mut().as_hashdb_mut(), &chunk, &mut checkpoint).unwrap();
+				checkpoint.next_chunk += 1;
+			}
+		}
+		let checkpoint: ImportCheckpoint = decode(&encode(&checkpoint).to_vec());
+
+		// when
+		let mut reader = &data[..];
+		let mut reader = StateReader::new(&mut reader).unwrap();
+		let mut checkpoint = checkpoint;
+		let mut index = 0;
+		while let Some(chunk) = reader.next_chunk().unwrap() {
+			if index >= checkpoint.next_chunk {
+				import_chunk(fresh.reference_mut().as_hashdb_mut(), &chunk, &mut checkpoint).unwrap();
+				checkpoint.next_chunk += 1;
+			}
+			index += 1;
+		}
+
+		// then
+		assert_eq!(checkpoint.accounts, 40);
+		assert_eq!(checkpoint.state_root, root);
+	}
+
+	#[test]
+	fn should_detect_corrupted_and_truncated_exports() {
+		// given
+		let (mut state_result, root) = prepare_state(8);
+		let (_, db) = state_result.take().drop();
+		let mut data = Vec::new();
+		export(db.as_hashdb(), &manifest(&root), &mut data, 256, &mut |_| true).unwrap();
+		let mut corrupted = data.clone();
+		let last = corrupted.len() - 40;
+		corrupted[last] ^= 0xff;
+		let truncated = &data[..data.len() - 4];
+
+		// when
+		let corrupted = import_all(get_temp_journal_db().reference_mut().as_hashdb_mut(), &corrupted);
+		let truncated = import_all(get_temp_journal_db().reference_mut().as_hashdb_mut(), truncated);
+
+		// then
+		match corrupted {
+			Err(StateTransferError::CorruptedChunk(_)) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+		match truncated {
+			Err(StateTransferError::Io(_)) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+	}
+}
@@ -0,0 +1,267 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! State trie statistics.
+//!
+//! Storage and code of an account are keyed by the account address in the database, while the state trie
+//! holds only hashes of addresses. Addresses are looked up by their hash (see `state_export::account_address`),
+//! storage and code of accounts which address is not known are not counted.
+
+use common::*;
+use account_db::AccountDB;
+use super::state_export::account_address;
+
+/// Number of accounts visited (or storage slots counted) between progress reports.
+const PROGRESS_INTERVAL: u64 = 10_000;
+/// Smallest fraction of the state that can be sampled.
+const MIN_SAMPLE: f64 = 0.0001;
+/// Number of accounts with the largest storage reported.
+pub const LARGEST_STORAGE_COUNT: usize = 10;
+
+/// Number of storage slots of a single account.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountStorage {
+	/// Address of the account.
+	pub address: Address,
+	/// Number of storage slots.
+	pub slots: u64,
+}
+
+/// Statistics of accounts stored in the state trie.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateStats {
+	/// Root of the walked state trie.
+	pub state_root: H256,
+	/// Number of accounts.
+	pub accounts: u64,
+	/// Number of accounts with code.
+	pub contracts: u64,
+	/// Number of accounts with non-empty storage.
+	pub accounts_with_storage: u64,
+	/// Number of storage slots of all accounts.
+	pub storage_slots: u64,
+	/// Number of contracts by size of their code.
+	/// Keyed by the upper bound of the size bucket, which is a power of two.
+	pub code_sizes: BTreeMap<u64, u64>,
+	/// Accounts with the most storage slots, largest first. Not extrapolated when sampling.
+	pub largest_storage: Vec<AccountStorage>,
+	/// Number of accounts with storage or code which address is not known.
+	pub unknown_addresses: u64,
+	/// Fraction of the key space that was walked (`1.0` for exact counts).
+	pub sample: f64,
+}
+
+impl StateStats {
+	/// Returns `true` if the whole trie was walked and counts are exact.
+	pub fn is_exact(&self) -> bool {
+		self.sample >= 1.0
+	}
+}
+
+/// Walks the state trie with given `root` and collects `StateStats`.
+///
+/// If `sample` is given only that fraction of the key space is walked and counts are extrapolated.
+/// Keys of the state trie are hashes, so accounts are evenly distributed across the key space.
+/// `progress` is called periodically with the number of visited accounts; returning `false` cancels the walk.
+pub fn collect(db: &HashDB, root: &H256, sample: Option<f64>, progress: &mut FnMut(u64) -> bool) -> Option<StateStats> {
+	let sample = sample.map_or(1.0, |s| s.max(MIN_SAMPLE).min(1.0));
+	// Keys are visited in order, so the walk stops at the first key above the threshold.
+	let threshold = match sample {
+		s if s >= 1.0 => None,
+		s => Some((s * u64::max_value() as f64) as u64),
+	};
+
+	let mut visited = 0u64;
+	let mut contracts = 0u64;
+	let mut with_storage = 0u64;
+	let mut storage_slots = 0u64;
+	let mut code_sizes = BTreeMap::new();
+	let mut largest_storage = Vec::new();
+	let mut unknown_addresses = 0u64;
+	let trie = TrieDB::new(db, root);
+	for (key, value) in trie.iter() {
+		if let Some(threshold) = threshold {
+			if key_prefix(&key) > threshold {
+				break;
+			}
+		}
+
+		let rlp = Rlp::new(value);
+		let storage_root: H256 = rlp.val_at(2);
+		let code_hash: H256 = rlp.val_at(3);
+		let has_code = code_hash != SHA3_EMPTY;
+		let has_storage = storage_root != SHA3_NULL_RLP;
+		visited += 1;
+		if has_code {
+			contracts += 1;
+		}
+		if has_storage {
+			with_storage += 1;
+		}
+
+		if has_code || has_storage {
+			match account_address(db, &H256::from_slice(&key)) {
+				Some(ref address) => {
+					let account_db = AccountDB::new(db, address);
+					if has_code {
+						let size = account_db.lookup(&code_hash).map_or(0, |code| code.len() as u64);
+						*code_sizes.entry(size.next_power_of_two()).or_insert(0) += 1;
+					}
+					if has_storage {
+						let slots = match count_slots(&account_db, &storage_root, visited, progress) {
+							Some(slots) => slots,
+							None => return None,
+						};
+						storage_slots += slots;
+						insert_largest(&mut largest_storage, AccountStorage { address: address.clone(), slots: slots });
+					}
+				},
+				None => unknown_addresses += 1,
+			}
+		}
+
+		if visited % PROGRESS_INTERVAL == 0 && !progress(visited) {
+			trace!(target: "client", "State stats collection cancelled after {} accounts.", visited);
+			return None;
+		}
+	}
+
+	let estimate = |count: u64| (count as f64 / sample).round() as u64;
+	Some(StateStats {
+		state_root: root.clone(),
+		accounts: estimate(visited),
+		contracts: estimate(contracts),
+		accounts_with_storage: estimate(with_storage),
+		storage_slots: estimate(storage_slots),
+		code_sizes: code_sizes.into_iter().map(|(size, count)| (size, estimate(count))).collect(),
+		largest_storage: largest_storage,
+		unknown_addresses: estimate(unknown_addresses),
+		sample: sample,
+	})
+}
+
+/// Counts entries of storage trie with given `root`. Returns `None` if cancelled by `progress`.
+fn count_slots(db: &HashDB, root: &H256, visited: u64, progress: &mut FnMut(u64) -> bool) -> Option<u64> {
+	let mut slots = 0u64;
+	for _ in TrieDB::new(db, root).iter() {
+		slots += 1;
+		if slots % PROGRESS_INTERVAL == 0 && !progress(visited) {
+			trace!(target: "client", "State stats collection cancelled while counting storage after {} accounts.", visited);
+			return None;
+		}
+	}
+	Some(slots)
+}
+
+/// Inserts `storage` into list sorted by number of slots, keeping at most `LARGEST_STORAGE_COUNT` entries.
+fn insert_largest(largest: &mut Vec<AccountStorage>, storage: AccountStorage) {
+	let position = largest.iter().position(|s| s.slots < storage.slots).unwrap_or(largest.len());
+	if position < LARGEST_STORAGE_COUNT {
+		largest.insert(position, storage);
+		largest.truncate(LARGEST_STORAGE_COUNT);
+	}
+}
+
+/// First 8 bytes of the key as a number.
+fn key_prefix(key: &[u8]) -> u64 {
+	key.iter().take(8).fold(0u64, |acc, b| (acc << 8) | *b as u64)
+}
+
+#[cfg(test)]
+mod tests {
+	use common::*;
+	use tests::helpers::*;
+	use super::*;
+
+	/// Creates state with `n` accounts. Every 4th account is a contract and every 8th has storage too.
+	/// Contract `i` has `(i / 8) % 16 + 1` storage slots.
+	fn prepare_state(n: u64) -> (GuardedTempResult<::state::State>, H256) {
+		let mut state_result = get_temp_state();
+		let root = {
+			let state = state_result.reference_mut();
+			for i in 1..(n + 1) {
+				let address = Address::from(i);
+				if i % 4 == 0 {
+					state.new_contract(&address, U256::from(i));
+					state.init_code(&address, vec![0x60, 0x00, 0x60, 0x00]);
+					if i % 8 == 0 {
+						for slot in 0..((i / 8) % 16 + 1) {
+							state.set_storage(&address, H256::from(&U256::from(slot + 1)), H256::from(&U256::from(i)));
+						}
+					}
+				} else {
+					state.add_balance(&address, &U256::from(i));
+				}
+			}
+			state.commit();
+			state.root().clone()
+		};
+		(state_result, root)
+	}
+
+	#[test]
+	fn should_count_accounts_exactly() {
+		// given
+		let (mut state_result, root) = prepare_state(100);
+		let (_, db) = state_result.take().drop();
+
+		// when
+		let stats = collect(db.as_hashdb(), &root, None, &mut |_| true).unwrap();
+
+		// then
+		assert!(stats.is_exact());
+		assert_eq!(stats.state_root, root);
+		assert_eq!(stats.accounts, 100);
+		assert_eq!(stats.contracts, 25);
+		assert_eq!(stats.accounts_with_storage, 12);
+		assert_eq!(stats.storage_slots, 90);
+		assert_eq!(stats.code_sizes, vec![(4, 25)].into_iter().collect());
+		assert_eq!(stats.largest_storage.len(), super::LARGEST_STORAGE_COUNT);
+		assert_eq!(stats.largest_storage[0], AccountStorage { address: Address::from(96), slots: 13 });
+		assert_eq!(stats.largest_storage[9], AccountStorage { address: Address::from(24), slots: 4 });
+		assert_eq!(stats.unknown_addresses, 0);
+	}
+
+	#[test]
+	fn should_estimate_counts_when_sampling() {
+		// given
+		let (mut state_result, root) = prepare_state(4000);
+		let (_, db) = state_result.take().drop();
+
+		// when
+		let stats = collect(db.as_hashdb(), &root, Some(0.5), &mut |_| true).unwrap();
+
+		// then
+		assert!(!stats.is_exact());
+		assert!(stats.accounts > 3600 && stats.accounts < 4400, "Estimate out of tolerance: {}", stats.accounts);
+		assert!(stats.contracts > 800 && stats.contracts < 1200, "Estimate out of tolerance: {}", stats.contracts);
+	}
+
+	#[test]
+	fn should_cancel_collection() {
+		// given
+		let (mut state_result, root) = prepare_state(super::PROGRESS_INTERVAL + 1);
+		let (_, db) = state_result.take().drop();
+		let mut reported = 0;
+
+		// when
+		let stats = collect(db.as_hashdb(), &root, None, &mut |visited| { reported = visited; false });
+
+		// then
+		assert_eq!(stats, None);
+		assert_eq!(reported, super::PROGRESS_INTERVAL);
+	}
+}
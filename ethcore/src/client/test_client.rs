@@ -17,15 +17,19 @@
 //! Test client.
 
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrder};
+use std::time::Instant;
 use util::*;
 use transaction::{Transaction, LocalizedTransaction, SignedTransaction, Action};
 use blockchain::TreeRoute;
-use client::{BlockChainClient, BlockChainInfo, BlockStatus, BlockID, TransactionID, UncleID, TraceId, TraceFilter, LastHashes};
+use views::BlockView;
+use client::{BlockChainClient, BlockChainInfo, EnvInfo, BlockStatus, BlockID, TransactionID, UncleID, TraceId, TraceFilter, LastHashes, StateStats, AncestryIter};
 use header::{Header as BlockHeader, BlockNumber};
 use filter::Filter;
 use log_entry::LocalizedLogEntry;
 use receipt::{Receipt, LocalizedReceipt};
+use uncle_info::UncleInfo;
 use extras::BlockReceipts;
+use gas_limit::GasLimitTarget;
 use error::{ImportResult};
 use evm::Factory as EvmFactory;
 
@@ -59,8 +63,14 @@ pub struct TestBlockChainClient {
 	pub execution_result: RwLock<Option<Executed>>,
 	/// Transaction receipts.
 	pub receipts: RwLock<HashMap<TransactionID, LocalizedReceipt>>,
+	/// Receipts of blocks.
+	pub block_receipts: RwLock<HashMap<H256, Bytes>>,
 	/// Block queue size.
 	pub queue_size: AtomicUsize,
+	/// Number of blocks prepared for sealing.
+	pub sealing_requests: AtomicUsize,
+	/// Chain id.
+	pub chain_id: RwLock<Option<u64>>,
 }
 
 #[derive(Clone)]
@@ -98,7 +108,10 @@ impl TestBlockChainClient {
 			code: RwLock::new(HashMap::new()),
 			execution_result: RwLock::new(None),
 			receipts: RwLock::new(HashMap::new()),
+			block_receipts: RwLock::new(HashMap::new()),
 			queue_size: AtomicUsize::new(0),
+			sealing_requests: AtomicUsize::new(0),
+			chain_id: RwLock::new(None),
 		};
 		client.add_blocks(1, EachBlockWith::Nothing); // add genesis block
 		client.genesis_hash = client.last_hash.read().unwrap().clone();
@@ -110,6 +123,18 @@ impl TestBlockChainClient {
 		self.receipts.write().unwrap().insert(id, receipt);
 	}
 
+	/// Set receipts of block with given `hash`.
+	pub fn set_block_receipts(&self, hash: H256, receipts: Vec<Receipt>) {
+		let mut rlp = RlpStream::new();
+		rlp.append(&BlockReceipts::new(receipts));
+		self.block_receipts.write().unwrap().insert(hash, rlp.out());
+	}
+
+	/// Set the chain id.
+	pub fn set_chain_id(&self, chain_id: Option<u64>) {
+		*self.chain_id.write().unwrap() = chain_id;
+	}
+
 	/// Set the execution result.
 	pub fn set_execution_result(&self, result: Executed) {
 		*self.execution_result.write().unwrap() = Some(result);
@@ -230,13 +255,35 @@ impl TestBlockChainClient {
 			BlockID::Latest => self.numbers.read().unwrap().get(&(self.numbers.read().unwrap().len() - 1)).cloned()
 		}
 	}
+
+	/// Returns hashes of block `hash` and all its ancestors (known blocks, including side chains).
+	fn ancestry(&self, hash: H256) -> Option<Vec<H256>> {
+		let blocks = self.blocks.read().unwrap();
+		if !blocks.contains_key(&hash) {
+			return None;
+		}
+		let mut ancestry = vec![];
+		let mut current = Some(hash);
+		while let Some(hash) = current {
+			current = blocks.get(&hash).map(|block| Rlp::new(block).val_at::<BlockHeader>(0).parent_hash);
+			ancestry.push(hash);
+		}
+		// parent of genesis is not a block
+		ancestry.pop();
+		Some(ancestry)
+	}
 }
 
 impl BlockChainClient for TestBlockChainClient {
-	fn call(&self, _t: &SignedTransaction) -> Result<Executed, ExecutionError> {
+	fn call_with_env(&self, _t: &SignedTransaction, _alter_env: &Fn(&mut EnvInfo), _static_call: bool, _deadline: Option<Instant>) -> Result<Executed, ExecutionError> {
 		Ok(self.execution_result.read().unwrap().clone().unwrap())
 	}
 
+	fn estimate_gas(&self, _t: &SignedTransaction, _deadline: Option<Instant>) -> Result<U256, ExecutionError> {
+		let result = self.execution_result.read().unwrap().clone().unwrap();
+		Ok(result.gas_used + result.refunded)
+	}
+
 	fn block_total_difficulty(&self, _id: BlockID) -> Option<U256> {
 		Some(U256::zero())
 	}
@@ -265,8 +312,34 @@ impl BlockChainClient for TestBlockChainClient {
 		unimplemented!();
 	}
 
-	fn uncle(&self, _id: UncleID) -> Option<BlockHeader> {
-		unimplemented!();
+	fn uncle(&self, id: UncleID) -> Option<BlockHeader> {
+		let index = id.1;
+		self.block(id.0).and_then(|block| BlockView::new(&block).uncle_at(index))
+	}
+
+	fn uncle_extra_info(&self, id: UncleID) -> Option<UncleInfo> {
+		let index = id.1;
+		self.block(id.0).and_then(|block| {
+			let view = BlockView::new(&block);
+			let nephew = view.header_view();
+			view.uncle_at(index).map(|uncle| UncleInfo {
+				hash: uncle.hash(),
+				number: uncle.number(),
+				author: uncle.author().clone(),
+				nephew_hash: nephew.sha3(),
+				nephew_number: nephew.number(),
+				index: index,
+				reward: U256::zero(),
+			})
+		})
+	}
+
+	fn uncle_position(&self, hash: &H256) -> Option<UncleID> {
+		let blocks = self.blocks.read().unwrap();
+		self.numbers.read().unwrap().values()
+			.filter_map(|block_hash| blocks.get(block_hash).map(|block| (block_hash.clone(), BlockView::new(block).uncle_hashes())))
+			.filter_map(|(block_hash, uncles)| uncles.iter().position(|uncle| uncle == hash).map(|index| UncleID(BlockID::Hash(block_hash), index)))
+			.next()
 	}
 
 	fn transaction_receipt(&self, id: TransactionID) -> Option<LocalizedReceipt> {
@@ -285,7 +358,12 @@ impl BlockChainClient for TestBlockChainClient {
 		unimplemented!();
 	}
 
-	fn prepare_sealing(&self, _author: Address, _gas_floor_target: U256, _extra_data: Bytes, _transactions: Vec<SignedTransaction>) -> (Option<ClosedBlock>, HashSet<H256>) {
+	fn chain_id(&self) -> Option<u64> {
+		*self.chain_id.read().unwrap()
+	}
+
+	fn prepare_sealing(&self, _author: Address, _gas_limit_target: GasLimitTarget, _extra_data: Bytes, _include_uncles: bool, _transactions: Vec<SignedTransaction>) -> (Option<ClosedBlock>, HashSet<H256>) {
+		self.sealing_requests.fetch_add(1, AtomicOrder::Relaxed);
 		(None, HashSet::new())
 	}
 
@@ -298,12 +376,17 @@ impl BlockChainClient for TestBlockChainClient {
 	}
 
 	fn block_body(&self, id: BlockID) -> Option<Bytes> {
-		self.block_hash(id).and_then(|hash| self.blocks.read().unwrap().get(&hash).map(|r| {
+		self.block_hash(id).and_then(|hash| self.block_bodies(&[hash]).pop().unwrap())
+	}
+
+	fn block_bodies(&self, hashes: &[H256]) -> Vec<Option<Bytes>> {
+		let blocks = self.blocks.read().unwrap();
+		hashes.iter().map(|hash| blocks.get(hash).map(|r| {
 			let mut stream = RlpStream::new_list(2);
 			stream.append_raw(Rlp::new(&r).at(1).as_raw(), 1);
 			stream.append_raw(Rlp::new(&r).at(2).as_raw(), 1);
 			stream.out()
-		}))
+		})).collect()
 	}
 
 	fn block(&self, id: BlockID) -> Option<Bytes> {
@@ -348,6 +431,22 @@ impl BlockChainClient for TestBlockChainClient {
 		})
 	}
 
+	fn ancestry_iter(&self, from: BlockID) -> Option<AncestryIter> {
+		self.block_hash(from)
+			.and_then(|hash| self.ancestry(hash))
+			.map(|ancestry| Box::new(ancestry.into_iter()) as AncestryIter)
+	}
+
+	fn common_ancestor(&self, a: H256, b: H256) -> Option<H256> {
+		match (self.ancestry(a), self.ancestry(b)) {
+			(Some(a), Some(b)) => {
+				let a = a.into_iter().collect::<HashSet<_>>();
+				b.into_iter().find(|hash| a.contains(hash))
+			},
+			_ => None,
+		}
+	}
+
 	fn find_uncles(&self, _hash: &H256) -> Option<Vec<H256>> {
 		None
 	}
@@ -364,6 +463,9 @@ impl BlockChainClient for TestBlockChainClient {
 	}
 
 	fn block_receipts(&self, hash: &H256) -> Option<Bytes> {
+		if let Some(receipts) = self.block_receipts.read().unwrap().get(hash) {
+			return Some(receipts.clone());
+		}
 		// starts with 'f' ?
 		if *hash > H256::from("f000000000000000000000000000000000000000000000000000000000000000") {
 			let receipt = BlockReceipts::new(vec![Receipt::new(
@@ -377,6 +479,10 @@ impl BlockChainClient for TestBlockChainClient {
 		None
 	}
 
+	fn block_receipts_list(&self, hashes: &[H256]) -> Vec<Option<Bytes>> {
+		hashes.iter().map(|hash| self.block_receipts(hash)).collect()
+	}
+
 	fn import_block(&self, b: Bytes) -> ImportResult {
 		let header = Rlp::new(&b).val_at::<BlockHeader>(0);
 		let h = header.hash();
@@ -465,4 +571,8 @@ impl BlockChainClient for TestBlockChainClient {
 	fn block_traces(&self, _trace: BlockID) -> Option<Vec<LocalizedTrace>> {
 		unimplemented!();
 	}
+
+	fn state_stats(&self, _id: BlockID, _sample: Option<f64>, _progress: &mut FnMut(u64) -> bool) -> Option<StateStats> {
+		None
+	}
 }
@@ -20,7 +20,7 @@ use std::ops::Deref;
 use std::hash::Hash;
 use std::sync::RwLock;
 use std::collections::HashMap;
-use util::{DBTransaction, Database};
+use util::{DBTransaction, Database, DatabaseSnapshot};
 use util::rlp::{encode, Encodable, decode, Decodable};
 
 #[derive(Clone, Copy)]
@@ -42,6 +42,9 @@ pub trait Writable {
 	/// Writes the value into the database.
 	fn write<T, R>(&self, key: &Key<T, Target = R>, value: &T) where T: Encodable, R: Deref<Target = [u8]>;
 
+	/// Deletes the value from the database.
+	fn delete<T, R>(&self, key: &Key<T, Target = R>) where R: Deref<Target = [u8]>;
+
 	/// Writes the value into the database and updates the cache.
 	fn write_with_cache<K, T, R>(&self, cache: &mut HashMap<K, T>, key: K, value: T, policy: CacheUpdatePolicy) where
 	K: Key<T, Target = R> + Hash + Eq,
@@ -78,6 +81,16 @@ pub trait Writable {
 			},
 		}
 	}
+
+	/// Deletes the values from the database and the cache.
+	fn delete_with_cache<K, T, R>(&self, cache: &mut HashMap<K, T>, keys: Vec<K>) where
+	K: Key<T, Target = R> + Hash + Eq,
+	R: Deref<Target = [u8]> {
+		for key in keys.into_iter() {
+			self.delete::<T, R>(&key);
+			cache.remove(&key);
+		}
+	}
 }
 
 /// Should be used to read values from database.
@@ -130,6 +143,37 @@ impl Writable for DBTransaction {
 			panic!("db put failed, key: {:?}, err: {:?}", &key.key() as &[u8], err);
 		}
 	}
+
+	fn delete<T, R>(&self, key: &Key<T, Target = R>) where R: Deref<Target = [u8]> {
+		let result = DBTransaction::delete(self, &key.key());
+		if let Err(err) = result {
+			panic!("db delete failed, key: {:?}, err: {:?}", &key.key() as &[u8], err);
+		}
+	}
+}
+
+impl<'a> Readable for DatabaseSnapshot<'a> {
+	fn read<T, R>(&self, key: &Key<T, Target = R>) -> Option<T> where T: Decodable, R: Deref<Target = [u8]> {
+		let result = self.get(&key.key());
+
+		match result {
+			Ok(option) => option.map(|v| decode(&v)),
+			Err(err) => {
+				panic!("db get failed, key: {:?}, err: {:?}", &key.key() as &[u8], err);
+			}
+		}
+	}
+
+	fn exists<T, R>(&self, key: &Key<T, Target = R>) -> bool where R: Deref<Target = [u8]> {
+		let result = self.get(&key.key());
+
+		match result {
+			Ok(v) => v.is_some(),
+			Err(err) => {
+				panic!("db get failed, key: {:?}, err: {:?}", &key.key() as &[u8], err);
+			}
+		}
+	}
 }
 
 impl Readable for Database {
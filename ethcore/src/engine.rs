@@ -21,6 +21,7 @@ use util::keys::store::AccountProvider;
 use block::ExecutedBlock;
 use spec::CommonParams;
 use evm::Schedule;
+use gas_limit::GasLimitTarget;
 
 /// A consensus mechanism for the chain. Generally either proof-of-work or proof-of-stake-based.
 /// Provides hooks into each of the major parts of block import.
@@ -59,6 +60,8 @@ pub trait Engine : Sync + Send {
 	fn on_new_block(&self, _block: &mut ExecutedBlock) {}
 	/// Block transformation functions, after the transactions.
 	fn on_close_block(&self, _block: &mut ExecutedBlock) {}
+	/// Reward paid to the author of `uncle` included in block number `nephew_number`.
+	fn uncle_reward(&self, _uncle: &Header, _nephew_number: BlockNumber) -> U256 { U256::zero() }
 
 	/// Attempt to seal the block internally.
 	///
@@ -96,7 +99,7 @@ pub trait Engine : Sync + Send {
 
 	/// Don't forget to call Super::populate_from_parent when subclassing & overriding.
 	// TODO: consider including State in the params.
-	fn populate_from_parent(&self, header: &mut Header, parent: &Header, _gas_floor_target: U256) {
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header, _gas_limit_target: GasLimitTarget) {
 		header.difficulty = parent.difficulty;
 		header.gas_limit = parent.gas_limit;
 		header.note_dirty();
@@ -110,8 +113,11 @@ pub trait Engine : Sync + Send {
 	/// Panics if `is_builtin(a)` is not true.
 	fn cost_of_builtin(&self, a: &Address, input: &[u8]) -> U256 { self.builtins().get(a).unwrap().cost(input.len()) }
 	/// Execution the builtin contract `a` on `input` and return `output`.
+	/// Returns an error if the builtin can't process the input.
 	/// Panics if `is_builtin(a)` is not true.
-	fn execute_builtin(&self, a: &Address, input: &[u8], output: &mut [u8]) { self.builtins().get(a).unwrap().execute(input, output); }
+	fn execute_builtin(&self, a: &Address, input: &[u8], output: &mut [u8]) -> Result<(), BuiltinError> {
+		self.builtins().get(a).unwrap().execute(input, output)
+	}
 
 	// TODO: sealing stuff - though might want to leave this for later.
 }
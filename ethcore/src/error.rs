@@ -58,6 +58,13 @@ pub enum TransactionError {
 	},
 	/// Transaction's gas limit (aka gas) is invalid.
 	InvalidGasLimit(OutOfBounds<U256>),
+	/// Transaction was signed for different chain (or without chain id after the transition).
+	InvalidChainId {
+		/// Id of the chain (`None` if chain has no id)
+		expected: Option<u64>,
+		/// Chain id of transaction (`None` if transaction is valid on any chain)
+		got: Option<u64>,
+	},
 }
 
 impl fmt::Display for TransactionError {
@@ -76,6 +83,8 @@ impl fmt::Display for TransactionError {
 			GasLimitExceeded { limit, got } =>
 				format!("Gas limit exceeded. Limit={}, Given={}", limit, got),
 			InvalidGasLimit(ref err) => format!("Invalid gas limit. {}", err),
+			InvalidChainId { expected, got } =>
+				format!("Invalid chain id. Expected={:?}, Given={:?}", expected, got),
 		};
 
 		f.write_fmt(format_args!("Transaction error ({})", msg))
@@ -224,6 +233,8 @@ pub enum Error {
 	PowHashInvalid,
 	/// The value of the nonce or mishash is invalid.
 	PowInvalid,
+	/// Work package of the given job is no longer known.
+	StaleJob,
 }
 
 impl fmt::Display for Error {
@@ -239,6 +250,7 @@ impl fmt::Display for Error {
 				f.write_fmt(format_args!("Unknown engine name ({})", name)),
 			Error::PowHashInvalid => f.write_str("Invalid or out of date PoW hash."),
 			Error::PowInvalid => f.write_str("Invalid nonce or mishash"),
+			Error::StaleJob => f.write_str("Work package is stale."),
 		}
 	}
 }
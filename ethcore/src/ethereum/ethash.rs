@@ -22,6 +22,7 @@ use block::*;
 use spec::CommonParams;
 use engine::*;
 use evm::Schedule;
+use gas_limit::GasLimitTarget;
 use ethjson;
 
 /// Ethash params.
@@ -76,6 +77,11 @@ impl Ethash {
 			pow: EthashManager::new(),
 		}
 	}
+
+	/// Checks if light cache needed to verify seal of block `block_number` is already prepared.
+	pub fn is_epoch_ready(&self, block_number: BlockNumber) -> bool {
+		self.pow.is_epoch_ready(block_number)
+	}
 }
 
 impl Engine for Ethash {
@@ -105,21 +111,19 @@ impl Engine for Ethash {
 		}
 	}
 
-	fn populate_from_parent(&self, header: &mut Header, parent: &Header, gas_floor_target: U256) {
+	fn populate_from_parent(&self, header: &mut Header, parent: &Header, gas_limit_target: GasLimitTarget) {
 		header.difficulty = self.calculate_difficuty(header, parent);
-		header.gas_limit = {
-			let gas_limit = parent.gas_limit;
-			let bound_divisor = self.ethash_params.gas_limit_bound_divisor;
-			if gas_limit < gas_floor_target {
-				min(gas_floor_target, gas_limit + gas_limit / bound_divisor - x!(1))
-			} else {
-				max(gas_floor_target, gas_limit - gas_limit / bound_divisor + x!(1) + (header.gas_used * x!(6) / x!(5)) / bound_divisor)
-			}
-		};
+		header.gas_limit = gas_limit_target.child_gas_limit(parent.gas_limit, self.ethash_params.gas_limit_bound_divisor, self.params().min_gas_limit);
 		header.note_dirty();
 //		info!("ethash: populate_from_parent #{}: difficulty={} and gas_limit={}", header.number, header.difficulty, header.gas_limit);
 	}
 
+	/// Prepare light cache of the next epoch in background when the epoch transition is close,
+	/// so verification of the first blocks of the new epoch doesn't stall.
+	fn on_new_block(&self, block: &mut ExecutedBlock) {
+		self.pow.note_block(block.fields().header.number());
+	}
+
 	/// Apply the block reward on finalisation of the block.
 	/// This assumes that all uncles are valid uncles (i.e. of at least one generation before the current).
 	fn on_close_block(&self, block: &mut ExecutedBlock) {
@@ -132,11 +136,15 @@ impl Engine for Ethash {
 		// Bestow uncle rewards
 		let current_number = fields.header.number();
 		for u in fields.uncles.iter() {
-			fields.state.add_balance(u.author(), &(reward * U256::from(8 + u.number() - current_number) / U256::from(8)));
+			fields.state.add_balance(u.author(), &self.uncle_reward(u, current_number));
 		}
 		fields.state.commit();
 	}
 
+	fn uncle_reward(&self, uncle: &Header, nephew_number: BlockNumber) -> U256 {
+		self.ethash_params.block_reward * U256::from(8 + uncle.number() - nephew_number) / U256::from(8)
+	}
+
 	fn verify_block_basic(&self, header: &Header, _block: Option<&[u8]>) -> result::Result<(), Error> {
 		// check the seal fields.
 		if header.seal.len() != self.seal_fields() {
@@ -296,6 +304,7 @@ mod tests {
 	use common::*;
 	use block::*;
 	use tests::helpers::*;
+	use gas_limit::GasLimitTarget;
 	use super::super::new_morden;
 
 	#[test]
@@ -308,11 +317,46 @@ mod tests {
 		spec.ensure_db_good(db.as_hashdb_mut());
 		let last_hashes = vec![genesis_header.hash()];
 		let vm_factory = Default::default();
-		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), x!(3141562), vec![]);
+		let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
 		let b = b.close();
 		assert_eq!(b.state().balance(&Address::zero()), U256::from_str("4563918244f40000").unwrap());
 	}
 
+	#[test]
+	fn populates_gas_limit_according_to_strategy() {
+		use gas_limit::GasLimitStrategy;
+
+		let spec = new_morden();
+		let engine = &spec.engine;
+		let child_gas_limit = |parent_gas_limit: u64, target: GasLimitTarget| {
+			let mut parent = spec.genesis_header();
+			parent.gas_limit = U256::from(parent_gas_limit);
+			parent.note_dirty();
+			let mut db_result = get_temp_journal_db();
+			let mut db = db_result.take();
+			spec.ensure_db_good(db.as_hashdb_mut());
+			let vm_factory = Default::default();
+			let b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &parent, vec![parent.hash()], Address::zero(), target, vec![]);
+			b.header().gas_limit().clone()
+		};
+		let range = GasLimitTarget::range(x!(2_000_000), x!(4_000_000));
+		let follow = GasLimitTarget { strategy: GasLimitStrategy::FollowParent, ..range };
+
+		// low parent
+		assert_eq!(child_gas_limit(1_024_000, GasLimitTarget::fixed(x!(3_000_000))), x!(1_024_999));
+		assert_eq!(child_gas_limit(1_024_000, range), x!(1_024_999));
+		assert_eq!(child_gas_limit(1_024_000, follow), x!(1_024_000));
+		// high parent
+		assert_eq!(child_gas_limit(8_192_000, GasLimitTarget::fixed(x!(3_000_000))), x!(8_184_001));
+		assert_eq!(child_gas_limit(8_192_000, range), x!(8_184_001));
+		assert_eq!(child_gas_limit(8_192_000, follow), x!(8_192_000));
+		// parent within range
+		assert_eq!(child_gas_limit(3_500_000, GasLimitTarget::fixed(x!(3_000_000))), x!(3_496_584));
+		assert_eq!(child_gas_limit(3_500_000, range), x!(3_500_000));
+		// target below minimal gas limit
+		assert_eq!(child_gas_limit(5_000, GasLimitTarget::fixed(x!(1_000))), x!(5_000));
+	}
+
 	#[test]
 	fn on_close_block_with_uncle() {
 		let spec = new_morden();
@@ -323,7 +367,7 @@ mod tests {
 		spec.ensure_db_good(db.as_hashdb_mut());
 		let last_hashes = vec![genesis_header.hash()];
 		let vm_factory = Default::default();
-		let mut b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), x!(3141562), vec![]);
+		let mut b = OpenBlock::new(engine.deref(), &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(x!(3141562)), vec![]);
 		let mut uncle = Header::new();
 		let uncle_author = address_from_hex("ef2d6d194084c2de36e0dabfce45d046b37d1106");
 		uncle.author = uncle_author.clone();
@@ -334,6 +378,17 @@ mod tests {
 		assert_eq!(b.state().balance(&uncle_author), U256::from_str("3cb71f51fc558000").unwrap());
 	}
 
+	#[test]
+	fn uncle_reward_depends_on_uncle_depth() {
+		let engine = new_morden().engine;
+		let block_reward = U256::from_str("4563918244f40000").unwrap();
+		let mut uncle = Header::new();
+		uncle.number = 4;
+
+		assert_eq!(engine.uncle_reward(&uncle, 5), block_reward * U256::from(7) / U256::from(8));
+		assert_eq!(engine.uncle_reward(&uncle, 10), block_reward * U256::from(2) / U256::from(8));
+	}
+
 	#[test]
 	fn has_valid_metadata() {
 		let engine = new_morden().engine;
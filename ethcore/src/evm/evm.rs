@@ -57,6 +57,15 @@ pub enum Error {
 		/// What was the stack limit
 		limit: usize
 	},
+	/// `Timeout` is returned when execution exceeds the deadline set by the caller
+	/// (see `Ext::deadline_exceeded`). Never happens during block import.
+	Timeout,
+	/// `MutationNotAllowed` is returned when static execution (see `Ext::is_static`)
+	/// tries to modify the state: write storage, create contract, suicide, log or transfer value.
+	MutationNotAllowed,
+	/// `OutputTooLarge` is returned when the returned data exceeds the output size limit
+	/// set by the caller. The state is reverted as with `OutOfGas`. Never happens during block import.
+	OutputTooLarge,
 	/// Returned on evm internal error. Should never be ignored during development.
 	/// Likely to cause consensus issues.
 	#[allow(dead_code)] // created only by jit
@@ -105,4 +105,19 @@ pub trait Ext {
 
 	/// Increments sstore refunds count by 1.
 	fn inc_sstore_clears(&mut self);
+
+	/// Prepares to trace execution of instruction at `pc`.
+	/// Returns `true` if `trace_executed` should be called once it's executed.
+	fn trace_prepare_execute(&mut self, _pc: usize, _instruction: u8, _gas_cost: &U256) -> bool { false }
+
+	/// Traces executed instruction: gas left after execution and values pushed to the stack.
+	fn trace_executed(&mut self, _gas_left: U256, _stack_push: &[U256]) {}
+
+	/// Returns `true` if execution should be aborted with `Error::Timeout`.
+	/// Checked by the interpreter periodically, so it should be cheap.
+	fn deadline_exceeded(&self) -> bool { false }
+
+	/// Returns `true` if execution must not modify the state.
+	/// State-modifying instructions abort execution with `Error::MutationNotAllowed` then.
+	fn is_static(&self) -> bool { false }
 }
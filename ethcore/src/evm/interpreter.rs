@@ -49,6 +49,9 @@ macro_rules! overflowing {
 	}}
 }
 
+/// Number of instructions executed between checks of the deadline.
+const DEADLINE_CHECK_INTERVAL: usize = 1024;
+
 type CodePosition = usize;
 type Gas = U256;
 type ProgramCounter = usize;
@@ -65,6 +68,8 @@ trait Stack<T> {
 	fn pop_back(&mut self) -> T;
 	/// Get (up to `instructions::MAX_NO_OF_TOPICS`) elements from top and remove them from Stack. Panics if stack is empty.
 	fn pop_n(&mut self, no_of_elems: usize) -> &[T];
+	/// Get last `no_of_elems` elements (the last one is on top)
+	fn peek_top(&self, no_of_elems: usize) -> &[T];
 	/// Add element on top of the Stack
 	fn push(&mut self, elem: T);
 	/// Get number of elements on Stack
@@ -121,6 +126,11 @@ impl<S : fmt::Display> Stack<S> for VecStack<S> {
 		&self.logs[0..no_of_elems]
 	}
 
+	fn peek_top(&self, no_from_top: usize) -> &[S] {
+		assert!(self.stack.len() >= no_from_top, "peek_top asked for more items than exist.");
+		&self.stack[self.stack.len() - no_from_top .. self.stack.len()]
+	}
+
 	fn push(&mut self, elem: S) {
 		evm_debug!({
 			println!("  PUSH: {}", elem)
@@ -290,13 +300,27 @@ impl evm::Evm for Interpreter {
 			position: 0,
 			code: &code
 		};
+		let mut executed = 0usize;
 
 		while reader.position < code.len() {
+			if executed % DEADLINE_CHECK_INTERVAL == 0 && ext.deadline_exceeded() {
+				return Err(evm::Error::Timeout);
+			}
+			executed += 1;
+
 			let instruction = code[reader.position];
 			reader.position += 1;
 
+			if ext.is_static() && modifies_state(instruction, &stack) {
+				return Err(evm::Error::MutationNotAllowed);
+			}
+
 			// Calculate gas cost
 			let (gas_cost, mem_size) = try!(self.get_gas_cost_mem(ext, instruction, &mut mem, &stack));
+
+			// Instructions which fail are traced without the result
+			let trace_executed = ext.trace_prepare_execute(reader.position - 1, instruction, &gas_cost);
+
 			try!(self.verify_gas(&current_gas, &gas_cost));
 			mem.expand(mem_size);
 			current_gas = current_gas - gas_cost; //TODO: use operator -=
@@ -340,6 +364,10 @@ impl evm::Evm for Interpreter {
 					reader.position = code.len();
 				}
 			}
+
+			if trace_executed {
+				ext.trace_executed(current_gas, stack.peek_top(instructions::get_info(instruction).ret));
+			}
 		}
 
 		Ok(current_gas)
@@ -1105,6 +1133,17 @@ impl Interpreter {
 	}
 }
 
+/// Checks if executing `instruction` with given `stack` would modify the state.
+/// Stack underflow is not reported here, it's detected when instruction's requirements are verified.
+fn modifies_state(instruction: Instruction, stack: &Stack<U256>) -> bool {
+	match instruction {
+		instructions::SSTORE | instructions::CREATE | instructions::SUICIDE => true,
+		instructions::LOG0...instructions::LOG4 => true,
+		instructions::CALL => stack.has(3) && !stack.peek(2).is_zero(),
+		_ => false,
+	}
+}
+
 fn get_and_reset_sign(value: U256) -> (U256, bool) {
 	let sign = (value >> 255).low_u64() == 1;
 	(set_sign(value, sign), sign)
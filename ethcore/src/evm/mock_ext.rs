@@ -0,0 +1,280 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Deterministic, scriptable externalities for VM tests.
+//!
+//! `MockExt` answers queries from scripted state (storage, balances, code, block hashes),
+//! replies to `CALL*`/`CREATE` with scripted responses and records every invocation.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use common::*;
+use evm::{self, Ext, Schedule, ContractCreateResult, MessageCallResult};
+
+/// Single invocation of externalities made by the VM.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Invocation {
+	/// `storage_at(key)`
+	StorageAt(H256),
+	/// `set_storage(key, value)`
+	SetStorage(H256, H256),
+	/// `exists(address)`
+	Exists(Address),
+	/// `balance(address)`
+	Balance(Address),
+	/// `blockhash(number)`
+	Blockhash(U256),
+	/// `create(gas, value, code)`
+	Create {
+		/// Gas given to the new contract
+		gas: U256,
+		/// Endowment
+		value: U256,
+		/// Init code
+		code: Bytes,
+	},
+	/// `call(gas, sender, receiver, value, data, code_address, _)`
+	Call {
+		/// Gas given to the callee (including stipend)
+		gas: U256,
+		/// Sender address
+		sender: Address,
+		/// Receiver address
+		receiver: Address,
+		/// Transferred value (`None` for `DELEGATECALL`)
+		value: Option<U256>,
+		/// Call data
+		data: Bytes,
+		/// Address of executed code
+		code_address: Address,
+	},
+	/// `extcode(address)`
+	Extcode(Address),
+	/// `log(topics, data)`
+	Log {
+		/// Log topics
+		topics: Vec<H256>,
+		/// Log data
+		data: Bytes,
+	},
+	/// `ret(_, data)`
+	Ret(Bytes),
+	/// `suicide(refund_address)`
+	Suicide(Address),
+	/// `inc_sstore_clears()`
+	IncSstoreClears,
+}
+
+impl Invocation {
+	/// Returns `true` if the invocation only reads state.
+	/// The VM is free to issue additional queries (e.g. while calculating gas), so traces
+	/// are usually compared without them.
+	pub fn is_query(&self) -> bool {
+		match *self {
+			Invocation::StorageAt(_) | Invocation::Exists(_) | Invocation::Balance(_) |
+			Invocation::Blockhash(_) | Invocation::Extcode(_) => true,
+			_ => false,
+		}
+	}
+}
+
+/// Scripted response to `CALL`, `CALLCODE` or `DELEGATECALL`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallResponse {
+	/// Call succeeds without using any gas and returns given output.
+	Success(Bytes),
+	/// Call fails.
+	Failed,
+}
+
+/// Scripted response to `CREATE`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CreateResponse {
+	/// Contract is created at given address without using any gas.
+	Created(Address),
+	/// Creation fails.
+	Failed,
+}
+
+/// Externalities with scripted responses and invocation recording.
+///
+/// Calls are answered with `call_responses` in order and default to `CallResponse::Success`
+/// with empty output. Creates are answered with `create_responses` and default to `CreateResponse::Failed`.
+pub struct MockExt {
+	/// Storage of the executed contract.
+	pub storage: HashMap<H256, H256>,
+	/// Account balances. Accounts with a balance or code exist.
+	pub balances: HashMap<Address, U256>,
+	/// Account codes.
+	pub codes: HashMap<Address, Bytes>,
+	/// Block hashes.
+	pub blockhashes: HashMap<U256, H256>,
+	/// Responses to subsequent calls.
+	pub call_responses: VecDeque<CallResponse>,
+	/// Responses to subsequent creates.
+	pub create_responses: VecDeque<CreateResponse>,
+	/// Reported execution depth.
+	pub depth: usize,
+	/// Environment info.
+	pub info: EnvInfo,
+	/// Schedule.
+	pub schedule: Schedule,
+	invocations: RefCell<Vec<Invocation>>,
+}
+
+impl MockExt {
+	/// Creates empty externalities with Frontier schedule.
+	pub fn new() -> Self {
+		Self::with_schedule(Schedule::new_frontier())
+	}
+
+	/// Creates empty externalities with Homestead schedule.
+	pub fn new_homestead() -> Self {
+		Self::with_schedule(Schedule::new_homestead())
+	}
+
+	/// Creates empty externalities with given schedule.
+	pub fn with_schedule(schedule: Schedule) -> Self {
+		MockExt {
+			storage: HashMap::new(),
+			balances: HashMap::new(),
+			codes: HashMap::new(),
+			blockhashes: HashMap::new(),
+			call_responses: VecDeque::new(),
+			create_responses: VecDeque::new(),
+			depth: 0,
+			info: EnvInfo::default(),
+			schedule: schedule,
+			invocations: RefCell::new(Vec::new()),
+		}
+	}
+
+	/// Returns all recorded invocations in order.
+	pub fn invocations(&self) -> Vec<Invocation> {
+		self.invocations.borrow().clone()
+	}
+
+	/// Returns recorded invocations which are not plain queries.
+	pub fn interactions(&self) -> Vec<Invocation> {
+		self.invocations.borrow().iter().filter(|i| !i.is_query()).cloned().collect()
+	}
+
+	fn record(&self, invocation: Invocation) {
+		self.invocations.borrow_mut().push(invocation);
+	}
+}
+
+impl Ext for MockExt {
+	fn storage_at(&self, key: &H256) -> H256 {
+		self.record(Invocation::StorageAt(key.clone()));
+		self.storage.get(key).cloned().unwrap_or_else(H256::new)
+	}
+
+	fn set_storage(&mut self, key: H256, value: H256) {
+		self.record(Invocation::SetStorage(key.clone(), value.clone()));
+		self.storage.insert(key, value);
+	}
+
+	fn exists(&self, address: &Address) -> bool {
+		self.record(Invocation::Exists(address.clone()));
+		self.balances.contains_key(address) || self.codes.contains_key(address)
+	}
+
+	fn balance(&self, address: &Address) -> U256 {
+		self.record(Invocation::Balance(address.clone()));
+		self.balances.get(address).cloned().unwrap_or_else(U256::zero)
+	}
+
+	fn blockhash(&self, number: &U256) -> H256 {
+		self.record(Invocation::Blockhash(number.clone()));
+		self.blockhashes.get(number).cloned().unwrap_or_else(H256::new)
+	}
+
+	fn create(&mut self, gas: &U256, value: &U256, code: &[u8]) -> ContractCreateResult {
+		self.record(Invocation::Create {
+			gas: *gas,
+			value: *value,
+			code: code.to_vec(),
+		});
+		match self.create_responses.pop_front() {
+			Some(CreateResponse::Created(address)) => ContractCreateResult::Created(address, *gas),
+			Some(CreateResponse::Failed) | None => ContractCreateResult::Failed,
+		}
+	}
+
+	fn call(&mut self,
+			gas: &U256,
+			sender_address: &Address,
+			receive_address: &Address,
+			value: Option<U256>,
+			data: &[u8],
+			code_address: &Address,
+			output: &mut [u8]) -> MessageCallResult {
+		self.record(Invocation::Call {
+			gas: *gas,
+			sender: sender_address.clone(),
+			receiver: receive_address.clone(),
+			value: value,
+			data: data.to_vec(),
+			code_address: code_address.clone(),
+		});
+		match self.call_responses.pop_front().unwrap_or_else(|| CallResponse::Success(vec![])) {
+			CallResponse::Success(result) => {
+				let len = cmp::min(result.len(), output.len());
+				output[..len].clone_from_slice(&result[..len]);
+				MessageCallResult::Success(*gas)
+			},
+			CallResponse::Failed => MessageCallResult::Failed,
+		}
+	}
+
+	fn extcode(&self, address: &Address) -> Bytes {
+		self.record(Invocation::Extcode(address.clone()));
+		self.codes.get(address).cloned().unwrap_or_else(Bytes::new)
+	}
+
+	fn log(&mut self, topics: Vec<H256>, data: &[u8]) {
+		self.record(Invocation::Log {
+			topics: topics,
+			data: data.to_vec(),
+		});
+	}
+
+	fn ret(&mut self, gas: &U256, data: &[u8]) -> result::Result<U256, evm::Error> {
+		self.record(Invocation::Ret(data.to_vec()));
+		Ok(*gas)
+	}
+
+	fn suicide(&mut self, refund_address: &Address) {
+		self.record(Invocation::Suicide(refund_address.clone()));
+	}
+
+	fn schedule(&self) -> &Schedule {
+		&self.schedule
+	}
+
+	fn env_info(&self) -> &EnvInfo {
+		&self.info
+	}
+
+	fn depth(&self) -> usize {
+		self.depth
+	}
+
+	fn inc_sstore_clears(&mut self) {
+		self.record(Invocation::IncSstoreClears);
+	}
+}
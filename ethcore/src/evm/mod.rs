@@ -28,8 +28,12 @@ mod jit;
 
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+pub mod mock_ext;
 
 pub use self::evm::{Evm, Error, Result};
 pub use self::ext::{Ext, ContractCreateResult, MessageCallResult};
 pub use self::factory::{Factory, VMType};
 pub use self::schedule::Schedule;
+#[cfg(test)]
+pub use self::mock_ext::{MockExt, Invocation, CallResponse, CreateResponse};
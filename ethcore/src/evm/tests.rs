@@ -16,7 +16,7 @@
 
 use common::*;
 use evm;
-use evm::{Ext, Schedule, Factory, VMType, ContractCreateResult, MessageCallResult};
+use evm::{Ext, Schedule, Factory, VMType, ContractCreateResult, MessageCallResult, MockExt, Invocation};
 use std::fmt::Debug;
 
 struct FakeLogEntry {
@@ -942,3 +942,41 @@ fn assert_store(ext: &FakeExt, pos: u64, val: &str) {
 	assert_eq!(ext.store.get(&H256::from(pos)).unwrap(), &H256::from_str(val).unwrap());
 }
 
+
+#[test]
+fn test_call_with_mock_ext() {
+	// 60 00 60 00 60 00 60 00 - out size, out offset, in size, in offset
+	// 60 10 - value
+	// 60 0b - address
+	// 62 011170 - gas
+	// f1 - call
+	let code = "60006000600060006010600b62011170f1".from_hex().unwrap();
+	let address = Address::from(0xa);
+	let receiver = Address::from(0xb);
+
+	let mut params = ActionParams::default();
+	params.address = address.clone();
+	params.gas = U256::from(200_000);
+	params.code = Some(code);
+	let mut ext = MockExt::new();
+	ext.balances.insert(address.clone(), U256::from(100));
+
+	{
+		let vm = Factory::new(VMType::Interpreter).create();
+		vm.exec(params, &mut ext).unwrap();
+	}
+
+	// interpreter checks existence of the receiver while calculating gas and balance of the caller before calling
+	assert_eq!(ext.invocations(), vec![
+		Invocation::Exists(receiver.clone()),
+		Invocation::Balance(address.clone()),
+		Invocation::Call {
+			gas: U256::from(70_000 + 2300),
+			sender: address.clone(),
+			receiver: receiver.clone(),
+			value: Some(U256::from(0x10)),
+			data: vec![],
+			code_address: receiver.clone(),
+		},
+	]);
+}
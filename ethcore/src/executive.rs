@@ -18,11 +18,12 @@
 use common::*;
 use state::*;
 use engine::*;
-use evm::{self, Ext, Factory};
+use evm::{self, Ext, Factory, VMType};
 use externalities::*;
 use substate::*;
-use trace::{Trace, Tracer, NoopTracer, ExecutiveTracer};
+use trace::{Trace, Tracer, NoopTracer, ExecutiveTracer, VMTrace, VMTracer, NoopVMTracer, ExecutiveVMTracer};
 use crossbeam;
+use std::time::Instant;
 
 pub use types::executed::{Executed, ExecutionResult};
 
@@ -31,6 +32,12 @@ pub use types::executed::{Executed, ExecutionResult};
 /// Maybe something like here: `https://github.com/ethereum/libethereum/blob/4db169b8504f2b87f7d5a481819cfb959fc65f6c/libethereum/ExtVM.cpp`
 const MAX_VM_DEPTH_FOR_THREAD: usize = 64;
 
+/// Maximal number of executions done while estimating gas.
+const MAX_ESTIMATE_GAS_ITERATIONS: usize = 64;
+
+/// Default limit of data returned by calls which are not part of consensus (e.g. `eth_call`).
+pub const MAX_CALL_OUTPUT_SIZE: usize = 16 * 1024 * 1024;
+
 /// Returns new address created from address and given nonce.
 pub fn contract_address(address: &Address, nonce: &U256) -> Address {
 	let mut stream = RlpStream::new_list(2);
@@ -43,8 +50,21 @@ pub fn contract_address(address: &Address, nonce: &U256) -> Address {
 pub struct TransactOptions {
 	/// Enable call tracing.
 	pub tracing: bool,
+	/// Enable tracing of executed VM instructions.
+	pub vm_tracing: bool,
 	/// Check transaction nonce before execution.
 	pub check_nonce: bool,
+	/// Charge the sender for gas and increment its nonce.
+	/// Read-only calls disable it, so senders without ether can execute them.
+	pub charge_fees: bool,
+	/// Calculate the difference in state caused by the transaction.
+	pub diffing: bool,
+	/// Abort execution with an exception if it tries to modify the state.
+	/// Such executions always run in the interpreter, since JIT doesn't check it.
+	pub static_call: bool,
+	/// Fail the call with an exception if it returns more than given number of bytes.
+	/// Must be `None` for consensus execution.
+	pub max_output_size: Option<usize>,
 }
 
 /// Transaction executor.
@@ -54,6 +74,9 @@ pub struct Executive<'a> {
 	engine: &'a Engine,
 	vm_factory: &'a Factory,
 	depth: usize,
+	deadline: Option<Instant>,
+	static_call: bool,
+	max_output_size: Option<usize>,
 }
 
 impl<'a> Executive<'a> {
@@ -65,6 +88,9 @@ impl<'a> Executive<'a> {
 			engine: engine,
 			vm_factory: vm_factory,
 			depth: 0,
+			deadline: None,
+			static_call: false,
+			max_output_size: None,
 		}
 	}
 
@@ -76,30 +102,116 @@ impl<'a> Executive<'a> {
 			engine: engine,
 			vm_factory: vm_factory,
 			depth: parent_depth + 1,
+			deadline: None,
+			static_call: false,
+			max_output_size: None,
 		}
 	}
 
+	/// Aborts execution with `ExecutionError::Timeout` once `deadline` passes.
+	/// Only the interpreter checks the deadline; JIT executions always run to completion.
+	pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+		self.deadline = deadline;
+		self
+	}
+
+	/// Aborts execution with `evm::Error::MutationNotAllowed` if it tries to modify the state.
+	/// Set by `TransactOptions::static_call` for transactions.
+	pub fn with_static_call(mut self, static_call: bool) -> Self {
+		self.static_call = static_call;
+		self
+	}
+
 	/// Creates `Externalities` from `Executive`.
-	pub fn as_externalities<'_, T>(&'_ mut self, origin_info: OriginInfo, substate: &'_ mut Substate, output: OutputPolicy<'_, '_>, tracer: &'_ mut T) -> Externalities<'_, T> where T: Tracer {
-		Externalities::new(self.state, self.info, self.engine, self.vm_factory, self.depth, origin_info, substate, output, tracer)
+	pub fn as_externalities<'_, T, V>(
+		&'_ mut self,
+		origin_info: OriginInfo,
+		substate: &'_ mut Substate,
+		output: OutputPolicy<'_, '_>,
+		tracer: &'_ mut T,
+		vm_tracer: &'_ mut V
+	) -> Externalities<'_, T, V> where T: Tracer, V: VMTracer {
+		Externalities::new(self.state, self.info, self.engine, self.vm_factory, self.depth, origin_info, substate, output, tracer, vm_tracer)
+			.with_deadline(self.deadline)
+			.with_static_call(self.static_call)
+			.with_max_output_size(self.max_output_size)
 	}
 
 	/// This function should be used to execute transaction.
 	pub fn transact(&'a mut self, t: &SignedTransaction, options: TransactOptions) -> Result<Executed, ExecutionError> {
-		let check = options.check_nonce;
-		match options.tracing {
-			true => self.transact_with_tracer(t, check, ExecutiveTracer::default()),
-			false => self.transact_with_tracer(t, check, NoopTracer),
+		match (options.tracing, options.vm_tracing) {
+			(true, true) => self.transact_with_tracer(t, &options, ExecutiveTracer::default(), ExecutiveVMTracer::toplevel()),
+			(true, false) => self.transact_with_tracer(t, &options, ExecutiveTracer::default(), NoopVMTracer),
+			(false, true) => self.transact_with_tracer(t, &options, NoopTracer, ExecutiveVMTracer::toplevel()),
+			(false, false) => self.transact_with_tracer(t, &options, NoopTracer, NoopVMTracer),
+		}
+	}
+
+	/// Finds minimal gas for which transaction executes without an exception (gas of `t` is ignored).
+	/// Gas is searched between intrinsic gas of the transaction and gas left in the block.
+	/// State is reverted after every execution.
+	pub fn estimate_gas(&mut self, t: &SignedTransaction, options: TransactOptions) -> Result<U256, ExecutionError> {
+		let sender = try!(t.sender().map_err(|e| {
+			let message = format!("Transaction malformed: {:?}", e);
+			ExecutionError::TransactionMalformed(message)
+		}));
+		let schedule = self.engine.schedule(self.info);
+		let mut lower = U256::from(t.gas_required(&schedule));
+		let mut upper = match self.info.gas_limit > self.info.gas_used {
+			true => self.info.gas_limit - self.info.gas_used,
+			false => U256::zero(),
+		};
+		if upper < lower {
+			return Err(ExecutionError::NotEnoughBaseGas { required: lower, got: upper });
 		}
+
+		if !try!(self.executes_with_gas(t, &sender, upper, &options)) {
+			return Err(ExecutionError::GasRequiredExceedsLimit { limit: upper });
+		}
+		if try!(self.executes_with_gas(t, &sender, lower, &options)) {
+			return Ok(lower);
+		}
+
+		// transaction fails with `lower` and succeeds with `upper` gas
+		let mut iterations = 0;
+		while upper - lower > U256::one() && iterations < MAX_ESTIMATE_GAS_ITERATIONS {
+			let mid = lower + (upper - lower) / U256::from(2);
+			match try!(self.executes_with_gas(t, &sender, mid, &options)) {
+				true => upper = mid,
+				false => lower = mid,
+			}
+			iterations += 1;
+		}
+		Ok(upper)
+	}
+
+	/// Executes `t` with given `gas` and reverts the state. Returns `true` if there was no exception.
+	fn executes_with_gas(&mut self, t: &SignedTransaction, sender: &Address, gas: U256, options: &TransactOptions) -> Result<bool, ExecutionError> {
+		let t = Transaction { gas: gas, ..t.deref().clone() }.fake_sign(sender.clone());
+		let options = TransactOptions { tracing: false, vm_tracing: false, check_nonce: options.check_nonce, charge_fees: options.charge_fees, diffing: false, static_call: options.static_call, max_output_size: options.max_output_size };
+		self.state.snapshot();
+		let result = Executive::new(self.state, self.info, self.engine, self.vm_factory)
+			.with_deadline(self.deadline)
+			.transact(&t, options);
+		self.state.revert_snapshot();
+		result.map(|executed| !executed.exception)
 	}
 
 	/// Execute transaction/call with tracing enabled
-	pub fn transact_with_tracer<T>(&'a mut self, t: &SignedTransaction, check_nonce: bool, mut tracer: T) -> Result<Executed, ExecutionError> where T: Tracer {
+	pub fn transact_with_tracer<T, V>(
+		&'a mut self,
+		t: &SignedTransaction,
+		options: &TransactOptions,
+		mut tracer: T,
+		mut vm_tracer: V
+	) -> Result<Executed, ExecutionError> where T: Tracer, V: VMTracer {
 		let sender = try!(t.sender().map_err(|e| {
 			let message = format!("Transaction malformed: {:?}", e);
 			ExecutionError::TransactionMalformed(message)
 		}));
 		let nonce = self.state.nonce(&sender);
+		self.static_call = options.static_call;
+		self.max_output_size = options.max_output_size;
 
 		let schedule = self.engine.schedule(self.info);
 		let base_gas_required = U256::from(t.gas_required(&schedule));
@@ -111,7 +223,7 @@ impl<'a> Executive<'a> {
 		let init_gas = t.gas - base_gas_required;
 
 		// validate transaction nonce
-		if check_nonce && t.nonce != nonce {
+		if options.check_nonce && t.nonce != nonce {
 			return Err(From::from(ExecutionError::InvalidNonce { expected: nonce, got: t.nonce }));
 		}
 
@@ -126,17 +238,29 @@ impl<'a> Executive<'a> {
 
 		// TODO: we might need bigints here, or at least check overflows.
 		let balance = self.state.balance(&sender);
-		let gas_cost = U512::from(t.gas) * U512::from(t.gas_price);
+		let gas_cost = match options.charge_fees {
+			true => U512::from(t.gas) * U512::from(t.gas_price),
+			false => U512::zero(),
+		};
 		let total_cost = U512::from(t.value) + gas_cost;
 
 		// avoid unaffordable transactions
 		if U512::from(balance) < total_cost {
-			return Err(From::from(ExecutionError::NotEnoughCash { required: total_cost, got: U512::from(balance) }));
+			if options.charge_fees {
+				return Err(From::from(ExecutionError::NotEnoughCash { required: total_cost, got: U512::from(balance) }));
+			}
+			// calls which aren't charged for are executed as if the sender could afford the value
+			self.state.add_balance(&sender, &(t.value - balance));
 		}
 
 		// NOTE: there can be no invalid transactions from this point.
-		self.state.inc_nonce(&sender);
-		self.state.sub_balance(&sender, &U256::from(gas_cost));
+		if options.diffing {
+			self.state.snapshot();
+		}
+		if options.charge_fees {
+			self.state.inc_nonce(&sender);
+			self.state.sub_balance(&sender, &U256::from(gas_cost));
+		}
 
 		let mut substate = Substate::new();
 
@@ -145,7 +269,7 @@ impl<'a> Executive<'a> {
 				let new_address = contract_address(&sender, &nonce);
 				let params = ActionParams {
 					code_address: new_address.clone(),
-					address: new_address,
+					address: new_address.clone(),
 					sender: sender.clone(),
 					origin: sender.clone(),
 					gas: init_gas,
@@ -154,7 +278,16 @@ impl<'a> Executive<'a> {
 					code: Some(t.data.clone()),
 					data: None,
 				};
-				(self.create(params, &mut substate, &mut tracer), vec![])
+				let res = self.create(params, &mut substate, &mut tracer, &mut vm_tracer);
+				// output of contract creation is the code stored under the new address
+				let output = match res {
+					Ok(_) => {
+						substate.contracts_created.push(new_address.clone());
+						self.state.code(&new_address).unwrap_or_else(Vec::new)
+					},
+					Err(_) => vec![],
+				};
+				(res, output)
 			},
 			Action::Call(ref address) => {
 				let params = ActionParams {
@@ -170,22 +303,40 @@ impl<'a> Executive<'a> {
 				};
 				// TODO: move output upstream
 				let mut out = vec![];
-				(self.call(params, &mut substate, BytesRef::Flexible(&mut out), &mut tracer), out)
+				(self.call(params, &mut substate, BytesRef::Flexible(&mut out), &mut tracer, &mut vm_tracer), out)
 			}
 		};
 
+		// nested calls aborted by the deadline look like failed calls to their callers
+		let gas_left = match self.deadline {
+			Some(deadline) if Instant::now() >= deadline => Err(evm::Error::Timeout),
+			_ => gas_left,
+		};
+
 		// finalize here!
-		Ok(try!(self.finalize(t, substate, gas_left, output, tracer.traces().pop())))
+		let executed = self.finalize(t, options.charge_fees, substate, gas_left, output, tracer.traces().pop(), vm_tracer.drain());
+		if options.diffing {
+			let diff = self.state.diff_since_snapshot();
+			self.state.clear_snapshot();
+			return executed.map(|executed| Executed { state_diff: Some(diff), ..executed });
+		}
+		executed
 	}
 
-	fn exec_vm<T>(&mut self, params: ActionParams, unconfirmed_substate: &mut Substate, output_policy: OutputPolicy, tracer: &mut T)
-		-> evm::Result where T: Tracer {
+	fn exec_vm<T, V>(
+		&mut self,
+		params: ActionParams,
+		unconfirmed_substate: &mut Substate,
+		output_policy: OutputPolicy,
+		tracer: &mut T,
+		vm_tracer: &mut V
+	) -> evm::Result where T: Tracer, V: VMTracer {
 		// Ordinary execution - keep VM in same thread
 		if (self.depth + 1) % MAX_VM_DEPTH_FOR_THREAD != 0 {
-			let vm_factory = self.vm_factory;
-			let mut ext = self.as_externalities(OriginInfo::from(&params), unconfirmed_substate, output_policy, tracer);
+			let vm = Self::create_vm(self.vm_factory, self.static_call);
+			let mut ext = self.as_externalities(OriginInfo::from(&params), unconfirmed_substate, output_policy, tracer, vm_tracer);
 			trace!(target: "executive", "ext.schedule.have_delegate_call: {}", ext.schedule().have_delegate_call);
-			return vm_factory.create().exec(params, &mut ext);
+			return vm.exec(params, &mut ext);
 		}
 
 		// Start in new thread to reset stack
@@ -193,20 +344,37 @@ impl<'a> Executive<'a> {
 		// https://github.com/aturon/crossbeam/issues/16
 		crossbeam::scope(|scope| {
 			let vm_factory = self.vm_factory;
-			let mut ext = self.as_externalities(OriginInfo::from(&params), unconfirmed_substate, output_policy, tracer);
+			let static_call = self.static_call;
+			let mut ext = self.as_externalities(OriginInfo::from(&params), unconfirmed_substate, output_policy, tracer, vm_tracer);
 
 			scope.spawn(move || {
-				vm_factory.create().exec(params, &mut ext)
+				Self::create_vm(vm_factory, static_call).exec(params, &mut ext)
 			})
 		}).join()
 	}
 
+	/// Creates VM for the execution. Static executions always use the interpreter,
+	/// because JIT doesn't check `Ext::is_static`.
+	fn create_vm(vm_factory: &Factory, static_call: bool) -> Box<evm::Evm> {
+		if static_call {
+			Factory::new(VMType::Interpreter).create()
+		} else {
+			vm_factory.create()
+		}
+	}
+
 	/// Calls contract function with given contract params.
 	/// NOTE. It does not finalize the transaction (doesn't do refunds, nor suicides).
 	/// Modifies the substate and the output.
 	/// Returns either gas_left or `evm::Error`.
-	pub fn call<T>(&mut self, params: ActionParams, substate: &mut Substate, mut output: BytesRef, tracer: &mut T)
-		-> evm::Result where T: Tracer {
+	pub fn call<T, V>(
+		&mut self,
+		params: ActionParams,
+		substate: &mut Substate,
+		mut output: BytesRef,
+		tracer: &mut T,
+		vm_tracer: &mut V
+	) -> evm::Result where T: Tracer, V: VMTracer {
 		// backup used in case of running out of gas
 		self.state.snapshot();
 
@@ -227,39 +395,42 @@ impl<'a> Executive<'a> {
 			let trace_info = tracer.prepare_trace_call(&params);
 
 			let cost = self.engine.cost_of_builtin(&params.code_address, data);
-			match cost <= params.gas {
-				true => {
-					self.engine.execute_builtin(&params.code_address, data, &mut output);
-					self.state.clear_snapshot();
-
-					// trace only top level calls to builtins to avoid DDoS attacks
-					if self.depth == 0 {
-						let mut trace_output = tracer.prepare_trace_output();
-						if let Some(mut out) = trace_output.as_mut() {
-							*out = output.to_owned();
+			if cost <= params.gas {
+				match self.engine.execute_builtin(&params.code_address, data, &mut output) {
+					Ok(()) => {
+						self.state.clear_snapshot();
+
+						// trace only top level calls to builtins to avoid DDoS attacks
+						if self.depth == 0 {
+							let mut trace_output = tracer.prepare_trace_output();
+							if let Some(mut out) = trace_output.as_mut() {
+								*out = output.to_owned();
+							}
+
+							tracer.trace_call(
+								trace_info,
+								cost,
+								trace_output,
+								self.depth,
+								vec![],
+								delegate_call
+							);
 						}
 
-						tracer.trace_call(
-							trace_info,
-							cost,
-							trace_output,
-							self.depth,
-							vec![],
-							delegate_call
-						);
-					}
-
-					Ok(params.gas - cost)
-				},
-				// just drain the whole gas
-				false => {
-					self.state.revert_snapshot();
-
-					tracer.trace_failed_call(trace_info, self.depth, vec![], delegate_call);
-
-					Err(evm::Error::OutOfGas)
+						return Ok(params.gas - cost);
+					},
+					Err(e) => {
+						trace!(target: "executive", "Builtin {} failed: {:?}", params.code_address, e);
+					},
 				}
 			}
+
+			// not enough gas or builtin failed; just drain the whole gas
+			self.state.revert_snapshot();
+
+			tracer.trace_failed_call(trace_info, self.depth, vec![], delegate_call);
+
+			Err(evm::Error::OutOfGas)
 		} else {
 			let trace_info = tracer.prepare_trace_call(&params);
 			let mut trace_output = tracer.prepare_trace_output();
@@ -270,10 +441,14 @@ impl<'a> Executive<'a> {
 				// part of substate that may be reverted
 				let mut unconfirmed_substate = Substate::new();
 
+				let mut subvmtracer = vm_tracer.prepare_subtrace(params.code.as_ref().expect("scope is conditional on params.code.is_some(); qed"));
+
 				let res = {
-					self.exec_vm(params, &mut unconfirmed_substate, OutputPolicy::Return(output, trace_output.as_mut()), &mut subtracer)
+					self.exec_vm(params, &mut unconfirmed_substate, OutputPolicy::Return(output, trace_output.as_mut()), &mut subtracer, &mut subvmtracer)
 				};
 
+				vm_tracer.done_subtrace(subvmtracer);
+
 				trace!(target: "executive", "res={:?}", res);
 
 				let traces = subtracer.traces();
@@ -307,8 +482,21 @@ impl<'a> Executive<'a> {
 	/// Creates contract with given contract params.
 	/// NOTE. It does not finalize the transaction (doesn't do refunds, nor suicides).
 	/// Modifies the substate.
-	pub fn create<T>(&mut self, params: ActionParams, substate: &mut Substate, tracer: &mut T) -> evm::Result where T:
-		Tracer {
+	pub fn create<T, V>(
+		&mut self,
+		params: ActionParams,
+		substate: &mut Substate,
+		tracer: &mut T,
+		vm_tracer: &mut V
+	) -> evm::Result where T: Tracer, V: VMTracer {
+		// never overwrite an existing contract or an account which was already used;
+		// the create fails and consumes all the gas given to it.
+		if self.is_create_collision(&params.address) {
+			let trace_info = tracer.prepare_trace_create(&params);
+			tracer.trace_failed_create(trace_info, self.depth, vec![]);
+			return Err(evm::Error::OutOfGas);
+		}
+
 		// backup used in case of running out of gas
 		self.state.snapshot();
 
@@ -330,10 +518,14 @@ impl<'a> Executive<'a> {
 		let gas = params.gas;
 		let created = params.address.clone();
 
+		let mut subvmtracer = vm_tracer.prepare_subtrace(params.code.as_ref().expect("two ways into create (Externalities::create and Executive::transact_with_tracer); both place `Some(...)` `code` in `params`; qed"));
+
 		let res = {
-			self.exec_vm(params, &mut unconfirmed_substate, OutputPolicy::InitContract(trace_output.as_mut()), &mut subtracer)
+			self.exec_vm(params, &mut unconfirmed_substate, OutputPolicy::InitContract(trace_output.as_mut()), &mut subtracer, &mut subvmtracer)
 		};
 
+		vm_tracer.done_subtrace(subvmtracer);
+
 		match res {
 			Ok(gas_left) => tracer.trace_create(
 				trace_info,
@@ -351,7 +543,17 @@ impl<'a> Executive<'a> {
 	}
 
 	/// Finalizes the transaction (does refunds and suicides).
-	fn finalize(&mut self, t: &SignedTransaction, substate: Substate, result: evm::Result, output: Bytes, trace: Option<Trace>) -> ExecutionResult {
+	#[cfg_attr(feature="dev", allow(too_many_arguments))]
+	fn finalize(
+		&mut self,
+		t: &SignedTransaction,
+		charge_fees: bool,
+		substate: Substate,
+		result: evm::Result,
+		output: Bytes,
+		trace: Option<Trace>,
+		vm_trace: Option<VMTrace>
+	) -> ExecutionResult {
 		let schedule = self.engine.schedule(self.info);
 
 		// refunds from SSTORE nonzero -> zero
@@ -372,10 +574,12 @@ impl<'a> Executive<'a> {
 		trace!("exec::finalize: t.gas={}, sstore_refunds={}, suicide_refunds={}, refunds_bound={}, gas_left_prerefund={}, refunded={}, gas_left={}, gas_used={}, refund_value={}, fees_value={}\n",
 			t.gas, sstore_refunds, suicide_refunds, refunds_bound, gas_left_prerefund, refunded, gas_left, gas_used, refund_value, fees_value);
 
-		trace!("exec::finalize: Refunding refund_value={}, sender={}\n", refund_value, t.sender().unwrap());
-		self.state.add_balance(&t.sender().unwrap(), &refund_value);
-		trace!("exec::finalize: Compensating author: fees_value={}, author={}\n", fees_value, &self.info.author);
-		self.state.add_balance(&self.info.author, &fees_value);
+		if charge_fees {
+			trace!("exec::finalize: Refunding refund_value={}, sender={}\n", refund_value, t.sender().unwrap());
+			self.state.add_balance(&t.sender().unwrap(), &refund_value);
+			trace!("exec::finalize: Compensating author: fees_value={}, author={}\n", fees_value, &self.info.author);
+			self.state.add_balance(&self.info.author, &fees_value);
+		}
 
 		// perform suicides
 		for address in &substate.suicides {
@@ -384,6 +588,7 @@ impl<'a> Executive<'a> {
 
 		match result {
 			Err(evm::Error::Internal) => Err(ExecutionError::Internal),
+			Err(evm::Error::Timeout) => Err(ExecutionError::Timeout),
 			Err(_) => {
 				Ok(Executed {
 					gas: t.gas,
@@ -394,6 +599,9 @@ impl<'a> Executive<'a> {
 					contracts_created: vec![],
 					output: output,
 					trace: trace,
+					vm_trace: vm_trace,
+					exception: true,
+					state_diff: None,
 				})
 			},
 			_ => {
@@ -406,18 +614,34 @@ impl<'a> Executive<'a> {
 					contracts_created: substate.contracts_created,
 					output: output,
 					trace: trace,
+					vm_trace: vm_trace,
+					exception: false,
+					state_diff: None,
 				})
 			},
 		}
 	}
 
+	/// Returns true if there is already a contract or an account with a used nonce at the address
+	/// and the engine rejects such collisions at the current block.
+	fn is_create_collision(&self, address: &Address) -> bool {
+		if !self.engine.params().rejects_create_collisions(self.info.number) {
+			return false;
+		}
+		self.state.nonce(address) > self.engine.account_start_nonce()
+			|| self.state.code(address).map_or(false, |code| !code.is_empty())
+	}
+
 	fn enact_result(&mut self, result: &evm::Result, substate: &mut Substate, un_substate: Substate) {
 		match *result {
 			Err(evm::Error::OutOfGas)
 				| Err(evm::Error::BadJumpDestination {..})
 				| Err(evm::Error::BadInstruction {.. })
 				| Err(evm::Error::StackUnderflow {..})
-				| Err(evm::Error::OutOfStack {..}) => {
+				| Err(evm::Error::OutOfStack {..})
+				| Err(evm::Error::Timeout)
+				| Err(evm::Error::MutationNotAllowed)
+				| Err(evm::Error::OutputTooLarge) => {
 					self.state.revert_snapshot();
 			},
 			Ok(_) | Err(evm::Error::Internal) => {
@@ -433,11 +657,79 @@ impl<'a> Executive<'a> {
 mod tests {
 	use super::*;
 	use common::*;
-	use evm::{Factory, VMType};
+	use std::time::{Instant, Duration};
+	use evm::{self, Factory, VMType, Schedule};
 	use substate::*;
 	use tests::helpers::*;
 	use trace::trace;
-	use trace::{Trace, Tracer, NoopTracer, ExecutiveTracer};
+	use trace::{Trace, Tracer, NoopTracer, ExecutiveTracer, NoopVMTracer, VMTrace, VMOperation, VMExecutedOperation};
+	use engine::Engine;
+	use spec::CommonParams;
+	use state::State;
+	use devtools::RandomTempPath;
+
+	/// Engine with single builtin at address 0x10 which always fails.
+	struct FailingBuiltinEngine {
+		engine: TestEngine,
+		builtins: BTreeMap<Address, Builtin>,
+	}
+
+	impl FailingBuiltinEngine {
+		fn new() -> Self {
+			let builtin = Builtin::new(
+				Box::new(|_: usize| U256::from(10)),
+				Box::new(|_: &[u8], _: &mut [u8]| -> Result<(), BuiltinError> { Err(BuiltinError("Invalid input")) })
+			);
+			let mut builtins = BTreeMap::new();
+			builtins.insert(Address::from(0x10), builtin);
+			FailingBuiltinEngine {
+				engine: TestEngine::new(0),
+				builtins: builtins,
+			}
+		}
+	}
+
+	impl Engine for FailingBuiltinEngine {
+		fn name(&self) -> &str {
+			"FailingBuiltinEngine"
+		}
+
+		fn params(&self) -> &CommonParams {
+			self.engine.params()
+		}
+
+		fn builtins(&self) -> &BTreeMap<Address, Builtin> {
+			&self.builtins
+		}
+
+		fn schedule(&self, env_info: &EnvInfo) -> Schedule {
+			self.engine.schedule(env_info)
+		}
+	}
+
+	fn builtin_params(address: u64, data: Vec<u8>) -> ActionParams {
+		let mut params = ActionParams::default();
+		params.address = Address::from(address);
+		params.code_address = Address::from(address);
+		params.gas = U256::from(100_000);
+		params.data = Some(data);
+		params
+	}
+
+	fn builtin_call_trace(address: u64, data: Vec<u8>, result: trace::Res) -> Vec<Trace> {
+		vec![Trace {
+			depth: 0,
+			action: trace::Action::Call(trace::Call {
+				from: Address::new(),
+				to: Address::from(address),
+				value: U256::zero(),
+				gas: U256::from(100_000),
+				input: data,
+			}),
+			result: result,
+			subs: vec![],
+		}]
+	}
 
 	#[test]
 	fn test_contract_address() {
@@ -466,7 +758,7 @@ mod tests {
 
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.create(params, &mut substate, &mut NoopTracer).unwrap()
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer).unwrap()
 		};
 
 		assert_eq!(gas_left, U256::from(79_975));
@@ -525,7 +817,7 @@ mod tests {
 
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.create(params, &mut substate, &mut NoopTracer).unwrap()
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer).unwrap()
 		};
 
 		assert_eq!(gas_left, U256::from(62_976));
@@ -582,7 +874,7 @@ mod tests {
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
 			let output = BytesRef::Fixed(&mut[0u8;0]);
-			ex.call(params, &mut substate, output, &mut tracer).unwrap()
+			ex.call(params, &mut substate, output, &mut tracer, &mut NoopVMTracer).unwrap()
 		};
 
 		let expected_trace = vec![ Trace {
@@ -614,10 +906,89 @@ mod tests {
 				subs: vec![]
 			}]
 		}];
-		assert_eq!(tracer.traces(), expected_trace);
+		let traces = tracer.traces();
+		assert_eq!(traces, expected_trace);
+		let flat = traces.into_iter().flat_map(Trace::flat).collect::<Vec<_>>();
+		assert_eq!(flat.len(), 2);
+		assert_eq!(flat[0].trace_address, vec![]);
+		assert_eq!(flat[0].subtraces, 1);
+		assert_eq!(flat[0].result, expected_trace[0].result);
+		assert_eq!(flat[1].trace_address, vec![0]);
+		assert_eq!(flat[1].subtraces, 0);
+		assert_eq!(flat[1].result, expected_trace[0].subs[0].result);
 		assert_eq!(gas_left, U256::from(44_752));
 	}
 
+	evm_test!{test_suicide_trace: test_suicide_trace_jit, test_suicide_trace_int}
+	fn test_suicide_trace(factory: Factory) {
+		// code:
+		//
+		// 73 ... 11 - push refund address (20 bytes)
+		// ff - suicide
+
+		let code = "730000000000000000000000000000000000000011ff".from_hex().unwrap();
+
+		let sender = Address::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap();
+		let address = contract_address(&sender, &U256::zero());
+		let refund_address = Address::from(0x11);
+		let mut params = ActionParams::default();
+		params.address = address.clone();
+		params.code_address = address.clone();
+		params.sender = sender.clone();
+		params.origin = sender.clone();
+		params.gas = U256::from(100_000);
+		params.code = Some(code.clone());
+		params.value = ActionValue::Transfer(U256::from(100));
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.add_balance(&sender, &U256::from(100));
+		state.add_balance(&address, &U256::from(50));
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(5);
+		let mut substate = Substate::new();
+		let mut tracer = ExecutiveTracer::default();
+
+		let gas_left = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			let output = BytesRef::Fixed(&mut[0u8;0]);
+			ex.call(params, &mut substate, output, &mut tracer, &mut NoopVMTracer).unwrap()
+		};
+
+		let expected_trace = vec![ Trace {
+			depth: 0,
+			action: trace::Action::Call(trace::Call {
+				from: sender.clone(),
+				to: address.clone(),
+				value: U256::from(100),
+				gas: U256::from(100_000),
+				input: vec![],
+			}),
+			result: trace::Res::Call(trace::CallResult {
+				gas_used: U256::from(3),
+				output: vec![],
+			}),
+			subs: vec![Trace {
+				depth: 1,
+				action: trace::Action::Suicide(trace::Suicide {
+					address: address.clone(),
+					refund_address: refund_address.clone(),
+					balance: U256::from(150),
+				}),
+				result: trace::Res::None,
+				subs: vec![]
+			}]
+		}];
+		let traces = tracer.traces();
+		assert_eq!(traces, expected_trace);
+		let flat = traces.into_iter().flat_map(Trace::flat).collect::<Vec<_>>();
+		assert_eq!(flat.len(), 2);
+		assert_eq!(flat[1].trace_address, vec![0]);
+		assert_eq!(flat[1].action, expected_trace[0].subs[0].action);
+		assert_eq!(gas_left, U256::from(99_997));
+		assert_eq!(state.balance(&refund_address), U256::from(150));
+		assert!(substate.suicides.contains(&address));
+	}
+
 	evm_test!{test_create_contract: test_create_contract_jit, test_create_contract_int}
 	fn test_create_contract(factory: Factory) {
 		// code:
@@ -653,7 +1024,7 @@ mod tests {
 
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.create(params.clone(), &mut substate, &mut tracer).unwrap()
+			ex.create(params.clone(), &mut substate, &mut tracer, &mut NoopVMTracer).unwrap()
 		};
 
 		let expected_trace = vec![Trace {
@@ -676,6 +1047,175 @@ mod tests {
 		assert_eq!(gas_left, U256::from(96_776));
 	}
 
+	evm_test!{test_create_contract_collision_with_code: test_create_contract_collision_with_code_jit, test_create_contract_collision_with_code_int}
+	fn test_create_contract_collision_with_code(factory: Factory) {
+		// given
+		let code = "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap();
+		let existing_code = "600160020100".from_hex().unwrap();
+
+		let sender = Address::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap();
+		let address = contract_address(&sender, &U256::zero());
+		let mut params = ActionParams::default();
+		params.address = address.clone();
+		params.sender = sender.clone();
+		params.origin = sender.clone();
+		params.gas = U256::from(100_000);
+		params.code = Some(code.clone());
+		params.value = ActionValue::Transfer(x!(100));
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.add_balance(&sender, &U256::from(100));
+		state.init_code(&address, existing_code.clone());
+		state.add_balance(&address, &U256::from(5));
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(5).with_params(collision_params(U256::zero()));
+		let mut substate = Substate::new();
+		let mut tracer = ExecutiveTracer::default();
+
+		// when
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.create(params.clone(), &mut substate, &mut tracer, &mut NoopVMTracer)
+		};
+
+		// then
+		match res {
+			Err(evm::Error::OutOfGas) => (),
+			_ => assert!(false, "Expected colliding create to fail. {:?}", res)
+		}
+		let expected_trace = vec![Trace {
+			depth: 0,
+			action: trace::Action::Create(trace::Create {
+				from: params.sender,
+				value: x!(100),
+				gas: params.gas,
+				init: code,
+			}),
+			result: trace::Res::FailedCreate,
+			subs: vec![]
+		}];
+		assert_eq!(tracer.traces(), expected_trace);
+		assert_eq!(state.balance(&sender), U256::from(100));
+		assert_eq!(state.balance(&address), U256::from(5));
+		assert_eq!(state.code(&address), Some(existing_code));
+	}
+
+	evm_test!{test_create_contract_collision_with_nonce: test_create_contract_collision_with_nonce_jit, test_create_contract_collision_with_nonce_int}
+	fn test_create_contract_collision_with_nonce(factory: Factory) {
+		// given
+		let code = "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap();
+
+		let sender = Address::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap();
+		let address = contract_address(&sender, &U256::zero());
+		let mut params = ActionParams::default();
+		params.address = address.clone();
+		params.sender = sender.clone();
+		params.origin = sender.clone();
+		params.gas = U256::from(100_000);
+		params.code = Some(code);
+		params.value = ActionValue::Transfer(x!(100));
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.add_balance(&sender, &U256::from(100));
+		state.inc_nonce(&address);
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(5).with_params(collision_params(U256::zero()));
+		let mut substate = Substate::new();
+
+		// when
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer)
+		};
+
+		// then
+		match res {
+			Err(evm::Error::OutOfGas) => (),
+			_ => assert!(false, "Expected colliding create to fail. {:?}", res)
+		}
+		assert_eq!(state.balance(&sender), U256::from(100));
+		assert_eq!(state.balance(&address), U256::zero());
+		assert_eq!(state.nonce(&address), U256::one());
+		assert_eq!(state.code(&address), Some(vec![]));
+	}
+
+	fn collision_params(account_start_nonce: U256) -> CommonParams {
+		let mut params = TestEngine::new(0).params().clone();
+		params.account_start_nonce = account_start_nonce;
+		params.create_collision_transition = 0;
+		params
+	}
+
+	evm_test!{test_create_contract_at_account_with_start_nonce: test_create_contract_at_account_with_start_nonce_jit, test_create_contract_at_account_with_start_nonce_int}
+	fn test_create_contract_at_account_with_start_nonce(factory: Factory) {
+		// given
+		let code = "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap();
+		let start_nonce = U256::from(0x100000);
+
+		let sender = Address::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap();
+		let address = contract_address(&sender, &start_nonce);
+		let mut params = ActionParams::default();
+		params.address = address.clone();
+		params.sender = sender.clone();
+		params.origin = sender.clone();
+		params.gas = U256::from(100_000);
+		params.code = Some(code);
+		params.value = ActionValue::Transfer(x!(100));
+		let temp = RandomTempPath::new();
+		let mut state = State::new(get_temp_journal_db_in(temp.as_path()), start_nonce);
+		state.add_balance(&sender, &U256::from(100));
+		state.add_balance(&address, &U256::from(5));
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(5).with_params(collision_params(start_nonce));
+		let mut substate = Substate::new();
+
+		// when
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer)
+		};
+
+		// then
+		assert!(res.is_ok(), "Expected create at an unused account to succeed. {:?}", res);
+		assert_eq!(state.balance(&address), U256::from(105));
+	}
+
+	evm_test!{test_create_contract_collision_before_transition: test_create_contract_collision_before_transition_jit, test_create_contract_collision_before_transition_int}
+	fn test_create_contract_collision_before_transition(factory: Factory) {
+		// given
+		let code = "601080600c6000396000f3006000355415600957005b60203560003555".from_hex().unwrap();
+
+		let sender = Address::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap();
+		let address = contract_address(&sender, &U256::zero());
+		let mut params = ActionParams::default();
+		params.address = address.clone();
+		params.sender = sender.clone();
+		params.origin = sender.clone();
+		params.gas = U256::from(100_000);
+		params.code = Some(code);
+		params.value = ActionValue::Transfer(x!(100));
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.add_balance(&sender, &U256::from(100));
+		state.inc_nonce(&address);
+		let mut info = EnvInfo::default();
+		info.number = 9;
+		let mut engine_params = collision_params(U256::zero());
+		engine_params.create_collision_transition = 10;
+		let engine = TestEngine::new(5).with_params(engine_params);
+		let mut substate = Substate::new();
+
+		// when
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer)
+		};
+
+		// then
+		assert!(res.is_ok(), "Expected create before the transition to succeed. {:?}", res);
+		assert_eq!(state.balance(&address), U256::from(100));
+	}
+
 	evm_test!{test_create_contract_value_too_high: test_create_contract_value_too_high_jit, test_create_contract_value_too_high_int}
 	fn test_create_contract_value_too_high(factory: Factory) {
 		// code:
@@ -722,7 +1262,7 @@ mod tests {
 
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.create(params, &mut substate, &mut NoopTracer).unwrap()
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer).unwrap()
 		};
 
 		assert_eq!(gas_left, U256::from(62_976));
@@ -774,7 +1314,7 @@ mod tests {
 
 		{
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.create(params, &mut substate, &mut NoopTracer).unwrap();
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer).unwrap();
 		}
 
 		assert_eq!(substate.contracts_created.len(), 1);
@@ -835,7 +1375,7 @@ mod tests {
 
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.call(params, &mut substate, BytesRef::Fixed(&mut []), &mut NoopTracer).unwrap()
+			ex.call(params, &mut substate, BytesRef::Fixed(&mut []), &mut NoopTracer, &mut NoopVMTracer).unwrap()
 		};
 
 		assert_eq!(gas_left, U256::from(73_237));
@@ -880,7 +1420,7 @@ mod tests {
 
 		let gas_left = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.call(params, &mut substate, BytesRef::Fixed(&mut []), &mut NoopTracer).unwrap()
+			ex.call(params, &mut substate, BytesRef::Fixed(&mut []), &mut NoopTracer, &mut NoopVMTracer).unwrap()
 		};
 
 		assert_eq!(gas_left, U256::from(59_870));
@@ -913,7 +1453,7 @@ mod tests {
 
 		let executed = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			let opts = TransactOptions { check_nonce: true, tracing: false };
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
 			ex.transact(&t, opts).unwrap()
 		};
 
@@ -922,13 +1462,520 @@ mod tests {
 		assert_eq!(executed.refunded, U256::from(58_699));
 		assert_eq!(executed.cumulative_gas_used, U256::from(41_301));
 		assert_eq!(executed.logs.len(), 0);
-		assert_eq!(executed.contracts_created.len(), 0);
+		assert_eq!(executed.contracts_created, vec![contract.clone()]);
 		assert_eq!(state.balance(&sender), U256::from(1));
 		assert_eq!(state.balance(&contract), U256::from(17));
 		assert_eq!(state.nonce(&sender), U256::from(1));
 		assert_eq!(state.storage_at(&contract, &H256::new()), H256::from(&U256::from(1)));
 	}
 
+	evm_test!{test_transact_create_returns_code: test_transact_create_returns_code_jit, test_transact_create_returns_code_int}
+	fn test_transact_create_returns_code(factory: Factory) {
+		// 60 02 - push 2 (size of the runtime code)
+		// 60 0c - push 12 (offset of the runtime code)
+		// 60 00 - push 0
+		// 39 - codecopy
+		// 60 02 - push 2
+		// 60 00 - push 0
+		// f3 - return
+		// 60 01 - runtime code
+		let keypair = KeyPair::create().unwrap();
+		let t = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "6002600c60003960026000f36001".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero()
+		}.sign(&keypair.secret());
+		let sender = t.sender().unwrap();
+		let contract = contract_address(&sender, &U256::zero());
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		let engine = TestEngine::new(0);
+
+		let executed = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
+			ex.transact(&t, opts).unwrap()
+		};
+
+		assert_eq!(executed.output, "6001".from_hex().unwrap());
+		assert_eq!(executed.contracts_created, vec![contract.clone()]);
+		assert_eq!(state.code(&contract), Some("6001".from_hex().unwrap()));
+	}
+
+	evm_test!{test_estimate_gas_with_refund: test_estimate_gas_with_refund_jit, test_estimate_gas_with_refund_int}
+	fn test_estimate_gas_with_refund(factory: Factory) {
+		// 60 00 - push 0
+		// 60 00 - push 0
+		// 55 - sstore (clears the slot, so part of the gas is refunded)
+		let address = Address::from(0x1234);
+		let keypair = KeyPair::create().unwrap();
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(21_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero()
+		}.sign(&keypair.secret());
+		let sender = t.sender().unwrap();
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, "6000600055".from_hex().unwrap());
+		state.set_storage(&address, H256::new(), H256::from(&U256::one()));
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		let engine = TestEngine::new(0);
+
+		// when
+		let estimate = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.estimate_gas(&t, TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None }).unwrap()
+		};
+
+		// then
+		assert_eq!(estimate, U256::from(26_006));
+		assert_eq!(state.storage_at(&address, &H256::new()), H256::from(&U256::one()));
+		assert_eq!(state.nonce(&sender), U256::zero());
+
+		// when
+		let t = Transaction { gas: estimate, ..t.deref().clone() }.sign(&keypair.secret());
+		let executed = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None }).unwrap()
+		};
+
+		// then
+		assert!(!executed.exception);
+		assert_eq!(executed.gas_used, U256::from(13_003));
+		assert_eq!(executed.refunded, U256::from(13_003));
+		assert_eq!(state.storage_at(&address, &H256::new()), H256::new());
+	}
+
+	evm_test!{test_estimate_gas_of_always_failing_transaction: test_estimate_gas_of_always_failing_transaction_jit, test_estimate_gas_of_always_failing_transaction_int}
+	fn test_estimate_gas_of_always_failing_transaction(factory: Factory) {
+		// 5b - jumpdest
+		// 60 00 - push 0
+		// 56 - jump (loops forever)
+		let address = Address::from(0x1234);
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(21_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, "5b600056".from_hex().unwrap());
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		let engine = TestEngine::new(0);
+
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.estimate_gas(&t, TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None })
+		};
+
+		assert_eq!(res, Err(ExecutionError::GasRequiredExceedsLimit { limit: U256::from(100_000) }));
+	}
+
+	#[test]
+	fn test_call_times_out_after_deadline() {
+		// 5b - jumpdest
+		// 60 00 - push 0
+		// 56 - jump (loops forever)
+		let factory = Factory::new(VMType::Interpreter);
+		let address = Address::from(0x1234);
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(u64::max_value()),
+			gas_price: U256::zero(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, "5b600056".from_hex().unwrap());
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::max_value();
+		let engine = TestEngine::new(0);
+
+		// when
+		let started = Instant::now();
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory).with_deadline(Some(started + Duration::from_millis(100)));
+			ex.transact(&t, TransactOptions { check_nonce: false, tracing: false, vm_tracing: false, charge_fees: false, diffing: false, static_call: false, max_output_size: None })
+		};
+
+		// then
+		assert_eq!(res.map(|executed| executed.exception), Err(ExecutionError::Timeout));
+		assert!(started.elapsed() < Duration::from_secs(5));
+	}
+
+	#[test]
+	fn test_call_fails_if_output_exceeds_limit() {
+		// 62 10 00 00 - push 1MB
+		// 60 00 - push 0
+		// f3 - return
+		let factory = Factory::new(VMType::Interpreter);
+		let address = Address::from(0x1234);
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(10_000_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, "621000006000f3".from_hex().unwrap());
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(10_000_000);
+		let engine = TestEngine::new(0);
+		let options = |max_output_size| TransactOptions { check_nonce: false, tracing: false, vm_tracing: false, charge_fees: false, diffing: false, static_call: false, max_output_size: max_output_size };
+
+		// when
+		let limited = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, options(Some(64 * 1024))).unwrap()
+		};
+		let unlimited = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, options(None)).unwrap()
+		};
+
+		// then
+		assert!(limited.exception);
+		assert!(limited.output.is_empty());
+		assert_eq!(limited.gas_used, U256::from(10_000_000));
+		assert!(!unlimited.exception);
+		assert_eq!(unlimited.output.len(), 1024 * 1024);
+	}
+
+	evm_test!{test_static_call_does_not_modify_state: test_static_call_does_not_modify_state_jit, test_static_call_does_not_modify_state_int}
+	fn test_static_call_does_not_modify_state(factory: Factory) {
+		// 60 01 - push 1
+		// 60 00 - push 0
+		// 55 - sstore
+		let sender = Address::from(0x10);
+		let address = Address::from(0x1234);
+		let mut params = ActionParams::default();
+		params.address = address.clone();
+		params.code_address = address.clone();
+		params.sender = sender.clone();
+		params.origin = sender.clone();
+		params.gas = U256::from(100_000);
+		params.code = Some("600160005500".from_hex().unwrap());
+		params.value = ActionValue::Transfer(U256::zero());
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, "600160005500".from_hex().unwrap());
+		state.commit();
+		let root = state.root().clone();
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(0);
+
+		// when
+		let static_result = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory).with_static_call(true);
+			let output = BytesRef::Fixed(&mut[0u8;0]);
+			ex.call(params.clone(), &mut Substate::new(), output, &mut NoopTracer, &mut NoopVMTracer)
+		};
+		state.commit();
+		let static_root = state.root().clone();
+		let result = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			let output = BytesRef::Fixed(&mut[0u8;0]);
+			ex.call(params, &mut Substate::new(), output, &mut NoopTracer, &mut NoopVMTracer)
+		};
+
+		// then
+		match static_result {
+			Err(evm::Error::MutationNotAllowed) => (),
+			other => panic!("Expected mutation to be rejected, got {:?}", other),
+		}
+		assert_eq!(static_root, root);
+		assert!(result.is_ok());
+		assert_eq!(state.storage_at(&address, &H256::new()), H256::from(&U256::from(1)));
+	}
+
+	evm_test!{test_call_with_value_without_charging_fees: test_call_with_value_without_charging_fees_jit, test_call_with_value_without_charging_fees_int}
+	fn test_call_with_value_without_charging_fees(factory: Factory) {
+		// given
+		let address = Address::from(0x1234);
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::from(100),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: U256::one(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+		let sender = t.sender().unwrap();
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.add_balance(&sender, &U256::from(40));
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		let engine = TestEngine::new(0);
+
+		// when
+		let executed = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, TransactOptions { check_nonce: false, tracing: false, vm_tracing: false, charge_fees: false, diffing: false, static_call: false, max_output_size: None })
+		};
+
+		// then
+		assert!(executed.is_ok(), "Unexpected error: {:?}", executed);
+		assert_eq!(state.balance(&sender), U256::zero());
+		assert_eq!(state.balance(&address), U256::from(100));
+	}
+
+	evm_test!{test_call_without_charging_fees: test_call_without_charging_fees_jit, test_call_without_charging_fees_int}
+	fn test_call_without_charging_fees(factory: Factory) {
+		// 33 - caller
+		// 31 - balance
+		// 60 00 - push 0
+		// 52 - mstore
+		// 60 20 - push 32
+		// 60 00 - push 0
+		// f3 - return
+		let address = Address::from(0x1234);
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: U256::one(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+		let sender = t.sender().unwrap();
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, "333160005260206000f3".from_hex().unwrap());
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		let engine = TestEngine::new(0);
+
+		// when
+		let charged = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None })
+		};
+		let executed = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: false, diffing: false, static_call: false, max_output_size: None }).unwrap()
+		};
+
+		// then
+		match charged {
+			Err(ExecutionError::NotEnoughCash { .. }) => (),
+			_ => assert!(false, "Expected not enough cash error. {:?}", charged)
+		}
+		assert!(!executed.exception);
+		assert_eq!(executed.output, vec![0u8; 32]);
+		assert_eq!(state.balance(&sender), U256::zero());
+		assert_eq!(state.balance(&info.author), U256::zero());
+		assert_eq!(state.nonce(&sender), U256::zero());
+	}
+
+	evm_test!{test_transact_with_state_diff: test_transact_with_state_diff_jit, test_transact_with_state_diff_int}
+	fn test_transact_with_state_diff(factory: Factory) {
+		// given
+		let receiver = Address::from(0x1234);
+		let t = Transaction {
+			action: Action::Call(receiver.clone()),
+			value: U256::from(10),
+			data: vec![],
+			gas: U256::from(21_000),
+			gas_price: U256::one(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+		let sender = t.sender().unwrap();
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.add_balance(&sender, &U256::from(100_000));
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		info.author = Address::from(0x5678);
+		let engine = TestEngine::new(0);
+
+		// when
+		let executed = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.transact(&t, TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: true, static_call: false, max_output_size: None }).unwrap()
+		};
+
+		// then
+		let diff = executed.state_diff.unwrap();
+		assert_eq!(diff.len(), 3);
+		assert_eq!(diff[&sender].balance, Diff::Changed(U256::from(100_000), U256::from(78_990)));
+		assert_eq!(diff[&sender].nonce, Diff::Changed(U256::zero(), U256::one()));
+		assert_eq!(diff[&receiver].balance, Diff::Born(U256::from(10)));
+		assert_eq!(diff[&info.author].balance, Diff::Born(U256::from(21_000)));
+		assert_eq!(state.balance(&sender), U256::from(78_990));
+	}
+
+	#[test]
+	fn test_transact_with_vm_trace() {
+		// 60 01 - push 1
+		// 60 02 - push 2
+		// 01 - add
+		// 00 - stop
+		let factory = Factory::new(VMType::Interpreter);
+		let address = Address::from(0x1234);
+		let code = "600160020100".from_hex().unwrap();
+		let t = Transaction {
+			action: Action::Call(address.clone()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero()
+		}.sign(&KeyPair::create().unwrap().secret());
+
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		state.init_code(&address, code.clone());
+		let mut info = EnvInfo::default();
+		info.gas_limit = U256::from(100_000);
+		let engine = TestEngine::new(0);
+
+		// when
+		let executed = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: true, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
+			ex.transact(&t, opts).unwrap()
+		};
+
+		// then
+		let operation = |pc: usize, instruction: u8, gas_cost: u64, gas_left: u64, stack_push: Vec<U256>| VMOperation {
+			pc: pc,
+			instruction: instruction,
+			gas_cost: U256::from(gas_cost),
+			executed: Some(VMExecutedOperation {
+				gas_left: U256::from(gas_left),
+				stack_push: stack_push,
+			}),
+		};
+		assert_eq!(executed.vm_trace, Some(VMTrace {
+			parent_step: 0,
+			code: code,
+			operations: vec![
+				operation(0, 0x60, 3, 78_997, vec![U256::from(1)]),
+				operation(2, 0x60, 3, 78_994, vec![U256::from(2)]),
+				operation(4, 0x01, 3, 78_991, vec![U256::from(3)]),
+				operation(5, 0x00, 0, 78_991, vec![]),
+			],
+			subs: vec![],
+		}));
+	}
+
+	#[test]
+	fn test_call_identity_builtin_with_truncated_input() {
+		// given
+		let factory = Factory::default();
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(0);
+		let mut substate = Substate::new();
+		let mut tracer = ExecutiveTracer::default();
+		let mut output = [0xffu8; 4];
+
+		// when
+		let gas_left = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.call(builtin_params(4, vec![1, 2, 3]), &mut substate, BytesRef::Fixed(&mut output), &mut tracer, &mut NoopVMTracer).unwrap()
+		};
+
+		// then
+		// 15 base + 3 per word
+		assert_eq!(gas_left, U256::from(99_982));
+		assert_eq!(output, [1, 2, 3, 0xff]);
+		assert_eq!(tracer.traces(), builtin_call_trace(4, vec![1, 2, 3], trace::Res::Call(trace::CallResult {
+			gas_used: U256::from(18),
+			output: vec![1, 2, 3, 0xff],
+		})));
+	}
+
+	#[test]
+	fn test_call_ecrecover_builtin_with_truncated_input() {
+		// given
+		let factory = Factory::default();
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		let info = EnvInfo::default();
+		let engine = TestEngine::new(0);
+		let mut substate = Substate::new();
+		let mut tracer = ExecutiveTracer::default();
+		let mut output = [0u8; 32];
+		// hash only, missing signature is padded with zeros
+		let data = "47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad".from_hex().unwrap();
+
+		// when
+		let gas_left = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.call(builtin_params(1, data.clone()), &mut substate, BytesRef::Fixed(&mut output), &mut tracer, &mut NoopVMTracer).unwrap()
+		};
+
+		// then
+		assert_eq!(gas_left, U256::from(97_000));
+		assert_eq!(output, [0u8; 32]);
+		assert_eq!(tracer.traces(), builtin_call_trace(1, data, trace::Res::Call(trace::CallResult {
+			gas_used: U256::from(3000),
+			output: vec![0u8; 32],
+		})));
+	}
+
+	#[test]
+	fn test_call_failing_builtin() {
+		// given
+		let factory = Factory::default();
+		let mut state_result = get_temp_state();
+		let mut state = state_result.reference_mut();
+		let info = EnvInfo::default();
+		let engine = FailingBuiltinEngine::new();
+		let mut substate = Substate::new();
+		let mut tracer = ExecutiveTracer::default();
+		let mut output = [0u8; 4];
+
+		// when
+		let res = {
+			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
+			ex.call(builtin_params(0x10, vec![1, 2, 3]), &mut substate, BytesRef::Fixed(&mut output), &mut tracer, &mut NoopVMTracer)
+		};
+
+		// then
+		match res {
+			Err(evm::Error::OutOfGas) => (),
+			_ => assert!(false, "Expected failing builtin to drain the gas. {:?}", res)
+		}
+		let traces = tracer.traces();
+		assert_eq!(traces, builtin_call_trace(0x10, vec![1, 2, 3], trace::Res::FailedCall));
+		let flat = traces.into_iter().flat_map(Trace::flat).collect::<Vec<_>>();
+		assert_eq!(flat.len(), 1);
+		assert_eq!(flat[0].trace_address, vec![]);
+		assert_eq!(flat[0].subtraces, 0);
+		assert_eq!(flat[0].result, trace::Res::FailedCall);
+	}
+
 	evm_test!{test_transact_invalid_sender: test_transact_invalid_sender_jit, test_transact_invalid_sender_int}
 	fn test_transact_invalid_sender(factory: Factory) {
 		let t = Transaction {
@@ -947,7 +1994,7 @@ mod tests {
 
 		let res = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			let opts = TransactOptions { check_nonce: true, tracing: false };
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
 			ex.transact(&t, opts)
 		};
 
@@ -979,7 +2026,7 @@ mod tests {
 
 		let res = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			let opts = TransactOptions { check_nonce: true, tracing: false };
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
 			ex.transact(&t, opts)
 		};
 
@@ -1013,7 +2060,7 @@ mod tests {
 
 		let res = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			let opts = TransactOptions { check_nonce: true, tracing: false };
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
 			ex.transact(&t, opts)
 		};
 
@@ -1047,7 +2094,7 @@ mod tests {
 
 		let res = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			let opts = TransactOptions { check_nonce: true, tracing: false };
+			let opts = TransactOptions { check_nonce: true, tracing: false, vm_tracing: false, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
 			ex.transact(&t, opts)
 		};
 
@@ -1082,7 +2129,7 @@ mod tests {
 
 		let result = {
 			let mut ex = Executive::new(&mut state, &info, &engine, &factory);
-			ex.create(params, &mut substate, &mut NoopTracer)
+			ex.create(params, &mut substate, &mut NoopTracer, &mut NoopVMTracer)
 		};
 
 		match result {
@@ -21,7 +21,8 @@ use engine::*;
 use executive::*;
 use evm::{self, Schedule, Ext, ContractCreateResult, MessageCallResult, Factory};
 use substate::*;
-use trace::Tracer;
+use trace::{Tracer, VMTracer};
+use std::time::Instant;
 
 /// Policy for handling output data on `RETURN` opcode.
 pub enum OutputPolicy<'a, 'b> {
@@ -55,7 +56,7 @@ impl OriginInfo {
 }
 
 /// Implementation of evm Externalities.
-pub struct Externalities<'a, T> where T: 'a + Tracer {
+pub struct Externalities<'a, T, V> where T: 'a + Tracer, V: 'a + VMTracer {
 	state: &'a mut State,
 	env_info: &'a EnvInfo,
 	engine: &'a Engine,
@@ -66,9 +67,13 @@ pub struct Externalities<'a, T> where T: 'a + Tracer {
 	schedule: Schedule,
 	output: OutputPolicy<'a, 'a>,
 	tracer: &'a mut T,
+	vm_tracer: &'a mut V,
+	deadline: Option<Instant>,
+	static_call: bool,
+	max_output_size: Option<usize>,
 }
 
-impl<'a, T> Externalities<'a, T> where T: 'a + Tracer {
+impl<'a, T, V> Externalities<'a, T, V> where T: 'a + Tracer, V: 'a + VMTracer {
 
 	#[cfg_attr(feature="dev", allow(too_many_arguments))]
 	/// Basic `Externalities` constructor.
@@ -81,6 +86,7 @@ impl<'a, T> Externalities<'a, T> where T: 'a + Tracer {
 		substate: &'a mut Substate,
 		output: OutputPolicy<'a, 'a>,
 		tracer: &'a mut T,
+		vm_tracer: &'a mut V,
 	) -> Self {
 		Externalities {
 			state: state,
@@ -93,11 +99,34 @@ impl<'a, T> Externalities<'a, T> where T: 'a + Tracer {
 			schedule: engine.schedule(env_info),
 			output: output,
 			tracer: tracer,
+			vm_tracer: vm_tracer,
+			deadline: None,
+			static_call: false,
+			max_output_size: None,
 		}
 	}
+
+	/// Aborts execution with `evm::Error::Timeout` once `deadline` passes.
+	pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+		self.deadline = deadline;
+		self
+	}
+
+	/// Aborts execution with `evm::Error::MutationNotAllowed` if it tries to modify the state.
+	pub fn with_static_call(mut self, static_call: bool) -> Self {
+		self.static_call = static_call;
+		self
+	}
+
+	/// Aborts execution with `evm::Error::OutputTooLarge` if data returned into a flexible
+	/// output buffer is longer than `max_output_size` bytes.
+	pub fn with_max_output_size(mut self, max_output_size: Option<usize>) -> Self {
+		self.max_output_size = max_output_size;
+		self
+	}
 }
 
-impl<'a, T> Ext for Externalities<'a, T> where T: 'a + Tracer {
+impl<'a, T, V> Ext for Externalities<'a, T, V> where T: 'a + Tracer, V: 'a + VMTracer {
 	fn storage_at(&self, key: &H256) -> H256 {
 		self.state.storage_at(&self.origin_info.address, key)
 	}
@@ -149,10 +178,12 @@ impl<'a, T> Ext for Externalities<'a, T> where T: 'a + Tracer {
 		};
 
 		self.state.inc_nonce(&self.origin_info.address);
-		let mut ex = Executive::from_parent(self.state, self.env_info, self.engine, self.vm_factory, self.depth);
+		let mut ex = Executive::from_parent(self.state, self.env_info, self.engine, self.vm_factory, self.depth)
+			.with_deadline(self.deadline)
+			.with_static_call(self.static_call);
 
 		// TODO: handle internal error separately
-		match ex.create(params, self.substate, self.tracer) {
+		match ex.create(params, self.substate, self.tracer, self.vm_tracer) {
 			Ok(gas_left) => {
 				self.substate.contracts_created.push(address.clone());
 				ContractCreateResult::Created(address, gas_left)
@@ -188,9 +219,11 @@ impl<'a, T> Ext for Externalities<'a, T> where T: 'a + Tracer {
 			params.value = ActionValue::Transfer(value);
 		}
 
-		let mut ex = Executive::from_parent(self.state, self.env_info, self.engine, self.vm_factory, self.depth);
+		let mut ex = Executive::from_parent(self.state, self.env_info, self.engine, self.vm_factory, self.depth)
+			.with_deadline(self.deadline)
+			.with_static_call(self.static_call);
 
-		match ex.call(params, self.substate, BytesRef::Fixed(output), self.tracer) {
+		match ex.call(params, self.substate, BytesRef::Fixed(output), self.tracer, self.vm_tracer) {
 			Ok(gas_left) => MessageCallResult::Success(gas_left),
 			_ => MessageCallResult::Failed
 		}
@@ -216,6 +249,9 @@ impl<'a, T> Ext for Externalities<'a, T> where T: 'a + Tracer {
 				Ok(*gas)
 			},
 			OutputPolicy::Return(BytesRef::Flexible(ref mut vec), ref mut copy) => {
+				if self.max_output_size.map_or(false, |max| data.len() > max) {
+					return Err(evm::Error::OutputTooLarge);
+				}
 				handle_copy(copy);
 
 				vec.clear();
@@ -268,6 +304,8 @@ impl<'a, T> Ext for Externalities<'a, T> where T: 'a + Tracer {
 			trace!("Suiciding {} -> {} (xfer: {})", address, refund_address, balance);
 			self.state.transfer_balance(&address, refund_address, &balance);
 		}
+		// Suicide is an action of the currently executed code, one level below its call.
+		self.tracer.trace_suicide(address.clone(), balance, refund_address.clone(), self.depth + 1);
 		self.substate.suicides.insert(address);
 	}
 
@@ -286,6 +324,22 @@ impl<'a, T> Ext for Externalities<'a, T> where T: 'a + Tracer {
 	fn inc_sstore_clears(&mut self) {
 		self.substate.sstore_clears_count = self.substate.sstore_clears_count + U256::one();
 	}
+
+	fn trace_prepare_execute(&mut self, pc: usize, instruction: u8, gas_cost: &U256) -> bool {
+		self.vm_tracer.trace_prepare_execute(pc, instruction, gas_cost)
+	}
+
+	fn trace_executed(&mut self, gas_left: U256, stack_push: &[U256]) {
+		self.vm_tracer.trace_executed(gas_left, stack_push)
+	}
+
+	fn deadline_exceeded(&self) -> bool {
+		self.deadline.map_or(false, |deadline| Instant::now() >= deadline)
+	}
+
+	fn is_static(&self) -> bool {
+		self.static_call
+	}
 }
 
 #[cfg(test)]
@@ -297,7 +351,7 @@ mod tests {
 	use substate::*;
 	use tests::helpers::*;
 	use super::*;
-	use trace::{NoopTracer};
+	use trace::{NoopTracer, NoopVMTracer};
 
 	fn get_test_origin() -> OriginInfo {
 		OriginInfo {
@@ -349,9 +403,10 @@ mod tests {
 		let mut setup = TestSetup::new();
 		let state = setup.state.reference_mut();
 		let mut tracer = NoopTracer;
+		let mut vm_tracer = NoopVMTracer;
 
 		let vm_factory = Default::default();
-		let ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer);
+		let ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer, &mut vm_tracer);
 
 		assert_eq!(ext.env_info().number, 100);
 	}
@@ -361,9 +416,10 @@ mod tests {
 		let mut setup = TestSetup::new();
 		let state = setup.state.reference_mut();
 		let mut tracer = NoopTracer;
+		let mut vm_tracer = NoopVMTracer;
 
 		let vm_factory = Default::default();
-		let ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer);
+		let ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer, &mut vm_tracer);
 
 		let hash = ext.blockhash(&U256::from_str("0000000000000000000000000000000000000000000000000000000000120000").unwrap());
 
@@ -383,9 +439,10 @@ mod tests {
 		}
 		let state = setup.state.reference_mut();
 		let mut tracer = NoopTracer;
+		let mut vm_tracer = NoopVMTracer;
 
 		let vm_factory = Default::default();
-		let ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer);
+		let ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer, &mut vm_tracer);
 
 		let hash = ext.blockhash(&U256::from_str("0000000000000000000000000000000000000000000000000000000000120000").unwrap());
 
@@ -398,9 +455,10 @@ mod tests {
 		let mut setup = TestSetup::new();
 		let state = setup.state.reference_mut();
 		let mut tracer = NoopTracer;
+		let mut vm_tracer = NoopVMTracer;
 
 		let vm_factory = Default::default();
-		let mut ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer);
+		let mut ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer, &mut vm_tracer);
 
 		let mut output = vec![];
 
@@ -423,10 +481,11 @@ mod tests {
 		let mut setup = TestSetup::new();
 		let state = setup.state.reference_mut();
 		let mut tracer = NoopTracer;
+		let mut vm_tracer = NoopVMTracer;
 
 		{
 			let vm_factory = Default::default();
-			let mut ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer);
+			let mut ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer, &mut vm_tracer);
 			ext.log(log_topics, &log_data);
 		}
 
@@ -440,10 +499,11 @@ mod tests {
 		let mut setup = TestSetup::new();
 		let state = setup.state.reference_mut();
 		let mut tracer = NoopTracer;
+		let mut vm_tracer = NoopVMTracer;
 
 		{
 			let vm_factory = Default::default();
-			let mut ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer);
+			let mut ext = Externalities::new(state, &setup.env_info, &*setup.engine, &vm_factory, 0, get_test_origin(), &mut setup.sub_state, OutputPolicy::InitContract(None), &mut tracer, &mut vm_tracer);
 			ext.suicide(&refund_account);
 		}
 
@@ -36,6 +36,8 @@ pub enum ExtrasIndex {
 	BlocksBlooms = 4,
 	/// Block receipts index
 	BlockReceipts = 5,
+	/// Uncle address index
+	UncleAddress = 6,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -84,6 +86,12 @@ impl ExtrasIndexable for BlockReceipts {
 	}
 }
 
+impl ExtrasIndexable for UncleAddress {
+	fn index() -> ExtrasIndex {
+		ExtrasIndex::UncleAddress
+	}
+}
+
 impl Key<H256> for BlockNumber {
 	type Target = H264;
 
@@ -132,6 +140,14 @@ impl Key<BlockReceipts> for H256 {
 	}
 }
 
+impl Key<UncleAddress> for H256 {
+	type Target = H264;
+
+	fn key(&self) -> H264 {
+		with_index(self, ExtrasIndex::UncleAddress)
+	}
+}
+
 /// Familial details concerning a block
 #[derive(Debug, Clone)]
 pub struct BlockDetails {
@@ -289,6 +305,39 @@ impl Encodable for TransactionAddress {
 	}
 }
 
+/// Represents address of certain uncle within the block which included it
+#[derive(Debug, Clone, PartialEq)]
+pub struct UncleAddress {
+	/// Hash of the including (nephew) block
+	pub block_hash: H256,
+	/// Uncle index within the block
+	pub index: usize
+}
+
+impl HeapSizeOf for UncleAddress {
+	fn heap_size_of_children(&self) -> usize { 0 }
+}
+
+impl Decodable for UncleAddress {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let d = decoder.as_rlp();
+		let uncle_address = UncleAddress {
+			block_hash: try!(d.val_at(0)),
+			index: try!(d.val_at(1)),
+		};
+
+		Ok(uncle_address)
+	}
+}
+
+impl Encodable for UncleAddress {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(2);
+		s.append(&self.block_hash);
+		s.append(&self.index);
+	}
+}
+
 /// Contains all block receipts.
 #[derive(Clone)]
 pub struct BlockReceipts {
@@ -0,0 +1,146 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Gas limit of authored blocks.
+
+use util::*;
+
+/// Way the gas limit of authored blocks follows the targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GasLimitStrategy {
+	/// Keep the gas limit of the parent block.
+	FollowParent,
+	/// Move the gas limit into the range between floor and ceil targets by the maximal allowed step
+	/// and keep it unchanged while it's within the range.
+	TargetRange,
+	/// Move the gas limit towards the floor target by the maximal allowed step.
+	Fixed,
+}
+
+/// Desired gas limit of authored blocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GasLimitTarget {
+	/// Way the gas limit follows the targets.
+	pub strategy: GasLimitStrategy,
+	/// Lower bound of the gas limit (the target itself for `Fixed` strategy).
+	pub floor: U256,
+	/// Upper bound of the gas limit. Used by `TargetRange` strategy only.
+	pub ceil: U256,
+}
+
+impl GasLimitTarget {
+	/// Targets exactly `target` gas limit.
+	pub fn fixed(target: U256) -> Self {
+		GasLimitTarget {
+			strategy: GasLimitStrategy::Fixed,
+			floor: target,
+			ceil: target,
+		}
+	}
+
+	/// Keeps the gas limit between `floor` and `ceil`.
+	pub fn range(floor: U256, ceil: U256) -> Self {
+		GasLimitTarget {
+			strategy: GasLimitStrategy::TargetRange,
+			floor: floor,
+			ceil: ceil,
+		}
+	}
+
+	/// Returns gas limit of a block authored on top of parent with `parent_gas_limit`.
+	///
+	/// The result is always within protocol bounds: it's never lower than `min_gas_limit`
+	/// (unless the parent limit is too low to get there in a single block)
+	/// and differs from the parent limit by less than `parent_gas_limit / bound_divisor`.
+	pub fn child_gas_limit(&self, parent_gas_limit: U256, bound_divisor: U256, min_gas_limit: U256) -> U256 {
+		let (lower, upper) = match self.strategy {
+			GasLimitStrategy::FollowParent => (parent_gas_limit, parent_gas_limit),
+			GasLimitStrategy::TargetRange => (self.floor, self.ceil),
+			GasLimitStrategy::Fixed => (self.floor, self.floor),
+		};
+		let lower = max(lower, min_gas_limit);
+		let upper = max(upper, lower);
+
+		let bound = parent_gas_limit / bound_divisor;
+		let step = if bound.is_zero() { bound } else { bound - U256::one() };
+		if parent_gas_limit < lower {
+			min(lower, parent_gas_limit + step)
+		} else if parent_gas_limit > upper {
+			max(upper, parent_gas_limit - step)
+		} else {
+			parent_gas_limit
+		}
+	}
+}
+
+impl Default for GasLimitTarget {
+	fn default() -> Self {
+		GasLimitTarget::fixed(U256::zero())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use super::{GasLimitTarget, GasLimitStrategy};
+
+	const DIVISOR: u64 = 1024;
+	const MIN: u64 = 5000;
+
+	fn child(target: GasLimitTarget, parent: u64) -> U256 {
+		target.child_gas_limit(U256::from(parent), U256::from(DIVISOR), U256::from(MIN))
+	}
+
+	#[test]
+	fn should_move_towards_fixed_target() {
+		let target = GasLimitTarget::fixed(U256::from(3_000_000));
+
+		assert_eq!(child(target, 1_024_000), U256::from(1_024_999));
+		assert_eq!(child(target, 4_096_000), U256::from(4_092_001));
+		assert_eq!(child(target, 2_999_000), U256::from(3_000_000));
+		assert_eq!(child(target, 3_000_000), U256::from(3_000_000));
+	}
+
+	#[test]
+	fn should_keep_limit_within_target_range() {
+		let target = GasLimitTarget::range(U256::from(2_000_000), U256::from(4_000_000));
+
+		assert_eq!(child(target, 1_024_000), U256::from(1_024_999));
+		assert_eq!(child(target, 8_192_000), U256::from(8_184_001));
+		assert_eq!(child(target, 3_000_000), U256::from(3_000_000));
+		assert_eq!(child(target, 4_001_000), U256::from(4_000_000));
+	}
+
+	#[test]
+	fn should_follow_parent() {
+		let target = GasLimitTarget { strategy: GasLimitStrategy::FollowParent, ..GasLimitTarget::fixed(U256::from(3_000_000)) };
+
+		assert_eq!(child(target, 1_024_000), U256::from(1_024_000));
+		assert_eq!(child(target, 8_192_000), U256::from(8_192_000));
+	}
+
+	#[test]
+	fn should_clamp_targets_to_protocol_bounds() {
+		// floor below minimal gas limit
+		assert_eq!(child(GasLimitTarget::fixed(U256::from(1000)), 5_000), U256::from(5_000));
+		assert_eq!(child(GasLimitTarget::fixed(U256::from(1000)), 10_240), U256::from(10_231));
+		// ceil below floor
+		assert_eq!(child(GasLimitTarget::range(U256::from(20_480), U256::from(10_000)), 20_480), U256::from(20_480));
+		// parent below minimal gas limit
+		let follow = GasLimitTarget { strategy: GasLimitStrategy::FollowParent, ..GasLimitTarget::default() };
+		assert_eq!(child(follow, 4_096), U256::from(4_099));
+	}
+}
@@ -0,0 +1,220 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Rules for minimal gas price required from transaction senders.
+
+use util::*;
+use error::{Error, TransactionError};
+use transaction::SignedTransaction;
+
+/// Rule determining minimal gas price of transactions sent by given sender.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GasPriceRule {
+	/// The same minimal gas price for every sender.
+	Uniform(U256),
+	/// `floor` for every sender except `exempt` ones, which may use any gas price (including zero).
+	PerSenderExemptions {
+		/// Minimal gas price of senders which are not exempt.
+		floor: U256,
+		/// Exempt senders.
+		exempt: HashSet<Address>,
+	},
+	/// Minimal gas price is returned by a contract at `registry`.
+	///
+	/// The contract is called with `selector` followed by ABI-encoded sender address
+	/// and should return the minimal gas price as a single `uint256`.
+	Contract {
+		/// Address of the rule contract.
+		registry: Address,
+		/// Function selector.
+		selector: Bytes,
+	},
+}
+
+impl Default for GasPriceRule {
+	fn default() -> Self {
+		GasPriceRule::Uniform(U256::zero())
+	}
+}
+
+impl GasPriceRule {
+	/// Returns minimal gas price of senders which are not exempt.
+	/// Rules depending on state have no common floor.
+	pub fn floor(&self) -> U256 {
+		match *self {
+			GasPriceRule::Uniform(ref floor) | GasPriceRule::PerSenderExemptions { ref floor, .. } => *floor,
+			GasPriceRule::Contract { .. } => U256::zero(),
+		}
+	}
+
+	/// Returns the same rule with different floor. Exemptions are kept and `Contract` rule is not affected.
+	pub fn with_floor(self, floor: U256) -> Self {
+		match self {
+			GasPriceRule::Uniform(_) => GasPriceRule::Uniform(floor),
+			GasPriceRule::PerSenderExemptions { exempt, .. } => GasPriceRule::PerSenderExemptions {
+				floor: floor,
+				exempt: exempt,
+			},
+			rule @ GasPriceRule::Contract { .. } => rule,
+		}
+	}
+
+	/// Returns `true` if the rule depends on state and has to be resolved with a contract call.
+	pub fn requires_call(&self) -> bool {
+		match *self {
+			GasPriceRule::Contract { .. } => true,
+			_ => false,
+		}
+	}
+
+	/// Returns minimal gas price required from `sender`.
+	///
+	/// `call` is used only by `Contract` rule. It executes a call to given address with given data
+	/// and returns the output or `None` if the call failed. Senders for which the call fails are
+	/// not allowed to send any transactions.
+	pub fn required_gas_price<F>(&self, sender: &Address, call: F) -> U256 where F: FnOnce(&Address, Bytes) -> Option<Bytes> {
+		match *self {
+			GasPriceRule::Uniform(ref floor) => *floor,
+			GasPriceRule::PerSenderExemptions { ref floor, ref exempt } => match exempt.contains(sender) {
+				true => U256::zero(),
+				false => *floor,
+			},
+			GasPriceRule::Contract { ref registry, ref selector } => {
+				let mut data = selector.clone();
+				data.extend_from_slice(&[0u8; 12]);
+				data.extend_from_slice(sender);
+				match call(registry, data) {
+					Some(ref output) if output.len() >= 32 => U256::from(&output[0..32]),
+					_ => {
+						warn!(target: "miner", "Gas price rule contract call failed for {}.", sender);
+						U256::max_value()
+					}
+				}
+			},
+		}
+	}
+
+	/// Verifies that transaction in a block satisfies the rule.
+	/// `Contract` rule depends on state and is not checked here.
+	pub fn verify_transaction(&self, t: &SignedTransaction) -> Result<(), Error> {
+		if self.requires_call() {
+			return Ok(());
+		}
+		let sender = try!(t.sender());
+		let minimal = self.required_gas_price(&sender, |_, _| None);
+		if t.gas_price < minimal {
+			return Err(From::from(TransactionError::InsufficientGasPrice {
+				minimal: minimal,
+				got: t.gas_price,
+			}));
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use transaction::{Transaction, Action};
+	use super::*;
+
+	fn transaction(secret: &Secret, gas_price: U256) -> SignedTransaction {
+		Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: gas_price,
+			nonce: U256::zero(),
+		}.sign(secret)
+	}
+
+	#[test]
+	fn should_require_floor_from_non_exempt_senders_only() {
+		// given
+		let exempt = KeyPair::create().unwrap();
+		let other = KeyPair::create().unwrap();
+		let rule = GasPriceRule::PerSenderExemptions {
+			floor: U256::from(20),
+			exempt: vec![exempt.address()].into_iter().collect(),
+		};
+
+		// when
+		let exempt_result = rule.verify_transaction(&transaction(exempt.secret(), U256::zero()));
+		let other_result = rule.verify_transaction(&transaction(other.secret(), U256::zero()));
+
+		// then
+		assert!(exempt_result.is_ok());
+		match other_result {
+			Err(Error::Transaction(TransactionError::InsufficientGasPrice { minimal, got })) => {
+				assert_eq!(minimal, U256::from(20));
+				assert_eq!(got, U256::zero());
+			},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+	}
+
+	#[test]
+	fn should_keep_exemptions_when_changing_floor() {
+		// given
+		let exempt: HashSet<Address> = vec![Address::from(1)].into_iter().collect();
+		let rule = GasPriceRule::PerSenderExemptions { floor: U256::from(20), exempt: exempt.clone() };
+
+		// when
+		let rule = rule.with_floor(U256::from(30));
+
+		// then
+		assert_eq!(rule, GasPriceRule::PerSenderExemptions { floor: U256::from(30), exempt: exempt });
+		assert_eq!(GasPriceRule::Uniform(U256::one()).with_floor(U256::from(2)), GasPriceRule::Uniform(U256::from(2)));
+	}
+
+	#[test]
+	fn should_query_contract_with_sender_address() {
+		// given
+		let rule = GasPriceRule::Contract {
+			registry: Address::from(0x10),
+			selector: vec![0xde, 0xad, 0xbe, 0xef],
+		};
+		let sender = Address::from(0x20);
+		let mut called = None;
+
+		// when
+		let price = rule.required_gas_price(&sender, |address, data| {
+			called = Some((address.clone(), data));
+			Some(H256::from(&U256::from(5)).to_vec())
+		});
+
+		// then
+		assert_eq!(price, U256::from(5));
+		let (address, data) = called.unwrap();
+		assert_eq!(address, Address::from(0x10));
+		assert_eq!(&data[0..4], &[0xde, 0xad, 0xbe, 0xef]);
+		assert_eq!(&data[4..16], &[0u8; 12]);
+		assert_eq!(&data[16..], &*sender);
+	}
+
+	#[test]
+	fn should_reject_senders_when_contract_call_fails() {
+		// given
+		let rule = GasPriceRule::Contract { registry: Address::from(0x10), selector: vec![0; 4] };
+
+		// when
+		let price = rule.required_gas_price(&Address::from(0x20), |_, _| None);
+
+		// then
+		assert_eq!(price, U256::max_value());
+	}
+}
@@ -137,6 +137,8 @@ impl Header {
 	pub fn state_root(&self) -> &H256 { &self.state_root }
 	/// Get the receipts root field of the header.
 	pub fn receipts_root(&self) -> &H256 { &self.receipts_root }
+	/// Get the gas used field of the header.
+	pub fn gas_used(&self) -> &U256 { &self.gas_used }
 	/// Get the gas limit field of the header.
 	pub fn gas_limit(&self) -> &U256 { &self.gas_limit }
 
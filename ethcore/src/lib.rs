@@ -107,7 +107,11 @@ pub mod trace;
 pub mod spec;
 pub mod views;
 pub mod pod_state;
+pub mod account_diff;
+pub mod state_diff;
 pub mod engine;
+pub mod gas_price_rule;
+pub mod gas_limit;
 
 mod db;
 mod common;
@@ -115,8 +119,6 @@ mod basic_types;
 #[macro_use] mod evm;
 mod env_info;
 mod pod_account;
-mod account_diff;
-mod state_diff;
 mod state;
 mod account;
 mod account_db;
@@ -26,6 +26,7 @@ use super::seal::Generic as GenericSeal;
 use ethereum;
 use basic_authority::BasicAuthority;
 use ethjson;
+use gas_price_rule::GasPriceRule;
 
 /// Parameters common to all engines.
 #[derive(Debug, PartialEq, Clone)]
@@ -38,6 +39,26 @@ pub struct CommonParams {
 	pub network_id: U256,
 	/// Minimum gas limit.
 	pub min_gas_limit: U256,
+	/// Minimal gas price required by the chain (if any).
+	pub gas_price_rule: Option<GasPriceRule>,
+	/// Chain id transactions may be signed with to prevent replaying them on other chains.
+	pub chain_id: Option<u64>,
+	/// Block from which transactions signed without chain id are rejected.
+	pub chain_id_transition: BlockNumber,
+	/// Block from which contract creation at an address with code or a used nonce fails.
+	pub create_collision_transition: BlockNumber,
+}
+
+impl CommonParams {
+	/// Returns `true` if transactions signed without chain id can be included in block `number`.
+	pub fn allows_unprotected_transactions(&self, number: BlockNumber) -> bool {
+		number < self.chain_id_transition
+	}
+
+	/// Returns `true` if creating a contract at an already used address fails in block `number`.
+	pub fn rejects_create_collisions(&self, number: BlockNumber) -> bool {
+		number >= self.create_collision_transition
+	}
 }
 
 impl From<ethjson::spec::Params> for CommonParams {
@@ -47,6 +68,21 @@ impl From<ethjson::spec::Params> for CommonParams {
 			maximum_extra_data_size: p.maximum_extra_data_size.into(),
 			network_id: p.network_id.into(),
 			min_gas_limit: p.min_gas_limit.into(),
+			gas_price_rule: match (p.gas_price_contract, p.min_gas_price, p.gas_price_exempt) {
+				(Some(contract), _, _) => Some(GasPriceRule::Contract {
+					registry: contract.registry.into(),
+					selector: contract.selector.into(),
+				}),
+				(None, floor, Some(exempt)) => Some(GasPriceRule::PerSenderExemptions {
+					floor: floor.map_or_else(U256::zero, Into::into),
+					exempt: exempt.into_iter().map(Into::into).collect(),
+				}),
+				(None, Some(floor), None) => Some(GasPriceRule::Uniform(floor.into())),
+				(None, None, None) => None,
+			},
+			chain_id: p.chain_id.map(Into::into),
+			chain_id_transition: p.chain_id_transition.map_or(BlockNumber::max_value(), Into::into),
+			create_collision_transition: p.create_collision_transition.map_or(BlockNumber::max_value(), Into::into),
 		}
 	}
 }
@@ -229,6 +265,10 @@ impl Spec {
 			}
 			for (address, account) in self.genesis_state.get().iter() {
 				account.insert_additional(&mut AccountDBMut::new(db, address));
+				// Address preimage, see `State::commit_into`.
+				if !account.code.is_empty() || !account.storage.is_empty() {
+					db.insert(address.as_slice());
+				}
 			}
 			assert!(db.contains(&self.state_root()));
 			true
@@ -20,9 +20,8 @@ use executive::{Executive, TransactOptions};
 use evm::Factory as EvmFactory;
 use account_db::*;
 use trace::Trace;
-#[cfg(test)]
-#[cfg(feature = "json-tests")]
 use pod_account::*;
+use state_diff::StateDiff;
 #[cfg(test)]
 #[cfg(feature = "json-tests")]
 use pod_state::PodState;
@@ -117,6 +116,46 @@ impl State {
 		}
 	}
 
+	/// Returns the difference between state at the time of the last snapshot and now.
+	/// Only accounts loaded or modified since the snapshot are considered.
+	pub fn diff_since_snapshot(&self) -> StateDiff {
+		let snapshots = self.snapshots.borrow();
+		let snapshot = match snapshots.last() {
+			Some(snapshot) => snapshot,
+			None => return StateDiff::default(),
+		};
+		let cache = self.cache.borrow();
+		StateDiff::diff_accounts(snapshot.iter().map(|(address, pre)| {
+			let pre = match *pre {
+				Some(ref account) => account.clone(),
+				// account wasn't cached, so it's the same as in the trie
+				None => SecTrieDB::new(self.db.as_hashdb(), &self.root).get(address).map(Account::from_rlp),
+			};
+			let post = cache.get(address).cloned().unwrap_or(None);
+			let keys: BTreeSet<H256> = pre.iter().chain(post.iter())
+				.flat_map(|account| account.storage_overlay().keys().cloned().collect::<Vec<_>>())
+				.collect();
+			(address.clone(), self.pod_account(address, pre, &keys), self.pod_account(address, post, &keys))
+		}).collect::<Vec<_>>())
+	}
+
+	/// Converts `account` at `address` to plain data. Storage is limited to non-zero values under `keys`.
+	fn pod_account(&self, address: &Address, account: Option<Account>, keys: &BTreeSet<H256>) -> Option<PodAccount> {
+		account.map(|mut account| {
+			let db = AccountDB::new(self.db.as_hashdb(), address);
+			account.cache_code(&db);
+			PodAccount {
+				balance: *account.balance(),
+				nonce: *account.nonce(),
+				code: account.code().map_or_else(Vec::new, |code| code.to_vec()),
+				storage: keys.iter()
+					.map(|key| (key.clone(), account.storage_at(&db, key)))
+					.filter(|&(_, ref value)| !value.is_zero())
+					.collect(),
+			}
+		})
+	}
+
 	fn insert_cache(&self, address: &Address, account: Option<Account>) {
 		if let Some(ref mut snapshot) = self.snapshots.borrow_mut().last_mut() {
 			if !snapshot.contains_key(&address) {
@@ -222,7 +261,7 @@ impl State {
 	pub fn apply(&mut self, env_info: &EnvInfo, engine: &Engine, vm_factory: &EvmFactory, t: &SignedTransaction, tracing: bool) -> ApplyResult {
 //		let old = self.to_pod();
 
-		let options = TransactOptions { tracing: tracing, check_nonce: true };
+		let options = TransactOptions { tracing: tracing, vm_tracing: false, check_nonce: true, charge_fees: true, diffing: false, static_call: false, max_output_size: None };
 		let e = try!(Executive::new(self, env_info, engine, vm_factory).transact(t, options));
 
 		// TODO uncomment once to_pod() works correctly.
@@ -242,11 +281,23 @@ impl State {
 		for (address, ref mut a) in accounts.iter_mut() {
 			match a {
 				&mut&mut Some(ref mut account) => {
-					let mut account_db = AccountDBMut::new(db, address);
-					account.commit_storage(&mut account_db);
-					account.commit_code(&mut account_db);
+					let (storage_changed, code_changed) = {
+						let mut account_db = AccountDBMut::new(db, address);
+						(account.commit_storage(&mut account_db), account.commit_code(&mut account_db))
+					};
+					// Storage and code are keyed by the address, which the state trie holds only the hash of.
+					// Keep the preimage so they can be found when walking the trie.
+					if storage_changed || code_changed {
+						db.insert(address.as_slice());
+					}
+				}
+				&mut&mut None => {
+					// Journal removal of the preimage so it is pruned together with the account.
+					let address_hash = address.sha3();
+					if db.contains(&address_hash) {
+						db.remove(&address_hash);
+					}
 				}
-				&mut&mut None => {}
 			}
 		}
 
@@ -359,6 +410,8 @@ mod tests {
 
 use super::*;
 use util::common::*;
+use util::hashdb::HashDB;
+use util::memorydb::MemoryDB;
 use account::*;
 use tests::helpers::*;
 use devtools::*;
@@ -1213,6 +1266,28 @@ fn remove_from_database() {
 	assert_eq!(state.nonce(&a), U256::from(0u64));
 }
 
+#[test]
+fn remove_address_preimage_of_killed_account() {
+	// given
+	let a = Address::zero();
+	let mut db = MemoryDB::new();
+	let mut root = SHA3_NULL_RLP;
+	let mut account = Account::new_contract(U256::zero(), U256::zero());
+	account.init_code(vec![0x60, 0x00]);
+	let mut accounts = HashMap::new();
+	accounts.insert(a.clone(), Some(account));
+	State::commit_into(&mut db, &mut root, &mut accounts);
+	assert!(db.contains(&a.sha3()));
+
+	// when
+	let mut accounts = HashMap::new();
+	accounts.insert(a.clone(), None);
+	State::commit_into(&mut db, &mut root, &mut accounts);
+
+	// then
+	assert!(!db.contains(&a.sha3()));
+}
+
 #[test]
 fn alter_balance() {
 	let mut state_result = get_temp_state();
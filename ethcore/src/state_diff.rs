@@ -17,14 +17,22 @@
 use util::*;
 #[cfg(test)]
 use pod_state::*;
+use pod_account::*;
 use account_diff::*;
+use ipc::binary::{BinaryConvertable, BinaryConvertError};
 
-#[derive(Debug,Clone,PartialEq,Eq)]
+#[derive(Debug,Clone,PartialEq,Eq,Default)]
 /// Expression for the delta between two system states. Encoded the
 /// delta of every altered account.
 pub struct StateDiff (BTreeMap<Address, AccountDiff>);
 
 impl StateDiff {
+	/// Calculate and return diff of given accounts. Each item is an address
+	/// together with the account before and after the change.
+	pub fn diff_accounts<I>(accounts: I) -> StateDiff where I: IntoIterator<Item=(Address, Option<PodAccount>, Option<PodAccount>)> {
+		StateDiff(accounts.into_iter().filter_map(|(address, pre, post)| AccountDiff::diff_pod(pre.as_ref(), post.as_ref()).map(|d| (address, d))).collect())
+	}
+
 	#[cfg(test)]
 	/// Calculate and return diff between `pre` state and `post` state.
 	pub fn diff_pod(pre: &PodState, post: &PodState) -> StateDiff {
@@ -41,6 +49,45 @@ impl fmt::Display for StateDiff {
 	}
 }
 
+impl Encodable for StateDiff {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(self.0.len());
+		for (address, diff) in &self.0 {
+			s.begin_list(2).append(address).append(diff);
+		}
+	}
+}
+
+impl Decodable for StateDiff {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let mut accounts = BTreeMap::new();
+		for item in decoder.as_rlp().iter() {
+			accounts.insert(try!(item.val_at(0)), try!(item.val_at(1)));
+		}
+		Ok(StateDiff(accounts))
+	}
+}
+
+// `Executed` is sent over IPC, so the diff is passed as RLP.
+impl BinaryConvertable for StateDiff {
+	fn size(&self) -> usize {
+		encode(self).len()
+	}
+
+	fn to_bytes(&self, buffer: &mut [u8], _length_stack: &mut VecDeque<usize>) -> Result<(), BinaryConvertError> {
+		buffer[..].clone_from_slice(&encode(self));
+		Ok(())
+	}
+
+	fn from_bytes(buffer: &[u8], _length_stack: &mut VecDeque<usize>) -> Result<Self, BinaryConvertError> {
+		UntrustedRlp::new(buffer).as_val().map_err(|_| BinaryConvertError)
+	}
+
+	fn len_params() -> usize {
+		1
+	}
+}
+
 impl Deref for StateDiff {
 	type Target = BTreeMap<Address, AccountDiff>;
 
@@ -57,6 +104,19 @@ mod test {
 	use pod_account::*;
 	use super::*;
 
+	#[test]
+	fn should_encode_and_decode_rlp() {
+		let diff = StateDiff(map![
+			x!(1) => AccountDiff{
+				balance: Diff::Changed(x!(69), x!(42)),
+				nonce: Diff::Same,
+				code: Diff::Born(vec![0x60, 0x00]),
+				storage: map![ x!(1) => Diff::Died(x!(2)) ],
+			}
+		]);
+		assert_eq!(decode::<StateDiff>(&encode(&diff)), diff);
+	}
+
 	#[test]
 	fn create_delete() {
 		let a = PodState::from(map![ x!(1) => PodAccount::new(x!(69), x!(0), vec![], map![]) ]);
@@ -14,8 +14,10 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
-use client::{BlockChainClient, Client, ClientConfig, BlockID};
+use client::{BlockChainClient, Client, ClientConfig, BlockID, UncleID, MemoryBudgetConfig, VerifierType};
+use ethereum;
 use block::IsBlock;
+use gas_limit::GasLimitTarget;
 use tests::helpers::*;
 use common::*;
 use devtools::*;
@@ -94,6 +96,24 @@ fn returns_block_body() {
 	assert_eq!(body.at(1).as_raw()[..], block.rlp().at(2).as_raw()[..]);
 }
 
+#[test]
+fn returns_block_bodies_in_requested_order() {
+	let client_result = generate_dummy_client(3);
+	let client = client_result.reference();
+	let hash = |n| client.block_hash(BlockID::Number(n)).unwrap();
+	let hashes = vec![hash(3), H256::from(0xdeadu64), hash(1)];
+
+	let bodies = client.block_bodies(&hashes);
+	let receipts = client.block_receipts_list(&hashes);
+
+	assert_eq!(bodies.len(), 3);
+	assert_eq!(bodies[0], client.block_body(BlockID::Number(3)));
+	assert_eq!(bodies[1], None);
+	assert_eq!(bodies[2], client.block_body(BlockID::Number(1)));
+	assert_eq!(receipts, vec![client.block_receipts(&hashes[0]), None, client.block_receipts(&hashes[2])]);
+	assert!(receipts[0].is_some());
+}
+
 #[test]
 fn imports_block_sequence() {
 	let client_result = generate_dummy_client(6);
@@ -136,8 +156,300 @@ fn can_mine() {
 	let client_result = get_test_client_with_blocks(vec![dummy_blocks[0].clone()]);
 	let client = client_result.reference();
 
-	let b = client.prepare_sealing(Address::default(), x!(31415926), vec![], vec![]).0.unwrap();
+	let b = client.prepare_sealing(Address::default(), GasLimitTarget::fixed(x!(31415926)), vec![], true, vec![]).0.unwrap();
 
 	assert_eq!(*b.block().header().parent_hash(), BlockView::new(&dummy_blocks[0]).header_view().sha3());
 	assert!(client.try_seal(b.lock(), vec![]).is_ok());
 }
+
+#[test]
+fn can_export_and_import_state() {
+	// given
+	let client_result = generate_dummy_client(6);
+	let client = client_result.reference();
+	let mut data = Vec::new();
+	client.export_state(BlockID::Latest, &mut data, &mut |_| true).unwrap();
+	let root = HeaderView::new(&client.best_block_header()).state_root();
+
+	let dir = RandomTempPath::new();
+	let fresh = Client::new(ClientConfig::default(), get_test_spec(), dir.as_path(), IoChannel::disconnected()).unwrap();
+
+	// when
+	fresh.import_state(&mut &data[..], &root, &mut |_| true).unwrap();
+	push_blocks_to_client(&fresh, 40, 7, 2);
+	fresh.flush_queue();
+	fresh.import_verified_blocks(&IoChannel::disconnected());
+
+	// then
+	assert_eq!(fresh.state_import_checkpoint(), None);
+	assert_eq!(fresh.block_hash(BlockID::Number(6)), client.block_hash(BlockID::Number(6)));
+	assert_eq!(fresh.block_hash(BlockID::Number(5)), None);
+	assert_eq!(fresh.chain_info().best_block_number, 8);
+	let address = Address::from_str("102e61f5d8f9bc71d0ad4a084df4e65e05ce0e1c").unwrap();
+	assert_eq!(fresh.balance(&address), client.balance(&address));
+}
+
+#[test]
+fn rejects_state_of_unexpected_root() {
+	// given
+	let client_result = generate_dummy_client(2);
+	let client = client_result.reference();
+	let mut data = Vec::new();
+	client.export_state(BlockID::Latest, &mut data, &mut |_| true).unwrap();
+	let dir = RandomTempPath::new();
+	let fresh = Client::new(ClientConfig::default(), get_test_spec(), dir.as_path(), IoChannel::disconnected()).unwrap();
+
+	// when
+	let result = fresh.import_state(&mut &data[..], &H256::from(1), &mut |_| true);
+
+	// then
+	assert!(result.is_err());
+	assert_eq!(fresh.chain_info().best_block_number, 0);
+}
+
+fn no_seal_config() -> ClientConfig {
+	ClientConfig {
+		verifier_type: VerifierType::CanonNoSeal,
+		trusted_import: true,
+		..ClientConfig::default()
+	}
+}
+
+/// Creates a chain of `count` frontier blocks sealed with an invalid proof of work.
+fn blocks_with_invalid_seals(count: usize) -> Vec<Bytes> {
+	let dir = RandomTempPath::new();
+	let client = Client::new(no_seal_config(), ethereum::new_frontier_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+	let engine = ethereum::new_frontier_test().engine;
+	let seal = vec![rlp::encode(&H256::zero()).to_vec(), rlp::encode(&H64::zero()).to_vec()];
+	(0..count).map(|i| {
+		let b = client.prepare_sealing(Address::default(), GasLimitTarget::fixed(x!(31415926)), vec![i as u8], true, vec![]).0.unwrap();
+		let bytes = b.lock().seal(&*engine, seal.clone()).unwrap().rlp_bytes();
+		client.import_trusted_block(bytes.clone()).unwrap();
+		bytes
+	}).collect()
+}
+
+#[test]
+fn includes_valid_uncles_in_prepared_block_only_if_requested() {
+	// given
+	let dir = RandomTempPath::new();
+	let client = Client::new(no_seal_config(), ethereum::new_frontier_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+	let engine = ethereum::new_frontier_test().engine;
+	let seal = vec![rlp::encode(&H256::zero()).to_vec(), rlp::encode(&H64::zero()).to_vec()];
+	let b = client.prepare_sealing(Address::default(), GasLimitTarget::fixed(x!(31415926)), vec![1], false, vec![]).0.unwrap();
+	let parent = b.lock().seal(&*engine, seal).unwrap();
+	let mut uncle = parent.header().clone();
+	uncle.extra_data = vec![2];
+	client.import_trusted_block(parent.rlp_bytes()).unwrap();
+	client.import_trusted_block(create_test_block(&uncle)).unwrap();
+	let author = Address::from(1);
+	let reward = U256::from_str("4563918244f40000").unwrap();
+
+	// when
+	let without_uncles = client.prepare_sealing(author.clone(), GasLimitTarget::fixed(x!(31415926)), vec![], false, vec![]).0.unwrap();
+	let with_uncles = client.prepare_sealing(author.clone(), GasLimitTarget::fixed(x!(31415926)), vec![], true, vec![]).0.unwrap();
+
+	// then
+	assert_eq!(without_uncles.uncles().len(), 0);
+	assert_eq!(without_uncles.state().balance(&author), reward);
+	assert_eq!(with_uncles.uncles().len(), 1);
+	assert_eq!(with_uncles.uncles()[0].hash(), uncle.hash());
+	assert_eq!(with_uncles.state().balance(&author), reward + reward / U256::from(32));
+	assert_eq!(with_uncles.state().balance(&Address::default()), reward + reward * U256::from(7) / U256::from(8));
+}
+
+#[test]
+fn imports_invalid_seals_only_without_seal_verification() {
+	// given
+	let blocks = blocks_with_invalid_seals(3);
+	let (canon_dir, no_seal_dir) = (RandomTempPath::new(), RandomTempPath::new());
+	let canon = Client::new(ClientConfig::default(), ethereum::new_frontier_test(), canon_dir.as_path(), IoChannel::disconnected()).unwrap();
+	let no_seal = Client::new(no_seal_config(), ethereum::new_frontier_test(), no_seal_dir.as_path(), IoChannel::disconnected()).unwrap();
+
+	// when
+	let canon_results = blocks.iter().map(|b| canon.import_block(b.clone())).collect::<Vec<_>>();
+	canon.flush_queue();
+	canon.import_verified_blocks(&IoChannel::disconnected());
+	for b in &blocks {
+		no_seal.import_block(b.clone()).unwrap();
+	}
+	no_seal.flush_queue();
+	no_seal.import_verified_blocks(&IoChannel::disconnected());
+
+	// then
+	assert!(canon_results.iter().all(Result::is_err));
+	assert_eq!(canon.chain_info().best_block_number, 0);
+	assert_eq!(no_seal.chain_info().best_block_number, 3);
+	assert_eq!(no_seal.chain_info().best_block_hash, BlockView::new(&blocks[2]).header_view().sha3());
+}
+
+#[test]
+fn trusted_import_verifies_state_root() {
+	let dir = RandomTempPath::new();
+	let client = Client::new(ClientConfig::default(), get_test_spec(), dir.as_path(), IoChannel::disconnected()).unwrap();
+
+	assert!(client.import_trusted_block(get_bad_state_dummy_block()).is_err());
+	assert!(client.import_trusted_block(create_test_block(&Header::default())).is_err());
+	assert_eq!(client.chain_info().best_block_number, 0);
+	assert!(client.import_trusted_block(get_good_dummy_block()).is_ok());
+	assert_eq!(client.chain_info().best_block_number, 1);
+}
+
+#[test]
+fn rejects_unverified_import_for_live_sync() {
+	let dir = RandomTempPath::new();
+	let config = ClientConfig { verifier_type: VerifierType::Noop, ..ClientConfig::default() };
+
+	assert!(Client::new(config, get_test_spec(), dir.as_path(), IoChannel::disconnected()).is_err());
+}
+
+/// Seals new block on top of the best block without importing it.
+fn sealed_block(client: &Client, extra_data: Bytes) -> Bytes {
+	let b = client.prepare_sealing(Address::default(), GasLimitTarget::fixed(x!(31415926)), extra_data, true, vec![]).0.unwrap();
+	client.try_seal(b.lock(), vec![]).ok().unwrap().rlp_bytes()
+}
+
+fn import_blocks(client: &Client, blocks: Vec<Bytes>) {
+	for block in blocks {
+		client.import_block(block).unwrap();
+	}
+	client.flush_queue();
+	client.import_verified_blocks(&IoChannel::disconnected());
+}
+
+fn seal_block(client: &Client, extra_data: Bytes) -> H256 {
+	let block = sealed_block(client, extra_data);
+	let hash = BlockView::new(&block).header_view().sha3();
+	import_blocks(client, vec![block]);
+	hash
+}
+
+/// Creates client with block `1` having a sibling which is included as an uncle in block `2`.
+/// Returns hashes of the uncle and of the block including it.
+fn client_with_uncle(dir: &RandomTempPath, index_uncles: bool) -> (Arc<Client>, H256, H256) {
+	let mut config = ClientConfig::default();
+	config.blockchain.index_uncles = index_uncles;
+	let client = Client::new(config, get_test_spec(), dir.as_path(), IoChannel::disconnected()).unwrap();
+	let siblings = vec![sealed_block(&client, vec![1]), sealed_block(&client, vec![2])];
+	let hashes = siblings.iter().map(|block| BlockView::new(block).header_view().sha3()).collect::<Vec<_>>();
+	import_blocks(&client, siblings);
+	let best = client.chain_info().best_block_hash;
+	assert!(hashes.contains(&best));
+	let uncle = hashes.into_iter().find(|hash| *hash != best).unwrap();
+	let nephew = seal_block(&client, vec![]);
+	(client, uncle, nephew)
+}
+
+#[test]
+fn returns_uncle_info_by_hash_and_number() {
+	// given
+	let dir = RandomTempPath::new();
+	let (client, uncle, nephew) = client_with_uncle(&dir, true);
+	assert_eq!(client.chain_info().best_block_hash, nephew);
+
+	// when
+	let by_hash = client.uncle_extra_info(UncleID(BlockID::Hash(nephew.clone()), 0)).unwrap();
+	let by_number = client.uncle_extra_info(UncleID(BlockID::Number(2), 0)).unwrap();
+
+	// then
+	assert_eq!(by_hash, by_number);
+	assert_eq!(by_hash.hash, uncle);
+	assert_eq!(by_hash.number, 1);
+	assert_eq!(by_hash.nephew_hash, nephew);
+	assert_eq!(by_hash.nephew_number, 2);
+	assert_eq!(by_hash.index, 0);
+	let header = client.uncle(UncleID(BlockID::Number(2), 0)).unwrap();
+	assert_eq!(by_hash.reward, get_test_spec().engine.uncle_reward(&header, 2));
+	assert_eq!(client.uncle_extra_info(UncleID(BlockID::Number(2), 1)), None);
+	assert_eq!(client.uncle_extra_info(UncleID(BlockID::Number(3), 0)), None);
+}
+
+#[test]
+fn returns_uncle_position_from_index() {
+	// given
+	let dir = RandomTempPath::new();
+	let (client, uncle, nephew) = client_with_uncle(&dir, true);
+
+	// then
+	assert_eq!(client.uncle_position(&uncle), Some(UncleID(BlockID::Hash(nephew), 0)));
+	assert_eq!(client.uncle_position(&H256::from(1)), None);
+}
+
+#[test]
+fn backfills_uncle_index() {
+	// given
+	let dir = RandomTempPath::new();
+	let (client, uncle, nephew) = client_with_uncle(&dir, false);
+	assert_eq!(client.uncle_position(&uncle), None);
+
+	// when
+	let indexed = client.backfill_uncle_index(0, 10);
+
+	// then
+	assert_eq!(indexed, 1);
+	assert_eq!(client.uncle_position(&uncle), Some(UncleID(BlockID::Hash(nephew), 0)));
+}
+
+#[test]
+fn splits_memory_budget_between_caches() {
+	// given
+	let dir = RandomTempPath::new();
+	let mut config = ClientConfig::default();
+	config.memory_budget = Some(MemoryBudgetConfig::new(4 * 1024 * 1024));
+
+	// when
+	let client = Client::new(config, get_test_spec(), dir.as_path(), IoChannel::disconnected()).unwrap();
+	client.tick();
+
+	// then
+	let assignments = client.report().memory_budget;
+	assert_eq!(assignments.len(), 3);
+	assert!(assignments.iter().fold(0, |sum, a| sum + a.assigned) <= 4 * 1024 * 1024);
+	assert_eq!(client.queue_info().max_mem_use, 1024 * 1024);
+}
+
+#[test]
+fn reindexes_trace_blooms_on_request() {
+	use bloomchain::Config as BloomConfig;
+	use client::{Switch, Error as ClientError};
+	use trace::Error as TraceError;
+
+	// given
+	let dir = RandomTempPath::new();
+	let config = |levels, reindex| {
+		let mut config = ClientConfig::default();
+		config.tracing.enabled = Switch::On;
+		config.tracing.blooms = BloomConfig { levels: levels, elements_per_index: 16 };
+		config.tracing.reindex = reindex;
+		config
+	};
+	drop(Client::new(config(3, false), get_test_spec(), dir.as_path(), IoChannel::disconnected()).unwrap());
+
+	// when
+	let not_reindexed = Client::new(config(2, false), get_test_spec(), dir.as_path(), IoChannel::disconnected());
+	let reindexed = Client::new(config(2, true), get_test_spec(), dir.as_path(), IoChannel::disconnected());
+
+	// then
+	match not_reindexed {
+		Err(ClientError::Trace(TraceError::ReindexRequired { stored, requested })) => {
+			assert_eq!(stored, (3, 16));
+			assert_eq!(requested, (2, 16));
+		},
+		_ => panic!("Expected reindexing to be required."),
+	}
+	assert!(reindexed.is_ok());
+}
+
+#[test]
+fn iterates_over_ancestry() {
+	let client_result = generate_dummy_client(6);
+	let client = client_result.reference();
+	let hash = |n| client.block_hash(BlockID::Number(n)).unwrap();
+
+	let ancestry: Vec<_> = client.ancestry_iter(BlockID::Number(4)).unwrap().collect();
+	assert_eq!(ancestry, vec![hash(4), hash(3), hash(2), hash(1), hash(0)]);
+	assert!(client.ancestry_iter(BlockID::Hash(H256::from(123))).is_none());
+
+	assert_eq!(client.common_ancestor(hash(6), hash(3)), Some(hash(3)));
+	assert_eq!(client.common_ancestor(hash(2), hash(2)), Some(hash(2)));
+}
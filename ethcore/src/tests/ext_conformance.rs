@@ -0,0 +1,564 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Conformance of externalities.
+//!
+//! Small programs are executed by the interpreter against `MockExt` and compared with expected
+//! interaction traces (queries are left out). Programs touching `CALL*` are then executed with `Executive`
+//! against real `Externalities` to check that the recorded parameters have the intended effect.
+//!
+//! All programs run as `0x0a` (called by `0x05`), `0x0b` and `0x0c` are other accounts.
+
+use common::*;
+use state::*;
+use engine::*;
+use executive::*;
+use externalities::*;
+use substate::*;
+use evm::{self, Ext, Factory, VMType, MockExt, Invocation, CallResponse, CreateResponse};
+use trace::{NoopTracer, NoopVMTracer};
+use tests::helpers::*;
+
+/// Stores `CALLER`, `CALLVALUE` and `ADDRESS` in slots 0, 1 and 2.
+const CONTEXT_RECORDER: &'static str = "336000553460015530600255";
+/// Returns `0x1234`.
+const RETURNER: &'static str = "6112346000526002601ef3";
+/// `CALL` to `0x0b` with 70000 gas and value 0x10, result stored in slot 3.
+const CALL_WITH_VALUE: &'static str = "60006000600060006010600b62011170f1600355";
+/// `CALL` to `0x0b` with 70000 gas and no value, result stored in slot 3.
+const CALL_WITHOUT_VALUE: &'static str = "60006000600060006000600b62011170f1600355";
+/// `CALLCODE` of `0x0b` with 70000 gas and value 0x10, result stored in slot 3.
+const CALLCODE_WITH_VALUE: &'static str = "60006000600060006010600b62011170f2600355";
+/// `DELEGATECALL` of `0x0b` with 70000 gas, result stored in slot 3.
+const DELEGATECALL: &'static str = "6000600060006000600b62011170f4600355";
+/// `CALL` to `0x0b` with 70000 gas reading 2 bytes of output, output stored in slot 1.
+const CALL_WITH_OUTPUT: &'static str = "60026000600060006000600b62011170f150600051600155";
+
+const GAS: u64 = 200_000;
+
+fn contract() -> Address { Address::from(0xa) }
+fn callee() -> Address { Address::from(0xb) }
+fn third() -> Address { Address::from(0xc) }
+fn sender() -> Address { Address::from(0x5) }
+
+fn word(value: u64) -> H256 {
+	H256::from(value)
+}
+
+fn params(code: &str, value: U256) -> ActionParams {
+	let mut params = ActionParams::default();
+	params.address = contract();
+	params.code_address = contract();
+	params.sender = sender();
+	params.origin = sender();
+	params.gas = U256::from(GAS);
+	params.value = ActionValue::Transfer(value);
+	params.code = Some(code.from_hex().unwrap());
+	params
+}
+
+/// Executes `code` with the interpreter against `ext` and returns recorded interactions.
+fn run_mock(code: &str, ext: &mut MockExt) -> Vec<Invocation> {
+	let vm = Factory::new(VMType::Interpreter).create();
+	vm.exec(params(code, U256::zero()), ext).unwrap();
+	ext.interactions()
+}
+
+/// Calls `code` as `contract()` from `sender()` with given value using `Executive`.
+fn run_real(engine: &Engine, state: &mut State, code: &str, value: U256) -> evm::Result {
+	let info = EnvInfo::default();
+	let factory = Factory::new(VMType::Interpreter);
+	let mut substate = Substate::new();
+	let mut output = vec![];
+	let mut ex = Executive::new(state, &info, engine, &factory);
+	ex.call(params(code, value), &mut substate, BytesRef::Flexible(&mut output), &mut NoopTracer, &mut NoopVMTracer)
+}
+
+/// Calls `ret` of real `Externalities` with given output policy.
+fn ret_with_policy(engine: &Engine, state: &mut State, output: OutputPolicy, gas: u64, data: &[u8]) -> evm::Result {
+	let info = EnvInfo::default();
+	let factory = Factory::new(VMType::Interpreter);
+	let mut substate = Substate::new();
+	let mut tracer = NoopTracer;
+	let mut vm_tracer = NoopVMTracer;
+	let origin_info = OriginInfo::from(&params("", U256::zero()));
+	let mut ext = Externalities::new(state, &info, engine, &factory, 0, origin_info, &mut substate, output, &mut tracer, &mut vm_tracer);
+	ext.ret(&U256::from(gas), data)
+}
+
+/// `PUSH1 0x2a PUSH1 0 SSTORE`
+///
+/// Trace: `SetStorage(0, 0x2a)`
+#[test]
+fn sstore_sets_storage() {
+	let mut ext = MockExt::new();
+
+	let trace = run_mock("602a600055", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::SetStorage(word(0), word(0x2a))]);
+}
+
+/// `PUSH1 0 PUSH1 0 SSTORE` with slot 0 set to 1.
+///
+/// Trace: `IncSstoreClears`, `SetStorage(0, 0)`
+#[test]
+fn sstore_clear_increments_refund_counter() {
+	let mut ext = MockExt::new();
+	ext.storage.insert(word(0), word(1));
+
+	let trace = run_mock("6000600055", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::IncSstoreClears, Invocation::SetStorage(word(0), H256::zero())]);
+}
+
+/// `PUSH1 0 SLOAD PUSH1 1 SSTORE` with slot 0 set to 5.
+///
+/// Trace: `SetStorage(1, 5)`
+#[test]
+fn sload_reads_storage() {
+	let mut ext = MockExt::new();
+	ext.storage.insert(word(0), word(5));
+
+	let trace = run_mock("600054600155", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::SetStorage(word(1), word(5))]);
+}
+
+/// `PUSH1 0x10 BLOCKHASH PUSH1 0 SSTORE`
+///
+/// Trace: `SetStorage(0, hash of block 0x10)`
+#[test]
+fn blockhash_reads_block_hashes() {
+	let hash = H256::from_str("abababababababababababababababababababababababababababababababab").unwrap();
+	let mut ext = MockExt::new();
+	ext.blockhashes.insert(U256::from(0x10), hash.clone());
+
+	let trace = run_mock("601040600055", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::SetStorage(word(0), hash)]);
+}
+
+/// `PUSH1 0x0b BALANCE PUSH1 0 SSTORE`
+///
+/// Trace: `SetStorage(0, balance of 0x0b)`
+#[test]
+fn balance_reads_balance_of_other_account() {
+	let mut ext = MockExt::new();
+	ext.balances.insert(callee(), U256::from(7));
+
+	let trace = run_mock("600b31600055", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::SetStorage(word(0), word(7))]);
+}
+
+/// `PUSH1 0xff PUSH1 0 MSTORE8 PUSH1 0xbb PUSH1 0xaa PUSH1 0x20 PUSH1 0 LOG2`
+///
+/// Trace: `Log([0xaa, 0xbb], ff00..00)`
+#[test]
+fn log_passes_topics_and_memory() {
+	let mut ext = MockExt::new();
+	let mut data = vec![0u8; 32];
+	data[0] = 0xff;
+
+	let trace = run_mock("60ff60005360bb60aa60206000a2", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::Log { topics: vec![word(0xaa), word(0xbb)], data: data }]);
+}
+
+/// `PUSH1 0x2a PUSH1 0 MSTORE PUSH1 0x20 PUSH1 0 RETURN`
+///
+/// Trace: `Ret(00..002a)`
+#[test]
+fn return_passes_memory() {
+	let mut ext = MockExt::new();
+	let mut data = vec![0u8; 32];
+	data[31] = 0x2a;
+
+	let trace = run_mock("602a60005260206000f3", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::Ret(data)]);
+}
+
+/// `PUSH1 0x0b SUICIDE`
+///
+/// Trace: `Suicide(0x0b)`
+#[test]
+fn suicide_passes_refund_address() {
+	let mut ext = MockExt::new();
+
+	let trace = run_mock("600bff", &mut ext);
+
+	assert_eq!(trace, vec![Invocation::Suicide(callee())]);
+}
+
+/// `PUSH1 0xff PUSH1 0 MSTORE8 PUSH1 1 PUSH1 0 PUSH1 0x10 CREATE PUSH1 0 SSTORE`
+///
+/// Trace: `Create(gas left after CREATE, 0x10, ff)`, `SetStorage(0, 0x0c)`
+#[test]
+fn create_passes_endowment_and_init_code() {
+	let mut ext = MockExt::new();
+	ext.balances.insert(contract(), U256::from(100));
+	ext.create_responses.push_back(CreateResponse::Created(third()));
+
+	let trace = run_mock("60ff600053600160006010f0600055", &mut ext);
+
+	assert_eq!(trace, vec![
+		// 5 pushes, MSTORE8 with memory expansion and CREATE itself
+		Invocation::Create { gas: U256::from(GAS - 5 * 3 - 6 - 32000), value: U256::from(0x10), code: vec![0xff] },
+		Invocation::SetStorage(word(0), word(0xc)),
+	]);
+}
+
+/// `CALL_WITH_VALUE`
+///
+/// Trace: `Call(70000 + stipend, 0x0a -> 0x0b, 0x10, code of 0x0b)`, `SetStorage(3, 1)`
+#[test]
+fn call_transfers_value_with_stipend() {
+	let mut ext = MockExt::new();
+	ext.balances.insert(contract(), U256::from(100));
+
+	let trace = run_mock(CALL_WITH_VALUE, &mut ext);
+
+	assert_eq!(trace, vec![
+		Invocation::Call {
+			gas: U256::from(72_300),
+			sender: contract(),
+			receiver: callee(),
+			value: Some(U256::from(0x10)),
+			data: vec![],
+			code_address: callee(),
+		},
+		Invocation::SetStorage(word(3), word(1)),
+	]);
+}
+
+#[test]
+fn real_call_transfers_value() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	state.init_code(&callee(), CONTEXT_RECORDER.from_hex().unwrap());
+	state.add_balance(&contract(), &U256::from(100));
+	let engine = TestEngine::new(1024);
+
+	run_real(&engine, &mut state, CALL_WITH_VALUE, U256::zero()).unwrap();
+
+	assert_eq!(state.storage_at(&callee(), &word(0)), word(0xa));
+	assert_eq!(state.storage_at(&callee(), &word(1)), word(0x10));
+	assert_eq!(state.storage_at(&callee(), &word(2)), word(0xb));
+	assert_eq!(state.storage_at(&contract(), &word(3)), word(1));
+	assert_eq!(state.balance(&contract()), U256::from(100 - 0x10));
+	assert_eq!(state.balance(&callee()), U256::from(0x10));
+}
+
+/// `PUSH1 0xff PUSH1 0 MSTORE8` followed by `CALL` to `0x0b` with one byte of input and no value.
+///
+/// Trace: `Call(70000, 0x0a -> 0x0b, 0, ff, code of 0x0b)`, `SetStorage(3, 1)`
+#[test]
+fn call_without_value_passes_data_without_stipend() {
+	let mut ext = MockExt::new();
+
+	let trace = run_mock("60ff60005360006000600160006000600b62011170f1600355", &mut ext);
+
+	assert_eq!(trace, vec![
+		Invocation::Call {
+			gas: U256::from(70_000),
+			sender: contract(),
+			receiver: callee(),
+			value: Some(U256::zero()),
+			data: vec![0xff],
+			code_address: callee(),
+		},
+		Invocation::SetStorage(word(3), word(1)),
+	]);
+}
+
+/// `CALLCODE_WITH_VALUE`
+///
+/// Trace: `Call(70000 + stipend, 0x0a -> 0x0a, 0x10, code of 0x0b)`, `SetStorage(3, 1)`
+#[test]
+fn callcode_executes_foreign_code_on_own_account() {
+	let mut ext = MockExt::new();
+	ext.balances.insert(contract(), U256::from(100));
+
+	let trace = run_mock(CALLCODE_WITH_VALUE, &mut ext);
+
+	assert_eq!(trace, vec![
+		Invocation::Call {
+			gas: U256::from(72_300),
+			sender: contract(),
+			receiver: contract(),
+			value: Some(U256::from(0x10)),
+			data: vec![],
+			code_address: callee(),
+		},
+		Invocation::SetStorage(word(3), word(1)),
+	]);
+}
+
+#[test]
+fn real_callcode_uses_own_storage_and_balance() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	state.init_code(&callee(), CONTEXT_RECORDER.from_hex().unwrap());
+	state.add_balance(&contract(), &U256::from(100));
+	let engine = TestEngine::new(1024);
+
+	run_real(&engine, &mut state, CALLCODE_WITH_VALUE, U256::zero()).unwrap();
+
+	assert_eq!(state.storage_at(&contract(), &word(0)), word(0xa));
+	assert_eq!(state.storage_at(&contract(), &word(1)), word(0x10));
+	assert_eq!(state.storage_at(&contract(), &word(2)), word(0xa));
+	assert_eq!(state.storage_at(&contract(), &word(3)), word(1));
+	assert_eq!(state.storage_at(&callee(), &word(0)), H256::zero());
+	assert_eq!(state.balance(&contract()), U256::from(100));
+	assert_eq!(state.balance(&callee()), U256::zero());
+}
+
+/// `DELEGATECALL`
+///
+/// Trace: `Call(70000, 0x05 -> 0x0a, no value, code of 0x0b)`, `SetStorage(3, 1)`
+#[test]
+fn delegatecall_keeps_sender_and_passes_no_value() {
+	let mut ext = MockExt::new_homestead();
+
+	let trace = run_mock(DELEGATECALL, &mut ext);
+
+	assert_eq!(trace, vec![
+		Invocation::Call {
+			gas: U256::from(70_000),
+			sender: sender(),
+			receiver: contract(),
+			value: None,
+			data: vec![],
+			code_address: callee(),
+		},
+		Invocation::SetStorage(word(3), word(1)),
+	]);
+}
+
+#[test]
+fn delegatecall_is_rejected_before_homestead() {
+	let mut ext = MockExt::new();
+	let vm = Factory::new(VMType::Interpreter).create();
+
+	let result = vm.exec(params(DELEGATECALL, U256::zero()), &mut ext);
+
+	match result {
+		Err(evm::Error::BadInstruction { .. }) => {},
+		other => panic!("Expected BadInstruction, got: {:?}", other),
+	}
+	assert_eq!(ext.interactions(), vec![]);
+}
+
+#[test]
+fn real_delegatecall_uses_apparent_value() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	state.init_code(&callee(), CONTEXT_RECORDER.from_hex().unwrap());
+	state.add_balance(&sender(), &U256::from(100));
+	let spec = get_test_spec();
+
+	run_real(&*spec.engine, &mut state, DELEGATECALL, U256::from(0x20)).unwrap();
+
+	assert_eq!(state.storage_at(&contract(), &word(0)), word(0x5));
+	assert_eq!(state.storage_at(&contract(), &word(1)), word(0x20));
+	assert_eq!(state.storage_at(&contract(), &word(2)), word(0xa));
+	assert_eq!(state.storage_at(&contract(), &word(3)), word(1));
+	// value was transferred only once, by the outer call
+	assert_eq!(state.balance(&sender()), U256::from(100 - 0x20));
+	assert_eq!(state.balance(&contract()), U256::from(0x20));
+	assert_eq!(state.balance(&callee()), U256::zero());
+}
+
+/// `CALL_WITH_OUTPUT` with callee returning `1234`.
+///
+/// Trace: `Call(70000, 0x0a -> 0x0b, 0, code of 0x0b)`, `SetStorage(1, 1234..00)`
+#[test]
+fn call_output_is_written_to_memory() {
+	let output = H256::from_str("1234000000000000000000000000000000000000000000000000000000000000").unwrap();
+	let mut ext = MockExt::new();
+	ext.call_responses.push_back(CallResponse::Success(vec![0x12, 0x34]));
+
+	let trace = run_mock(CALL_WITH_OUTPUT, &mut ext);
+
+	assert_eq!(trace, vec![
+		Invocation::Call {
+			gas: U256::from(70_000),
+			sender: contract(),
+			receiver: callee(),
+			value: Some(U256::zero()),
+			data: vec![],
+			code_address: callee(),
+		},
+		Invocation::SetStorage(word(1), output),
+	]);
+}
+
+#[test]
+fn real_call_output_is_written_to_memory() {
+	let output = H256::from_str("1234000000000000000000000000000000000000000000000000000000000000").unwrap();
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	state.init_code(&callee(), RETURNER.from_hex().unwrap());
+	let engine = TestEngine::new(1024);
+
+	run_real(&engine, &mut state, CALL_WITH_OUTPUT, U256::zero()).unwrap();
+
+	assert_eq!(state.storage_at(&contract(), &word(1)), output);
+}
+
+/// `CALL_WITHOUT_VALUE` with failing callee.
+///
+/// Trace: `Call(70000, 0x0a -> 0x0b, 0, code of 0x0b)`, `SetStorage(3, 0)`
+#[test]
+fn failed_call_pushes_zero() {
+	let mut ext = MockExt::new();
+	ext.call_responses.push_back(CallResponse::Failed);
+
+	let trace = run_mock(CALL_WITHOUT_VALUE, &mut ext);
+
+	assert_eq!(trace, vec![
+		Invocation::Call {
+			gas: U256::from(70_000),
+			sender: contract(),
+			receiver: callee(),
+			value: Some(U256::zero()),
+			data: vec![],
+			code_address: callee(),
+		},
+		Invocation::SetStorage(word(3), H256::zero()),
+	]);
+}
+
+/// `CALL_WITHOUT_VALUE` at maximal depth.
+///
+/// Trace: `SetStorage(3, 0)`
+#[test]
+fn call_at_max_depth_is_not_made() {
+	let mut ext = MockExt::new();
+	ext.depth = ext.schedule.max_depth;
+
+	let trace = run_mock(CALL_WITHOUT_VALUE, &mut ext);
+
+	assert_eq!(trace, vec![Invocation::SetStorage(word(3), H256::zero())]);
+}
+
+/// `CALL_WITH_VALUE` without sufficient balance.
+///
+/// Trace: `SetStorage(3, 0)`
+#[test]
+fn call_without_balance_is_not_made() {
+	let mut ext = MockExt::new();
+
+	let trace = run_mock(CALL_WITH_VALUE, &mut ext);
+
+	assert_eq!(trace, vec![Invocation::SetStorage(word(3), H256::zero())]);
+}
+
+/// Runs `0x0a` calling `0x0b` calling `0x0c` with given maximal depth.
+fn run_call_chain(max_depth: usize) -> (H256, H256, H256) {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	// call to 0x0c with 30000 gas, result stored in slot 3
+	state.init_code(&callee(), "60006000600060006000600c617530f1600355".from_hex().unwrap());
+	state.init_code(&third(), "6001600055".from_hex().unwrap());
+	let engine = TestEngine::new(max_depth);
+
+	run_real(&engine, &mut state, CALL_WITHOUT_VALUE, U256::zero()).unwrap();
+
+	(
+		state.storage_at(&contract(), &word(3)),
+		state.storage_at(&callee(), &word(3)),
+		state.storage_at(&third(), &word(0)),
+	)
+}
+
+#[test]
+fn real_calls_increment_depth() {
+	assert_eq!(run_call_chain(2), (word(1), word(1), word(1)));
+	// 0x0b runs at depth 1 and can't call any deeper
+	assert_eq!(run_call_chain(1), (word(1), H256::zero(), H256::zero()));
+}
+
+#[test]
+fn return_policy_copies_to_fixed_output() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	let engine = TestEngine::new(1024);
+	let mut output = [0u8; 2];
+	let mut copy = vec![];
+
+	let gas_left = ret_with_policy(&engine, &mut state, OutputPolicy::Return(BytesRef::Fixed(&mut output), Some(&mut copy)), 100, &[1, 2, 3]);
+
+	assert_eq!(gas_left.unwrap(), U256::from(100));
+	assert_eq!(output, [1, 2]);
+	assert_eq!(copy, vec![1, 2, 3]);
+}
+
+#[test]
+fn return_policy_replaces_flexible_output() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	let engine = TestEngine::new(1024);
+	let mut output = vec![9u8; 5];
+
+	let gas_left = ret_with_policy(&engine, &mut state, OutputPolicy::Return(BytesRef::Flexible(&mut output), None), 100, &[1, 2, 3]);
+
+	assert_eq!(gas_left.unwrap(), U256::from(100));
+	assert_eq!(output, vec![1, 2, 3]);
+}
+
+#[test]
+fn init_contract_policy_deposits_code() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	let engine = TestEngine::new(1024);
+	let mut copy = vec![];
+
+	let gas_left = ret_with_policy(&engine, &mut state, OutputPolicy::InitContract(Some(&mut copy)), 1000, &[1, 2, 3]);
+
+	// create_data_gas is 200 per byte
+	assert_eq!(gas_left.unwrap(), U256::from(400));
+	assert_eq!(copy, vec![1, 2, 3]);
+	assert_eq!(state.code(&contract()), Some(vec![1, 2, 3]));
+}
+
+#[test]
+fn init_contract_policy_without_gas_skips_deposit_on_frontier() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	let engine = TestEngine::new(1024);
+
+	let gas_left = ret_with_policy(&engine, &mut state, OutputPolicy::InitContract(None), 100, &[1, 2, 3]);
+
+	assert_eq!(gas_left.unwrap(), U256::from(100));
+	assert_eq!(state.code(&contract()), None);
+}
+
+#[test]
+fn init_contract_policy_without_gas_fails_on_homestead() {
+	let mut state_result = get_temp_state();
+	let mut state = state_result.reference_mut();
+	let spec = get_test_spec();
+
+	let gas_left = ret_with_policy(&*spec.engine, &mut state, OutputPolicy::InitContract(None), 100, &[1, 2, 3]);
+
+	match gas_left {
+		Err(evm::Error::OutOfGas) => {},
+		other => panic!("Expected OutOfGas, got: {:?}", other),
+	}
+	assert_eq!(state.code(&contract()), None);
+}
@@ -15,4 +15,5 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 pub mod helpers;
-mod client;
\ No newline at end of file
+mod client;
+mod ext_conformance;
\ No newline at end of file
@@ -1,3 +1,4 @@
+use util::HeapSizeOf;
 use util::rlp::*;
 use basic_types::LogBloom;
 use super::Trace;
@@ -18,6 +19,12 @@ impl Into<Vec<Trace>> for BlockTraces {
 	}
 }
 
+impl HeapSizeOf for BlockTraces {
+	fn heap_size_of_children(&self) -> usize {
+		self.0.heap_size_of_children()
+	}
+}
+
 impl Decodable for BlockTraces {
 	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
 		let traces = try!(Decodable::decode(decoder));
@@ -1,5 +1,7 @@
 use bloomchain::Bloom;
 use bloomchain::group::{BloomGroup, GroupPosition};
+use std::mem;
+use util::HeapSizeOf;
 use util::rlp::*;
 use basic_types::LogBloom;
 
@@ -33,6 +35,12 @@ pub struct BlockTracesBloomGroup {
 	blooms: Vec<BlockTracesBloom>,
 }
 
+impl HeapSizeOf for BlockTracesBloomGroup {
+	fn heap_size_of_children(&self) -> usize {
+		self.blooms.capacity() * mem::size_of::<BlockTracesBloom>()
+	}
+}
+
 impl From<BloomGroup> for BlockTracesBloomGroup {
 	fn from(group: BloomGroup) -> Self {
 		let blooms = group.blooms
@@ -46,8 +46,15 @@ pub struct Config {
 	/// Indicates if tracing should be enabled or not.
 	/// If it's None, it will be automatically configured.
 	pub enabled: Switch,
-	/// Traces blooms configuration.
+	/// Traces blooms configuration (number of levels and elements per index of the bloom index).
+	/// Changing it for existing database requires reindexing (see `TraceDB::reindex`).
 	pub blooms: BloomConfig,
+	/// Preferred cache size in bytes.
+	pub pref_cache_size: usize,
+	/// Maximum cache size in bytes.
+	pub max_cache_size: usize,
+	/// Rebuild blooms of existing traces with configured granularity when opening the database.
+	pub reindex: bool,
 }
 
 impl Default for Config {
@@ -57,7 +64,10 @@ impl Default for Config {
 			blooms: BloomConfig {
 				levels: 3,
 				elements_per_index: 16,
-			}
+			},
+			pref_cache_size: 15 * 1024 * 1024,
+			max_cache_size: 20 * 1024 * 1024,
+			reindex: false,
 		}
 	}
 }
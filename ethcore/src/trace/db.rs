@@ -15,15 +15,19 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Trace database.
-use std::ptr;
+use std::{mem, ptr};
 use std::ops::Deref;
+use std::hash::Hash;
 use std::collections::HashMap;
 use std::sync::{RwLock, Arc};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrder};
 use std::path::Path;
 use bloomchain::{Number, Config as BloomConfig};
 use bloomchain::group::{BloomGroupDatabase, BloomGroupChain, GroupPosition, BloomGroup};
-use util::{H256, H264, Database, DBTransaction};
+use util::{H256, H264, Database, DBTransaction, HeapSizeOf};
+use util::rlp::{RlpStream, UntrustedRlp, Stream, View};
 use header::BlockNumber;
+use basic_types::LogBloom;
 use trace::{BlockTraces, LocalizedTrace, Config, Switch, Filter, Database as TraceDatabase, ImportRequest,
 DatabaseExtras, Error};
 use db::{Key, Writable, Readable, CacheUpdatePolicy};
@@ -31,6 +35,8 @@ use super::bloom::{TraceGroupPosition, BlockTracesBloom, BlockTracesBloomGroup};
 use super::flat::{FlatTrace, FlatBlockTraces, FlatTransactionTraces};
 
 const TRACE_DB_VER: &'static [u8] = b"1.0";
+/// Number of blocks which blooms are rebuilt at once during reindexing.
+const REINDEX_BATCH_SIZE: usize = 1024;
 
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature="dev", allow(enum_variant_names))]
@@ -84,6 +90,8 @@ pub struct TraceDB<T> where T: DatabaseExtras {
 	// cache
 	traces: RwLock<HashMap<H256, BlockTraces>>,
 	blooms: RwLock<HashMap<TraceGroupPosition, BlockTracesBloomGroup>>,
+	pref_cache_size: AtomicUsize,
+	max_cache_size: AtomicUsize,
 	// db
 	tracesdb: Database,
 	// config,
@@ -101,9 +109,96 @@ impl<T> BloomGroupDatabase for TraceDB<T> where T: DatabaseExtras {
 	}
 }
 
+fn encode_bloom_config(config: &BloomConfig) -> Vec<u8> {
+	let mut stream = RlpStream::new_list(2);
+	stream.append(&config.levels);
+	stream.append(&config.elements_per_index);
+	stream.out()
+}
+
+/// Approximate memory used by cache entries.
+fn cache_entries_size<K, V>(cache: &HashMap<K, V>) -> usize where K: Eq + Hash, V: HeapSizeOf {
+	cache.values().fold(cache.len() * mem::size_of::<(K, V)>(), |sum, value| sum + value.heap_size_of_children())
+}
+
+fn decode_bloom_config(bytes: &[u8]) -> BloomConfig {
+	let rlp = UntrustedRlp::new(bytes);
+	match (rlp.val_at(0), rlp.val_at(1)) {
+		(Ok(levels), Ok(elements_per_index)) => BloomConfig {
+			levels: levels,
+			elements_per_index: elements_per_index,
+		},
+		_ => panic!("tracesdb is corrupted"),
+	}
+}
+
 impl<T> TraceDB<T> where T: DatabaseExtras {
 	/// Creates new instance of `TraceDB`.
+	/// Fails if blooms of existing traces were built with different configuration.
 	pub fn new(config: Config, path: &Path, extras: Arc<T>) -> Result<Self, Error> {
+		let (db, stored_blooms) = try!(Self::open(config, path, extras));
+		let requested = (db.bloom_config.levels, db.bloom_config.elements_per_index);
+		if db.enabled && (stored_blooms.levels, stored_blooms.elements_per_index) != requested {
+			return Err(Error::ReindexRequired {
+				stored: (stored_blooms.levels, stored_blooms.elements_per_index),
+				requested: requested,
+			});
+		}
+		db.tracesdb.put(b"blooms", &encode_bloom_config(&db.bloom_config)).unwrap();
+		Ok(db)
+	}
+
+	/// Set the cache configuration.
+	pub fn configure_cache(&self, pref_cache_size: usize, max_cache_size: usize) {
+		self.pref_cache_size.store(pref_cache_size, AtomicOrder::Relaxed);
+		self.max_cache_size.store(max_cache_size, AtomicOrder::Relaxed);
+	}
+
+	/// Returns number of bytes used by cached traces and blooms.
+	pub fn cache_size(&self) -> usize {
+		cache_entries_size(&self.traces.read().unwrap()) + cache_entries_size(&self.blooms.read().unwrap())
+	}
+
+	/// Ticks our cache system and throws out any old data.
+	/// Once the cache grows over the preferred size traces are evicted first, blooms only if it's still over the maximum.
+	pub fn collect_garbage(&self) {
+		let pref_cache_size = self.pref_cache_size.load(AtomicOrder::Relaxed);
+		if self.cache_size() < pref_cache_size {
+			return;
+		}
+
+		let mut traces = self.traces.write().unwrap();
+		let mut blooms = self.blooms.write().unwrap();
+		let mut size = cache_entries_size(&traces) + cache_entries_size(&blooms);
+		let hashes = traces.keys().cloned().collect::<Vec<_>>();
+		for hash in hashes {
+			if size < pref_cache_size {
+				break;
+			}
+			if let Some(removed) = traces.remove(&hash) {
+				size = size.saturating_sub(removed.heap_size_of_children() + mem::size_of::<(H256, BlockTraces)>());
+			}
+		}
+		if size >= self.max_cache_size.load(AtomicOrder::Relaxed) {
+			blooms.clear();
+		}
+		traces.shrink_to_fit();
+		blooms.shrink_to_fit();
+	}
+
+	/// Creates new instance of `TraceDB` rebuilding blooms of existing traces with configured granularity.
+	/// Traces of consecutive blocks (starting at genesis) known to `extras` are indexed.
+	pub fn reindex(config: Config, path: &Path, extras: Arc<T>) -> Result<Self, Error> {
+		let (db, _) = try!(Self::open(config, path, extras));
+		if db.enabled {
+			db.rebuild_blooms();
+		}
+		db.tracesdb.put(b"blooms", &encode_bloom_config(&db.bloom_config)).unwrap();
+		Ok(db)
+	}
+
+	/// Opens the database. Returns it together with configuration of stored blooms.
+	fn open(config: Config, path: &Path, extras: Arc<T>) -> Result<(Self, BloomConfig), Error> {
 		let mut tracedb_path = path.to_path_buf();
 		tracedb_path.push("tracedb");
 		let tracesdb = Database::open_default(tracedb_path.to_str().unwrap()).unwrap();
@@ -118,6 +213,13 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 
 		let enabled = try!(old_tracing.turn_to(config.enabled));
 
+		// databases created before blooms configuration was stored always used the default one
+		let stored_blooms = match tracesdb.get(b"blooms").unwrap() {
+			Some(ref value) => decode_bloom_config(value),
+			None if old_tracing == Switch::On => Config::default().blooms,
+			None => config.blooms,
+		};
+
 		let encoded_tracing = match enabled {
 			true => [0x1],
 			false => [0x0]
@@ -129,13 +231,60 @@ impl<T> TraceDB<T> where T: DatabaseExtras {
 		let db = TraceDB {
 			traces: RwLock::new(HashMap::new()),
 			blooms: RwLock::new(HashMap::new()),
+			pref_cache_size: AtomicUsize::new(config.pref_cache_size),
+			max_cache_size: AtomicUsize::new(config.max_cache_size),
 			tracesdb: tracesdb,
 			bloom_config: config.blooms,
 			enabled: enabled,
 			extras: extras,
 		};
 
-		Ok(db)
+		Ok((db, stored_blooms))
+	}
+
+	/// Removes all blooms and builds them again from stored traces.
+	fn rebuild_blooms(&self) {
+		let batch = DBTransaction::new();
+		for (key, _) in self.tracesdb.iter() {
+			if key.len() == 6 && key[0] == TraceDBIndex::BlockTracesBloomGroups as u8 {
+				batch.delete(&key).unwrap();
+			}
+		}
+		self.tracesdb.write(batch).unwrap();
+		self.blooms.write().unwrap().clear();
+
+		let mut number = 0;
+		loop {
+			let blooms = (number..number + REINDEX_BATCH_SIZE)
+				.map(|n| self.extras.block_hash(n as BlockNumber))
+				.take_while(Option::is_some)
+				.map(|hash| {
+					let hash = hash.expect("take_while stops at the first unknown block; qed");
+					// blocks without traces (e.g. genesis) have empty blooms
+					self.traces(&hash).map_or_else(LogBloom::default, |traces| traces.bloom())
+				})
+				.map(BlockTracesBloom::from)
+				.map(Into::into)
+				.collect::<Vec<_>>();
+			if blooms.is_empty() {
+				break;
+			}
+
+			let indexed = blooms.len();
+			let batch = DBTransaction::new();
+			{
+				let chain = BloomGroupChain::new(self.bloom_config, self);
+				let trace_blooms = chain.replace(&(number..number), blooms);
+				let blooms_to_insert = trace_blooms.into_iter()
+					.map(|p| (From::from(p.0), From::from(p.1)))
+					.collect::<HashMap<TraceGroupPosition, BlockTracesBloomGroup>>();
+
+				let mut blooms = self.blooms.write().unwrap();
+				batch.extend_with_cache(&mut blooms, blooms_to_insert, CacheUpdatePolicy::Remove);
+			}
+			self.tracesdb.write(batch).unwrap();
+			number += indexed;
+		}
 	}
 
 	/// Returns traces for block with hash.
@@ -353,7 +502,8 @@ mod tests {
 	use util::{Address, U256, H256};
 	use devtools::RandomTempPath;
 	use header::BlockNumber;
-	use trace::{Config, Switch, TraceDB, Database, DatabaseExtras, ImportRequest};
+	use bloomchain::Config as BloomConfig;
+	use trace::{Config, Switch, TraceDB, Database, DatabaseExtras, ImportRequest, Error};
 	use trace::{BlockTraces, Trace, Filter, LocalizedTrace, AddressesFilter};
 	use trace::trace::{Call, Action, Res};
 
@@ -582,4 +732,79 @@ mod tests {
 		assert_eq!(tracedb.trace(0, 0, vec![]).unwrap(), create_simple_localized_trace(0, block_0.clone(), tx_0.clone()));
 		assert_eq!(tracedb.trace(1, 0, vec![]).unwrap(), create_simple_localized_trace(1, block_1.clone(), tx_1.clone()));
 	}
+
+	#[test]
+	fn should_evict_traces_over_preferred_cache_size() {
+		// given
+		let temp = RandomTempPath::new();
+		let mut config = Config::default();
+		config.enabled = Switch::On;
+		let mut extras = Extras::default();
+		for number in 0..10 {
+			extras.block_hashes.insert(number, H256::from(number + 1));
+			extras.transaction_hashes.insert(number, vec![H256::from(number + 0x1000)]);
+		}
+		let tracedb = TraceDB::new(config, temp.as_path(), Arc::new(extras)).unwrap();
+		for number in 0..10 {
+			tracedb.import(create_simple_import_request(number, H256::from(number + 1)));
+		}
+		let size = tracedb.cache_size();
+		assert!(size > 0);
+
+		// when
+		tracedb.configure_cache(size / 2, size * 2);
+		tracedb.collect_garbage();
+
+		// then
+		assert!(tracedb.cache_size() < size / 2);
+		assert_eq!(tracedb.block_traces(3).unwrap()[0], create_simple_localized_trace(3, H256::from(4), H256::from(0x1003)));
+	}
+
+	#[test]
+	fn test_reindexing_blooms() {
+		// given
+		let temp = RandomTempPath::new();
+		let mut config = Config::default();
+		config.enabled = Switch::On;
+		let mut extras = Extras::default();
+		for number in 0..300 {
+			extras.block_hashes.insert(number, H256::from(number + 1));
+			extras.transaction_hashes.insert(number, vec![H256::from(number + 0x1000)]);
+		}
+		let extras = Arc::new(extras);
+		let filter = Filter {
+			range: (17..250),
+			from_address: AddressesFilter::from(vec![Address::from(1)]),
+			to_address: AddressesFilter::from(vec![]),
+		};
+		let traces = {
+			let tracedb = TraceDB::new(config.clone(), temp.as_path(), extras.clone()).unwrap();
+			for number in 0..300 {
+				tracedb.import(create_simple_import_request(number, H256::from(number + 1)));
+			}
+			tracedb.filter(&filter)
+		};
+		assert_eq!(traces.len(), 234);
+
+		// when
+		config.blooms = BloomConfig {
+			levels: 2,
+			elements_per_index: 8,
+		};
+		let reopened = TraceDB::new(config.clone(), temp.as_path(), extras.clone());
+		let reindexed = TraceDB::reindex(config.clone(), temp.as_path(), extras.clone()).unwrap();
+
+		// then
+		match reopened {
+			Err(Error::ReindexRequired { stored, requested }) => {
+				assert_eq!(stored, (3, 16));
+				assert_eq!(requested, (2, 8));
+			},
+			_ => panic!("Expected reindexing to be required."),
+		}
+		assert_eq!(reindexed.filter(&filter), traces);
+		drop(reindexed);
+		let tracedb = TraceDB::new(config, temp.as_path(), extras).unwrap();
+		assert_eq!(tracedb.filter(&filter), traces);
+	}
 }
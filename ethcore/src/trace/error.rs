@@ -32,10 +32,25 @@ pub enum Error {
 	/// Returned when tracing is enabled,
 	/// but database does not contain traces of old transactions.
 	ResyncRequired,
+	/// Returned when configured blooms granularity differs from the one
+	/// used to build blooms of the database.
+	ReindexRequired {
+		/// Levels and elements per index of existing blooms.
+		stored: (usize, usize),
+		/// Configured levels and elements per index.
+		requested: (usize, usize),
+	},
 }
 
 impl Display for Error {
 	fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
-		write!(f, "{}", RESYNC_ERR)
+		match *self {
+			Error::ResyncRequired => write!(f, "{}", RESYNC_ERR),
+			Error::ReindexRequired { stored, requested } => write!(f,
+				"Trace blooms were built with {} levels and {} elements per index, but {} levels and {} elements per index are configured.
+Restart parity with --tracing-reindex to rebuild them with the configured granularity.",
+				stored.0, stored.1, requested.0, requested.1
+			),
+		}
 	}
 }
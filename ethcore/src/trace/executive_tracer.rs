@@ -18,10 +18,10 @@
 
 use util::{Bytes, Address, U256};
 use action_params::ActionParams;
-use trace::trace::{Trace, Call, Create, Action, Res, CreateResult, CallResult};
-use trace::Tracer;
+use trace::trace::{Trace, Call, Create, Suicide, Action, Res, CreateResult, CallResult, VMTrace, VMOperation, VMExecutedOperation};
+use trace::{Tracer, VMTracer};
 
-/// Simple executive tracer. Traces all calls and creates. Ignores delegatecalls.
+/// Simple executive tracer. Traces all calls, creates and suicides. Ignores delegatecalls.
 #[derive(Default)]
 pub struct ExecutiveTracer {
 	traces: Vec<Trace>
@@ -98,6 +98,20 @@ impl Tracer for ExecutiveTracer {
 		self.traces.push(trace);
 	}
 
+	fn trace_suicide(&mut self, address: Address, balance: U256, refund_address: Address, depth: usize) {
+		let trace = Trace {
+			depth: depth,
+			subs: vec![],
+			action: Action::Suicide(Suicide {
+				address: address,
+				refund_address: refund_address,
+				balance: balance,
+			}),
+			result: Res::None,
+		};
+		self.traces.push(trace);
+	}
+
 	fn subtracer(&self) -> Self {
 		ExecutiveTracer::default()
 	}
@@ -106,3 +120,57 @@ impl Tracer for ExecutiveTracer {
 		self.traces
 	}
 }
+
+/// Simple VM tracer. Traces all executed instructions.
+pub struct ExecutiveVMTracer {
+	data: VMTrace,
+}
+
+impl ExecutiveVMTracer {
+	/// Creates tracer of the top level execution.
+	pub fn toplevel() -> Self {
+		ExecutiveVMTracer {
+			data: VMTrace::default(),
+		}
+	}
+}
+
+impl VMTracer for ExecutiveVMTracer {
+	fn trace_prepare_execute(&mut self, pc: usize, instruction: u8, gas_cost: &U256) -> bool {
+		self.data.operations.push(VMOperation {
+			pc: pc,
+			instruction: instruction,
+			gas_cost: gas_cost.clone(),
+			executed: None,
+		});
+		true
+	}
+
+	fn trace_executed(&mut self, gas_left: U256, stack_push: &[U256]) {
+		let operation = self.data.operations.last_mut().expect("trace_executed is always called after trace_prepare_execute; qed");
+		operation.executed = Some(VMExecutedOperation {
+			gas_left: gas_left,
+			stack_push: stack_push.to_vec(),
+		});
+	}
+
+	fn prepare_subtrace(&self, code: &[u8]) -> Self {
+		ExecutiveVMTracer {
+			data: VMTrace {
+				parent_step: self.data.operations.len(),
+				code: code.to_vec(),
+				operations: vec![],
+				subs: vec![],
+			},
+		}
+	}
+
+	fn done_subtrace(&mut self, sub: Self) {
+		self.data.subs.push(sub.data);
+	}
+
+	fn drain(mut self) -> Option<VMTrace> {
+		// top level tracer doesn't execute any code, its only sub is the transaction execution
+		self.data.subs.pop()
+	}
+}
@@ -22,6 +22,7 @@ use super::trace::{Trace, Action, Res};
 /// Trace localized in vector of traces produced by a single transaction.
 ///
 /// Parent and children indexes refer to positions in this vector.
+#[derive(Debug, PartialEq, Clone)]
 pub struct FlatTrace {
 	/// Type of action performed by a transaction.
 	pub action: Action,
@@ -51,7 +52,7 @@ impl From<BlockTraces> for FlatBlockTraces {
 	fn from(block_traces: BlockTraces) -> Self {
 		let traces: Vec<Trace> = block_traces.into();
 		let ordered = traces.into_iter()
-			.map(|trace| FlatBlockTraces::flatten(vec![], trace))
+			.map(Trace::flat)
 			.map(FlatTransactionTraces)
 			.collect();
 		FlatBlockTraces(ordered)
@@ -64,33 +65,6 @@ impl Into<Vec<FlatTransactionTraces>> for FlatBlockTraces {
 	}
 }
 
-impl FlatBlockTraces {
-	/// Helper function flattening nested tree structure to vector of ordered traces.
-	fn flatten(address: Vec<usize>, trace: Trace) -> Vec<FlatTrace> {
-		let subtraces = trace.subs.len();
-		let all_subs = trace.subs
-			.into_iter()
-			.enumerate()
-			.flat_map(|(index, subtrace)| {
-				let mut subtrace_address = address.clone();
-				subtrace_address.push(index);
-				FlatBlockTraces::flatten(subtrace_address, subtrace)
-			})
-			.collect::<Vec<_>>();
-
-		let ordered = FlatTrace {
-			action: trace.action,
-			result: trace.result,
-			subtraces: subtraces,
-			trace_address: address,
-		};
-
-		let mut result = vec![ordered];
-		result.extend(all_subs);
-		result
-	}
-}
-
 #[cfg(test)]
 mod tests {
 	use super::{FlatBlockTraces, FlatTransactionTraces, FlatTrace};
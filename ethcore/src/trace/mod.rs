@@ -31,9 +31,9 @@ pub use self::block::BlockTraces;
 pub use self::config::{Config, Switch};
 pub use self::db::TraceDB;
 pub use self::error::Error;
-pub use types::trace_types::trace::Trace;
-pub use self::noop_tracer::NoopTracer;
-pub use self::executive_tracer::ExecutiveTracer;
+pub use types::trace_types::trace::{Trace, VMTrace, VMOperation, VMExecutedOperation};
+pub use self::noop_tracer::{NoopTracer, NoopVMTracer};
+pub use self::executive_tracer::{ExecutiveTracer, ExecutiveVMTracer};
 pub use types::trace_types::filter::{Filter, AddressesFilter};
 pub use self::import::ImportRequest;
 pub use self::localized::LocalizedTrace;
@@ -81,6 +81,9 @@ pub trait Tracer: Send {
 	/// Stores failed create trace.
 	fn trace_failed_create(&mut self, create: Option<Create>, depth: usize, subs: Vec<Trace>);
 
+	/// Stores suicide info (including balance swept to `refund_address`).
+	fn trace_suicide(&mut self, address: Address, balance: U256, refund_address: Address, depth: usize);
+
 	/// Spawn subracer which will be used to trace deeper levels of execution.
 	fn subtracer(&self) -> Self where Self: Sized;
 
@@ -88,6 +91,25 @@ pub trait Tracer: Send {
 	fn traces(self) -> Vec<Trace>;
 }
 
+/// This trait is used by executive to build traces of executed VM instructions.
+pub trait VMTracer: Send {
+	/// Prepares to trace execution of instruction at `pc`.
+	/// Returns `true` if `trace_executed` should be called once it's executed.
+	fn trace_prepare_execute(&mut self, _pc: usize, _instruction: u8, _gas_cost: &U256) -> bool { false }
+
+	/// Stores result of the prepared instruction.
+	fn trace_executed(&mut self, _gas_left: U256, _stack_push: &[U256]) {}
+
+	/// Spawn subtracer which will be used to trace execution of given code at deeper level.
+	fn prepare_subtrace(&self, code: &[u8]) -> Self where Self: Sized;
+
+	/// Stores traces of finished subtracer.
+	fn done_subtrace(&mut self, sub: Self) where Self: Sized;
+
+	/// Consumes self and returns the VM trace.
+	fn drain(self) -> Option<VMTrace>;
+}
+
 /// `DbExtras` provides an interface to query extra data which is not stored in tracesdb,
 /// but necessary to work correctly.
 pub trait DatabaseExtras {
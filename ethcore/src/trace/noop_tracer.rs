@@ -18,8 +18,8 @@
 
 use util::{Bytes, Address, U256};
 use action_params::ActionParams;
-use trace::Tracer;
-use trace::trace::{Trace, Call, Create};
+use trace::{Tracer, VMTracer};
+use trace::trace::{Trace, Call, Create, VMTrace};
 
 /// Nonoperative tracer. Does not trace anything.
 pub struct NoopTracer;
@@ -55,6 +55,8 @@ impl Tracer for NoopTracer {
 		assert!(create.is_none(), "self.prepare_trace_create().is_none(): so we can't be tracing: qed");
 	}
 
+	fn trace_suicide(&mut self, _: Address, _: U256, _: Address, _: usize) {}
+
 	fn subtracer(&self) -> Self {
 		NoopTracer
 	}
@@ -63,3 +65,18 @@ impl Tracer for NoopTracer {
 		vec![]
 	}
 }
+
+/// Nonoperative VM tracer. Does not trace anything.
+pub struct NoopVMTracer;
+
+impl VMTracer for NoopVMTracer {
+	fn prepare_subtrace(&self, _: &[u8]) -> Self {
+		NoopVMTracer
+	}
+
+	fn done_subtrace(&mut self, _: Self) {}
+
+	fn drain(self) -> Option<VMTrace> {
+		None
+	}
+}
@@ -18,7 +18,8 @@
 
 use util::numbers::*;
 use util::Bytes;
-use trace::Trace;
+use trace::{Trace, VMTrace};
+use state_diff::StateDiff;
 use types::log_entry::LogEntry;
 use ipc::binary::BinaryConvertError;
 use std::fmt;
@@ -59,6 +60,13 @@ pub struct Executed {
 	pub output: Bytes,
 	/// The trace of this transaction.
 	pub trace: Option<Trace>,
+	/// The VM trace of this transaction.
+	pub vm_trace: Option<VMTrace>,
+	/// Whether execution ended with an exception (e.g. out of gas).
+	/// All gas is consumed and no state changes are made in that case.
+	pub exception: bool,
+	/// Changes in state made by transaction, if diffing was enabled.
+	pub state_diff: Option<StateDiff>,
 }
 
 /// Result of executing the transaction.
@@ -101,8 +109,16 @@ pub enum ExecutionError {
 	},
 	/// Returned when internal evm error occurs.
 	Internal,
+	/// Returned when execution didn't finish before the deadline.
+	Timeout,
 	/// Returned when generic transaction occurs
 	TransactionMalformed(String),
+	/// Returned by gas estimation when transaction fails even with all the gas
+	/// available in block.
+	GasRequiredExceedsLimit {
+		/// Gas available in block.
+		limit: U256,
+	},
 }
 
 impl fmt::Display for ExecutionError {
@@ -121,7 +137,10 @@ impl fmt::Display for ExecutionError {
 				format!("Cost of transaction exceeds sender balance. {} is required \
 					but the sender only has {}", required, got),
 			Internal => "Internal evm error".into(),
+			Timeout => "Execution timed out".into(),
 			TransactionMalformed(ref err) => format!("Malformed transaction: {}", err),
+			GasRequiredExceedsLimit { limit } =>
+				format!("Transaction fails even with all {} gas available in block", limit),
 		};
 
 		f.write_fmt(format_args!("Transaction execution error ({}).", msg))
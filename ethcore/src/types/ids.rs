@@ -55,7 +55,7 @@ pub struct TraceId {
 }
 
 /// Uniquely identifies Uncle.
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct UncleID (
 	/// Block id.
 	pub BlockID,
@@ -16,11 +16,12 @@
 
 //! Tracing datatypes.
 
-use util::{U256, Bytes, Address, FixedHash};
+use util::{U256, Bytes, Address, FixedHash, HeapSizeOf};
 use util::rlp::*;
 use util::sha3::Hashable;
 use action_params::ActionParams;
 use basic_types::LogBloom;
+use trace::flat::FlatTrace;
 use ipc::binary::BinaryConvertError;
 use std::mem;
 use std::collections::VecDeque;
@@ -205,13 +206,67 @@ impl Create {
 	}
 }
 
-/// Description of an action that we trace; will be either a call or a create.
+/// Description of a _suicide_ action, i.e. a `SUICIDE` operation sweeping contract balance.
+#[derive(Debug, Clone, PartialEq, Binary)]
+pub struct Suicide {
+	/// The suicided contract.
+	pub address: Address,
+	/// The account receiving the remaining balance of the contract.
+	pub refund_address: Address,
+	/// The balance of the contract just before suicide.
+	pub balance: U256,
+}
+
+impl Encodable for Suicide {
+	fn rlp_append(&self, s: &mut RlpStream) {
+		s.begin_list(3);
+		s.append(&self.address);
+		s.append(&self.refund_address);
+		s.append(&self.balance);
+	}
+}
+
+impl Decodable for Suicide {
+	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
+		let d = decoder.as_rlp();
+		let res = Suicide {
+			address: try!(d.val_at(0)),
+			refund_address: try!(d.val_at(1)),
+			balance: try!(d.val_at(2)),
+		};
+
+		Ok(res)
+	}
+}
+
+impl Suicide {
+	/// Returns suicide action bloom.
+	/// The bloom contains suicided and refund addresses.
+	pub fn bloom(&self) -> LogBloom {
+		LogBloom::from_bloomed(&self.address.sha3())
+			.with_bloomed(&self.refund_address.sha3())
+	}
+}
+
+/// Description of an action that we trace; will be either a call, a create or a suicide.
 #[derive(Debug, Clone, PartialEq, Binary)]
 pub enum Action {
 	/// It's a call action.
 	Call(Call),
 	/// It's a create action.
 	Create(Create),
+	/// It's a suicide action.
+	Suicide(Suicide),
+}
+
+impl HeapSizeOf for Action {
+	fn heap_size_of_children(&self) -> usize {
+		match *self {
+			Action::Call(ref call) => call.input.heap_size_of_children(),
+			Action::Create(ref create) => create.init.heap_size_of_children(),
+			Action::Suicide(_) => 0,
+		}
+	}
 }
 
 impl Encodable for Action {
@@ -225,6 +280,10 @@ impl Encodable for Action {
 			Action::Create(ref create) => {
 				s.append(&1u8);
 				s.append(create);
+			},
+			Action::Suicide(ref suicide) => {
+				s.append(&2u8);
+				s.append(suicide);
 			}
 		}
 	}
@@ -237,6 +296,7 @@ impl Decodable for Action {
 		match action_type {
 			0 => d.val_at(1).map(Action::Call),
 			1 => d.val_at(1).map(Action::Create),
+			2 => d.val_at(1).map(Action::Suicide),
 			_ => Err(DecoderError::Custom("Invalid action type.")),
 		}
 	}
@@ -248,6 +308,7 @@ impl Action {
 		match *self {
 			Action::Call(ref call) => call.bloom(),
 			Action::Create(ref create) => create.bloom(),
+			Action::Suicide(ref suicide) => suicide.bloom(),
 		}
 	}
 }
@@ -263,6 +324,18 @@ pub enum Res {
 	FailedCall,
 	/// Failed create.
 	FailedCreate,
+	/// Action without result (e.g. suicide).
+	None,
+}
+
+impl HeapSizeOf for Res {
+	fn heap_size_of_children(&self) -> usize {
+		match *self {
+			Res::Call(ref call) => call.output.heap_size_of_children(),
+			Res::Create(ref create) => create.code.heap_size_of_children(),
+			_ => 0,
+		}
+	}
 }
 
 impl Encodable for Res {
@@ -285,6 +358,10 @@ impl Encodable for Res {
 			Res::FailedCreate => {
 				s.begin_list(1);
 				s.append(&3u8);
+			},
+			Res::None => {
+				s.begin_list(1);
+				s.append(&4u8);
 			}
 		}
 	}
@@ -299,6 +376,7 @@ impl Decodable for Res {
 			1 => d.val_at(1).map(Res::Create),
 			2 => Ok(Res::FailedCall),
 			3 => Ok(Res::FailedCreate),
+			4 => Ok(Res::None),
 			_ => Err(DecoderError::Custom("Invalid result type.")),
 		}
 	}
@@ -318,6 +396,12 @@ pub struct Trace {
 	pub result: Res,
 }
 
+impl HeapSizeOf for Trace {
+	fn heap_size_of_children(&self) -> usize {
+		self.action.heap_size_of_children() + self.subs.heap_size_of_children() + self.result.heap_size_of_children()
+	}
+}
+
 impl Encodable for Trace {
 	fn rlp_append(&self, s: &mut RlpStream) {
 		s.begin_list(4);
@@ -347,6 +431,72 @@ impl Trace {
 	pub fn bloom(&self) -> LogBloom {
 		self.subs.iter().fold(self.action.bloom(), |b, s| b | s.bloom())
 	}
+
+	/// Converts the tree of traces into a vector ordered by execution.
+	/// Each trace is followed by its subtraces.
+	pub fn flat(self) -> Vec<FlatTrace> {
+		self.flatten(vec![])
+	}
+
+	fn flatten(self, address: Vec<usize>) -> Vec<FlatTrace> {
+		let subtraces = self.subs.len();
+		let all_subs = self.subs
+			.into_iter()
+			.enumerate()
+			.flat_map(|(index, subtrace)| {
+				let mut subtrace_address = address.clone();
+				subtrace_address.push(index);
+				subtrace.flatten(subtrace_address)
+			})
+			.collect::<Vec<_>>();
+
+		let ordered = FlatTrace {
+			action: self.action,
+			result: self.result,
+			subtraces: subtraces,
+			trace_address: address,
+		};
+
+		let mut result = vec![ordered];
+		result.extend(all_subs);
+		result
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Binary)]
+/// Result of executing single VM instruction.
+pub struct VMExecutedOperation {
+	/// Gas left after the instruction was executed.
+	pub gas_left: U256,
+	/// Values pushed to the stack by the instruction.
+	pub stack_push: Vec<U256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Binary)]
+/// Single VM instruction.
+pub struct VMOperation {
+	/// Program counter of the instruction.
+	pub pc: usize,
+	/// The instruction.
+	pub instruction: u8,
+	/// Gas cost of the instruction.
+	pub gas_cost: U256,
+	/// Result of the instruction or `None` if it failed.
+	pub executed: Option<VMExecutedOperation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Binary)]
+/// VM trace of single code execution; includes traces of code executed by nested calls and creates.
+pub struct VMTrace {
+	/// Number of operations of the parent trace executed before this one started
+	/// (including the instruction which started it).
+	pub parent_step: usize,
+	/// The executed code.
+	pub code: Bytes,
+	/// Executed instructions.
+	pub operations: Vec<VMOperation>,
+	/// Traces of nested executions, in order of execution.
+	pub subs: Vec<VMTrace>,
 }
 
 #[cfg(test)]
@@ -354,7 +504,7 @@ mod tests {
 	use util::{Address, U256, FixedHash};
 	use util::rlp::{encode, decode};
 	use util::sha3::Hashable;
-	use trace::trace::{Call, CallResult, Create, Res, Action, Trace};
+	use trace::trace::{Call, CallResult, Create, Suicide, Res, Action, Trace};
 
 	#[test]
 	fn traces_rlp() {
@@ -378,6 +528,16 @@ mod tests {
 					}),
 					subs: vec![],
 					result: Res::FailedCreate
+				},
+				Trace {
+					depth: 3,
+					action: Action::Suicide(Suicide {
+						address: Address::from(2),
+						refund_address: Address::from(11),
+						balance: U256::from(12),
+					}),
+					subs: vec![],
+					result: Res::None
 				}
 			],
 			result: Res::Call(CallResult {
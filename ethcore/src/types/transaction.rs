@@ -75,7 +75,13 @@ pub struct Transaction {
 impl Transaction {
 	/// Append object with a without signature into RLP stream
 	pub fn rlp_append_unsigned_transaction(&self, s: &mut RlpStream) {
-		s.begin_list(6);
+		self.rlp_append_unsigned_transaction_with_chain_id(s, None)
+	}
+
+	/// Append object without signature into RLP stream.
+	/// With `chain_id` the chain id and two empty items are appended, so the signature is valid on that chain only.
+	fn rlp_append_unsigned_transaction_with_chain_id(&self, s: &mut RlpStream, chain_id: Option<u64>) {
+		s.begin_list(if chain_id.is_some() { 9 } else { 6 });
 		s.append(&self.nonce);
 		s.append(&self.gas_price);
 		s.append(&self.gas);
@@ -85,6 +91,11 @@ impl Transaction {
 		};
 		s.append(&self.value);
 		s.append(&self.data);
+		if let Some(chain_id) = chain_id {
+			s.append(&chain_id);
+			s.append(&0u8);
+			s.append(&0u8);
+		}
 	}
 }
 
@@ -132,20 +143,34 @@ impl From<ethjson::transaction::Transaction> for SignedTransaction {
 impl Transaction {
 	/// The message hash of the transaction.
 	pub fn hash(&self) -> H256 {
+		self.hash_with_chain_id(None)
+	}
+
+	/// The message hash of the transaction signed for chain with given id (or any chain if `None`).
+	pub fn hash_with_chain_id(&self, chain_id: Option<u64>) -> H256 {
 		let mut stream = RlpStream::new();
-		self.rlp_append_unsigned_transaction(&mut stream);
+		self.rlp_append_unsigned_transaction_with_chain_id(&mut stream, chain_id);
 		stream.out().sha3()
 	}
 
 	/// Signs the transaction as coming from `sender`.
 	pub fn sign(self, secret: &Secret) -> SignedTransaction {
-		let sig = ec::sign(secret, &self.hash());
+		self.sign_with_chain_id(secret, None)
+	}
+
+	/// Signs the transaction as coming from `sender`.
+	/// Transactions signed with `chain_id` can't be replayed on chains with different id.
+	pub fn sign_with_chain_id(self, secret: &Secret, chain_id: Option<u64>) -> SignedTransaction {
+		let sig = ec::sign(secret, &self.hash_with_chain_id(chain_id));
 		let (r, s, v) = sig.unwrap().to_rsv();
 		SignedTransaction {
 			unsigned: self,
 			r: r,
 			s: s,
-			v: v + 27,
+			v: match chain_id {
+				Some(chain_id) => v as u64 + 35 + chain_id * 2,
+				None => v as u64 + 27,
+			},
 			hash: Cell::new(None),
 			sender: Cell::new(None),
 		}
@@ -195,8 +220,9 @@ impl Transaction {
 pub struct SignedTransaction {
 	/// Plain Transaction.
 	unsigned: Transaction,
-	/// The V field of the signature, either 27 or 28; helps describe the point on the curve.
-	v: u8,
+	/// The V field of the signature; helps describe the point on the curve.
+	/// Either 27 or 28 or, for transactions signed with chain id, `35 + chain_id * 2` plus 0 or 1.
+	v: u64,
 	/// The R field of the signature; helps describe the point on the curve.
 	r: U256,
 	/// The S field of the signature; helps describe the point on the curve.
@@ -281,7 +307,35 @@ impl SignedTransaction {
 	}
 
 	/// 0 is `v` is 27, 1 if 28, and 4 otherwise.
-	pub fn standard_v(&self) -> u8 { match self.v { 27 => 0, 28 => 1, _ => 4 } }
+	/// For transactions signed with chain id 0 or 1 depending on parity of `v`.
+	pub fn standard_v(&self) -> u8 {
+		match self.v {
+			27 => 0,
+			28 => 1,
+			v if v >= 35 => ((v - 35) % 2) as u8,
+			_ => 4,
+		}
+	}
+
+	/// Returns id of the chain the transaction was signed for or `None` if it's valid on any chain.
+	pub fn chain_id(&self) -> Option<u64> {
+		match self.v {
+			v if v >= 35 => Some((v - 35) / 2),
+			_ => None,
+		}
+	}
+
+	/// Checks that the transaction can be included on chain with `chain_id`.
+	/// Transactions signed without chain id are accepted only if `allow_unprotected` is `true`
+	/// (or the chain has no id at all).
+	pub fn verify_chain_id(&self, chain_id: Option<u64>, allow_unprotected: bool) -> Result<(), TransactionError> {
+		match (chain_id, self.chain_id()) {
+			(None, None) => Ok(()),
+			(Some(_), None) if allow_unprotected => Ok(()),
+			(Some(expected), Some(got)) if expected == got => Ok(()),
+			(expected, got) => Err(TransactionError::InvalidChainId { expected: expected, got: got }),
+		}
+	}
 
 	/// Construct a signature object from the sig.
 	pub fn signature(&self) -> Signature { Signature::from_rsv(&From::from(&self.r), &From::from(&self.s), self.standard_v()) }
@@ -301,7 +355,7 @@ impl SignedTransaction {
 		match sender {
 			Some(s) => Ok(s),
 			None => {
-				let s = Address::from(try!(ec::recover(&self.signature(), &self.unsigned.hash())).sha3());
+				let s = Address::from(try!(ec::recover(&self.signature(), &self.unsigned.hash_with_chain_id(self.chain_id()))).sha3());
 				self.sender.set(Some(s));
 				Ok(s)
 			}
@@ -389,3 +443,68 @@ fn fake_signing() {
 	let t = t.clone();
 	assert_eq!(Address::from(0x69), t.sender().unwrap());
 }
+
+#[test]
+fn signing_with_chain_id() {
+	let key = ::util::crypto::KeyPair::create().unwrap();
+	let t = Transaction {
+		action: Action::Create,
+		nonce: U256::from(42),
+		gas_price: U256::from(3000),
+		gas: U256::from(50_000),
+		value: U256::from(1),
+		data: b"Hello!".to_vec()
+	}.sign_with_chain_id(&key.secret(), Some(18));
+	assert_eq!(t.chain_id(), Some(18));
+	assert!(t.v == 71 || t.v == 72);
+	assert_eq!(Address::from(key.public().sha3()), t.sender().unwrap());
+
+	let decoded: SignedTransaction = decode(&encode(&t).to_vec());
+	assert_eq!(decoded, t);
+	assert_eq!(decoded.chain_id(), Some(18));
+	assert_eq!(Address::from(key.public().sha3()), decoded.sender().unwrap());
+}
+
+#[test]
+fn should_recover_sender_of_legacy_and_chain_id_signatures() {
+	let key = ::util::crypto::KeyPair::create().unwrap();
+	let transaction = Transaction {
+		action: Action::Create,
+		nonce: U256::from(42),
+		gas_price: U256::from(3000),
+		gas: U256::from(50_000),
+		value: U256::from(1),
+		data: b"Hello!".to_vec()
+	};
+	let legacy: SignedTransaction = decode(&encode(&transaction.clone().sign(&key.secret())).to_vec());
+	let protected: SignedTransaction = decode(&encode(&transaction.sign_with_chain_id(&key.secret(), Some(1))).to_vec());
+
+	assert_eq!(legacy.chain_id(), None);
+	assert!(legacy.v == 27 || legacy.v == 28);
+	assert_eq!(protected.chain_id(), Some(1));
+	assert!(protected.v == 37 || protected.v == 38);
+	assert_eq!(legacy.sender().unwrap(), Address::from(key.public().sha3()));
+	assert_eq!(protected.sender().unwrap(), Address::from(key.public().sha3()));
+}
+
+#[test]
+fn should_verify_chain_id() {
+	let key = ::util::crypto::KeyPair::create().unwrap();
+	let transaction = Transaction {
+		action: Action::Create,
+		nonce: U256::from(42),
+		gas_price: U256::from(3000),
+		gas: U256::from(50_000),
+		value: U256::from(1),
+		data: b"Hello!".to_vec()
+	};
+	let legacy = transaction.clone().sign(&key.secret());
+	let protected = transaction.sign_with_chain_id(&key.secret(), Some(2));
+
+	assert_eq!(legacy.verify_chain_id(None, false), Ok(()));
+	assert_eq!(legacy.verify_chain_id(Some(2), true), Ok(()));
+	assert_eq!(legacy.verify_chain_id(Some(2), false), Err(TransactionError::InvalidChainId { expected: Some(2), got: None }));
+	assert_eq!(protected.verify_chain_id(Some(2), false), Ok(()));
+	assert_eq!(protected.verify_chain_id(Some(3), true), Err(TransactionError::InvalidChainId { expected: Some(3), got: Some(2) }));
+	assert_eq!(protected.verify_chain_id(None, true), Err(TransactionError::InvalidChainId { expected: None, got: Some(2) }));
+}
@@ -0,0 +1,42 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Uncle inclusion info.
+
+use util::numbers::*;
+use header::BlockNumber;
+use ipc::binary::BinaryConvertError;
+use std::mem;
+use std::collections::VecDeque;
+
+/// Information about an uncle and the block which included it.
+#[derive(Debug, PartialEq, Clone, Binary)]
+pub struct UncleInfo {
+	/// Uncle hash.
+	pub hash: H256,
+	/// Uncle number.
+	pub number: BlockNumber,
+	/// Uncle author.
+	pub author: Address,
+	/// Hash of the block which included the uncle.
+	pub nephew_hash: H256,
+	/// Number of the block which included the uncle.
+	pub nephew_number: BlockNumber,
+	/// Position of the uncle within the including block.
+	pub index: usize,
+	/// Reward paid to the uncle author.
+	pub reward: U256,
+}
@@ -17,11 +17,41 @@
 pub mod verification;
 pub mod verifier;
 mod canon_verifier;
-#[cfg(test)]
 mod noop_verifier;
 
 pub use self::verification::*;
 pub use self::verifier::Verifier;
 pub use self::canon_verifier::CanonVerifier;
-#[cfg(test)]
 pub use self::noop_verifier::NoopVerifier;
+
+/// Verifier type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerifierType {
+	/// Verifies block normally.
+	Canon,
+	/// Verifies block normally, but skips seal verification.
+	CanonNoSeal,
+	/// Does not verify block family and final state at all. Seals are not verified either.
+	Noop,
+}
+
+impl VerifierType {
+	/// Returns `true` if seals of the blocks should be verified.
+	pub fn verifying_seal(&self) -> bool {
+		*self == VerifierType::Canon
+	}
+}
+
+impl Default for VerifierType {
+	fn default() -> Self {
+		VerifierType::Canon
+	}
+}
+
+/// Create a new verifier based on type.
+pub fn new(v: VerifierType) -> Box<Verifier> {
+	match v {
+		VerifierType::Canon | VerifierType::CanonNoSeal => Box::new(CanonVerifier),
+		VerifierType::Noop => Box::new(NoopVerifier),
+	}
+}
@@ -36,13 +36,18 @@ pub struct PreverifiedBlock {
 }
 
 /// Phase 1 quick block verification. Only does checks that are cheap. Operates on a single block
-pub fn verify_block_basic(header: &Header, bytes: &[u8], engine: &Engine) -> Result<(), Error> {
+/// Seal of the block and its uncles is checked only if `check_seal` is set.
+pub fn verify_block_basic(header: &Header, bytes: &[u8], engine: &Engine, check_seal: bool) -> Result<(), Error> {
 	try!(verify_header(&header, engine));
 	try!(verify_block_integrity(bytes, &header.transactions_root, &header.uncles_hash));
-	try!(engine.verify_block_basic(&header, Some(bytes)));
+	if check_seal {
+		try!(engine.verify_block_basic(&header, Some(bytes)));
+	}
 	for u in Rlp::new(bytes).at(2).iter().map(|rlp| rlp.as_val::<Header>()) {
 		try!(verify_header(&u, engine));
-		try!(engine.verify_block_basic(&u, None));
+		if check_seal {
+			try!(engine.verify_block_basic(&u, None));
+		}
 	}
 	// Verify transactions.
 	// TODO: either use transaction views or cache the decoded transactions.
@@ -54,19 +59,26 @@ pub fn verify_block_basic(header: &Header, bytes: &[u8], engine: &Engine) -> Res
 }
 
 /// Phase 2 verification. Perform costly checks such as transaction signatures and block nonce for ethash.
-/// Still operates on a individual block
+/// Still operates on a individual block. Seals are checked only if `check_seal` is set.
 /// Returns a `PreverifiedBlock` structure populated with transactions
-pub fn verify_block_unordered(header: Header, bytes: Bytes, engine: &Engine) -> Result<PreverifiedBlock, Error> {
-	try!(engine.verify_block_unordered(&header, Some(&bytes)));
-	for u in Rlp::new(&bytes).at(2).iter().map(|rlp| rlp.as_val::<Header>()) {
-		try!(engine.verify_block_unordered(&u, None));
+pub fn verify_block_unordered(header: Header, bytes: Bytes, engine: &Engine, check_seal: bool) -> Result<PreverifiedBlock, Error> {
+	if check_seal {
+		try!(engine.verify_block_unordered(&header, Some(&bytes)));
+		for u in Rlp::new(&bytes).at(2).iter().map(|rlp| rlp.as_val::<Header>()) {
+			try!(engine.verify_block_unordered(&u, None));
+		}
 	}
 	// Verify transactions.
 	let mut transactions = Vec::new();
 	{
 		let v = BlockView::new(&bytes);
+		let params = engine.params();
 		for t in v.transactions() {
 			try!(engine.verify_transaction(&t, &header));
+			try!(t.verify_chain_id(params.chain_id, params.allows_unprotected_transactions(header.number)));
+			if let Some(ref rule) = params.gas_price_rule {
+				try!(rule.verify_transaction(&t));
+			}
 			transactions.push(t);
 		}
 	}
@@ -237,6 +249,8 @@ mod tests {
 	use spec::*;
 	use transaction::*;
 	use tests::helpers::*;
+	use null_engine::NullEngine;
+	use gas_price_rule::GasPriceRule;
 
 	fn check_ok(result: Result<(), Error>) {
 		result.unwrap_or_else(|e| panic!("Block verification failed: {:?}", e));
@@ -320,7 +334,7 @@ mod tests {
 
 	fn basic_test(bytes: &[u8], engine: &Engine) -> Result<(), Error> {
 		let header = BlockView::new(bytes).header();
-		verify_block_basic(&header, bytes, engine)
+		verify_block_basic(&header, bytes, engine, true)
 	}
 
 	fn family_test<BC>(bytes: &[u8], engine: &Engine, bc: &BC) -> Result<(), Error> where BC: BlockProvider {
@@ -482,4 +496,78 @@ mod tests {
 
 		// TODO: some additional uncle checks
 	}
+
+	#[test]
+	fn should_verify_gas_price_rule() {
+		// given
+		let exempt = KeyPair::create().unwrap();
+		let other = KeyPair::create().unwrap();
+		let spec = Spec::new_test();
+		let mut params = spec.engine.params().clone();
+		params.gas_price_rule = Some(GasPriceRule::PerSenderExemptions {
+			floor: U256::from(20),
+			exempt: vec![exempt.address()].into_iter().collect(),
+		});
+		let engine = NullEngine::new(params, BTreeMap::new());
+		let zero_price = |keypair: &KeyPair| Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: Bytes::new(),
+			gas: U256::from(30_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero(),
+		}.sign(keypair.secret());
+		let header = Header::new();
+
+		// when
+		let exempt_block = create_test_block_with_data(&header, &[&zero_price(&exempt)], &[]);
+		let other_block = create_test_block_with_data(&header, &[&zero_price(&other)], &[]);
+
+		// then
+		assert!(verify_block_unordered(header.clone(), exempt_block, &engine, true).is_ok());
+		match verify_block_unordered(header, other_block, &engine, true) {
+			Err(Error::Transaction(TransactionError::InsufficientGasPrice { .. })) => (),
+			Err(e) => panic!("Unexpected error: {:?}", e),
+			Ok(_) => panic!("Block with non-exempt zero gas price transaction should be rejected."),
+		}
+	}
+
+	#[test]
+	fn should_verify_chain_id() {
+		// given
+		let keypair = KeyPair::create().unwrap();
+		let spec = Spec::new_test();
+		let mut params = spec.engine.params().clone();
+		params.chain_id = Some(17);
+		params.chain_id_transition = 10;
+		let engine = NullEngine::new(params, BTreeMap::new());
+		let transaction = |chain_id| Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: Bytes::new(),
+			gas: U256::from(30_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero(),
+		}.sign_with_chain_id(keypair.secret(), chain_id);
+		let block = |header: &Header, chain_id| create_test_block_with_data(header, &[&transaction(chain_id)], &[]);
+		let mut before_transition = Header::new();
+		before_transition.number = 9;
+		let mut after_transition = Header::new();
+		after_transition.number = 10;
+
+		// then
+		assert!(verify_block_unordered(before_transition.clone(), block(&before_transition, Some(17)), &engine, true).is_ok());
+		assert!(verify_block_unordered(before_transition.clone(), block(&before_transition, None), &engine, true).is_ok());
+		assert!(verify_block_unordered(after_transition.clone(), block(&after_transition, Some(17)), &engine, true).is_ok());
+		match verify_block_unordered(after_transition.clone(), block(&after_transition, None), &engine, true) {
+			Err(Error::Transaction(TransactionError::InvalidChainId { expected: Some(17), got: None })) => (),
+			Err(e) => panic!("Unexpected error: {:?}", e),
+			Ok(_) => panic!("Block with unprotected transaction after the transition should be rejected."),
+		}
+		match verify_block_unordered(before_transition.clone(), block(&before_transition, Some(18)), &engine, true) {
+			Err(Error::Transaction(TransactionError::InvalidChainId { expected: Some(17), got: Some(18) })) => (),
+			Err(e) => panic!("Unexpected error: {:?}", e),
+			Ok(_) => panic!("Block with transaction signed for other chain should be rejected."),
+		}
+	}
 }
@@ -86,8 +86,13 @@ fn serialize_item(
 		&generics,
 		ty.clone()));
 
-	let (size_expr, read_expr, write_expr) =
-		(binary_expressions.size, binary_expressions.read, binary_expressions.write);
+	let (size_expr, read_expr, write_expr, empty_expr, len_params_expr) = (
+		binary_expressions.size,
+		binary_expressions.read,
+		binary_expressions.write,
+		binary_expressions.empty,
+		binary_expressions.len_params,
+	);
 
 	Ok(quote_item!(cx,
 		impl $generics ::ipc::BinaryConvertable for $ty $where_clause {
@@ -103,8 +108,12 @@ fn serialize_item(
 				$read_expr
 			}
 
+			fn from_empty_bytes() -> Result<Self, BinaryConvertError> {
+				$empty_expr
+			}
+
 			fn len_params() -> usize {
-				1
+				$len_params_expr
 			}
         }
     ).unwrap())
@@ -119,6 +128,15 @@ fn binary_expr(
 	ty: P<ast::Ty>,
 ) -> Result<BinaryExpressions, Error> {
 	match item.node {
+		ast::ItemKind::Struct(ref variant_data, _) if is_versioned(item) => {
+			binary_expr_versioned_struct(
+				cx,
+				builder,
+				ty,
+				item.span,
+				variant_data,
+			)
+		},
 		ast::ItemKind::Struct(ref variant_data, _) => {
 			binary_expr_item_struct(
 				cx,
@@ -129,6 +147,10 @@ fn binary_expr(
 				variant_data,
 			)
 		},
+		ast::ItemKind::Enum(_, _) if is_versioned(item) => {
+			cx.span_err(item.span, "`#[binary(versioned)]` may only be applied to structs");
+			Err(Error)
+		},
 		ast::ItemKind::Enum(ref enum_def, _) => {
 			binary_expr_enum(
 				cx,
@@ -148,20 +170,72 @@ fn binary_expr(
 	}
 }
 
+/// Checks if the item is marked with `#[binary(versioned)]`.
+fn is_versioned(item: &Item) -> bool {
+	item.attrs.iter().any(|attr| match attr.node.value.node {
+		ast::MetaItemKind::List(ref name, ref items) if name == &"binary" => items.iter().any(|item| match item.node {
+			ast::MetaItemKind::Word(ref word) if word == &"versioned" => true,
+			_ => false,
+		}),
+		_ => false,
+	})
+}
+
 struct BinaryExpressions {
 	pub size: P<ast::Expr>,
 	pub write: P<ast::Expr>,
 	pub read: P<ast::Expr>,
+	/// Value read from no bytes at all.
+	pub empty: P<ast::Expr>,
+	pub len_params: P<ast::Expr>,
 }
 
+/// Converts printed type into a path usable in expressions.
+/// Generic arguments of every path segment get a turbofish (`a::B<C>::D<E<F>>` -> `a::B::<C>::D::<E<F>>`),
+/// nested arguments are already in type position and are left as they are.
+/// Types which are not paths (slices, arrays, tuples) are wrapped in a qualified path (`<[u8; 4]>`).
 fn replace_qualified(s: &str) -> String {
-	if let Some(pos) = s.find("<") {
-		let mut source = s.to_owned();
-		source.insert(pos, ':');
-		source.insert(pos, ':');
-		source
+	if s.starts_with('[') || s.starts_with('(') {
+		return format!("<{}>", s);
+	}
+
+	let mut result = String::with_capacity(s.len() + 4);
+	let mut depth = 0usize;
+	let mut last = None;
+	for c in s.chars() {
+		match c {
+			'<' => {
+				if depth == 0 && last != Some(':') {
+					result.push_str("::");
+				}
+				depth += 1;
+			},
+			'>' if depth > 0 => depth -= 1,
+			_ => {},
+		}
+		result.push(c);
+		if !c.is_whitespace() {
+			last = Some(c);
+		}
+	}
+	result
+}
+
+/// Checks if type is a `Vec`, also when given by its full path (like `::std::vec::Vec`).
+fn is_vec(ty: &ast::Ty) -> bool {
+	match ty.node {
+		ast::TyKind::Path(None, ref path) => path.segments.last().map_or(false, |segment| &*segment.identifier.name.as_str() == "Vec"),
+		_ => false,
+	}
+}
+
+/// Path of the function reading a field of type `ty` from the buffer.
+fn field_reader(ty: &ast::Ty) -> String {
+	let printed = ::syntax::print::pprust::ty_to_string(ty);
+	match is_vec(ty) {
+		true => format!("::ipc::binary::vec_from_bytes::<{}>", printed),
+		false => format!("::ipc::binary::from_bytes_or_empty::<{}>", printed),
 	}
-	else { s.to_owned() }
 }
 
 fn binary_expr_struct(
@@ -216,15 +290,15 @@ fn binary_expr_struct(
 		}
 	}).collect();
 
-	let first_size_expr = size_exprs[0].clone();
-	let mut total_size_expr = quote_expr!(cx, 0usize + $first_size_expr);
-	for index in 1..size_exprs.len() {
-		let next_expr = size_exprs[index].clone();
-		total_size_expr = quote_expr!(cx, $total_size_expr + $next_expr);
+	let mut total_size_expr = quote_expr!(cx, 0usize);
+	for size_expr in size_exprs {
+		total_size_expr = quote_expr!(cx, $total_size_expr + $size_expr);
 	}
 
+	// lengths of all the fields go first, so that they can be read before the fields themselves
 	let mut write_stmts = Vec::<ast::Stmt>::new();
-	write_stmts.push(quote_stmt!(cx, let mut offset = 0usize;).unwrap());
+	let mut field_write_stmts = Vec::<ast::Stmt>::new();
+	field_write_stmts.push(quote_stmt!(cx, let mut offset = 0usize;).unwrap());
 
 	let mut map_stmts = Vec::<ast::Stmt>::new();
 	let field_amount = builder.id(&format!("{}",fields.len()));
@@ -251,26 +325,39 @@ fn binary_expr_struct(
 		let raw_ident = ::syntax::print::pprust::ty_to_string(&codegen::strip_ptr(&field.ty));
 		match raw_ident.as_ref() {
 			"u8" => {
-				write_stmts.push(quote_stmt!(cx, let next_line = offset + 1;).unwrap());
-				write_stmts.push(quote_stmt!(cx, buffer[offset] = $member_expr; ).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, let next_line = offset + 1;).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, buffer[offset] = $member_expr; ).unwrap());
 			},
 			"[u8]" => {
-				write_stmts.push(quote_stmt!(cx, let size = $member_expr .len();).unwrap());
-				write_stmts.push(quote_stmt!(cx, let next_line = offset + size;).unwrap());
-				write_stmts.push(quote_stmt!(cx, length_stack.push_back(size);).unwrap());
-				write_stmts.push(quote_stmt!(cx, buffer[offset..next_line].clone_from_slice($member_expr); ).unwrap());
+				write_stmts.push(quote_stmt!(cx, length_stack.push_back($member_expr .len());).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, let size = $member_expr .len();).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, let next_line = offset + size;).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, buffer[offset..next_line].clone_from_slice($member_expr); ).unwrap());
+			}
+			_ if is_vec(&codegen::strip_ptr(&field.ty)) => {
+				write_stmts.push(quote_stmt!(cx, length_stack.push_back($member_expr .size());).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, let next_line = offset + $member_expr .size();).unwrap());
+				// vectors are written even if they take no bytes, element count goes to the length stack
+				field_write_stmts.push(quote_stmt!(cx,
+					if let Err(e) = $member_expr .to_bytes(&mut buffer[offset..next_line], length_stack) { return Err(e) };
+				).unwrap());
 			}
 			_ => {
-				write_stmts.push(quote_stmt!(cx, let next_line = offset + match $field_type_ident_qualified::len_params() {
+				write_stmts.push(quote_stmt!(cx, if $field_type_ident_qualified::len_params() != 0 {
+						length_stack.push_back($member_expr .size());
+					}).unwrap());
+				field_write_stmts.push(quote_stmt!(cx, let next_line = offset + match $field_type_ident_qualified::len_params() {
 						0 => mem::size_of::<$field_type_ident>(),
-						_ => { let size = $member_expr .size(); length_stack.push_back(size); size },
+						_ => $member_expr .size(),
+					}).unwrap());
+				// fields serialized to no bytes at all (e.g. `None`) are restored by `from_empty_bytes`
+				field_write_stmts.push(quote_stmt!(cx, if next_line > offset {
+						if let Err(e) = $member_expr .to_bytes(&mut buffer[offset..next_line], length_stack) { return Err(e) };
 					}).unwrap());
-				write_stmts.push(quote_stmt!(cx,
-					if let Err(e) = $member_expr .to_bytes(&mut buffer[offset..next_line], length_stack) { return Err(e) };).unwrap());
 			}
 		}
 
-		write_stmts.push(quote_stmt!(cx, offset = next_line; ).unwrap());
+		field_write_stmts.push(quote_stmt!(cx, offset = next_line; ).unwrap());
 
 		let field_index = builder.id(&format!("{}", index));
 		map_stmts.push(quote_stmt!(cx, map[$field_index] = total;).unwrap());
@@ -280,19 +367,24 @@ fn binary_expr_struct(
 				map_stmts.push(quote_stmt!(cx, total += 1;).unwrap());
 			},
 			"[u8]" => {
-				map_stmts.push(quote_stmt!(cx, let size = length_stack.pop_front().unwrap();).unwrap());
-				map_stmts.push(quote_stmt!(cx, total += size;).unwrap());
+				map_stmts.push(quote_stmt!(cx, let size = try!(length_stack.pop_front().ok_or(BinaryConvertError));).unwrap());
+				map_stmts.push(quote_stmt!(cx, total = try!(total.checked_add(size).ok_or(BinaryConvertError));).unwrap());
 			},
 			_ => {
 				map_stmts.push(quote_stmt!(cx, let size = match $field_type_ident_qualified::len_params() {
 						0 => mem::size_of::<$field_type_ident>(),
-						_ => length_stack.pop_front().unwrap(),
+						_ => try!(length_stack.pop_front().ok_or(BinaryConvertError)),
 					}).unwrap());
-				map_stmts.push(quote_stmt!(cx, total += size;).unwrap());
+				map_stmts.push(quote_stmt!(cx, total = try!(total.checked_add(size).ok_or(BinaryConvertError));).unwrap());
 			}
 		}
 	};
 
+	// lengths come from the (untrusted) message, so all the field ranges have to fit in the buffer
+	map_stmts.push(quote_stmt!(cx, if total > buffer.len() { return Err(BinaryConvertError); }).unwrap());
+
+	write_stmts.extend(field_write_stmts);
+
 	let read_expr = match fields.iter().any(|f| codegen::has_ptr(&f.ty)) {
 		true => {
 			// cannot create structs with pointers
@@ -314,6 +406,8 @@ fn binary_expr_struct(
 		size: total_size_expr,
 		write: quote_expr!(cx, { $write_stmts; Ok(()) } ),
 		read: read_expr,
+		empty: quote_expr!(cx, Err(BinaryConvertError)),
+		len_params: quote_expr!(cx, 1),
 	})
 }
 
@@ -323,10 +417,18 @@ fn binary_expr_item_struct(
 	builder: &aster::AstBuilder,
 	_impl_generics: &ast::Generics,
 	ty: P<ast::Ty>,
-	span: Span,
+	_span: Span,
 	variant_data: &ast::VariantData,
 ) -> Result<BinaryExpressions, Error> {
 	match *variant_data {
+		ast::VariantData::Tuple(ref fields, _) if fields.len() == 1 => {
+			binary_expr_newtype(
+				cx,
+				&builder,
+				ty,
+				&fields[0],
+			)
+		},
 		ast::VariantData::Tuple(ref fields, _) => {
 			binary_expr_struct(
 				cx,
@@ -347,13 +449,96 @@ fn binary_expr_item_struct(
 				None,
 			)
 		},
+		ast::VariantData::Unit(_) => {
+			let unit = builder.id(replace_qualified(&::syntax::print::pprust::ty_to_string(&ty)));
+			Ok(BinaryExpressions {
+				size: quote_expr!(cx, 0usize),
+				write: quote_expr!(cx, Ok(())),
+				read: quote_expr!(cx, Ok($unit)),
+				empty: quote_expr!(cx, Ok($unit)),
+				len_params: quote_expr!(cx, 1),
+			})
+		},
+	}
+}
+
+/// Single-field tuple struct is serialized exactly like the wrapped type.
+fn binary_expr_newtype(
+	cx: &ExtCtxt,
+	builder: &aster::AstBuilder,
+	ty: P<ast::Ty>,
+	field: &ast::StructField,
+) -> Result<BinaryExpressions, Error> {
+	if codegen::has_ptr(&field.ty) {
+		return Ok(BinaryExpressions {
+			size: quote_expr!(cx, self.0.size()),
+			write: quote_expr!(cx, self.0.to_bytes(buffer, length_stack)),
+			// cannot create structs with pointers
+			read: quote_expr!(cx, Err(BinaryConvertError)),
+			empty: quote_expr!(cx, Err(BinaryConvertError)),
+			len_params: quote_expr!(cx, 1),
+		});
+	}
+
+	let wrapper = builder.id(replace_qualified(&::syntax::print::pprust::ty_to_string(&ty)));
+	let inner = builder.id(replace_qualified(&::syntax::print::pprust::ty_to_string(&field.ty)));
+
+	Ok(BinaryExpressions {
+		size: quote_expr!(cx, self.0.size()),
+		write: quote_expr!(cx, self.0.to_bytes(buffer, length_stack)),
+		read: quote_expr!(cx, Ok($wrapper(try!($inner::from_bytes(buffer, length_stack))))),
+		empty: quote_expr!(cx, Ok($wrapper(try!($inner::from_empty_bytes())))),
+		len_params: quote_expr!(cx, $inner::len_params()),
+	})
+}
+
+/// Versioned layout: number of fields and byte length of each field followed by the fields,
+/// every one of them serialized with its own length stack. Readers skip trailing fields they don't know
+/// and fill the fields missing in the layout with `Default`.
+fn binary_expr_versioned_struct(
+	cx: &ExtCtxt,
+	builder: &aster::AstBuilder,
+	ty: P<ast::Ty>,
+	span: Span,
+	variant_data: &ast::VariantData,
+) -> Result<BinaryExpressions, Error> {
+	let fields = match *variant_data {
+		ast::VariantData::Struct(ref fields, _) => fields,
 		_ => {
-			cx.span_bug(span,
-				&format!("#[derive(Binary)] Unsupported struct content, expected tuple/struct, found: {:?}",
-					variant_data));
-			Err(Error)
+			cx.span_err(span, "`#[binary(versioned)]` may only be applied to structs with named fields");
+			return Err(Error);
 		},
+	};
+
+	if fields.iter().any(|f| codegen::has_ptr(&f.ty)) {
+		cx.span_err(span, "`#[binary(versioned)]` structs cannot contain references");
+		return Err(Error);
 	}
+
+	let field_amount = builder.id(&format!("{}", fields.len()));
+	let mut size_stmts = Vec::<ast::Stmt>::new();
+	let mut write_stmts = Vec::<ast::Stmt>::new();
+	size_stmts.push(quote_stmt!(cx, let mut size = ::ipc::binary::versioned_header_size($field_amount);).unwrap());
+	write_stmts.push(quote_stmt!(cx, let mut fields = Vec::with_capacity($field_amount);).unwrap());
+	for field in fields {
+		let field_id = builder.id(field.ident.unwrap());
+		size_stmts.push(quote_stmt!(cx, size += ::ipc::binary::serialized_size(&self. $field_id);).unwrap());
+		write_stmts.push(quote_stmt!(cx, fields.push(try!(::ipc::binary::serialize(&self. $field_id)));).unwrap());
+	}
+
+	let instance_create = versioned_fields_sequence(cx, &ty, fields);
+
+	Ok(BinaryExpressions {
+		size: quote_expr!(cx, { $size_stmts; size }),
+		write: quote_expr!(cx, { $write_stmts; ::ipc::binary::write_versioned(&fields, buffer) }),
+		read: quote_expr!(cx, {
+			let fields = try!(::ipc::binary::read_versioned(buffer));
+			$instance_create;
+			Ok(result)
+		}),
+		empty: quote_expr!(cx, Err(BinaryConvertError)),
+		len_params: quote_expr!(cx, 1),
+	})
 }
 
 fn binary_expr_enum(
@@ -365,10 +550,29 @@ fn binary_expr_enum(
 	span: Span,
 	enum_def: &ast::EnumDef,
 ) -> Result<BinaryExpressions, Error> {
+	let explicit_indices: Vec<Option<u64>> = try!(
+		enum_def.variants.iter()
+			.map(|variant| explicit_variant_index(cx, variant))
+			.collect()
+	);
+	let indices = match variant_indices(&explicit_indices) {
+		Ok(indices) => indices,
+		Err(err) => {
+			let (position, message) = match err {
+				VariantIndexError::Mixed(position) =>
+					(position, "either all or none of the variants of `#[derive(Binary)]` enum should have `#[binary(index = N)]`"),
+				VariantIndexError::Duplicate(position) => (position, "duplicate `#[binary(index = N)]` of enum variant"),
+				VariantIndexError::TooLarge(position) => (position, "`#[binary(index = N)]` of enum variant should be at most 255"),
+			};
+			cx.span_err(enum_def.variants[position].span, message);
+			return Err(Error);
+		},
+	};
+
 	let arms: Vec<_> = try!(
 		enum_def.variants.iter()
-			.enumerate()
-			.map(|(variant_index, variant)| {
+			.zip(indices.into_iter())
+			.map(|(variant, variant_index)| {
 				binary_expr_variant(
 					cx,
 					builder,
@@ -393,10 +597,78 @@ fn binary_expr_enum(
 	Ok(BinaryExpressions {
 		size: quote_expr!(cx, 1usize + match *self { $size_arms }),
 		write: quote_expr!(cx, match *self { $write_arms }; ),
-		read: quote_expr!(cx, match buffer[0] { $read_arms }),
+		read: quote_expr!(cx, {
+			if buffer.is_empty() { return Err(BinaryConvertError); }
+			match buffer[0] { $read_arms }
+		}),
+		empty: quote_expr!(cx, Err(BinaryConvertError)),
+		len_params: quote_expr!(cx, 1),
 	})
 }
 
+/// Problem with explicit variant indices of an enum.
+#[derive(Debug, PartialEq)]
+enum VariantIndexError {
+	/// Only some of the variants have explicit index. Position of the first variant which differs from the first one.
+	Mixed(usize),
+	/// Index is used by more than one variant. Position of the second variant using it.
+	Duplicate(usize),
+	/// Index doesn't fit into the variant byte. Position of the variant.
+	TooLarge(usize),
+}
+
+/// Reads `N` of `#[binary(index = N)]` attribute of enum variant.
+fn explicit_variant_index(cx: &ExtCtxt, variant: &ast::Variant) -> Result<Option<u64>, Error> {
+	for attr in &variant.node.attrs {
+		let items = match attr.node.value.node {
+			ast::MetaItemKind::List(ref name, ref items) if name == &"binary" => items,
+			_ => continue,
+		};
+		for item in items {
+			match item.node {
+				ast::MetaItemKind::NameValue(ref key, ref lit) if key == &"index" => match lit.node {
+					ast::LitKind::Int(index, _) => return Ok(Some(index)),
+					_ => {
+						cx.span_err(item.span, "`#[binary(index = N)]` expects an integer");
+						return Err(Error);
+					},
+				},
+				_ => {
+					cx.span_err(item.span, "unknown `#[binary]` attribute of enum variant, expected `index = N`");
+					return Err(Error);
+				},
+			}
+		}
+	}
+	Ok(None)
+}
+
+/// Returns variant bytes of the enum variants: explicit indices if all of the variants have one,
+/// positional indices if none of them does.
+fn variant_indices(explicit: &[Option<u64>]) -> Result<Vec<usize>, VariantIndexError> {
+	let has_explicit = explicit.first().map_or(false, Option::is_some);
+	if let Some(position) = explicit.iter().position(|index| index.is_some() != has_explicit) {
+		return Err(VariantIndexError::Mixed(position));
+	}
+	if !has_explicit {
+		return Ok((0..explicit.len()).collect());
+	}
+
+	let mut indices = Vec::with_capacity(explicit.len());
+	for (position, index) in explicit.iter().enumerate() {
+		let index = index.expect("all the variants have explicit index; qed");
+		if index > 255 {
+			return Err(VariantIndexError::TooLarge(position));
+		}
+		let index = index as usize;
+		if indices.contains(&index) {
+			return Err(VariantIndexError::Duplicate(position));
+		}
+		indices.push(index);
+	}
+	Ok(indices)
+}
+
 struct BinaryArm {
 	size: ast::Arm,
 	write: ast::Arm,
@@ -478,10 +750,8 @@ fn fields_sequence(
 				tt.push(
 					Token(
 						_sp,
-						token::Ident(ext_cx.ident_of(&replace_qualified(&::syntax::print::pprust::ty_to_string(&field.ty))))
+						token::Ident(ext_cx.ident_of(&field_reader(&field.ty)))
 					));
-				tt.push(Token(_sp, token::ModSep));
-				tt.push(Token(_sp, token::Ident(ext_cx.ident_of("from_bytes"))));
 				tt.push(Token(_sp, token::OpenDelim(token::Paren)));
 
 				tt.push(Token(_sp, token::BinOp(token::And)));
@@ -596,10 +866,8 @@ fn named_fields_sequence(
 				tt.push(Token(
 					_sp,
 					token::Ident(
-						ext_cx.ident_of(&replace_qualified(&::syntax::print::pprust::ty_to_string(&field.ty)))
+						ext_cx.ident_of(&field_reader(&field.ty))
 						)));
-				tt.push(Token(_sp, token::ModSep));
-				tt.push(Token(_sp, token::Ident(ext_cx.ident_of("from_bytes"))));
 				tt.push(Token(_sp, token::OpenDelim(token::Paren)));
 
 				tt.push(Token(_sp, token::BinOp(token::And)));
@@ -635,6 +903,48 @@ fn named_fields_sequence(
 	).unwrap()
 }
 
+fn versioned_fields_sequence(
+	ext_cx: &ExtCtxt,
+	ty: &P<ast::Ty>,
+	fields: &[ast::StructField],
+) -> ast::Stmt {
+	use syntax::parse::token;
+	use syntax::ast::TokenTree::Token;
+
+	::quasi::parse_stmt_panic(&mut ::syntax::parse::new_parser_from_tts(
+		ext_cx.parse_sess(),
+		ext_cx.cfg(),
+		{
+			let _sp = ext_cx.call_site();
+			let mut tt = ::std::vec::Vec::new();
+			tt.push(Token(_sp, token::Ident(ext_cx.ident_of("let"))));
+			tt.push(Token(_sp, token::Ident(ext_cx.ident_of("result"))));
+			tt.push(Token(_sp, token::Eq));
+			tt.push(Token(_sp, token::Ident(ext_cx.ident_of(&::syntax::print::pprust::ty_to_string(ty)))));
+			tt.push(Token(_sp, token::OpenDelim(token::Brace)));
+
+			for (idx, field) in fields.iter().enumerate() {
+				tt.push(Token(_sp, token::Ident(field.ident.clone().unwrap())));
+				tt.push(Token(_sp, token::Colon));
+				tt.push(Token(_sp, token::Ident(ext_cx.ident_of("try!"))));
+				tt.push(Token(_sp, token::OpenDelim(token::Paren)));
+				tt.push(Token(_sp, token::Ident(ext_cx.ident_of("::ipc::binary::versioned_field"))));
+				tt.push(Token(_sp, token::OpenDelim(token::Paren)));
+				tt.push(Token(_sp, token::BinOp(token::And)));
+				tt.push(Token(_sp, token::Ident(ext_cx.ident_of("fields"))));
+				tt.push(Token(_sp, token::Comma));
+				tt.push(Token(_sp, token::Ident(ext_cx.ident_of(&format!("{}", idx)))));
+				tt.push(Token(_sp, token::CloseDelim(token::Paren)));
+				tt.push(Token(_sp, token::CloseDelim(token::Paren)));
+				tt.push(Token(_sp, token::Comma));
+			}
+
+			tt.push(Token(_sp, token::CloseDelim(token::Brace)));
+			tt
+		})
+	).unwrap()
+}
+
 fn binary_expr_variant(
 	cx: &ExtCtxt,
 	builder: &aster::AstBuilder,
@@ -693,7 +1003,7 @@ fn binary_expr_variant(
 						let buffer = &mut buffer[1..];
 						$write_expr
 				}),
-				read: quote_arm!(cx, $variant_index_ident => { $read_expr } ),
+				read: quote_arm!(cx, $variant_index_ident => { let buffer = &buffer[1..]; $read_expr } ),
 			})
 		},
 		ast::VariantData::Struct(ref fields, _) => {
@@ -728,8 +1038,48 @@ fn binary_expr_variant(
 						let buffer = &mut buffer[1..];
 						$write_expr
 				}),
-				read: quote_arm!(cx, $variant_index_ident => { $read_expr } ),
+				read: quote_arm!(cx, $variant_index_ident => { let buffer = &buffer[1..]; $read_expr } ),
 			})
 		},
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{variant_indices, VariantIndexError, field_reader};
+	use syntax::ast;
+	use syntax::parse::{ParseSess, new_parser_from_source_str};
+	use syntax::ptr::P;
+
+	fn parse_ty(ty: &str) -> P<ast::Ty> {
+		let sess = ParseSess::new();
+		let ty = new_parser_from_source_str(&sess, Vec::new(), "ty".to_owned(), ty.to_owned()).parse_ty().unwrap();
+		ty
+	}
+
+	#[test]
+	fn vec_fields_are_always_read() {
+		assert_eq!(field_reader(&parse_ty("Vec<H256>")), "::ipc::binary::vec_from_bytes::<Vec<H256>>");
+		assert_eq!(field_reader(&parse_ty("::std::vec::Vec<H256>")), "::ipc::binary::vec_from_bytes::<::std::vec::Vec<H256>>");
+		assert_eq!(field_reader(&parse_ty("Option<Vec<u8>>")), "::ipc::binary::from_bytes_or_empty::<Option<Vec<u8>>>");
+	}
+
+	#[test]
+	fn positional_variant_indices() {
+		assert_eq!(variant_indices(&[None, None, None]), Ok(vec![0, 1, 2]));
+		assert_eq!(variant_indices(&[]), Ok(vec![]));
+	}
+
+	#[test]
+	fn explicit_variant_indices() {
+		assert_eq!(variant_indices(&[Some(5), Some(0), Some(255)]), Ok(vec![5, 0, 255]));
+	}
+
+	#[test]
+	fn invalid_variant_indices() {
+		assert_eq!(variant_indices(&[Some(1), None, Some(2)]), Err(VariantIndexError::Mixed(1)));
+		assert_eq!(variant_indices(&[None, Some(1)]), Err(VariantIndexError::Mixed(1)));
+		assert_eq!(variant_indices(&[Some(1), Some(2), Some(1)]), Err(VariantIndexError::Duplicate(2)));
+		assert_eq!(variant_indices(&[Some(256)]), Err(VariantIndexError::TooLarge(0)));
+	}
+}
@@ -19,7 +19,8 @@
 use util::bytes::Populatable;
 use util::numbers::{U256, U512, H256, H2048, Address};
 use std::mem;
-use std::collections::VecDeque;
+use std::collections::{VecDeque, HashMap};
+use std::hash::Hash;
 use std::ops::Range;
 
 #[derive(Debug)]
@@ -49,7 +50,7 @@ impl<T> BinaryConvertable for Option<T> where T: BinaryConvertable {
 	}
 
 	fn to_bytes(&self, buffer: &mut [u8], length_stack: &mut VecDeque<usize>) -> Result<(), BinaryConvertError> {
-		match *self { None => Err(BinaryConvertError), Some(ref val) => val.to_bytes(buffer, length_stack) }
+		match *self { None => Ok(()), Some(ref val) => val.to_bytes(buffer, length_stack) }
 	}
 
 	fn from_bytes(buffer: &[u8], length_stack: &mut VecDeque<usize>) -> Result<Self, BinaryConvertError> {
@@ -75,7 +76,7 @@ impl<E: BinaryConvertable> BinaryConvertable for Result<(), E> {
 
 	fn to_bytes(&self, buffer: &mut [u8], length_stack: &mut VecDeque<usize>) -> Result<(), BinaryConvertError> {
 		match *self {
-			Ok(_) => Err(BinaryConvertError),
+			Ok(_) => Ok(()),
 			Err(ref e) => Ok(try!(e.to_bytes(buffer, length_stack))),
 		}
 	}
@@ -105,14 +106,17 @@ impl<R: BinaryConvertable, E: BinaryConvertable> BinaryConvertable for Result<R,
 	fn to_bytes(&self, buffer: &mut [u8], length_stack: &mut VecDeque<usize>) -> Result<(), BinaryConvertError> {
 		match *self {
 			Ok(ref r) => { buffer[0] = 0; Ok(try!(r.to_bytes(&mut buffer[1..], length_stack))) },
-			Err(ref e) => { buffer[1] = 1; Ok(try!(e.to_bytes(&mut buffer[1..], length_stack))) },
+			Err(ref e) => { buffer[0] = 1; Ok(try!(e.to_bytes(&mut buffer[1..], length_stack))) },
 		}
 	}
 
 	fn from_bytes(buffer: &[u8], length_stack: &mut VecDeque<usize>) -> Result<Self, BinaryConvertError> {
+		if buffer.is_empty() {
+			return Err(BinaryConvertError);
+		}
 		match buffer[0] {
-			0 => Ok(Ok(try!(R::from_bytes(&buffer[1..], length_stack)))),
-			1 => Ok(Err(try!(E::from_bytes(&buffer[1..], length_stack)))),
+			0 => Ok(Ok(try!(from_bytes_or_empty::<R>(&buffer[1..], length_stack)))),
+			1 => Ok(Err(try!(from_bytes_or_empty::<E>(&buffer[1..], length_stack)))),
 			_ => Err(BinaryConvertError)
 		}
 	}
@@ -131,55 +135,106 @@ impl<T> BinaryConvertable for Vec<T> where T: BinaryConvertable {
 	}
 
 	fn to_bytes(&self, buffer: &mut [u8], length_stack: &mut VecDeque<usize>) -> Result<(), BinaryConvertError> {
+		// Elements of variable size can't be counted from the buffer length (some of them may take no bytes at all),
+		// so the element count precedes their lengths on the stack.
+		if T::len_params() != 0 {
+			length_stack.push_back(self.len());
+		}
 		let mut offset = 0usize;
 		for item in self.iter() {
-			let next_size = match T::len_params() {
-				0 => mem::size_of::<T>(),
-				_ => { let size = item.size(); length_stack.push_back(size); size },
-			};
-			if next_size > 0 {
-				let item_end = offset + next_size;
-				try!(item.to_bytes(&mut buffer[offset..item_end], length_stack));
-				offset = item_end;
-			}
+			offset = try!(write_item(item, buffer, offset, length_stack));
 		}
 		Ok(())
 	}
 
 	fn from_bytes(buffer: &[u8], length_stack: &mut VecDeque<usize>) -> Result<Self, BinaryConvertError> {
-		let mut index = 0;
-		let mut result = Self::with_capacity(
-			match T::len_params() {
-				0 => buffer.len() /  mem::size_of::<T>(),
-				_ => 128,
-			});
-
-		loop {
-			let next_size = match T::len_params() {
-				0 => mem::size_of::<T>(),
-				_ => try!(length_stack.pop_front().ok_or(BinaryConvertError)),
-			};
-			let item = if next_size == 0 {
-				try!(T::from_empty_bytes())
-			}
-			else {
-				try!(T::from_bytes(&buffer[index..index+next_size], length_stack))
-			};
+		let count = match T::len_params() {
+			0 => {
+				let item_size = mem::size_of::<T>();
+				if item_size == 0 || buffer.len() % item_size != 0 {
+					return Err(BinaryConvertError);
+				}
+				buffer.len() / item_size
+			},
+			// every element has its length on the stack, so the count can't exceed the stack size
+			_ => try!(length_stack.pop_front().ok_or(BinaryConvertError)),
+		};
+		if T::len_params() != 0 && count > length_stack.len() {
+			return Err(BinaryConvertError);
+		}
+
+		let mut result = Self::with_capacity(count);
+		let mut offset = 0usize;
+		for _ in 0..count {
+			let (item, next) = try!(read_item::<T>(buffer, offset, length_stack));
 			result.push(item);
+			offset = next;
+		}
+		if offset != buffer.len() {
+			return Err(BinaryConvertError);
+		}
+		Ok(result)
+	}
 
-			index = index + next_size;
-			if index == buffer.len() { break; }
-			if index > buffer.len() {
-				return Err(BinaryConvertError)
-			}
+	fn from_empty_bytes() -> Result<Self, BinaryConvertError> {
+		Ok(Self::new())
+	}
+
+	fn len_params() -> usize {
+		1
+	}
+}
+
+impl<K, V> BinaryConvertable for HashMap<K, V> where K: BinaryConvertable + Hash + Eq, V: BinaryConvertable {
+	fn size(&self) -> usize {
+		self.iter().fold(0usize, |acc, (k, v)| acc + k.size() + v.size())
+	}
+
+	fn to_bytes(&self, buffer: &mut [u8], length_stack: &mut VecDeque<usize>) -> Result<(), BinaryConvertError> {
+		// Same as for `Vec`, entries of variable size may take no bytes at all, so the entry count precedes their lengths.
+		if K::len_params() + V::len_params() != 0 {
+			length_stack.push_back(self.len());
+		}
+		let mut offset = 0usize;
+		for (key, value) in self.iter() {
+			offset = try!(write_item(key, buffer, offset, length_stack));
+			offset = try!(write_item(value, buffer, offset, length_stack));
+		}
+		Ok(())
+	}
 
+	fn from_bytes(buffer: &[u8], length_stack: &mut VecDeque<usize>) -> Result<Self, BinaryConvertError> {
+		let count = match K::len_params() + V::len_params() {
+			0 => {
+				let entry_size = mem::size_of::<K>() + mem::size_of::<V>();
+				if entry_size == 0 || buffer.len() % entry_size != 0 {
+					return Err(BinaryConvertError);
+				}
+				buffer.len() / entry_size
+			},
+			// every entry has at least one length on the stack
+			_ => try!(length_stack.pop_front().ok_or(BinaryConvertError)),
+		};
+		if K::len_params() + V::len_params() != 0 && count > length_stack.len() {
+			return Err(BinaryConvertError);
 		}
 
+		let mut result = HashMap::with_capacity(count);
+		let mut offset = 0usize;
+		for _ in 0..count {
+			let (key, next) = try!(read_item::<K>(buffer, offset, length_stack));
+			let (value, next) = try!(read_item::<V>(buffer, next, length_stack));
+			result.insert(key, value);
+			offset = next;
+		}
+		if offset != buffer.len() {
+			return Err(BinaryConvertError);
+		}
 		Ok(result)
 	}
 
 	fn from_empty_bytes() -> Result<Self, BinaryConvertError> {
-		Ok(Self::new())
+		Ok(HashMap::new())
 	}
 
 	fn len_params() -> usize {
@@ -282,6 +337,56 @@ impl BinaryConvertable for Vec<u8> {
 	}
 }
 
+/// Reads `Vec` field of a struct. Vectors of variable-size elements keep their element count on the length stack,
+/// so unlike other fields they are read even if they take no bytes at all.
+pub fn vec_from_bytes<V>(buffer: &[u8], length_stack: &mut VecDeque<usize>) -> Result<V, BinaryConvertError>
+	where V: BinaryConvertable
+{
+	V::from_bytes(buffer, length_stack)
+}
+
+/// Reads value from `buffer`. Values serialized to no bytes at all (e.g. `None`) are created by `from_empty_bytes`.
+pub fn from_bytes_or_empty<T>(buffer: &[u8], length_stack: &mut VecDeque<usize>) -> Result<T, BinaryConvertError>
+	where T: BinaryConvertable
+{
+	match buffer.len() {
+		0 => T::from_empty_bytes(),
+		_ => T::from_bytes(buffer, length_stack),
+	}
+}
+
+/// Writes item of a sequence at `offset`, its size is pushed to the length stack unless it's fixed.
+/// Returns offset of the next item.
+fn write_item<T>(item: &T, buffer: &mut [u8], offset: usize, length_stack: &mut VecDeque<usize>) -> Result<usize, BinaryConvertError>
+	where T: BinaryConvertable
+{
+	let size = match T::len_params() {
+		0 => mem::size_of::<T>(),
+		_ => { let size = item.size(); length_stack.push_back(size); size },
+	};
+	let end = offset + size;
+	if size > 0 {
+		try!(item.to_bytes(&mut buffer[offset..end], length_stack));
+	}
+	Ok(end)
+}
+
+/// Reads item of a sequence written by `write_item`. Returns the item and offset of the next one.
+fn read_item<T>(buffer: &[u8], offset: usize, length_stack: &mut VecDeque<usize>) -> Result<(T, usize), BinaryConvertError>
+	where T: BinaryConvertable
+{
+	let size = match T::len_params() {
+		0 => mem::size_of::<T>(),
+		_ => try!(length_stack.pop_front().ok_or(BinaryConvertError)),
+	};
+	let end = offset + size;
+	if end > buffer.len() {
+		return Err(BinaryConvertError);
+	}
+	let item = try!(from_bytes_or_empty(&buffer[offset..end], length_stack));
+	Ok((item, end))
+}
+
 pub fn deserialize_from<T, R>(r: &mut R) -> Result<T, BinaryConvertError>
 	where R: ::std::io::Read,
 		T: BinaryConvertable
@@ -313,7 +418,7 @@ pub fn deserialize_from<T, R>(r: &mut R) -> Result<T, BinaryConvertError>
 			//try!(r.read(&mut size_buffer).map_err(|_| BinaryConvertError));
 			let size = try!(u64::from_bytes(&payload[8+stack_len*8..16+stack_len*8], &mut fake_stack)) as usize;
 			match size {
-				0 => {
+				0 if stack_len == 0 => {
 					T::from_empty_bytes()
 				},
 				_ => {
@@ -350,16 +455,17 @@ pub fn serialize_into<T, W>(t: &T, w: &mut W) -> Result<(), BinaryConvertError>
 			let mut size_buffer = [0u8; 8];
 
 			let size = t.size();
-			if size == 0 {
+			let mut buffer = Vec::with_capacity(size);
+			unsafe { buffer.set_len(size); }
+			try!(t.to_bytes(&mut buffer[..], &mut length_stack));
+
+			// values taking no bytes can still have lengths on the stack (e.g. vector of `None`s)
+			if size == 0 && length_stack.is_empty() {
 				try!(w.write(&size_buffer).map_err(|_| BinaryConvertError));
 				try!(w.write(&size_buffer).map_err(|_| BinaryConvertError));
 				return Ok(());
 			}
 
-			let mut buffer = Vec::with_capacity(size);
-			unsafe { buffer.set_len(size); }
-			try!(t.to_bytes(&mut buffer[..], &mut length_stack));
-
 			let stack_len = length_stack.len();
 			try!((stack_len as u64).to_bytes(&mut size_buffer[..], &mut fake_stack));
 			try!(w.write(&size_buffer[..]).map_err(|_| BinaryConvertError));
@@ -396,6 +502,71 @@ pub fn serialize<T: BinaryConvertable>(t: &T) -> Result<Vec<u8>, BinaryConvertEr
 	Ok(into_inner)
 }
 
+/// Size of the header of versioned layout (`#[binary(versioned)]`) with given number of fields.
+pub fn versioned_header_size(fields: usize) -> usize {
+	mem::size_of::<u32>() * (fields + 1)
+}
+
+/// Size of `t` serialized by `serialize`, i.e. as a field of versioned layout.
+pub fn serialized_size<T: BinaryConvertable>(t: &T) -> usize {
+	serialize(t).map(|bytes| bytes.len()).unwrap_or(0)
+}
+
+/// Writes fields serialized by `serialize` in versioned layout:
+/// number of fields and byte length of each field followed by the fields.
+pub fn write_versioned(fields: &[Vec<u8>], buffer: &mut [u8]) -> Result<(), BinaryConvertError> {
+	let mut fake_stack = VecDeque::new();
+	let header_size = versioned_header_size(fields.len());
+	if buffer.len() != fields.iter().fold(header_size, |acc, field| acc + field.len()) {
+		return Err(BinaryConvertError);
+	}
+
+	try!((fields.len() as u32).to_bytes(&mut buffer[0..4], &mut fake_stack));
+	let mut offset = header_size;
+	for (index, field) in fields.iter().enumerate() {
+		try!((field.len() as u32).to_bytes(&mut buffer[4 + index * 4..8 + index * 4], &mut fake_stack));
+		buffer[offset..offset + field.len()].clone_from_slice(field);
+		offset = offset + field.len();
+	}
+	Ok(())
+}
+
+/// Splits versioned layout written by `write_versioned` into serialized fields.
+pub fn read_versioned(buffer: &[u8]) -> Result<Vec<&[u8]>, BinaryConvertError> {
+	let mut fake_stack = VecDeque::new();
+	if buffer.len() < 4 {
+		return Err(BinaryConvertError);
+	}
+	let count = try!(u32::from_bytes(&buffer[0..4], &mut fake_stack)) as usize;
+	let header_size = versioned_header_size(count);
+	if buffer.len() < header_size {
+		return Err(BinaryConvertError);
+	}
+
+	let mut fields = Vec::with_capacity(count);
+	let mut offset = header_size;
+	for index in 0..count {
+		let size = try!(u32::from_bytes(&buffer[4 + index * 4..8 + index * 4], &mut fake_stack)) as usize;
+		if offset + size > buffer.len() {
+			return Err(BinaryConvertError);
+		}
+		fields.push(&buffer[offset..offset + size]);
+		offset = offset + size;
+	}
+	Ok(fields)
+}
+
+/// Reads field `index` of versioned layout split by `read_versioned`.
+/// Fields missing in the layout (written by an older version of the struct) are `Default`.
+pub fn versioned_field<T>(fields: &[&[u8]], index: usize) -> Result<T, BinaryConvertError>
+	where T: BinaryConvertable + Default
+{
+	match fields.get(index) {
+		Some(bytes) => deserialize(bytes),
+		None => Ok(T::default()),
+	}
+}
+
 #[macro_export]
 macro_rules! binary_fixed_size {
 	($target_ty: ty) => {
@@ -490,7 +661,9 @@ fn vec_deserialize_chained() {
 	let result = v.to_bytes(&mut data[..], &mut length_stack);
 
 	assert!(result.is_ok());
-	assert_eq!(4, length_stack.len());
+	// element count followed by lengths of the elements
+	assert_eq!(5, length_stack.len());
+	assert_eq!(Some(&4), length_stack.front());
 }
 
 #[test]
@@ -526,10 +699,11 @@ fn serialize_into_ok() {
 	v.push(Some(12u64));
 
 	serialize_into(&v, &mut buff).unwrap();
-	assert_eq!(5, buff.get_ref()[0]);
-	assert_eq!(8, buff.get_ref()[8]);
-	assert_eq!(0, buff.get_ref()[16]);
-	assert_eq!(8, buff.get_ref()[24]);
+	assert_eq!(6, buff.get_ref()[0]);
+	assert_eq!(5, buff.get_ref()[8]);
+	assert_eq!(8, buff.get_ref()[16]);
+	assert_eq!(0, buff.get_ref()[24]);
+	assert_eq!(8, buff.get_ref()[32]);
 }
 
 #[test]
@@ -619,3 +793,92 @@ fn deserialize_opt_vec_in_out() {
 
 	assert!(vec.is_none());
 }
+
+#[test]
+fn serialize_result_err() {
+	let result: Result<u64, u32> = Err(7u32);
+	let payload = serialize(&result).unwrap();
+	let de_result = deserialize::<Result<u64, u32>>(&payload).unwrap();
+
+	assert_eq!(result, de_result);
+}
+
+#[test]
+fn serialize_hash_map() {
+	let mut map = HashMap::new();
+	map.insert(1u64, Some(vec![1u8, 2u8]));
+	map.insert(2u64, None);
+	map.insert(3u64, Some(vec![3u8]));
+
+	let payload = serialize(&map).unwrap();
+	let de_map = deserialize::<HashMap<u64, Option<Vec<u8>>>>(&payload).unwrap();
+
+	assert_eq!(map, de_map);
+}
+
+#[test]
+fn serialize_empty_hash_map() {
+	let map = HashMap::<Address, U256>::new();
+	let payload = serialize(&map).unwrap();
+
+	assert_eq!(vec![0u8; 16], payload);
+	assert!(deserialize::<HashMap<Address, U256>>(&payload).unwrap().is_empty());
+}
+
+#[test]
+fn serialize_hash_map_of_empty_entries() {
+	let mut map = HashMap::new();
+	map.insert(Vec::new(), None);
+	map.insert(vec![1u8], None);
+	let payload = serialize(&map).unwrap();
+
+	assert_eq!(map, deserialize::<HashMap<Vec<u8>, Option<u64>>>(&payload).unwrap());
+
+	let mut map = HashMap::new();
+	map.insert(Vec::<u8>::new(), None::<u64>);
+	let payload = serialize(&map).unwrap();
+
+	assert_eq!(map, deserialize::<HashMap<Vec<u8>, Option<u64>>>(&payload).unwrap());
+}
+
+#[test]
+fn serialize_vec_with_variable_size_elements() {
+	let v = vec![vec![1u8, 2u8], Vec::new(), vec![3u8], Vec::new()];
+	let payload = serialize(&v).unwrap();
+
+	assert_eq!(v, deserialize::<Vec<Vec<u8>>>(&payload).unwrap());
+}
+
+#[test]
+fn serialize_vec_of_empty_elements() {
+	let v: Vec<Option<u64>> = vec![None, None];
+	let payload = serialize(&v).unwrap();
+
+	assert_eq!(v, deserialize::<Vec<Option<u64>>>(&payload).unwrap());
+	assert!(deserialize::<Vec<Vec<u8>>>(&serialize(&Vec::<Vec<u8>>::new()).unwrap()).unwrap().is_empty());
+}
+
+#[test]
+fn vec_rejects_count_exceeding_lengths() {
+	let mut length_stack = VecDeque::new();
+	length_stack.push_back(3);
+	length_stack.push_back(1);
+
+	assert!(Vec::<Vec<u8>>::from_bytes(&[1u8], &mut length_stack).is_err());
+	assert!(Vec::<u64>::from_bytes(&[1u8, 2u8, 3u8], &mut VecDeque::new()).is_err());
+}
+
+#[test]
+fn versioned_layout() {
+	let fields = vec![serialize(&5u64).unwrap(), serialize(&Some(vec![1u8, 2u8])).unwrap()];
+	let mut buffer = vec![0u8; versioned_header_size(2) + fields[0].len() + fields[1].len()];
+
+	write_versioned(&fields, &mut buffer).unwrap();
+	let read = read_versioned(&buffer).unwrap();
+
+	assert_eq!(read.len(), 2);
+	assert_eq!(5u64, versioned_field::<u64>(&read, 0).unwrap());
+	assert_eq!(Some(vec![1u8, 2u8]), versioned_field::<Option<Vec<u8>>>(&read, 1).unwrap());
+	assert_eq!(0u32, versioned_field::<u32>(&read, 2).unwrap());
+	assert!(read_versioned(&buffer[..buffer.len() - 1]).is_err());
+}
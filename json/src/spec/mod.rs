@@ -30,7 +30,7 @@ pub mod basic_authority;
 pub use self::account::Account;
 pub use self::builtin::{Builtin, Pricing, Linear};
 pub use self::genesis::Genesis;
-pub use self::params::Params;
+pub use self::params::{Params, GasPriceContract};
 pub use self::spec::Spec;
 pub use self::seal::{Seal, Ethereum, Generic};
 pub use self::engine::Engine;
@@ -17,6 +17,8 @@
 //! Spec params deserialization.
 
 use uint::Uint;
+use hash::Address;
+use bytes::Bytes;
 
 /// Spec params.
 #[derive(Debug, PartialEq, Deserialize)]
@@ -30,14 +32,43 @@ pub struct Params {
 	/// Network id.
 	#[serde(rename="networkID")]
 	pub network_id: Uint,
+	/// Chain id used for replay protection of transactions.
+	#[serde(rename="chainID")]
+	pub chain_id: Option<Uint>,
+	/// Block from which transactions signed without chain id are rejected.
+	#[serde(rename="chainIDTransition")]
+	pub chain_id_transition: Option<Uint>,
+	/// Block from which contract creation at an already used address fails.
+	#[serde(rename="createCollisionTransition")]
+	pub create_collision_transition: Option<Uint>,
 	/// Minimum gas limit.
 	#[serde(rename="minGasLimit")]
 	pub min_gas_limit: Uint,
+	/// Minimal gas price of transactions.
+	#[serde(rename="minGasPrice")]
+	pub min_gas_price: Option<Uint>,
+	/// Senders exempt from minimal gas price.
+	#[serde(rename="gasPriceExempt")]
+	pub gas_price_exempt: Option<Vec<Address>>,
+	/// Contract determining minimal gas price of each sender.
+	#[serde(rename="gasPriceContract")]
+	pub gas_price_contract: Option<GasPriceContract>,
+}
+
+/// Contract determining minimal gas price.
+#[derive(Debug, PartialEq, Deserialize)]
+pub struct GasPriceContract {
+	/// Contract address.
+	pub registry: Address,
+	/// Selector of the function returning minimal gas price of given sender.
+	pub selector: Bytes,
 }
 
 #[cfg(test)]
 mod tests {
 	use serde_json;
+	use util::numbers::U256;
+	use uint::Uint;
 	use spec::params::Params;
 
 	#[test]
@@ -53,4 +84,51 @@ mod tests {
 		let _deserialized: Params = serde_json::from_str(s).unwrap();
 		// TODO: validate all fields
 	}
+
+	#[test]
+	fn params_with_gas_price_rule_deserialization() {
+		let s = r#"{
+			"maximumExtraDataSize": "0x20",
+			"networkID" : "0x1",
+			"minGasLimit": "0x1388",
+			"accountStartNonce": "0x00",
+			"minGasPrice": "0x4a817c800",
+			"gasPriceExempt": ["0x0000000000000000000000000000000000000001"]
+		}"#;
+
+		let deserialized: Params = serde_json::from_str(s).unwrap();
+		assert!(deserialized.min_gas_price.is_some());
+		assert_eq!(deserialized.gas_price_exempt.map(|e| e.len()), Some(1));
+		assert_eq!(deserialized.gas_price_contract, None);
+	}
+
+	#[test]
+	fn params_with_chain_id_deserialization() {
+		let s = r#"{
+			"maximumExtraDataSize": "0x20",
+			"networkID" : "0x2",
+			"chainID" : "0x3e",
+			"chainIDTransition": "0x64",
+			"minGasLimit": "0x1388",
+			"accountStartNonce": "0x00"
+		}"#;
+
+		let deserialized: Params = serde_json::from_str(s).unwrap();
+		assert_eq!(deserialized.chain_id, Some(Uint(U256::from(0x3e))));
+		assert_eq!(deserialized.chain_id_transition, Some(Uint(U256::from(0x64))));
+	}
+
+	#[test]
+	fn params_with_create_collision_transition_deserialization() {
+		let s = r#"{
+			"maximumExtraDataSize": "0x20",
+			"networkID" : "0x2",
+			"createCollisionTransition": "0x1f4",
+			"minGasLimit": "0x1388",
+			"accountStartNonce": "0x0100000"
+		}"#;
+
+		let deserialized: Params = serde_json::from_str(s).unwrap();
+		assert_eq!(deserialized.create_collision_transition, Some(Uint(U256::from(0x1f4))));
+	}
 }
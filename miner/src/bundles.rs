@@ -0,0 +1,382 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bundles of transactions which have to be included in a block together and in order.
+//!
+//! Nonces order transactions of single sender only. Bundles provide the ordering across senders
+//! (e.g. deploy a contract and call it from another account). Bundles are kept outside of the
+//! transaction queue (so they are never propagated) and are pushed to the pending block atomically:
+//! either all members are included in the given order or the whole bundle waits for the next block.
+
+use std::fmt;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use util::{Address, H256, U256};
+use ethcore::header::BlockNumber;
+use ethcore::transaction::SignedTransaction;
+use transaction_queue::{TransactionQueue, TransactionImportError, AccountDetails};
+
+/// Identifier of imported bundle.
+pub type BundleId = u64;
+
+/// Default number of blocks after which bundles which weren't included are dropped.
+pub const DEFAULT_BUNDLE_MAX_AGE: BlockNumber = 50;
+
+/// Number of mined or expired bundles whose status is remembered.
+const FINISHED_BUNDLES_KEPT: usize = 1024;
+
+/// Status of the bundle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BundleStatus {
+	/// Waiting to be included in a block.
+	Pending,
+	/// All members were included in given block.
+	Mined {
+		/// Number of the block.
+		number: BlockNumber,
+		/// Hash of the block.
+		hash: H256,
+	},
+	/// Bundle wasn't included in time and all its members were dropped.
+	Expired,
+}
+
+/// Reason of rejecting the whole bundle.
+#[derive(Debug, PartialEq)]
+pub enum BundleImportError {
+	/// Bundle has no transactions.
+	Empty,
+	/// Bundle would never fit into a block.
+	GasLimitExceeded {
+		/// Current block gas limit
+		limit: U256,
+		/// Total gas of bundle transactions
+		got: U256,
+	},
+	/// Nonce of a member doesn't follow the nonce of sender's account (or sender's previous member).
+	InvalidNonce {
+		/// Index of the member
+		index: usize,
+		/// Expected nonce
+		expected: U256,
+		/// Transaction nonce
+		got: U256,
+	},
+	/// Member of the bundle wouldn't be accepted to the transaction queue.
+	InvalidTransaction {
+		/// Index of the member
+		index: usize,
+		/// Reason of rejection
+		error: TransactionImportError,
+	},
+}
+
+impl fmt::Display for BundleImportError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::BundleImportError::*;
+		let msg = match *self {
+			Empty => "Bundle has no transactions".into(),
+			GasLimitExceeded { limit, got } =>
+				format!("Bundle gas exceeds block gas limit. Limit={}, Given={}", limit, got),
+			InvalidNonce { index, expected, got } =>
+				format!("Invalid nonce of transaction {}. Expected={}, Given={}", index, expected, got),
+			InvalidTransaction { index, ref error } =>
+				format!("Invalid transaction {}: {}", index, error),
+		};
+
+		f.write_fmt(format_args!("Bundle import error ({})", msg))
+	}
+}
+
+/// Checks that every member of the bundle would be accepted by `queue`, that members of each sender
+/// have subsequent nonces starting from the account nonce, and that the whole bundle fits into `gas_limit`.
+pub fn verify_bundle<T>(transactions: &[SignedTransaction], queue: &TransactionQueue, gas_limit: U256, fetch_account: &T) -> Result<(), BundleImportError>
+	where T: Fn(&Address) -> AccountDetails {
+	if transactions.is_empty() {
+		return Err(BundleImportError::Empty);
+	}
+
+	let gas = transactions.iter().fold(U256::zero(), |sum, t| sum + t.gas);
+	if gas > gas_limit {
+		return Err(BundleImportError::GasLimitExceeded {
+			limit: gas_limit,
+			got: gas,
+		});
+	}
+
+	let mut next_nonces = HashMap::new();
+	for (index, tx) in transactions.iter().enumerate() {
+		let account = try!(queue.verify(tx, fetch_account).map_err(|e| BundleImportError::InvalidTransaction {
+			index: index,
+			error: e,
+		}));
+		let sender = tx.sender().expect("Sender is verified by the queue; qed");
+		let expected = *next_nonces.entry(sender).or_insert(account.nonce);
+		if tx.nonce != expected {
+			return Err(BundleImportError::InvalidNonce {
+				index: index,
+				expected: expected,
+				got: tx.nonce,
+			});
+		}
+		next_nonces.insert(sender, expected + U256::one());
+	}
+	Ok(())
+}
+
+struct Bundle {
+	transactions: Vec<SignedTransaction>,
+	inserted_at: BlockNumber,
+	status: BundleStatus,
+}
+
+/// Imported bundles together with status of the recently finished ones.
+pub struct TransactionBundles {
+	bundles: BTreeMap<BundleId, Bundle>,
+	finished: VecDeque<BundleId>,
+	next_id: BundleId,
+	max_age: BlockNumber,
+}
+
+impl Default for TransactionBundles {
+	fn default() -> Self {
+		TransactionBundles {
+			bundles: BTreeMap::new(),
+			finished: VecDeque::new(),
+			next_id: 0,
+			max_age: DEFAULT_BUNDLE_MAX_AGE,
+		}
+	}
+}
+
+impl TransactionBundles {
+	/// Returns number of blocks after which pending bundles are dropped.
+	pub fn max_age(&self) -> BlockNumber {
+		self.max_age
+	}
+
+	/// Sets number of blocks after which pending bundles are dropped.
+	pub fn set_max_age(&mut self, max_age: BlockNumber) {
+		self.max_age = max_age;
+	}
+
+	/// Adds verified bundle imported at block `current_block`.
+	pub fn add(&mut self, transactions: Vec<SignedTransaction>, current_block: BlockNumber) -> BundleId {
+		let id = self.next_id;
+		self.next_id += 1;
+		self.bundles.insert(id, Bundle {
+			transactions: transactions,
+			inserted_at: current_block,
+			status: BundleStatus::Pending,
+		});
+		id
+	}
+
+	/// Returns status of the bundle or `None` if it's unknown.
+	pub fn status(&self, id: BundleId) -> Option<BundleStatus> {
+		self.bundles.get(&id).map(|b| b.status)
+	}
+
+	/// Returns `true` if there is any bundle waiting for inclusion.
+	pub fn has_pending(&self) -> bool {
+		self.bundles.values().any(|b| b.status == BundleStatus::Pending)
+	}
+
+	/// Returns transactions of pending bundles (in order of import).
+	pub fn pending(&self) -> Vec<Vec<SignedTransaction>> {
+		self.bundles.values()
+			.filter(|b| b.status == BundleStatus::Pending)
+			.map(|b| b.transactions.clone())
+			.collect()
+	}
+
+	/// Marks pending bundles with all members in block `hash` as mined.
+	pub fn note_mined(&mut self, number: BlockNumber, hash: H256, transactions: &HashSet<H256>) {
+		let mined: Vec<BundleId> = self.bundles.iter()
+			.filter(|&(_, b)| b.status == BundleStatus::Pending && b.transactions.iter().all(|t| transactions.contains(&t.hash())))
+			.map(|(id, _)| *id)
+			.collect();
+		for id in mined {
+			trace!(target: "miner", "Bundle {} mined in block #{} ({:?})", id, number, hash);
+			self.finish(id, BundleStatus::Mined { number: number, hash: hash });
+		}
+	}
+
+	/// Makes bundles mined in retracted block `hash` pending again.
+	pub fn note_retracted(&mut self, hash: &H256) {
+		for (id, bundle) in &mut self.bundles {
+			let retracted = match bundle.status {
+				BundleStatus::Mined { hash: ref mined_in, .. } => mined_in == hash,
+				_ => false,
+			};
+			if retracted {
+				trace!(target: "miner", "Bundle {} retracted with block {:?}", id, hash);
+				bundle.status = BundleStatus::Pending;
+			}
+		}
+	}
+
+	/// Drops pending bundles which weren't included for more than `max_age` blocks.
+	/// Returns ids of dropped bundles with hashes of their members.
+	pub fn remove_expired(&mut self, current_block: BlockNumber) -> Vec<(BundleId, Vec<H256>)> {
+		let max_age = self.max_age;
+		let expired: Vec<(BundleId, Vec<H256>)> = self.bundles.iter()
+			.filter(|&(_, b)| b.status == BundleStatus::Pending && current_block.saturating_sub(b.inserted_at) > max_age)
+			.map(|(id, b)| (*id, b.transactions.iter().map(|t| t.hash()).collect()))
+			.collect();
+		for &(id, _) in &expired {
+			self.finish(id, BundleStatus::Expired);
+		}
+		expired
+	}
+
+	fn finish(&mut self, id: BundleId, status: BundleStatus) {
+		if let Some(bundle) = self.bundles.get_mut(&id) {
+			bundle.status = status;
+			if status == BundleStatus::Expired {
+				bundle.transactions.clear();
+			}
+		}
+		self.finished.push_back(id);
+
+		while self.finished.len() > FINISHED_BUNDLES_KEPT {
+			let id = self.finished.pop_front().expect("Length is greater than 0; qed");
+			// Bundle might have been retracted since.
+			if self.status(id) != Some(BundleStatus::Pending) {
+				self.bundles.remove(&id);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use util::*;
+	use ethcore::transaction::{Transaction, SignedTransaction, Action};
+	use transaction_queue::{TransactionQueue, TransactionImportError, AccountDetails};
+	use super::*;
+
+	fn transaction(keypair: &KeyPair, nonce: u64) -> SignedTransaction {
+		Transaction {
+			action: Action::Call(Address::zero()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(30_000),
+			gas_price: U256::one(),
+			nonce: U256::from(nonce),
+		}.sign(keypair.secret())
+	}
+
+	fn account_details(_address: &Address) -> AccountDetails {
+		AccountDetails {
+			nonce: U256::from(5),
+			balance: U256::from(1_000_000),
+		}
+	}
+
+	#[test]
+	fn should_verify_bundles() {
+		// given
+		let queue = TransactionQueue::new();
+		let deployer = KeyPair::create().unwrap();
+		let caller = KeyPair::create().unwrap();
+		let good = vec![transaction(&deployer, 5), transaction(&caller, 5), transaction(&deployer, 6)];
+		let gas_limit = U256::from(100_000);
+
+		// then
+		assert_eq!(verify_bundle(&good, &queue, gas_limit, &account_details), Ok(()));
+		assert_eq!(verify_bundle(&[], &queue, gas_limit, &account_details), Err(BundleImportError::Empty));
+		assert_eq!(verify_bundle(&good, &queue, U256::from(60_000), &account_details), Err(BundleImportError::GasLimitExceeded {
+			limit: U256::from(60_000),
+			got: U256::from(90_000),
+		}));
+		assert_eq!(verify_bundle(&[transaction(&deployer, 5), transaction(&deployer, 7)], &queue, gas_limit, &account_details), Err(BundleImportError::InvalidNonce {
+			index: 1,
+			expected: U256::from(6),
+			got: U256::from(7),
+		}));
+	}
+
+	#[test]
+	fn should_reject_whole_bundle_with_one_bad_member() {
+		// given
+		let queue = TransactionQueue::new();
+		let keypair = KeyPair::create().unwrap();
+		let mut expensive = transaction(&keypair, 6).deref().clone();
+		expensive.value = U256::from(10_000_000);
+		let bundle = vec![transaction(&keypair, 5), expensive.sign(keypair.secret())];
+
+		// when
+		let result = verify_bundle(&bundle, &queue, U256::from(100_000), &account_details);
+
+		// then
+		assert_eq!(result, Err(BundleImportError::InvalidTransaction {
+			index: 1,
+			error: TransactionImportError::InsufficientBalance {
+				cost: U256::from(10_030_000),
+				balance: U256::from(1_000_000),
+			},
+		}));
+	}
+
+	#[test]
+	fn should_track_mined_and_retracted_bundles() {
+		// given
+		let mut bundles = TransactionBundles::default();
+		let keypair = KeyPair::create().unwrap();
+		let txs = vec![transaction(&keypair, 5), transaction(&keypair, 6)];
+		let id = bundles.add(txs.clone(), 10);
+		let partial: HashSet<H256> = vec![txs[0].hash()].into_iter().collect();
+		let all: HashSet<H256> = txs.iter().map(|t| t.hash()).collect();
+
+		// when
+		bundles.note_mined(11, H256::from(11), &partial);
+		assert_eq!(bundles.status(id), Some(BundleStatus::Pending));
+		bundles.note_mined(12, H256::from(12), &all);
+
+		// then
+		assert_eq!(bundles.status(id), Some(BundleStatus::Mined { number: 12, hash: H256::from(12) }));
+		assert!(!bundles.has_pending());
+
+		// when
+		bundles.note_retracted(&H256::from(12));
+
+		// then
+		assert_eq!(bundles.status(id), Some(BundleStatus::Pending));
+		assert_eq!(bundles.pending(), vec![txs]);
+		assert_eq!(bundles.status(id + 1), None);
+	}
+
+	#[test]
+	fn should_expire_old_bundles() {
+		// given
+		let mut bundles = TransactionBundles::default();
+		bundles.set_max_age(5);
+		let keypair = KeyPair::create().unwrap();
+		let old = bundles.add(vec![transaction(&keypair, 5)], 10);
+		let new = bundles.add(vec![transaction(&keypair, 6)], 12);
+
+		// when
+		assert!(bundles.remove_expired(15).is_empty());
+		let expired = bundles.remove_expired(16);
+
+		// then
+		assert_eq!(expired, vec![(old, vec![transaction(&keypair, 5).hash()])]);
+		assert_eq!(bundles.status(old), Some(BundleStatus::Expired));
+		assert_eq!(bundles.status(new), Some(BundleStatus::Pending));
+		assert_eq!(bundles.pending().len(), 1);
+	}
+}
@@ -16,9 +16,15 @@
 
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use util::numbers::U256;
 use util::hash::H256;
 
+/// Miners usually resubmit their hashrate every few seconds.
+const TYPICAL_SUBMIT_INTERVAL_SECS: u64 = 5;
+/// Default time after which hashrate that wasn't resubmitted is ignored.
+pub const DEFAULT_HASHRATE_TTL_SECS: u64 = 2 * TYPICAL_SUBMIT_INTERVAL_SECS;
+
 /// External miner interface.
 pub trait ExternalMinerService: Send + Sync {
 	/// Submit hashrate for given miner.
@@ -32,43 +38,80 @@ pub trait ExternalMinerService: Send + Sync {
 }
 
 /// External Miner.
+///
+/// Hashrates are kept together with time of their submission and expire
+/// when not resubmitted within `ttl`.
 pub struct ExternalMiner {
-	hashrates: Arc<RwLock<HashMap<H256, U256>>>,
+	hashrates: Arc<RwLock<HashMap<H256, (U256, Instant)>>>,
+	ttl: Duration,
 }
 
 impl Default for ExternalMiner {
 	fn default() -> Self {
-		ExternalMiner {
-			hashrates: Arc::new(RwLock::new(HashMap::new())),
-		}
+		ExternalMiner::new(Arc::new(RwLock::new(HashMap::new())))
 	}
 }
 
 impl ExternalMiner {
 	/// Creates new external miner with prefilled hashrates.
-	pub fn new(hashrates: Arc<RwLock<HashMap<H256, U256>>>) -> Self {
+	pub fn new(hashrates: Arc<RwLock<HashMap<H256, (U256, Instant)>>>) -> Self {
 		ExternalMiner {
-			hashrates: hashrates
+			hashrates: hashrates,
+			ttl: Duration::from_secs(DEFAULT_HASHRATE_TTL_SECS),
+		}
+	}
+
+	/// Changes time after which hashrate that wasn't resubmitted is ignored.
+	pub fn with_ttl(mut self, ttl: Duration) -> Self {
+		self.ttl = ttl;
+		self
+	}
+
+	fn submit_hashrate_at(&self, hashrate: U256, id: H256, now: Instant) {
+		self.hashrates.write().unwrap().insert(id, (hashrate, now));
+	}
+
+	/// Removes hashrates not resubmitted within `ttl` before `now`.
+	fn prune(&self, now: Instant) {
+		let mut hashrates = self.hashrates.write().unwrap();
+		let expired: Vec<H256> = hashrates.iter()
+			// Entry might have been submitted after `now` was taken.
+			.filter(|&(_, &(_, submitted))| submitted < now && now.duration_since(submitted) >= self.ttl)
+			.map(|(id, _)| id.clone())
+			.collect();
+		for id in expired {
+			hashrates.remove(&id);
 		}
 	}
+
+	fn hashrate_at(&self, now: Instant) -> U256 {
+		self.prune(now);
+		self.hashrates.read().unwrap().iter().fold(U256::from(0), |sum, (_, &(v, _))| sum + v)
+	}
+
+	fn is_mining_at(&self, now: Instant) -> bool {
+		self.prune(now);
+		!self.hashrates.read().unwrap().is_empty()
+	}
 }
 
 impl ExternalMinerService for ExternalMiner {
 	fn submit_hashrate(&self, hashrate: U256, id: H256) {
-		self.hashrates.write().unwrap().insert(id, hashrate);
+		self.submit_hashrate_at(hashrate, id, Instant::now());
 	}
 
 	fn hashrate(&self) -> U256 {
-		self.hashrates.read().unwrap().iter().fold(U256::from(0), |sum, (_, v)| sum + *v)
+		self.hashrate_at(Instant::now())
 	}
 
 	fn is_mining(&self) -> bool {
-		!self.hashrates.read().unwrap().is_empty()
+		self.is_mining_at(Instant::now())
 	}
 }
 
 #[cfg(test)]
 mod tests {
+	use std::time::{Duration, Instant};
 	use super::*;
 	use util::{H256, U256};
 
@@ -105,4 +148,51 @@ mod tests {
 		// then
 		assert_eq!(m.hashrate(), U256::from(35));
 	}
+
+	#[test]
+	fn should_ignore_hashrates_which_were_not_resubmitted() {
+		// given
+		let m = miner().with_ttl(Duration::from_secs(10));
+		let start = Instant::now();
+		m.submit_hashrate_at(U256::from(10), H256::from(1), start);
+		m.submit_hashrate_at(U256::from(20), H256::from(2), start + Duration::from_secs(5));
+
+		// when
+		let before_expiry = m.hashrate_at(start + Duration::from_secs(9));
+		let after_first_expired = m.hashrate_at(start + Duration::from_secs(10));
+
+		// then
+		assert_eq!(before_expiry, U256::from(30));
+		assert_eq!(after_first_expired, U256::from(20));
+	}
+
+	#[test]
+	fn should_keep_resubmitted_hashrates() {
+		// given
+		let m = miner().with_ttl(Duration::from_secs(10));
+		let start = Instant::now();
+		m.submit_hashrate_at(U256::from(10), H256::from(1), start);
+
+		// when
+		m.submit_hashrate_at(U256::from(15), H256::from(1), start + Duration::from_secs(8));
+
+		// then
+		assert_eq!(m.hashrate_at(start + Duration::from_secs(15)), U256::from(15));
+	}
+
+	#[test]
+	fn should_stop_mining_when_all_hashrates_expire() {
+		// given
+		let m = miner().with_ttl(Duration::from_secs(10));
+		let start = Instant::now();
+		m.submit_hashrate_at(U256::from(10), H256::from(1), start);
+		assert_eq!(m.is_mining_at(start + Duration::from_secs(1)), true);
+
+		// when
+		let mining = m.is_mining_at(start + Duration::from_secs(11));
+
+		// then
+		assert_eq!(mining, false);
+		assert_eq!(m.hashrate_at(start + Duration::from_secs(11)), U256::from(0));
+	}
 }
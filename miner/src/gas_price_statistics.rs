@@ -0,0 +1,163 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Gas price statistics gathered from recent blocks.
+
+use util::*;
+use ethcore::client::{BlockChainClient, BlockID};
+use ethcore::views::BlockView;
+use ethcore::header::BlockNumber;
+
+/// Distribution of gas prices of transactions included in recent blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasPriceStatistics {
+	/// Non-zero gas prices in ascending order.
+	prices: Vec<U256>,
+}
+
+impl GasPriceStatistics {
+	/// Creates distribution of given gas prices.
+	/// Zero gas prices are ignored, returns `None` if there are no other prices.
+	pub fn new(mut prices: Vec<U256>) -> Option<Self> {
+		prices.retain(|price| !price.is_zero());
+		if prices.is_empty() {
+			return None;
+		}
+		prices.sort();
+		Some(GasPriceStatistics {
+			prices: prices,
+		})
+	}
+
+	/// Returns the lowest gas price such that at least `percentile` percent of prices are not higher (nearest-rank method).
+	/// `percentile` above 100 is treated as 100.
+	pub fn percentile(&self, percentile: usize) -> U256 {
+		let percentile = cmp::min(percentile, 100);
+		let rank = (percentile * self.prices.len() + 99) / 100;
+		self.prices[cmp::max(rank, 1) - 1]
+	}
+}
+
+/// Collects gas prices of transactions in last `sample_size` blocks.
+///
+/// Returns `None` if the chain is shorter than `sample_size` or there are no transactions with non-zero gas price.
+pub fn gas_price_statistics(chain: &BlockChainClient, sample_size: usize) -> Option<GasPriceStatistics> {
+	let best_block = chain.chain_info().best_block_number;
+	if sample_size == 0 || best_block < sample_size as BlockNumber {
+		return None;
+	}
+
+	let mut prices = Vec::new();
+	for number in (best_block - sample_size as BlockNumber + 1)..(best_block + 1) {
+		if let Some(block) = chain.block(BlockID::Number(number)) {
+			prices.extend(BlockView::new(&block).transactions().into_iter().map(|t| t.gas_price));
+		}
+	}
+	GasPriceStatistics::new(prices)
+}
+
+#[cfg(test)]
+mod tests {
+	use util::*;
+	use ethcore::client::{BlockChainClient, TestBlockChainClient};
+	use ethcore::header::Header;
+	use ethcore::transaction::{Transaction, Action};
+	use super::{GasPriceStatistics, gas_price_statistics};
+
+	fn add_block_with_gas_prices(client: &TestBlockChainClient, prices: &[u64]) {
+		let info = client.chain_info();
+		let mut header = Header::new();
+		header.parent_hash = info.best_block_hash;
+		header.number = info.best_block_number + 1;
+		header.gas_limit = U256::from(1_000_000);
+
+		let mut txs = RlpStream::new_list(prices.len());
+		for price in prices {
+			let keypair = KeyPair::create().unwrap();
+			txs.append(&Transaction {
+				action: Action::Create,
+				value: U256::zero(),
+				data: vec![],
+				gas: U256::from(100_000),
+				gas_price: U256::from(*price),
+				nonce: U256::zero(),
+			}.sign(keypair.secret()));
+		}
+
+		let mut rlp = RlpStream::new_list(3);
+		rlp.append(&header);
+		rlp.append_raw(txs.as_raw(), 1);
+		rlp.append_raw(&RlpStream::new_list(0).out(), 1);
+		client.import_block(rlp.out()).unwrap();
+	}
+
+	fn prices(prices: &[u64]) -> Vec<U256> {
+		prices.iter().map(|p| U256::from(*p)).collect()
+	}
+
+	#[test]
+	fn should_pick_percentiles_of_distribution() {
+		// given
+		let statistics = GasPriceStatistics::new(prices(&[40, 10, 30, 20, 50])).unwrap();
+
+		// then
+		assert_eq!(statistics.percentile(0), U256::from(10));
+		assert_eq!(statistics.percentile(50), U256::from(30));
+		assert_eq!(statistics.percentile(60), U256::from(30));
+		assert_eq!(statistics.percentile(61), U256::from(40));
+		assert_eq!(statistics.percentile(100), U256::from(50));
+		assert_eq!(statistics.percentile(1000), U256::from(50));
+	}
+
+	#[test]
+	fn should_ignore_zero_gas_prices() {
+		assert_eq!(GasPriceStatistics::new(prices(&[0, 0])), None);
+		assert_eq!(GasPriceStatistics::new(prices(&[0, 0, 0, 7])).unwrap().percentile(50), U256::from(7));
+	}
+
+	#[test]
+	fn should_collect_gas_prices_of_last_blocks() {
+		// given
+		let client = TestBlockChainClient::new();
+		add_block_with_gas_prices(&client, &[1000, 1000]);
+		add_block_with_gas_prices(&client, &[10, 20]);
+		add_block_with_gas_prices(&client, &[]);
+		add_block_with_gas_prices(&client, &[30, 0]);
+
+		// when
+		let statistics = gas_price_statistics(&client, 3).unwrap();
+
+		// then
+		assert_eq!(statistics, GasPriceStatistics::new(prices(&[10, 20, 30])).unwrap());
+	}
+
+	#[test]
+	fn should_return_none_for_short_or_empty_chains() {
+		// given
+		let client = TestBlockChainClient::new();
+		add_block_with_gas_prices(&client, &[10]);
+
+		// then
+		assert_eq!(gas_price_statistics(&client, 2), None);
+
+		// when
+		add_block_with_gas_prices(&client, &[]);
+		add_block_with_gas_prices(&client, &[0, 0]);
+
+		// then
+		assert_eq!(gas_price_statistics(&client, 2), None);
+	}
+}
@@ -49,24 +49,42 @@ extern crate log;
 #[macro_use]
 extern crate ethcore_util as util;
 extern crate ethcore;
+extern crate ethash;
 extern crate env_logger;
 extern crate rayon;
+#[cfg(test)] extern crate ethcore_devtools as devtools;
 
 mod miner;
 mod external;
 mod transaction_queue;
-
-pub use transaction_queue::{TransactionQueue, AccountDetails, TransactionImportResult, TransactionOrigin};
-pub use miner::{Miner};
+mod gas_price_statistics;
+mod submission_audit;
+mod work_history;
+mod work_notify;
+mod bundles;
+
+pub use transaction_queue::{TransactionQueue, AccountDetails, TransactionImportResult, TransactionImportError, TransactionOrigin, TransactionDetails, TransactionState};
+pub use miner::{Miner, ResealPolicy, ChainEvent, ChainListener, ChainListenerId};
 pub use external::{ExternalMiner, ExternalMinerService};
+pub use gas_price_statistics::{GasPriceStatistics, gas_price_statistics};
+pub use work_notify::{WorkPoster, HttpWorkPoster};
+pub use submission_audit::{SubmissionOrigin, SubmissionAudit, SubmissionStats, AuditEntry};
+pub use bundles::{BundleId, BundleStatus, BundleImportError};
 
 use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::time::{Instant, Duration};
 use util::{H256, U256, Address, Bytes};
-use ethcore::client::{BlockChainClient, Executed};
+use ethcore::client::{BlockChainClient, Executed, EnvInfo};
 use ethcore::block::{ClosedBlock};
 use ethcore::receipt::{Receipt};
+use ethcore::filter::Filter;
+use ethcore::log_entry::LocalizedLogEntry;
 use ethcore::error::{Error, ExecutionError};
 use ethcore::transaction::SignedTransaction;
+use ethcore::header::BlockNumber;
+use ethcore::gas_price_rule::GasPriceRule;
+use ethcore::gas_limit::GasLimitStrategy;
 
 /// Miner client API
 pub trait MinerService : Send + Sync {
@@ -92,28 +110,141 @@ pub trait MinerService : Send + Sync {
 	/// Set minimal gas price of transaction to be accepted for mining.
 	fn set_minimal_gas_price(&self, min_gas_price: U256);
 
+	/// Get current rule determining minimal gas price of transactions of given sender.
+	fn gas_price_rule(&self) -> GasPriceRule;
+
+	/// Set rule determining minimal gas price of transactions of given sender.
+	fn set_gas_price_rule(&self, rule: GasPriceRule);
+
 	/// Get the gas limit we wish to target when sealing a new block.
 	fn gas_floor_target(&self) -> U256;
 
 	/// Set the gas limit we wish to target when sealing a new block.
+	/// Gas ceil target is raised to `target` if it's lower.
 	fn set_gas_floor_target(&self, target: U256);
 
+	/// Get the upper bound of gas limit of new blocks (see `GasLimitStrategy::TargetRange`).
+	fn gas_ceil_target(&self) -> U256;
+
+	/// Set floor and ceil gas limit targets of new blocks.
+	/// Targets outside of protocol bounds are clamped with a warning.
+	fn set_gas_range_target(&self, range: (U256, U256));
+
+	/// Get the way gas limit of new blocks follows the targets.
+	fn gas_limit_strategy(&self) -> GasLimitStrategy;
+
+	/// Set the way gas limit of new blocks follows the targets.
+	fn set_gas_limit_strategy(&self, strategy: GasLimitStrategy);
+
+	/// Returns true if valid uncles are included in newly prepared blocks.
+	fn include_uncles(&self) -> bool;
+
+	/// Set whether valid uncles should be included in newly prepared blocks (enabled by default).
+	fn set_include_uncles(&self, include: bool);
+
+	/// Get maximal gas of single transaction accepted to the queue.
+	fn tx_gas_limit(&self) -> U256;
+
+	/// Set maximal gas of single transaction accepted to the queue.
+	/// Once set, the limit is no longer updated to the gas limit of the latest block.
+	fn set_tx_gas_limit(&self, limit: U256);
+
 	/// Get current transactions limit in queue.
 	fn transactions_limit(&self) -> usize;
 
 	/// Set maximal number of transactions kept in the queue (both current and future).
 	fn set_transactions_limit(&self, limit: usize);
 
-	/// Imports transactions to transaction queue.
-	fn import_transactions<T>(&self, transactions: Vec<SignedTransaction>, fetch_account: T) ->
-		Vec<Result<TransactionImportResult, Error>>
+	/// Get current limit of total size (in bytes) of transactions kept in the queue.
+	fn total_byte_limit(&self) -> usize;
+
+	/// Set maximal total size (in bytes) of transactions kept in the queue (both current and future).
+	fn set_total_byte_limit(&self, limit: usize);
+
+	/// Get current limit of transactions from single sender kept in the queue.
+	fn max_per_sender(&self) -> usize;
+
+	/// Set maximal number of transactions from single sender kept in the queue (both current and future).
+	fn set_max_per_sender(&self, max_per_sender: usize);
+
+	/// Ban `address`. Transactions sent from or to it are dropped from the queue and rejected.
+	fn ban_address(&self, address: Address);
+
+	/// Lift ban of `address`. Returns `false` if the address was not banned.
+	fn unban_address(&self, address: &Address) -> bool;
+
+	/// Get all banned addresses.
+	fn banned_addresses(&self) -> Vec<Address>;
+
+	/// Get number of blocks after which transactions are dropped from the queue.
+	fn transaction_queue_max_age(&self) -> BlockNumber;
+
+	/// Set number of blocks after which transactions are dropped from the queue.
+	fn set_transaction_queue_max_age(&self, max_age: BlockNumber);
+
+	/// Get minimal period between preparing subsequent blocks on top of the same parent.
+	fn reseal_min_period(&self) -> Duration;
+
+	/// Set minimal period between preparing subsequent blocks on top of the same parent.
+	/// Reseals requested earlier are deferred until the sealing work is requested after the period.
+	fn set_reseal_min_period(&self, period: Duration);
+
+	/// Returns `true` if preparing blocks for sealing is enabled.
+	fn sealing_enabled(&self) -> bool;
+
+	/// Enable or disable preparing blocks for sealing. While disabled no sealing work is given out.
+	fn set_sealing_enabled(&self, enabled: bool);
+
+	/// Get period after which sealing work stops being prepared if nobody requested it.
+	fn sealing_work_timeout(&self) -> Option<Duration>;
+
+	/// Set period after which sealing work stops being prepared if nobody requested it (`None` disables the timeout).
+	/// Preparing is resumed on the next request of sealing work.
+	fn set_sealing_work_timeout(&self, timeout: Option<Duration>);
+
+	/// Imports transactions received from the network to transaction queue.
+	/// Submissions are recorded in the audit trail only if `origin` is given.
+	fn import_transactions<T>(&self, chain: &BlockChainClient, transactions: Vec<SignedTransaction>, origin: Option<SubmissionOrigin>, fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
+		where T: Fn(&Address) -> AccountDetails;
+
+	/// Imports RLP-encoded transactions received from the network to transaction queue.
+	/// Malformed transactions are rejected with `TransactionImportError::InvalidRlp` without affecting the rest of the batch.
+	fn import_raw_transactions<T>(&self, chain: &BlockChainClient, transactions: Vec<Bytes>, fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
 		where T: Fn(&Address) -> AccountDetails;
 
 	/// Imports own (node owner) transaction to queue.
-	fn import_own_transaction<T>(&self, chain: &BlockChainClient, transaction: SignedTransaction, fetch_account: T) ->
-		Result<TransactionImportResult, Error>
+	/// Submission is recorded in the audit trail, unknown `origin` is recorded as anonymous.
+	fn import_own_transaction<T>(&self, chain: &BlockChainClient, transaction: SignedTransaction, origin: Option<SubmissionOrigin>, fetch_account: T) ->
+		Result<TransactionImportResult, TransactionImportError>
+		where T: Fn(&Address) -> AccountDetails;
+
+	/// Imports bundle of own transactions which have to be included in a block together and in given order.
+	/// Whole bundle is rejected if any of its members is invalid.
+	fn import_transaction_bundle<T>(&self, chain: &BlockChainClient, transactions: Vec<SignedTransaction>, fetch_account: T) ->
+		Result<BundleId, BundleImportError>
 		where T: Fn(&Address) -> AccountDetails;
 
+	/// Returns status of the bundle or `None` if the bundle is unknown.
+	fn bundle_status(&self, id: BundleId) -> Option<BundleStatus>;
+
+	/// Get number of blocks after which bundles which weren't included are dropped.
+	fn bundle_max_age(&self) -> BlockNumber;
+
+	/// Set number of blocks after which bundles which weren't included are dropped.
+	fn set_bundle_max_age(&self, max_age: BlockNumber);
+
+	/// Returns recorded submissions of transaction with given hash.
+	fn transaction_audit(&self, hash: &H256) -> Vec<AuditEntry>;
+
+	/// Returns numbers of submissions aggregated per origin.
+	fn submission_stats(&self) -> BTreeMap<SubmissionOrigin, SubmissionStats>;
+
+	/// Removes transaction from the queue and returns it (if it was there).
+	/// Subsequent transactions from the same sender are moved to future.
+	fn remove_transaction(&self, hash: &H256) -> Option<SignedTransaction>;
+
 	/// Returns hashes of transactions currently in pending
 	fn pending_transactions_hashes(&self) -> Vec<H256>;
 
@@ -128,10 +259,15 @@ pub trait MinerService : Send + Sync {
 
 	/// Submit `seal` as a valid solution for the header of `pow_hash`.
 	/// Will check the seal, but not actually insert the block into the chain.
-	fn submit_seal(&self, chain: &BlockChainClient, pow_hash: H256, seal: Vec<Bytes>) -> Result<(), Error>;
+	/// If `job_id` is given and none of the cached work packages belongs to that job `Error::StaleJob` is returned.
+	/// Returns hash of the sealed block.
+	fn submit_seal(&self, chain: &BlockChainClient, pow_hash: H256, seal: Vec<Bytes>, job_id: Option<u64>) -> Result<H256, Error>;
 
 	/// Get the sealing work package and if `Some`, apply some transform.
-	fn map_sealing_work<F, T>(&self, chain: &BlockChainClient, f: F) -> Option<T> where F: FnOnce(&ClosedBlock) -> T;
+	fn map_sealing_work<F, T>(&self, chain: &BlockChainClient, f: F) -> Option<T> where F: FnOnce(&SealingWork) -> T;
+
+	/// Returns id of the most recent sealing job handed out by `map_sealing_work`.
+	fn current_job_id(&self) -> Option<u64>;
 
 	/// Query pending transactions for hash.
 	fn transaction(&self, hash: &H256) -> Option<SignedTransaction>;
@@ -142,14 +278,33 @@ pub trait MinerService : Send + Sync {
 	/// Get a list of all pending transactions.
 	fn pending_transactions(&self) -> Vec<SignedTransaction>;
 
+	/// Get a list of all future transactions (waiting for transactions with lower nonces).
+	fn future_transactions(&self) -> Vec<SignedTransaction>;
+
+	/// Get details (origin, insertion block and state) of transaction in the queue.
+	fn transaction_details(&self, hash: &H256) -> Option<TransactionDetails>;
+
 	/// Get a list of all pending receipts.
 	fn pending_receipts(&self) -> BTreeMap<H256, Receipt>;
 
+	/// Get logs of pending block matching given filter (block range of the filter is ignored).
+	/// Returns nothing if there is no pending block.
+	fn pending_logs(&self, filter: &Filter) -> Vec<LocalizedLogEntry>;
+
 	/// Returns highest transaction nonce for given address.
 	fn last_nonce(&self, address: &Address) -> Option<U256>;
 
-	/// Suggested gas price.
-	fn sensible_gas_price(&self) -> U256 { x!(20000000000u64) }
+	/// Returns nonce for the next local transaction of `address` and reserves it,
+	/// so concurrent callers get consecutive nonces even before their transactions are imported.
+	/// Reservation expires if the transaction isn't imported in time.
+	fn reserve_nonce(&self, chain: &BlockChainClient, address: &Address) -> U256;
+
+	/// Releases `nonce` reserved for `address`, e.g. because the transaction using it couldn't be imported.
+	/// The nonce is given out by the next `reserve_nonce` call.
+	fn release_nonce(&self, address: &Address, nonce: &U256);
+
+	/// Suggested gas price based on recent transactions in `chain`.
+	fn sensible_gas_price(&self, _chain: &BlockChainClient) -> U256 { x!(20000000000u64) }
 
 	/// Suggested gas limit.
 	fn sensible_gas_limit(&self) -> U256 { x!(21000) }
@@ -158,7 +313,14 @@ pub trait MinerService : Send + Sync {
 	fn balance(&self, chain: &BlockChainClient, address: &Address) -> U256;
 
 	/// Call into contract code using pending state.
-	fn call(&self, chain: &BlockChainClient, t: &SignedTransaction) -> Result<Executed, ExecutionError>;
+	/// Environment of the pending block is altered by `overrides` (the block itself is left untouched).
+	/// Without pending block the call is executed on top of the latest block, with environment altered the same way.
+	/// Execution is aborted with `ExecutionError::Timeout` if it doesn't finish before `deadline`.
+	fn call(&self, chain: &BlockChainClient, t: &SignedTransaction, overrides: &CallOverrides, deadline: Option<Instant>) -> Result<Executed, ExecutionError>;
+
+	/// Estimates minimal gas required to execute transaction using pending state.
+	/// Estimation is aborted with `ExecutionError::Timeout` if it doesn't finish before `deadline`.
+	fn estimate_gas(&self, chain: &BlockChainClient, t: &SignedTransaction, deadline: Option<Instant>) -> Result<U256, ExecutionError>;
 
 	/// Get storage value in pending state.
 	fn storage_at(&self, chain: &BlockChainClient, address: &Address, position: &H256) -> H256;
@@ -179,4 +341,66 @@ pub struct MinerStatus {
 	pub transactions_in_future_queue: usize,
 	/// Number of transactions included in currently mined block
 	pub transactions_in_pending_block: usize,
+	/// Total size (in bytes) of transactions in queue
+	pub transactions_bytes: usize,
+	/// Number of uncles included in currently mined block
+	pub uncles_in_pending_block: usize,
+}
+
+/// Work package handed out for sealing.
+///
+/// Dereferences to the block being sealed.
+pub struct SealingWork<'a> {
+	/// Block to be sealed.
+	pub block: &'a ClosedBlock,
+	/// Identifier of the job, increasing every time new work is handed out.
+	pub job_id: u64,
+	/// Hash of the block header without seal.
+	pub pow_hash: H256,
+	/// Number of the block being sealed.
+	pub number: BlockNumber,
+	/// Difficulty of the block being sealed.
+	pub difficulty: U256,
+}
+
+impl<'a> Deref for SealingWork<'a> {
+	type Target = ClosedBlock;
+
+	fn deref(&self) -> &ClosedBlock {
+		self.block
+	}
+}
+
+/// Environment values used instead of the pending block ones when executing a call,
+/// and the way the call is executed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CallOverrides {
+	/// Block timestamp
+	pub timestamp: Option<u64>,
+	/// Block number
+	pub block_number: Option<BlockNumber>,
+	/// Block gas limit
+	pub gas_limit: Option<U256>,
+	/// Block author
+	pub author: Option<Address>,
+	/// Abort the call with an exception if it tries to modify the state.
+	pub static_call: bool,
+}
+
+impl CallOverrides {
+	/// Applies overrides to given environment.
+	pub fn apply(&self, env_info: &mut EnvInfo) {
+		if let Some(timestamp) = self.timestamp {
+			env_info.timestamp = timestamp;
+		}
+		if let Some(number) = self.block_number {
+			env_info.number = number;
+		}
+		if let Some(gas_limit) = self.gas_limit {
+			env_info.gas_limit = gas_limit;
+		}
+		if let Some(author) = self.author {
+			env_info.author = author;
+		}
+	}
 }
@@ -15,35 +15,127 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use rayon::prelude::*;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Instant, Duration};
+use std::io;
+use std::path::Path;
 
 use util::*;
 use util::keys::store::{AccountService, AccountProvider};
 use ethcore::views::{BlockView, HeaderView};
 use ethcore::client::{BlockChainClient, BlockID};
-use ethcore::block::{ClosedBlock, IsBlock};
+use ethcore::block::{ClosedBlock, OpenBlock, IsBlock};
 use ethcore::error::*;
-use ethcore::client::{Executive, Executed, EnvInfo, TransactOptions};
-use ethcore::transaction::SignedTransaction;
+use ethcore::client::{Executive, Executed, EnvInfo, TransactOptions, MAX_CALL_OUTPUT_SIZE};
+use ethcore::transaction::{Transaction, SignedTransaction, Action};
 use ethcore::receipt::{Receipt};
+use ethcore::filter::Filter;
+use ethcore::log_entry::LocalizedLogEntry;
 use ethcore::spec::Spec;
 use ethcore::engine::Engine;
-use super::{MinerService, MinerStatus, TransactionQueue, AccountDetails, TransactionImportResult, TransactionOrigin};
+use ethcore::header::BlockNumber;
+use ethcore::gas_price_rule::GasPriceRule;
+use ethcore::gas_limit::{GasLimitTarget, GasLimitStrategy};
+use gas_price_statistics::{GasPriceStatistics, gas_price_statistics};
+use work_history::WorkHistory;
+use work_notify::{WorkNotifier, WorkPoster, HttpWorkPoster};
+use bundles::{TransactionBundles, verify_bundle};
+use super::{MinerService, MinerStatus, SealingWork, CallOverrides, TransactionQueue, AccountDetails, TransactionImportResult, TransactionImportError, TransactionOrigin, TransactionDetails};
+use super::{SubmissionOrigin, SubmissionAudit, SubmissionStats, AuditEntry};
+use super::{BundleId, BundleStatus, BundleImportError};
+
+/// Default number of blocks after which transactions are dropped from the queue.
+const DEFAULT_TRANSACTION_QUEUE_MAX_AGE: BlockNumber = 600;
+/// Number of recently given out work packages for which solutions are accepted.
+const SEALING_WORK_HISTORY_SIZE: usize = 20;
+/// Number of seconds after which reserved nonce is given out again if its transaction wasn't imported.
+const NONCE_RESERVATION_TIMEOUT_SECS: u64 = 60;
+
+/// Kinds of imported transactions which trigger preparing a new block for sealing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResealPolicy {
+	/// Reseal when transactions from the network are imported.
+	pub reseal_on_external_tx: bool,
+	/// Reseal when own transaction is imported.
+	pub reseal_on_own_tx: bool,
+}
+
+impl Default for ResealPolicy {
+	fn default() -> Self {
+		ResealPolicy {
+			reseal_on_external_tx: true,
+			reseal_on_own_tx: true,
+		}
+	}
+}
+
+/// Changes of the chain reported to chain listeners.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainEvent {
+	/// Hashes of imported blocks.
+	pub imported: Vec<H256>,
+	/// Hashes of blocks found invalid.
+	pub invalid: Vec<H256>,
+	/// Hashes of blocks which became part of the canonical chain.
+	pub enacted: Vec<H256>,
+	/// Hashes of blocks removed from the canonical chain.
+	pub retracted: Vec<H256>,
+	/// Number of canonical blocks replaced by the reorganisation (`0` if the chain was only extended).
+	pub reorg_depth: usize,
+}
+
+/// Identifier of registered chain listener.
+pub type ChainListenerId = usize;
+
+/// Callback notified about changes of the chain.
+pub type ChainListener = Box<Fn(&ChainEvent) + Send + Sync>;
 
 /// Keeps track of transactions using priority queue and holds currently mined block.
 pub struct Miner {
 	transaction_queue: Mutex<TransactionQueue>,
+	transaction_queue_max_age: RwLock<BlockNumber>,
+	/// Transaction gas limit was set explicitly and it's not updated with block gas limit.
+	tx_gas_limit_pinned: AtomicBool,
+	submission_audit: Mutex<SubmissionAudit>,
+	transaction_bundles: Mutex<TransactionBundles>,
 
 	// for sealing...
 	force_sealing: bool,
-	sealing_enabled: AtomicBool,
+	sealing_active: AtomicBool,
+	sealing_allowed: AtomicBool,
+	sealing_paused: AtomicBool,
 	sealing_block_last_request: Mutex<u64>,
+	sealing_work_timeout: RwLock<Option<Duration>>,
+	sealing_last_work_request: Mutex<Option<Instant>>,
 	sealing_work: Mutex<UsingQueue<ClosedBlock>>,
-	gas_floor_target: RwLock<U256>,
+	/// Work given out to miners (with its job id), solutions are accepted for any of it.
+	sealing_history: Mutex<WorkHistory<(u64, ClosedBlock)>>,
+	/// Id of the most recent job given out to miners.
+	sealing_job_id: Mutex<Option<u64>>,
+	work_notify: RwLock<Option<WorkNotifier>>,
+	reseal_policy: RwLock<ResealPolicy>,
+	reseal_min_period: RwLock<Duration>,
+	gas_price_percentile: RwLock<usize>,
+	/// Gas prices of recent blocks (with the best block hash they were collected at).
+	recent_gas_prices: Mutex<Option<(H256, Option<GasPriceStatistics>)>>,
+	/// Time and parent hash of the last prepared block.
+	sealing_last_prepared: Mutex<Option<(Instant, H256)>>,
+	sealing_dirty: AtomicBool,
+	/// Parameters of block construction (author, extra data, etc.) changed since the last prepared block.
+	sealing_outdated: AtomicBool,
+	/// Nonces given out to local senders (with their expiry time) whose transactions are not yet in the queue.
+	nonce_reservations: Mutex<HashMap<Address, BTreeMap<U256, Instant>>>,
+	clock: Box<Fn() -> Instant + Send + Sync>,
+	gas_limit_target: RwLock<GasLimitTarget>,
+	/// Include valid uncles in prepared blocks.
+	include_uncles: AtomicBool,
 	author: RwLock<Address>,
 	extra_data: RwLock<Bytes>,
 	spec: Spec,
 
+	chain_listeners: RwLock<BTreeMap<ChainListenerId, Arc<ChainListener>>>,
+	next_chain_listener_id: AtomicUsize,
 	accounts: RwLock<Option<Arc<AccountService>>>,		// TODO: this is horrible since AccountService already contains a single RwLock field. refactor.
 }
 
@@ -51,11 +143,34 @@ impl Default for Miner {
 	fn default() -> Miner {
 		Miner {
 			transaction_queue: Mutex::new(TransactionQueue::new()),
+			transaction_queue_max_age: RwLock::new(DEFAULT_TRANSACTION_QUEUE_MAX_AGE),
+			tx_gas_limit_pinned: AtomicBool::new(false),
+			submission_audit: Mutex::new(SubmissionAudit::default()),
+			transaction_bundles: Mutex::new(TransactionBundles::default()),
 			force_sealing: false,
-			sealing_enabled: AtomicBool::new(false),
+			sealing_active: AtomicBool::new(false),
+			sealing_allowed: AtomicBool::new(true),
+			sealing_paused: AtomicBool::new(false),
 			sealing_block_last_request: Mutex::new(0),
+			sealing_work_timeout: RwLock::new(None),
+			sealing_last_work_request: Mutex::new(None),
 			sealing_work: Mutex::new(UsingQueue::new(5)),
-			gas_floor_target: RwLock::new(U256::zero()),
+			sealing_history: Mutex::new(WorkHistory::new(SEALING_WORK_HISTORY_SIZE)),
+			sealing_job_id: Mutex::new(None),
+			work_notify: RwLock::new(None),
+			reseal_policy: RwLock::new(ResealPolicy::default()),
+			reseal_min_period: RwLock::new(Duration::from_millis(0)),
+			gas_price_percentile: RwLock::new(DEFAULT_GAS_PRICE_PERCENTILE),
+			recent_gas_prices: Mutex::new(None),
+			sealing_last_prepared: Mutex::new(None),
+			sealing_dirty: AtomicBool::new(false),
+			sealing_outdated: AtomicBool::new(false),
+			nonce_reservations: Mutex::new(HashMap::new()),
+			clock: Box::new(Instant::now),
+			gas_limit_target: RwLock::new(GasLimitTarget::default()),
+			include_uncles: AtomicBool::new(true),
+			chain_listeners: RwLock::new(BTreeMap::new()),
+			next_chain_listener_id: AtomicUsize::new(0),
 			author: RwLock::new(Address::default()),
 			extra_data: RwLock::new(Vec::new()),
 			accounts: RwLock::new(None),
@@ -64,16 +179,105 @@ impl Default for Miner {
 	}
 }
 
+/// Returns environment for executing transactions on top of pending block `work`.
+fn pending_env_info(chain: &BlockChainClient, work: &ClosedBlock) -> EnvInfo {
+	let header = work.block().header();
+	EnvInfo {
+		number: header.number(),
+		author: *header.author(),
+		timestamp: header.timestamp(),
+		difficulty: *header.difficulty(),
+		last_hashes: chain.last_hashes(),
+		gas_used: U256::zero(),
+		gas_limit: *header.gas_limit(),
+	}
+}
+
+/// Decodes raw transactions and recovers their senders in parallel.
+/// Results are returned in the order of given transactions.
+fn decode_transactions(transactions: &[Bytes]) -> Vec<Result<SignedTransaction, TransactionImportError>> {
+	let mut valid = Vec::with_capacity(transactions.len());
+	let decoded = transactions.iter()
+		.map(|rlp| match UntrustedRlp::new(rlp).as_val::<SignedTransaction>() {
+			Ok(transaction) => {
+				valid.push(transaction);
+				Ok(())
+			},
+			Err(e) => Err(TransactionImportError::InvalidRlp(e)),
+		})
+		.collect::<Vec<_>>();
+	recover_senders(&mut valid);
+
+	let mut valid = valid.into_iter();
+	decoded.into_iter()
+		.map(|tx| tx.and_then(|_| {
+			let transaction = valid.next().expect("one valid transaction is collected for every decoded one; qed");
+			try!(transaction.sender().map_err(|_| TransactionImportError::InvalidSignature));
+			Ok(transaction)
+		}))
+		.collect()
+}
+
+/// Recovers senders of all transactions in parallel and caches them within the transactions.
+/// Transactions with invalid signatures are left as they are and get rejected by the queue.
+fn recover_senders(transactions: &mut [SignedTransaction]) {
+	transactions.par_iter_mut().for_each(|tx| {
+		let _sender = tx.sender();
+	});
+}
+
+/// Number of recent blocks used to suggest gas price.
+const GAS_PRICE_SAMPLE_SIZE: usize = 100;
+/// Percentile of recent gas prices suggested by default.
+const DEFAULT_GAS_PRICE_PERCENTILE: usize = 50;
+
+/// Gas given to the gas price rule contract call.
+const GAS_PRICE_RULE_CALL_GAS: u64 = 50_000;
+
+/// Creates transaction queue respecting gas price rule of the chain.
+fn transaction_queue_for(spec: &Spec) -> TransactionQueue {
+	let mut queue = TransactionQueue::new();
+	let params = spec.engine.params();
+	if let Some(ref rule) = params.gas_price_rule {
+		queue.set_gas_price_rule(rule.clone());
+	}
+	queue.set_chain_id(params.chain_id, params.chain_id_transition);
+	queue
+}
+
 impl Miner {
 	/// Creates new instance of miner
 	pub fn new(force_sealing: bool, spec: Spec) -> Arc<Miner> {
 		Arc::new(Miner {
-			transaction_queue: Mutex::new(TransactionQueue::new()),
+			transaction_queue: Mutex::new(transaction_queue_for(&spec)),
+			transaction_queue_max_age: RwLock::new(DEFAULT_TRANSACTION_QUEUE_MAX_AGE),
+			tx_gas_limit_pinned: AtomicBool::new(false),
+			submission_audit: Mutex::new(SubmissionAudit::default()),
+			transaction_bundles: Mutex::new(TransactionBundles::default()),
 			force_sealing: force_sealing,
-			sealing_enabled: AtomicBool::new(force_sealing),
+			sealing_active: AtomicBool::new(force_sealing),
+			sealing_allowed: AtomicBool::new(true),
+			sealing_paused: AtomicBool::new(false),
 			sealing_block_last_request: Mutex::new(0),
+			sealing_work_timeout: RwLock::new(None),
+			sealing_last_work_request: Mutex::new(None),
 			sealing_work: Mutex::new(UsingQueue::new(5)),
-			gas_floor_target: RwLock::new(U256::zero()),
+			sealing_history: Mutex::new(WorkHistory::new(SEALING_WORK_HISTORY_SIZE)),
+			sealing_job_id: Mutex::new(None),
+			work_notify: RwLock::new(None),
+			reseal_policy: RwLock::new(ResealPolicy::default()),
+			reseal_min_period: RwLock::new(Duration::from_millis(0)),
+			gas_price_percentile: RwLock::new(DEFAULT_GAS_PRICE_PERCENTILE),
+			recent_gas_prices: Mutex::new(None),
+			sealing_last_prepared: Mutex::new(None),
+			sealing_dirty: AtomicBool::new(false),
+			sealing_outdated: AtomicBool::new(false),
+			nonce_reservations: Mutex::new(HashMap::new()),
+			clock: Box::new(Instant::now),
+			gas_limit_target: RwLock::new(GasLimitTarget::default()),
+			include_uncles: AtomicBool::new(true),
+			chain_listeners: RwLock::new(BTreeMap::new()),
+			next_chain_listener_id: AtomicUsize::new(0),
 			author: RwLock::new(Address::default()),
 			extra_data: RwLock::new(Vec::new()),
 			accounts: RwLock::new(None),
@@ -84,12 +288,35 @@ impl Miner {
 	/// Creates new instance of miner
 	pub fn with_accounts(force_sealing: bool, spec: Spec, accounts: Arc<AccountService>) -> Arc<Miner> {
 		Arc::new(Miner {
-			transaction_queue: Mutex::new(TransactionQueue::new()),
+			transaction_queue: Mutex::new(transaction_queue_for(&spec)),
+			transaction_queue_max_age: RwLock::new(DEFAULT_TRANSACTION_QUEUE_MAX_AGE),
+			tx_gas_limit_pinned: AtomicBool::new(false),
+			submission_audit: Mutex::new(SubmissionAudit::default()),
+			transaction_bundles: Mutex::new(TransactionBundles::default()),
 			force_sealing: force_sealing,
-			sealing_enabled: AtomicBool::new(force_sealing),
+			sealing_active: AtomicBool::new(force_sealing),
+			sealing_allowed: AtomicBool::new(true),
+			sealing_paused: AtomicBool::new(false),
 			sealing_block_last_request: Mutex::new(0),
+			sealing_work_timeout: RwLock::new(None),
+			sealing_last_work_request: Mutex::new(None),
 			sealing_work: Mutex::new(UsingQueue::new(5)),
-			gas_floor_target: RwLock::new(U256::zero()),
+			sealing_history: Mutex::new(WorkHistory::new(SEALING_WORK_HISTORY_SIZE)),
+			sealing_job_id: Mutex::new(None),
+			work_notify: RwLock::new(None),
+			reseal_policy: RwLock::new(ResealPolicy::default()),
+			reseal_min_period: RwLock::new(Duration::from_millis(0)),
+			gas_price_percentile: RwLock::new(DEFAULT_GAS_PRICE_PERCENTILE),
+			recent_gas_prices: Mutex::new(None),
+			sealing_last_prepared: Mutex::new(None),
+			sealing_dirty: AtomicBool::new(false),
+			sealing_outdated: AtomicBool::new(false),
+			nonce_reservations: Mutex::new(HashMap::new()),
+			clock: Box::new(Instant::now),
+			gas_limit_target: RwLock::new(GasLimitTarget::default()),
+			include_uncles: AtomicBool::new(true),
+			chain_listeners: RwLock::new(BTreeMap::new()),
+			next_chain_listener_id: AtomicUsize::new(0),
 			author: RwLock::new(Address::default()),
 			extra_data: RwLock::new(Vec::new()),
 			accounts: RwLock::new(Some(accounts)),
@@ -101,14 +328,234 @@ impl Miner {
 		self.spec.engine.deref()
 	}
 
+	/// Returns gas limit targets moved into protocol bounds, warning about every adjusted value.
+	fn clamp_gas_range_target(&self, floor: U256, ceil: U256) -> (U256, U256) {
+		let min_gas_limit = self.engine().params().min_gas_limit;
+		let floor = if floor < min_gas_limit {
+			warn!(target: "miner", "Gas floor target {} is below minimal gas limit; using {} instead.", floor, min_gas_limit);
+			min_gas_limit
+		} else {
+			floor
+		};
+		let ceil = if ceil < floor {
+			warn!(target: "miner", "Gas ceil target {} is below gas floor target; using {} instead.", ceil, floor);
+			floor
+		} else {
+			ceil
+		};
+		(floor, ceil)
+	}
+
+	fn gas_limit_target(&self) -> GasLimitTarget {
+		*self.gas_limit_target.read().unwrap()
+	}
+
+	/// Pauses (or resumes) preparing blocks for sealing.
+	/// While paused no sealing work is given out.
+	pub fn set_sealing_paused(&self, paused: bool) {
+		self.sealing_paused.store(paused, atomic::Ordering::Relaxed);
+		if paused {
+			self.sealing_work.lock().unwrap().reset();
+			self.sealing_history.lock().unwrap().clear();
+		}
+	}
+
+	/// Sets URLs to which every new work package is posted. Empty list disables notifications.
+	pub fn set_work_notify(&self, urls: Vec<String>) {
+		self.set_work_notify_with(urls, Arc::new(HttpWorkPoster));
+	}
+
+	/// Sets URLs to which every new work package is posted using given `poster`.
+	pub fn set_work_notify_with(&self, urls: Vec<String>, poster: Arc<WorkPoster>) {
+		*self.work_notify.write().unwrap() = match urls.is_empty() {
+			true => None,
+			false => Some(WorkNotifier::new(urls, poster)),
+		};
+	}
+
+	/// Returns `true` if sealing is paused.
+	pub fn is_sealing_paused(&self) -> bool {
+		self.sealing_paused.load(atomic::Ordering::Relaxed)
+	}
+
+	/// Sets which imported transactions trigger preparing a new block for sealing.
+	pub fn set_reseal_policy(&self, policy: ResealPolicy) {
+		*self.reseal_policy.write().unwrap() = policy;
+	}
+
+	/// Returns current reseal policy.
+	pub fn reseal_policy(&self) -> ResealPolicy {
+		*self.reseal_policy.read().unwrap()
+	}
+
+	/// Saves all transactions from the queue to file at `path`, so they can be restored after restart.
+	pub fn save_transactions(&self, path: &Path) -> io::Result<()> {
+		self.transaction_queue.lock().unwrap().save_to(path)
+	}
+
+	/// Restores transactions saved with `save_transactions`.
+	/// Transactions which are not valid in the latest state of `chain` are dropped.
+	/// Returns number of restored transactions.
+	pub fn load_transactions(&self, chain: &BlockChainClient, path: &Path) -> usize {
+		let fetch_account = |a: &Address| AccountDetails {
+			nonce: chain.nonce(a),
+			balance: chain.balance(a),
+		};
+		let mut queue = self.transaction_queue.lock().unwrap();
+		queue.set_current_block(chain.chain_info().best_block_number);
+		queue.load_from(path, &fetch_account)
+	}
+
+	/// Sets senders of service transactions.
+	/// Their transactions are accepted regardless of gas price and are included before all other transactions.
+	pub fn set_service_addresses(&self, addresses: Vec<Address>) {
+		self.transaction_queue.lock().unwrap().set_service_addresses(addresses);
+	}
+
+	/// Returns senders of service transactions.
+	pub fn service_addresses(&self) -> Vec<Address> {
+		self.transaction_queue.lock().unwrap().service_addresses()
+	}
+
+	/// Sets percentile of recent gas prices returned by `sensible_gas_price`.
+	pub fn set_gas_price_percentile(&self, percentile: usize) {
+		*self.gas_price_percentile.write().unwrap() = cmp::min(percentile, 100);
+	}
+
+	/// Returns percentile of recent gas prices returned by `sensible_gas_price`.
+	pub fn gas_price_percentile(&self) -> usize {
+		*self.gas_price_percentile.read().unwrap()
+	}
+
+	/// Returns gas prices of recent blocks. Collected again only when the best block changes.
+	fn recent_gas_prices(&self, chain: &BlockChainClient) -> Option<GasPriceStatistics> {
+		let best_block = chain.chain_info().best_block_hash;
+		let mut recent = self.recent_gas_prices.lock().unwrap();
+		if let Some((ref hash, ref statistics)) = *recent {
+			if *hash == best_block {
+				return statistics.clone();
+			}
+		}
+		let statistics = gas_price_statistics(chain, GAS_PRICE_SAMPLE_SIZE);
+		*recent = Some((best_block, statistics.clone()));
+		statistics
+	}
+
+	/// Registers `listener` notified after the miner has processed new blocks.
+	/// Returns id which can be used to remove the listener.
+	pub fn add_chain_listener(&self, listener: ChainListener) -> ChainListenerId {
+		let id = self.next_chain_listener_id.fetch_add(1, atomic::Ordering::Relaxed);
+		self.chain_listeners.write().unwrap().insert(id, Arc::new(listener));
+		id
+	}
+
+	/// Removes chain listener. Returns `false` if there was no listener with given id.
+	pub fn remove_chain_listener(&self, id: ChainListenerId) -> bool {
+		self.chain_listeners.write().unwrap().remove(&id).is_some()
+	}
+
+	/// Notifies chain listeners about `event`. Panicking listeners are logged and skipped.
+	fn notify_chain_listeners(&self, event: &ChainEvent) {
+		let listeners = self.chain_listeners.read().unwrap().iter().map(|(id, l)| (*id, l.clone())).collect::<Vec<_>>();
+		for (id, listener) in listeners {
+			if panic::catch_unwind(AssertUnwindSafe(|| listener(event))).is_err() {
+				warn!(target: "miner", "Chain listener {} panicked.", id);
+			}
+		}
+	}
+
+	/// Resolves minimal gas prices of senders not known to the queue yet.
+	/// Does nothing unless the gas price rule has to be resolved with a contract call.
+	fn resolve_contract_gas_prices<'a, I>(&self, chain: &BlockChainClient, transaction_queue: &mut TransactionQueue, transactions: I)
+		where I: IntoIterator<Item=&'a SignedTransaction> {
+		if !transaction_queue.gas_price_rule().requires_call() {
+			return;
+		}
+		let rule = transaction_queue.gas_price_rule().clone();
+		for sender in transactions.into_iter().filter_map(|t| t.sender().ok()) {
+			if transaction_queue.has_contract_gas_price(&sender) {
+				continue;
+			}
+			let price = rule.required_gas_price(&sender, |registry, data| {
+				let call = Transaction {
+					nonce: chain.nonce(&Address::zero()),
+					action: Action::Call(registry.clone()),
+					gas: U256::from(GAS_PRICE_RULE_CALL_GAS),
+					gas_price: U256::zero(),
+					value: U256::zero(),
+					data: data,
+				}.fake_sign(Address::zero());
+				chain.call(&call, None).ok().map(|executed| executed.output)
+			});
+			trace!(target: "miner", "Resolved minimal gas price of {}: {}", sender, price);
+			transaction_queue.set_contract_gas_price(sender, price);
+		}
+	}
+
+	/// Returns `true` if sealing work wasn't requested for longer than configured timeout.
+	fn sealing_work_timed_out(&self) -> bool {
+		let timeout = *self.sealing_work_timeout.read().unwrap();
+		match (timeout, *self.sealing_last_work_request.lock().unwrap()) {
+			(Some(timeout), Some(last_request)) => (self.clock)() >= last_request + timeout,
+			_ => false,
+		}
+	}
+
+	/// Returns `true` if a block on top of current best block was prepared less than `reseal_min_period` ago.
+	fn reseal_too_soon(&self, chain: &BlockChainClient) -> bool {
+		let min_period = *self.reseal_min_period.read().unwrap();
+		match *self.sealing_last_prepared.lock().unwrap() {
+			Some((prepared_at, ref parent)) => *parent == chain.chain_info().best_block_hash && (self.clock)() < prepared_at + min_period,
+			None => false,
+		}
+	}
+
+	/// Applies `pending` to the pending block if there is one built on top of current best block.
+	/// Otherwise (sealing disabled or pending block is stale) returns the `latest` state equivalent.
+	fn pending_state_or_latest<T, P, L>(&self, chain: &BlockChainClient, pending: P, latest: L) -> T
+		where P: FnOnce(&ClosedBlock) -> T, L: FnOnce() -> T {
+		let sealing_work = self.sealing_work.lock().unwrap();
+		match sealing_work.peek_last_ref() {
+			Some(b) if *b.block().header().parent_hash() == chain.chain_info().best_block_hash => pending(b),
+			_ => latest(),
+		}
+	}
+
+	/// Marks pending block as built with outdated parameters.
+	/// It's rebuilt from scratch when sealing work is requested next time.
+	fn outdate_sealing_work(&self) {
+		self.sealing_outdated.store(true, atomic::Ordering::Relaxed);
+		self.sealing_dirty.store(true, atomic::Ordering::Relaxed);
+	}
+
+	fn add_transactions_to_queue<T>(&self, chain: &BlockChainClient, transactions: Vec<SignedTransaction>, origin: TransactionOrigin, fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
+		where T: Fn(&Address) -> AccountDetails {
+		let mut transaction_queue = self.transaction_queue.lock().unwrap();
+		self.resolve_contract_gas_prices(chain, &mut transaction_queue, &transactions);
+		transactions.into_iter()
+			.map(|tx| transaction_queue.add(tx, &fetch_account, origin))
+			.collect()
+	}
+
 	/// Prepares new block for sealing including top transactions from queue.
 	#[cfg_attr(feature="dev", allow(match_same_arms))]
 	#[cfg_attr(feature="dev", allow(cyclomatic_complexity))]
 	fn prepare_sealing(&self, chain: &BlockChainClient) {
 		trace!(target: "miner", "prepare_sealing: entering");
+		if self.is_sealing_paused() || !self.sealing_enabled() {
+			trace!(target: "miner", "prepare_sealing: sealing paused or disabled");
+			return;
+		}
 		let transactions = self.transaction_queue.lock().unwrap().top_transactions();
+		let bundles = self.transaction_bundles.lock().unwrap().pending();
 		let mut sealing_work = self.sealing_work.lock().unwrap();
+		// Pending work may be taken out and refreshed below, remember it to avoid duplicate notifications.
+		let previous_work = sealing_work.peek_last_ref().map(|b| b.hash());
 		let best_hash = chain.best_block_header().sha3();
+		*self.sealing_last_prepared.lock().unwrap() = Some(((self.clock)(), chain.chain_info().best_block_hash));
+		self.sealing_dirty.store(false, atomic::Ordering::Relaxed);
+		let outdated = self.sealing_outdated.swap(false, atomic::Ordering::Relaxed);
 
 /*
 		// check to see if last ClosedBlock in would_seals is actually same parent block.
@@ -119,52 +566,58 @@ impl Miner {
 		// otherwise, author a fresh block.
 */
 
-		let (b, invalid_transactions) = match sealing_work.pop_if(|b| b.block().fields().header.parent_hash() == &best_hash) {
+		let (b, invalid_transactions) = match sealing_work.pop_if(|b| !outdated && b.block().fields().header.parent_hash() == &best_hash) {
 			Some(old_block) => {
 				trace!(target: "miner", "Already have previous work; updating and returning");
-				// add transactions to old_block
-				let e = self.engine();
-				let mut invalid_transactions = HashSet::new();
-				let mut block = old_block.reopen(e, chain.vm_factory());
-				let block_number = block.block().fields().header.number();
-
-				// TODO: push new uncles, too.
-				// TODO: refactor with chain.prepare_sealing
-				for tx in transactions {
-					let hash = tx.hash();
-					let res = block.push_transaction(tx, None);
-					match res {
-						Err(Error::Execution(ExecutionError::BlockGasLimitReached { gas_limit, gas_used, .. })) => {
-							trace!(target: "miner", "Skipping adding transaction to block because of gas limit: {:?}", hash);
-							// Exit early if gas left is smaller then min_tx_gas
-							let min_tx_gas: U256 = x!(21000);	// TODO: figure this out properly.
-							if gas_limit - gas_used < min_tx_gas {
-								break;
-							}
-						},
-						Err(Error::Transaction(TransactionError::AlreadyImported)) => {}	// already have transaction - ignore
-						Err(e) => {
-							invalid_transactions.insert(hash);
-							trace!(target: "miner",
-								   "Error adding transaction to block: number={}. transaction_hash={:?}, Error: {:?}",
-								   block_number, hash, e);
-						},
-						_ => {}	// imported ok
-					}
-				}
-				(Some(block.close()), invalid_transactions)
+				let (block, invalid_transactions) = self.extend_block(chain, old_block, bundles, transactions);
+				(Some(block), invalid_transactions)
 			}
-			None => {
+			None if bundles.is_empty() => {
 				// block not found - create it.
 				trace!(target: "miner", "No existing work - making new block");
 				chain.prepare_sealing(
 					self.author(),
-					self.gas_floor_target(),
+					self.gas_limit_target(),
 					self.extra_data(),
+					self.include_uncles(),
 					transactions,
 				)
 			}
+			None => {
+				// Bundles have to go first, so they are not deferred by transactions from the queue.
+				trace!(target: "miner", "No existing work - making new block with bundles");
+				match chain.prepare_sealing(self.author(), self.gas_limit_target(), self.extra_data(), self.include_uncles(), Vec::new()) {
+					(Some(block), _) => {
+						let (block, invalid_transactions) = self.extend_block(chain, block, bundles, transactions);
+						(Some(block), invalid_transactions)
+					},
+					(None, invalid_transactions) => (None, invalid_transactions),
+				}
+			}
+		};
+		let (b, invalid_transactions) = if b.as_ref().map_or(false, |b| Self::exceeds_gas_limit(b)) {
+			// Should never happen, but never publish work that would be rejected by peers.
+			warn!(target: "miner", "prepare_sealing: prepared block exceeds its gas limit; rebuilding.");
+			let transactions = self.transaction_queue.lock().unwrap().top_transactions();
+			let (rebuilt, mut rebuilt_invalid) = chain.prepare_sealing(
+				self.author(),
+				self.gas_limit_target(),
+				self.extra_data(),
+				self.include_uncles(),
+				transactions,
+			);
+			rebuilt_invalid.extend(invalid_transactions);
+			match rebuilt {
+				Some(ref b) if Self::exceeds_gas_limit(b) => {
+					warn!(target: "miner", "prepare_sealing: rebuilt block still exceeds its gas limit; discarding.");
+					(None, rebuilt_invalid)
+				},
+				rebuilt => (rebuilt, rebuilt_invalid),
+			}
+		} else {
+			(b, invalid_transactions)
 		};
+
 		let mut queue = self.transaction_queue.lock().unwrap();
 		let fetch_account = |a: &Address| AccountDetails {
 			nonce: chain.nonce(a),
@@ -200,16 +653,66 @@ impl Miner {
 			}
 			if sealing_work.peek_last_ref().map_or(true, |pb| pb.block().fields().header.hash() != block.block().fields().header.hash()) {
 				trace!(target: "miner", "Pushing a new, refreshed or borrowed pending {}...", block.block().fields().header.hash());
+				match *self.work_notify.read().unwrap() {
+					Some(ref notifier) if previous_work != Some(block.hash()) => {
+						let header = &block.block().fields().header;
+						notifier.notify(block.hash(), header.difficulty().clone(), header.number());
+					},
+					_ => {},
+				}
 				sealing_work.push(block);
 			}
 		}
 		trace!(target: "miner", "prepare_sealing: leaving (last={:?})", sealing_work.peek_last_ref().map(|b| b.block().fields().header.hash()));
 	}
 
+	/// Reopens `block` and pushes `bundles` followed by `transactions` to it.
+	/// Returns closed block and hashes of invalid transactions.
+	fn extend_block(&self, chain: &BlockChainClient, block: ClosedBlock, bundles: Vec<Vec<SignedTransaction>>, transactions: Vec<SignedTransaction>)
+		-> (ClosedBlock, HashSet<H256>) {
+		let mut block = block.reopen(self.engine(), chain.vm_factory());
+		Self::push_bundles(&mut block, bundles);
+
+		// TODO: push new uncles, too.
+		let invalid_transactions = block.push_transactions(transactions);
+		(block.close(), invalid_transactions)
+	}
+
+	/// Pushes each bundle to the block as a whole. Bundles which can't be included are deferred.
+	fn push_bundles(block: &mut OpenBlock, bundles: Vec<Vec<SignedTransaction>>) {
+		for bundle in bundles {
+			let first = bundle[0].hash();
+			if let Err(e) = block.push_transactions_atomically(bundle) {
+				// Also happens when the bundle is already included in reopened block.
+				trace!(target: "miner", "Deferring bundle starting with {:?}: {:?}", first, e);
+			}
+		}
+	}
+
+	/// Checks if header gas fields of closed block are consistent.
+	fn exceeds_gas_limit(block: &ClosedBlock) -> bool {
+		let header = block.block().header();
+		header.gas_used() > header.gas_limit()
+	}
+
 	fn update_gas_limit(&self, chain: &BlockChainClient) {
 		let gas_limit = HeaderView::new(&chain.best_block_header()).gas_limit();
 		let mut queue = self.transaction_queue.lock().unwrap();
 		queue.set_gas_limit(gas_limit);
+		if !self.tx_gas_limit_pinned.load(atomic::Ordering::Relaxed) {
+			queue.set_tx_gas_limit(gas_limit);
+		}
+	}
+
+	/// Makes sure that newly imported own work is included in pending block.
+	fn reseal_for_own_work(&self, chain: &BlockChainClient) {
+		// We need to create pending block and enable sealing
+		let prepared = self.enable_and_prepare_sealing(chain);
+		// If new block has not been prepared (means we already had one)
+		// we need to update sealing
+		if !prepared {
+			self.update_sealing(chain);
+		}
 	}
 
 	/// Returns true if we had to prepare new pending block
@@ -218,7 +721,7 @@ impl Miner {
 		let have_work = self.sealing_work.lock().unwrap().peek_last_ref().is_some();
 		trace!(target: "miner", "enable_and_prepare_sealing: have_work={}", have_work);
 		if !have_work {
-			self.sealing_enabled.store(true, atomic::Ordering::Relaxed);
+			self.sealing_active.store(true, atomic::Ordering::Relaxed);
 			self.prepare_sealing(chain);
 		}
 		let mut sealing_block_last_request = self.sealing_block_last_request.lock().unwrap();
@@ -249,93 +752,156 @@ impl MinerService for Miner {
 			transactions_in_pending_queue: status.pending,
 			transactions_in_future_queue: status.future,
 			transactions_in_pending_block: sealing_work.peek_last_ref().map_or(0, |b| b.transactions().len()),
+			transactions_bytes: status.bytes,
+			uncles_in_pending_block: sealing_work.peek_last_ref().map_or(0, |b| b.uncles().len()),
+		}
+	}
+
+	fn call(&self, chain: &BlockChainClient, t: &SignedTransaction, overrides: &CallOverrides, deadline: Option<Instant>) -> Result<Executed, ExecutionError> {
+		let sealing_work = self.sealing_work.lock().unwrap();
+		match sealing_work.peek_last_ref() {
+			Some(work) => {
+				let mut env_info = pending_env_info(chain, work);
+				env_info.gas_limit = U256::max_value();
+				overrides.apply(&mut env_info);
+				// that's just a copy of the state.
+				let mut state = work.block().state().clone();
+				let options = TransactOptions { tracing: false, vm_tracing: false, check_nonce: false, charge_fees: false, diffing: false, static_call: overrides.static_call, max_output_size: Some(MAX_CALL_OUTPUT_SIZE) };
+
+				Executive::new(&mut state, &env_info, self.engine(), chain.vm_factory())
+					.with_deadline(deadline)
+					.transact(t, options)
+			},
+			None => {
+				chain.call_with_env(t, &|env_info| overrides.apply(env_info), overrides.static_call, deadline)
+			}
 		}
 	}
 
-	fn call(&self, chain: &BlockChainClient, t: &SignedTransaction) -> Result<Executed, ExecutionError> {
+	fn estimate_gas(&self, chain: &BlockChainClient, t: &SignedTransaction, deadline: Option<Instant>) -> Result<U256, ExecutionError> {
 		let sealing_work = self.sealing_work.lock().unwrap();
 		match sealing_work.peek_last_ref() {
 			Some(work) => {
-				let block = work.block();
-				let header = block.header();
-				let last_hashes = chain.last_hashes();
-				let env_info = EnvInfo {
-					number: header.number(),
-					author: *header.author(),
-					timestamp: header.timestamp(),
-					difficulty: *header.difficulty(),
-					last_hashes: last_hashes,
-					gas_used: U256::zero(),
-					gas_limit: U256::max_value(),
-				};
+				let env_info = pending_env_info(chain, work);
 				// that's just a copy of the state.
-				let mut state = block.state().clone();
-				let sender = try!(t.sender().map_err(|e| {
-					let message = format!("Transaction malformed: {:?}", e);
-					ExecutionError::TransactionMalformed(message)
-				}));
-				let balance = state.balance(&sender);
-				// give the sender max balance
-				state.sub_balance(&sender, &balance);
-				state.add_balance(&sender, &U256::max_value());
-				let options = TransactOptions { tracing: false, check_nonce: false };
-
-				Executive::new(&mut state, &env_info, self.engine(), chain.vm_factory()).transact(t, options)
+				let mut state = work.block().state().clone();
+				let options = TransactOptions { tracing: false, vm_tracing: false, check_nonce: false, charge_fees: false, diffing: false, static_call: false, max_output_size: Some(MAX_CALL_OUTPUT_SIZE) };
+
+				Executive::new(&mut state, &env_info, self.engine(), chain.vm_factory())
+					.with_deadline(deadline)
+					.estimate_gas(t, options)
 			},
 			None => {
-				chain.call(t)
+				chain.estimate_gas(t, deadline)
 			}
 		}
 	}
 
 	fn balance(&self, chain: &BlockChainClient, address: &Address) -> U256 {
-		let sealing_work = self.sealing_work.lock().unwrap();
-		sealing_work.peek_last_ref().map_or_else(|| chain.balance(address), |b| b.block().fields().state.balance(address))
+		self.pending_state_or_latest(chain, |b| b.block().fields().state.balance(address), || chain.balance(address))
 	}
 
 	fn storage_at(&self, chain: &BlockChainClient, address: &Address, position: &H256) -> H256 {
-		let sealing_work = self.sealing_work.lock().unwrap();
-		sealing_work.peek_last_ref().map_or_else(|| chain.storage_at(address, position), |b| b.block().fields().state.storage_at(address, position))
+		self.pending_state_or_latest(chain, |b| b.block().fields().state.storage_at(address, position), || chain.storage_at(address, position))
 	}
 
 	fn nonce(&self, chain: &BlockChainClient, address: &Address) -> U256 {
-		let sealing_work = self.sealing_work.lock().unwrap();
-		sealing_work.peek_last_ref().map_or_else(|| chain.nonce(address), |b| b.block().fields().state.nonce(address))
+		self.pending_state_or_latest(chain, |b| b.block().fields().state.nonce(address), || chain.nonce(address))
 	}
 
 	fn code(&self, chain: &BlockChainClient, address: &Address) -> Option<Bytes> {
-		let sealing_work = self.sealing_work.lock().unwrap();
-		sealing_work.peek_last_ref().map_or_else(|| chain.code(address), |b| b.block().fields().state.code(address))
+		self.pending_state_or_latest(chain, |b| b.block().fields().state.code(address), || chain.code(address))
 	}
 
 	fn set_author(&self, author: Address) {
 		*self.author.write().unwrap() = author;
+		self.outdate_sealing_work();
 	}
 
 	fn set_extra_data(&self, extra_data: Bytes) {
 		*self.extra_data.write().unwrap() = extra_data;
+		self.outdate_sealing_work();
 	}
 
 	/// Set the gas limit we wish to target when sealing a new block.
 	fn set_gas_floor_target(&self, target: U256) {
-		*self.gas_floor_target.write().unwrap() = target;
+		let ceil = self.gas_ceil_target();
+		self.set_gas_range_target((target, max(ceil, target)));
+	}
+
+	fn gas_ceil_target(&self) -> U256 {
+		self.gas_limit_target.read().unwrap().ceil
+	}
+
+	fn set_gas_range_target(&self, (floor, ceil): (U256, U256)) {
+		let (floor, ceil) = self.clamp_gas_range_target(floor, ceil);
+		{
+			let mut gas_limit_target = self.gas_limit_target.write().unwrap();
+			gas_limit_target.floor = floor;
+			gas_limit_target.ceil = ceil;
+		}
+		self.outdate_sealing_work();
+	}
+
+	fn gas_limit_strategy(&self) -> GasLimitStrategy {
+		self.gas_limit_target.read().unwrap().strategy
+	}
+
+	fn set_gas_limit_strategy(&self, strategy: GasLimitStrategy) {
+		self.gas_limit_target.write().unwrap().strategy = strategy;
+		self.outdate_sealing_work();
+	}
+
+	fn include_uncles(&self) -> bool {
+		self.include_uncles.load(atomic::Ordering::Relaxed)
+	}
+
+	fn set_include_uncles(&self, include: bool) {
+		self.include_uncles.store(include, atomic::Ordering::Relaxed);
+		self.outdate_sealing_work();
+	}
+
+	fn tx_gas_limit(&self) -> U256 {
+		self.transaction_queue.lock().unwrap().tx_gas_limit()
+	}
+
+	fn set_tx_gas_limit(&self, limit: U256) {
+		let mut queue = self.transaction_queue.lock().unwrap();
+		self.tx_gas_limit_pinned.store(true, atomic::Ordering::Relaxed);
+		queue.set_tx_gas_limit(limit);
 	}
 
 	fn set_minimal_gas_price(&self, min_gas_price: U256) {
-		self.transaction_queue.lock().unwrap().set_minimal_gas_price(min_gas_price);
+		// Never go below the floor of the chain - blocks with such transactions would be invalid.
+		let chain_floor = self.engine().params().gas_price_rule.as_ref().map_or_else(U256::zero, GasPriceRule::floor);
+		self.transaction_queue.lock().unwrap().set_minimal_gas_price(cmp::max(min_gas_price, chain_floor));
+		self.outdate_sealing_work();
 	}
 
 	fn minimal_gas_price(&self) -> U256 {
-		*self.transaction_queue.lock().unwrap().minimal_gas_price()
+		self.transaction_queue.lock().unwrap().minimal_gas_price()
+	}
+
+	fn sensible_gas_price(&self, chain: &BlockChainClient) -> U256 {
+		let minimal = self.minimal_gas_price();
+		match self.recent_gas_prices(chain) {
+			// Never suggest a price our own queue would reject.
+			Some(statistics) => cmp::max(statistics.percentile(self.gas_price_percentile()), minimal),
+			// 10% above our minimum.
+			None => minimal * x!(110) / x!(100),
+		}
+	}
+
+	fn gas_price_rule(&self) -> GasPriceRule {
+		self.transaction_queue.lock().unwrap().gas_price_rule().clone()
 	}
 
-	fn sensible_gas_price(&self) -> U256 {
-		// 10% above our minimum.
-		*self.transaction_queue.lock().unwrap().minimal_gas_price() * x!(110) / x!(100)
+	fn set_gas_price_rule(&self, rule: GasPriceRule) {
+		self.transaction_queue.lock().unwrap().set_gas_price_rule(rule);
 	}
 
 	fn sensible_gas_limit(&self) -> U256 {
-		*self.gas_floor_target.read().unwrap() / x!(5)
+		self.gas_floor_target() / x!(5)
 	}
 
 	fn transactions_limit(&self) -> usize {
@@ -346,6 +912,84 @@ impl MinerService for Miner {
 		self.transaction_queue.lock().unwrap().set_limit(limit)
 	}
 
+	fn total_byte_limit(&self) -> usize {
+		self.transaction_queue.lock().unwrap().total_byte_limit()
+	}
+
+	fn set_total_byte_limit(&self, limit: usize) {
+		self.transaction_queue.lock().unwrap().set_total_byte_limit(limit)
+	}
+
+	fn max_per_sender(&self) -> usize {
+		self.transaction_queue.lock().unwrap().max_per_sender()
+	}
+
+	fn set_max_per_sender(&self, max_per_sender: usize) {
+		self.transaction_queue.lock().unwrap().set_max_per_sender(max_per_sender)
+	}
+
+	fn ban_address(&self, address: Address) {
+		let mut queue = self.transaction_queue.lock().unwrap();
+		queue.ban_sender(address.clone());
+		queue.ban_recipient(address);
+	}
+
+	fn unban_address(&self, address: &Address) -> bool {
+		let mut queue = self.transaction_queue.lock().unwrap();
+		let sender = queue.unban_sender(address);
+		let recipient = queue.unban_recipient(address);
+		sender || recipient
+	}
+
+	fn banned_addresses(&self) -> Vec<Address> {
+		let queue = self.transaction_queue.lock().unwrap();
+		let banned = queue.banned_senders().into_iter()
+			.chain(queue.banned_recipients().into_iter())
+			.collect::<BTreeSet<_>>();
+		banned.into_iter().collect()
+	}
+
+	fn transaction_queue_max_age(&self) -> BlockNumber {
+		*self.transaction_queue_max_age.read().unwrap()
+	}
+
+	fn set_transaction_queue_max_age(&self, max_age: BlockNumber) {
+		*self.transaction_queue_max_age.write().unwrap() = max_age;
+	}
+
+	fn reseal_min_period(&self) -> Duration {
+		*self.reseal_min_period.read().unwrap()
+	}
+
+	fn set_reseal_min_period(&self, period: Duration) {
+		*self.reseal_min_period.write().unwrap() = period;
+	}
+
+	fn sealing_enabled(&self) -> bool {
+		self.sealing_allowed.load(atomic::Ordering::Relaxed)
+	}
+
+	fn set_sealing_enabled(&self, enabled: bool) {
+		self.sealing_allowed.store(enabled, atomic::Ordering::Relaxed);
+		if !enabled {
+			self.sealing_work.lock().unwrap().reset();
+			self.sealing_history.lock().unwrap().clear();
+		}
+	}
+
+	fn sealing_work_timeout(&self) -> Option<Duration> {
+		*self.sealing_work_timeout.read().unwrap()
+	}
+
+	fn set_sealing_work_timeout(&self, timeout: Option<Duration>) {
+		*self.sealing_work_timeout.write().unwrap() = timeout;
+		// Start measuring from now if the work wasn't requested yet.
+		let mut last_request = self.sealing_last_work_request.lock().unwrap();
+		if last_request.is_none() {
+			*last_request = Some((self.clock)());
+		}
+	}
+
 	/// Get the author that we will seal blocks as.
 	fn author(&self) -> Address {
 		*self.author.read().unwrap()
@@ -358,20 +1002,51 @@ impl MinerService for Miner {
 
 	/// Get the gas limit we wish to target when sealing a new block.
 	fn gas_floor_target(&self) -> U256 {
-		*self.gas_floor_target.read().unwrap()
+		self.gas_limit_target.read().unwrap().floor
 	}
 
-	fn import_transactions<T>(&self, transactions: Vec<SignedTransaction>, fetch_account: T) ->
-		Vec<Result<TransactionImportResult, Error>>
+	fn import_transactions<T>(&self, chain: &BlockChainClient, mut transactions: Vec<SignedTransaction>, origin: Option<SubmissionOrigin>, fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
 		where T: Fn(&Address) -> AccountDetails {
-		let mut transaction_queue = self.transaction_queue.lock().unwrap();
-		transactions.into_iter()
-			.map(|tx| transaction_queue.add(tx, &fetch_account, TransactionOrigin::External))
-			.collect()
+		// Sender recovery is expensive, do it before taking the queue lock.
+		recover_senders(&mut transactions);
+		let hashes = match origin {
+			Some(_) => transactions.iter().map(|t| t.hash()).collect(),
+			None => Vec::new(),
+		};
+		let results = self.add_transactions_to_queue(chain, transactions, TransactionOrigin::External, fetch_account);
+		if let Some(origin) = origin {
+			let mut audit = self.submission_audit.lock().unwrap();
+			for (hash, result) in hashes.into_iter().zip(results.iter()) {
+				audit.record(hash, origin.clone(), result);
+			}
+		}
+		if self.reseal_policy().reseal_on_external_tx && results.iter().any(|r| r.is_ok()) {
+			self.update_sealing(chain);
+		}
+		results
+	}
+
+	fn import_raw_transactions<T>(&self, chain: &BlockChainClient, transactions: Vec<Bytes>, fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
+		where T: Fn(&Address) -> AccountDetails {
+		// Decoding and sender recovery don't need the queue.
+		let decoded = decode_transactions(&transactions);
+		let results = {
+			let mut transaction_queue = self.transaction_queue.lock().unwrap();
+			self.resolve_contract_gas_prices(chain, &mut transaction_queue, decoded.iter().filter_map(|tx| tx.as_ref().ok()));
+			decoded.into_iter()
+				.map(|tx| tx.and_then(|tx| transaction_queue.add(tx, &fetch_account, TransactionOrigin::External)))
+				.collect::<Vec<_>>()
+		};
+		if self.reseal_policy().reseal_on_external_tx && results.iter().any(|r| r.is_ok()) {
+			self.update_sealing(chain);
+		}
+		results
 	}
 
-	fn import_own_transaction<T>(&self, chain: &BlockChainClient, transaction: SignedTransaction, fetch_account: T) ->
-		Result<TransactionImportResult, Error>
+	fn import_own_transaction<T>(&self, chain: &BlockChainClient, transaction: SignedTransaction, origin: Option<SubmissionOrigin>, fetch_account: T) ->
+		Result<TransactionImportResult, TransactionImportError>
 		where T: Fn(&Address) -> AccountDetails {
 		let hash = transaction.hash();
 		trace!(target: "own_tx", "Importing transaction: {:?}", transaction);
@@ -379,6 +1054,7 @@ impl MinerService for Miner {
 		let imported = {
 			// Be sure to release the lock before we call enable_and_prepare_sealing
 			let mut transaction_queue = self.transaction_queue.lock().unwrap();
+			self.resolve_contract_gas_prices(chain, &mut transaction_queue, Some(&transaction));
 			let import = transaction_queue.add(transaction, &fetch_account, TransactionOrigin::Local);
 
 			match import {
@@ -393,23 +1069,71 @@ impl MinerService for Miner {
 			}
 			import
 		};
+		self.submission_audit.lock().unwrap().record(hash, origin.unwrap_or_else(SubmissionOrigin::anonymous), &imported);
 
-		if imported.is_ok() {
+		if imported.is_ok() && self.reseal_policy().reseal_on_own_tx {
 			// Make sure to do it after transaction is imported and lock is droped.
-			// We need to create pending block and enable sealing
-			let prepared = self.enable_and_prepare_sealing(chain);
-			// If new block has not been prepared (means we already had one)
-			// we need to update sealing
-			if !prepared {
-				self.update_sealing(chain);
-			}
+			self.reseal_for_own_work(chain);
 		}
 
 		imported
 	}
 
+	fn import_transaction_bundle<T>(&self, chain: &BlockChainClient, transactions: Vec<SignedTransaction>, fetch_account: T) ->
+		Result<BundleId, BundleImportError>
+		where T: Fn(&Address) -> AccountDetails {
+		{
+			let mut transaction_queue = self.transaction_queue.lock().unwrap();
+			self.resolve_contract_gas_prices(chain, &mut transaction_queue, &transactions);
+			let gas_limit = HeaderView::new(&chain.best_block_header()).gas_limit();
+			try!(verify_bundle(&transactions, &transaction_queue, gas_limit, &fetch_account));
+		}
+		let id = self.transaction_bundles.lock().unwrap().add(transactions, chain.chain_info().best_block_number);
+		trace!(target: "own_tx", "Imported bundle {}", id);
+
+		if self.reseal_policy().reseal_on_own_tx {
+			self.reseal_for_own_work(chain);
+		}
+		Ok(id)
+	}
+
+	fn bundle_status(&self, id: BundleId) -> Option<BundleStatus> {
+		self.transaction_bundles.lock().unwrap().status(id)
+	}
+
+	fn bundle_max_age(&self) -> BlockNumber {
+		self.transaction_bundles.lock().unwrap().max_age()
+	}
+
+	fn set_bundle_max_age(&self, max_age: BlockNumber) {
+		self.transaction_bundles.lock().unwrap().set_max_age(max_age);
+	}
+
+	fn transaction_audit(&self, hash: &H256) -> Vec<AuditEntry> {
+		self.submission_audit.lock().unwrap().entries(hash)
+	}
+
+	fn submission_stats(&self) -> BTreeMap<SubmissionOrigin, SubmissionStats> {
+		self.submission_audit.lock().unwrap().stats()
+	}
+
+	fn remove_transaction(&self, hash: &H256) -> Option<SignedTransaction> {
+		let removed = self.transaction_queue.lock().unwrap().remove(hash);
+		if removed.is_some() {
+			let mut sealing_work = self.sealing_work.lock().unwrap();
+			let is_pending = sealing_work.peek_last_ref().map_or(false, |b| b.transactions().iter().any(|t| &t.hash() == hash));
+			if is_pending {
+				// Block being sealed contains removed transaction, it needs to be prepared again.
+				trace!(target: "miner", "Removed transaction {:?} was included in pending block. Resetting sealing work.", hash);
+				sealing_work.reset();
+			}
+			self.sealing_history.lock().unwrap().retain(|&(_, ref b)| b.transactions().iter().all(|t| &t.hash() != hash));
+		}
+		removed
+	}
+
 	fn pending_transactions_hashes(&self) -> Vec<H256> {
-		match (self.sealing_enabled.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
+		match (self.sealing_active.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
 			(true, Some(pending)) => pending.transactions().iter().map(|t| t.hash()).collect(),
 			_ => {
 				let queue = self.transaction_queue.lock().unwrap();
@@ -419,7 +1143,7 @@ impl MinerService for Miner {
 	}
 
 	fn transaction(&self, hash: &H256) -> Option<SignedTransaction> {
-		match (self.sealing_enabled.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
+		match (self.sealing_active.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
 			(true, Some(pending)) => pending.transactions().iter().find(|t| &t.hash() == hash).map(|t| t.clone()),
 			_ => {
 				let queue = self.transaction_queue.lock().unwrap();
@@ -433,9 +1157,17 @@ impl MinerService for Miner {
 		queue.top_transactions()
 	}
 
+	fn future_transactions(&self) -> Vec<SignedTransaction> {
+		self.transaction_queue.lock().unwrap().future_transactions()
+	}
+
+	fn transaction_details(&self, hash: &H256) -> Option<TransactionDetails> {
+		self.transaction_queue.lock().unwrap().transaction_details(hash)
+	}
+
 	fn pending_transactions(&self) -> Vec<SignedTransaction> {
 		// TODO: should only use the sealing_work when it's current (it could be an old block)
-		match (self.sealing_enabled.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
+		match (self.sealing_active.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
 			(true, Some(pending)) => pending.transactions().clone(),
 			_ => {
 				let queue = self.transaction_queue.lock().unwrap();
@@ -445,7 +1177,7 @@ impl MinerService for Miner {
 	}
 
 	fn pending_receipts(&self) -> BTreeMap<H256, Receipt> {
-		match (self.sealing_enabled.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
+		match (self.sealing_active.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
 			(true, Some(pending)) => {
 				let hashes = pending.transactions()
 					.iter()
@@ -459,55 +1191,167 @@ impl MinerService for Miner {
 		}
 	}
 
+	fn pending_logs(&self, filter: &Filter) -> Vec<LocalizedLogEntry> {
+		match (self.sealing_active.load(atomic::Ordering::Relaxed), self.sealing_work.lock().unwrap().peek_last_ref()) {
+			(true, Some(pending)) => {
+				let header = &pending.block().fields().header;
+				let (block_hash, block_number) = (header.hash(), header.number());
+				let mut log_index = 0;
+				pending.transactions().iter()
+					.zip(pending.receipts().iter())
+					.enumerate()
+					.flat_map(|(index, (tx, receipt))| {
+						let first_log_index = log_index;
+						log_index += receipt.logs.len();
+						receipt.logs.iter()
+							.enumerate()
+							.filter(|&(_, log)| filter.matches(log))
+							.map(|(i, log)| LocalizedLogEntry {
+								entry: log.clone(),
+								block_hash: block_hash,
+								block_number: block_number,
+								transaction_hash: tx.hash(),
+								transaction_index: index,
+								log_index: first_log_index + i,
+							})
+							.collect::<Vec<_>>()
+					})
+					.collect()
+			},
+			_ => Vec::new()
+		}
+	}
+
 	fn last_nonce(&self, address: &Address) -> Option<U256> {
 		self.transaction_queue.lock().unwrap().last_nonce(address)
 	}
 
+	fn reserve_nonce(&self, chain: &BlockChainClient, address: &Address) -> U256 {
+		let queue = self.transaction_queue.lock().unwrap();
+		let mut reservations = self.nonce_reservations.lock().unwrap();
+		let now = (self.clock)();
+		let state_nonce = chain.nonce(address);
+		let next_nonce = queue.last_nonce(address).map_or(state_nonce, |nonce| cmp::max(state_nonce, nonce + U256::one()));
+
+		let reserved = reservations.entry(address.clone()).or_insert_with(BTreeMap::new);
+		// forget reservations which got imported (or mined) and the expired ones
+		let outdated = reserved.iter()
+			.filter(|&(nonce, expiry)| *nonce < next_nonce || *expiry <= now)
+			.map(|(nonce, _)| *nonce)
+			.collect::<Vec<_>>();
+		for nonce in outdated {
+			reserved.remove(&nonce);
+		}
+
+		// lowest nonce which is not reserved, so released reservations don't leave a gap
+		let mut nonce = next_nonce;
+		while reserved.contains_key(&nonce) {
+			nonce = nonce + U256::one();
+		}
+		reserved.insert(nonce, now + Duration::from_secs(NONCE_RESERVATION_TIMEOUT_SECS));
+		nonce
+	}
+
+	fn release_nonce(&self, address: &Address, nonce: &U256) {
+		if let Some(reserved) = self.nonce_reservations.lock().unwrap().get_mut(address) {
+			reserved.remove(nonce);
+		}
+	}
+
 	fn update_sealing(&self, chain: &BlockChainClient) {
-		if self.sealing_enabled.load(atomic::Ordering::Relaxed) {
+		if self.sealing_active.load(atomic::Ordering::Relaxed) && self.sealing_enabled() {
 			let current_no = chain.chain_info().best_block_number;
-			let has_local_transactions = self.transaction_queue.lock().unwrap().has_local_pending_transactions();
+			let has_local_transactions = self.transaction_queue.lock().unwrap().has_local_pending_transactions()
+				|| self.transaction_bundles.lock().unwrap().has_pending();
 			let last_request = *self.sealing_block_last_request.lock().unwrap();
-			let should_disable_sealing = !self.force_sealing
+			let work_timed_out = self.sealing_work_timed_out();
+			let should_disable_sealing = work_timed_out || (!self.force_sealing
 				&& !has_local_transactions
 				&& current_no > last_request
-				&& current_no - last_request > SEALING_TIMEOUT_IN_BLOCKS;
+				&& current_no - last_request > SEALING_TIMEOUT_IN_BLOCKS);
 
 			if should_disable_sealing {
-				trace!(target: "miner", "Miner sleeping (current {}, last {})", current_no, last_request);
-				self.sealing_enabled.store(false, atomic::Ordering::Relaxed);
+				trace!(target: "miner", "Miner sleeping (current {}, last {}, work request timed out: {})", current_no, last_request, work_timed_out);
+				self.sealing_active.store(false, atomic::Ordering::Relaxed);
 				self.sealing_work.lock().unwrap().reset();
-			} else if self.sealing_enabled.load(atomic::Ordering::Relaxed) {
+				self.sealing_history.lock().unwrap().clear();
+			} else if self.reseal_too_soon(chain) {
+				trace!(target: "miner", "update_sealing: block prepared recently, deferring");
+				self.sealing_dirty.store(true, atomic::Ordering::Relaxed);
+			} else if self.sealing_active.load(atomic::Ordering::Relaxed) {
 				self.prepare_sealing(chain);
 			}
 		}
 	}
 
-	fn map_sealing_work<F, T>(&self, chain: &BlockChainClient, f: F) -> Option<T> where F: FnOnce(&ClosedBlock) -> T {
+	fn map_sealing_work<F, T>(&self, chain: &BlockChainClient, f: F) -> Option<T> where F: FnOnce(&SealingWork) -> T {
 		trace!(target: "miner", "map_sealing_work: entering");
-		self.enable_and_prepare_sealing(chain);
+		if self.is_sealing_paused() || !self.sealing_enabled() {
+			trace!(target: "miner", "map_sealing_work: sealing paused or disabled");
+			return None;
+		}
+		*self.sealing_last_work_request.lock().unwrap() = Some((self.clock)());
+		let prepared = self.enable_and_prepare_sealing(chain);
+		let outdated = self.sealing_outdated.load(atomic::Ordering::Relaxed);
+		if !prepared && self.sealing_dirty.load(atomic::Ordering::Relaxed) && (outdated || !self.reseal_too_soon(chain)) {
+			trace!(target: "miner", "map_sealing_work: preparing deferred block");
+			self.prepare_sealing(chain);
+		}
 		trace!(target: "miner", "map_sealing_work: sealing prepared");
 		let mut sealing_work = self.sealing_work.lock().unwrap();
 		let ret = sealing_work.use_last_ref();
 		trace!(target: "miner", "map_sealing_work: leaving use_last_ref={:?}", ret.as_ref().map(|b| b.block().fields().header.hash()));
-		ret.map(f)
+		ret.map(|b| {
+			let pow_hash = b.hash();
+			let mut history = self.sealing_history.lock().unwrap();
+			let mut current_job_id = self.sealing_job_id.lock().unwrap();
+			let job_id = match history.get(&pow_hash) {
+				Some(&(job_id, _)) => job_id,
+				None => {
+					let job_id = current_job_id.map_or(0, |id| id + 1);
+					history.insert(pow_hash.clone(), (job_id, b.clone()));
+					job_id
+				},
+			};
+			*current_job_id = Some(job_id);
+			f(&SealingWork {
+				block: b,
+				job_id: job_id,
+				pow_hash: pow_hash,
+				number: b.block().header().number(),
+				difficulty: b.block().header().difficulty().clone(),
+			})
+		})
+	}
+
+	fn current_job_id(&self) -> Option<u64> {
+		*self.sealing_job_id.lock().unwrap()
 	}
 
-	fn submit_seal(&self, chain: &BlockChainClient, pow_hash: H256, seal: Vec<Bytes>) -> Result<(), Error> {
-		if let Some(b) = self.sealing_work.lock().unwrap().take_used_if(|b| &b.hash() == &pow_hash) {
+	fn submit_seal(&self, chain: &BlockChainClient, pow_hash: H256, seal: Vec<Bytes>, job_id: Option<u64>) -> Result<H256, Error> {
+		let work = {
+			let mut history = self.sealing_history.lock().unwrap();
+			if let Some(job_id) = job_id {
+				if !history.any(|&(id, _)| id == job_id) {
+					info!(target: "miner", "Mined block rejected, job {} is stale.", job_id);
+					return Err(Error::StaleJob);
+				}
+			}
+			history.take(&pow_hash)
+		};
+		if let Some((_, b)) = work {
 			match chain.try_seal(b.lock(), seal) {
 				Err(_) => {
 					info!(target: "miner", "Mined block rejected, PoW was invalid.");
 					Err(Error::PowInvalid)
 				}
 				Ok(sealed) => {
-					info!(target: "miner", "New block mined, hash: {}", sealed.header().hash());
+					let hash = sealed.header().hash();
+					info!(target: "miner", "New block mined, hash: {}", hash);
 					// TODO: commit DB from `sealed.drain` and make a VerifiedBlock to skip running the transactions twice.
-					let b = sealed.rlp_bytes();
-					let h = b.sha3();
-					try!(chain.import_block(b));
-					info!("Block {} submitted and imported.", h);
-					Ok(())
+					try!(chain.import_block(sealed.rlp_bytes()));
+					info!("Block {} submitted and imported.", hash);
+					Ok(hash)
 				}
 			}
 		} else {
@@ -516,7 +1360,7 @@ impl MinerService for Miner {
 		}
 	}
 
-	fn chain_new_blocks(&self, chain: &BlockChainClient, _imported: &[H256], _invalid: &[H256], enacted: &[H256], retracted: &[H256]) {
+	fn chain_new_blocks(&self, chain: &BlockChainClient, imported: &[H256], invalid: &[H256], enacted: &[H256], retracted: &[H256]) {
 		fn fetch_transactions(chain: &BlockChainClient, hash: &H256) -> Vec<SignedTransaction> {
 			let block = chain
 				.block(BlockID::Hash(*hash))
@@ -531,23 +1375,36 @@ impl MinerService for Miner {
 		// 2. We ignore blocks that are `invalid` because it doesn't have any meaning in terms of the transactions that
 		//    are in those blocks
 
-		// First update gas limit in transaction queue
+		// First update gas limit and current block in transaction queue
 		self.update_gas_limit(chain);
+		{
+			let mut transaction_queue = self.transaction_queue.lock().unwrap();
+			transaction_queue.set_current_block(chain.chain_info().best_block_number);
+			// Gas prices returned by the rule contract may differ in the new state.
+			transaction_queue.clear_contract_gas_prices();
+		}
 
 		// Then import all transactions...
 		{
-			let out_of_chain = retracted
-				.par_iter()
-				.map(|h| fetch_transactions(chain, h));
-			out_of_chain.for_each(|txs| {
-				// populate sender
-				for tx in &txs {
-					let _sender = tx.sender();
+			let mut out_of_chain = Vec::new();
+			for hash in retracted {
+				out_of_chain.extend(fetch_transactions(chain, hash));
+			}
+			recover_senders(&mut out_of_chain);
+			let senders = out_of_chain.iter()
+				.map(|tx| tx.sender().expect("Transaction is in block, so sender has to be defined."))
+				.collect::<HashSet<Address>>();
+			{
+				// Queued transactions of those senders are ordered relative to the retracted state nonce.
+				// Move them back to future first, so they get promoted once the gap is filled again.
+				let mut transaction_queue = self.transaction_queue.lock().unwrap();
+				for sender in senders {
+					transaction_queue.remove_all(sender, chain.nonce(&sender));
 				}
-				let _ = self.import_transactions(txs, |a| AccountDetails {
-					nonce: chain.nonce(a),
-					balance: chain.balance(a),
-				});
+			}
+			let _ = self.add_transactions_to_queue(chain, out_of_chain, TransactionOrigin::RetractedBlock, |a| AccountDetails {
+				nonce: chain.nonce(a),
+				balance: chain.balance(a),
 			});
 		}
 
@@ -562,6 +1419,8 @@ impl MinerService for Miner {
 
 				let to_remove = txs.drain(..)
 						.map(|tx| {
+							// Drop the mined transaction itself, stale ones of its sender are removed by nonce below.
+							transaction_queue.remove(&tx.hash());
 							tx.sender().expect("Transaction is in block, so sender has to be defined.")
 						})
 						.collect::<HashSet<Address>>();
@@ -571,7 +1430,36 @@ impl MinerService for Miner {
 			});
 		}
 
+		// Finally drop transactions that stayed in the queue for too long
+		self.transaction_queue.lock().unwrap().remove_old(self.transaction_queue_max_age());
+
+		// Track bundles included in the new blocks and drop the ones which weren't included in time
+		{
+			let mut bundles = self.transaction_bundles.lock().unwrap();
+			for hash in retracted {
+				bundles.note_retracted(hash);
+			}
+			if bundles.has_pending() {
+				for hash in enacted {
+					let block = chain.block(BlockID::Hash(*hash)).expect("Expected in-chain blocks.");
+					let block = BlockView::new(&block);
+					bundles.note_mined(block.header_view().number(), *hash, &block.transaction_hashes().into_iter().collect());
+				}
+			}
+			for (id, transactions) in bundles.remove_expired(chain.chain_info().best_block_number) {
+				warn!(target: "miner", "Bundle {} was not included in time and has been dropped. Transactions: {:?}", id, transactions);
+			}
+		}
+
 		self.update_sealing(chain);
+
+		self.notify_chain_listeners(&ChainEvent {
+			imported: imported.to_vec(),
+			invalid: invalid.to_vec(),
+			enacted: enacted.to_vec(),
+			retracted: retracted.to_vec(),
+			reorg_depth: retracted.len(),
+		});
 	}
 }
 
@@ -579,10 +1467,37 @@ impl MinerService for Miner {
 mod tests {
 
 	use MinerService;
-	use super::{Miner};
+	use super::{Miner, ResealPolicy, ChainEvent};
 	use util::*;
-	use ethcore::client::{TestBlockChainClient, EachBlockWith};
+	use ethcore::client::{TestBlockChainClient, EachBlockWith, Executed};
+	use ethcore::gas_price_rule::GasPriceRule;
+	use ethcore::gas_limit::{GasLimitTarget, GasLimitStrategy};
+	use std::time::{Instant, Duration};
+	use std::sync::atomic::AtomicBool;
 	use ethcore::block::*;
+	use ethcore::transaction::{Transaction, SignedTransaction, Action};
+	use ethcore::spec::Spec;
+	use ethcore::header::{Header, BlockNumber};
+	use ethcore::error::Error;
+	use {AccountDetails, SubmissionOrigin, SubmissionStats, BundleStatus, TransactionImportResult, TransactionImportError, CallOverrides};
+	use {TransactionOrigin, TransactionState};
+	use ethcore::client::BlockID;
+	use ethcore::filter::Filter;
+	use ethcore::client::{BlockChainClient, Client, ClientConfig};
+	use devtools::RandomTempPath;
+	use std::sync::mpsc::{channel, Sender};
+	use WorkPoster;
+
+	fn transaction(secret: &Secret, nonce: u64) -> SignedTransaction {
+		Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "3331600055".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::one(),
+			nonce: U256::from(nonce),
+		}.sign(secret)
+	}
 
 	// TODO [ToDr] To uncomment when TestBlockChainClient can actually return a ClosedBlock.
 	#[ignore]
@@ -606,7 +1521,7 @@ mod tests {
 
 		let res = miner.map_sealing_work(&client, |b| b.block().fields().header.hash());
 		assert!(res.is_some());
-		assert!(miner.submit_seal(&client, res.unwrap(), vec![]).is_ok());
+		assert!(miner.submit_seal(&client, res.unwrap(), vec![], None).is_ok());
 
 		// two more blocks mined, work requested.
 		client.add_blocks(1, EachBlockWith::Uncle);
@@ -616,6 +1531,1243 @@ mod tests {
 		miner.map_sealing_work(&client, |b| b.block().fields().header.hash());
 
 		// solution to original work submitted.
-		assert!(miner.submit_seal(&client, res.unwrap(), vec![]).is_ok());
+		assert!(miner.submit_seal(&client, res.unwrap(), vec![], None).is_ok());
+	}
+
+	#[test]
+	fn should_list_future_transactions_separately() {
+		// given
+		let miner = Miner::default();
+		let keypair = KeyPair::create().unwrap();
+		let txs = vec![
+			transaction(&keypair.secret(), 0),
+			transaction(&keypair.secret(), 1),
+			transaction(&keypair.secret(), 3),
+			transaction(&keypair.secret(), 4),
+		];
+
+		// when
+		let res = miner.import_transactions(&TestBlockChainClient::default(), txs.clone(), None, |_| AccountDetails { nonce: U256::zero(), balance: !U256::zero() });
+
+		// then
+		assert!(res.iter().all(|r| r.is_ok()));
+		assert_eq!(miner.pending_transactions(), vec![txs[0].clone(), txs[1].clone()]);
+		assert_eq!(miner.future_transactions(), vec![txs[2].clone(), txs[3].clone()]);
+	}
+
+	#[test]
+	fn should_evict_and_reject_transactions_of_banned_address() {
+		// given
+		let miner = Miner::default();
+		let client = TestBlockChainClient::default();
+		let keypair = KeyPair::create().unwrap();
+		let tx = transaction(&keypair.secret(), 1);
+		miner.import_transactions(&client, vec![tx.clone()], None, account_details);
+		assert_eq!(miner.future_transactions(), vec![tx.clone()]);
+
+		// when
+		miner.ban_address(keypair.address());
+
+		// then
+		assert!(miner.future_transactions().is_empty());
+		assert_eq!(miner.banned_addresses(), vec![keypair.address()]);
+		let res = miner.import_transactions(&client, vec![tx.clone()], None, account_details);
+		assert_eq!(res[0], Err(TransactionImportError::SenderBanned));
+		assert!(miner.unban_address(&keypair.address()));
+		assert!(miner.banned_addresses().is_empty());
+		let res = miner.import_transactions(&client, vec![tx], None, account_details);
+		assert!(res[0].is_ok());
+	}
+
+	fn import_block_with_gas_limit(client: &TestBlockChainClient, gas_limit: u64) -> H256 {
+		let info = client.chain_info();
+		let mut header = Header::new();
+		header.number = info.best_block_number + 1;
+		header.parent_hash = info.best_block_hash;
+		header.gas_limit = U256::from(gas_limit);
+		let hash = header.hash();
+		let mut block = RlpStream::new_list(3);
+		block.append(&header);
+		block.append_raw(&rlp::EMPTY_LIST_RLP, 1);
+		block.append_raw(&rlp::EMPTY_LIST_RLP, 1);
+		client.import_block(block.out()).unwrap();
+		hash
+	}
+
+	#[test]
+	fn should_notify_chain_listeners_and_survive_panicking_ones() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let hashes = (0..3).map(|_| {
+			client.add_blocks(1, EachBlockWith::Nothing);
+			client.chain_info().best_block_hash
+		}).collect::<Vec<_>>();
+		let events = Arc::new(Mutex::new(Vec::new()));
+		miner.add_chain_listener(Box::new(|_| panic!("Listener failure")));
+		let recorded = events.clone();
+		let id = miner.add_chain_listener(Box::new(move |e| recorded.lock().unwrap().push(e.clone())));
+
+		// when
+		miner.chain_new_blocks(&client, &hashes[1..], &[H256::from(9)], &hashes[1..], &hashes[..1]);
+
+		// then
+		assert_eq!(*events.lock().unwrap(), vec![ChainEvent {
+			imported: hashes[1..].to_vec(),
+			invalid: vec![H256::from(9)],
+			enacted: hashes[1..].to_vec(),
+			retracted: hashes[..1].to_vec(),
+			reorg_depth: 1,
+		}]);
+
+		// when
+		assert!(miner.remove_chain_listener(id));
+		assert!(!miner.remove_chain_listener(id));
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+
+		// then
+		assert_eq!(events.lock().unwrap().len(), 1);
+		assert_eq!(miner.status().transactions_in_pending_queue, 0);
+	}
+
+	#[test]
+	fn should_update_tx_gas_limit_with_block_gas_limit_unless_pinned() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let keypair = KeyPair::create().unwrap();
+
+		// when
+		let hash = import_block_with_gas_limit(&client, 2_000_000);
+		miner.chain_new_blocks(&client, &[hash], &[], &[hash], &[]);
+
+		// then
+		assert_eq!(miner.tx_gas_limit(), U256::from(2_000_000));
+
+		// when
+		let hash = import_block_with_gas_limit(&client, 1_500_000);
+		miner.chain_new_blocks(&client, &[hash], &[], &[hash], &[]);
+
+		// then
+		assert_eq!(miner.tx_gas_limit(), U256::from(1_500_000));
+		let tx = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(1_600_000),
+			gas_price: U256::one(),
+			nonce: U256::zero(),
+		}.sign(&keypair.secret());
+		let res = miner.import_transactions(&client, vec![tx], None, account_details);
+		assert_eq!(res[0], Err(TransactionImportError::GasLimitExceeded {
+			limit: U256::from(1_500_000),
+			got: U256::from(1_600_000),
+		}));
+
+		// when
+		miner.set_tx_gas_limit(U256::from(100_000));
+		let hash = import_block_with_gas_limit(&client, 3_000_000);
+		miner.chain_new_blocks(&client, &[hash], &[], &[hash], &[]);
+
+		// then
+		assert_eq!(miner.tx_gas_limit(), U256::from(100_000));
+	}
+
+	fn account_details(_address: &Address) -> AccountDetails {
+		AccountDetails { nonce: U256::zero(), balance: !U256::zero() }
+	}
+
+	#[test]
+	fn should_import_raw_transactions_and_reject_invalid_ones() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let keypair = KeyPair::create().unwrap();
+		let valid = transaction(&keypair.secret(), 0);
+		let bad_signature = {
+			let encoded = encode(&transaction(&keypair.secret(), 1)).to_vec();
+			let rlp = UntrustedRlp::new(&encoded);
+			let mut stream = RlpStream::new_list(9);
+			for i in 0..7 {
+				stream.append_raw(rlp.at(i).unwrap().as_raw(), 1);
+			}
+			stream.append(&U256::zero());
+			stream.append(&U256::zero());
+			stream.out()
+		};
+
+		// when
+		let res = miner.import_raw_transactions(&client, vec![encode(&valid).to_vec(), vec![0x01, 0x02], bad_signature], account_details);
+
+		// then
+		assert_eq!(res.len(), 3);
+		assert_eq!(res[0], Ok(TransactionImportResult::Current));
+		match res[1] {
+			Err(TransactionImportError::InvalidRlp(_)) => {},
+			ref other => panic!("Unexpected result: {:?}", other),
+		}
+		assert_eq!(res[2], Err(TransactionImportError::InvalidSignature));
+		assert_eq!(miner.pending_transactions(), vec![valid]);
+	}
+
+	#[test]
+	fn should_import_batch_with_the_same_results_as_one_by_one() {
+		// given
+		let client = TestBlockChainClient::default();
+		let batch_miner = Miner::default();
+		let serial_miner = Miner::default();
+		let txs = (0..128).map(|i| {
+			let tx = Transaction {
+				action: Action::Call(Address::zero()),
+				value: U256::zero(),
+				data: vec![],
+				gas: U256::from(100_000),
+				gas_price: U256::from(20_000_000_000u64 + i),
+				nonce: U256::zero(),
+			};
+			match i % 7 {
+				0 => tx.invalid_sign(),
+				_ => tx.sign(&KeyPair::create().unwrap().secret()),
+			}
+		}).collect::<Vec<_>>();
+
+		// when
+		let batch = batch_miner.import_transactions(&client, txs.clone(), None, account_details);
+		let serial = txs.iter()
+			.cloned()
+			.flat_map(|tx| serial_miner.import_transactions(&client, vec![tx], None, account_details))
+			.collect::<Vec<_>>();
+
+		// then
+		assert_eq!(batch.len(), 128);
+		for (i, res) in batch.iter().enumerate() {
+			match i % 7 {
+				0 => assert_eq!(*res, Err(TransactionImportError::InvalidSignature)),
+				_ => assert_eq!(*res, Ok(TransactionImportResult::Current)),
+			}
+		}
+		assert_eq!(batch, serial);
+		assert_eq!(batch_miner.pending_transactions(), serial_miner.pending_transactions());
+	}
+
+	#[test]
+	fn should_put_service_transactions_before_other_transactions() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		miner.set_minimal_gas_price(U256::from(10));
+		let service = KeyPair::create().unwrap();
+		miner.set_service_addresses(vec![service.address()]);
+		let call = |gas_price: u64| Transaction {
+			action: Action::Call(Address::zero()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: U256::from(gas_price),
+			nonce: U256::zero(),
+		};
+		let service_tx = call(0).sign(&service.secret());
+		let tx = call(100).sign(&KeyPair::create().unwrap().secret());
+
+		// when
+		let res = miner.import_transactions(&client, vec![tx.clone(), service_tx.clone()], None, account_details);
+
+		// then
+		assert!(res.iter().all(|r| r.is_ok()));
+		assert_eq!(miner.pending_transactions(), vec![service_tx, tx]);
+	}
+
+	#[test]
+	fn should_reseal_only_on_own_transactions() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::new(true, Spec::new_test());
+		miner.set_reseal_policy(ResealPolicy { reseal_on_external_tx: false, reseal_on_own_tx: true });
+		let keypair = KeyPair::create().unwrap();
+
+		// when
+		let res = miner.import_transactions(&client, vec![transaction(&keypair.secret(), 0)], None, account_details);
+
+		// then
+		assert!(res[0].is_ok());
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 0);
+
+		// when
+		miner.import_own_transaction(&client, transaction(&keypair.secret(), 1), None, account_details).unwrap();
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 1);
+	}
+
+	#[test]
+	fn should_reseal_only_on_external_transactions() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::new(true, Spec::new_test());
+		miner.set_reseal_policy(ResealPolicy { reseal_on_external_tx: true, reseal_on_own_tx: false });
+		let keypair = KeyPair::create().unwrap();
+
+		// when
+		miner.import_own_transaction(&client, transaction(&keypair.secret(), 0), None, account_details).unwrap();
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 0);
+
+		// when
+		let res = miner.import_transactions(&client, vec![transaction(&keypair.secret(), 1)], None, account_details);
+
+		// then
+		assert!(res[0].is_ok());
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 1);
+	}
+
+	fn gas_price_call_result(price: u64) -> Executed {
+		Executed {
+			gas: U256::zero(),
+			gas_used: U256::zero(),
+			refunded: U256::zero(),
+			cumulative_gas_used: U256::zero(),
+			logs: vec![],
+			contracts_created: vec![],
+			output: H256::from(&U256::from(price)).to_vec(),
+			trace: None,
+			vm_trace: None,
+			exception: false,
+			state_diff: None,
+		}
+	}
+
+	#[test]
+	fn should_resolve_gas_price_with_rule_contract_once_per_block() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		miner.set_gas_price_rule(GasPriceRule::Contract { registry: Address::from(0x10), selector: vec![0; 4] });
+		let keypair = KeyPair::create().unwrap();
+		client.set_execution_result(gas_price_call_result(1));
+
+		// when
+		let res = miner.import_transactions(&client, vec![transaction(&keypair.secret(), 0)], None, account_details);
+
+		// then
+		assert!(res[0].is_ok());
+
+		// when
+		client.set_execution_result(gas_price_call_result(2));
+		let cached = miner.import_transactions(&client, vec![transaction(&keypair.secret(), 1)], None, account_details);
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+		let resolved = miner.import_transactions(&client, vec![transaction(&keypair.secret(), 2)], None, account_details);
+
+		// then
+		assert!(cached[0].is_ok());
+		assert!(resolved[0].is_err());
+	}
+
+	fn miner_with_clock(now: Arc<Mutex<Instant>>) -> Miner {
+		Miner {
+			force_sealing: true,
+			sealing_active: AtomicBool::new(true),
+			clock: Box::new(move || *now.lock().unwrap()),
+			..Miner::default()
+		}
+	}
+
+	#[test]
+	fn should_not_prepare_blocks_more_often_than_reseal_min_period() {
+		// given
+		let client = TestBlockChainClient::default();
+		let now = Arc::new(Mutex::new(Instant::now()));
+		let miner = miner_with_clock(now.clone());
+		miner.set_reseal_min_period(Duration::from_millis(2000));
+		let keypair = KeyPair::create().unwrap();
+
+		// when
+		for nonce in 0..10 {
+			miner.import_transactions(&client, vec![transaction(&keypair.secret(), nonce)], None, account_details);
+			*now.lock().unwrap() += Duration::from_millis(100);
+		}
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 1);
+		assert!(miner.sealing_dirty.load(atomic::Ordering::Relaxed));
+
+		// when
+		*now.lock().unwrap() += Duration::from_millis(1000);
+		miner.import_transactions(&client, vec![transaction(&keypair.secret(), 10)], None, account_details);
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 2);
+		assert!(!miner.sealing_dirty.load(atomic::Ordering::Relaxed));
+	}
+
+	#[test]
+	fn should_prepare_block_on_new_chain_head_within_reseal_min_period() {
+		// given
+		let client = TestBlockChainClient::default();
+		let now = Arc::new(Mutex::new(Instant::now()));
+		let miner = miner_with_clock(now.clone());
+		miner.set_reseal_min_period(Duration::from_millis(2000));
+		let keypair = KeyPair::create().unwrap();
+		miner.import_transactions(&client, vec![transaction(&keypair.secret(), 0)], None, account_details);
+
+		// when
+		client.add_blocks(1, EachBlockWith::Nothing);
+		miner.update_sealing(&client);
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 2);
+	}
+
+	#[test]
+	fn should_prepare_block_on_every_import_without_reseal_min_period() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = miner_with_clock(Arc::new(Mutex::new(Instant::now())));
+		let keypair = KeyPair::create().unwrap();
+
+		// when
+		for nonce in 0..3 {
+			miner.import_transactions(&client, vec![transaction(&keypair.secret(), nonce)], None, account_details);
+		}
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 3);
+	}
+
+	#[test]
+	fn should_stop_preparing_blocks_when_sealing_work_is_not_requested() {
+		// given
+		let client = TestBlockChainClient::default();
+		let now = Arc::new(Mutex::new(Instant::now()));
+		let miner = miner_with_clock(now.clone());
+		miner.set_sealing_work_timeout(Some(Duration::from_secs(10)));
+		miner.map_sealing_work(&client, |_| ());
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 2);
+
+		// when
+		*now.lock().unwrap() += Duration::from_secs(11);
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 2);
+
+		// when
+		miner.map_sealing_work(&client, |_| ());
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 4);
+	}
+
+	#[test]
+	fn should_not_give_out_sealing_work_when_sealing_is_disabled() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = miner_with_clock(Arc::new(Mutex::new(Instant::now())));
+
+		// when
+		miner.set_sealing_enabled(false);
+		miner.map_sealing_work(&client, |_| ());
+		miner.chain_new_blocks(&client, &[], &[], &[], &[]);
+
+		// then
+		assert!(!miner.sealing_enabled());
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 0);
+
+		// when
+		miner.set_sealing_enabled(true);
+		miner.map_sealing_work(&client, |_| ());
+
+		// then
+		assert_eq!(client.sealing_requests.load(atomic::Ordering::Relaxed), 1);
+	}
+
+	#[test]
+	fn should_suggest_gas_price_from_recent_blocks() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		miner.set_minimal_gas_price(U256::from(100));
+
+		// then
+		assert_eq!(miner.sensible_gas_price(&client), U256::from(110));
+
+		// when
+		client.add_blocks(100, EachBlockWith::Transaction);
+
+		// then
+		assert_eq!(miner.sensible_gas_price(&client), U256::from(100));
+		miner.set_minimal_gas_price(U256::zero());
+		assert_eq!(miner.sensible_gas_price(&client), U256::one());
+	}
+
+	#[test]
+	fn should_collect_recent_gas_prices_once_per_best_block() {
+		use gas_price_statistics::GasPriceStatistics;
+
+		// given
+		let client = TestBlockChainClient::default();
+		client.add_blocks(100, EachBlockWith::Transaction);
+		let miner = Miner::default();
+		miner.set_minimal_gas_price(U256::zero());
+		assert_eq!(miner.sensible_gas_price(&client), U256::one());
+		let best_block = client.chain_info().best_block_hash;
+		let prices = GasPriceStatistics::new(vec![U256::from(500)]);
+		*miner.recent_gas_prices.lock().unwrap() = Some((best_block, prices));
+
+		// then
+		assert_eq!(miner.sensible_gas_price(&client), U256::from(500));
+
+		// when
+		client.add_blocks(1, EachBlockWith::Transaction);
+
+		// then
+		assert_eq!(miner.sensible_gas_price(&client), U256::one());
+	}
+
+	#[test]
+	fn should_record_submissions_per_origin() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let keypair = KeyPair::create().unwrap();
+		let wallet = SubmissionOrigin::new("dapp:wallet");
+		let tx = transaction(&keypair.secret(), 0);
+
+		// when
+		miner.import_own_transaction(&client, tx.clone(), Some(wallet.clone()), account_details).unwrap();
+		miner.import_own_transaction(&client, tx.clone(), None, account_details).unwrap_err();
+		miner.import_transactions(&client, vec![transaction(&keypair.secret(), 1)], Some(wallet.clone()), account_details);
+		miner.import_transactions(&client, vec![transaction(&keypair.secret(), 2)], None, account_details);
+
+		// then
+		let audit = miner.transaction_audit(&tx.hash());
+		assert_eq!(audit.len(), 2);
+		assert_eq!(audit[0].origin, wallet);
+		assert_eq!(audit[0].error, None);
+		assert_eq!(audit[1].origin, SubmissionOrigin::anonymous());
+		assert!(audit[1].error.is_some());
+
+		let stats = miner.submission_stats();
+		assert_eq!(stats.len(), 2);
+		assert_eq!(stats[&wallet], SubmissionStats { imported: 2, rejected: 0 });
+		assert_eq!(stats[&SubmissionOrigin::anonymous()], SubmissionStats { imported: 0, rejected: 1 });
+		assert_eq!(miner.status().transactions_in_pending_queue, 3);
+	}
+
+	#[test]
+	fn should_accept_solution_for_older_work() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let old_work = miner.map_sealing_work(client.deref(), |b| b.hash()).unwrap();
+
+		// when
+		import_block(&client, seal_block(&client, vec![1]));
+		miner.update_sealing(client.deref());
+		let new_work = miner.map_sealing_work(client.deref(), |b| b.hash()).unwrap();
+
+		// then
+		assert!(new_work != old_work);
+		assert!(miner.submit_seal(client.deref(), old_work, vec![], None).is_ok());
+		assert!(miner.submit_seal(client.deref(), new_work, vec![], None).is_ok());
+		assert!(miner.submit_seal(client.deref(), old_work, vec![], None).is_err());
+	}
+
+	#[test]
+	fn should_expose_number_and_difficulty_of_sealing_work() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+
+		// when
+		let (number, difficulty, header) = miner.map_sealing_work(client.deref(), |w| {
+			(w.number, w.difficulty.clone(), w.block().header().clone())
+		}).unwrap();
+
+		// then
+		assert_eq!(number, 1);
+		assert_eq!(number, header.number());
+		assert_eq!(&difficulty, header.difficulty());
+	}
+
+	#[test]
+	fn should_bump_job_id_only_when_work_changes() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		assert_eq!(miner.current_job_id(), None);
+		let (old_job, old_work) = miner.map_sealing_work(client.deref(), |w| (w.job_id, w.pow_hash.clone())).unwrap();
+
+		// when
+		let same_job = miner.map_sealing_work(client.deref(), |w| w.job_id).unwrap();
+		import_block(&client, seal_block(&client, vec![1]));
+		miner.update_sealing(client.deref());
+		let (new_job, new_work) = miner.map_sealing_work(client.deref(), |w| (w.job_id, w.hash())).unwrap();
+
+		// then
+		assert_eq!(same_job, old_job);
+		assert!(new_work != old_work);
+		assert!(new_job > old_job);
+		assert_eq!(miner.current_job_id(), Some(new_job));
+	}
+
+	#[test]
+	fn should_classify_submissions_for_unknown_jobs_as_stale() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let (job_id, pow_hash) = miner.map_sealing_work(client.deref(), |w| (w.job_id, w.pow_hash.clone())).unwrap();
+
+		// when
+		let stale = miner.submit_seal(client.deref(), pow_hash.clone(), vec![], Some(job_id + 1));
+		let invalid = miner.submit_seal(client.deref(), H256::from(1), vec![], Some(job_id));
+		let valid = miner.submit_seal(client.deref(), pow_hash, vec![], Some(job_id));
+
+		// then
+		match stale {
+			Err(Error::StaleJob) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+		match invalid {
+			Err(Error::PowHashInvalid) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+		assert!(valid.is_ok());
+	}
+
+	#[test]
+	fn should_clamp_gas_range_target_to_protocol_bounds() {
+		// given
+		let miner = Miner::default();
+		let min_gas_limit = U256::from(0x1388);
+
+		// when
+		miner.set_gas_range_target((U256::from(1000), U256::from(500)));
+
+		// then
+		assert_eq!(miner.gas_floor_target(), min_gas_limit);
+		assert_eq!(miner.gas_ceil_target(), min_gas_limit);
+
+		// when
+		miner.set_gas_range_target((U256::from(2_000_000), U256::from(4_000_000)));
+		miner.set_gas_floor_target(U256::from(5_000_000));
+
+		// then
+		assert_eq!(miner.gas_floor_target(), U256::from(5_000_000));
+		assert_eq!(miner.gas_ceil_target(), U256::from(5_000_000));
+		assert_eq!(miner.gas_limit_target(), GasLimitTarget::fixed(U256::from(5_000_000)));
+	}
+
+	#[test]
+	fn should_use_configured_gas_limit_strategy() {
+		// given
+		let miner = Miner::default();
+		assert_eq!(miner.gas_limit_strategy(), GasLimitStrategy::Fixed);
+
+		// when
+		miner.set_gas_range_target((U256::from(2_000_000), U256::from(4_000_000)));
+		miner.set_gas_limit_strategy(GasLimitStrategy::TargetRange);
+
+		// then
+		assert_eq!(miner.gas_limit_target(), GasLimitTarget::range(U256::from(2_000_000), U256::from(4_000_000)));
+	}
+
+	#[test]
+	fn should_rebuild_work_after_author_change() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let (old_work, old_author) = miner.map_sealing_work(client.deref(), |b| (b.hash(), *b.block().header().author())).unwrap();
+
+		// when
+		miner.set_author(Address::from(5));
+		miner.set_extra_data(vec![1, 2, 3]);
+		let (new_work, new_author, extra_data) = miner.map_sealing_work(client.deref(), |b| {
+			(b.hash(), *b.block().header().author(), b.block().header().extra_data().clone())
+		}).unwrap();
+
+		// then
+		assert!(new_work != old_work);
+		assert!(new_author != old_author);
+		assert_eq!(new_author, Address::from(5));
+		assert_eq!(extra_data, vec![1, 2, 3]);
+		assert!(miner.submit_seal(client.deref(), new_work, vec![], None).is_ok());
+	}
+
+	#[test]
+	fn should_return_hash_of_sealed_block() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let pow_hash = miner.map_sealing_work(client.deref(), |b| b.hash()).unwrap();
+
+		// when
+		let hash = miner.submit_seal(client.deref(), pow_hash, vec![], None).unwrap();
+		client.flush_queue();
+		client.import_verified_blocks(&IoChannel::disconnected());
+
+		// then
+		assert_eq!(client.chain_info().best_block_hash, hash);
+		assert!(hash != pow_hash);
+		match miner.submit_seal(client.deref(), H256::from(1), vec![], None) {
+			Err(Error::PowHashInvalid) => {},
+			other => panic!("Unexpected result: {:?}", other),
+		}
+	}
+
+	struct RecordingPoster(Mutex<Sender<String>>);
+
+	impl WorkPoster for RecordingPoster {
+		fn post(&self, _url: &str, body: &str) -> Result<(), String> {
+			self.0.lock().unwrap().send(body.to_owned()).unwrap();
+			Ok(())
+		}
+	}
+
+	fn seal_block(client: &Client, extra_data: Bytes) -> Bytes {
+		let block = client.prepare_sealing(Address::zero(), GasLimitTarget::fixed(U256::from(31415926)), extra_data, true, vec![]).0.unwrap();
+		client.try_seal(block.lock(), vec![]).ok().expect("Null engine accepts empty seal.").rlp_bytes()
+	}
+
+	fn import_block(client: &Client, block: Bytes) {
+		assert!(client.import_block(block).is_ok());
+		client.flush_queue();
+		client.import_verified_blocks(&IoChannel::disconnected());
+	}
+
+	#[test]
+	fn should_notify_once_per_new_work_package() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let fork_dir = RandomTempPath::new();
+		let fork = Client::new(ClientConfig::default(), Spec::new_test(), fork_dir.as_path(), IoChannel::disconnected()).unwrap();
+		let (sender, receiver) = channel();
+		let miner = Miner::new(true, Spec::new_test());
+		miner.set_work_notify_with(vec!["http://localhost:3000/".to_owned()], Arc::new(RecordingPoster(Mutex::new(sender))));
+
+		// when
+		let mut work = vec![miner.map_sealing_work(client.deref(), |b| b.hash()).unwrap()];
+		import_block(&client, seal_block(&client, vec![1]));
+		miner.update_sealing(client.deref());
+		miner.update_sealing(client.deref());
+		work.push(miner.map_sealing_work(client.deref(), |b| b.hash()).unwrap());
+		// longer fork replaces the block
+		for _ in 0..2 {
+			let block = seal_block(&fork, vec![2]);
+			import_block(&fork, block.clone());
+			import_block(&client, block);
+		}
+		miner.update_sealing(client.deref());
+		work.push(miner.map_sealing_work(client.deref(), |b| b.hash()).unwrap());
+
+		// then
+		assert_eq!(client.chain_info().best_block_hash, fork.chain_info().best_block_hash);
+		for pow_hash in &work {
+			let body = receiver.recv().unwrap();
+			assert!(body.starts_with(&format!(r#"{{"pow_hash":"0x{}""#, pow_hash.hex())), "Unexpected notification: {}", body);
+		}
+	}
+
+	fn call(secret: &Secret, nonce: U256, gas: U256) -> SignedTransaction {
+		Transaction {
+			action: Action::Call(Address::zero()),
+			value: U256::zero(),
+			data: vec![],
+			gas: gas,
+			gas_price: U256::zero(),
+			nonce: nonce,
+		}.sign(secret)
+	}
+
+	fn mine_pending_block(client: &Client, miner: &Miner) -> H256 {
+		let pow_hash = miner.map_sealing_work(client, |b| b.hash()).unwrap();
+		miner.submit_seal(client, pow_hash, vec![], None).unwrap();
+		client.flush_queue();
+		client.import_verified_blocks(&IoChannel::disconnected());
+		let best = client.chain_info().best_block_hash;
+		miner.chain_new_blocks(client, &[best], &[], &[best], &[]);
+		best
+	}
+
+	#[test]
+	fn should_not_exceed_block_gas_limit_with_imported_transactions() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let transactions = (0..3)
+			.map(|_| KeyPair::create().unwrap())
+			.map(|keypair| call(keypair.secret(), client.nonce(&keypair.address()), U256::from(1_200_000)))
+			.collect::<Vec<_>>();
+
+		// when
+		let res = miner.import_transactions(client.deref(), transactions.clone(), None, |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		});
+
+		// then
+		assert!(res.iter().all(|r| r.is_ok()));
+		let (included, gas_used, gas_limit) = miner.map_sealing_work(client.deref(), |b| {
+			let header = b.block().header();
+			(b.transactions().len(), header.gas_used().clone(), header.gas_limit().clone())
+		}).unwrap();
+		assert_eq!(included, 2);
+		assert!(gas_used <= gas_limit);
+		assert_eq!(miner.pending_transactions().len(), 3);
+	}
+
+	#[test]
+	fn should_include_bundle_atomically_and_in_order() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let (deployer, caller) = (KeyPair::create().unwrap(), KeyPair::create().unwrap());
+		let (deployer_nonce, caller_nonce) = (client.nonce(&deployer.address()), client.nonce(&caller.address()));
+		let bundle = vec![
+			call(deployer.secret(), deployer_nonce, U256::from(21_000)),
+			call(caller.secret(), caller_nonce, U256::from(21_000)),
+			call(deployer.secret(), deployer_nonce + U256::one(), U256::from(21_000)),
+		];
+		let hashes = bundle.iter().map(|t| t.hash()).collect::<Vec<_>>();
+
+		// when
+		let id = miner.import_transaction_bundle(client.deref(), bundle, |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		}).unwrap();
+
+		// then
+		let pending = miner.map_sealing_work(client.deref(), |b| b.transactions().iter().map(|t| t.hash()).collect::<Vec<_>>()).unwrap();
+		assert_eq!(pending, hashes);
+		assert_eq!(miner.bundle_status(id), Some(BundleStatus::Pending));
+
+		let hash = mine_pending_block(&client, &miner);
+		assert_eq!(miner.bundle_status(id), Some(BundleStatus::Mined { number: 1, hash: hash }));
+	}
+
+	#[test]
+	fn should_defer_bundle_which_does_not_fit_into_pending_block() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let (queued, bundled) = (KeyPair::create().unwrap(), KeyPair::create().unwrap());
+		let gas_limit = miner.map_sealing_work(client.deref(), |b| b.block().header().gas_limit().clone()).unwrap();
+		let fetch_account = |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		};
+		let queued_tx = call(queued.secret(), client.nonce(&queued.address()), U256::from(21_000));
+		let nonce = client.nonce(&bundled.address());
+		let bundle = vec![
+			call(bundled.secret(), nonce, gas_limit / U256::from(2)),
+			call(bundled.secret(), nonce + U256::one(), gas_limit - gas_limit / U256::from(2)),
+		];
+		let hashes = bundle.iter().map(|t| t.hash()).collect::<Vec<_>>();
+
+		// when
+		assert!(miner.import_own_transaction(client.deref(), queued_tx, None, &fetch_account).is_ok());
+		let id = miner.import_transaction_bundle(client.deref(), bundle, &fetch_account).unwrap();
+
+		// then
+		let pending = miner.map_sealing_work(client.deref(), |b| b.transactions().len()).unwrap();
+		assert_eq!(pending, 1);
+		mine_pending_block(&client, &miner);
+		assert_eq!(miner.bundle_status(id), Some(BundleStatus::Pending));
+
+		let pending = miner.map_sealing_work(client.deref(), |b| b.transactions().iter().map(|t| t.hash()).collect::<Vec<_>>()).unwrap();
+		assert_eq!(pending, hashes);
+	}
+
+	#[test]
+	fn should_skip_later_transactions_of_sender_whose_transaction_does_not_fit() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let sender = KeyPair::create().unwrap();
+		let gas_limit = miner.map_sealing_work(client.deref(), |b| b.block().header().gas_limit().clone()).unwrap();
+		let fetch_account = |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		};
+		let nonce = client.nonce(&sender.address());
+		let transactions = vec![
+			call(sender.secret(), nonce, U256::from(21_000)),
+			call(sender.secret(), nonce + U256::one(), gas_limit),
+			call(sender.secret(), nonce + U256::from(2), U256::from(21_000)),
+		];
+		let hashes = transactions.iter().map(|t| t.hash()).collect::<Vec<_>>();
+
+		// when
+		for tx in transactions {
+			assert!(miner.import_own_transaction(client.deref(), tx, None, &fetch_account).is_ok());
+		}
+
+		// then
+		let pending = miner.map_sealing_work(client.deref(), |b| b.transactions().iter().map(|t| t.hash()).collect::<Vec<_>>()).unwrap();
+		assert_eq!(pending, vec![hashes[0].clone()]);
+		// the last transaction was never executed with a nonce gap, so it wasn't dropped as invalid
+		let queued = miner.transaction_queue.lock().unwrap().top_transactions();
+		assert_eq!(queued.iter().map(|t| t.hash()).collect::<Vec<_>>(), hashes);
+	}
+
+	fn log_filter(address: Option<Address>, topic: Option<H256>) -> Filter {
+		Filter {
+			from_block: BlockID::Latest,
+			to_block: BlockID::Latest,
+			address: address.map(|a| vec![a]),
+			topics: [topic.map(|t| vec![t]), None, None, None],
+		}
+	}
+
+	#[test]
+	fn should_return_pending_logs_matching_filter() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let keypair = KeyPair::create().unwrap();
+		let nonce = client.nonce(&keypair.address());
+		let contract = {
+			let mut stream = RlpStream::new_list(2);
+			stream.append(&keypair.address());
+			stream.append(&nonce);
+			Address::from(stream.out().sha3())
+		};
+		let fetch_account = |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		};
+		// deploys code which emits a log with topics [1, 2] when called
+		let deploy = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "600a600c600039600a6000f36002600160006000a200".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: nonce,
+		}.sign(keypair.secret());
+		let call_tx = Transaction {
+			action: Action::Call(contract),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: nonce + U256::one(),
+		}.sign(keypair.secret());
+		let call_hash = call_tx.hash();
+
+		// when
+		assert!(miner.import_own_transaction(client.deref(), deploy, None, &fetch_account).is_ok());
+		assert!(miner.import_own_transaction(client.deref(), call_tx, None, &fetch_account).is_ok());
+
+		// then
+		let logs = miner.pending_logs(&log_filter(Some(contract), Some(H256::from(1))));
+		assert_eq!(logs.len(), 1);
+		assert_eq!(logs[0].address, contract);
+		assert_eq!(logs[0].topics, vec![H256::from(1), H256::from(2)]);
+		assert_eq!(logs[0].transaction_hash, call_hash);
+		assert_eq!(logs[0].transaction_index, 1);
+		assert_eq!(logs[0].log_index, 0);
+		assert_eq!(miner.pending_logs(&log_filter(None, None)).len(), 1);
+		assert!(miner.pending_logs(&log_filter(Some(Address::zero()), None)).is_empty());
+		assert!(miner.pending_logs(&log_filter(Some(contract), Some(H256::from(2)))).is_empty());
+	}
+
+	#[test]
+	fn should_return_no_pending_logs_without_pending_block() {
+		// given
+		let miner = Miner::default();
+
+		// then
+		assert!(miner.pending_logs(&log_filter(None, None)).is_empty());
+	}
+
+	#[test]
+	fn should_execute_call_with_overridden_environment() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let keypair = KeyPair::create().unwrap();
+		let nonce = client.nonce(&keypair.address());
+		let contract = {
+			let mut stream = RlpStream::new_list(2);
+			stream.append(&keypair.address());
+			stream.append(&nonce);
+			Address::from(stream.out().sha3())
+		};
+		let fetch_account = |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		};
+		// deploys code which stores (and returns) `1000 < TIMESTAMP` when called
+		let deploy = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "601580600b6000396000f3426103e81060005560005460005260206000f3".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: nonce,
+		}.sign(keypair.secret());
+		let call_tx = Transaction {
+			action: Action::Call(contract),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: nonce + U256::one(),
+		}.sign(keypair.secret());
+		assert!(miner.import_own_transaction(client.deref(), deploy, None, &fetch_account).is_ok());
+		let timestamp = miner.map_sealing_work(client.deref(), |b| b.block().header().timestamp()).unwrap();
+		let overrides = |timestamp| CallOverrides { timestamp: Some(timestamp), ..CallOverrides::default() };
+
+		// when
+		let before = miner.call(client.deref(), &call_tx, &overrides(999), None).unwrap();
+		let after = miner.call(client.deref(), &call_tx, &overrides(1001), None).unwrap();
+
+		// then
+		assert_eq!(H256::from_slice(&before.output), H256::zero());
+		assert_eq!(H256::from_slice(&after.output), H256::from(1));
+		assert_eq!(miner.storage_at(client.deref(), &contract, &H256::zero()), H256::zero());
+		assert_eq!(miner.map_sealing_work(client.deref(), |b| b.block().header().timestamp()), Some(timestamp));
+	}
+
+	#[test]
+	fn should_apply_call_overrides_without_pending_block() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::default();
+		// init code returning `1000 < TIMESTAMP`
+		let call_tx = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "426103e81060005260206000f3".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero(),
+		}.sign(KeyPair::create().unwrap().secret());
+		let overrides = |timestamp| CallOverrides { timestamp: Some(timestamp), ..CallOverrides::default() };
+
+		// when
+		let before = miner.call(client.deref(), &call_tx, &overrides(999), None).unwrap();
+		let after = miner.call(client.deref(), &call_tx, &overrides(1001), None).unwrap();
+
+		// then
+		assert_eq!(H256::from_slice(&before.output), H256::zero());
+		assert_eq!(H256::from_slice(&after.output), H256::from(1));
+	}
+
+	#[test]
+	fn should_reject_state_modification_only_in_static_call() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::default();
+		// init code storing 1 at 0
+		let call_tx = Transaction {
+			action: Action::Create,
+			value: U256::zero(),
+			data: "6001600055".from_hex().unwrap(),
+			gas: U256::from(100_000),
+			gas_price: U256::zero(),
+			nonce: U256::zero(),
+		}.sign(KeyPair::create().unwrap().secret());
+		let static_call = CallOverrides { static_call: true, ..CallOverrides::default() };
+
+		// when
+		let normal = miner.call(client.deref(), &call_tx, &CallOverrides::default(), None).unwrap();
+		let rejected = miner.call(client.deref(), &call_tx, &static_call, None).unwrap();
+
+		// then
+		assert!(!normal.exception);
+		assert!(rejected.exception);
+	}
+
+	#[test]
+	fn should_read_latest_state_without_pending_block() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let address = Address::from(1);
+		client.set_balance(address, U256::from(10));
+		client.set_nonce(address, U256::from(2));
+		client.set_code(address, vec![0x60, 0x00]);
+		client.set_storage(address, H256::from(3), H256::from(4));
+
+		// then
+		assert_eq!(miner.balance(&client, &address), U256::from(10));
+		assert_eq!(miner.nonce(&client, &address), U256::from(2));
+		assert_eq!(miner.code(&client, &address), Some(vec![0x60, 0x00]));
+		assert_eq!(miner.storage_at(&client, &address, &H256::from(3)), H256::from(4));
+	}
+
+	#[test]
+	fn should_read_latest_state_when_pending_block_is_stale() {
+		// given
+		let dir = RandomTempPath::new();
+		let client = Client::new(ClientConfig::default(), Spec::new_test(), dir.as_path(), IoChannel::disconnected()).unwrap();
+		let miner = Miner::new(true, Spec::new_test());
+		let keypair = KeyPair::create().unwrap();
+		let fetch_account = |a: &Address| AccountDetails {
+			nonce: client.nonce(a),
+			balance: client.balance(a),
+		};
+		let tx = call(keypair.secret(), client.nonce(&keypair.address()), U256::from(21_000));
+		assert!(miner.import_own_transaction(client.deref(), tx, None, &fetch_account).is_ok());
+		assert_eq!(miner.nonce(client.deref(), &keypair.address()), U256::one());
+
+		// when
+		import_block(&client, seal_block(&client, vec![]));
+
+		// then
+		assert_eq!(miner.nonce(client.deref(), &keypair.address()), U256::zero());
+	}
+
+	fn block_with_transactions(parent: H256, number: BlockNumber, transactions: &[SignedTransaction]) -> Bytes {
+		let mut header = Header::new();
+		header.number = number;
+		header.parent_hash = parent;
+		header.gas_limit = U256::from(1_000_000);
+		let mut block = RlpStream::new_list(3);
+		block.append(&header);
+		block.begin_list(transactions.len());
+		for tx in transactions {
+			block.append(tx);
+		}
+		block.append_raw(&rlp::EMPTY_LIST_RLP, 1);
+		block.out()
+	}
+
+	#[test]
+	fn should_reinject_transactions_of_retracted_blocks() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let keypair = KeyPair::create().unwrap();
+		let sender = keypair.address();
+		let tx = |nonce: u64, gas_price: u64| Transaction {
+			action: Action::Call(Address::zero()),
+			value: U256::zero(),
+			data: vec![],
+			gas: U256::from(21_000),
+			gas_price: U256::from(gas_price),
+			nonce: U256::from(nonce),
+		}.sign(keypair.secret());
+		let (tx0, tx1, tx2) = (tx(0, 1), tx(1, 1), tx(2, 20));
+		client.set_balance(sender, U256::from(1_000_000_000));
+		miner.set_minimal_gas_price(U256::from(10));
+
+		let genesis = client.chain_info().best_block_hash;
+		let a1 = client.import_block(block_with_transactions(genesis, 1, &[tx0.clone()])).unwrap();
+		let a2 = client.import_block(block_with_transactions(a1, 2, &[tx1.clone()])).unwrap();
+		client.set_nonce(sender, U256::from(2));
+		miner.chain_new_blocks(&client, &[a1, a2], &[], &[a1, a2], &[]);
+		let fetch_account = |a: &Address| AccountDetails { nonce: client.nonce(a), balance: client.balance(a) };
+		assert!(miner.import_transactions(&client, vec![tx2.clone()], None, &fetch_account)[0].is_ok());
+		assert_eq!(miner.pending_transactions_hashes(), vec![tx2.hash()]);
+
+		// when
+		let b1 = client.import_block(block_with_transactions(genesis, 1, &[])).unwrap();
+		let b2 = client.import_block(block_with_transactions(b1, 2, &[])).unwrap();
+		let b3 = client.import_block(block_with_transactions(b2, 3, &[])).unwrap();
+		client.set_nonce(sender, U256::zero());
+		miner.chain_new_blocks(&client, &[b1, b2, b3], &[], &[b1, b2, b3], &[a1, a2]);
+
+		// then
+		assert_eq!(client.chain_info().best_block_hash, b3);
+		assert_eq!(miner.pending_transactions_hashes(), vec![tx0.hash(), tx1.hash(), tx2.hash()]);
+		assert!(miner.future_transactions().is_empty());
+		let details = miner.transaction_details(&tx0.hash()).unwrap();
+		assert_eq!((details.origin, details.state), (TransactionOrigin::RetractedBlock, TransactionState::Pending));
+		assert_eq!(miner.transaction_details(&tx2.hash()).unwrap().origin, TransactionOrigin::External);
+
+		// and when the retracted transactions get mined again
+		let b4 = client.import_block(block_with_transactions(b3, 4, &[tx0.clone(), tx1.clone()])).unwrap();
+		client.set_nonce(sender, U256::from(2));
+		miner.chain_new_blocks(&client, &[b4], &[], &[b4], &[]);
+
+		// then
+		assert_eq!(miner.pending_transactions_hashes(), vec![tx2.hash()]);
+	}
+
+	#[test]
+	fn should_reserve_consecutive_nonces_concurrently() {
+		use std::thread;
+
+		// given
+		let client = Arc::new(TestBlockChainClient::default());
+		let miner = Arc::new(Miner::default());
+		let address = Address::from(10);
+		client.set_nonce(address.clone(), U256::from(5));
+
+		// when
+		let handles = (0..10)
+			.map(|_| {
+				let (client, miner, address) = (client.clone(), miner.clone(), address.clone());
+				thread::spawn(move || miner.reserve_nonce(&*client, &address))
+			})
+			.collect::<Vec<_>>();
+		let mut nonces = handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>();
+		nonces.sort();
+
+		// then
+		assert_eq!(nonces, (5..15u64).map(U256::from).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn should_give_out_expired_nonce_reservations_again() {
+		// given
+		let client = TestBlockChainClient::default();
+		let now = Arc::new(Mutex::new(Instant::now()));
+		let miner = miner_with_clock(now.clone());
+		let keypair = KeyPair::create().unwrap();
+		let address = keypair.address();
+		assert_eq!(miner.reserve_nonce(&client, &address), U256::from(0));
+		assert_eq!(miner.reserve_nonce(&client, &address), U256::from(1));
+
+		// when
+		miner.import_transactions(&client, vec![transaction(&keypair.secret(), 0)], None, account_details);
+		let after_import = miner.reserve_nonce(&client, &address);
+		*now.lock().unwrap() += Duration::from_secs(61);
+		let after_expiry = miner.reserve_nonce(&client, &address);
+
+		// then
+		assert_eq!(after_import, U256::from(2));
+		assert_eq!(after_expiry, U256::from(1));
+	}
+
+	#[test]
+	fn should_give_out_released_nonce_again() {
+		// given
+		let client = TestBlockChainClient::default();
+		let miner = Miner::default();
+		let address = Address::from(10);
+		assert_eq!(miner.reserve_nonce(&client, &address), U256::from(0));
+		assert_eq!(miner.reserve_nonce(&client, &address), U256::from(1));
+		assert_eq!(miner.reserve_nonce(&client, &address), U256::from(2));
+
+		// when
+		miner.release_nonce(&address, &U256::from(1));
+		let released = miner.reserve_nonce(&client, &address);
+		let next = miner.reserve_nonce(&client, &address);
+
+		// then
+		assert_eq!(released, U256::from(1));
+		assert_eq!(next, U256::from(3));
 	}
 }
@@ -0,0 +1,249 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Audit trail of transactions submitted to the miner.
+//!
+//! Every submission is attributed to its origin (dapp, RPC user, IPC peer, ...),
+//! so operators of shared nodes can tell who sent a particular transaction.
+//! Origin is used only for attribution, it never affects the transaction queue.
+
+use std::fmt;
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+use util::H256;
+use transaction_queue::{TransactionImportResult, TransactionImportError};
+
+/// Default number of submissions kept in the audit trail.
+pub const DEFAULT_AUDIT_TRAIL_SIZE: usize = 4096;
+/// Default number of origins with aggregated counters.
+pub const DEFAULT_TRACKED_ORIGINS: usize = 256;
+
+const ANONYMOUS: &'static str = "anonymous";
+
+/// Tag identifying who submitted a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SubmissionOrigin(String);
+
+impl SubmissionOrigin {
+	/// Creates origin with given tag. Empty tag is treated as anonymous origin.
+	pub fn new<T: Into<String>>(tag: T) -> Self {
+		let tag = tag.into();
+		match tag.is_empty() {
+			true => SubmissionOrigin::anonymous(),
+			false => SubmissionOrigin(tag),
+		}
+	}
+
+	/// Origin of submissions which can't be attributed.
+	pub fn anonymous() -> Self {
+		SubmissionOrigin(ANONYMOUS.to_owned())
+	}
+
+	/// Returns the tag.
+	pub fn tag(&self) -> &str {
+		&self.0
+	}
+}
+
+impl Default for SubmissionOrigin {
+	fn default() -> Self {
+		SubmissionOrigin::anonymous()
+	}
+}
+
+impl fmt::Display for SubmissionOrigin {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.0)
+	}
+}
+
+/// Single submission recorded in the audit trail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+	/// Hash of submitted transaction.
+	pub hash: H256,
+	/// Who submitted the transaction.
+	pub origin: SubmissionOrigin,
+	/// Unix timestamp of the submission.
+	pub timestamp: u64,
+	/// Reason of rejection or `None` if transaction was imported to the queue.
+	pub error: Option<String>,
+}
+
+/// Aggregated submissions of single origin.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SubmissionStats {
+	/// Number of transactions imported to the queue.
+	pub imported: usize,
+	/// Number of rejected transactions.
+	pub rejected: usize,
+}
+
+/// Bounded log of submissions together with per-origin counters.
+///
+/// Only last `limit` entries are kept in the log. Origin tags may come from request headers,
+/// so counters are kept for at most `max_origins` origins; the least recently active one is evicted first.
+pub struct SubmissionAudit {
+	entries: VecDeque<AuditEntry>,
+	/// Counters and sequence number of the last submission of every tracked origin.
+	stats: BTreeMap<SubmissionOrigin, (SubmissionStats, u64)>,
+	limit: usize,
+	max_origins: usize,
+	submissions: u64,
+}
+
+impl Default for SubmissionAudit {
+	fn default() -> Self {
+		SubmissionAudit::with_limit(DEFAULT_AUDIT_TRAIL_SIZE)
+	}
+}
+
+impl SubmissionAudit {
+	/// Creates audit trail keeping at most `limit` entries.
+	pub fn with_limit(limit: usize) -> Self {
+		SubmissionAudit {
+			entries: VecDeque::new(),
+			stats: BTreeMap::new(),
+			limit: limit,
+			max_origins: DEFAULT_TRACKED_ORIGINS,
+			submissions: 0,
+		}
+	}
+
+	/// Keep counters of at most `max_origins` origins.
+	pub fn with_max_origins(mut self, max_origins: usize) -> Self {
+		self.max_origins = max_origins;
+		self
+	}
+
+	/// Records result of importing transaction `hash` submitted by `origin`.
+	pub fn record(&mut self, hash: H256, origin: SubmissionOrigin, result: &Result<TransactionImportResult, TransactionImportError>) {
+		self.submissions += 1;
+		if !self.stats.contains_key(&origin) && self.stats.len() >= self.max_origins {
+			let oldest = self.stats.iter().min_by_key(|&(_, &(_, last))| last).map(|(origin, _)| origin.clone());
+			if let Some(oldest) = oldest {
+				self.stats.remove(&oldest);
+			}
+		}
+		if self.max_origins > 0 {
+			let &mut (ref mut stats, ref mut last) = self.stats.entry(origin.clone()).or_insert_with(Default::default);
+			match *result {
+				Ok(_) => stats.imported += 1,
+				Err(_) => stats.rejected += 1,
+			}
+			*last = self.submissions;
+		}
+
+		if self.limit == 0 {
+			return;
+		}
+		if self.entries.len() == self.limit {
+			self.entries.pop_front();
+		}
+		self.entries.push_back(AuditEntry {
+			hash: hash,
+			origin: origin,
+			timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+			error: result.as_ref().err().map(|e| format!("{}", e)),
+		});
+	}
+
+	/// Returns all recorded submissions of transaction `hash` (oldest first).
+	pub fn entries(&self, hash: &H256) -> Vec<AuditEntry> {
+		self.entries.iter().filter(|e| &e.hash == hash).cloned().collect()
+	}
+
+	/// Returns aggregated submissions per origin.
+	pub fn stats(&self) -> BTreeMap<SubmissionOrigin, SubmissionStats> {
+		self.stats.iter().map(|(origin, &(stats, _))| (origin.clone(), stats)).collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::H256;
+	use transaction_queue::{TransactionImportResult, TransactionImportError};
+	use super::*;
+
+	#[test]
+	fn should_default_to_anonymous_origin() {
+		assert_eq!(SubmissionOrigin::default().tag(), "anonymous");
+		assert_eq!(SubmissionOrigin::new(""), SubmissionOrigin::anonymous());
+		assert_eq!(SubmissionOrigin::new("dapp:wallet").tag(), "dapp:wallet");
+	}
+
+	#[test]
+	fn should_record_entries_and_count_per_origin() {
+		// given
+		let mut audit = SubmissionAudit::default();
+		let wallet = SubmissionOrigin::new("dapp:wallet");
+		let ipc = SubmissionOrigin::new("ipc:uid=1000");
+
+		// when
+		audit.record(H256::from(1), wallet.clone(), &Ok(TransactionImportResult::Current));
+		audit.record(H256::from(1), ipc.clone(), &Err(TransactionImportError::AlreadyImported));
+		audit.record(H256::from(2), wallet.clone(), &Ok(TransactionImportResult::Future));
+
+		// then
+		let entries = audit.entries(&H256::from(1));
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[0].origin, wallet);
+		assert_eq!(entries[0].error, None);
+		assert_eq!(entries[1].origin, ipc);
+		assert!(entries[1].error.is_some());
+
+		let stats = audit.stats();
+		assert_eq!(stats[&wallet], SubmissionStats { imported: 2, rejected: 0 });
+		assert_eq!(stats[&ipc], SubmissionStats { imported: 0, rejected: 1 });
+	}
+
+	#[test]
+	fn should_keep_only_last_entries_but_all_counts() {
+		// given
+		let mut audit = SubmissionAudit::with_limit(2);
+
+		// when
+		for i in 1..4 {
+			audit.record(H256::from(i), SubmissionOrigin::anonymous(), &Ok(TransactionImportResult::Current));
+		}
+
+		// then
+		assert!(audit.entries(&H256::from(1)).is_empty());
+		assert_eq!(audit.entries(&H256::from(3)).len(), 1);
+		assert_eq!(audit.stats()[&SubmissionOrigin::anonymous()].imported, 3);
+	}
+
+	#[test]
+	fn should_evict_least_recently_active_origin() {
+		// given
+		let mut audit = SubmissionAudit::default().with_max_origins(2);
+		let origin = |i: u64| SubmissionOrigin::new(format!("dapp:{}", i));
+
+		// when
+		audit.record(H256::from(1), origin(1), &Ok(TransactionImportResult::Current));
+		audit.record(H256::from(2), origin(2), &Ok(TransactionImportResult::Current));
+		audit.record(H256::from(3), origin(1), &Ok(TransactionImportResult::Current));
+		audit.record(H256::from(4), origin(3), &Ok(TransactionImportResult::Current));
+
+		// then
+		let stats = audit.stats();
+		assert_eq!(stats.len(), 2);
+		assert_eq!(stats[&origin(1)].imported, 2);
+		assert_eq!(stats[&origin(3)].imported, 1);
+		assert!(!stats.contains_key(&origin(2)));
+		assert_eq!(audit.entries(&H256::from(2))[0].origin, origin(2));
+	}
+}
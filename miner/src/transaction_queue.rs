@@ -85,12 +85,19 @@
 use std::default::Default;
 use std::cmp::{Ordering};
 use std::cmp;
-use std::collections::{HashMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::collections::{HashMap, HashSet, BTreeSet, BTreeMap};
 use util::numbers::{Uint, U256};
 use util::hash::{Address, H256};
+use util::rlp::{UntrustedRlp, RlpStream, Stream, View, DecoderError, encode};
 use util::table::*;
 use ethcore::transaction::*;
-use ethcore::error::{Error, TransactionError};
+use ethcore::error::Error;
+use ethcore::header::BlockNumber;
+use ethcore::gas_price_rule::GasPriceRule;
 
 /// Transaction origin
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -99,6 +106,8 @@ pub enum TransactionOrigin {
 	Local,
 	/// External transaction received from network
 	External,
+	/// Transaction from a block retracted during chain reorganization
+	RetractedBlock,
 }
 
 impl PartialOrd for TransactionOrigin {
@@ -109,14 +118,18 @@ impl PartialOrd for TransactionOrigin {
 
 impl Ord for TransactionOrigin {
 	fn cmp(&self, other: &TransactionOrigin) -> Ordering {
-		if *other == *self {
-			return Ordering::Equal;
-		}
+		self.priority().cmp(&other.priority())
+	}
+}
 
-		if *self == TransactionOrigin::Local {
-			Ordering::Less
-		} else {
-			Ordering::Greater
+impl TransactionOrigin {
+	/// Lower value means higher priority. Retracted transactions were already mined once,
+	/// so they go before external ones.
+	fn priority(&self) -> u8 {
+		match *self {
+			TransactionOrigin::Local => 0,
+			TransactionOrigin::RetractedBlock => 1,
+			TransactionOrigin::External => 2,
 		}
 	}
 }
@@ -135,6 +148,14 @@ struct TransactionOrder {
 	hash: H256,
 	/// Origin of the transaction
 	origin: TransactionOrigin,
+	/// Whether the transaction was sent by a service address.
+	/// Service transactions are processed before all other transactions.
+	service: bool,
+	/// Sequence number of transaction arrival.
+	/// Transactions with the same nonce height and gas price are processed in order of arrival.
+	insertion_id: u64,
+	/// Size of the RLP-encoded transaction (in bytes).
+	mem_usage: usize,
 }
 
 
@@ -145,6 +166,9 @@ impl TransactionOrder {
 			gas_price: tx.transaction.gas_price,
 			hash: tx.hash(),
 			origin: tx.origin,
+			service: tx.service,
+			insertion_id: tx.insertion_id,
+			mem_usage: encode(&tx.transaction).len(),
 		}
 	}
 
@@ -168,6 +192,11 @@ impl PartialOrd for TransactionOrder {
 
 impl Ord for TransactionOrder {
 	fn cmp(&self, b: &TransactionOrder) -> Ordering {
+		// Service transactions go before everything else
+		if self.service != b.service {
+			return b.service.cmp(&self.service);
+		}
+
 		// Local transactions should always have priority
 		if self.origin != b.origin {
 			return self.origin.cmp(&b.origin);
@@ -185,6 +214,11 @@ impl Ord for TransactionOrder {
 			return b_gas.cmp(&a_gas);
 		}
 
+		// Then prefer transactions which arrived earlier
+		if self.insertion_id != b.insertion_id {
+			return self.insertion_id.cmp(&b.insertion_id);
+		}
+
 		// Compare hashes
 		self.hash.cmp(&b.hash)
 	}
@@ -196,16 +230,30 @@ struct VerifiedTransaction {
 	transaction: SignedTransaction,
 	/// transaction origin
 	origin: TransactionOrigin,
+	/// Number of the best block when transaction was inserted to the queue
+	insertion_block: BlockNumber,
+	/// Sequence number of transaction arrival
+	insertion_id: u64,
+	/// Whether the sender is a service address
+	service: bool,
 }
 impl VerifiedTransaction {
-	fn new(transaction: SignedTransaction, origin: TransactionOrigin) -> Result<Self, Error> {
+	fn new(transaction: SignedTransaction, origin: TransactionOrigin, insertion_block: BlockNumber, insertion_id: u64) -> Result<Self, Error> {
 		try!(transaction.sender());
 		Ok(VerifiedTransaction {
 			transaction: transaction,
 			origin: origin,
+			insertion_block: insertion_block,
+			insertion_id: insertion_id,
+			service: false,
 		})
 	}
 
+	fn with_service(mut self, service: bool) -> Self {
+		self.service = service;
+		self
+	}
+
 	fn hash(&self) -> H256 {
 		self.transaction.hash()
 	}
@@ -221,41 +269,55 @@ impl VerifiedTransaction {
 
 /// Holds transactions accessible by (address, nonce) and by priority
 ///
-/// `TransactionSet` keeps number of entries below limit, but it doesn't
+/// `TransactionSet` keeps number and total size of entries below limits, but it doesn't
 /// automatically happen during `insert/remove` operations.
 /// You have to call `enforce_limit` to remove lowest priority transactions from set.
 struct TransactionSet {
 	by_priority: BTreeSet<TransactionOrder>,
 	by_address: Table<Address, U256, TransactionOrder>,
 	limit: usize,
+	/// Maximal total size (in bytes) of transactions in this set.
+	byte_limit: usize,
+	/// Total size (in bytes) of transactions in this set.
+	bytes: usize,
 }
 
 impl TransactionSet {
 	/// Inserts `TransactionOrder` to this set
 	fn insert(&mut self, sender: Address, nonce: U256, order: TransactionOrder) -> Option<TransactionOrder> {
 		self.by_priority.insert(order.clone());
+		self.bytes += order.mem_usage;
 		let r = self.by_address.insert(sender, nonce, order);
 		// If transaction was replaced remove it from priority queue
 		if let Some(ref order) = r {
 			self.by_priority.remove(order);
+			self.bytes -= order.mem_usage;
 		}
 		r
 	}
 
-	/// Remove low priority transactions if there is more then specified by given `limit`.
+	/// Remove low priority transactions if there is more then specified by given `limit`
+	/// or their total size exceeds `byte_limit`.
 	///
 	/// It drops transactions from this set but also removes associated `VerifiedTransaction`.
 	/// Returns addresses and highes nonces of transactions removed because of limit.
 	fn enforce_limit(&mut self, by_hash: &mut HashMap<H256, VerifiedTransaction>) -> Option<HashMap<Address, U256>> {
 		let len = self.by_priority.len();
-		if len <= self.limit {
+		if len <= self.limit && self.bytes <= self.byte_limit {
 			return None;
 		}
 
 		let to_drop : Vec<(Address, U256)> = {
+			let (limit, byte_limit) = (self.limit, self.byte_limit);
+			let mut count = 0;
+			let mut bytes = 0;
 			self.by_priority
 				.iter()
-				.skip(self.limit)
+				.skip_while(|order| {
+					count += 1;
+					bytes += order.mem_usage;
+					count <= limit && bytes <= byte_limit
+				})
 				.map(|order| by_hash.get(&order.hash)
 					.expect("All transactions in `self.by_priority` and `self.by_address` are kept in sync with `by_hash`."))
 				.map(|tx| (tx.sender(), tx.nonce()))
@@ -280,6 +342,7 @@ impl TransactionSet {
 	fn drop(&mut self, sender: &Address, nonce: &U256) -> Option<TransactionOrder> {
 		if let Some(tx_order) = self.by_address.remove(sender, nonce) {
 			self.by_priority.remove(&tx_order);
+			self.bytes -= tx_order.mem_usage;
 			return Some(tx_order);
 		}
 		None
@@ -289,6 +352,7 @@ impl TransactionSet {
 	fn clear(&mut self) {
 		self.by_priority.clear();
 		self.by_address.clear();
+		self.bytes = 0;
 	}
 
 	/// Sets new limit for number of transactions in this `TransactionSet`.
@@ -296,6 +360,12 @@ impl TransactionSet {
 	fn set_limit(&mut self, limit: usize) {
 		self.limit = limit;
 	}
+
+	/// Sets new limit of total size (in bytes) of transactions in this `TransactionSet`.
+	/// Note the limit is not applied (no transactions are removed) by calling this method.
+	fn set_byte_limit(&mut self, byte_limit: usize) {
+		self.byte_limit = byte_limit;
+	}
 }
 
 #[derive(Debug)]
@@ -305,6 +375,30 @@ pub struct TransactionQueueStatus {
 	pub pending: usize,
 	/// Number of future transactions (waiting for transactions with lower nonces first)
 	pub future: usize,
+	/// Total size (in bytes) of transactions in the queue
+	pub bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// State of transaction in the queue
+pub enum TransactionState {
+	/// Transaction is ready to go to block
+	Pending,
+	/// Transaction is waiting for transactions with lower nonces first
+	Future,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Details of transaction kept in the queue
+pub struct TransactionDetails {
+	/// Transaction hash
+	pub hash: H256,
+	/// Transaction origin
+	pub origin: TransactionOrigin,
+	/// Number of the best block when transaction was inserted to the queue
+	pub insertion_block: BlockNumber,
+	/// Current state of the transaction
+	pub state: TransactionState,
 }
 
 #[derive(Debug, PartialEq)]
@@ -316,6 +410,94 @@ pub enum TransactionImportResult {
 	Future
 }
 
+#[derive(Debug, PartialEq)]
+/// Reason why transaction was not imported to the queue.
+pub enum TransactionImportError {
+	/// Transaction's gas price is below threshold.
+	GasPriceTooLow {
+		/// Minimal expected gas price
+		minimal: U256,
+		/// Transaction gas price
+		got: U256,
+	},
+	/// Transaction is already imported to the queue
+	AlreadyImported,
+	/// Transaction is not valid anymore (state already has higher nonce)
+	Old,
+	/// Transaction has too low fee
+	/// (there is already a transaction with the same sender-nonce but higher gas price)
+	TooCheapToReplace,
+	/// Transaction was not imported to the queue because limit has been reached.
+	LimitReached,
+	/// Transaction was not imported to the queue because sender has too many transactions in the queue already.
+	TooManyFromSender,
+	/// Sender doesn't have enough funds to pay for this transaction
+	InsufficientBalance {
+		/// Transaction cost
+		cost: U256,
+		/// Senders balance
+		balance: U256,
+	},
+	/// Transaction's gas is higher than current gas limit
+	InvalidGasLimit {
+		/// Current gas limit
+		limit: U256,
+		/// Declared transaction gas
+		got: U256,
+	},
+	/// Transaction's gas is higher than limit of gas of single transaction
+	GasLimitExceeded {
+		/// Current limit of gas of single transaction
+		limit: U256,
+		/// Declared transaction gas
+		got: U256,
+	},
+	/// Transaction signature is invalid (sender can't be recovered or `s` is not low)
+	InvalidSignature,
+	/// Sender of the transaction is banned
+	SenderBanned,
+	/// Recipient of the transaction is banned
+	RecipientBanned,
+	/// Transaction was signed for different chain (or without chain id after the transition)
+	InvalidChainId {
+		/// Id of the chain (`None` if chain has no id)
+		expected: Option<u64>,
+		/// Chain id of transaction (`None` if transaction is valid on any chain)
+		got: Option<u64>,
+	},
+	/// Transaction RLP couldn't be decoded
+	InvalidRlp(DecoderError),
+}
+
+impl fmt::Display for TransactionImportError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		use self::TransactionImportError::*;
+		let msg = match *self {
+			GasPriceTooLow { minimal, got } =>
+				format!("Insufficient gas price. Min={}, Given={}", minimal, got),
+			AlreadyImported => "Already imported".into(),
+			Old => "No longer valid".into(),
+			TooCheapToReplace => "Gas price too low to replace".into(),
+			LimitReached => "Transaction limit reached".into(),
+			TooManyFromSender => "Too many transactions from this sender".into(),
+			InsufficientBalance { cost, balance } =>
+				format!("Insufficient balance for transaction. Balance={}, Cost={}", balance, cost),
+			InvalidGasLimit { limit, got } =>
+				format!("Gas limit exceeded. Limit={}, Given={}", limit, got),
+			GasLimitExceeded { limit, got } =>
+				format!("Transaction gas limit exceeded. Limit={}, Given={}", limit, got),
+			InvalidSignature => "Invalid signature".into(),
+			SenderBanned => "Sender is banned".into(),
+			RecipientBanned => "Recipient is banned".into(),
+			InvalidChainId { expected, got } =>
+				format!("Invalid chain id. Expected={:?}, Given={:?}", expected, got),
+			InvalidRlp(ref err) => format!("Invalid RLP: {}", err),
+		};
+
+		f.write_fmt(format_args!("Transaction import error ({})", msg))
+	}
+}
+
 /// Details of account
 pub struct AccountDetails {
 	/// Most recent account nonce
@@ -327,13 +509,33 @@ pub struct AccountDetails {
 
 /// Transactions with `gas > (gas_limit + gas_limit * Factor(in percents))` are not imported to the queue.
 const GAS_LIMIT_HYSTERESIS: usize = 10; // %
+/// Default minimal gas price increase required to replace a transaction.
+const DEFAULT_REPLACEMENT_BUMP: u32 = 10; // %
 
 /// `TransactionQueue` implementation
 pub struct TransactionQueue {
-	/// Gas Price threshold for transactions that can be imported to this queue (defaults to 0)
-	minimal_gas_price: U256,
+	/// Rule determining minimal gas price of transactions that can be imported to this queue (defaults to 0 for all senders)
+	gas_price_rule: GasPriceRule,
+	/// Minimal gas prices of senders resolved for `GasPriceRule::Contract` at current chain head
+	contract_gas_prices: HashMap<Address, U256>,
+	/// Sequence number of next imported transaction
+	next_insertion_id: u64,
 	/// Current gas limit (block gas limit * factor). Transactions above the limit will not be accepted (default to !0)
 	gas_limit: U256,
+	/// Maximal gas of single transaction. Transactions above the limit will not be accepted (default to !0)
+	tx_gas_limit: U256,
+	/// Maximal number of transactions from single sender kept in the queue (both current and future).
+	max_per_sender: usize,
+	/// Minimal gas price increase (in percent) required to replace transaction with the same `(sender, nonce)`.
+	minimal_replacement_bump: u32,
+	/// Number of the current best block. Used to determine age of transactions.
+	current_block: BlockNumber,
+	/// Chain id transactions have to be signed with (if any).
+	chain_id: Option<u64>,
+	/// Block from which transactions signed without chain id are rejected.
+	chain_id_transition: BlockNumber,
+	/// Maximal total size (in bytes) of transactions in `current` and `future` together (default to !0)
+	total_byte_limit: usize,
 	/// Priority queue for transactions that can go to block
 	current: TransactionSet,
 	/// Priority queue for transactions that has been received but are not yet valid to go to block
@@ -342,6 +544,12 @@ pub struct TransactionQueue {
 	by_hash: HashMap<H256, VerifiedTransaction>,
 	/// Last nonce of transaction in current (to quickly check next expected transaction)
 	last_nonces: HashMap<Address, U256>,
+	/// Senders whose transactions are rejected
+	banned_senders: HashSet<Address>,
+	/// Recipients of transactions which are rejected
+	banned_recipients: HashSet<Address>,
+	/// Senders whose transactions are accepted regardless of gas price and go before all other transactions
+	service_addresses: HashSet<Address>,
 }
 
 impl Default for TransactionQueue {
@@ -362,21 +570,37 @@ impl TransactionQueue {
 			by_priority: BTreeSet::new(),
 			by_address: Table::new(),
 			limit: limit,
+			byte_limit: usize::max_value(),
+			bytes: 0,
 		};
 
 		let future = TransactionSet {
 			by_priority: BTreeSet::new(),
 			by_address: Table::new(),
 			limit: limit,
+			byte_limit: usize::max_value(),
+			bytes: 0,
 		};
 
 		TransactionQueue {
-			minimal_gas_price: U256::zero(),
+			gas_price_rule: GasPriceRule::default(),
+			contract_gas_prices: HashMap::new(),
+			next_insertion_id: 0,
 			gas_limit: !U256::zero(),
+			tx_gas_limit: !U256::zero(),
+			max_per_sender: usize::max_value(),
+			minimal_replacement_bump: DEFAULT_REPLACEMENT_BUMP,
+			current_block: 0,
+			chain_id: None,
+			chain_id_transition: BlockNumber::max_value(),
+			total_byte_limit: usize::max_value(),
 			current: current,
 			future: future,
 			by_hash: HashMap::new(),
 			last_nonces: HashMap::new(),
+			banned_senders: HashSet::new(),
+			banned_recipients: HashSet::new(),
+			service_addresses: HashSet::new(),
 		}
 	}
 
@@ -386,7 +610,7 @@ impl TransactionQueue {
 		self.future.set_limit(limit);
 		// And ensure the limits
 		self.current.enforce_limit(&mut self.by_hash);
-		self.future.enforce_limit(&mut self.by_hash);
+		self.enforce_future_limit();
 	}
 
 	/// Returns current limit of transactions in the queue.
@@ -394,15 +618,74 @@ impl TransactionQueue {
 		self.current.limit
 	}
 
-	/// Get the minimal gas price.
-	pub fn minimal_gas_price(&self) -> &U256 {
-		&self.minimal_gas_price
+	/// Set the new limit of total size (in bytes) of transactions in the queue (`current` and `future` together).
+	/// Lowest priority transactions are removed when either the count or the size limit is exceeded.
+	/// Transactions in `current` take precedence, `future` may only use what is left.
+	pub fn set_total_byte_limit(&mut self, byte_limit: usize) {
+		self.total_byte_limit = byte_limit;
+		self.current.set_byte_limit(byte_limit);
+		let removed = self.current.enforce_limit(&mut self.by_hash);
+		self.update_last_nonces(&removed);
+		self.enforce_future_limit();
+	}
+
+	/// Returns current limit of total size (in bytes) of transactions in the queue.
+	pub fn total_byte_limit(&self) -> usize {
+		self.total_byte_limit
+	}
+
+	/// Enforces limits of `future` queue. The byte limit of `future` is whatever is not used by `current`.
+	fn enforce_future_limit(&mut self) -> Option<HashMap<Address, U256>> {
+		let byte_limit = self.total_byte_limit.saturating_sub(self.current.bytes);
+		self.future.set_byte_limit(byte_limit);
+		self.future.enforce_limit(&mut self.by_hash)
 	}
 
-	/// Sets new gas price threshold for incoming transactions.
+	/// Get the minimal gas price (of senders which are not exempt).
+	pub fn minimal_gas_price(&self) -> U256 {
+		self.gas_price_rule.floor()
+	}
+
+	/// Sets new gas price threshold for incoming transactions. Exemptions of the current rule are kept.
 	/// Any transaction already imported to the queue is not affected.
 	pub fn set_minimal_gas_price(&mut self, min_gas_price: U256) {
-		self.minimal_gas_price = min_gas_price;
+		self.gas_price_rule = self.gas_price_rule.clone().with_floor(min_gas_price);
+	}
+
+	/// Returns rule determining minimal gas price of incoming transactions.
+	pub fn gas_price_rule(&self) -> &GasPriceRule {
+		&self.gas_price_rule
+	}
+
+	/// Sets new rule determining minimal gas price of incoming transactions.
+	/// Any transaction already imported to the queue is not affected.
+	pub fn set_gas_price_rule(&mut self, rule: GasPriceRule) {
+		self.gas_price_rule = rule;
+		self.contract_gas_prices.clear();
+	}
+
+	/// Returns `true` if minimal gas price of `sender` has been resolved for `GasPriceRule::Contract`.
+	pub fn has_contract_gas_price(&self, sender: &Address) -> bool {
+		self.contract_gas_prices.contains_key(sender)
+	}
+
+	/// Notes minimal gas price of `sender` returned by `GasPriceRule::Contract` at current chain head.
+	/// Transactions of senders without resolved price are rejected while the rule is active.
+	pub fn set_contract_gas_price(&mut self, sender: Address, gas_price: U256) {
+		self.contract_gas_prices.insert(sender, gas_price);
+	}
+
+	/// Forgets gas prices resolved for `GasPriceRule::Contract`. Should be called when chain head changes.
+	pub fn clear_contract_gas_prices(&mut self) {
+		self.contract_gas_prices.clear();
+	}
+
+	/// Returns minimal gas price required from `sender`.
+	fn required_gas_price(&self, sender: &Address) -> U256 {
+		match self.gas_price_rule {
+			GasPriceRule::Contract { .. } => self.contract_gas_prices.get(sender).cloned().unwrap_or_else(U256::max_value),
+			ref rule => rule.required_gas_price(sender, |_, _| None),
+		}
 	}
 
 	/// Sets new gas limit. Transactions with gas slightly (`GAS_LIMIT_HYSTERESIS`) above the limit won't be imported.
@@ -416,60 +699,278 @@ impl TransactionQueue {
 		};
 	}
 
+	/// Sets maximal gas of single transaction. Transactions above the limit won't be imported.
+	/// Any transaction already imported to the queue is not affected.
+	pub fn set_tx_gas_limit(&mut self, limit: U256) {
+		self.tx_gas_limit = limit;
+	}
+
+	/// Returns maximal gas of single transaction.
+	pub fn tx_gas_limit(&self) -> U256 {
+		self.tx_gas_limit
+	}
+
+	/// Returns maximal number of transactions from single sender kept in the queue.
+	pub fn max_per_sender(&self) -> usize {
+		self.max_per_sender
+	}
+
+	/// Sets new limit of transactions from single sender (counted across `current` and `future`).
+	/// Any transaction already imported to the queue is not affected.
+	pub fn set_max_per_sender(&mut self, max_per_sender: usize) {
+		self.max_per_sender = max_per_sender;
+	}
+
+	/// Returns minimal gas price increase (in percent) required to replace a transaction.
+	pub fn minimal_replacement_bump(&self) -> u32 {
+		self.minimal_replacement_bump
+	}
+
+	/// Sets minimal gas price increase (in percent) required to replace transaction with the same `(sender, nonce)`.
+	pub fn set_minimal_replacement_bump(&mut self, percent: u32) {
+		self.minimal_replacement_bump = percent;
+	}
+
+	/// Sets chain id incoming transactions have to be signed with.
+	/// Transactions signed without chain id are accepted until the `transition` block.
+	/// Any transaction already imported to the queue is not affected.
+	pub fn set_chain_id(&mut self, chain_id: Option<u64>, transition: BlockNumber) {
+		self.chain_id = chain_id;
+		self.chain_id_transition = transition;
+	}
+
+	/// Sets number of the current best block.
+	/// Transactions imported from now on are considered to be inserted at this block.
+	pub fn set_current_block(&mut self, number: BlockNumber) {
+		self.current_block = number;
+	}
+
+	/// Removes all transactions inserted more than `max_age` blocks ago (both from `current` and `future`).
+	/// Subsequent transactions of senders whose transaction got removed from `current` are moved to `future`.
+	pub fn remove_old(&mut self, max_age: BlockNumber) {
+		let current_block = self.current_block;
+		let old = self.by_hash.iter()
+			.filter(|&(_, tx)| current_block.saturating_sub(tx.insertion_block) > max_age)
+			.map(|(hash, _)| hash.clone())
+			.collect::<Vec<_>>();
+
+		for hash in old {
+			trace!(target: "miner", "Removing stale transaction: {:?}", hash);
+			self.remove(&hash);
+		}
+	}
+
 	/// Returns current status for this queue
 	pub fn status(&self) -> TransactionQueueStatus {
 		TransactionQueueStatus {
 			pending: self.current.by_priority.len(),
 			future: self.future.by_priority.len(),
+			bytes: self.current.bytes + self.future.bytes,
+		}
+	}
+
+	/// Bans `sender`. Its transactions are removed from the queue and new ones are rejected.
+	pub fn ban_sender(&mut self, sender: Address) {
+		let to_remove = self.by_hash.values()
+			.filter(|tx| tx.sender() == sender)
+			.map(|tx| tx.hash())
+			.collect::<Vec<_>>();
+		self.remove_banned(to_remove);
+		self.banned_senders.insert(sender);
+	}
+
+	/// Bans `recipient`. Transactions calling it are removed from the queue and new ones are rejected.
+	/// Contract creations are never affected.
+	pub fn ban_recipient(&mut self, recipient: Address) {
+		let to_remove = self.by_hash.values()
+			.filter(|tx| tx.transaction.action == Action::Call(recipient.clone()))
+			.map(|tx| tx.hash())
+			.collect::<Vec<_>>();
+		self.remove_banned(to_remove);
+		self.banned_recipients.insert(recipient);
+	}
+
+	/// Lifts ban of `sender`. Returns `false` if sender was not banned.
+	pub fn unban_sender(&mut self, sender: &Address) -> bool {
+		self.banned_senders.remove(sender)
+	}
+
+	/// Lifts ban of `recipient`. Returns `false` if recipient was not banned.
+	pub fn unban_recipient(&mut self, recipient: &Address) -> bool {
+		self.banned_recipients.remove(recipient)
+	}
+
+	/// Returns all banned senders.
+	pub fn banned_senders(&self) -> Vec<Address> {
+		self.banned_senders.iter().cloned().collect()
+	}
+
+	/// Returns all banned recipients.
+	pub fn banned_recipients(&self) -> Vec<Address> {
+		self.banned_recipients.iter().cloned().collect()
+	}
+
+	/// Sets senders of service transactions. Their transactions are accepted regardless of gas price
+	/// and are included before all other transactions. Any transaction already imported to the queue is not affected.
+	pub fn set_service_addresses(&mut self, addresses: Vec<Address>) {
+		self.service_addresses = addresses.into_iter().collect();
+	}
+
+	/// Returns senders of service transactions.
+	pub fn service_addresses(&self) -> Vec<Address> {
+		self.service_addresses.iter().cloned().collect()
+	}
+
+	fn remove_banned(&mut self, hashes: Vec<H256>) {
+		for hash in hashes {
+			trace!(target: "miner", "Dropping transaction of banned address: {:?}", hash);
+			self.remove(&hash);
 		}
 	}
 
 	/// Add signed transaction to queue to be verified and imported
-	pub fn add<T>(&mut self, tx: SignedTransaction, fetch_account: &T, origin: TransactionOrigin) -> Result<TransactionImportResult, Error>
+	pub fn add<T>(&mut self, tx: SignedTransaction, fetch_account: &T, origin: TransactionOrigin) -> Result<TransactionImportResult, TransactionImportError>
 		where T: Fn(&Address) -> AccountDetails {
 
 		trace!(target: "miner", "Importing: {:?}", tx.hash());
 
-		if tx.gas_price < self.minimal_gas_price {
+		let client_account = try!(self.verify_with_origin(&tx, fetch_account, origin));
+		let vtx = try!(VerifiedTransaction::new(tx, origin, self.current_block, self.next_insertion_id).map_err(|_| TransactionImportError::InvalidSignature));
+		let vtx = {
+			let service = self.service_addresses.contains(&vtx.sender());
+			vtx.with_service(service)
+		};
+		self.next_insertion_id += 1;
+
+		self.import_tx(vtx, client_account.nonce)
+	}
+
+	/// Checks whether transaction could be imported to the queue (without importing it).
+	/// Returns details of the sender's account.
+	pub fn verify<T>(&self, tx: &SignedTransaction, fetch_account: &T) -> Result<AccountDetails, TransactionImportError>
+		where T: Fn(&Address) -> AccountDetails {
+		self.verify_with_origin(tx, fetch_account, TransactionOrigin::External)
+	}
+
+	/// Transactions of retracted blocks were already mined once, so minimal gas price is not required for them.
+	fn verify_with_origin<T>(&self, tx: &SignedTransaction, fetch_account: &T, origin: TransactionOrigin) -> Result<AccountDetails, TransactionImportError>
+		where T: Fn(&Address) -> AccountDetails {
+
+		try!(tx.check_low_s().map_err(|_| TransactionImportError::InvalidSignature));
+
+		// Transactions from the queue are going to be included in the next block.
+		let allow_unprotected = self.current_block + 1 < self.chain_id_transition;
+		if tx.verify_chain_id(self.chain_id, allow_unprotected).is_err() {
 			trace!(target: "miner",
-				"Dropping transaction below minimal gas price threshold: {:?} (gp: {} < {})",
-				tx.hash(), tx.gas_price, self.minimal_gas_price
+				"Dropping transaction with invalid chain id: {:?} ({:?} != {:?})",
+				tx.hash(), tx.chain_id(), self.chain_id
 			);
 
-			return Err(Error::Transaction(TransactionError::InsufficientGasPrice {
-				minimal: self.minimal_gas_price,
-				got: tx.gas_price,
-			}));
+			return Err(TransactionImportError::InvalidChainId {
+				expected: self.chain_id,
+				got: tx.chain_id(),
+			});
 		}
 
-		try!(tx.check_low_s());
-
 		if tx.gas > self.gas_limit {
 			trace!(target: "miner",
 				"Dropping transaction above gas limit: {:?} ({} > {})",
 				tx.hash(), tx.gas, self.gas_limit
 			);
 
-			return Err(Error::Transaction(TransactionError::GasLimitExceeded {
+			return Err(TransactionImportError::InvalidGasLimit {
 				limit: self.gas_limit,
 				got: tx.gas,
-			}));
+			});
+		}
+
+		if tx.gas > self.tx_gas_limit {
+			trace!(target: "miner",
+				"Dropping transaction above transaction gas limit: {:?} ({} > {})",
+				tx.hash(), tx.gas, self.tx_gas_limit
+			);
+
+			return Err(TransactionImportError::GasLimitExceeded {
+				limit: self.tx_gas_limit,
+				got: tx.gas,
+			});
+		}
+
+		let sender = try!(tx.sender().map_err(|_| TransactionImportError::InvalidSignature));
+
+		if self.banned_senders.contains(&sender) {
+			trace!(target: "miner", "Dropping transaction from banned sender: {:?} ({:?})", tx.hash(), sender);
+			return Err(TransactionImportError::SenderBanned);
+		}
+		if let Action::Call(ref recipient) = tx.action {
+			if self.banned_recipients.contains(recipient) {
+				trace!(target: "miner", "Dropping transaction to banned recipient: {:?} ({:?})", tx.hash(), recipient);
+				return Err(TransactionImportError::RecipientBanned);
+			}
+		}
+
+		let minimal_gas_price = match origin == TransactionOrigin::RetractedBlock || self.service_addresses.contains(&sender) {
+			true => U256::zero(),
+			false => self.required_gas_price(&sender),
+		};
+		if tx.gas_price < minimal_gas_price {
+			trace!(target: "miner",
+				"Dropping transaction below minimal gas price threshold: {:?} (gp: {} < {})",
+				tx.hash(), tx.gas_price, minimal_gas_price
+			);
+
+			return Err(TransactionImportError::GasPriceTooLow {
+				minimal: minimal_gas_price,
+				got: tx.gas_price,
+			});
 		}
 
-		let vtx = try!(VerifiedTransaction::new(tx, origin));
-		let client_account = fetch_account(&vtx.sender());
+		let client_account = fetch_account(&sender);
 
-		let cost = vtx.transaction.value + vtx.transaction.gas_price * vtx.transaction.gas;
+		let cost = tx.value + tx.gas_price * tx.gas;
 		if client_account.balance < cost {
 			trace!(target: "miner", "Dropping transaction without sufficient balance: {:?} ({} < {})",
-				vtx.hash(), client_account.balance, cost);
-			return Err(Error::Transaction(TransactionError::InsufficientBalance {
+				tx.hash(), client_account.balance, cost);
+			return Err(TransactionImportError::InsufficientBalance {
 				cost: cost,
 				balance: client_account.balance
-			}));
+			});
+		}
+
+		Ok(client_account)
+	}
+
+	/// Makes sure that sender is allowed to have one more transaction in the queue.
+	///
+	/// Replacements of already queued `(sender, nonce)` are always allowed. When the limit is reached
+	/// sender's highest-nonce `future` transaction is dropped in favour of a transaction with lower nonce.
+	fn check_sender_limit(&mut self, sender: &Address, nonce: &U256) -> Result<(), TransactionImportError> {
+		if self.current.by_address.get(sender, nonce).is_some() || self.future.by_address.get(sender, nonce).is_some() {
+			return Ok(());
+		}
+
+		let in_current = self.current.by_address.row(sender).map_or(0, |row| row.len());
+		let in_future = self.future.by_address.row(sender).map_or(0, |row| row.len());
+		if in_current + in_future < self.max_per_sender {
+			return Ok(());
 		}
 
-		self.import_tx(vtx, client_account.nonce).map_err(Error::Transaction)
+		let highest_future = self.future.by_address.row(sender)
+			.and_then(|row| row.keys().max().cloned());
+		match highest_future {
+			Some(highest) if highest > *nonce => {
+				let order = self.future.drop(sender, &highest)
+					.expect("Nonce has just been found in `future.by_address`; qed");
+				trace!(target: "miner", "Dropping future transaction because of sender limit: {:?}", order.hash);
+				self.by_hash.remove(&order.hash);
+				Ok(())
+			},
+			_ => {
+				trace!(target: "miner", "Dropping transaction from sender exceeding the limit: {:?} ({} >= {})",
+					sender, in_current + in_future, self.max_per_sender);
+				Err(TransactionImportError::TooManyFromSender)
+			}
+		}
 	}
 
 	/// Removes all transactions from particular sender up to (excluding) given client (state) nonce.
@@ -532,6 +1033,34 @@ impl TransactionQueue {
 		}
 	}
 
+	/// Removes transaction identified by hash from the queue and returns it.
+	///
+	/// If the transaction was in `current` all subsequent transactions from the same sender
+	/// are moved to `future` since there is a nonce gap now.
+	pub fn remove(&mut self, transaction_hash: &H256) -> Option<SignedTransaction> {
+		let transaction = match self.by_hash.remove(transaction_hash) {
+			Some(transaction) => transaction,
+			None => return None,
+		};
+		let sender = transaction.sender();
+		let nonce = transaction.nonce();
+
+		// Future transactions are waiting for a gap anyway, so no other transaction is affected.
+		if self.future.drop(&sender, &nonce).is_some() {
+			return Some(transaction.transaction);
+		}
+
+		if let Some(order) = self.current.drop(&sender, &nonce) {
+			// Height of transactions in current is relative to nonce in state.
+			let state_nonce = nonce - order.nonce_height;
+			self.last_nonces.remove(&sender);
+			self.update_future(&sender, state_nonce);
+			self.move_all_to_future(&sender, state_nonce);
+			self.move_matching_future_to_current(sender, state_nonce, state_nonce);
+		}
+		Some(transaction.transaction)
+	}
+
 	/// Update height of all transactions in future transactions set.
 	fn update_future(&mut self, sender: &Address, current_nonce: U256) {
 		// We need to drain all transactions for current sender from future and reinsert them with updated height
@@ -569,7 +1098,7 @@ impl TransactionQueue {
 				self.by_hash.remove(&order.hash);
 			}
 		}
-		self.future.enforce_limit(&mut self.by_hash);
+		self.enforce_future_limit();
 	}
 
 	/// Returns top transactions from the queue ordered by priority.
@@ -581,6 +1110,31 @@ impl TransactionQueue {
 			.collect()
 	}
 
+	/// Returns pending transactions grouped by sender. Transactions of each sender are ordered by nonce.
+	pub fn pending_by_sender(&self) -> BTreeMap<Address, Vec<SignedTransaction>> {
+		let mut by_sender = BTreeMap::new();
+		for t in &self.current.by_priority {
+			let tx = self.by_hash.get(&t.hash).expect("All transactions in `current` and `future` are always included in `by_hash`");
+			by_sender.entry(tx.sender()).or_insert_with(Vec::new).push(tx.transaction.clone());
+		}
+		for transactions in by_sender.values_mut() {
+			transactions.sort_by_key(|t| t.nonce);
+		}
+		by_sender
+	}
+
+	/// Returns all transactions from future (waiting for a nonce gap to be filled).
+	/// Transactions are grouped by sender and ordered by nonce.
+	pub fn future_transactions(&self) -> Vec<SignedTransaction> {
+		let mut transactions = self.future.by_priority
+			.iter()
+			.map(|t| self.by_hash.get(&t.hash).expect("All transactions in `current` and `future` are always included in `by_hash`"))
+			.map(|t| t.transaction.clone())
+			.collect::<Vec<_>>();
+		transactions.sort_by_key(|t| (t.sender().expect("Transactions in queue have verified senders"), t.nonce));
+		transactions
+	}
+
 	/// Returns hashes of all transactions from current, ordered by priority.
 	pub fn pending_hashes(&self) -> Vec<H256> {
 		self.current.by_priority
@@ -599,6 +1153,22 @@ impl TransactionQueue {
 		match self.by_hash.get(hash) { Some(transaction_ref) => Some(transaction_ref.transaction.clone()), None => None }
 	}
 
+	/// Returns details of transaction in the queue (if any)
+	pub fn transaction_details(&self, hash: &H256) -> Option<TransactionDetails> {
+		self.by_hash.get(hash).map(|tx| {
+			let is_current = self.current.by_address.get(&tx.sender(), &tx.nonce()).map_or(false, |order| &order.hash == hash);
+			TransactionDetails {
+				hash: hash.clone(),
+				origin: tx.origin,
+				insertion_block: tx.insertion_block,
+				state: match is_current {
+					true => TransactionState::Pending,
+					false => TransactionState::Future,
+				},
+			}
+		})
+	}
+
 	/// Removes all elements (in any state) from the queue
 	pub fn clear(&mut self) {
 		self.current.clear();
@@ -607,6 +1177,91 @@ impl TransactionQueue {
 		self.last_nonces.clear();
 	}
 
+	/// Saves all transactions in the queue (together with their origin) to file at `path`.
+	/// The file is replaced atomically, so it's never left partially written.
+	pub fn save_to(&self, path: &Path) -> io::Result<()> {
+		let mut transactions = self.by_hash.values().collect::<Vec<_>>();
+		transactions.sort_by_key(|tx| (tx.sender(), tx.nonce()));
+
+		let mut stream = RlpStream::new_list(transactions.len());
+		for tx in transactions {
+			let origin: u8 = match tx.origin {
+				TransactionOrigin::Local => 0,
+				TransactionOrigin::External => 1,
+				TransactionOrigin::RetractedBlock => 2,
+			};
+			stream.begin_list(2);
+			stream.append(&origin);
+			stream.append(&tx.transaction);
+		}
+
+		let tmp_path = path.with_extension("tmp");
+		{
+			let mut file = try!(fs::File::create(&tmp_path));
+			try!(file.write_all(&stream.out()));
+			try!(file.sync_all());
+		}
+		fs::rename(&tmp_path, path)
+	}
+
+	/// Imports transactions saved with `save_to` from file at `path`.
+	/// Transactions are verified again, so transactions which are no longer valid are dropped.
+	/// Unreadable or corrupted file is ignored. Returns number of imported transactions.
+	pub fn load_from<T>(&mut self, path: &Path, fetch_account: &T) -> usize
+		where T: Fn(&Address) -> AccountDetails {
+
+		let mut bytes = Vec::new();
+		match fs::File::open(path).and_then(|mut file| file.read_to_end(&mut bytes)) {
+			Ok(_) => {},
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return 0,
+			Err(e) => {
+				warn!(target: "miner", "Unable to read saved transactions from {}: {}", path.display(), e);
+				return 0;
+			},
+		}
+
+		let saved = match Self::decode_saved(&bytes) {
+			Ok(saved) => saved,
+			Err(e) => {
+				warn!(target: "miner", "Ignoring corrupted saved transactions in {}: {:?}", path.display(), e);
+				return 0;
+			},
+		};
+
+		saved.into_iter().fold(0, |imported, (tx, origin)| {
+			let hash = tx.hash();
+			match self.add(tx, fetch_account, origin) {
+				Ok(_) => imported + 1,
+				Err(e) => {
+					debug!(target: "miner", "Dropping saved transaction {:?}: {}", hash, e);
+					imported
+				},
+			}
+		})
+	}
+
+	fn decode_saved(bytes: &[u8]) -> Result<Vec<(SignedTransaction, TransactionOrigin)>, DecoderError> {
+		let rlp = UntrustedRlp::new(bytes);
+		// Make sure the file was not truncated or appended to.
+		if try!(rlp.payload_info()).total() != bytes.len() {
+			return Err(DecoderError::RlpIsTooBig);
+		}
+		if !rlp.is_list() {
+			return Err(DecoderError::RlpExpectedToBeList);
+		}
+
+		let mut saved = Vec::new();
+		for item in rlp.iter() {
+			let origin = match try!(item.val_at::<u8>(0)) {
+				0 => TransactionOrigin::Local,
+				2 => TransactionOrigin::RetractedBlock,
+				_ => TransactionOrigin::External,
+			};
+			saved.push((try!(item.val_at(1)), origin));
+		}
+		Ok(saved)
+	}
+
 	/// Returns highest transaction nonce for given address.
 	pub fn last_nonce(&self, address: &Address) -> Option<U256> {
 		self.last_nonces.get(address).cloned()
@@ -649,12 +1304,12 @@ impl TransactionQueue {
 	/// iff `(address, nonce)` is the same but `gas_price` is higher.
 	///
 	/// Returns `true` when transaction was imported successfuly
-	fn import_tx(&mut self, tx: VerifiedTransaction, state_nonce: U256) -> Result<TransactionImportResult, TransactionError> {
+	fn import_tx(&mut self, tx: VerifiedTransaction, state_nonce: U256) -> Result<TransactionImportResult, TransactionImportError> {
 
 		if self.by_hash.get(&tx.hash()).is_some() {
 			// Transaction is already imported.
 			trace!(target: "miner", "Dropping already imported transaction: {:?}", tx.hash());
-			return Err(TransactionError::AlreadyImported);
+			return Err(TransactionImportError::AlreadyImported);
 		}
 
 		let address = tx.sender();
@@ -665,19 +1320,24 @@ impl TransactionQueue {
 			.cloned()
 			.map_or(state_nonce, |n| n + U256::one());
 
+		if nonce < state_nonce {
+			// Droping transaction
+			trace!(target: "miner", "Dropping old transaction: {:?} (nonce: {} < {})", tx.hash(), nonce, next_nonce);
+			return Err(TransactionImportError::Old);
+		}
+
+		// Only transactions which are going to be queued may evict other transactions of the sender.
+		try!(self.check_sender_limit(&address, &nonce));
+
 		// Check height
 		if nonce > next_nonce {
 			// We have a gap - put to future
-			try!(check_too_cheap(Self::replace_transaction(tx, next_nonce, &mut self.future, &mut self.by_hash)));
-			try!(check_if_removed(&address, &nonce, self.future.enforce_limit(&mut self.by_hash)));
+			try!(check_too_cheap(Self::replace_transaction(tx, next_nonce, self.minimal_replacement_bump, &mut self.future, &mut self.by_hash)));
+			try!(check_if_removed(&address, &nonce, self.enforce_future_limit()));
 			return Ok(TransactionImportResult::Future);
-		} else if nonce < state_nonce {
-			// Droping transaction
-			trace!(target: "miner", "Dropping old transaction: {:?} (nonce: {} < {})", tx.hash(), nonce, next_nonce);
-			return Err(TransactionError::Old);
 		}
 
-		try!(check_too_cheap(Self::replace_transaction(tx, state_nonce, &mut self.current, &mut self.by_hash)));
+		try!(check_too_cheap(Self::replace_transaction(tx, state_nonce, self.minimal_replacement_bump, &mut self.current, &mut self.by_hash)));
 		// Keep track of highest nonce stored in current
 		self.last_nonces.insert(address, nonce);
 		// Update nonces of transactions in future
@@ -689,7 +1349,7 @@ impl TransactionQueue {
 		if let Some(order) = self.future.drop(&address, &nonce) {
 			// Let's insert that transaction to current (if it has higher gas_price)
 			let future_tx = self.by_hash.remove(&order.hash).unwrap();
-			try!(check_too_cheap(Self::replace_transaction(future_tx, state_nonce, &mut self.current, &mut self.by_hash)));
+			try!(check_too_cheap(Self::replace_transaction(future_tx, state_nonce, 0, &mut self.current, &mut self.by_hash)));
 		}
 
 		// Also enforce the limit
@@ -698,6 +1358,8 @@ impl TransactionQueue {
 		self.update_last_nonces(&removed);
 		// Trigger error if we were removed.
 		try!(check_if_removed(&address, &nonce, removed));
+		// Current might have grown, so there is less space left for future.
+		self.enforce_future_limit();
 
 		trace!(target: "miner", "status: {:?}", self.status());
 		Ok(TransactionImportResult::Current)
@@ -718,12 +1380,13 @@ impl TransactionQueue {
 
 	/// Replaces transaction in given set (could be `future` or `current`).
 	///
-	/// If there is already transaction with same `(sender, nonce)` it will be replaced iff `gas_price` is higher.
+	/// If there is already transaction with same `(sender, nonce)` it will be replaced iff `gas_price`
+	/// is higher by at least `bump` percent.
 	/// One of the transactions is dropped from set and also removed from queue entirely (from `by_hash`).
 	///
 	/// Returns `true` if transaction actually got to the queue (`false` if there was already a transaction with higher
 	/// gas_price)
-	fn replace_transaction(tx: VerifiedTransaction, base_nonce: U256, set: &mut TransactionSet, by_hash: &mut HashMap<H256, VerifiedTransaction>) -> bool {
+	fn replace_transaction(tx: VerifiedTransaction, base_nonce: U256, bump: u32, set: &mut TransactionSet, by_hash: &mut HashMap<H256, VerifiedTransaction>) -> bool {
 		let order = TransactionOrder::for_transaction(&tx, base_nonce);
 		let hash = tx.hash();
 		let address = tx.sender();
@@ -733,10 +1396,10 @@ impl TransactionQueue {
 
 		if let Some(old) = set.insert(address, nonce, order.clone()) {
 			// There was already transaction in queue. Let's check which one should stay
-			let old_fee = old.gas_price;
+			let required_fee = bumped_gas_price(&old.gas_price, bump);
 			let new_fee = order.gas_price;
-			if old_fee.cmp(&new_fee) == Ordering::Greater {
-				// Put back old transaction since it has greater priority (higher gas_price)
+			if required_fee.cmp(&new_fee) == Ordering::Greater {
+				// Put back old transaction since new one doesn't pay enough to replace it
 				set.insert(address, nonce, old);
 				// and remove new one
 				by_hash.remove(&hash);
@@ -752,19 +1415,27 @@ impl TransactionQueue {
 	}
 }
 
-fn check_too_cheap(is_in: bool) -> Result<(), TransactionError> {
+/// Returns gas price required to replace transaction with `gas_price` given minimal `bump` in percent.
+fn bumped_gas_price(gas_price: &U256, bump: u32) -> U256 {
+	match gas_price.overflowing_mul(U256::from(100 + bump as u64)) {
+		(_, true) => !U256::zero(),
+		(val, false) => val / U256::from(100),
+	}
+}
+
+fn check_too_cheap(is_in: bool) -> Result<(), TransactionImportError> {
 	if is_in {
 		Ok(())
 	} else {
-		Err(TransactionError::TooCheapToReplace)
+		Err(TransactionImportError::TooCheapToReplace)
 	}
 }
 
-fn check_if_removed(sender: &Address, nonce: &U256, dropped: Option<HashMap<Address, U256>>) -> Result<(), TransactionError> {
+fn check_if_removed(sender: &Address, nonce: &U256, dropped: Option<HashMap<Address, U256>>) -> Result<(), TransactionImportError> {
 	match dropped {
 		Some(ref dropped) => match dropped.get(sender) {
 			Some(max) if nonce <= max => {
-				Err(TransactionError::LimitReached)
+				Err(TransactionImportError::LimitReached)
 			},
 			_ => Ok(()),
 		},
@@ -779,15 +1450,15 @@ mod test {
 	use util::table::*;
 	use util::*;
 	use ethcore::transaction::*;
-	use ethcore::error::{Error, TransactionError};
+	use ethcore::gas_price_rule::GasPriceRule;
 	use super::*;
 	use super::{TransactionSet, TransactionOrder, VerifiedTransaction};
+	use std::fs::File;
+	use std::io::{Read, Write};
+	use devtools::RandomTempPath;
 
-	fn unwrap_tx_err(err: Result<TransactionImportResult, Error>) -> TransactionError {
-		match err.unwrap_err() {
-			Error::Transaction(e) => e,
-			_ => panic!("Expected transaction error!"),
-		}
+	fn unwrap_tx_err(err: Result<TransactionImportResult, TransactionImportError>) -> TransactionImportError {
+		err.unwrap_err()
 	}
 
 	fn new_unsigned_tx(nonce: U256) -> Transaction {
@@ -851,15 +1522,17 @@ mod test {
 		let mut set = TransactionSet {
 			by_priority: BTreeSet::new(),
 			by_address: Table::new(),
-			limit: 1
+			limit: 1,
+			byte_limit: usize::max_value(),
+			bytes: 0,
 		};
 		let (tx1, tx2) = new_txs(U256::from(1));
-		let tx1 = VerifiedTransaction::new(tx1, TransactionOrigin::External).unwrap();
-		let tx2 = VerifiedTransaction::new(tx2, TransactionOrigin::External).unwrap();
+		let tx1 = VerifiedTransaction::new(tx1, TransactionOrigin::External, 0, 0).unwrap();
+		let tx2 = VerifiedTransaction::new(tx2, TransactionOrigin::External, 0, 0).unwrap();
 		let mut by_hash = {
 			let mut x = HashMap::new();
-			let tx1 = VerifiedTransaction::new(tx1.transaction.clone(), TransactionOrigin::External).unwrap();
-			let tx2 = VerifiedTransaction::new(tx2.transaction.clone(), TransactionOrigin::External).unwrap();
+			let tx1 = VerifiedTransaction::new(tx1.transaction.clone(), TransactionOrigin::External, 0, 0).unwrap();
+			let tx2 = VerifiedTransaction::new(tx2.transaction.clone(), TransactionOrigin::External, 0, 0).unwrap();
 			x.insert(tx1.hash(), tx1);
 			x.insert(tx2.hash(), tx2);
 			x
@@ -890,17 +1563,19 @@ mod test {
 		let mut set = TransactionSet {
 			by_priority: BTreeSet::new(),
 			by_address: Table::new(),
-			limit: 1
+			limit: 1,
+			byte_limit: usize::max_value(),
+			bytes: 0,
 		};
 		// Create two transactions with same nonce
 		// (same hash)
 		let (tx1, tx2) = new_txs(U256::from(0));
-		let tx1 = VerifiedTransaction::new(tx1, TransactionOrigin::External).unwrap();
-		let tx2 = VerifiedTransaction::new(tx2, TransactionOrigin::External).unwrap();
+		let tx1 = VerifiedTransaction::new(tx1, TransactionOrigin::External, 0, 0).unwrap();
+		let tx2 = VerifiedTransaction::new(tx2, TransactionOrigin::External, 0, 0).unwrap();
 		let by_hash = {
 			let mut x = HashMap::new();
-			let tx1 = VerifiedTransaction::new(tx1.transaction.clone(), TransactionOrigin::External).unwrap();
-			let tx2 = VerifiedTransaction::new(tx2.transaction.clone(), TransactionOrigin::External).unwrap();
+			let tx1 = VerifiedTransaction::new(tx1.transaction.clone(), TransactionOrigin::External, 0, 0).unwrap();
+			let tx2 = VerifiedTransaction::new(tx2.transaction.clone(), TransactionOrigin::External, 0, 0).unwrap();
 			x.insert(tx1.hash(), tx1);
 			x.insert(tx2.hash(), tx2);
 			x
@@ -940,7 +1615,7 @@ mod test {
 		let res = txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External);
 
 		// and then there should be only one transaction in current (the one with higher gas_price)
-		assert_eq!(unwrap_tx_err(res), TransactionError::TooCheapToReplace);
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::TooCheapToReplace);
 		assert_eq!(txq.status().pending, 1);
 		assert_eq!(txq.status().future, 0);
 		assert_eq!(txq.current.by_priority.len(), 1);
@@ -991,7 +1666,7 @@ mod test {
 		let res = txq.add(tx, &default_nonce, TransactionOrigin::External);
 
 		// then
-		assert_eq!(unwrap_tx_err(res), TransactionError::GasLimitExceeded {
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::InvalidGasLimit {
 			limit: U256::from(55_000), // Should be 110% of set_gas_limit
 			got: gas,
 		});
@@ -1001,6 +1676,55 @@ mod test {
 	}
 
 
+	#[test]
+	fn should_not_import_transaction_above_tx_gas_limit() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let tx = new_tx();
+		let gas = tx.gas;
+		txq.set_tx_gas_limit(gas - U256::one());
+
+		// when
+		let res = txq.add(tx.clone(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::GasLimitExceeded {
+			limit: gas - U256::one(),
+			got: gas,
+		});
+		assert_eq!(txq.status().pending, 0);
+
+		// when
+		txq.set_tx_gas_limit(gas);
+
+		// then
+		assert!(txq.add(tx, &default_nonce, TransactionOrigin::External).is_ok());
+	}
+
+	#[test]
+	fn should_accept_and_prioritize_service_transactions() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_minimal_gas_price(U256::from(10));
+		let keypair = KeyPair::create().unwrap();
+		let service_tx = Transaction { gas_price: U256::zero(), ..new_unsigned_tx(U256::from(123)) }.sign(keypair.secret());
+		let tx = Transaction { gas_price: U256::from(100), ..new_unsigned_tx(U256::from(123)) }.sign(KeyPair::create().unwrap().secret());
+
+		// when
+		let res = txq.add(service_tx.clone(), &default_nonce, TransactionOrigin::External);
+		txq.set_service_addresses(vec![keypair.address()]);
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::Local).unwrap();
+		txq.add(service_tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::GasPriceTooLow {
+			minimal: U256::from(10),
+			got: U256::zero(),
+		});
+		assert_eq!(txq.service_addresses(), vec![keypair.address()]);
+		assert_eq!(txq.top_transactions(), vec![service_tx, tx]);
+	}
+
 	#[test]
 	fn should_drop_transactions_from_senders_without_balance() {
 		// given
@@ -1015,7 +1739,7 @@ mod test {
 		let res = txq.add(tx, &account, TransactionOrigin::External);
 
 		// then
-		assert_eq!(unwrap_tx_err(res), TransactionError::InsufficientBalance {
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::InsufficientBalance {
 			balance: U256::from(1),
 			cost: U256::from(100_100),
 		});
@@ -1035,7 +1759,7 @@ mod test {
 		let res = txq.add(tx, &default_nonce, TransactionOrigin::External);
 
 		// then
-		assert_eq!(unwrap_tx_err(res), TransactionError::InsufficientGasPrice {
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::GasPriceTooLow {
 			minimal: U256::from(2),
 			got: U256::from(1),
 		});
@@ -1044,51 +1768,201 @@ mod test {
 		assert_eq!(stats.future, 0);
 	}
 
+	fn new_zero_price_tx(keypair: &KeyPair) -> SignedTransaction {
+		let mut tx = new_unsigned_tx(default_nonce_val());
+		tx.gas_price = U256::zero();
+		tx.sign(keypair.secret())
+	}
+
 	#[test]
-	fn should_reject_incorectly_signed_transaction() {
+	fn should_import_zero_gas_price_transactions_of_exempt_senders_only() {
 		// given
+		let exempt = KeyPair::create().unwrap();
+		let other = KeyPair::create().unwrap();
 		let mut txq = TransactionQueue::new();
-		let tx = new_unsigned_tx(U256::from(123));
-		let stx = {
-			let mut s = RlpStream::new_list(9);
-			s.append(&tx.nonce);
-			s.append(&tx.gas_price);
-			s.append(&tx.gas);
-			s.append_empty_data(); // action=create
-			s.append(&tx.value);
-			s.append(&tx.data);
-			s.append(&0u64); // v
-			s.append(&U256::zero()); // r
-			s.append(&U256::zero()); // s
-			decode(s.as_raw())
-		};
+		txq.set_gas_price_rule(GasPriceRule::PerSenderExemptions {
+			floor: U256::from(20),
+			exempt: vec![exempt.address()].into_iter().collect(),
+		});
+
 		// when
-		let res = txq.add(stx, &default_nonce, TransactionOrigin::External);
+		let exempt_res = txq.add(new_zero_price_tx(&exempt), &default_nonce, TransactionOrigin::External);
+		let other_res = txq.add(new_zero_price_tx(&other), &default_nonce, TransactionOrigin::External);
 
 		// then
-		assert!(res.is_err());
+		assert_eq!(exempt_res.unwrap(), TransactionImportResult::Current);
+		assert_eq!(unwrap_tx_err(other_res), TransactionImportError::GasPriceTooLow {
+			minimal: U256::from(20),
+			got: U256::zero(),
+		});
+		assert_eq!(txq.status().pending, 1);
 	}
 
 	#[test]
-	fn should_import_txs_from_same_sender() {
+	fn should_keep_exemptions_when_minimal_gas_price_changes() {
 		// given
+		let exempt = KeyPair::create().unwrap();
 		let mut txq = TransactionQueue::new();
-
-		let (tx, tx2) = new_txs(U256::from(1));
+		txq.set_gas_price_rule(GasPriceRule::PerSenderExemptions {
+			floor: U256::from(20),
+			exempt: vec![exempt.address()].into_iter().collect(),
+		});
 
 		// when
-		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
-		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.set_minimal_gas_price(U256::from(30));
+		let res = txq.add(new_zero_price_tx(&exempt), &default_nonce, TransactionOrigin::External);
 
 		// then
-		let top = txq.top_transactions();
-		assert_eq!(top[0], tx);
-		assert_eq!(top[1], tx2);
-		assert_eq!(top.len(), 2);
+		assert_eq!(txq.minimal_gas_price(), U256::from(30));
+		assert_eq!(res.unwrap(), TransactionImportResult::Current);
 	}
 
 	#[test]
-	fn should_prioritize_local_transactions() {
+	fn should_order_exempt_transactions_by_arrival() {
+		// given
+		let senders: Vec<_> = (0..5).map(|_| KeyPair::create().unwrap()).collect();
+		let mut txq = TransactionQueue::new();
+		txq.set_gas_price_rule(GasPriceRule::PerSenderExemptions {
+			floor: U256::from(20),
+			exempt: senders.iter().map(|k| k.address()).collect(),
+		});
+		let txs: Vec<_> = senders.iter().map(new_zero_price_tx).collect();
+
+		// when
+		for tx in &txs {
+			txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		}
+
+		// then
+		assert_eq!(txq.top_transactions(), txs);
+	}
+
+	#[test]
+	fn should_use_resolved_contract_gas_prices() {
+		// given
+		let resolved = KeyPair::create().unwrap();
+		let unknown = KeyPair::create().unwrap();
+		let mut txq = TransactionQueue::new();
+		txq.set_gas_price_rule(GasPriceRule::Contract { registry: Address::from(0x10), selector: vec![0; 4] });
+
+		// when
+		txq.set_contract_gas_price(resolved.address(), U256::zero());
+		let resolved_res = txq.add(new_zero_price_tx(&resolved), &default_nonce, TransactionOrigin::External);
+		let unknown_res = txq.add(new_zero_price_tx(&unknown), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(resolved_res.unwrap(), TransactionImportResult::Current);
+		assert_eq!(unwrap_tx_err(unknown_res), TransactionImportError::GasPriceTooLow {
+			minimal: U256::max_value(),
+			got: U256::zero(),
+		});
+
+		// when
+		txq.clear_contract_gas_prices();
+
+		// then
+		assert!(!txq.has_contract_gas_price(&resolved.address()));
+	}
+
+	#[test]
+	fn should_reject_incorectly_signed_transaction() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let tx = new_unsigned_tx(U256::from(123));
+		let stx = {
+			let mut s = RlpStream::new_list(9);
+			s.append(&tx.nonce);
+			s.append(&tx.gas_price);
+			s.append(&tx.gas);
+			s.append_empty_data(); // action=create
+			s.append(&tx.value);
+			s.append(&tx.data);
+			s.append(&0u64); // v
+			s.append(&U256::zero()); // r
+			s.append(&U256::zero()); // s
+			decode(s.as_raw())
+		};
+		// when
+		let res = txq.add(stx, &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::InvalidSignature);
+	}
+
+	#[test]
+	fn should_accept_transactions_with_matching_chain_id() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_chain_id(Some(5), 0);
+		let keypair = KeyPair::create().unwrap();
+		let tx = new_unsigned_tx(U256::from(123)).sign_with_chain_id(keypair.secret(), Some(5));
+
+		// when
+		let res = txq.add(tx, &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(res.unwrap(), TransactionImportResult::Current);
+	}
+
+	#[test]
+	fn should_reject_transactions_with_different_chain_id() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_chain_id(Some(5), 0);
+		let keypair = KeyPair::create().unwrap();
+		let tx = new_unsigned_tx(U256::from(123)).sign_with_chain_id(keypair.secret(), Some(6));
+
+		// when
+		let res = txq.add(tx, &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::InvalidChainId {
+			expected: Some(5),
+			got: Some(6),
+		});
+		assert_eq!(txq.status().pending, 0);
+	}
+
+	#[test]
+	fn should_accept_unprotected_transactions_only_before_transition() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_chain_id(Some(5), 10);
+		txq.set_current_block(8);
+
+		// when
+		let before = txq.add(new_tx(), &default_nonce, TransactionOrigin::External);
+		txq.set_current_block(9);
+		let after = txq.add(new_tx(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(before.unwrap(), TransactionImportResult::Current);
+		assert_eq!(unwrap_tx_err(after), TransactionImportError::InvalidChainId {
+			expected: Some(5),
+			got: None,
+		});
+	}
+
+	#[test]
+	fn should_import_txs_from_same_sender() {
+		// given
+		let mut txq = TransactionQueue::new();
+
+		let (tx, tx2) = new_txs(U256::from(1));
+
+		// when
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// then
+		let top = txq.top_transactions();
+		assert_eq!(top[0], tx);
+		assert_eq!(top[1], tx2);
+		assert_eq!(top.len(), 2);
+	}
+
+	#[test]
+	fn should_prioritize_local_transactions() {
 		// given
 		let mut txq = TransactionQueue::new();
 		let (tx, tx2) = new_txs(U256::from(1));
@@ -1191,6 +2065,167 @@ mod test {
 		assert_eq!(stats.future, 0);
 	}
 
+	#[test]
+	fn should_return_transaction_details() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let kp = KeyPair::create().unwrap();
+		let secret = kp.secret();
+		let tx = new_unsigned_tx(U256::from(123)).sign(secret);
+		let tx1 = new_unsigned_tx(U256::from(124)).sign(secret);
+		let tx2 = new_unsigned_tx(U256::from(125)).sign(secret);
+		txq.set_current_block(1);
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::Local).unwrap();
+		assert_eq!(txq.transaction_details(&tx2.hash()), Some(TransactionDetails {
+			hash: tx2.hash(),
+			origin: TransactionOrigin::Local,
+			insertion_block: 1,
+			state: TransactionState::Future,
+		}));
+
+		// when
+		txq.set_current_block(2);
+		txq.add(tx1.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.remove_all(tx.sender().unwrap(), tx.nonce + U256::one());
+
+		// then
+		assert_eq!(txq.transaction_details(&tx.hash()), None);
+		assert_eq!(txq.transaction_details(&tx1.hash()), Some(TransactionDetails {
+			hash: tx1.hash(),
+			origin: TransactionOrigin::External,
+			insertion_block: 2,
+			state: TransactionState::Pending,
+		}));
+		assert_eq!(txq.transaction_details(&tx2.hash()), Some(TransactionDetails {
+			hash: tx2.hash(),
+			origin: TransactionOrigin::Local,
+			insertion_block: 1,
+			state: TransactionState::Pending,
+		}));
+	}
+
+	#[test]
+	fn should_evict_and_reject_transactions_of_banned_sender() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_txs(U256::from(1));
+		let sender = tx.sender().unwrap();
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 2);
+
+		// when
+		txq.ban_sender(sender.clone());
+
+		// then
+		assert_eq!(txq.status().pending, 0);
+		assert_eq!(txq.status().future, 0);
+		assert_eq!(txq.banned_senders(), vec![sender.clone()]);
+		assert_eq!(unwrap_tx_err(txq.add(tx.clone(), &default_nonce, TransactionOrigin::External)), TransactionImportError::SenderBanned);
+		assert!(txq.unban_sender(&sender));
+		assert!(!txq.unban_sender(&sender));
+		txq.add(tx, &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 1);
+	}
+
+	#[test]
+	fn should_evict_and_reject_calls_to_banned_recipient_only() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let kp = KeyPair::create().unwrap();
+		let recipient = Address::zero();
+		let create = new_unsigned_tx(U256::from(123)).sign(kp.secret());
+		let mut call = new_unsigned_tx(U256::from(124));
+		call.action = Action::Call(recipient.clone());
+		let call = call.sign(kp.secret());
+		let mut other_create = new_unsigned_tx(U256::from(123));
+		other_create.gas_price = U256::from(2);
+		let other_create = other_create.sign(KeyPair::create().unwrap().secret());
+		txq.add(create.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(call.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 2);
+
+		// when
+		txq.ban_recipient(recipient.clone());
+
+		// then
+		assert_eq!(txq.top_transactions(), vec![create]);
+		assert_eq!(unwrap_tx_err(txq.add(call, &default_nonce, TransactionOrigin::External)), TransactionImportError::RecipientBanned);
+		txq.add(other_create, &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 2);
+		assert_eq!(txq.banned_recipients(), vec![recipient]);
+	}
+
+	#[test]
+	fn should_save_and_load_transactions() {
+		// given
+		let dir = RandomTempPath::create_dir();
+		let path = dir.as_path().join("transactions.rlp");
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_txs(U256::from(2));
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::Local).unwrap();
+
+		// when
+		txq.save_to(&path).unwrap();
+		let mut restored = TransactionQueue::new();
+		let imported = restored.load_from(&path, &default_nonce);
+
+		// then
+		assert_eq!(imported, 2);
+		assert_eq!(restored.top_transactions(), vec![tx.clone()]);
+		assert_eq!(restored.future_transactions(), vec![tx2.clone()]);
+		assert_eq!(restored.transaction_details(&tx.hash()).unwrap().origin, TransactionOrigin::External);
+		assert_eq!(restored.transaction_details(&tx2.hash()).unwrap().origin, TransactionOrigin::Local);
+	}
+
+	#[test]
+	fn should_drop_saved_transactions_with_old_nonce() {
+		// given
+		let dir = RandomTempPath::create_dir();
+		let path = dir.as_path().join("transactions.rlp");
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_txs(U256::from(1));
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::Local).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::Local).unwrap();
+		txq.save_to(&path).unwrap();
+
+		// when
+		let mut restored = TransactionQueue::new();
+		let imported = restored.load_from(&path, &|_: &Address| AccountDetails {
+			nonce: default_nonce_val() + U256::one(),
+			balance: !U256::zero(),
+		});
+
+		// then
+		assert_eq!(imported, 1);
+		assert_eq!(restored.find(&tx.hash()), None);
+		assert_eq!(restored.top_transactions(), vec![tx2]);
+	}
+
+	#[test]
+	fn should_ignore_missing_and_corrupted_saved_transactions() {
+		// given
+		let dir = RandomTempPath::create_dir();
+		let path = dir.as_path().join("transactions.rlp");
+		let mut txq = TransactionQueue::new();
+		txq.add(new_tx(), &default_nonce, TransactionOrigin::Local).unwrap();
+		txq.save_to(&path).unwrap();
+		let mut bytes = Vec::new();
+		File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+		let truncated = dir.as_path().join("truncated.rlp");
+		File::create(&truncated).unwrap().write_all(&bytes[..bytes.len() - 10]).unwrap();
+
+		// when
+		let mut restored = TransactionQueue::new();
+
+		// then
+		assert_eq!(restored.load_from(&dir.as_path().join("missing.rlp"), &default_nonce), 0);
+		assert_eq!(restored.load_from(&truncated, &default_nonce), 0);
+		assert_eq!(restored.status().pending, 0);
+	}
+
 	#[test]
 	fn should_remove_transaction() {
 		// given
@@ -1268,13 +2303,75 @@ mod test {
 
 		// then
 		let t = txq.top_transactions();
-		assert_eq!(unwrap_tx_err(res), TransactionError::LimitReached);
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::LimitReached);
 		assert_eq!(txq.status().pending, 1);
 		assert_eq!(t.len(), 1);
 		assert_eq!(t[0], tx);
 		assert_eq!(txq.last_nonce(&sender), Some(nonce));
 	}
 
+	#[test]
+	fn should_drop_lowest_priority_transactions_when_hitting_the_byte_limit() {
+		// given
+		let mut txq = TransactionQueue::with_limit(100);
+		txq.set_total_byte_limit(25_000);
+		let large_tx = |gas_price: u64| {
+			let keypair = KeyPair::create().unwrap();
+			let mut tx = new_unsigned_tx(default_nonce_val());
+			tx.data = vec![0u8; 10_000];
+			tx.gas_price = U256::from(gas_price);
+			tx.sign(keypair.secret())
+		};
+		let (tx1, tx2, tx3, tx4) = (large_tx(2), large_tx(3), large_tx(4), large_tx(1));
+		txq.add(tx1.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 2);
+		assert!(txq.status().bytes > 20_000);
+
+		// when
+		txq.add(tx3.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		let res = txq.add(tx4.clone(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::LimitReached);
+		assert_eq!(txq.top_transactions(), vec![tx3, tx2]);
+		assert_eq!(txq.status().pending, 2);
+		assert!(txq.status().bytes <= 25_000);
+		assert!(txq.find(&tx1.hash()).is_none());
+	}
+
+	#[test]
+	fn should_apply_byte_limit_to_current_and_future_together() {
+		// given
+		let mut txq = TransactionQueue::with_limit(100);
+		txq.set_total_byte_limit(25_000);
+		let large_tx = |nonce: U256, gas_price: u64| {
+			let keypair = KeyPair::create().unwrap();
+			let mut tx = new_unsigned_tx(nonce);
+			tx.data = vec![0u8; 10_000];
+			tx.gas_price = U256::from(gas_price);
+			tx.sign(keypair.secret())
+		};
+		let future_nonce = default_nonce_val() + U256::one();
+		let (future1, future2) = (large_tx(future_nonce, 2), large_tx(future_nonce, 3));
+		txq.add(future1.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(future2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().future, 2);
+		assert!(txq.status().bytes > 20_000);
+
+		// when
+		let current = large_tx(default_nonce_val(), 1);
+		let res = txq.add(current.clone(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(res.unwrap(), TransactionImportResult::Current);
+		assert_eq!(txq.status().pending, 1);
+		assert_eq!(txq.status().future, 1);
+		assert!(txq.status().bytes <= 25_000);
+		assert!(txq.find(&future1.hash()).is_none());
+		assert!(txq.find(&future2.hash()).is_some());
+	}
+
 	#[test]
 	fn should_return_correct_nonces_when_dropped_because_of_limit() {
 		// given
@@ -1316,6 +2413,78 @@ mod test {
 		assert_eq!(txq.status().future, 1);
 	}
 
+	#[test]
+	fn should_not_import_more_transactions_from_sender_than_limit() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_max_per_sender(2);
+		let kp = KeyPair::create().unwrap();
+		let secret = kp.secret();
+		let tx1 = new_unsigned_tx(U256::from(123)).sign(secret);
+		let tx2 = new_unsigned_tx(U256::from(124)).sign(secret);
+		let tx3 = new_unsigned_tx(U256::from(125)).sign(secret);
+		txq.add(tx1, &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2, &default_nonce, TransactionOrigin::External).unwrap();
+
+		// when
+		let res = txq.add(tx3, &default_nonce, TransactionOrigin::External);
+		let other = txq.add(new_tx(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::TooManyFromSender);
+		assert_eq!(other.unwrap(), TransactionImportResult::Current);
+		assert_eq!(txq.status().pending, 3);
+	}
+
+	#[test]
+	fn should_drop_highest_future_transaction_of_sender_when_limit_reached() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_max_per_sender(2);
+		let kp = KeyPair::create().unwrap();
+		let secret = kp.secret();
+		let tx1 = new_unsigned_tx(U256::from(123)).sign(secret);
+		let tx2 = new_unsigned_tx(U256::from(125)).sign(secret);
+		let tx3 = new_unsigned_tx(U256::from(124)).sign(secret);
+		txq.add(tx1, &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().future, 1);
+
+		// when
+		let res = txq.add(tx3, &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(res.unwrap(), TransactionImportResult::Current);
+		assert_eq!(txq.status().pending, 2);
+		assert_eq!(txq.status().future, 0);
+		assert!(txq.find(&tx2.hash()).is_none());
+	}
+
+	#[test]
+	fn should_not_drop_future_transaction_of_sender_for_rejected_transaction() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_max_per_sender(2);
+		let kp = KeyPair::create().unwrap();
+		let secret = kp.secret();
+		let tx1 = new_unsigned_tx(U256::from(123)).sign(secret);
+		let tx2 = new_unsigned_tx(U256::from(125)).sign(secret);
+		let old = new_unsigned_tx(U256::from(122)).sign(secret);
+		txq.add(tx1, &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// when
+		let duplicate = txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External);
+		let old = txq.add(old, &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(duplicate), TransactionImportError::AlreadyImported);
+		assert_eq!(unwrap_tx_err(old), TransactionImportError::Old);
+		assert_eq!(txq.status().pending, 1);
+		assert_eq!(txq.status().future, 1);
+		assert!(txq.find(&tx2.hash()).is_some());
+	}
+
 	#[test]
 	fn should_drop_transactions_with_old_nonces() {
 		let mut txq = TransactionQueue::new();
@@ -1327,7 +2496,7 @@ mod test {
 		let res = txq.add(tx, &fetch_last_nonce, TransactionOrigin::External);
 
 		// then
-		assert_eq!(unwrap_tx_err(res), TransactionError::Old);
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::Old);
 		let stats = txq.status();
 		assert_eq!(stats.pending, 0);
 		assert_eq!(stats.future, 0);
@@ -1348,7 +2517,7 @@ mod test {
 		let res = txq.add(tx2.clone(), &nonce, TransactionOrigin::External);
 
 		// then
-		assert_eq!(unwrap_tx_err(res), TransactionError::AlreadyImported);
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::AlreadyImported);
 		let stats = txq.status();
 		assert_eq!(stats.future, 1);
 		assert_eq!(stats.pending, 0);
@@ -1451,6 +2620,146 @@ mod test {
 		assert_eq!(txq.top_transactions()[1].gas_price, U256::from(200));
 	}
 
+	fn new_replacement_txs(nonce: U256, gas_price: U256, new_gas_price: U256) -> (SignedTransaction, SignedTransaction) {
+		let keypair = KeyPair::create().unwrap();
+		let mut tx = new_unsigned_tx(nonce);
+		tx.gas_price = gas_price;
+		let mut tx2 = tx.clone();
+		tx2.gas_price = new_gas_price;
+		(tx.sign(keypair.secret()), tx2.sign(keypair.secret()))
+	}
+
+	#[test]
+	fn should_replace_transaction_when_gas_price_bumped_by_exact_threshold() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_replacement_txs(default_nonce_val(), U256::from(100), U256::from(110));
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// when
+		let res = txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(res.unwrap(), TransactionImportResult::Current);
+		assert_eq!(txq.status().pending, 1);
+		assert_eq!(txq.top_transactions()[0], tx2);
+		assert_eq!(txq.find(&tx.hash()), None);
+		assert_eq!(txq.current.by_priority.len(), 1);
+		assert_eq!(txq.by_hash.len(), 1);
+	}
+
+	#[test]
+	fn should_not_replace_transaction_when_gas_price_bump_below_threshold() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_replacement_txs(default_nonce_val(), U256::from(100), U256::from(109));
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// when
+		let res = txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(unwrap_tx_err(res), TransactionImportError::TooCheapToReplace);
+		assert_eq!(txq.status().pending, 1);
+		assert_eq!(txq.top_transactions()[0], tx);
+		assert_eq!(txq.find(&tx2.hash()), None);
+		assert_eq!(txq.by_hash.len(), 1);
+	}
+
+	#[test]
+	fn should_replace_future_transaction_when_gas_price_bumped() {
+		// given
+		let mut txq = TransactionQueue::new();
+		txq.set_minimal_replacement_bump(50);
+		let nonce = default_nonce_val() + U256::one();
+		let (tx, tx2) = new_replacement_txs(nonce, U256::from(100), U256::from(150));
+		let res = txq.add(tx.clone(), &default_nonce, TransactionOrigin::External);
+		assert_eq!(res.unwrap(), TransactionImportResult::Future);
+
+		// when
+		let res = txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External);
+
+		// then
+		assert_eq!(res.unwrap(), TransactionImportResult::Future);
+		assert_eq!(txq.status().future, 1);
+		assert_eq!(txq.status().pending, 0);
+		assert_eq!(txq.find(&tx.hash()), None);
+		assert_eq!(txq.find(&tx2.hash()), Some(tx2));
+		assert_eq!(txq.future.by_priority.len(), 1);
+		assert_eq!(txq.by_hash.len(), 1);
+	}
+
+	#[test]
+	fn should_demote_subsequent_transactions_to_future_when_removing_from_current() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let keypair = KeyPair::create().unwrap();
+		let tx = new_unsigned_tx(default_nonce_val()).sign(keypair.secret());
+		let tx2 = new_unsigned_tx(default_nonce_val() + U256::one()).sign(keypair.secret());
+		let tx3 = new_unsigned_tx(default_nonce_val() + U256::from(2)).sign(keypair.secret());
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx3.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 3);
+
+		// when
+		let removed = txq.remove(&tx.hash());
+
+		// then
+		assert_eq!(removed, Some(tx.clone()));
+		assert_eq!(txq.find(&tx.hash()), None);
+		let stats = txq.status();
+		assert_eq!(stats.pending, 0);
+		assert_eq!(stats.future, 2);
+		assert_eq!(txq.find(&tx3.hash()), Some(tx3));
+		assert_eq!(txq.last_nonce(&tx.sender().unwrap()), None);
+
+		// and when the gap is filled again
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().pending, 3);
+		assert_eq!(txq.status().future, 0);
+	}
+
+	#[test]
+	fn should_keep_lower_nonces_in_current_when_removing_from_current() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_txs(U256::one());
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// when
+		let removed = txq.remove(&tx2.hash());
+
+		// then
+		assert_eq!(removed, Some(tx2));
+		let stats = txq.status();
+		assert_eq!(stats.pending, 1);
+		assert_eq!(stats.future, 0);
+		assert_eq!(txq.top_transactions(), vec![tx.clone()]);
+		assert_eq!(txq.last_nonce(&tx.sender().unwrap()), Some(tx.nonce));
+	}
+
+	#[test]
+	fn should_remove_future_transaction_without_affecting_current() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx, tx2) = new_txs(U256::from(2));
+		txq.add(tx.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		assert_eq!(txq.status().future, 1);
+
+		// when
+		let removed = txq.remove(&tx2.hash());
+
+		// then
+		assert_eq!(removed, Some(tx2.clone()));
+		let stats = txq.status();
+		assert_eq!(stats.pending, 1);
+		assert_eq!(stats.future, 0);
+		assert_eq!(txq.remove(&tx2.hash()), None);
+	}
+
 	#[test]
 	fn should_recalculate_height_when_removing_from_future() {
 		// given
@@ -1540,6 +2849,79 @@ mod test {
 		assert_eq!(txq.last_nonce(&sender), None);
 	}
 
+	#[test]
+	fn should_return_future_transactions_ordered_by_nonce() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let keypair = KeyPair::create().unwrap();
+		let secret = &keypair.secret();
+		let nonce = default_nonce_val();
+		let tx0 = new_unsigned_tx(nonce).sign(secret);
+		let tx3 = new_unsigned_tx(nonce + U256::from(3)).sign(secret);
+		let tx2 = new_unsigned_tx(nonce + U256::from(2)).sign(secret);
+
+		// when
+		txq.add(tx3.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx0.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+		txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap();
+
+		// then
+		assert_eq!(txq.top_transactions(), vec![tx0]);
+		assert_eq!(txq.future_transactions(), vec![tx2, tx3]);
+	}
+
+	#[test]
+	fn should_return_pending_transactions_grouped_by_sender() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx1, tx2) = new_txs_with_gas_price_diff(U256::from(1), U256::from(10));
+		let (tx3, tx4) = new_txs(U256::from(1));
+		let future = new_txs(U256::from(2)).1;
+
+		// when
+		for tx in vec![tx2.clone(), tx4.clone(), tx1.clone(), tx3.clone(), future.clone()] {
+			txq.add(tx, &default_nonce, TransactionOrigin::External).unwrap();
+		}
+
+		// then
+		let by_sender = txq.pending_by_sender();
+		assert_eq!(by_sender.len(), 2);
+		assert_eq!(by_sender[&tx1.sender().unwrap()], vec![tx1, tx2]);
+		assert_eq!(by_sender[&tx3.sender().unwrap()], vec![tx3, tx4]);
+	}
+
+	#[test]
+	fn should_remove_old_transactions() {
+		// given
+		let mut txq = TransactionQueue::new();
+		let (tx1, tx2) = new_txs(U256::from(2));
+		// Future transaction with a nonce gap that is never going to be filled
+		txq.set_current_block(10);
+		assert_eq!(txq.add(tx2.clone(), &default_nonce, TransactionOrigin::External).unwrap(), TransactionImportResult::Future);
+		txq.set_current_block(500);
+		assert_eq!(txq.add(tx1.clone(), &default_nonce, TransactionOrigin::External).unwrap(), TransactionImportResult::Current);
+
+		// when
+		txq.set_current_block(610);
+		txq.remove_old(600);
+
+		// then
+		assert_eq!(txq.status().pending, 1);
+		assert_eq!(txq.status().future, 1);
+
+		// when
+		txq.set_current_block(611);
+		txq.remove_old(600);
+
+		// then
+		assert_eq!(txq.status().pending, 1);
+		assert_eq!(txq.status().future, 0);
+		assert!(txq.find(&tx2.hash()).is_none());
+		assert!(txq.find(&tx1.hash()).is_some());
+		assert_eq!(txq.by_hash.len(), 1);
+		assert!(txq.future.by_address.is_empty());
+	}
+
 	#[test]
 	fn should_return_true_if_there_is_local_transaction_pending() {
 		// given
@@ -0,0 +1,133 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Bounded history of sealing work given out to miners.
+
+use std::collections::{HashMap, VecDeque};
+use util::H256;
+
+/// Work packages keyed by their pow hash.
+///
+/// Keeps at most `limit` packages, the oldest inserted ones are pruned first.
+pub struct WorkHistory<T> {
+	work: HashMap<H256, T>,
+	order: VecDeque<H256>,
+	limit: usize,
+}
+
+impl<T> WorkHistory<T> {
+	/// Creates history keeping at most `limit` work packages.
+	pub fn new(limit: usize) -> Self {
+		WorkHistory {
+			work: HashMap::new(),
+			order: VecDeque::new(),
+			limit: limit,
+		}
+	}
+
+	/// Inserts work package unless package with the same pow hash is already known.
+	pub fn insert(&mut self, pow_hash: H256, work: T) {
+		if self.limit == 0 || self.work.contains_key(&pow_hash) {
+			return;
+		}
+		while self.order.len() >= self.limit {
+			if let Some(oldest) = self.order.pop_front() {
+				self.work.remove(&oldest);
+			}
+		}
+		self.order.push_back(pow_hash.clone());
+		self.work.insert(pow_hash, work);
+	}
+
+	/// Removes and returns work package with given pow hash.
+	pub fn take(&mut self, pow_hash: &H256) -> Option<T> {
+		let work = self.work.remove(pow_hash);
+		if work.is_some() {
+			self.order.retain(|h| h != pow_hash);
+		}
+		work
+	}
+
+	/// Returns work package with given pow hash.
+	pub fn get(&self, pow_hash: &H256) -> Option<&T> {
+		self.work.get(pow_hash)
+	}
+
+	/// Returns `true` if `predicate` holds for any of the work packages.
+	pub fn any<P>(&self, predicate: P) -> bool where P: Fn(&T) -> bool {
+		self.work.values().any(predicate)
+	}
+
+	/// Keeps only work packages for which `predicate` returns `true`.
+	pub fn retain<P>(&mut self, predicate: P) where P: Fn(&T) -> bool {
+		let work = &mut self.work;
+		self.order.retain(|h| {
+			let keep = work.get(h).map_or(false, |w| predicate(w));
+			if !keep {
+				work.remove(h);
+			}
+			keep
+		});
+	}
+
+	/// Removes all work packages.
+	pub fn clear(&mut self) {
+		self.work.clear();
+		self.order.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use util::H256;
+	use super::WorkHistory;
+
+	#[test]
+	fn should_prune_oldest_work() {
+		// given
+		let mut history = WorkHistory::new(2);
+
+		// when
+		history.insert(H256::from(1), 1);
+		history.insert(H256::from(2), 2);
+		history.insert(H256::from(1), 10);
+		history.insert(H256::from(3), 3);
+
+		// then
+		assert_eq!(history.take(&H256::from(1)), None);
+		assert_eq!(history.take(&H256::from(2)), Some(2));
+		assert_eq!(history.take(&H256::from(2)), None);
+		assert_eq!(history.take(&H256::from(3)), Some(3));
+	}
+
+	#[test]
+	fn should_retain_matching_work() {
+		// given
+		let mut history = WorkHistory::new(5);
+		for i in 1..5 {
+			history.insert(H256::from(i), i);
+		}
+
+		// when
+		history.retain(|w| w % 2 == 0);
+
+		// then
+		assert_eq!(history.take(&H256::from(1)), None);
+		assert_eq!(history.take(&H256::from(3)), None);
+		assert_eq!(history.take(&H256::from(2)), Some(2));
+		assert_eq!(history.take(&H256::from(4)), Some(4));
+	}
+}
@@ -0,0 +1,211 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Push notifications of new sealing work.
+//!
+//! Every new work package is posted (as JSON with `pow_hash`, `seed_hash` and `target`)
+//! to configured URLs, so miners don't need to poll `eth_getWork`.
+//! Notifications are delivered by a background thread and never block sealing.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+use util::{H256, U256, FixedHash};
+use ethcore::ethereum::Ethash;
+use ethcore::header::BlockNumber;
+use ethash::SeedHashCompute;
+
+/// Number of retries of failed notification.
+const NOTIFY_RETRIES: u32 = 2;
+/// Delay before the first retry. Doubled with every subsequent retry.
+const NOTIFY_RETRY_DELAY_MS: u64 = 200;
+/// Timeout of single HTTP request.
+const NOTIFY_TIMEOUT_MS: u64 = 2000;
+
+/// Delivers notification body to given URL.
+pub trait WorkPoster: Send + Sync {
+	/// Posts `body` to `url`. Returns description of the error on failure.
+	fn post(&self, url: &str, body: &str) -> Result<(), String>;
+}
+
+/// Posts notifications with plain HTTP/1.1 requests. Only `http://` URLs are supported.
+pub struct HttpWorkPoster;
+
+/// Splits `http://host[:port]/path` into address to connect to, `Host` header and path.
+fn parse_http_url(url: &str) -> Result<(String, String, String), String> {
+	if !url.starts_with("http://") {
+		return Err(format!("Unsupported work notification URL: {}", url));
+	}
+	let url = &url["http://".len()..];
+	let (host, path) = match url.find('/') {
+		Some(pos) => (&url[..pos], &url[pos..]),
+		None => (url, "/"),
+	};
+	if host.is_empty() {
+		return Err(format!("Missing host in work notification URL: {}", url));
+	}
+	let address = match host.contains(':') {
+		true => host.to_owned(),
+		false => format!("{}:80", host),
+	};
+	Ok((address, host.to_owned(), path.to_owned()))
+}
+
+impl WorkPoster for HttpWorkPoster {
+	fn post(&self, url: &str, body: &str) -> Result<(), String> {
+		let (address, host, path) = try!(parse_http_url(url));
+		let timeout = Some(Duration::from_millis(NOTIFY_TIMEOUT_MS));
+		let mut stream = try!(TcpStream::connect(&*address).map_err(|e| format!("{}", e)));
+		try!(stream.set_read_timeout(timeout).and_then(|_| stream.set_write_timeout(timeout)).map_err(|e| format!("{}", e)));
+
+		let request = format!(
+			"POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			path, host, body.len(), body
+		);
+		try!(stream.write_all(request.as_bytes()).map_err(|e| format!("{}", e)));
+
+		// We only care about the status code: `HTTP/1.1 200 OK`
+		let mut status = [0u8; 12];
+		try!(stream.read_exact(&mut status).map_err(|e| format!("{}", e)));
+		match status[9] {
+			b'2' => Ok(()),
+			_ => Err(format!("Unexpected response: {}", String::from_utf8_lossy(&status))),
+		}
+	}
+}
+
+struct WorkPackage {
+	pow_hash: H256,
+	difficulty: U256,
+	number: BlockNumber,
+}
+
+/// Returns JSON notification body of work package.
+fn notification_body(pow_hash: &H256, seed_hash: &H256, target: &H256) -> String {
+	format!(r#"{{"pow_hash":"0x{}","seed_hash":"0x{}","target":"0x{}"}}"#, pow_hash.hex(), seed_hash.hex(), target.hex())
+}
+
+/// Posts every new work package to configured URLs in background.
+pub struct WorkNotifier {
+	sender: Sender<WorkPackage>,
+}
+
+impl WorkNotifier {
+	/// Creates notifier posting to `urls` using `poster`.
+	pub fn new(urls: Vec<String>, poster: Arc<WorkPoster>) -> Self {
+		let (sender, receiver) = channel::<WorkPackage>();
+		thread::Builder::new().name("work-notify".to_owned()).spawn(move || {
+			let seed_compute = SeedHashCompute::new();
+			// Ends when notifier is dropped.
+			for work in receiver {
+				let seed_hash = H256::from_slice(&seed_compute.get_seedhash(work.number));
+				let target = Ethash::difficulty_to_boundary(&work.difficulty);
+				let body = notification_body(&work.pow_hash, &seed_hash, &target);
+				for url in &urls {
+					post_with_retries(&*poster, url, &body);
+				}
+			}
+		}).expect("Error creating work notification thread");
+
+		WorkNotifier {
+			sender: sender,
+		}
+	}
+
+	/// Schedules notification of new work package.
+	pub fn notify(&self, pow_hash: H256, difficulty: U256, number: BlockNumber) {
+		let work = WorkPackage {
+			pow_hash: pow_hash,
+			difficulty: difficulty,
+			number: number,
+		};
+		if self.sender.send(work).is_err() {
+			warn!(target: "miner", "Work notification thread is not running.");
+		}
+	}
+}
+
+fn post_with_retries(poster: &WorkPoster, url: &str, body: &str) {
+	let mut delay = NOTIFY_RETRY_DELAY_MS;
+	for attempt in 0..(NOTIFY_RETRIES + 1) {
+		if attempt > 0 {
+			thread::sleep(Duration::from_millis(delay));
+			delay *= 2;
+		}
+		match poster.post(url, body) {
+			Ok(()) => return,
+			Err(e) => warn!(target: "miner", "Error sending work notification to {} (attempt {}): {}", url, attempt + 1, e),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+	use std::sync::mpsc::{channel, Sender};
+	use util::{H256, U256};
+	use super::{WorkPoster, WorkNotifier, parse_http_url};
+
+	struct FailingPoster {
+		failures: Mutex<usize>,
+		posted: Mutex<Sender<(String, String)>>,
+	}
+
+	impl WorkPoster for FailingPoster {
+		fn post(&self, url: &str, body: &str) -> Result<(), String> {
+			self.posted.lock().unwrap().send((url.to_owned(), body.to_owned())).unwrap();
+			let mut failures = self.failures.lock().unwrap();
+			match *failures {
+				0 => Ok(()),
+				_ => {
+					*failures -= 1;
+					Err("Connection refused".to_owned())
+				},
+			}
+		}
+	}
+
+	#[test]
+	fn should_parse_http_urls() {
+		assert_eq!(parse_http_url("http://localhost:3000/work"), Ok(("localhost:3000".to_owned(), "localhost:3000".to_owned(), "/work".to_owned())));
+		assert_eq!(parse_http_url("http://miner"), Ok(("miner:80".to_owned(), "miner".to_owned(), "/".to_owned())));
+		assert!(parse_http_url("https://miner/").is_err());
+		assert!(parse_http_url("http:///").is_err());
+	}
+
+	#[test]
+	fn should_post_work_to_all_urls_and_retry_failures() {
+		// given
+		let (sender, receiver) = channel();
+		let poster = Arc::new(FailingPoster { failures: Mutex::new(1), posted: Mutex::new(sender) });
+		let notifier = WorkNotifier::new(vec!["http://a/".to_owned(), "http://b/".to_owned()], poster);
+
+		// when
+		notifier.notify(H256::from(1), U256::from(2), 0);
+
+		// then
+		let body = r#"{"pow_hash":"0x0000000000000000000000000000000000000000000000000000000000000001","seed_hash":"0x0000000000000000000000000000000000000000000000000000000000000000","target":"0x8000000000000000000000000000000000000000000000000000000000000000"}"#;
+		let posted: Vec<_> = (0..3).map(|_| receiver.recv().unwrap()).collect();
+		assert_eq!(posted, vec![
+			("http://a/".to_owned(), body.to_owned()),
+			("http://a/".to_owned(), body.to_owned()),
+			("http://b/".to_owned(), body.to_owned()),
+		]);
+	}
+}
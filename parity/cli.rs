@@ -26,6 +26,7 @@ Usage:
   parity account (new | list) [options]
   parity import [ <file> ] [options]
   parity export [ <file> ] [options]
+  parity index-uncles [options]
   parity [options]
 
 Protocol Options:
@@ -63,6 +64,11 @@ Networking Options:
   --no-discovery           Disable new peer discovery.
   --node-key KEY           Specify node secret key, either as 64-character hex
                            string or input to SHA3 operation.
+  --clock-skew SECS        Warn when local clock differs from the time
+                           reported in blocks of peers by more than SECS
+                           seconds [default: 30].
+  --no-serve-receipts      Do not serve block receipts to peers. Only eth/62
+                           is advertised then.
 
 API and Console Options:
   --jsonrpc-off            Disable the JSON-RPC API server.
@@ -75,14 +81,43 @@ API and Console Options:
   --jsonrpc-apis APIS      Specify the APIs available through the JSONRPC
                            interface. APIS is a comma-delimited list of API
                            name. Possible name are web3, eth, net, personal,
-                           ethcore, traces.
+                           ethcore, traces. Use safe for all APIs except
+                           personal or all for every API.
                            [default: web3,eth,net,personal,ethcore,traces].
+  --jsonrpc-read-only      Reject all methods which mutate node state or use
+                           keys (regardless of enabled APIs). Applies to the
+                           JSON-RPC, WebSockets and IPC servers and the WebApps
+                           RPC endpoint.
+  --jsonrpc-max-batch NUM  Reject JSON-RPC batch requests of more than NUM
+                           calls, 0 for no limit [default: 1024].
+  --jsonrpc-timeout MS     Abort eth_call and eth_estimateGas executions
+                           running longer than MS milliseconds, 0 for no
+                           limit [default: 5000].
 
   --ipc-off                Disable JSON-RPC over IPC service.
   --ipc-path PATH          Specify custom path for JSON-RPC over IPC service
                            [default: $HOME/.parity/jsonrpc.ipc].
   --ipc-apis APIS          Specify custom API set available via JSON-RPC over
-                           IPC [default: web3,eth,net,personal,ethcore].
+                           IPC (see --jsonrpc-apis for possible names)
+                           [default: web3,eth,net,personal,ethcore].
+  --ipc-safe-group GID     Allow members of group GID to connect to the IPC
+                           service with access to web3, eth and net APIs
+                           only. Other users are always rejected.
+
+  --ws                     Enable JSON-RPC over WebSockets service.
+  --ws-interface IP        Specify the hostname portion of the WebSockets
+                           JSON-RPC server, IP should be an interface's IP
+                           address, or all (all interfaces) or local
+                           [default: local].
+  --ws-port PORT           Specify the port portion of the WebSockets
+                           JSON-RPC server [default: 8546].
+  --ws-apis APIS           Specify the APIs available through the WebSockets
+                           interface (see --jsonrpc-apis for possible names)
+                           [default: web3,eth,net,ethcore,traces].
+  --ws-origins URL         Comma-delimited list of origins allowed to connect
+                           to the WebSockets server. Use * to allow any
+                           origin. Only local origins (e.g. http://localhost)
+                           are allowed if not given.
 
   --webapp-off             Disable the web applications server (e.g. status
                            page).
@@ -97,10 +132,31 @@ API and Console Options:
                            asked for password on startup.
   --webapp-pass PASSWORD   Specify password for WebApps server. Use only in
                            conjunction with --webapp-user.
+  --server-threads NUM     Number of worker threads shared by the JSON-RPC and
+                           WebApps servers. Servers use their own listener
+                           threads when set to 0 [default: 4].
+  --server-queue NUM       Number of requests which may wait for a free server
+                           thread. Further requests are rejected with
+                           503 Service Unavailable [default: 64].
+  --server-limit NUM       Maximal number of requests of a single server
+                           being handled at once [default: 48].
 
 Sealing/Mining Options:
   --force-sealing          Force the node to author new blocks as if it were
                            always sealing/mining.
+  --reseal-on-txs SET      Specify which transactions should force the node
+                           to reseal a block. SET is one of:
+                           none - never reseal on new transactions;
+                           own - reseal only on a new local transaction;
+                           ext - reseal only on a new external transaction;
+                           all - reseal on all new transactions [default: all].
+  --reseal-min-period MS   Specify the minimum time between reseals from
+                           incoming transactions. MS is time measured in
+                           milliseconds [default: 2000].
+  --work-timeout SECS      Stop preparing new blocks for sealing if no sealing
+                           work was requested for SECS seconds. Preparing is
+                           resumed on the next request. 0 disables the timeout
+                           [default: 0].
   --usd-per-tx USD         Amount of USD to be paid for a basic transaction
                            [default: 0.005]. The minimum gas price is set
                            accordingly.
@@ -108,8 +164,13 @@ Sealing/Mining Options:
                            amount in USD, a web service or 'auto' to use each
                            web service in turn and fallback on the last known
                            good value [default: auto].
+  --gas-price-pct PCT      Percentile of gas prices in recent blocks suggested
+                           for new transactions [default: 50].
   --gas-floor-target GAS   Amount of gas per block to target when sealing a new
                            block [default: 4712388].
+  --tx-gas-limit GAS       Maximal amount of gas of single transaction accepted
+                           to the queue. If not given, gas limit of the latest
+                           block is used.
   --author ADDRESS         Specify the block author (aka "coinbase") address
                            for sending block rewards from sealed blocks
                            [default: 0037a6b811ffeb6e072da21179d11b1406371c63].
@@ -117,6 +178,22 @@ Sealing/Mining Options:
                            more than 32 characters.
   --tx-limit LIMIT         Limit of transactions kept in the queue (waiting to
                            be included in next block) [default: 1024].
+  --tx-max-age BLOCKS      Drop transactions which stayed in the queue for more
+                           than BLOCKS blocks [default: 600].
+  --tx-queue-persist       Save transactions in the queue on exit and restore
+                           them on the next start.
+  --bundle-max-age BLOCKS  Drop transaction bundles which were not included
+                           within BLOCKS blocks [default: 50].
+  --no-seal-on-clock-skew  Stop authoring blocks while local clock is skewed
+                           (see --clock-skew).
+  --notify-work URLS       URLs to which work package notifications are pushed.
+                           URLS should be a comma-delimited list of HTTP URLs.
+  --legacy-get-work        Return only the pow hash, seed hash and target from
+                           eth_getWork, omitting the block number expected by
+                           pool software.
+  --service-senders ADDRS  Accept transactions of ADDRS regardless of their gas
+                           price and include them before all other
+                           transactions. ADDRS is a comma-delimited list.
 
 Footprint Options:
   --tracing BOOL           Indicates if full transaction tracing should be
@@ -124,6 +201,12 @@ Footprint Options:
                            with tracing enabled. BOOL may be one of auto, on,
                            off. auto uses last used value of this option (off
                            if it does not exist) [default: auto].
+  --tracing-reindex        Rebuild blooms of existing traces. Required after
+                           blooms configuration of the traces database has
+                           changed.
+  --index-uncles           Index uncles of imported blocks, so they can be
+                           looked up by hash. Uncles of blocks imported
+                           before can be indexed with parity index-uncles.
   --pruning METHOD         Configure pruning of the state/storage trie. METHOD
                            may be one of auto, archive, fast, basic, light:
                            archive - keep all state trie data. No pruning.
@@ -142,13 +225,16 @@ Footprint Options:
                            queue [default: 52428800].
   --cache MEGABYTES        Set total amount of discretionary memory to use for
                            the entire system, overrides other cache and queue
-                           options.
+                           options. Initially half of it goes to the blockchain
+                           cache and quarter to each of the trace cache and the
+                           block queue, then it's moved to caches which need it.
 
 Import/Export Options:
-  --from BLOCK             Export from block BLOCK, which may be an index or
-                           hash [default: 1].
-  --to BLOCK               Export to (including) block BLOCK, which may be an
-                           index, hash or 'latest' [default: latest].
+  --from BLOCK             Export (or index uncles) from block BLOCK, which
+                           may be an index or hash [default: 1].
+  --to BLOCK               Export (or index uncles) to (including) block BLOCK,
+                           which may be an index, hash or 'latest'
+                           [default: latest].
   --format FORMAT          For import/export in given format. FORMAT must be
                            one of 'hex' and 'binary'.
 
@@ -199,6 +285,7 @@ pub struct Args {
 	pub cmd_list: bool,
 	pub cmd_export: bool,
 	pub cmd_import: bool,
+	pub cmd_index_uncles: bool,
 	pub arg_pid_file: String,
 	pub arg_file: Option<String>,
 	pub flag_chain: String,
@@ -214,11 +301,15 @@ pub struct Args {
 	pub flag_network_id: Option<String>,
 	pub flag_pruning: String,
 	pub flag_tracing: String,
+	pub flag_tracing_reindex: bool,
+	pub flag_index_uncles: bool,
 	pub flag_port: u16,
 	pub flag_peers: usize,
 	pub flag_no_discovery: bool,
 	pub flag_nat: String,
 	pub flag_node_key: Option<String>,
+	pub flag_clock_skew: u64,
+	pub flag_no_serve_receipts: bool,
 	pub flag_cache_pref_size: usize,
 	pub flag_cache_max_size: usize,
 	pub flag_queue_max_size: usize,
@@ -227,21 +318,45 @@ pub struct Args {
 	pub flag_jsonrpc_port: u16,
 	pub flag_jsonrpc_cors: Option<String>,
 	pub flag_jsonrpc_apis: String,
+	pub flag_jsonrpc_read_only: bool,
+	pub flag_jsonrpc_max_batch: usize,
+	pub flag_jsonrpc_timeout: u64,
 	pub flag_ipc_off: bool,
 	pub flag_ipc_path: String,
 	pub flag_ipc_apis: String,
+	pub flag_ipc_safe_group: Option<u32>,
+	pub flag_ws: bool,
+	pub flag_ws_interface: String,
+	pub flag_ws_port: u16,
+	pub flag_ws_apis: String,
+	pub flag_ws_origins: Option<String>,
 	pub flag_webapp_off: bool,
 	pub flag_webapp_port: u16,
 	pub flag_webapp_interface: String,
 	pub flag_webapp_user: Option<String>,
 	pub flag_webapp_pass: Option<String>,
+	pub flag_server_threads: usize,
+	pub flag_server_queue: usize,
+	pub flag_server_limit: usize,
 	pub flag_force_sealing: bool,
+	pub flag_reseal_on_txs: String,
+	pub flag_reseal_min_period: u64,
+	pub flag_work_timeout: u64,
 	pub flag_author: String,
 	pub flag_usd_per_tx: String,
 	pub flag_usd_per_eth: String,
+	pub flag_gas_price_pct: usize,
 	pub flag_gas_floor_target: String,
+	pub flag_tx_gas_limit: Option<String>,
 	pub flag_extra_data: Option<String>,
 	pub flag_tx_limit: usize,
+	pub flag_tx_max_age: u64,
+	pub flag_tx_queue_persist: bool,
+	pub flag_bundle_max_age: u64,
+	pub flag_no_seal_on_clock_skew: bool,
+	pub flag_notify_work: Option<String>,
+	pub flag_legacy_get_work: bool,
+	pub flag_service_senders: Option<String>,
 	pub flag_logging: Option<String>,
 	pub flag_version: bool,
 	pub flag_from: String,
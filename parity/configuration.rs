@@ -19,6 +19,7 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::net::{SocketAddr, IpAddr};
 use std::path::PathBuf;
+use std::time::Duration;
 use cli::{USAGE, Args};
 use docopt::Docopt;
 
@@ -26,12 +27,15 @@ use die::*;
 use util::*;
 use util::keys::store::{ImportKeySet, AccountService};
 use util::network_settings::NetworkSettings;
-use ethcore::client::{append_path, get_db_path, ClientConfig, Switch, VMType};
+use util::server_pool::ServerPoolConfig;
+use ethcore::client::{append_path, get_db_path, ClientConfig, Switch, VMType, MemoryBudgetConfig};
 use ethcore::ethereum;
 use ethcore::spec::Spec;
 use ethsync::SyncConfig;
+use ethminer::ResealPolicy;
 use price_info::PriceInfo;
-use rpc::IpcConfiguration;
+use rpc::{IpcConfiguration, WsConfiguration};
+use ethcore_rpc::ipc_access::IpcAccessPolicy;
 
 pub struct Configuration {
 	pub args: Args
@@ -79,6 +83,12 @@ impl Configuration {
 		})
 	}
 
+	pub fn tx_gas_limit(&self) -> Option<U256> {
+		self.args.flag_tx_gas_limit.as_ref().map(|d| U256::from_dec_str(d).unwrap_or_else(|_| {
+			die!("{}: Invalid transaction gas limit given. Must be a decimal unsigned 256-bit number.", d)
+		}))
+	}
+
 	pub fn gas_price(&self) -> U256 {
 		match self.args.flag_gasprice.as_ref() {
 			Some(d) => {
@@ -112,6 +122,20 @@ impl Configuration {
 		}
 	}
 
+	pub fn reseal_policy(&self) -> ResealPolicy {
+		let (own, external) = match self.args.flag_reseal_on_txs.as_str() {
+			"none" => (false, false),
+			"own" => (true, false),
+			"ext" => (false, true),
+			"all" => (true, true),
+			x => die!("{}: Invalid value for --reseal-on-txs option. Use --help for more information.", x)
+		};
+		ResealPolicy {
+			reseal_on_own_tx: own,
+			reseal_on_external_tx: external,
+		}
+	}
+
 	pub fn extra_data(&self) -> Bytes {
 		match self.args.flag_extradata.as_ref().or(self.args.flag_extra_data.as_ref()) {
 			Some(ref x) if x.len() <= 32 => x.as_bytes().to_owned(),
@@ -139,6 +163,18 @@ impl Configuration {
 		}
 	}
 
+	pub fn work_notify(&self) -> Vec<String> {
+		self.args.flag_notify_work.as_ref().map_or_else(Vec::new, |s| s.split(',').map(|s| s.to_owned()).collect())
+	}
+
+	pub fn service_senders(&self) -> Vec<Address> {
+		self.args.flag_service_senders.as_ref().map_or_else(Vec::new, |s| s.split(',').map(|d| {
+			Address::from_str(clean_0x(d)).unwrap_or_else(|_| {
+				die!("{}: Invalid address for --service-senders. Must be 40 hex characters, with or without the 0x at the beginning.", d)
+			})
+		}).collect())
+	}
+
 	pub fn init_nodes(&self, spec: &Spec) -> Vec<String> {
 		match self.args.flag_bootnodes {
 			Some(ref x) if !x.is_empty() => x.split(',').map(|s| {
@@ -185,7 +221,7 @@ impl Configuration {
 		let mut latest_era = None;
 		let jdb_types = [journaldb::Algorithm::Archive, journaldb::Algorithm::EarlyMerge, journaldb::Algorithm::OverlayRecent, journaldb::Algorithm::RefCounted];
 		for i in jdb_types.into_iter() {
-			let db = journaldb::new(&append_path(&get_db_path(Path::new(&self.path()), *i, spec.genesis_header().hash()), "state"), *i);
+			let db = journaldb::new(&append_path(&get_db_path(Path::new(&self.path()), *i, spec.genesis_header().hash()), "state"), *i, DatabaseConfig::default());
 			trace!(target: "parity", "Looking for best DB: {} at {:?}", i, db.latest_era());
 			match (latest_era, db.latest_era()) {
 				(Some(best), Some(this)) if best >= this => {}
@@ -204,8 +240,7 @@ impl Configuration {
 
 		match self.args.flag_cache {
 			Some(mb) => {
-				client_config.blockchain.max_cache_size = mb * 1024 * 1024;
-				client_config.blockchain.pref_cache_size = client_config.blockchain.max_cache_size * 3 / 4;
+				client_config.memory_budget = Some(MemoryBudgetConfig::new(mb * 1024 * 1024));
 			}
 			None => {
 				client_config.blockchain.pref_cache_size = self.args.flag_cache_pref_size;
@@ -219,6 +254,8 @@ impl Configuration {
 			"off" => Switch::Off,
 			_ => { die!("Invalid tracing method given!") }
 		};
+		client_config.tracing.reindex = self.args.flag_tracing_reindex;
+		client_config.blockchain.index_uncles = self.args.flag_index_uncles;
 
 		client_config.pruning = match self.args.flag_pruning.as_str() {
 			"archive" => journaldb::Algorithm::Archive,
@@ -244,6 +281,9 @@ impl Configuration {
 		sync_config.network_id = self.args.flag_network_id.as_ref().or(self.args.flag_networkid.as_ref()).map_or(spec.network_id(), |id| {
 			U256::from_str(id).unwrap_or_else(|_| die!("{}: Invalid index given with --network-id/--networkid", id))
 		});
+		sync_config.clock_skew_threshold = self.args.flag_clock_skew;
+		sync_config.pause_sealing_on_clock_skew = self.args.flag_no_seal_on_clock_skew;
+		sync_config.serve_receipts = !self.args.flag_no_serve_receipts;
 		sync_config
 	}
 
@@ -284,6 +324,20 @@ impl Configuration {
 		cors.map_or_else(Vec::new, |c| c.split(',').map(|s| s.to_owned()).collect())
 	}
 
+	pub fn rpc_max_batch_size(&self) -> Option<usize> {
+		match self.args.flag_jsonrpc_max_batch {
+			0 => None,
+			size => Some(size),
+		}
+	}
+
+	pub fn rpc_call_timeout(&self) -> Option<Duration> {
+		match self.args.flag_jsonrpc_timeout {
+			0 => None,
+			timeout => Some(Duration::from_millis(timeout)),
+		}
+	}
+
 	fn geth_ipc_path() -> String {
 		path::ethereum::with_default("geth.ipc").to_str().unwrap().to_owned()
 	}
@@ -292,11 +346,39 @@ impl Configuration {
 		self.args.flag_keys_iterations
 	}
 
-	pub fn ipc_settings(&self) -> IpcConfiguration {
-		IpcConfiguration {
+	pub fn ipc_settings(&self) -> Result<IpcConfiguration, String> {
+		Ok(IpcConfiguration {
 			enabled: !(self.args.flag_ipcdisable || self.args.flag_ipc_off),
 			socket_addr: self.ipc_path(),
-			apis: self.args.flag_ipcapi.clone().unwrap_or(self.args.flag_ipc_apis.clone()),
+			apis: try!(self.args.flag_ipcapi.clone().unwrap_or(self.args.flag_ipc_apis.clone()).parse()),
+			access_policy: IpcAccessPolicy {
+				safe_gid: self.args.flag_ipc_safe_group,
+				..IpcAccessPolicy::owner_only()
+			},
+			read_only: self.args.flag_jsonrpc_read_only,
+		})
+	}
+
+	pub fn ws_settings(&self) -> Result<WsConfiguration, String> {
+		Ok(WsConfiguration {
+			enabled: self.args.flag_ws,
+			interface: self.args.flag_ws_interface.clone(),
+			port: self.args.flag_ws_port,
+			apis: try!(self.args.flag_ws_apis.parse()),
+			origins: self.args.flag_ws_origins.as_ref().map_or_else(Vec::new, |o| o.split(',').map(|s| s.to_owned()).collect()),
+			read_only: self.args.flag_jsonrpc_read_only,
+		})
+	}
+
+	pub fn server_pool_config(&self) -> Option<ServerPoolConfig> {
+		match self.args.flag_server_threads {
+			0 => None,
+			threads => Some(ServerPoolConfig {
+				threads: threads,
+				queue_size: self.args.flag_server_queue,
+				server_limit: self.args.flag_server_limit,
+				..Default::default()
+			}),
 		}
 	}
 
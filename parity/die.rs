@@ -18,6 +18,7 @@ use std;
 use ethcore;
 use ethcore::client::Error as ClientError;
 use util::UtilError;
+use util::network::NetworkError;
 use std::process::exit;
 
 #[macro_export]
@@ -30,6 +31,9 @@ pub fn die_with_error(module: &'static str, e: ethcore::error::Error) -> ! {
 
 	match e {
 		Error::Util(UtilError::StdIo(e)) => die_with_io_error(module, e),
+		Error::Util(UtilError::Network(NetworkError::AddressInUse(address))) => {
+			die!("{}: Address {} is already in use. Please make sure that nothing is listening on the same port or try using a different one.", module, address)
+		},
 		Error::Client(ClientError::Trace(e)) => die_with_message(&format!("{}", e)),
 		_ => {
 			trace!(target: module, "{:?}", e);
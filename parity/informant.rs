@@ -15,14 +15,15 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 extern crate ansi_term;
-use self::ansi_term::Colour::{White, Yellow, Green, Cyan, Blue, Purple};
+use self::ansi_term::Colour::{White, Yellow, Green, Cyan, Blue, Purple, Red};
 use self::ansi_term::Style;
 
 use std::time::{Instant, Duration};
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 use std::ops::{Deref, DerefMut};
 use ethsync::{EthSync, SyncProvider};
 use util::Uint;
+use util::server_pool::ServerPool;
 use ethcore::client::*;
 use number_prefix::{binary_prefix, Standalone, Prefixed};
 
@@ -32,6 +33,8 @@ pub struct Informant {
 	report: RwLock<Option<ClientReport>>,
 	last_tick: RwLock<Instant>,
 	with_color: bool,
+	server_pool: Option<Arc<ServerPool>>,
+	last_rejected: RwLock<u64>,
 }
 
 impl Default for Informant {
@@ -42,6 +45,8 @@ impl Default for Informant {
 			report: RwLock::new(None),
 			last_tick: RwLock::new(Instant::now()),
 			with_color: true,
+			server_pool: None,
+			last_rejected: RwLock::new(0),
 		}
 	}
 }
@@ -57,14 +62,16 @@ impl MillisecondDuration for Duration {
 }
 
 impl Informant {
-	/// Make a new instance potentially `with_color` output.
-	pub fn new(with_color: bool) -> Self {
+	/// Make a new instance potentially `with_color` output, reporting utilization of `server_pool` if given.
+	pub fn new(with_color: bool, server_pool: Option<Arc<ServerPool>>) -> Self {
 		Informant {
 			chain_info: RwLock::new(None),
 			cache_info: RwLock::new(None),
 			report: RwLock::new(None),
 			last_tick: RwLock::new(Instant::now()),
 			with_color: with_color,
+			server_pool: server_pool,
+			last_rejected: RwLock::new(0),
 		}
 	}
 
@@ -136,6 +143,44 @@ impl Informant {
 			);
 		}
 
+		if !report.memory_budget.is_empty() {
+			let total = report.memory_budget.iter().fold(0, |sum, a| sum + a.assigned);
+			let caches = report.memory_budget.iter()
+				.map(|a| format!("{} {}/{}",
+					match a.cache {
+						BudgetedCache::Blockchain => "chain",
+						BudgetedCache::Traces => "traces",
+						BudgetedCache::BlockQueue => "queue",
+					},
+					paint(Purple.bold(), Informant::format_bytes(a.used)),
+					Informant::format_bytes(a.assigned),
+				))
+				.collect::<Vec<_>>();
+			println!("Memory budget: {}   {}", paint(Purple.bold(), Informant::format_bytes(total)), caches.join("   "));
+		}
+
+		if let Some(sync) = maybe_sync {
+			let sync_info = sync.status();
+			if sync_info.clock_skewed {
+				println!("{}", paint(Red.bold(), format!("Local clock is skewed by ~{}s according to peers. Check the system time.", sync_info.clock_skew.unwrap_or(0).abs())));
+			}
+		}
+
+		if let Some(ref pool) = self.server_pool {
+			let status = pool.status();
+			let rejected = status.rejected();
+			let mut last_rejected = self.last_rejected.write().unwrap();
+			if status.busy > 0 || rejected > *last_rejected {
+				println!("Servers: {}/{} threads busy   {} requests   {} rejected",
+					paint(Yellow.bold(), format!("{}", status.busy)),
+					status.threads,
+					paint(Blue.bold(), format!("{}", status.in_flight())),
+					paint(Red.bold(), format!("{}", rejected - *last_rejected)),
+				);
+			}
+			*last_rejected = rejected;
+		}
+
 		*self.chain_info.write().unwrap().deref_mut() = Some(chain_info);
 		*self.cache_info.write().unwrap().deref_mut() = Some(cache_info);
 		*write_report.deref_mut() = Some(report);
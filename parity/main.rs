@@ -42,7 +42,6 @@ extern crate ethcore_ipc as ipc;
 extern crate ethcore_ipc_nano as nanoipc;
 #[macro_use]
 extern crate hyper; // for price_info.rs
-extern crate json_ipc_server as jsonipc;
 
 #[cfg(feature = "rpc")]
 extern crate ethcore_rpc;
@@ -70,6 +69,7 @@ use std::fs::File;
 use std::thread::sleep;
 use std::io::{BufReader, BufRead};
 use util::panics::{MayPanic, ForwardPanic, PanicHandler};
+use util::server_pool::ServerPool;
 use ethcore::client::{BlockID, BlockChainClient};
 use ethcore::error::{Error, ImportError};
 use ethcore::service::ClientService;
@@ -121,6 +121,11 @@ fn execute(conf: Configuration) {
 		return;
 	}
 
+	if conf.args.cmd_index_uncles {
+		execute_index_uncles(conf);
+		return;
+	}
+
 	execute_client(conf);
 }
 
@@ -165,15 +170,45 @@ fn execute_client(conf: Configuration) {
 	let miner = Miner::with_accounts(conf.args.flag_force_sealing, conf.spec(), account_service.clone());
 	miner.set_author(conf.author());
 	miner.set_gas_floor_target(conf.gas_floor_target());
+	if let Some(limit) = conf.tx_gas_limit() {
+		miner.set_tx_gas_limit(limit);
+	}
 	miner.set_extra_data(conf.extra_data());
 	miner.set_minimal_gas_price(conf.gas_price());
+	miner.set_gas_price_percentile(conf.args.flag_gas_price_pct);
 	miner.set_transactions_limit(conf.args.flag_tx_limit);
+	miner.set_transaction_queue_max_age(conf.args.flag_tx_max_age);
+	miner.set_bundle_max_age(conf.args.flag_bundle_max_age);
+	miner.set_reseal_policy(conf.reseal_policy());
+	miner.set_reseal_min_period(Duration::from_millis(conf.args.flag_reseal_min_period));
+	miner.set_sealing_work_timeout(match conf.args.flag_work_timeout {
+		0 => None,
+		secs => Some(Duration::from_secs(secs)),
+	});
+	miner.set_work_notify(conf.work_notify());
+	miner.set_service_addresses(conf.service_senders());
+	let transactions_path = match conf.args.flag_tx_queue_persist {
+		true => Some(Path::new(&conf.path()).join("transactions.rlp")),
+		false => None,
+	};
+	if let Some(ref path) = transactions_path {
+		let restored = miner.load_transactions(client.deref(), path);
+		info!("Restored {} transactions from the queue", restored);
+	}
 
 	let external_miner = Arc::new(ExternalMiner::default());
 	let network_settings = Arc::new(conf.network_settings());
 
 	// Sync
 	let sync = EthSync::register(service.network(), sync_config, client.clone(), miner.clone());
+	panic_handler.forward_from(sync.deref());
+	panic_handler.on_alert(|alert: String| warn!("{}", alert));
+
+	// Threads shared by http servers
+	let server_pool = conf.server_pool_config().map(|config| Arc::new(ServerPool::new(config)));
+
+	// Statistics of RPC calls shared by http servers
+	let rpc_stats = Arc::new(rpc::RpcStats::default());
 
 	let dependencies = Arc::new(rpc::Dependencies {
 		panic_handler: panic_handler.clone(),
@@ -184,19 +219,31 @@ fn execute_client(conf: Configuration) {
 		external_miner: external_miner.clone(),
 		logger: logger.clone(),
 		settings: network_settings.clone(),
+		rpc_stats: rpc_stats.clone(),
+		send_block_number_in_get_work: !conf.args.flag_legacy_get_work,
+		call_timeout: conf.rpc_call_timeout(),
 	});
 
+	let ipc_settings = conf.ipc_settings().unwrap_or_else(|e| die!("IPC: {}", e));
+	let ws_settings = conf.ws_settings().unwrap_or_else(|e| die!("WebSockets: {}", e));
+
 	// Setup http rpc
 	let rpc_server = rpc::new_http(rpc::HttpConfiguration {
 		enabled: network_settings.rpc_enabled,
 		interface: network_settings.rpc_interface.clone(),
 		port: network_settings.rpc_port,
-		apis: conf.rpc_apis(),
+		apis: conf.rpc_apis().parse().unwrap_or_else(|e| die!("JSON-RPC: {}", e)),
 		cors: conf.rpc_cors(),
+		read_only: conf.args.flag_jsonrpc_read_only,
+		server_pool: server_pool.clone(),
+		max_batch_size: conf.rpc_max_batch_size(),
 	}, &dependencies);
 
 	// setup ipc rpc
-	let _ipc_server = rpc::new_ipc(conf.ipc_settings(), &dependencies);
+	let _ipc_server = rpc::new_ipc(ipc_settings, &dependencies);
+
+	// setup websockets rpc
+	let _ws_server = rpc::new_ws(ws_settings, &dependencies);
 
 	if conf.args.flag_webapp { println!("WARNING: Flag -w/--webapp is deprecated. Web app server is now on by default. Ignoring."); }
 	let webapp_server = webapp::new(webapp::Configuration {
@@ -205,6 +252,8 @@ fn execute_client(conf: Configuration) {
 		port: conf.args.flag_webapp_port,
 		user: conf.args.flag_webapp_user.clone(),
 		pass: conf.args.flag_webapp_pass.clone(),
+		read_only: conf.args.flag_jsonrpc_read_only,
+		server_pool: server_pool.clone(),
 	}, webapp::Dependencies {
 		panic_handler: panic_handler.clone(),
 		client: client.clone(),
@@ -214,12 +263,15 @@ fn execute_client(conf: Configuration) {
 		external_miner: external_miner.clone(),
 		logger: logger.clone(),
 		settings: network_settings.clone(),
+		rpc_stats: rpc_stats.clone(),
+		send_block_number_in_get_work: !conf.args.flag_legacy_get_work,
+		call_timeout: conf.rpc_call_timeout(),
 	});
 
 	// Register IO handler
 	let io_handler  = Arc::new(ClientIoHandler {
 		client: service.client(),
-		info: Informant::new(!conf.args.flag_no_color),
+		info: Informant::new(!conf.args.flag_no_color, server_pool),
 		sync: sync.clone(),
 		accounts: account_service.clone(),
 	});
@@ -227,6 +279,12 @@ fn execute_client(conf: Configuration) {
 
 	// Handle exit
 	wait_for_exit(panic_handler, rpc_server, webapp_server);
+
+	if let Some(ref path) = transactions_path {
+		if let Err(e) = miner.save_transactions(path) {
+			warn!("Unable to save transactions from the queue: {}", e);
+		}
+	}
 }
 
 fn flush_stdout() {
@@ -257,6 +315,7 @@ fn execute_export(conf: Configuration) {
 		boot_nodes: Vec::new(),
 		use_secret: None,
 		ideal_peers: 0,
+		..NetworkConfiguration::new()
 	};
 	let client_config = conf.client_config(&spec);
 
@@ -328,6 +387,7 @@ fn execute_import(conf: Configuration) {
 		boot_nodes: Vec::new(),
 		use_secret: None,
 		ideal_peers: 0,
+		..NetworkConfiguration::new()
 	};
 	let client_config = conf.client_config(&spec);
 
@@ -371,7 +431,7 @@ fn execute_import(conf: Configuration) {
 		}
 	};
 
-	let informant = Informant::new(!conf.args.flag_no_color);
+	let informant = Informant::new(!conf.args.flag_no_color, None);
 
 	let do_import = |bytes| {
 		while client.queue_info().is_full() { sleep(Duration::from_secs(1)); }
@@ -409,6 +469,66 @@ fn execute_import(conf: Configuration) {
 	client.flush_queue();
 }
 
+fn execute_index_uncles(conf: Configuration) {
+	// Setup panic handler
+	let panic_handler = PanicHandler::new_in_arc();
+
+	// Raise fdlimit
+	unsafe { ::fdlimit::raise_fd_limit(); }
+
+	let spec = conf.spec();
+	let net_settings = NetworkConfiguration {
+		config_path: None,
+		listen_address: None,
+		public_address: None,
+		udp_port: None,
+		nat_enabled: false,
+		discovery_enabled: false,
+		pin: true,
+		boot_nodes: Vec::new(),
+		use_secret: None,
+		ideal_peers: 0,
+		..NetworkConfiguration::new()
+	};
+	let client_config = conf.client_config(&spec);
+
+	// Build client
+	let service = ClientService::start(
+		client_config, spec, net_settings, Path::new(&conf.path())
+	).unwrap_or_else(|e| die_with_error("Client", e));
+
+	panic_handler.forward_from(&service);
+	let client = service.client();
+
+	let parse_block_id = |s: &str, arg: &str| -> u64 {
+		if s == "latest" {
+			client.chain_info().best_block_number
+		} else if let Ok(n) = s.parse::<u64>() {
+			n
+		} else if let Ok(h) = H256::from_str(s) {
+			client.block_number(BlockID::Hash(h)).unwrap_or_else(|| {
+				die!("Unknown block hash passed to {} parameter: {:?}", arg, s);
+			})
+		} else {
+			die!("Invalid {} parameter given: {:?}", arg, s);
+		}
+	};
+	let from = parse_block_id(&conf.args.flag_from, "--from");
+	let to = parse_block_id(&conf.args.flag_to, "--to");
+
+	// Index in chunks, so that progress is visible and memory use is bounded.
+	let chunk = 10_000;
+	let mut indexed = 0;
+	let mut start = from;
+	while start <= to {
+		let end = ::std::cmp::min(start + chunk - 1, to);
+		indexed += client.backfill_uncle_index(start, end);
+		println!("Indexed uncles of blocks #{}..#{} ({} uncles so far).", start, end, indexed);
+		start = end + 1;
+	}
+	println!("Indexed {} uncles.", indexed);
+}
+
 fn execute_account_cli(conf: Configuration) {
 	use util::keys::store::SecretStore;
 	use rpassword::read_password;
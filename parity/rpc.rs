@@ -15,10 +15,12 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 
+use std::fmt;
 use std::collections::BTreeMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::net::SocketAddr;
+use std::time::Duration;
 use ethcore::client::Client;
 use ethsync::EthSync;
 use ethminer::{Miner, ExternalMiner};
@@ -26,28 +28,145 @@ use util::RotatingLogger;
 use util::panics::PanicHandler;
 use util::keys::store::{AccountService};
 use util::network_settings::NetworkSettings;
+use util::server_pool::{ServerPool, ServerPoolHandle};
 use die::*;
-use jsonipc;
 
 #[cfg(feature = "rpc")]
-pub use ethcore_rpc::Server as RpcServer;
+pub use ethcore_rpc::HttpServer as RpcServer;
+#[cfg(feature = "rpc")]
+pub use ethcore_rpc::WsServer;
 #[cfg(feature = "rpc")]
 use ethcore_rpc::{RpcServerError, RpcServer as Server};
+#[cfg(feature = "rpc")]
+use ethcore_rpc::ipc_access::IpcAccessPolicy;
+pub use ethcore_rpc::IpcServer;
+pub use ethcore_rpc::stats::RpcStats;
 #[cfg(not(feature = "rpc"))]
 pub struct RpcServer;
+#[cfg(not(feature = "rpc"))]
+pub struct WsServer;
 
 pub struct HttpConfiguration {
 	pub enabled: bool,
 	pub interface: String,
 	pub port: u16,
-	pub apis: String,
+	pub apis: ApiSet,
 	pub cors: Vec<String>,
+	pub read_only: bool,
+	pub server_pool: Option<Arc<ServerPool>>,
+	pub max_batch_size: Option<usize>,
 }
 
 pub struct IpcConfiguration {
 	pub enabled: bool,
 	pub socket_addr: String,
-	pub apis: String,
+	pub apis: ApiSet,
+	pub access_policy: IpcAccessPolicy,
+	pub read_only: bool,
+}
+
+pub struct WsConfiguration {
+	pub enabled: bool,
+	pub interface: String,
+	pub port: u16,
+	pub apis: ApiSet,
+	pub origins: Vec<String>,
+	pub read_only: bool,
+}
+
+/// APIs exposed over IPC to members of the policy's safe group.
+const SAFE_IPC_APIS: &'static [Api] = &[Api::Web3, Api::Eth, Api::Net];
+
+/// RPC API (set of delegates) which can be enabled on a transport.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Api {
+	Web3,
+	Net,
+	Eth,
+	Personal,
+	Ethcore,
+	Traces,
+}
+
+impl Api {
+	/// All known APIs.
+	pub fn all() -> &'static [Api] {
+		const ALL: &'static [Api] = &[Api::Web3, Api::Net, Api::Eth, Api::Personal, Api::Ethcore, Api::Traces];
+		ALL
+	}
+
+	/// Name of the API as used on the command line and in `rpc_modules`.
+	pub fn name(&self) -> &'static str {
+		match *self {
+			Api::Web3 => "web3",
+			Api::Net => "net",
+			Api::Eth => "eth",
+			Api::Personal => "personal",
+			Api::Ethcore => "ethcore",
+			Api::Traces => "traces",
+		}
+	}
+}
+
+impl fmt::Display for Api {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+impl FromStr for Api {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Api::all().iter().cloned().find(|api| api.name() == s).ok_or_else(|| {
+			let names = Api::all().iter().map(Api::name).collect::<Vec<_>>();
+			format!("{}: Invalid API name to be enabled. Valid names are: {}.", s, names.join(", "))
+		})
+	}
+}
+
+/// Set of APIs enabled on a transport.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ApiSet {
+	/// All APIs which don't give access to accounts (`safe`).
+	SafeContext,
+	/// All APIs, including `personal` (`all`).
+	UnsafePersonal,
+	/// Explicitly listed APIs.
+	List(Vec<Api>),
+}
+
+impl ApiSet {
+	/// Returns APIs in the set.
+	pub fn list_apis(&self) -> Vec<Api> {
+		match *self {
+			ApiSet::SafeContext => Api::all().iter().cloned().filter(|api| *api != Api::Personal).collect(),
+			ApiSet::UnsafePersonal => Api::all().to_vec(),
+			ApiSet::List(ref apis) => apis.clone(),
+		}
+	}
+
+	/// Returns `true` if the set gives access to accounts, either as `all` or by listing `personal` explicitly.
+	/// Methods restricted to the unsafe set are available only then.
+	pub fn is_unsafe(&self) -> bool {
+		self.list_apis().contains(&Api::Personal)
+	}
+}
+
+impl FromStr for ApiSet {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"safe" => Ok(ApiSet::SafeContext),
+			"all" => Ok(ApiSet::UnsafePersonal),
+			apis => apis.split(',')
+				.filter(|api| !api.is_empty())
+				.map(Api::from_str)
+				.collect::<Result<Vec<_>, _>>()
+				.map(ApiSet::List),
+		}
+	}
 }
 
 pub struct Dependencies {
@@ -59,6 +178,9 @@ pub struct Dependencies {
 	pub external_miner: Arc<ExternalMiner>,
 	pub logger: Arc<RotatingLogger>,
 	pub settings: Arc<NetworkSettings>,
+	pub rpc_stats: Arc<RpcStats>,
+	pub send_block_number_in_get_work: bool,
+	pub call_timeout: Option<Duration>,
 }
 
 pub fn new_http(conf: HttpConfiguration, deps: &Arc<Dependencies>) -> Option<RpcServer> {
@@ -66,62 +188,82 @@ pub fn new_http(conf: HttpConfiguration, deps: &Arc<Dependencies>) -> Option<Rpc
 		return None;
 	}
 
-	let interface = match conf.interface.as_str() {
+	let url = format!("{}:{}", interface(&conf.interface), conf.port);
+	let addr = SocketAddr::from_str(&url).unwrap_or_else(|_| die!("{}: Invalid JSONRPC listen host/port given.", url));
+
+	let pool = conf.server_pool.map(|pool| pool.register("rpc"));
+
+	Some(setup_http_rpc_server(deps, &addr, conf.cors, conf.apis, conf.read_only, pool, conf.max_batch_size))
+}
+
+pub fn new_ws(conf: WsConfiguration, deps: &Arc<Dependencies>) -> Option<WsServer> {
+	if !conf.enabled {
+		return None;
+	}
+
+	let url = format!("{}:{}", interface(&conf.interface), conf.port);
+	let addr = SocketAddr::from_str(&url).unwrap_or_else(|_| die!("{}: Invalid WebSockets JSON-RPC listen host/port given.", url));
+
+	Some(setup_ws_rpc_server(deps, &addr, conf.apis, conf.origins, conf.read_only))
+}
+
+fn interface(interface: &str) -> &str {
+	match interface {
 		"all" => "0.0.0.0",
 		"local" => "127.0.0.1",
 		x => x,
-	};
-	let apis = conf.apis.split(',').collect();
-	let url = format!("{}:{}", interface, conf.port);
-	let addr = SocketAddr::from_str(&url).unwrap_or_else(|_| die!("{}: Invalid JSONRPC listen host/port given.", url));
-
-	Some(setup_http_rpc_server(deps, &addr, conf.cors, apis))
+	}
 }
 
-pub fn new_ipc(conf: IpcConfiguration, deps: &Arc<Dependencies>) -> Option<jsonipc::Server> {
+pub fn new_ipc(conf: IpcConfiguration, deps: &Arc<Dependencies>) -> Option<IpcServer> {
 	if !conf.enabled { return None; }
-	let apis = conf.apis.split(',').collect();
-	Some(setup_ipc_rpc_server(deps, &conf.socket_addr, apis))
+	Some(setup_ipc_rpc_server(deps, &conf.socket_addr, conf.apis, conf.access_policy, conf.read_only))
+}
+
+/// Returns `rpc_modules` listing of given APIs.
+fn to_modules(apis: &[Api]) -> BTreeMap<String, String> {
+	apis.iter().map(|api| (api.name().to_owned(), "1.0".to_owned())).collect()
 }
 
-fn setup_rpc_server(apis: Vec<&str>, deps: &Arc<Dependencies>) -> Server {
+fn setup_rpc_server(apis: ApiSet, deps: &Arc<Dependencies>) -> Server {
 	use ethcore_rpc::v1::*;
 
-	let server = Server::new();
-	let mut modules = BTreeMap::new();
-	for api in apis.into_iter() {
-		match api {
-			"web3" => {
-				modules.insert("web3".to_owned(), "1.0".to_owned());
+	let server = Server::with_stats(deps.rpc_stats.clone());
+	let is_unsafe = apis.is_unsafe();
+	let apis = apis.list_apis();
+	for api in &apis {
+		match *api {
+			Api::Web3 => {
 				server.add_delegate(Web3Client::new().to_delegate());
 			},
-			"net" => {
-				modules.insert("net".to_owned(), "1.0".to_owned());
+			Api::Net => {
 				server.add_delegate(NetClient::new(&deps.sync).to_delegate());
 			},
-			"eth" => {
-				modules.insert("eth".to_owned(), "1.0".to_owned());
-				server.add_delegate(EthClient::new(&deps.client, &deps.sync, &deps.secret_store, &deps.miner, &deps.external_miner).to_delegate());
+			Api::Eth => {
+				server.add_delegate(EthClient::new(&deps.client, &deps.sync, &deps.secret_store, &deps.miner, &deps.external_miner)
+					.with_call_timeout(deps.call_timeout)
+					.with_send_block_number_in_get_work(deps.send_block_number_in_get_work)
+					.to_delegate());
 				server.add_delegate(EthFilterClient::new(&deps.client, &deps.miner).to_delegate());
 			},
-			"personal" => {
-				modules.insert("personal".to_owned(), "1.0".to_owned());
+			Api::Personal => {
 				server.add_delegate(PersonalClient::new(&deps.secret_store).to_delegate())
 			},
-			"ethcore" => {
-				modules.insert("ethcore".to_owned(), "1.0".to_owned());
-				server.add_delegate(EthcoreClient::new(&deps.miner, deps.logger.clone(), deps.settings.clone()).to_delegate())
+			Api::Ethcore => {
+				server.add_delegate(EthcoreClient::new(&deps.client, &deps.miner, deps.logger.clone(), deps.settings.clone())
+					.with_rpc_stats(deps.rpc_stats.clone())
+					.with_call_timeout(deps.call_timeout)
+					.to_delegate())
 			},
-			"traces" => {
-				modules.insert("traces".to_owned(), "1.0".to_owned());
+			Api::Traces => {
 				server.add_delegate(TracesClient::new(&deps.client).to_delegate())
 			},
-			_ => {
-				die!("{}: Invalid API name to be enabled.", api);
-			},
 		}
 	}
-	server.add_delegate(RpcClient::new(modules).to_delegate());
+	server.add_delegate(RpcClient::new(to_modules(&apis)).to_delegate());
+	if !is_unsafe {
+		server.restrict_to_safe();
+	}
 	server
 }
 
@@ -130,7 +272,10 @@ pub fn setup_http_rpc_server(
 	_deps: Dependencies,
 	_url: &SocketAddr,
 	_cors_domain: Option<String>,
-	_apis: Vec<&str>,
+	_apis: ApiSet,
+	_read_only: bool,
+	_pool: Option<ServerPoolHandle>,
+	_max_batch_size: Option<usize>,
 ) -> ! {
 	die!("Your Parity version has been compiled without JSON-RPC support.")
 }
@@ -140,10 +285,16 @@ pub fn setup_http_rpc_server(
 	dependencies: &Arc<Dependencies>,
 	url: &SocketAddr,
 	cors_domains: Vec<String>,
-	apis: Vec<&str>,
+	apis: ApiSet,
+	read_only: bool,
+	pool: Option<ServerPoolHandle>,
+	max_batch_size: Option<usize>,
 ) -> RpcServer {
 	let server = setup_rpc_server(apis, dependencies);
-	let start_result = server.start_http(url, cors_domains);
+	if read_only {
+		server.restrict_to_read_only();
+	}
+	let start_result = server.start_http(url, cors_domains, pool, max_batch_size);
 	let deps = dependencies.clone();
 	match start_result {
 		Err(RpcServerError::IoError(err)) => die_with_io_error("RPC", err),
@@ -157,11 +308,88 @@ pub fn setup_http_rpc_server(
 	}
 }
 
-pub fn setup_ipc_rpc_server(dependencies: &Arc<Dependencies>, addr: &str, apis: Vec<&str>) -> jsonipc::Server {
+#[cfg(not(feature = "rpc"))]
+pub fn setup_ws_rpc_server(_deps: &Arc<Dependencies>, _addr: &SocketAddr, _apis: ApiSet, _origins: Vec<String>, _read_only: bool) -> ! {
+	die!("Your Parity version has been compiled without JSON-RPC support.")
+}
+
+#[cfg(feature = "rpc")]
+pub fn setup_ws_rpc_server(dependencies: &Arc<Dependencies>, addr: &SocketAddr, apis: ApiSet, origins: Vec<String>, read_only: bool) -> WsServer {
+	let server = setup_rpc_server(apis, dependencies);
+	if read_only {
+		server.restrict_to_read_only();
+	}
+	let deps = dependencies.clone();
+	match server.start_ws(addr, origins) {
+		Err(err) => die_with_io_error("WebSockets", err),
+		Ok(server) => {
+			server.set_panic_handler(move || {
+				deps.panic_handler.notify_all("Panic in WebSockets thread.".to_owned());
+			});
+			server
+		},
+	}
+}
+
+pub fn setup_ipc_rpc_server(dependencies: &Arc<Dependencies>, addr: &str, apis: ApiSet, policy: IpcAccessPolicy, read_only: bool) -> IpcServer {
+	let safe_apis = ApiSet::List(apis.list_apis().into_iter().filter(|api| SAFE_IPC_APIS.contains(api)).collect());
 	let server = setup_rpc_server(apis, dependencies);
-	match server.start_ipc(addr) {
-		Err(jsonipc::Error::Io(io_error)) => die_with_io_error("RPC", io_error),
-		Err(any_error) => die!("RPC: {:?}", any_error),
+	let safe_server = setup_rpc_server(safe_apis, dependencies);
+	if read_only {
+		server.restrict_to_read_only();
+		safe_server.restrict_to_read_only();
+	}
+	match server.start_ipc_with_policy(addr, &safe_server, policy) {
+		Err(io_error) => die_with_io_error("RPC", io_error),
 		Ok(server) => server
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{Api, ApiSet, to_modules, SAFE_IPC_APIS};
+
+	fn modules(apis: &ApiSet) -> Vec<String> {
+		to_modules(&apis.list_apis()).keys().cloned().collect()
+	}
+
+	#[test]
+	fn should_parse_api_sets() {
+		assert_eq!("safe".parse(), Ok(ApiSet::SafeContext));
+		assert_eq!("all".parse(), Ok(ApiSet::UnsafePersonal));
+		assert_eq!("web3,eth".parse(), Ok(ApiSet::List(vec![Api::Web3, Api::Eth])));
+		assert_eq!("".parse(), Ok(ApiSet::List(vec![])));
+	}
+
+	#[test]
+	fn should_list_valid_names_on_unknown_api() {
+		// when
+		let result = "web3,admin".parse::<ApiSet>();
+
+		// then
+		let err = result.unwrap_err();
+		assert!(err.starts_with("admin:"), "Unexpected error: {}", err);
+		assert!(err.contains("web3, net, eth, personal, ethcore, traces"), "Unexpected error: {}", err);
+	}
+
+	#[test]
+	fn should_register_apis_of_each_set() {
+		assert_eq!(modules(&ApiSet::SafeContext), vec!["eth", "ethcore", "net", "traces", "web3"]);
+		assert_eq!(modules(&ApiSet::UnsafePersonal), vec!["eth", "ethcore", "net", "personal", "traces", "web3"]);
+		assert_eq!(modules(&ApiSet::List(vec![Api::Personal, Api::Net])), vec!["net", "personal"]);
+	}
+
+	#[test]
+	fn should_consider_only_sets_with_personal_unsafe() {
+		assert!(ApiSet::UnsafePersonal.is_unsafe());
+		assert!(ApiSet::List(vec![Api::Ethcore, Api::Personal]).is_unsafe());
+		assert!(!ApiSet::SafeContext.is_unsafe());
+		assert!(!ApiSet::List(vec![Api::Ethcore]).is_unsafe());
+	}
+
+	#[test]
+	fn should_never_expose_personal_to_safe_ipc_group() {
+		assert!(!SAFE_IPC_APIS.contains(&Api::Personal));
+		assert!(ApiSet::SafeContext.list_apis().iter().all(|api| *api != Api::Personal));
+	}
+}
@@ -17,6 +17,7 @@
 use std::sync::Arc;
 use std::str::FromStr;
 use std::net::SocketAddr;
+use std::time::Duration;
 use ethcore::client::Client;
 use ethsync::EthSync;
 use ethminer::{Miner, ExternalMiner};
@@ -24,7 +25,9 @@ use util::RotatingLogger;
 use util::panics::PanicHandler;
 use util::keys::store::{AccountService};
 use util::network_settings::NetworkSettings;
+use util::server_pool::{ServerPool, ServerPoolHandle};
 use die::*;
+use rpc::RpcStats;
 
 #[cfg(feature = "webapp")]
 pub use ethcore_webapp::Server as WebappServer;
@@ -37,6 +40,8 @@ pub struct Configuration {
 	pub port: u16,
 	pub user: Option<String>,
 	pub pass: Option<String>,
+	pub read_only: bool,
+	pub server_pool: Option<Arc<ServerPool>>,
 }
 
 pub struct Dependencies {
@@ -48,6 +53,9 @@ pub struct Dependencies {
 	pub external_miner: Arc<ExternalMiner>,
 	pub logger: Arc<RotatingLogger>,
 	pub settings: Arc<NetworkSettings>,
+	pub rpc_stats: Arc<RpcStats>,
+	pub send_block_number_in_get_work: bool,
+	pub call_timeout: Option<Duration>,
 }
 
 pub fn new(configuration: Configuration, deps: Dependencies) -> Option<WebappServer> {
@@ -74,7 +82,9 @@ pub fn new(configuration: Configuration, deps: Dependencies) -> Option<WebappSer
 		(username.to_owned(), password)
 	});
 
-	Some(setup_webapp_server(deps, &addr, auth))
+	let pool = configuration.server_pool.map(|pool| pool.register("webapp"));
+
+	Some(setup_webapp_server(deps, &addr, auth, configuration.read_only, pool))
 }
 
 #[cfg(not(feature = "webapp"))]
@@ -82,6 +92,8 @@ pub fn setup_webapp_server(
 	_deps: Dependencies,
 	_url: &SocketAddr,
 	_auth: Option<(String, String)>,
+	_read_only: bool,
+	_pool: Option<ServerPoolHandle>,
 ) -> ! {
 	die!("Your Parity version has been compiled without WebApps support.")
 }
@@ -90,18 +102,33 @@ pub fn setup_webapp_server(
 pub fn setup_webapp_server(
 	deps: Dependencies,
 	url: &SocketAddr,
-	auth: Option<(String, String)>
+	auth: Option<(String, String)>,
+	read_only: bool,
+	pool: Option<ServerPoolHandle>,
 ) -> WebappServer {
 	use ethcore_rpc::v1::*;
 	use ethcore_webapp as webapp;
 
-	let server = webapp::ServerBuilder::new();
+	let mut server = webapp::ServerBuilder::new();
+	if let Some(pool) = pool {
+		server.set_server_pool(pool);
+	}
 	server.add_delegate(Web3Client::new().to_delegate());
 	server.add_delegate(NetClient::new(&deps.sync).to_delegate());
-	server.add_delegate(EthClient::new(&deps.client, &deps.sync, &deps.secret_store, &deps.miner, &deps.external_miner).to_delegate());
+	server.add_delegate(EthClient::new(&deps.client, &deps.sync, &deps.secret_store, &deps.miner, &deps.external_miner)
+		.with_call_timeout(deps.call_timeout)
+		.with_send_block_number_in_get_work(deps.send_block_number_in_get_work)
+		.to_delegate());
 	server.add_delegate(EthFilterClient::new(&deps.client, &deps.miner).to_delegate());
 	server.add_delegate(PersonalClient::new(&deps.secret_store).to_delegate());
-	server.add_delegate(EthcoreClient::new(&deps.miner, deps.logger.clone(), deps.settings.clone()).to_delegate());
+	server.add_delegate(EthcoreClient::new(&deps.client, &deps.miner, deps.logger.clone(), deps.settings.clone())
+		.with_rpc_stats(deps.rpc_stats.clone())
+		.with_call_timeout(deps.call_timeout)
+		.to_delegate());
+	server.with_rpc_stats(deps.rpc_stats.clone());
+	if read_only {
+		server.restrict_to_read_only();
+	}
 
 	let start_result = match auth {
 		None => {
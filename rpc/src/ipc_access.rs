@@ -0,0 +1,305 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per-connection access control for IPC transport.
+//!
+//! Each connection is mapped (using credentials of the process on the other end of the socket)
+//! to one of the API sets: full, safe or none at all.
+//! On platforms without peer credentials support every connection gets full access and
+//! we rely on permissions of the socket file instead.
+
+use std::io;
+use std::sync::Arc;
+use read_only::FilteredHandler;
+use origin;
+
+/// Raw descriptor of connected socket.
+pub type SocketFd = i32;
+
+/// Identity of the process connected to IPC socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerCredentials {
+	/// User id
+	pub uid: u32,
+	/// Group id
+	pub gid: u32,
+}
+
+/// Resolves credentials of the process connected to given socket.
+pub trait CredentialsResolver: Send + Sync {
+	/// Returns credentials of the peer or `None` if they can't be determined.
+	fn resolve(&self, fd: SocketFd) -> Option<PeerCredentials>;
+}
+
+/// Resolver reading `SO_PEERCRED` socket option.
+pub struct SocketCredentialsResolver;
+
+#[cfg(target_os = "linux")]
+impl CredentialsResolver for SocketCredentialsResolver {
+	fn resolve(&self, fd: SocketFd) -> Option<PeerCredentials> {
+		use std::mem;
+		use libc;
+
+		#[repr(C)]
+		struct UCred {
+			pid: libc::pid_t,
+			uid: libc::uid_t,
+			gid: libc::gid_t,
+		}
+
+		let mut cred = UCred { pid: 0, uid: 0, gid: 0 };
+		let mut len = mem::size_of::<UCred>() as libc::socklen_t;
+		let res = unsafe {
+			libc::getsockopt(fd, libc::SOL_SOCKET, libc::SO_PEERCRED, &mut cred as *mut _ as *mut libc::c_void, &mut len)
+		};
+		match res {
+			0 => Some(PeerCredentials { uid: cred.uid, gid: cred.gid }),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(not(target_os = "linux"))]
+impl CredentialsResolver for SocketCredentialsResolver {
+	fn resolve(&self, _fd: SocketFd) -> Option<PeerCredentials> {
+		None
+	}
+}
+
+/// Access level granted to a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpcAccess {
+	/// All configured APIs are available.
+	Full,
+	/// Only the safe API set is available.
+	Safe,
+	/// Connection should be closed.
+	Rejected,
+}
+
+/// Maps peer credentials to access level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpcAccessPolicy {
+	/// User owning the node. Has full access.
+	pub owner_uid: u32,
+	/// Members of this group get access to safe APIs only.
+	pub safe_gid: Option<u32>,
+}
+
+impl IpcAccessPolicy {
+	/// Policy granting full access to the user running the node only.
+	pub fn owner_only() -> Self {
+		IpcAccessPolicy {
+			owner_uid: current_uid(),
+			safe_gid: None,
+		}
+	}
+
+	/// Returns access level for given credentials.
+	/// Unknown credentials (platform without support) fall back to the socket file permissions.
+	pub fn access(&self, credentials: Option<PeerCredentials>) -> IpcAccess {
+		match credentials {
+			None => IpcAccess::Full,
+			Some(ref c) if c.uid == self.owner_uid => IpcAccess::Full,
+			Some(ref c) if Some(c.gid) == self.safe_gid => IpcAccess::Safe,
+			Some(_) => IpcAccess::Rejected,
+		}
+	}
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+	use libc;
+	unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn current_uid() -> u32 {
+	0
+}
+
+/// Restricts permissions of the socket file so that only users allowed by the policy can connect.
+#[cfg(unix)]
+pub fn restrict_socket(path: &str, policy: &IpcAccessPolicy) -> io::Result<()> {
+	use std::fs;
+	use std::ffi::CString;
+	use std::os::unix::fs::PermissionsExt;
+	use libc;
+
+	let mode = match policy.safe_gid {
+		Some(gid) => {
+			let c_path = try!(CString::new(path).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Invalid socket path.")));
+			if unsafe { libc::chown(c_path.as_ptr(), !0, gid) } != 0 {
+				return Err(io::Error::last_os_error());
+			}
+			0o660
+		},
+		None => 0o600,
+	};
+	fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+/// Restricts permissions of the socket file so that only users allowed by the policy can connect.
+#[cfg(not(unix))]
+pub fn restrict_socket(_path: &str, _policy: &IpcAccessPolicy) -> io::Result<()> {
+	Ok(())
+}
+
+/// Response sent to connections rejected by the policy before they are closed.
+pub const ACCESS_DENIED_RESPONSE: &'static str = r#"{"jsonrpc":"2.0","error":{"code":-32003,"message":"Access denied for this connection."},"id":null}"#;
+
+/// Selects handler for each IPC connection according to the policy.
+pub struct IpcAccessControl {
+	full: Arc<FilteredHandler>,
+	safe: Arc<FilteredHandler>,
+	policy: IpcAccessPolicy,
+	resolver: Box<CredentialsResolver>,
+}
+
+impl IpcAccessControl {
+	/// Creates new access control using `SO_PEERCRED` to identify peers.
+	pub fn new(full: Arc<FilteredHandler>, safe: Arc<FilteredHandler>, policy: IpcAccessPolicy) -> Self {
+		Self::with_resolver(full, safe, policy, Box::new(SocketCredentialsResolver))
+	}
+
+	/// Creates new access control with custom credentials resolver.
+	pub fn with_resolver(full: Arc<FilteredHandler>, safe: Arc<FilteredHandler>, policy: IpcAccessPolicy, resolver: Box<CredentialsResolver>) -> Self {
+		IpcAccessControl {
+			full: full,
+			safe: safe,
+			policy: policy,
+			resolver: resolver,
+		}
+	}
+
+	/// Returns access level of connection.
+	pub fn access(&self, fd: SocketFd) -> IpcAccess {
+		self.policy.access(self.resolver.resolve(fd))
+	}
+
+	/// Returns handler that should serve given connection or `None` if connection is rejected.
+	pub fn handler(&self, fd: SocketFd) -> Option<Arc<FilteredHandler>> {
+		self.handler_for(self.resolver.resolve(fd))
+	}
+
+	fn handler_for(&self, credentials: Option<PeerCredentials>) -> Option<Arc<FilteredHandler>> {
+		match self.policy.access(credentials) {
+			IpcAccess::Full => Some(self.full.clone()),
+			IpcAccess::Safe => Some(self.safe.clone()),
+			IpcAccess::Rejected => None,
+		}
+	}
+
+	/// Handles request received on given connection.
+	/// Request is attributed to the user on the other end of the connection.
+	///
+	/// Returns the response and `true` if connection should be closed afterwards.
+	pub fn handle_request(&self, fd: SocketFd, request: &str) -> (Option<String>, bool) {
+		let credentials = self.resolver.resolve(fd);
+		match self.handler_for(credentials) {
+			Some(handler) => (origin::with_origin(origin::ipc_origin(credentials), || handler.handle_request(request)), false),
+			None => {
+				debug!(target: "rpc", "Rejecting IPC connection {}: access denied by policy.", fd);
+				(Some(ACCESS_DENIED_RESPONSE.to_owned()), true)
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use jsonrpc_core::IoHandler;
+	use v1::{Web3, Web3Client};
+	use super::*;
+
+	struct FakeResolver(Option<PeerCredentials>);
+
+	impl CredentialsResolver for FakeResolver {
+		fn resolve(&self, _fd: SocketFd) -> Option<PeerCredentials> {
+			self.0
+		}
+	}
+
+	fn policy() -> IpcAccessPolicy {
+		IpcAccessPolicy {
+			owner_uid: 1000,
+			safe_gid: Some(2000),
+		}
+	}
+
+	fn access_control(credentials: Option<PeerCredentials>) -> IpcAccessControl {
+		let full = Arc::new(IoHandler::new());
+		full.add_delegate(Web3Client::new().to_delegate());
+		let full = FilteredHandler::new(full, Default::default());
+		let safe = FilteredHandler::new(Arc::new(IoHandler::new()), Default::default());
+		IpcAccessControl::with_resolver(Arc::new(full), Arc::new(safe), policy(), Box::new(FakeResolver(credentials)))
+	}
+
+	const REQUEST: &'static str = r#"{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["0x00"], "id": 1}"#;
+
+	#[test]
+	fn should_map_credentials_to_access_level() {
+		let policy = policy();
+		assert_eq!(policy.access(Some(PeerCredentials { uid: 1000, gid: 1 })), IpcAccess::Full);
+		assert_eq!(policy.access(Some(PeerCredentials { uid: 1001, gid: 2000 })), IpcAccess::Safe);
+		assert_eq!(policy.access(Some(PeerCredentials { uid: 1001, gid: 1 })), IpcAccess::Rejected);
+		assert_eq!(policy.access(None), IpcAccess::Full);
+	}
+
+	#[test]
+	fn should_give_owner_full_access() {
+		let control = access_control(Some(PeerCredentials { uid: 1000, gid: 1000 }));
+		let (response, close) = control.handle_request(0, REQUEST);
+		assert!(response.unwrap().contains("result"));
+		assert!(!close);
+	}
+
+	#[test]
+	fn should_give_group_safe_access() {
+		let control = access_control(Some(PeerCredentials { uid: 1001, gid: 2000 }));
+		let (response, close) = control.handle_request(0, REQUEST);
+		assert!(response.unwrap().contains("Method not found"));
+		assert!(!close);
+	}
+
+	#[test]
+	fn should_reject_other_users() {
+		let control = access_control(Some(PeerCredentials { uid: 1001, gid: 1 }));
+		let (response, close) = control.handle_request(0, REQUEST);
+		assert_eq!(response.unwrap(), super::ACCESS_DENIED_RESPONSE);
+		assert!(close);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn should_resolve_credentials_of_same_user() {
+		use libc;
+		let mut fds = [0; 2];
+		let res = unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) };
+		assert_eq!(res, 0);
+
+		let credentials = SocketCredentialsResolver.resolve(fds[0]);
+		let policy = IpcAccessPolicy::owner_only();
+
+		assert_eq!(credentials.map(|c| c.uid), Some(unsafe { libc::getuid() }));
+		assert_eq!(policy.access(credentials), IpcAccess::Full);
+		unsafe {
+			libc::close(fds[0]);
+			libc::close(fds[1]);
+		}
+	}
+}
@@ -0,0 +1,507 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! JSON-RPC over unix domain sockets.
+//!
+//! Every connection is served by its own thread, so a slow client never blocks the others,
+//! and responses of a connection are written in order of its requests.
+//! The protocol doesn't delimit requests, so they are split from the stream of each connection
+//! by counting brackets of complete JSON values.
+//!
+//! On other platforms named pipes are served by `json-ipc-server`.
+
+#[cfg(unix)]
+pub use self::unix::{IpcHandler, IpcServer};
+
+/// Default maximal size of a single request in bytes.
+pub const MAX_REQUEST_SIZE: usize = 5 * 1024 * 1024;
+/// Default maximal number of open IPC connections.
+pub const MAX_CONNECTIONS: usize = 64;
+
+#[cfg(not(unix))]
+pub use self::named_pipe::IpcServer;
+
+/// Request which doesn't fit into the request buffer.
+#[derive(Debug, PartialEq)]
+pub struct RequestTooLarge {
+	/// Maximal size of a request in bytes.
+	pub limit: usize,
+}
+
+/// Splits stream of bytes into complete JSON values.
+/// Anything outside of JSON objects and arrays (e.g. whitespace between requests) is skipped.
+#[derive(Debug)]
+pub struct RequestBuffer {
+	/// Maximal number of bytes of an incomplete request.
+	limit: usize,
+	buffer: Vec<u8>,
+	/// Number of bytes of `buffer` already scanned.
+	scanned: usize,
+	depth: usize,
+	in_string: bool,
+	escaped: bool,
+}
+
+impl Default for RequestBuffer {
+	fn default() -> Self {
+		RequestBuffer::with_limit(MAX_REQUEST_SIZE)
+	}
+}
+
+impl RequestBuffer {
+	/// Creates buffer holding at most `limit` bytes of an incomplete request.
+	pub fn with_limit(limit: usize) -> Self {
+		RequestBuffer {
+			limit: limit,
+			buffer: Vec::new(),
+			scanned: 0,
+			depth: 0,
+			in_string: false,
+			escaped: false,
+		}
+	}
+
+	/// Appends received data and returns all requests completed by it.
+	/// Fails if the incomplete request left in the buffer exceeds the limit.
+	pub fn push(&mut self, data: &[u8]) -> Result<Vec<String>, RequestTooLarge> {
+		self.buffer.extend_from_slice(data);
+		let mut requests = Vec::new();
+		let mut start = 0;
+		for pos in self.scanned..self.buffer.len() {
+			let byte = self.buffer[pos];
+			if self.depth == 0 {
+				match byte {
+					b'{' | b'[' => self.depth = 1,
+					_ => start = pos + 1,
+				}
+				continue;
+			}
+			if self.in_string {
+				match byte {
+					_ if self.escaped => self.escaped = false,
+					b'\\' => self.escaped = true,
+					b'"' => self.in_string = false,
+					_ => {},
+				}
+				continue;
+			}
+			match byte {
+				b'"' => self.in_string = true,
+				b'{' | b'[' => self.depth += 1,
+				b'}' | b']' => {
+					self.depth -= 1;
+					if self.depth == 0 {
+						requests.push(String::from_utf8_lossy(&self.buffer[start..pos + 1]).into_owned());
+						start = pos + 1;
+					}
+				},
+				_ => {},
+			}
+		}
+		self.buffer.drain(..start);
+		self.scanned = self.buffer.len();
+		if self.buffer.len() > self.limit {
+			return Err(RequestTooLarge { limit: self.limit });
+		}
+		Ok(requests)
+	}
+}
+
+#[cfg(unix)]
+mod unix {
+	use std::{io, fs, thread};
+	use std::collections::HashMap;
+	use std::io::{Read, Write};
+	use std::net::Shutdown;
+	use std::os::unix::fs::FileTypeExt;
+	use std::os::unix::io::AsRawFd;
+	use std::os::unix::net::{UnixListener, UnixStream};
+	use std::sync::{Arc, Mutex};
+	use std::sync::atomic::{AtomicBool, Ordering};
+	use jsonrpc_core::IoHandler;
+	use ipc_access::{IpcAccessControl, SocketFd, ACCESS_DENIED_RESPONSE};
+	use read_only::FilteredHandler;
+	use super::{RequestBuffer, MAX_REQUEST_SIZE, MAX_CONNECTIONS};
+
+	/// Handles requests received over IPC connections.
+	pub trait IpcHandler: Send + Sync {
+		/// Handles request received on connection `fd`.
+		/// Returns the response and `true` if connection should be closed afterwards.
+		fn handle_request(&self, fd: SocketFd, request: &str) -> (Option<String>, bool);
+
+		/// Returns `false` if connection `fd` should be closed right after it's accepted.
+		fn accepts(&self, _fd: SocketFd) -> bool {
+			true
+		}
+	}
+
+	impl IpcHandler for IoHandler {
+		fn handle_request(&self, _fd: SocketFd, request: &str) -> (Option<String>, bool) {
+			(IoHandler::handle_request(self, request), false)
+		}
+	}
+
+	impl IpcHandler for FilteredHandler {
+		fn handle_request(&self, _fd: SocketFd, request: &str) -> (Option<String>, bool) {
+			(FilteredHandler::handle_request(self, request), false)
+		}
+	}
+
+	impl IpcHandler for IpcAccessControl {
+		fn handle_request(&self, fd: SocketFd, request: &str) -> (Option<String>, bool) {
+			IpcAccessControl::handle_request(self, fd, request)
+		}
+
+		fn accepts(&self, fd: SocketFd) -> bool {
+			self.handler(fd).is_some()
+		}
+	}
+
+	type Connections = Arc<Mutex<HashMap<usize, UnixStream>>>;
+
+	/// Running IPC server. Open connections are closed and the socket file is removed when dropped.
+	pub struct IpcServer {
+		path: String,
+		closed: Arc<AtomicBool>,
+		connections: Connections,
+		listener: Option<thread::JoinHandle<()>>,
+	}
+
+	impl IpcServer {
+		/// Starts listening on unix socket at `path`. Socket file left by previous run is replaced.
+		pub fn start(path: &str, handler: Arc<IpcHandler>) -> io::Result<Self> {
+			Self::start_with_limits(path, handler, MAX_CONNECTIONS, MAX_REQUEST_SIZE)
+		}
+
+		/// Starts listening on unix socket at `path`, serving at most `max_connections` connections at once.
+		/// Connections sending requests larger than `max_request_size` bytes are closed.
+		pub fn start_with_limits(path: &str, handler: Arc<IpcHandler>, max_connections: usize, max_request_size: usize) -> io::Result<Self> {
+			if fs::symlink_metadata(path).map(|metadata| metadata.file_type().is_socket()).unwrap_or(false) {
+				try!(fs::remove_file(path));
+			}
+			let listener = try!(UnixListener::bind(path));
+			let closed = Arc::new(AtomicBool::new(false));
+			let connections = Arc::new(Mutex::new(HashMap::new()));
+			let thread = {
+				let closed = closed.clone();
+				let connections = connections.clone();
+				try!(thread::Builder::new().name("jsonrpc-ipc".into()).spawn(move || accept(listener, handler, closed, connections, max_connections, max_request_size)))
+			};
+
+			Ok(IpcServer {
+				path: path.to_owned(),
+				closed: closed,
+				connections: connections,
+				listener: Some(thread),
+			})
+		}
+	}
+
+	impl Drop for IpcServer {
+		fn drop(&mut self) {
+			self.closed.store(true, Ordering::SeqCst);
+			// Wake up the listener blocked on accept.
+			let _ = UnixStream::connect(&self.path);
+			if let Some(listener) = self.listener.take() {
+				let _ = listener.join();
+			}
+			for (_, connection) in self.connections.lock().unwrap().drain() {
+				let _ = connection.shutdown(Shutdown::Both);
+			}
+			let _ = fs::remove_file(&self.path);
+		}
+	}
+
+	fn accept(listener: UnixListener, handler: Arc<IpcHandler>, closed: Arc<AtomicBool>, connections: Connections, max_connections: usize, max_request_size: usize) {
+		for (id, stream) in listener.incoming().enumerate() {
+			if closed.load(Ordering::SeqCst) {
+				break;
+			}
+			let stream = match stream {
+				Ok(ref stream) if connections.lock().unwrap().len() >= max_connections => {
+					debug!(target: "rpc", "Rejecting IPC connection {}: too many connections.", id);
+					let _ = stream.shutdown(Shutdown::Both);
+					continue;
+				},
+				Ok(ref stream) if !handler.accepts(stream.as_raw_fd()) => {
+					debug!(target: "rpc", "Rejecting IPC connection {}: access denied by policy.", id);
+					let mut writer = stream;
+					let _ = writer.write_all(ACCESS_DENIED_RESPONSE.as_bytes());
+					let _ = stream.shutdown(Shutdown::Both);
+					continue;
+				},
+				stream => stream,
+			};
+			let stream = match stream.and_then(|stream| stream.try_clone().map(|clone| (stream, clone))) {
+				Ok((stream, clone)) => {
+					connections.lock().unwrap().insert(id, clone);
+					stream
+				},
+				Err(e) => {
+					warn!(target: "rpc", "Unable to accept IPC connection: {:?}", e);
+					continue;
+				},
+			};
+
+			let handler = handler.clone();
+			let connection_registry = connections.clone();
+			let spawned = thread::Builder::new().name(format!("jsonrpc-ipc-{}", id)).spawn(move || {
+				if let Err(e) = serve(stream, &*handler, max_request_size) {
+					debug!(target: "rpc", "IPC connection {} closed: {:?}", id, e);
+				}
+				connection_registry.lock().unwrap().remove(&id);
+			});
+			if let Err(e) = spawned {
+				warn!(target: "rpc", "Unable to spawn thread for IPC connection: {:?}", e);
+				connections.lock().unwrap().remove(&id);
+			}
+		}
+	}
+
+	/// Handles requests of single connection until it's closed.
+	fn serve(mut stream: UnixStream, handler: &IpcHandler, max_request_size: usize) -> io::Result<()> {
+		let fd = stream.as_raw_fd();
+		let mut requests = RequestBuffer::with_limit(max_request_size);
+		let mut buf = [0u8; 4096];
+		loop {
+			let read = match stream.read(&mut buf) {
+				Ok(0) => return Ok(()),
+				Ok(read) => read,
+				Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(e),
+			};
+			let completed = match requests.push(&buf[..read]) {
+				Ok(completed) => completed,
+				Err(e) => {
+					let _ = stream.shutdown(Shutdown::Both);
+					return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)));
+				},
+			};
+			for request in completed {
+				let (response, close) = handler.handle_request(fd, &request);
+				if let Some(response) = response {
+					try!(stream.write_all(response.as_bytes()));
+					try!(stream.write_all(b"\n"));
+				}
+				if close {
+					return stream.shutdown(Shutdown::Both);
+				}
+			}
+		}
+	}
+}
+
+#[cfg(not(unix))]
+mod named_pipe {
+	use std::io;
+	use std::sync::Arc;
+	use jsonrpc_core::IoHandler;
+	use ipc;
+
+	/// Running IPC server.
+	pub struct IpcServer {
+		_server: ipc::Server,
+	}
+
+	impl IpcServer {
+		/// Starts serving named pipe at `path`.
+		pub fn start(path: &str, handler: Arc<IoHandler>) -> io::Result<Self> {
+			let server = try!(ipc::Server::new(path, &handler).map_err(to_io_error));
+			try!(server.run_async().map_err(to_io_error));
+			Ok(IpcServer {
+				_server: server,
+			})
+		}
+	}
+
+	fn to_io_error(error: ipc::Error) -> io::Error {
+		match error {
+			ipc::Error::Io(e) => e,
+			other => io::Error::new(io::ErrorKind::Other, format!("{:?}", other)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{RequestBuffer, RequestTooLarge};
+
+	#[test]
+	fn should_split_complete_requests() {
+		// given
+		let mut buffer = RequestBuffer::default();
+
+		// when
+		let first = buffer.push(br#"{"id": 1, "params": ["}{"#).unwrap();
+		let second = buffer.push(br#"\""]} [{"id": 2}]"#).unwrap();
+		let third = buffer.push(b"\n {\"id\": 3}\n{\"id\"").unwrap();
+
+		// then
+		assert!(first.is_empty());
+		assert_eq!(second, vec![r#"{"id": 1, "params": ["}{\""]}"#.to_owned(), r#"[{"id": 2}]"#.to_owned()]);
+		assert_eq!(third, vec![r#"{"id": 3}"#.to_owned()]);
+		assert_eq!(buffer.push(b": 4}"), Ok(vec![r#"{"id": 4}"#.to_owned()]));
+	}
+
+	#[test]
+	fn should_reject_requests_exceeding_limit() {
+		// given
+		let mut buffer = RequestBuffer::with_limit(16);
+
+		// when
+		let complete = buffer.push(br#"{"id": 1, "params": []}"#);
+		let incomplete = buffer.push(br#"{"id": 2, "params": ["#);
+
+		// then
+		assert_eq!(complete, Ok(vec![r#"{"id": 1, "params": []}"#.to_owned()]));
+		assert_eq!(incomplete, Err(RequestTooLarge { limit: 16 }));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn should_limit_number_of_connections() {
+		use std::env;
+		use std::io::{Read, Write};
+		use std::os::unix::net::UnixStream;
+		use std::sync::Arc;
+		use std::time::Duration;
+		use jsonrpc_core::IoHandler;
+		use v1::{Web3, Web3Client};
+		use super::IpcServer;
+
+		// given
+		let path = env::temp_dir().join("parity-rpc-test-max-connections.ipc");
+		let path = path.to_str().unwrap();
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+		let _server = IpcServer::start_with_limits(path, Arc::new(io), 1, 1024).unwrap();
+		let mut first = UnixStream::connect(path).unwrap();
+		first.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		first.write_all(br#"{"jsonrpc": "2.0", "method": "web3_clientVersion", "params": [], "id": 1}"#).unwrap();
+		let mut response = [0u8; 1];
+		first.read_exact(&mut response).unwrap();
+
+		// when
+		let mut second = UnixStream::connect(path).unwrap();
+		second.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		let mut rejected = Vec::new();
+		let read = second.read_to_end(&mut rejected);
+
+		// then
+		assert!(read.is_ok(), "Expected connection to be closed, got: {:?}", read);
+		assert!(rejected.is_empty());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn should_serve_concurrent_connections_independently() {
+		use std::env;
+		use std::io::{BufRead, BufReader, Write};
+		use std::os::unix::net::UnixStream;
+		use std::sync::Arc;
+		use std::time::Duration;
+		use jsonrpc_core::IoHandler;
+		use v1::{Web3, Web3Client};
+		use super::IpcServer;
+
+		fn request(data: &str, id: u64) -> String {
+			format!(r#"{{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["{}"], "id": {}}}"#, data, id)
+		}
+
+		fn response(hash: &str, id: u64) -> String {
+			format!(r#"{{"jsonrpc":"2.0","result":"0x{}","id":{}}}"#, hash, id)
+		}
+
+		fn connect(path: &str) -> (UnixStream, BufReader<UnixStream>) {
+			let stream = UnixStream::connect(path).unwrap();
+			stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+			let reader = BufReader::new(stream.try_clone().unwrap());
+			(stream, reader)
+		}
+
+		fn read_response(reader: &mut BufReader<UnixStream>) -> String {
+			let mut line = String::new();
+			reader.read_line(&mut line).unwrap();
+			line.trim_right().to_owned()
+		}
+
+		// given
+		const HASH_00: &'static str = "bc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a";
+		const HASH_HELLO: &'static str = "47173285a8d7341e5e972fc677286384f802f8ef42a5ec5f03bbfa254cb01fad";
+		let path = env::temp_dir().join("parity-rpc-test-concurrent.ipc");
+		let path = path.to_str().unwrap();
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+		let _server = IpcServer::start(path, Arc::new(io)).unwrap();
+		let (mut first, mut first_reader) = connect(path);
+		let (mut second, mut second_reader) = connect(path);
+
+		// when
+		let first_request = request("0x00", 1);
+		let (head, tail) = first_request.split_at(first_request.len() / 2);
+		first.write_all(head.as_bytes()).unwrap();
+		second.write_all(request("0x68656c6c6f20776f726c64", 1).as_bytes()).unwrap();
+		let second_response = read_response(&mut second_reader);
+		first.write_all(format!("{}{}", tail, request("0x68656c6c6f20776f726c64", 2)).as_bytes()).unwrap();
+		second.write_all(request("0x00", 2).as_bytes()).unwrap();
+
+		// then
+		assert_eq!(second_response, response(HASH_HELLO, 1));
+		assert_eq!(read_response(&mut first_reader), response(HASH_00, 1));
+		assert_eq!(read_response(&mut first_reader), response(HASH_HELLO, 2));
+		assert_eq!(read_response(&mut second_reader), response(HASH_00, 2));
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn should_close_connections_rejected_by_policy() {
+		use std::env;
+		use std::io::Read;
+		use std::os::unix::net::UnixStream;
+		use std::sync::Arc;
+		use std::time::Duration;
+		use jsonrpc_core::IoHandler;
+		use ipc_access::{IpcAccessControl, IpcAccessPolicy, CredentialsResolver, PeerCredentials, SocketFd, ACCESS_DENIED_RESPONSE};
+		use read_only::FilteredHandler;
+		use super::IpcServer;
+
+		struct Stranger;
+
+		impl CredentialsResolver for Stranger {
+			fn resolve(&self, _fd: SocketFd) -> Option<PeerCredentials> {
+				Some(PeerCredentials { uid: 1001, gid: 1001 })
+			}
+		}
+
+		// given
+		let path = env::temp_dir().join("parity-rpc-test-rejected.ipc");
+		let path = path.to_str().unwrap();
+		let policy = IpcAccessPolicy { owner_uid: 1000, safe_gid: None };
+		let handler = || Arc::new(FilteredHandler::new(Arc::new(IoHandler::new()), Default::default()));
+		let control = IpcAccessControl::with_resolver(handler(), handler(), policy, Box::new(Stranger));
+		let _server = IpcServer::start(path, Arc::new(control)).unwrap();
+
+		// when
+		let mut stream = UnixStream::connect(path).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		let mut response = Vec::new();
+		let read = stream.read_to_end(&mut response);
+
+		// then
+		assert!(read.is_ok(), "Expected connection to be closed, got: {:?}", read);
+		assert_eq!(String::from_utf8(response).unwrap(), ACCESS_DENIED_RESPONSE);
+	}
+}
@@ -22,16 +22,20 @@
 #[macro_use]
 extern crate log;
 extern crate rustc_serialize;
+extern crate crypto;
 extern crate serde;
 extern crate serde_json;
 extern crate jsonrpc_core;
 extern crate jsonrpc_http_server;
+extern crate hyper;
 extern crate ethcore_util as util;
 extern crate ethcore;
 extern crate ethsync;
 extern crate ethminer;
 extern crate transient_hashmap;
 extern crate json_ipc_server as ipc;
+extern crate libc;
+#[cfg(test)] extern crate ethcore_devtools as devtools;
 
 use std::sync::Arc;
 use std::net::SocketAddr;
@@ -39,37 +43,140 @@ use self::jsonrpc_core::{IoHandler, IoDelegate};
 
 pub use jsonrpc_http_server::{Server, RpcServerError};
 pub mod v1;
+pub mod ipc_access;
+pub mod ipc_server;
+pub mod pooled;
+pub mod origin;
+pub mod panics;
+pub mod read_only;
+pub mod ws;
+pub mod limits;
+pub mod stats;
+
+use ipc_access::IpcAccessPolicy;
+pub use ipc_server::IpcServer;
+use pooled::PooledServer;
+pub use ws::WsServer;
+use util::server_pool::ServerPoolHandle;
+use stats::{RpcStats, CountingHandler};
+use read_only::MethodFilter;
+#[cfg(unix)]
+use read_only::FilteredHandler;
+
+/// Running http server.
+pub enum HttpServer {
+	/// Server with its own listener threads.
+	Standalone(Server),
+	/// Server admitting requests into shared server pool (if any).
+	Pooled(PooledServer),
+}
+
+impl HttpServer {
+	/// Set callback for panics.
+	pub fn set_panic_handler<F>(&self, handler: F) where F : Fn() -> () + Send + 'static {
+		match *self {
+			HttpServer::Standalone(ref server) => server.set_panic_handler(handler),
+			HttpServer::Pooled(ref server) => server.set_panic_handler(handler),
+		}
+	}
+}
 
 /// Http server.
 pub struct RpcServer {
 	handler: Arc<jsonrpc_core::io::IoHandler>,
+	stats: Arc<RpcStats>,
+	filter: Arc<MethodFilter>,
 }
 
 impl RpcServer {
 	/// Construct new http server object.
 	pub fn new() -> RpcServer {
+		RpcServer::with_stats(Arc::new(RpcStats::default()))
+	}
+
+	/// Construct new http server object recording calls in given `stats`.
+	/// Calls over IPC are not recorded.
+	pub fn with_stats(stats: Arc<RpcStats>) -> RpcServer {
 		RpcServer {
 			handler: Arc::new(IoHandler::new()),
+			stats: stats,
+			filter: Arc::new(MethodFilter::default()),
 		}
 	}
 
+	fn counting_handler(&self) -> Arc<CountingHandler> {
+		Arc::new(CountingHandler::new(self.handler.clone(), self.stats.clone()).with_filter(self.filter.clone()))
+	}
+
+	#[cfg(unix)]
+	fn filtered_handler(&self) -> FilteredHandler {
+		FilteredHandler::new(self.handler.clone(), self.filter.clone())
+	}
+
 	/// Add io delegate.
 	pub fn add_delegate<D>(&self, delegate: IoDelegate<D>) where D: Send + Sync + 'static {
 		self.handler.add_delegate(delegate);
 	}
 
-	/// Start http server asynchronously and returns result with `Server` handle on success or an error.
-	pub fn start_http(&self, addr: &SocketAddr, cors_domains: Vec<String>) -> Result<Server, RpcServerError> {
+	/// Reject all methods which mutate state of the node or touch keys (see `read_only`).
+	/// Applies to every transport, including methods of delegates added later.
+	pub fn restrict_to_read_only(&self) {
+		self.filter.restrict_to_read_only();
+	}
+
+	/// Make methods available only in the unsafe API set (see `read_only::UNSAFE_METHODS`) unavailable.
+	/// Applies to every transport, including methods of delegates added later.
+	pub fn restrict_to_safe(&self) {
+		self.filter.restrict_to_safe();
+	}
+
+	/// Start http server asynchronously and returns result with `HttpServer` handle on success or an error.
+	/// If `pool` is given requests are admitted into the shared server pool and rejected with `503` when it's saturated.
+	/// Batches of more than `max_batch_size` calls are rejected with a single error response.
+	pub fn start_http(&self, addr: &SocketAddr, cors_domains: Vec<String>, pool: Option<ServerPoolHandle>, max_batch_size: Option<usize>) -> Result<HttpServer, RpcServerError> {
 		let cors_domains = cors_domains.into_iter()
 			.map(jsonrpc_http_server::AccessControlAllowOrigin::Value)
 			.collect();
-		Server::start(addr, self.handler.clone(), cors_domains)
+		PooledServer::start(addr, self.counting_handler(), cors_domains, pool, max_batch_size).map(HttpServer::Pooled)
+	}
+
+	/// Start WebSockets server asynchronously and returns result with `WsServer` handle on success or an error.
+	/// Only connections from given `origins` are accepted (any if empty).
+	pub fn start_ws(&self, addr: &SocketAddr, origins: Vec<String>) -> Result<WsServer, std::io::Error> {
+		WsServer::start(addr, self.counting_handler(), origins)
 	}
 
-	/// Start ipc server asynchronously and returns result with `Server` handle on success or an error.
-	pub fn start_ipc(&self, addr: &str) -> Result<ipc::Server, ipc::Error> {
-		let server = try!(ipc::Server::new(addr, &self.handler));
-		try!(server.run_async());
+	/// Start ipc server asynchronously and returns result with `IpcServer` handle on success or an error.
+	#[cfg(unix)]
+	pub fn start_ipc(&self, addr: &str) -> Result<IpcServer, std::io::Error> {
+		IpcServer::start(addr, Arc::new(self.filtered_handler()))
+	}
+
+	/// Start ipc server asynchronously and returns result with `IpcServer` handle on success or an error.
+	/// Named pipes hand requests straight to the `IoHandler`, so it has to be called after all delegates are added.
+	#[cfg(not(unix))]
+	pub fn start_ipc(&self, addr: &str) -> Result<IpcServer, std::io::Error> {
+		self.filter.apply_to(&self.handler);
+		IpcServer::start(addr, self.handler.clone())
+	}
+
+	/// Start ipc server with access restricted by given policy.
+	///
+	/// Each connection is served according to credentials of its peer, connections from members
+	/// of `policy.safe_gid` are served by `safe` handler. Socket file permissions are restricted accordingly.
+	#[cfg(unix)]
+	pub fn start_ipc_with_policy(&self, addr: &str, safe: &RpcServer, policy: IpcAccessPolicy) -> Result<IpcServer, std::io::Error> {
+		let access = ipc_access::IpcAccessControl::new(Arc::new(self.filtered_handler()), Arc::new(safe.filtered_handler()), policy.clone());
+		let server = try!(IpcServer::start(addr, Arc::new(access)));
+		try!(ipc_access::restrict_socket(addr, &policy));
 		Ok(server)
 	}
+
+	/// Start ipc server with access restricted by given policy.
+	///
+	/// Credentials of peers are not available on this platform, so every connection gets full access.
+	#[cfg(not(unix))]
+	pub fn start_ipc_with_policy(&self, addr: &str, _safe: &RpcServer, _policy: IpcAccessPolicy) -> Result<IpcServer, std::io::Error> {
+		self.start_ipc(addr)
+	}
 }
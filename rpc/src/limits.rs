@@ -0,0 +1,284 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! JSON-RPC over HTTP with request limits.
+//!
+//! `IoHandler` executes all calls of a batch at once, so the size of the batch
+//! has to be checked by the transport before the request is handled.
+//! Request bodies bigger than `MAX_REQUEST_SIZE` are rejected before they are read completely.
+
+use std::{io, mem, str};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use hyper::{header, server, Decoder, Encoder, Next};
+use hyper::method::Method;
+use hyper::net::HttpStream;
+use hyper::status::StatusCode;
+use jsonrpc_http_server::AccessControlAllowOrigin;
+use serde_json::{self, Value};
+use stats::CountingHandler;
+use pooled::Dispatcher;
+use panics::{PanicGuard, PanicHandlerCallback};
+
+/// JSON-RPC error code of invalid request.
+const INVALID_REQUEST: i64 = -32600;
+/// Maximal size of request body in bytes.
+pub const MAX_REQUEST_SIZE: usize = 5 * 1024 * 1024;
+
+/// Returns error response if `request` is a batch of more than `max_batch_size` calls.
+pub fn check_batch_size(request: &Value, max_batch_size: usize) -> Option<String> {
+	match *request {
+		Value::Array(ref calls) if calls.len() > max_batch_size => Some(format!(
+			r#"{{"jsonrpc":"2.0","error":{{"code":{},"message":"Batch of {} calls exceeds the limit of {} calls."}},"id":null}}"#,
+			INVALID_REQUEST, calls.len(), max_batch_size
+		)),
+		_ => None,
+	}
+}
+
+/// Returns CORS header for request with given `Origin` (if any).
+fn cors_header(domains: &[AccessControlAllowOrigin], origin: Option<&str>) -> Option<AccessControlAllowOrigin> {
+	domains.iter().find(|domain| match **domain {
+		AccessControlAllowOrigin::Value(ref value) => value == "*" || Some(value.as_str()) == origin,
+		_ => true,
+	}).cloned()
+}
+
+/// Handles JSON-RPC `request` unless it's a batch of more than `max_batch_size` calls.
+fn handle_request(handler: &CountingHandler, panic_handler: PanicHandlerCallback, max_batch_size: Option<usize>, request: Vec<u8>) -> Vec<u8> {
+	let request = String::from_utf8_lossy(&request).into_owned();
+	let _guard = PanicGuard(panic_handler);
+	let response = match serde_json::from_str::<Value>(&request) {
+		Ok(parsed) => {
+			if let Some(error) = max_batch_size.and_then(|max| check_batch_size(&parsed, max)) {
+				debug!(target: "rpc", "Rejecting batch request: {}", error);
+				return error.into_bytes();
+			}
+			handler.handle_parsed_request(&parsed, &request)
+		},
+		// Malformed requests are left to the `IoHandler`.
+		Err(_) => handler.handle_request(&request),
+	};
+	response.map_or_else(Vec::new, String::into_bytes)
+}
+
+/// JSON-RPC HTTP handler rejecting batches bigger than `max_batch_size` with a single error response
+/// and request bodies bigger than `MAX_REQUEST_SIZE` with `413 Payload Too Large`.
+///
+/// Requests are handled through the `Dispatcher`, i.e. on the server pool threads if there is a pool.
+pub struct LimitedHandler {
+	handler: Arc<CountingHandler>,
+	cors_domains: Vec<AccessControlAllowOrigin>,
+	panic_handler: PanicHandlerCallback,
+	max_batch_size: Option<usize>,
+	dispatcher: Dispatcher,
+	status: StatusCode,
+	cors: Option<AccessControlAllowOrigin>,
+	request: Vec<u8>,
+	result: Arc<Mutex<Vec<u8>>>,
+	response: Vec<u8>,
+	write_pos: usize,
+}
+
+impl LimitedHandler {
+	/// Creates new handler for single request.
+	pub fn new(handler: Arc<CountingHandler>, cors_domains: Vec<AccessControlAllowOrigin>, panic_handler: PanicHandlerCallback, max_batch_size: Option<usize>, dispatcher: Dispatcher) -> Self {
+		LimitedHandler {
+			handler: handler,
+			cors_domains: cors_domains,
+			panic_handler: panic_handler,
+			max_batch_size: max_batch_size,
+			dispatcher: dispatcher,
+			status: StatusCode::Ok,
+			cors: None,
+			request: Vec::new(),
+			result: Arc::new(Mutex::new(Vec::new())),
+			response: Vec::new(),
+			write_pos: 0,
+		}
+	}
+}
+
+impl server::Handler<HttpStream> for LimitedHandler {
+	fn on_request(&mut self, request: server::Request) -> Next {
+		let origin = request.headers().get_raw("Origin")
+			.and_then(|values| values.first())
+			.and_then(|value| str::from_utf8(value).ok());
+		self.cors = cors_header(&self.cors_domains, origin);
+
+		let too_large = request.headers().get::<header::ContentLength>().map_or(false, |length| length.0 > MAX_REQUEST_SIZE as u64);
+		match *request.method() {
+			Method::Post if too_large => {
+				self.status = StatusCode::PayloadTooLarge;
+				Next::write()
+			},
+			Method::Post => Next::read(),
+			Method::Options => Next::write(),
+			_ => {
+				self.status = StatusCode::MethodNotAllowed;
+				Next::write()
+			},
+		}
+	}
+
+	fn on_request_readable(&mut self, decoder: &mut Decoder<HttpStream>) -> Next {
+		let mut buf = [0u8; 4096];
+		loop {
+			match decoder.read(&mut buf) {
+				Ok(0) => break,
+				Ok(read) if self.request.len() + read > MAX_REQUEST_SIZE => {
+					self.status = StatusCode::PayloadTooLarge;
+					self.request = Vec::new();
+					return Next::write();
+				},
+				Ok(read) => self.request.extend_from_slice(&buf[..read]),
+				Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Next::read(),
+				Err(e) => {
+					debug!(target: "rpc", "Unable to read request: {:?}", e);
+					return Next::end();
+				},
+			}
+		}
+		let handler = self.handler.clone();
+		let panic_handler = self.panic_handler.clone();
+		let max_batch_size = self.max_batch_size;
+		let request = mem::replace(&mut self.request, Vec::new());
+		let result = self.result.clone();
+		self.dispatcher.dispatch(move || {
+			*result.lock().unwrap() = handle_request(&handler, panic_handler, max_batch_size, request);
+		})
+	}
+
+	fn on_response(&mut self, response: &mut server::Response) -> Next {
+		self.response = mem::replace(&mut *self.result.lock().unwrap(), Vec::new());
+		response.set_status(self.status);
+		if let Some(ref cors) = self.cors {
+			response.headers_mut().set(cors.clone());
+			response.headers_mut().set_raw("Access-Control-Allow-Methods", vec![b"OPTIONS, POST".to_vec()]);
+			response.headers_mut().set_raw("Access-Control-Allow-Headers", vec![b"Content-Type".to_vec()]);
+		}
+		response.headers_mut().set(header::ContentType("application/json".parse().unwrap()));
+		response.headers_mut().set(header::ContentLength(self.response.len() as u64));
+		Next::write()
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		if self.write_pos == self.response.len() {
+			return Next::end();
+		}
+		match encoder.write(&self.response[self.write_pos..]) {
+			Ok(bytes) => {
+				self.write_pos += bytes;
+				Next::write()
+			},
+			Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Next::write(),
+			Err(_) => Next::end(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+	use std::net::{SocketAddr, TcpStream};
+	use std::str::FromStr;
+	use std::sync::Arc;
+	use std::time::Duration;
+	use jsonrpc_core::IoHandler;
+	use v1::{Web3, Web3Client};
+	use pooled::PooledServer;
+	use serde_json::{self, Value};
+	use stats::{CountingHandler, RpcStats};
+	use super::{check_batch_size, MAX_REQUEST_SIZE};
+
+	fn batch(size: usize) -> String {
+		let calls = (0..size)
+			.map(|id| format!(r#"{{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["0x00"], "id": {}}}"#, id))
+			.collect::<Vec<_>>();
+		format!("[{}]", calls.join(","))
+	}
+
+	fn post(addr: &SocketAddr, body: &str) -> String {
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		stream.write_all(format!(
+			"POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			addr, body.len(), body
+		).as_bytes()).unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+		response
+	}
+
+	/// Address for a test server to listen on, the port is chosen by the OS.
+	fn any_port() -> SocketAddr {
+		SocketAddr::from_str("127.0.0.1:0").unwrap()
+	}
+
+	fn parse(request: &str) -> Value {
+		serde_json::from_str(request).unwrap()
+	}
+
+	#[test]
+	fn should_check_batch_size() {
+		assert_eq!(check_batch_size(&parse(&batch(2)), 2), None);
+		assert_eq!(check_batch_size(&parse(r#"{"jsonrpc": "2.0", "method": "web3_sha3", "params": [], "id": 1}"#), 0), None);
+		assert_eq!(
+			check_batch_size(&parse(&batch(3)), 2),
+			Some(r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Batch of 3 calls exceeds the limit of 2 calls."},"id":null}"#.to_owned())
+		);
+	}
+
+	#[test]
+	fn should_reject_oversized_batches_with_single_error() {
+		// given
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+		let handler = CountingHandler::new(Arc::new(io), Arc::new(RpcStats::default()));
+		let server = PooledServer::start(&any_port(), Arc::new(handler), vec![], None, Some(100)).unwrap();
+		let addr = server.addr();
+
+		// when
+		let rejected = post(&addr, &batch(10_000));
+		let accepted = post(&addr, &batch(100));
+
+		// then
+		assert!(rejected.starts_with("HTTP/1.1 200"), "Unexpected response: {}", rejected);
+		assert!(rejected.ends_with(r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"Batch of 10000 calls exceeds the limit of 100 calls."},"id":null}"#));
+		assert_eq!(accepted.matches(r#""result""#).count(), 100);
+	}
+
+	#[test]
+	fn should_reject_too_large_requests() {
+		// given
+		let handler = CountingHandler::new(Arc::new(IoHandler::new()), Arc::new(RpcStats::default()));
+		let server = PooledServer::start(&any_port(), Arc::new(handler), vec![], None, None).unwrap();
+		let addr = server.addr();
+		let mut stream = TcpStream::connect(&addr).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+		// when
+		stream.write_all(format!(
+			"POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+			addr, MAX_REQUEST_SIZE + 1
+		).as_bytes()).unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+
+		// then
+		assert!(response.starts_with("HTTP/1.1 413"), "Unexpected response: {}", response);
+	}
+}
@@ -0,0 +1,219 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Attribution of RPC requests to their origin.
+//!
+//! `IoHandler` doesn't pass any request metadata to the methods, so transports
+//! set the origin of request being handled for the current thread (see `with_origin`)
+//! and methods submitting transactions read it with `current_origin`.
+
+use std::mem;
+use std::cell::RefCell;
+use hyper::{header, server, Decoder, Encoder, Next};
+use hyper::net::HttpStream;
+use rustc_serialize::hex::ToHex;
+use util::sha3::Hashable;
+use ethminer::SubmissionOrigin;
+use ipc_access::PeerCredentials;
+
+const DAPPS_DOMAIN: &'static str = ".parity";
+
+thread_local!(static CURRENT_ORIGIN: RefCell<Option<SubmissionOrigin>> = RefCell::new(None));
+
+/// Restores previous origin of the thread when dropped (even on panic).
+struct OriginGuard(Option<SubmissionOrigin>);
+
+impl Drop for OriginGuard {
+	fn drop(&mut self) {
+		let previous = self.0.take();
+		CURRENT_ORIGIN.with(|origin| *origin.borrow_mut() = previous);
+	}
+}
+
+/// Runs `f` with `origin` set as origin of requests handled by current thread.
+pub fn with_origin<F, T>(origin: SubmissionOrigin, f: F) -> T where F: FnOnce() -> T {
+	let previous = CURRENT_ORIGIN.with(|current| mem::replace(&mut *current.borrow_mut(), Some(origin)));
+	let _guard = OriginGuard(previous);
+	f()
+}
+
+/// Returns origin of request handled by current thread or `None` if it's unknown.
+pub fn current_origin() -> Option<SubmissionOrigin> {
+	CURRENT_ORIGIN.with(|origin| origin.borrow().clone())
+}
+
+/// Returns origin of IPC connection with given peer credentials.
+pub fn ipc_origin(credentials: Option<PeerCredentials>) -> SubmissionOrigin {
+	match credentials {
+		Some(c) => SubmissionOrigin::new(format!("ipc:uid={}", c.uid)),
+		None => SubmissionOrigin::anonymous(),
+	}
+}
+
+/// Extracts name of the dapp from its URL (`http://wallet.parity/` or `http://localhost:8080/wallet/`).
+fn dapp_from_url(url: &str) -> Option<String> {
+	let url = url.splitn(2, "://").last().unwrap_or("");
+	let mut parts = url.splitn(2, '/');
+	let host = parts.next().unwrap_or("").split(':').next().unwrap_or("");
+	let path = parts.next().unwrap_or("").split(|c| c == '/' || c == '?' || c == '#').next().unwrap_or("");
+
+	if host.ends_with(DAPPS_DOMAIN) {
+		return Some(host[..host.len() - DAPPS_DOMAIN.len()].to_owned());
+	}
+	match (path.is_empty(), host.is_empty()) {
+		(false, _) => Some(path.to_owned()),
+		(true, false) => Some(host.to_owned()),
+		(true, true) => None,
+	}
+}
+
+/// Returns identity of the user or token the request was authenticated with.
+/// Tokens are identified by hash prefix so they don't leak to the audit trail.
+fn authenticated_identity(headers: &header::Headers) -> Option<String> {
+	if let Some(&header::Authorization(header::Basic { ref username, .. })) = headers.get::<header::Authorization<header::Basic>>() {
+		return Some(format!("user:{}", username));
+	}
+	headers.get_raw("Authorization")
+		.and_then(|values| values.first())
+		.and_then(|value| match value.starts_with(b"Bearer ") {
+			true => Some(format!("token:{}", value[7..].sha3()[0..4].to_hex())),
+			false => None,
+		})
+}
+
+/// Returns origin of HTTP request with given headers.
+///
+/// Requests coming through the dapps server (`dapps` is `true`) are attributed to the dapp
+/// (taken from `Referer` or the `Host` of dapp domain), all other requests to the authenticated
+/// user or token. Requests which can't be attributed are anonymous.
+pub fn http_origin(headers: &header::Headers, dapps: bool) -> SubmissionOrigin {
+	let dapp = match dapps {
+		true => headers.get::<header::Referer>()
+			.and_then(|referer| dapp_from_url(referer))
+			.or_else(|| headers.get::<header::Host>().and_then(|host| match host.hostname.ends_with(DAPPS_DOMAIN) {
+				true => dapp_from_url(&host.hostname),
+				false => None,
+			})),
+		false => None,
+	};
+
+	dapp.map(|dapp| format!("dapp:{}", dapp))
+		.or_else(|| authenticated_identity(headers))
+		.map_or_else(SubmissionOrigin::anonymous, SubmissionOrigin::new)
+}
+
+/// Handler attributing requests handled by inner handler to their origin.
+pub struct OriginHandler<H> {
+	handler: H,
+	dapps: bool,
+	origin: SubmissionOrigin,
+}
+
+impl<H> OriginHandler<H> {
+	/// Wraps given handler. `dapps` should be `true` for requests coming through the dapps server.
+	pub fn new(handler: H, dapps: bool) -> Self {
+		OriginHandler {
+			handler: handler,
+			dapps: dapps,
+			origin: SubmissionOrigin::anonymous(),
+		}
+	}
+}
+
+impl<H> server::Handler<HttpStream> for OriginHandler<H> where H: server::Handler<HttpStream> {
+	fn on_request(&mut self, request: server::Request) -> Next {
+		self.origin = http_origin(request.headers(), self.dapps);
+		let handler = &mut self.handler;
+		with_origin(self.origin.clone(), move || handler.on_request(request))
+	}
+
+	fn on_request_readable(&mut self, decoder: &mut Decoder<HttpStream>) -> Next {
+		let handler = &mut self.handler;
+		with_origin(self.origin.clone(), move || handler.on_request_readable(decoder))
+	}
+
+	fn on_response(&mut self, response: &mut server::Response) -> Next {
+		let handler = &mut self.handler;
+		with_origin(self.origin.clone(), move || handler.on_response(response))
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		self.handler.on_response_writable(encoder)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hyper::header::{Headers, Authorization, Basic, Referer, Host};
+	use ethminer::SubmissionOrigin;
+	use ipc_access::PeerCredentials;
+	use super::*;
+
+	fn origin(tag: &str) -> SubmissionOrigin {
+		SubmissionOrigin::new(tag)
+	}
+
+	#[test]
+	fn should_set_origin_for_the_call_only() {
+		assert_eq!(current_origin(), None);
+		with_origin(origin("a"), || {
+			assert_eq!(current_origin(), Some(origin("a")));
+			with_origin(origin("b"), || assert_eq!(current_origin(), Some(origin("b"))));
+			assert_eq!(current_origin(), Some(origin("a")));
+		});
+		assert_eq!(current_origin(), None);
+	}
+
+	#[test]
+	fn should_attribute_http_requests_to_authenticated_user_or_token() {
+		// given
+		let mut basic = Headers::new();
+		basic.set(Authorization(Basic { username: "alice".to_owned(), password: Some("secret".to_owned()) }));
+		let mut bearer = Headers::new();
+		bearer.set_raw("Authorization", vec![b"Bearer abc".to_vec()]);
+
+		// then
+		assert_eq!(http_origin(&basic, false), origin("user:alice"));
+		assert!(http_origin(&bearer, false).tag().starts_with("token:"));
+		assert!(!http_origin(&bearer, false).tag().contains("abc"));
+		assert_eq!(http_origin(&Headers::new(), false), SubmissionOrigin::anonymous());
+	}
+
+	#[test]
+	fn should_attribute_dapps_requests_to_dapp() {
+		// given
+		let mut domain = Headers::new();
+		domain.set(Referer("http://wallet.parity/index.html".to_owned()));
+		let mut path = Headers::new();
+		path.set(Referer("http://localhost:8080/status/".to_owned()));
+		let mut host = Headers::new();
+		host.set(Host { hostname: "wallet.parity".to_owned(), port: None });
+		host.set(Authorization(Basic { username: "alice".to_owned(), password: None }));
+
+		// then
+		assert_eq!(http_origin(&domain, true), origin("dapp:wallet"));
+		assert_eq!(http_origin(&path, true), origin("dapp:status"));
+		assert_eq!(http_origin(&host, true), origin("dapp:wallet"));
+		assert_eq!(http_origin(&host, false), origin("user:alice"));
+		assert_eq!(http_origin(&domain, false), SubmissionOrigin::anonymous());
+	}
+
+	#[test]
+	fn should_attribute_ipc_requests_to_peer_user() {
+		assert_eq!(ipc_origin(Some(PeerCredentials { uid: 1000, gid: 100 })), origin("ipc:uid=1000"));
+		assert_eq!(ipc_origin(None), SubmissionOrigin::anonymous());
+	}
+}
@@ -0,0 +1,38 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Panic notifications shared by the RPC transports.
+
+use std::thread;
+use std::sync::{Arc, Mutex};
+
+/// Shared callback notified about panics while handling requests.
+pub type PanicHandlerCallback = Arc<Mutex<Option<Box<Fn() -> () + Send>>>>;
+
+/// Notifies panic handler if the thread panics while the guard is alive.
+pub struct PanicGuard(pub PanicHandlerCallback);
+
+impl Drop for PanicGuard {
+	fn drop(&mut self) {
+		if thread::panicking() {
+			if let Ok(handler) = self.0.lock() {
+				if let Some(ref handler) = *handler {
+					handler();
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,231 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! HTTP servers sharing `ServerPool`.
+
+use std::sync::{Arc, Mutex};
+use std::net::SocketAddr;
+use hyper::{self, server, Control, Decoder, Encoder, Next};
+use hyper::net::HttpStream;
+use hyper::status::StatusCode;
+use jsonrpc_http_server::{AccessControlAllowOrigin, RpcServerError};
+use util::server_pool::{ServerPoolHandle, PoolTicket};
+use origin::{self, OriginHandler};
+use limits::LimitedHandler;
+use stats::CountingHandler;
+use panics::PanicHandlerCallback;
+
+/// Runs blocking part of admitted request on the pool threads.
+pub struct Dispatcher {
+	ticket: Option<PoolTicket>,
+	control: Control,
+}
+
+impl Dispatcher {
+	/// Runs `job` on the pool threads and resumes the request with `Next::write()` once it's done.
+	/// Returns `Next` the handler should wait for. Without a pool `job` is run in place.
+	///
+	/// Origin of the request (see `origin::current_origin`) is preserved for the job.
+	/// Only the first job of a request is run on the pool threads.
+	pub fn dispatch<F>(&mut self, job: F) -> Next where F: FnOnce() + Send + 'static {
+		match self.ticket.take() {
+			None => {
+				job();
+				Next::write()
+			},
+			Some(ticket) => {
+				let control = self.control.clone();
+				let origin = origin::current_origin();
+				ticket.execute(move || {
+					match origin {
+						Some(origin) => origin::with_origin(origin, job),
+						None => job(),
+					}
+					// Connection might have been closed in the meantime.
+					let _ = control.ready(Next::write());
+				});
+				Next::wait()
+			},
+		}
+	}
+}
+
+/// Handler admitting requests into shared server pool.
+///
+/// Requests are rejected with `503 Service Unavailable` when the pool is saturated.
+pub enum PooledHandler<H> {
+	/// Admitted request. Pool ticket (if any) is released once the request is done.
+	Admitted(H),
+	/// Rejected request.
+	Rejected,
+}
+
+impl<H> PooledHandler<H> where H: server::Handler<HttpStream> {
+	/// Admits request into the pool (if given) and creates inner handler on success.
+	/// Inner handler should run its blocking part through given `Dispatcher`.
+	pub fn new<F>(pool: Option<&ServerPoolHandle>, control: Control, handler: F) -> Self where F: FnOnce(Dispatcher) -> H {
+		let ticket = match pool.map(ServerPoolHandle::acquire) {
+			None => None,
+			Some(Ok(ticket)) => Some(ticket),
+			Some(Err(e)) => {
+				debug!(target: "rpc", "Rejecting request: {}", e);
+				return PooledHandler::Rejected;
+			},
+		};
+		PooledHandler::Admitted(handler(Dispatcher {
+			ticket: ticket,
+			control: control,
+		}))
+	}
+}
+
+impl<H> server::Handler<HttpStream> for PooledHandler<H> where H: server::Handler<HttpStream> {
+	fn on_request(&mut self, request: server::Request) -> Next {
+		match *self {
+			PooledHandler::Admitted(ref mut handler) => handler.on_request(request),
+			PooledHandler::Rejected => Next::write(),
+		}
+	}
+
+	fn on_request_readable(&mut self, decoder: &mut Decoder<HttpStream>) -> Next {
+		match *self {
+			PooledHandler::Admitted(ref mut handler) => handler.on_request_readable(decoder),
+			PooledHandler::Rejected => Next::write(),
+		}
+	}
+
+	fn on_response(&mut self, response: &mut server::Response) -> Next {
+		match *self {
+			PooledHandler::Admitted(ref mut handler) => handler.on_response(response),
+			PooledHandler::Rejected => {
+				response.set_status(StatusCode::ServiceUnavailable);
+				Next::write()
+			},
+		}
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		match *self {
+			PooledHandler::Admitted(ref mut handler) => handler.on_response_writable(encoder),
+			PooledHandler::Rejected => Next::end(),
+		}
+	}
+}
+
+/// JSON-RPC HTTP server (optionally) admitting requests into shared server pool.
+///
+/// Requests are attributed to their origin (see `origin::http_origin`).
+pub struct PooledServer {
+	server: Option<server::Listening>,
+	panic_handler: PanicHandlerCallback,
+}
+
+impl PooledServer {
+	/// Starts server asynchronously. Requests are admitted into `pool` if given.
+	/// Batches of more than `max_batch_size` calls are rejected (see `limits::LimitedHandler`).
+	pub fn start(addr: &SocketAddr, handler: Arc<CountingHandler>, cors_domains: Vec<AccessControlAllowOrigin>, pool: Option<ServerPoolHandle>, max_batch_size: Option<usize>) -> Result<Self, RpcServerError> {
+		let panic_handler: PanicHandlerCallback = Arc::new(Mutex::new(None));
+		let handler_panic = panic_handler.clone();
+		try!(hyper::Server::http(addr))
+			.handle(move |control| PooledHandler::new(pool.as_ref(), control, |dispatcher| {
+				OriginHandler::new(LimitedHandler::new(handler.clone(), cors_domains.clone(), handler_panic.clone(), max_batch_size, dispatcher), false)
+			}))
+			.map(|l| PooledServer {
+				server: Some(l),
+				panic_handler: panic_handler,
+			})
+			.map_err(RpcServerError::from)
+	}
+
+	/// Returns address the server is listening on.
+	pub fn addr(&self) -> SocketAddr {
+		self.server.as_ref().expect("Server is only taken on drop; qed").socket
+	}
+
+	/// Set callback for panics.
+	pub fn set_panic_handler<F>(&self, handler: F) where F : Fn() -> () + Send + 'static {
+		*self.panic_handler.lock().unwrap() = Some(Box::new(handler));
+	}
+}
+
+impl Drop for PooledServer {
+	fn drop(&mut self) {
+		self.server.take().unwrap().close()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+	use std::net::{SocketAddr, TcpStream};
+	use std::str::FromStr;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::thread;
+	use std::time::Duration;
+	use jsonrpc_core::{IoHandler, MethodCommand, Params, Value, Error};
+	use util::server_pool::{ServerPool, ServerPoolConfig};
+	use stats::{CountingHandler, RpcStats};
+	use super::PooledServer;
+
+	/// Returns `true` if the other call was running at the same time.
+	struct Rendezvous(Arc<AtomicUsize>);
+
+	impl MethodCommand for Rendezvous {
+		fn execute(&self, _params: Params) -> Result<Value, Error> {
+			self.0.fetch_add(1, Ordering::SeqCst);
+			let met = (0..500).any(|_| {
+				thread::sleep(Duration::from_millis(10));
+				self.0.load(Ordering::SeqCst) == 2
+			});
+			Ok(Value::Bool(met))
+		}
+	}
+
+	fn post(addr: SocketAddr, body: &'static str) -> String {
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+		stream.write_all(format!(
+			"POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			addr, body.len(), body
+		).as_bytes()).unwrap();
+		let mut response = String::new();
+		stream.read_to_string(&mut response).unwrap();
+		response
+	}
+
+	#[test]
+	fn should_handle_requests_concurrently_on_pool_threads() {
+		// given
+		let pool = ServerPool::new(ServerPoolConfig { threads: 2, ..ServerPoolConfig::default() });
+		let io = IoHandler::new();
+		io.add_method("test_rendezvous", Rendezvous(Arc::new(AtomicUsize::new(0))));
+		let handler = CountingHandler::new(Arc::new(io), Arc::new(RpcStats::default()));
+		let server = PooledServer::start(&SocketAddr::from_str("127.0.0.1:0").unwrap(), Arc::new(handler), vec![], Some(pool.register("rpc")), None).unwrap();
+		let addr = server.addr();
+
+		// when
+		let requests = (0..2).map(|_| thread::spawn(move || {
+			post(addr, r#"{"jsonrpc": "2.0", "method": "test_rendezvous", "params": [], "id": 1}"#)
+		})).collect::<Vec<_>>();
+
+		// then
+		for request in requests {
+			let response = request.join().unwrap();
+			assert!(response.ends_with(r#"{"jsonrpc":"2.0","result":true,"id":1}"#), "Unexpected response: {}", response);
+		}
+	}
+}
@@ -0,0 +1,394 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Read-only mode of RPC servers.
+//!
+//! Publicly exposed query nodes can't allow anything that mutates node state or touches keys,
+//! no matter which API sets were enabled. All methods are classified here, in one place,
+//! and every transport passes calls through `MethodFilter` which rejects restricted methods
+//! by name before they reach the `IoHandler`, so methods registered later can't bypass it.
+//! Methods which are too expensive to be exposed in the safe API set are classified here as well.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use jsonrpc_core::{IoHandler, MethodCommand, Params, Value, Error, ErrorCode};
+use serde_json;
+use origin;
+
+/// Error code returned when restricted method is called on a read-only node.
+pub const READ_ONLY_ERROR_CODE: i64 = -32011;
+/// JSON-RPC error code of unknown method.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Methods which mutate state of the node or touch keys.
+pub const RESTRICTED_METHODS: &'static [&'static str] = &[
+	"eth_accounts",
+	"eth_sign",
+	"eth_sendTransaction",
+	"eth_signAndSendTransaction",
+	"eth_sendRawTransaction",
+	"eth_getWork",
+	"eth_submitWork",
+	"eth_submitHashrate",
+	"ethcore_setMinGasPrice",
+	"ethcore_setGasFloorTarget",
+	"ethcore_setExtraData",
+	"ethcore_setAuthor",
+	"ethcore_setTransactionsLimit",
+	"ethcore_setSealingEnabled",
+	"ethcore_banAddress",
+	"ethcore_unbanAddress",
+	"ethcore_resetRpcStats",
+	"personal_listAccounts",
+	"personal_newAccount",
+	"personal_unlockAccount",
+];
+
+/// Methods which only query the node and are available in read-only mode.
+pub const QUERY_METHODS: &'static [&'static str] = &[
+	"web3_clientVersion",
+	"web3_sha3",
+	"net_version",
+	"net_peerCount",
+	"net_listening",
+	"eth_protocolVersion",
+	"eth_syncing",
+	"eth_hashrate",
+	"eth_coinbase",
+	"eth_mining",
+	"eth_chainId",
+	"eth_gasPrice",
+	"eth_blockNumber",
+	"eth_getBalance",
+	"eth_getStorageAt",
+	"eth_getTransactionCount",
+	"eth_getBlockTransactionCountByHash",
+	"eth_getBlockTransactionCountByNumber",
+	"eth_getUncleCountByBlockHash",
+	"eth_getUncleCountByBlockNumber",
+	"eth_getCode",
+	"eth_call",
+	"eth_estimateGas",
+	"eth_getBlockByHash",
+	"eth_getBlockByNumber",
+	"eth_getTransactionByHash",
+	"eth_getTransactionByBlockHashAndIndex",
+	"eth_getTransactionByBlockNumberAndIndex",
+	"eth_getTransactionReceipt",
+	"eth_getUncleByBlockHashAndIndex",
+	"eth_getUncleByBlockNumberAndIndex",
+	"eth_getCompilers",
+	"eth_compileLLL",
+	"eth_compileSolidity",
+	"eth_compileSerpent",
+	"eth_getLogs",
+	"eth_newFilter",
+	"eth_newBlockFilter",
+	"eth_newPendingTransactionFilter",
+	"eth_getFilterChanges",
+	"eth_getFilterLogs",
+	"eth_uninstallFilter",
+	"ethcore_extraData",
+	"ethcore_gasFloorTarget",
+	"ethcore_minGasPrice",
+	"ethcore_transactionsLimit",
+	"ethcore_sealingEnabled",
+	"ethcore_devLogs",
+	"ethcore_devLogsLevels",
+	"ethcore_netChain",
+	"ethcore_netMaxPeers",
+	"ethcore_netPort",
+	"ethcore_rpcSettings",
+	"ethcore_nodeName",
+	"ethcore_defaultExtraData",
+	"ethcore_stateStats",
+	"ethcore_transactionAudit",
+	"ethcore_submissionStats",
+	"ethcore_bundleStatus",
+	"ethcore_bannedAddresses",
+	"ethcore_rpcStats",
+	"ethcore_uncleInfo",
+	"ethcore_transactionDetails",
+	"trace_filter",
+	"trace_get",
+	"trace_transaction",
+	"trace_block",
+	"modules",
+	"rpc_modules",
+];
+
+/// Methods available only when the unsafe API set is enabled.
+pub const UNSAFE_METHODS: &'static [&'static str] = &[
+	"ethcore_stateStats",
+];
+
+/// Returns `true` if `method` is not available in read-only mode.
+pub fn is_restricted(method: &str) -> bool {
+	RESTRICTED_METHODS.contains(&method)
+}
+
+/// Error returned by restricted methods.
+pub fn read_only_error() -> Error {
+	Error {
+		code: ErrorCode::ServerError(READ_ONLY_ERROR_CODE),
+		message: "Method is not available on read-only node.".to_owned(),
+		data: None,
+	}
+}
+
+/// Reason of rejecting a call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rejection {
+	/// Method is not available on read-only node.
+	ReadOnly,
+	/// Method is available only in the unsafe API set.
+	Unsafe,
+}
+
+impl Rejection {
+	/// Error the call is answered with.
+	pub fn error(&self) -> Error {
+		match *self {
+			Rejection::ReadOnly => read_only_error(),
+			Rejection::Unsafe => Error::method_not_found(),
+		}
+	}
+
+	/// Serialized failure response to call with given `id`.
+	fn response(&self, id: &serde_json::Value) -> String {
+		let (code, message) = match *self {
+			Rejection::ReadOnly => (READ_ONLY_ERROR_CODE, "Method is not available on read-only node."),
+			Rejection::Unsafe => (METHOD_NOT_FOUND, "Method not found"),
+		};
+		format!(
+			r#"{{"jsonrpc":"2.0","error":{{"code":{},"message":"{}","data":null}},"id":{}}}"#,
+			code, message, serde_json::to_string(id).expect("Serialization of parsed value can't fail.")
+		)
+	}
+}
+
+/// Rejects calls of restricted methods by their name, before they are dispatched.
+///
+/// Restrictions can be enabled at any time and apply to methods registered later as well.
+#[derive(Debug, Default)]
+pub struct MethodFilter {
+	read_only: AtomicBool,
+	safe_only: AtomicBool,
+}
+
+impl MethodFilter {
+	/// Reject all methods which mutate state of the node or touch keys.
+	pub fn restrict_to_read_only(&self) {
+		self.read_only.store(true, Ordering::SeqCst);
+	}
+
+	/// Reject methods available only in the unsafe API set (see `UNSAFE_METHODS`).
+	pub fn restrict_to_safe(&self) {
+		self.safe_only.store(true, Ordering::SeqCst);
+	}
+
+	fn is_restricting(&self) -> bool {
+		self.read_only.load(Ordering::SeqCst) || self.safe_only.load(Ordering::SeqCst)
+	}
+
+	/// Returns the reason of rejecting call of `method` or `None` if it's allowed.
+	pub fn check(&self, method: &str) -> Option<Rejection> {
+		if self.read_only.load(Ordering::SeqCst) && is_restricted(method) {
+			let origin = origin::current_origin().map_or_else(|| "unknown".to_owned(), |o| o.tag().to_owned());
+			warn!(target: "rpc", "Rejected call of {} on read-only node (origin: {}).", method, origin);
+			return Some(Rejection::ReadOnly);
+		}
+		if self.safe_only.load(Ordering::SeqCst) && UNSAFE_METHODS.contains(&method) {
+			return Some(Rejection::Unsafe);
+		}
+		None
+	}
+
+	/// Handles the request (single call or a batch) with `handler`, see `IoHandler::handle_request`.
+	/// Calls of rejected methods are answered with an error and never reach the `handler`.
+	pub fn handle_request(&self, handler: &IoHandler, request: &str) -> Option<String> {
+		if !self.is_restricting() {
+			return handler.handle_request(request);
+		}
+		match serde_json::from_str::<serde_json::Value>(request) {
+			Ok(serde_json::Value::Array(ref calls)) if !calls.is_empty() => {
+				let responses = calls.iter()
+					.filter_map(|call| {
+						let request = serde_json::to_string(call).expect("Serialization of parsed value can't fail.");
+						self.handle_call(handler, call, &request)
+					})
+					.collect::<Vec<_>>();
+				match responses.is_empty() {
+					true => None,
+					false => Some(format!("[{}]", responses.join(","))),
+				}
+			},
+			Ok(ref call) => self.handle_call(handler, call, request),
+			Err(_) => handler.handle_request(request),
+		}
+	}
+
+	/// Handles single already parsed `call` (serialized as `request`) with `handler`.
+	/// Rejected notifications are dropped without a response.
+	pub fn handle_call(&self, handler: &IoHandler, call: &serde_json::Value, request: &str) -> Option<String> {
+		let rejection = call.find("method")
+			.and_then(serde_json::Value::as_string)
+			.and_then(|method| self.check(method));
+		match rejection {
+			Some(rejection) => call.find("id").map(|id| rejection.response(id)),
+			None => handler.handle_request(request),
+		}
+	}
+
+	/// Replaces currently restricted methods of `handler` with ones returning an error.
+	///
+	/// Only for transports which hand requests straight to the `IoHandler` (named pipes),
+	/// has to be called after all delegates are added.
+	pub fn apply_to(&self, handler: &IoHandler) {
+		if self.read_only.load(Ordering::SeqCst) {
+			for &method in RESTRICTED_METHODS {
+				handler.add_method(method, RejectedMethod(Rejection::ReadOnly));
+			}
+		}
+		if self.safe_only.load(Ordering::SeqCst) {
+			for &method in UNSAFE_METHODS {
+				handler.add_method(method, RejectedMethod(Rejection::Unsafe));
+			}
+		}
+	}
+}
+
+/// Replacement of rejected method.
+struct RejectedMethod(Rejection);
+
+impl MethodCommand for RejectedMethod {
+	fn execute(&self, _params: Params) -> Result<Value, Error> {
+		Err(self.0.error())
+	}
+}
+
+/// `IoHandler` with calls passed through the `MethodFilter`.
+pub struct FilteredHandler {
+	handler: Arc<IoHandler>,
+	filter: Arc<MethodFilter>,
+}
+
+impl FilteredHandler {
+	/// Wraps given handler, calls are checked by `filter`.
+	pub fn new(handler: Arc<IoHandler>, filter: Arc<MethodFilter>) -> Self {
+		FilteredHandler {
+			handler: handler,
+			filter: filter,
+		}
+	}
+
+	/// Handles the request (single call or a batch), see `MethodFilter::handle_request`.
+	pub fn handle_request(&self, request: &str) -> Option<String> {
+		self.filter.handle_request(&self.handler, request)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::collections::HashMap;
+	use jsonrpc_core::IoHandler;
+	use util::keys::TestAccountProvider;
+	use v1::{Web3, Web3Client, Personal, PersonalClient};
+	use super::{RESTRICTED_METHODS, QUERY_METHODS, MethodFilter, is_restricted};
+
+	/// Names of all methods registered by `to_delegate` of given trait source.
+	fn registered_methods(source: &'static str) -> Vec<&'static str> {
+		source.split("add_method(\"").skip(1)
+			.map(|rest| rest.split('"').next().unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn should_classify_every_registered_method() {
+		let sources = [
+			include_str!("v1/traits/web3.rs"),
+			include_str!("v1/traits/net.rs"),
+			include_str!("v1/traits/eth.rs"),
+			include_str!("v1/traits/personal.rs"),
+			include_str!("v1/traits/ethcore.rs"),
+			include_str!("v1/traits/traces.rs"),
+			include_str!("v1/traits/rpc.rs"),
+		];
+		let registered = sources.iter().flat_map(|source| registered_methods(source)).collect::<Vec<_>>();
+
+		for method in &registered {
+			assert!(RESTRICTED_METHODS.contains(method) != QUERY_METHODS.contains(method), "Method {} has to be classified exactly once.", method);
+		}
+		for method in RESTRICTED_METHODS.iter().chain(QUERY_METHODS.iter()) {
+			assert!(registered.contains(method), "Classified method {} is not registered.", method);
+		}
+	}
+
+	#[test]
+	fn should_restrict_mutating_methods_only() {
+		assert!(is_restricted("eth_sendTransaction"));
+		assert!(is_restricted("personal_unlockAccount"));
+		assert!(is_restricted("ethcore_setAuthor"));
+		assert!(!is_restricted("eth_getBalance"));
+		assert!(!is_restricted("ethcore_minGasPrice"));
+	}
+
+	#[test]
+	fn should_reject_methods_registered_after_restricting() {
+		// given
+		let filter = MethodFilter::default();
+		filter.restrict_to_read_only();
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+
+		// when
+		let accounts = Arc::new(TestAccountProvider::new(HashMap::new()));
+		io.add_delegate(PersonalClient::new(&accounts).to_delegate());
+
+		// then
+		let request = r#"{"jsonrpc": "2.0", "method": "personal_listAccounts", "params": [], "id": 1}"#;
+		let rejected = r#"{"jsonrpc":"2.0","error":{"code":-32011,"message":"Method is not available on read-only node.","data":null},"id":1}"#;
+		assert_eq!(filter.handle_request(&io, request), Some(rejected.to_owned()));
+		assert_eq!(io.handle_request(request), Some(r#"{"jsonrpc":"2.0","result":[],"id":1}"#.to_owned()));
+	}
+
+	#[test]
+	fn should_reject_calls_of_batch_one_by_one() {
+		// given
+		let filter = MethodFilter::default();
+		filter.restrict_to_read_only();
+		filter.restrict_to_safe();
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+		let batch = r#"[
+			{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["0x00"], "id": 1},
+			{"jsonrpc": "2.0", "method": "eth_sendTransaction", "params": [], "id": "2"},
+			{"jsonrpc": "2.0", "method": "eth_submitHashrate", "params": []},
+			{"jsonrpc": "2.0", "method": "ethcore_stateStats", "params": [], "id": 3}
+		]"#;
+
+		// when
+		let response = filter.handle_request(&io, batch).unwrap();
+
+		// then
+		assert_eq!(response, concat!(
+			r#"[{"jsonrpc":"2.0","result":"0xbc36789e7a1e281436464229828f817d6612f7b477d66591ff96a9e064bcc98a","id":1},"#,
+			r#"{"jsonrpc":"2.0","error":{"code":-32011,"message":"Method is not available on read-only node.","data":null},"id":"2"},"#,
+			r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found","data":null},"id":3}]"#
+		));
+	}
+}
@@ -0,0 +1,229 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Per-method statistics of RPC calls.
+//!
+//! `IoHandler` doesn't expose the methods it dispatches to, so calls are counted by
+//! `CountingHandler` wrapping it. Calls of a batch are handled (and counted) one by one.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use jsonrpc_core::{IoHandler, Output, ErrorCode};
+use serde_json::{self, Value};
+use read_only::MethodFilter;
+
+/// Calls of methods which don't exist (or aren't tracked anymore) are counted under this name.
+pub const OTHER_METHODS: &'static str = "other";
+/// Maximal number of distinct tracked methods.
+const MAX_METHODS: usize = 256;
+
+#[derive(Default)]
+struct MethodCounters {
+	calls: AtomicUsize,
+	errors: AtomicUsize,
+	latency: AtomicUsize,
+}
+
+/// Statistics of single method.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MethodStats {
+	/// Number of calls.
+	pub calls: usize,
+	/// Number of calls which returned an error.
+	pub errors: usize,
+	/// Cumulative time spent handling the calls (in microseconds).
+	pub latency: usize,
+}
+
+/// Per-method statistics of RPC calls shared by RPC servers.
+#[derive(Default)]
+pub struct RpcStats {
+	methods: RwLock<HashMap<String, Arc<MethodCounters>>>,
+}
+
+impl RpcStats {
+	/// Records call of `method` which took `elapsed` time.
+	pub fn record(&self, method: &str, error: bool, elapsed: Duration) {
+		let counters = self.counters(method);
+		counters.calls.fetch_add(1, Ordering::Relaxed);
+		if error {
+			counters.errors.fetch_add(1, Ordering::Relaxed);
+		}
+		let micros = elapsed.as_secs() as usize * 1_000_000 + elapsed.subsec_nanos() as usize / 1_000;
+		counters.latency.fetch_add(micros, Ordering::Relaxed);
+	}
+
+	/// Returns statistics of all called methods.
+	pub fn stats(&self) -> BTreeMap<String, MethodStats> {
+		self.methods.read().unwrap().iter()
+			.map(|(method, counters)| (method.clone(), MethodStats {
+				calls: counters.calls.load(Ordering::Relaxed),
+				errors: counters.errors.load(Ordering::Relaxed),
+				latency: counters.latency.load(Ordering::Relaxed),
+			}))
+			.collect()
+	}
+
+	/// Resets all counters.
+	pub fn reset(&self) {
+		self.methods.write().unwrap().clear();
+	}
+
+	fn counters(&self, method: &str) -> Arc<MethodCounters> {
+		if let Some(counters) = self.methods.read().unwrap().get(method) {
+			return counters.clone();
+		}
+		let mut methods = self.methods.write().unwrap();
+		let method = match methods.len() < MAX_METHODS || methods.contains_key(method) {
+			true => method,
+			false => OTHER_METHODS,
+		};
+		methods.entry(method.to_owned()).or_insert_with(Default::default).clone()
+	}
+}
+
+/// Returns error code of the response or `None` if the call succeeded.
+fn error_code(response: &str) -> Option<ErrorCode> {
+	match serde_json::from_str::<Output>(response) {
+		Ok(Output::Failure(failure)) => Some(failure.error.code),
+		_ => None,
+	}
+}
+
+/// `IoHandler` recording statistics of handled calls.
+pub struct CountingHandler {
+	handler: Arc<IoHandler>,
+	stats: Arc<RpcStats>,
+	filter: Arc<MethodFilter>,
+}
+
+impl CountingHandler {
+	/// Wraps given handler. Calls are recorded in `stats`.
+	pub fn new(handler: Arc<IoHandler>, stats: Arc<RpcStats>) -> Self {
+		CountingHandler {
+			handler: handler,
+			stats: stats,
+			filter: Arc::new(MethodFilter::default()),
+		}
+	}
+
+	/// Pass calls through given `filter` before they are handled.
+	pub fn with_filter(mut self, filter: Arc<MethodFilter>) -> Self {
+		self.filter = filter;
+		self
+	}
+
+	/// Handles the request (single call or a batch), see `IoHandler::handle_request`.
+	pub fn handle_request(&self, request: &str) -> Option<String> {
+		match serde_json::from_str::<Value>(request) {
+			Ok(ref parsed) => self.handle_parsed_request(parsed, request),
+			// Malformed requests are not counted.
+			Err(_) => self.handler.handle_request(request),
+		}
+	}
+
+	/// Handles the request which was already parsed from `raw`.
+	pub fn handle_parsed_request(&self, request: &Value, raw: &str) -> Option<String> {
+		match *request {
+			Value::Array(ref calls) if !calls.is_empty() => {
+				let responses = calls.iter()
+					.filter_map(|call| {
+						let request = serde_json::to_string(call).expect("Serialization of parsed value can't fail.");
+						self.handle_call(call, &request)
+					})
+					.collect::<Vec<_>>();
+				match responses.is_empty() {
+					true => None,
+					false => Some(format!("[{}]", responses.join(","))),
+				}
+			},
+			ref call => self.handle_call(call, raw),
+		}
+	}
+
+	fn handle_call(&self, call: &Value, request: &str) -> Option<String> {
+		let started = Instant::now();
+		let response = self.filter.handle_call(&self.handler, call, request);
+		if let Some(method) = call.find("method").and_then(Value::as_string) {
+			let error = response.as_ref().and_then(|response| error_code(response));
+			let method = match error {
+				Some(ErrorCode::MethodNotFound) => OTHER_METHODS,
+				_ => method,
+			};
+			self.stats.record(method, error.is_some(), started.elapsed());
+		}
+		response
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::time::Duration;
+	use jsonrpc_core::IoHandler;
+	use v1::{Web3, Web3Client};
+	use super::{RpcStats, MethodStats, CountingHandler, OTHER_METHODS};
+
+	fn stats(calls: usize, errors: usize, latency: usize) -> MethodStats {
+		MethodStats { calls: calls, errors: errors, latency: latency }
+	}
+
+	#[test]
+	fn should_record_and_reset_calls() {
+		// given
+		let rpc_stats = RpcStats::default();
+
+		// when
+		rpc_stats.record("eth_call", false, Duration::from_millis(2));
+		rpc_stats.record("eth_call", true, Duration::new(1, 500));
+		rpc_stats.record("web3_sha3", false, Duration::from_millis(0));
+
+		// then
+		let recorded = rpc_stats.stats();
+		assert_eq!(recorded.len(), 2);
+		assert_eq!(recorded["eth_call"], stats(2, 1, 1_002_000));
+		assert_eq!(recorded["web3_sha3"], stats(1, 0, 0));
+		rpc_stats.reset();
+		assert!(rpc_stats.stats().is_empty());
+	}
+
+	#[test]
+	fn should_count_calls_of_batches_one_by_one() {
+		// given
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+		let rpc_stats = Arc::new(RpcStats::default());
+		let handler = CountingHandler::new(Arc::new(io), rpc_stats.clone());
+		let batch = r#"[
+			{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["0x00"], "id": 1},
+			{"jsonrpc": "2.0", "method": "web3_sha3", "params": [], "id": 2},
+			{"jsonrpc": "2.0", "method": "eth_unknown", "params": [], "id": 3}
+		]"#;
+
+		// when
+		let response = handler.handle_request(batch).unwrap();
+
+		// then
+		assert!(response.starts_with(r#"[{"jsonrpc":"2.0","result":"0x"#), "Unexpected response: {}", response);
+		assert_eq!(response.matches(r#""error""#).count(), 2);
+		let recorded = rpc_stats.stats();
+		assert_eq!(recorded.keys().map(|method| method.as_str()).collect::<Vec<_>>(), vec![OTHER_METHODS, "web3_sha3"]);
+		assert_eq!((recorded["web3_sha3"].calls, recorded["web3_sha3"].errors), (2, 1));
+		assert_eq!((recorded[OTHER_METHODS].calls, recorded[OTHER_METHODS].errors), (1, 1));
+	}
+}
@@ -21,25 +21,26 @@ extern crate ethash;
 use std::collections::HashSet;
 use std::sync::{Arc, Weak, Mutex};
 use std::ops::Deref;
+use std::time::{Instant, Duration};
 use ethsync::{SyncProvider, SyncState};
-use ethminer::{MinerService, AccountDetails, ExternalMinerService};
+use ethminer::{MinerService, AccountDetails, ExternalMinerService, TransactionImportError, CallOverrides};
 use jsonrpc_core::*;
 use util::numbers::*;
 use util::sha3::*;
 use util::bytes::{ToPretty};
 use util::rlp::{encode, decode, UntrustedRlp, View};
 use ethcore::client::{BlockChainClient, BlockID, TransactionID, UncleID};
-use ethcore::block::IsBlock;
 use ethcore::views::*;
 use ethcore::ethereum::Ethash;
 use ethcore::transaction::{Transaction as EthTransaction, SignedTransaction, Action};
-use ethcore::log_entry::LogEntry;
 use ethcore::filter::Filter as EthcoreFilter;
+use ethcore::error::ExecutionError;
 use self::ethash::SeedHashCompute;
 use v1::traits::{Eth, EthFilter};
 use v1::types::{Block, BlockTransactions, BlockNumber, Bytes, SyncStatus, SyncInfo, Transaction, TransactionRequest, CallRequest, OptionalValue, Index, Filter, Log, Receipt};
 use v1::helpers::{PollFilter, PollManager};
 use util::keys::store::AccountProvider;
+use origin;
 use serde;
 
 /// Eth rpc implementation.
@@ -56,6 +57,8 @@ pub struct EthClient<C, S, A, M, EM> where
 	miner: Weak<M>,
 	external_miner: Arc<EM>,
 	seed_compute: Mutex<SeedHashCompute>,
+	call_timeout: Option<Duration>,
+	send_block_number_in_get_work: bool,
 }
 
 impl<C, S, A, M, EM> EthClient<C, S, A, M, EM> where
@@ -75,9 +78,27 @@ impl<C, S, A, M, EM> EthClient<C, S, A, M, EM> where
 			accounts: Arc::downgrade(accounts),
 			external_miner: em.clone(),
 			seed_compute: Mutex::new(SeedHashCompute::new()),
+			call_timeout: None,
+			send_block_number_in_get_work: true,
 		}
 	}
 
+	/// Aborts `eth_call` and `eth_estimateGas` executions running longer than `timeout`.
+	pub fn with_call_timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.call_timeout = timeout;
+		self
+	}
+
+	/// Appends the block number to `eth_getWork` responses. Disable for miners expecting the legacy 3-element array.
+	pub fn with_send_block_number_in_get_work(mut self, send: bool) -> Self {
+		self.send_block_number_in_get_work = send;
+		self
+	}
+
+	fn call_deadline(&self) -> Option<Instant> {
+		self.call_timeout.map(|timeout| Instant::now() + timeout)
+	}
+
 	fn block(&self, id: BlockID, include_txs: bool) -> Result<Value, Error> {
 		let client = take_weak!(self.client);
 		match (client.block(id.clone()), client.block_total_difficulty(id)) {
@@ -156,24 +177,28 @@ impl<C, S, A, M, EM> EthClient<C, S, A, M, EM> where
 	}
 
 	fn sign_and_dispatch(&self, request: TransactionRequest, secret: H256) -> Result<Value, Error> {
-		let signed_transaction = {
-			let client = take_weak!(self.client);
-			let miner = take_weak!(self.miner);
-			EthTransaction {
-				nonce: request.nonce
-					.or_else(|| miner
-							 .last_nonce(&request.from)
-							 .map(|nonce| nonce + U256::one()))
-					.unwrap_or_else(|| client.nonce(&request.from)),
-					action: request.to.map_or(Action::Create, Action::Call),
-					gas: request.gas.unwrap_or_else(|| miner.sensible_gas_limit()),
-					gas_price: request.gas_price.unwrap_or_else(|| miner.sensible_gas_price()),
-					value: request.value.unwrap_or_else(U256::zero),
-					data: request.data.map_or_else(Vec::new, |d| d.to_vec()),
-			}.sign(&secret)
+		let client = take_weak!(self.client);
+		let miner = take_weak!(self.miner);
+		let from = request.from.clone();
+		let (nonce, reserved) = match request.nonce {
+			Some(nonce) => (nonce, false),
+			None => (miner.reserve_nonce(client.deref(), &from), true),
 		};
+		let signed_transaction = EthTransaction {
+			nonce: nonce,
+			action: request.to.map_or(Action::Create, Action::Call),
+			gas: request.gas.unwrap_or_else(|| miner.sensible_gas_limit()),
+			gas_price: request.gas_price.unwrap_or_else(|| miner.sensible_gas_price(client.deref())),
+			value: request.value.unwrap_or_else(U256::zero),
+			data: request.data.map_or_else(Vec::new, |d| d.to_vec()),
+		}.sign_with_chain_id(&secret, client.chain_id());
 		trace!(target: "miner", "send_transaction: dispatching tx: {}", encode(&signed_transaction).to_vec().pretty());
-		self.dispatch_transaction(signed_transaction)
+		let result = self.dispatch_transaction(signed_transaction);
+		// don't leave a nonce gap for the following transactions of the account
+		if reserved && result.is_err() {
+			miner.release_nonce(&from, &nonce);
+		}
+		result
 	}
 
 	fn sign_call(&self, request: CallRequest) -> Result<SignedTransaction, Error> {
@@ -184,7 +209,7 @@ impl<C, S, A, M, EM> EthClient<C, S, A, M, EM> where
 			nonce: request.nonce.unwrap_or_else(|| client.nonce(&from)),
 			action: request.to.map_or(Action::Create, Action::Call),
 			gas: request.gas.unwrap_or(U256::from(50_000_000)),
-			gas_price: request.gas_price.unwrap_or_else(|| miner.sensible_gas_price()),
+			gas_price: request.gas_price.unwrap_or_else(|| miner.sensible_gas_price(client.deref())),
 			value: request.value.unwrap_or_else(U256::zero),
 			data: request.data.map_or_else(Vec::new, |d| d.to_vec())
 		}.fake_sign(from))
@@ -197,7 +222,7 @@ impl<C, S, A, M, EM> EthClient<C, S, A, M, EM> where
 			let client = take_weak!(self.client);
 			let miner = take_weak!(self.miner);
 
-			miner.import_own_transaction(client.deref(), signed_transaction, |a: &Address| {
+			miner.import_own_transaction(client.deref(), signed_transaction, origin::current_origin(), |a: &Address| {
 				AccountDetails {
 					nonce: client.nonce(&a),
 					balance: client.balance(&a),
@@ -209,12 +234,31 @@ impl<C, S, A, M, EM> EthClient<C, S, A, M, EM> where
 			Ok(_) => to_value(&hash),
 			Err(e) => {
 				warn!("Error sending transaction: {:?}", e);
-				to_value(&H256::zero())
+				Err(transaction_import_error(e))
 			}
 		}
 	}
 }
 
+const TRANSACTION_IMPORT_ERROR_CODE: i64 = -32010;
+const EXECUTION_TIMEOUT_ERROR_CODE: i64 = -32012;
+
+fn transaction_import_error(error: TransactionImportError) -> Error {
+	Error {
+		code: ErrorCode::ServerError(TRANSACTION_IMPORT_ERROR_CODE),
+		message: format!("{}", error),
+		data: None,
+	}
+}
+
+fn execution_timeout_error() -> Error {
+	Error {
+		code: ErrorCode::ServerError(EXECUTION_TIMEOUT_ERROR_CODE),
+		message: format!("{}", ExecutionError::Timeout),
+		data: None,
+	}
+}
+
 const MAX_QUEUE_SIZE_TO_MINE_ON: usize = 4;	// because uncles go back 6.
 
 fn params_len(params: &Params) -> usize {
@@ -239,22 +283,16 @@ fn from_params_default_third<F1, F2>(params: Params) -> Result<(F1, F2, BlockNum
 }
 
 fn pending_logs<M>(miner: &M, filter: &EthcoreFilter) -> Vec<Log> where M: MinerService {
-	let receipts = miner.pending_receipts();
-
-	let pending_logs = receipts.into_iter()
-		.flat_map(|(hash, r)| r.logs.into_iter().map(|l| (hash.clone(), l)).collect::<Vec<(H256, LogEntry)>>())
-		.collect::<Vec<(H256, LogEntry)>>();
-
-	let result = pending_logs.into_iter()
-		.filter(|pair| filter.matches(&pair.1))
-		.map(|pair| {
-			let mut log = Log::from(pair.1);
-			log.transaction_hash = Some(pair.0);
+	miner.pending_logs(filter).into_iter()
+		.map(|entry| {
+			// Pending block is not sealed yet, so only position within the block is known.
+			let mut log = Log::from(entry.entry);
+			log.transaction_hash = Some(entry.transaction_hash);
+			log.transaction_index = Some(U256::from(entry.transaction_index));
+			log.log_index = Some(U256::from(entry.log_index));
 			log
 		})
-		.collect();
-
-	result
+		.collect()
 }
 
 impl<C, S, A, M, EM> Eth for EthClient<C, S, A, M, EM> where
@@ -316,9 +354,19 @@ impl<C, S, A, M, EM> Eth for EthClient<C, S, A, M, EM> where
 		}
 	}
 
+	fn chain_id(&self, params: Params) -> Result<Value, Error> {
+		match params {
+			Params::None => to_value(&take_weak!(self.client).chain_id().map(U256::from)),
+			_ => Err(Error::invalid_params())
+		}
+	}
+
 	fn gas_price(&self, params: Params) -> Result<Value, Error> {
 		match params {
-			Params::None => to_value(&take_weak!(self.miner).sensible_gas_price()),
+			Params::None => {
+				let client = take_weak!(self.client);
+				to_value(&take_weak!(self.miner).sensible_gas_price(client.deref()))
+			},
 			_ => Err(Error::invalid_params())
 		}
 	}
@@ -494,16 +542,22 @@ impl<C, S, A, M, EM> Eth for EthClient<C, S, A, M, EM> where
 					//let sync = take_weak!(self.sync);
 					if /*sync.status().state != SyncState::Idle ||*/ client.queue_info().total_queue_size() > MAX_QUEUE_SIZE_TO_MINE_ON {
 						trace!(target: "miner", "Syncing. Cannot give any work.");
+						if self.send_block_number_in_get_work {
+							return to_value(&(String::new(), String::new(), String::new(), String::new()));
+						}
 						return to_value(&(String::new(), String::new(), String::new()));
 					}
 				}
 
 				let miner = take_weak!(self.miner);
-				miner.map_sealing_work(client.deref(), |b| {
-					let pow_hash = b.hash();
-					let target = Ethash::difficulty_to_boundary(b.block().header().difficulty());
-					let seed_hash = &self.seed_compute.lock().unwrap().get_seedhash(b.block().header().number());
-					to_value(&(pow_hash, H256::from_slice(&seed_hash[..]), target))
+				miner.map_sealing_work(client.deref(), |w| {
+					let target = Ethash::difficulty_to_boundary(&w.difficulty);
+					let seed_hash = H256::from_slice(&self.seed_compute.lock().unwrap().get_seedhash(w.number)[..]);
+					if self.send_block_number_in_get_work {
+						to_value(&(w.pow_hash.clone(), seed_hash, target, U256::from(w.number)))
+					} else {
+						to_value(&(w.pow_hash.clone(), seed_hash, target))
+					}
 				}).unwrap_or(Err(Error::internal_error()))	// no work found.
 			},
 			_ => Err(Error::invalid_params())
@@ -516,8 +570,16 @@ impl<C, S, A, M, EM> Eth for EthClient<C, S, A, M, EM> where
 			let miner = take_weak!(self.miner);
 			let client = take_weak!(self.client);
 			let seal = vec![encode(&mix_hash).to_vec(), encode(&nonce).to_vec()];
-			let r = miner.submit_seal(client.deref(), pow_hash, seal);
-			to_value(&r.is_ok())
+			match miner.submit_seal(client.deref(), pow_hash, seal, None) {
+				Ok(hash) => {
+					trace!(target: "miner", "submit_work: Sealed block {}", hash);
+					to_value(&true)
+				},
+				Err(e) => {
+					trace!(target: "miner", "submit_work: Seal rejected: {:?}", e);
+					to_value(&false)
+				},
+			}
 		})
 	}
 
@@ -566,12 +628,19 @@ impl<C, S, A, M, EM> Eth for EthClient<C, S, A, M, EM> where
 		from_params_default_second(params)
 			.and_then(|(request, block_number,)| {
 				let signed = try!(self.sign_call(request));
+				let deadline = self.call_deadline();
 				let r = match block_number {
-					BlockNumber::Pending => take_weak!(self.miner).call(take_weak!(self.client).deref(), &signed),
-					BlockNumber::Latest => take_weak!(self.client).call(&signed),
+					BlockNumber::Pending => {
+						let overrides = CallOverrides { static_call: true, ..CallOverrides::default() };
+						take_weak!(self.miner).call(take_weak!(self.client).deref(), &signed, &overrides, deadline)
+					},
+					BlockNumber::Latest => take_weak!(self.client).call_with_env(&signed, &|_| {}, true, deadline),
 					_ => panic!("{:?}", block_number),
 				};
-				to_value(&r.map(|e| Bytes(e.output)).unwrap_or(Bytes::new(vec![])))
+				match r {
+					Err(ExecutionError::Timeout) => Err(execution_timeout_error()),
+					r => to_value(&r.map(|e| Bytes(e.output)).unwrap_or(Bytes::new(vec![]))),
+				}
 			})
 	}
 
@@ -579,12 +648,16 @@ impl<C, S, A, M, EM> Eth for EthClient<C, S, A, M, EM> where
 		from_params_default_second(params)
 			.and_then(|(request, block_number,)| {
 				let signed = try!(self.sign_call(request));
+				let deadline = self.call_deadline();
 				let r = match block_number {
-					BlockNumber::Pending => take_weak!(self.miner).call(take_weak!(self.client).deref(), &signed),
-					BlockNumber::Latest => take_weak!(self.client).call(&signed),
+					BlockNumber::Pending => take_weak!(self.miner).estimate_gas(take_weak!(self.client).deref(), &signed, deadline),
+					BlockNumber::Latest => take_weak!(self.client).estimate_gas(&signed, deadline),
 					_ => return Err(Error::invalid_params()),
 				};
-				to_value(&r.map(|res| res.gas_used + res.refunded).unwrap_or(From::from(0)))
+				match r {
+					Err(ExecutionError::Timeout) => Err(execution_timeout_error()),
+					r => to_value(&r.unwrap_or(From::from(0))),
+				}
 			})
 	}
 }
@@ -15,77 +15,149 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 //! Ethcore-specific rpc implementation.
-use util::{U256, Address, RotatingLogger};
+use util::{H256, U256, Address, RotatingLogger};
 use util::network_settings::NetworkSettings;
 use util::misc::version_data;
 use std::sync::{Arc, Weak};
 use std::ops::Deref;
+use std::time::{Instant, Duration};
 use std::collections::BTreeMap;
 use jsonrpc_core::*;
+use ethcore::client::BlockChainClient;
 use ethminer::{MinerService};
 use v1::traits::Ethcore;
-use v1::types::Bytes;
+use v1::types::{Bytes, BlockNumber, StateStats, AuditEntry, SubmissionStats, BundleStatus, RpcMethodStats, UncleInfo, TransactionDetails};
+use stats::RpcStats;
+
+/// Maximal size of extra data that can be set for sealed blocks.
+const MAX_EXTRA_DATA_SIZE: usize = 32;
+/// Same code as the one of timed out `eth_call`.
+const TIMEOUT_ERROR_CODE: i64 = -32012;
+
+fn state_stats_timeout_error() -> Error {
+	Error {
+		code: ErrorCode::ServerError(TIMEOUT_ERROR_CODE),
+		message: "Collection of state statistics took too long and was cancelled.".to_owned(),
+		data: None,
+	}
+}
 
 /// Ethcore implementation.
-pub struct EthcoreClient<M>
-	where M: MinerService {
+pub struct EthcoreClient<C, M>
+	where C: BlockChainClient, M: MinerService {
+	client: Weak<C>,
 	miner: Weak<M>,
 	logger: Arc<RotatingLogger>,
 	settings: Arc<NetworkSettings>,
+	rpc_stats: Arc<RpcStats>,
+	call_timeout: Option<Duration>,
 }
 
-impl<M> EthcoreClient<M> where M: MinerService {
+impl<C, M> EthcoreClient<C, M> where C: BlockChainClient, M: MinerService {
 	/// Creates new `EthcoreClient`.
-	pub fn new(miner: &Arc<M>, logger: Arc<RotatingLogger>, settings: Arc<NetworkSettings>) -> Self {
+	pub fn new(client: &Arc<C>, miner: &Arc<M>, logger: Arc<RotatingLogger>, settings: Arc<NetworkSettings>) -> Self {
 		EthcoreClient {
+			client: Arc::downgrade(client),
 			miner: Arc::downgrade(miner),
 			logger: logger,
 			settings: settings,
+			rpc_stats: Arc::new(RpcStats::default()),
+			call_timeout: None,
 		}
 	}
+
+	/// Cancels collection of state statistics running longer than `timeout`.
+	pub fn with_call_timeout(mut self, timeout: Option<Duration>) -> Self {
+		self.call_timeout = timeout;
+		self
+	}
+
+	/// Reports (and resets) given RPC statistics.
+	pub fn with_rpc_stats(mut self, rpc_stats: Arc<RpcStats>) -> Self {
+		self.rpc_stats = rpc_stats;
+		self
+	}
 }
 
-impl<M> Ethcore for EthcoreClient<M> where M: MinerService + 'static {
+impl<C, M> Ethcore for EthcoreClient<C, M> where C: BlockChainClient + 'static, M: MinerService + 'static {
 
 	fn set_min_gas_price(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(U256,)>(params).and_then(|(gas_price,)| {
-			take_weak!(self.miner).set_minimal_gas_price(gas_price);
-			to_value(&true)
+			let miner = take_weak!(self.miner);
+			miner.set_minimal_gas_price(gas_price);
+			to_value(&miner.minimal_gas_price())
 		})
 	}
 
 	fn set_gas_floor_target(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(U256,)>(params).and_then(|(gas_floor_target,)| {
-			take_weak!(self.miner).set_gas_floor_target(gas_floor_target);
-			to_value(&true)
+			let miner = take_weak!(self.miner);
+			miner.set_gas_floor_target(gas_floor_target);
+			to_value(&miner.gas_floor_target())
 		})
 	}
 
 	fn set_extra_data(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(Bytes,)>(params).and_then(|(extra_data,)| {
-			take_weak!(self.miner).set_extra_data(extra_data.to_vec());
-			to_value(&true)
+			let extra_data = extra_data.to_vec();
+			if extra_data.len() > MAX_EXTRA_DATA_SIZE {
+				return Err(Error::invalid_params());
+			}
+			let miner = take_weak!(self.miner);
+			miner.set_extra_data(extra_data);
+			to_value(&Bytes::new(miner.extra_data()))
 		})
 	}
 
 	fn set_author(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(Address,)>(params).and_then(|(author,)| {
-			take_weak!(self.miner).set_author(author);
-			to_value(&true)
+			let miner = take_weak!(self.miner);
+			miner.set_author(author);
+			to_value(&miner.author())
 		})
 	}
 
 	fn set_transactions_limit(&self, params: Params) -> Result<Value, Error> {
 		from_params::<(usize,)>(params).and_then(|(limit,)| {
-			take_weak!(self.miner).set_transactions_limit(limit);
+			let miner = take_weak!(self.miner);
+			miner.set_transactions_limit(limit);
+			to_value(&miner.transactions_limit())
+		})
+	}
+
+	fn set_sealing_enabled(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(bool,)>(params).and_then(|(enabled,)| {
+			let miner = take_weak!(self.miner);
+			miner.set_sealing_enabled(enabled);
+			to_value(&miner.sealing_enabled())
+		})
+	}
+
+	fn ban_address(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(Address,)>(params).and_then(|(address,)| {
+			take_weak!(self.miner).ban_address(address);
 			to_value(&true)
 		})
 	}
 
+	fn unban_address(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(Address,)>(params).and_then(|(address,)| {
+			to_value(&take_weak!(self.miner).unban_address(&address))
+		})
+	}
+
+	fn banned_addresses(&self, _: Params) -> Result<Value, Error> {
+		to_value(&take_weak!(self.miner).banned_addresses())
+	}
+
 	fn transactions_limit(&self, _: Params) -> Result<Value, Error> {
 		to_value(&take_weak!(self.miner).transactions_limit())
 	}
 
+	fn sealing_enabled(&self, _: Params) -> Result<Value, Error> {
+		to_value(&take_weak!(self.miner).sealing_enabled())
+	}
+
 	fn min_gas_price(&self, _: Params) -> Result<Value, Error> {
 		to_value(&take_weak!(self.miner).minimal_gas_price())
 	}
@@ -135,4 +207,99 @@ impl<M> Ethcore for EthcoreClient<M> where M: MinerService + 'static {
 		let version = version_data();
 		to_value(&Bytes::new(version))
 	}
+
+	fn state_stats(&self, params: Params) -> Result<Value, Error> {
+		let params_len = match params {
+			Params::Array(ref vec) => vec.len(),
+			_ => 0,
+		};
+		let params = match params_len {
+			1 => from_params::<(BlockNumber,)>(params).map(|(block,)| (block, None)),
+			_ => from_params::<(BlockNumber, f64)>(params).map(|(block, sample)| (block, Some(sample))),
+		};
+		params.and_then(|(block, sample)| {
+			if let Some(sample) = sample {
+				if sample <= 0.0 || sample > 1.0 {
+					return Err(Error::invalid_params());
+				}
+			}
+			warn!(target: "rpc", "Collecting state statistics. This may take a long time.");
+			let deadline = self.call_timeout.map(|timeout| Instant::now() + timeout);
+			let mut cancelled = false;
+			let stats = take_weak!(self.client).state_stats(block.into(), sample, &mut |_| {
+				cancelled = deadline.map_or(false, |deadline| Instant::now() >= deadline);
+				!cancelled
+			});
+			match stats {
+				Some(stats) => to_value(&StateStats::from(stats)),
+				None if cancelled => Err(state_stats_timeout_error()),
+				None => Ok(Value::Null),
+			}
+		})
+	}
+
+	fn transaction_audit(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(H256,)>(params).and_then(|(hash,)| {
+			let entries = take_weak!(self.miner).transaction_audit(&hash).into_iter().map(AuditEntry::from).collect::<Vec<_>>();
+			to_value(&entries)
+		})
+	}
+
+	fn submission_stats(&self, params: Params) -> Result<Value, Error> {
+		match params {
+			Params::None => {
+				let stats = take_weak!(self.miner).submission_stats().into_iter()
+					.map(|(origin, stats)| (origin.tag().to_owned(), SubmissionStats::from(stats)))
+					.collect::<BTreeMap<_, _>>();
+				to_value(&stats)
+			},
+			_ => Err(Error::invalid_params()),
+		}
+	}
+
+	fn bundle_status(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(U256,)>(params).and_then(|(id,)| {
+			let status = take_weak!(self.miner).bundle_status(id.low_u64()).map(BundleStatus::from);
+			to_value(&status)
+		})
+	}
+
+	fn uncle_info(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(H256,)>(params).and_then(|(hash,)| {
+			let client = take_weak!(self.client);
+			let info = client.uncle_position(&hash)
+				.and_then(|id| client.uncle_extra_info(id))
+				.map(UncleInfo::from);
+			to_value(&info)
+		})
+	}
+
+	fn transaction_details(&self, params: Params) -> Result<Value, Error> {
+		from_params::<(H256,)>(params).and_then(|(hash,)| {
+			let details = take_weak!(self.miner).transaction_details(&hash).map(TransactionDetails::from);
+			to_value(&details)
+		})
+	}
+
+	fn rpc_stats(&self, params: Params) -> Result<Value, Error> {
+		match params {
+			Params::None => {
+				let stats = self.rpc_stats.stats().into_iter()
+					.map(|(method, stats)| (method, RpcMethodStats::from(stats)))
+					.collect::<BTreeMap<_, _>>();
+				to_value(&stats)
+			},
+			_ => Err(Error::invalid_params()),
+		}
+	}
+
+	fn reset_rpc_stats(&self, params: Params) -> Result<Value, Error> {
+		match params {
+			Params::None => {
+				self.rpc_stats.reset();
+				to_value(&true)
+			},
+			_ => Err(Error::invalid_params()),
+		}
+	}
 }
@@ -17,11 +17,20 @@
 use std::str::FromStr;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use jsonrpc_core::IoHandler;
 use util::hash::{Address, H256, FixedHash};
 use util::numbers::{Uint, U256};
 use util::keys::{TestAccount, TestAccountProvider};
-use ethcore::client::{TestBlockChainClient, EachBlockWith, Executed, TransactionID};
+use util::journaldb;
+use util::io::IoChannel;
+use util::kvdb::DatabaseConfig;
+use devtools::RandomTempPath;
+use ethcore::block::{ClosedBlock, OpenBlock, IsBlock};
+use ethcore::ethereum::Ethash;
+use ethcore::spec::Spec;
+use ethcore::gas_limit::GasLimitTarget;
+use ethcore::client::{BlockChainClient, Client, ClientConfig, TestBlockChainClient, EachBlockWith, Executed, TransactionID, BlockID, UncleID};
 use ethcore::log_entry::{LocalizedLogEntry, LogEntry};
 use ethcore::receipt::LocalizedReceipt;
 use ethcore::transaction::{Transaction, Action};
@@ -52,12 +61,23 @@ fn miner_service() -> Arc<TestMinerService> {
 	Arc::new(TestMinerService::default())
 }
 
+fn closed_block(dir: &RandomTempPath) -> ClosedBlock {
+	let spec = Spec::new_test();
+	let genesis_header = spec.genesis_header();
+	let mut db = journaldb::new(dir.as_str(), journaldb::Algorithm::EarlyMerge, DatabaseConfig::default());
+	spec.ensure_db_good(db.as_hashdb_mut());
+	let vm_factory = Default::default();
+	let last_hashes = vec![genesis_header.hash()];
+	let block = OpenBlock::new(&*spec.engine, &vm_factory, false, db, &genesis_header, last_hashes, Address::zero(), GasLimitTarget::fixed(U256::from(3141562)), vec![]);
+	block.close()
+}
+
 struct EthTester {
 	pub client: Arc<TestBlockChainClient>,
 	pub sync: Arc<TestSyncProvider>,
 	pub accounts_provider: Arc<TestAccountProvider>,
 	miner: Arc<TestMinerService>,
-	hashrates: Arc<RwLock<HashMap<H256, U256>>>,
+	hashrates: Arc<RwLock<HashMap<H256, (U256, Instant)>>>,
 	pub io: IoHandler,
 }
 
@@ -100,9 +120,9 @@ fn rpc_eth_syncing() {
 #[test]
 fn rpc_eth_hashrate() {
 	let tester = EthTester::default();
-	tester.hashrates.write().unwrap().insert(H256::from(0), U256::from(0xfffa));
-	tester.hashrates.write().unwrap().insert(H256::from(0), U256::from(0xfffb));
-	tester.hashrates.write().unwrap().insert(H256::from(1), U256::from(0x1));
+	tester.hashrates.write().unwrap().insert(H256::from(0), (U256::from(0xfffa), Instant::now()));
+	tester.hashrates.write().unwrap().insert(H256::from(0), (U256::from(0xfffb), Instant::now()));
+	tester.hashrates.write().unwrap().insert(H256::from(1), (U256::from(0x1), Instant::now()));
 
 	let request = r#"{"jsonrpc": "2.0", "method": "eth_hashrate", "params": [], "id": 1}"#;
 	let response = r#"{"jsonrpc":"2.0","result":"0xfffc","id":1}"#;
@@ -125,10 +145,34 @@ fn rpc_eth_submit_hashrate() {
 	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
 
 	assert_eq!(tester.io.handle_request(request), Some(response.to_owned()));
-	assert_eq!(tester.hashrates.read().unwrap().get(&H256::from("0x59daa26581d0acd1fce254fb7e85952f4c09d0915afd33d3886cd914bc7d283c")).cloned(),
+	assert_eq!(tester.hashrates.read().unwrap().get(&H256::from("0x59daa26581d0acd1fce254fb7e85952f4c09d0915afd33d3886cd914bc7d283c")).map(|&(hashrate, _)| hashrate),
 		Some(U256::from(0x500_000)));
 }
 
+#[test]
+fn rpc_eth_submit_work() {
+	let tester = EthTester::default();
+	let pow_hash = H256::from(1);
+	tester.miner.sealable_work.lock().unwrap().insert(pow_hash, H256::from(2));
+
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_submitWork",
+		"params": [
+			"0x0000000000000001",
+			"0x0000000000000000000000000000000000000000000000000000000000000001",
+			"0xd1fe5700000000000000000000000000d1fe5700000000000000000000000000"],
+		"id": 1
+	}"#;
+	let accepted = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	let rejected = r#"{"jsonrpc":"2.0","result":false,"id":1}"#;
+
+	assert_eq!(tester.io.handle_request(request), Some(accepted.to_owned()));
+	assert!(tester.miner.sealable_work.lock().unwrap().is_empty());
+	// the same work can't be sealed twice
+	assert_eq!(tester.io.handle_request(request), Some(rejected.to_owned()));
+}
+
 #[test]
 #[ignore]
 fn rpc_eth_author() {
@@ -143,13 +187,25 @@ fn rpc_eth_mining() {
 	let response = r#"{"jsonrpc":"2.0","result":false,"id":1}"#;
 	assert_eq!(tester.io.handle_request(request), Some(response.to_owned()));
 
-	tester.hashrates.write().unwrap().insert(H256::from(1), U256::from(0x1));
+	tester.hashrates.write().unwrap().insert(H256::from(1), (U256::from(0x1), Instant::now()));
 
 	let request = r#"{"jsonrpc": "2.0", "method": "eth_mining", "params": [], "id": 1}"#;
 	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
 	assert_eq!(tester.io.handle_request(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_eth_chain_id() {
+	let request = r#"{"jsonrpc": "2.0", "method": "eth_chainId", "params": [], "id": 1}"#;
+	let no_chain_id = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+	let chain_id = r#"{"jsonrpc":"2.0","result":"0x3d","id":1}"#;
+
+	let tester = EthTester::default();
+	assert_eq!(tester.io.handle_request(request), Some(no_chain_id.to_owned()));
+	tester.client.set_chain_id(Some(61));
+	assert_eq!(tester.io.handle_request(request), Some(chain_id.to_owned()));
+}
+
 #[test]
 fn rpc_eth_gas_price() {
 	let request = r#"{"jsonrpc": "2.0", "method": "eth_gasPrice", "params": [], "id": 1}"#;
@@ -325,6 +381,59 @@ fn rpc_eth_uncle_count_by_block_number() {
 	assert_eq!(EthTester::default().io.handle_request(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_eth_uncle_by_block_hash_and_index() {
+	let tester = EthTester::default();
+	tester.client.add_blocks(1, EachBlockWith::Uncle);
+	let block_hash = tester.client.block_hash(BlockID::Number(1)).unwrap();
+	let uncle = tester.client.uncle(UncleID(BlockID::Number(1), 0)).unwrap();
+
+	let request = format!(r#"{{
+		"jsonrpc": "2.0",
+		"method": "eth_getUncleByBlockHashAndIndex",
+		"params": ["0x{}", "0x0"],
+		"id": 1
+	}}"#, block_hash.hex());
+	let response = tester.io.handle_request(&request).unwrap();
+
+	assert!(response.contains(&format!(r#""hash":"0x{}""#, uncle.hash().hex())));
+	assert!(response.contains(r#""number":"0x01""#));
+}
+
+#[test]
+fn rpc_eth_uncle_by_block_number_and_index() {
+	let tester = EthTester::default();
+	tester.client.add_blocks(1, EachBlockWith::Uncle);
+	let uncle = tester.client.uncle(UncleID(BlockID::Number(1), 0)).unwrap();
+
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_getUncleByBlockNumberAndIndex",
+		"params": ["0x1", "0x0"],
+		"id": 1
+	}"#;
+	let response = tester.io.handle_request(request).unwrap();
+
+	assert!(response.contains(&format!(r#""hash":"0x{}""#, uncle.hash().hex())));
+	assert!(response.contains(r#""number":"0x01""#));
+}
+
+#[test]
+fn rpc_eth_uncle_out_of_range_index() {
+	let tester = EthTester::default();
+	tester.client.add_blocks(1, EachBlockWith::Uncle);
+
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_getUncleByBlockNumberAndIndex",
+		"params": ["0x1", "0x1"],
+		"id": 1
+	}"#;
+	let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+
+	assert_eq!(tester.io.handle_request(request), Some(response.to_owned()));
+}
+
 #[test]
 fn rpc_eth_code() {
 	let tester = EthTester::default();
@@ -353,6 +462,9 @@ fn rpc_eth_call() {
 		contracts_created: vec![],
 		output: vec![0x12, 0x34, 0xff],
 		trace: None,
+		vm_trace: None,
+		exception: false,
+		state_diff: None,
 	});
 
 	let request = r#"{
@@ -386,6 +498,9 @@ fn rpc_eth_call_default_block() {
 		contracts_created: vec![],
 		output: vec![0x12, 0x34, 0xff],
 		trace: None,
+		vm_trace: None,
+		exception: false,
+		state_diff: None,
 	});
 
 	let request = r#"{
@@ -406,6 +521,74 @@ fn rpc_eth_call_default_block() {
 	assert_eq!(tester.io.handle_request(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_eth_call_times_out() {
+	// given
+	let temp = RandomTempPath::new();
+	let client = Client::new(ClientConfig::default(), Spec::new_test(), temp.as_path(), IoChannel::disconnected()).unwrap();
+	let external_miner = Arc::new(ExternalMiner::default());
+	let eth = EthClient::new(&client, &sync_provider(), &accounts_provider(), &miner_service(), &external_miner)
+		.with_call_timeout(Some(Duration::from_millis(100)));
+	let io = IoHandler::new();
+	io.add_delegate(eth.to_delegate());
+
+	// 5b - jumpdest
+	// 60 00 - push 0
+	// 56 - jump (loops forever)
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_call",
+		"params": [{
+			"from": "0xb60e8dd61c5d32be8058bb8eb970870f07233155",
+			"gas": "0xffffffffffffffff",
+			"data": "0x5b600056"
+		},
+		"latest"],
+		"id": 1
+	}"#;
+
+	// when
+	let started = Instant::now();
+	let response = io.handle_request(request).unwrap();
+
+	// then
+	assert!(response.contains(r#""code":-32012"#), "Unexpected response: {}", response);
+	assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn rpc_eth_call_does_not_modify_state() {
+	// given
+	let temp = RandomTempPath::new();
+	let client = Client::new(ClientConfig::default(), Spec::new_test(), temp.as_path(), IoChannel::disconnected()).unwrap();
+	let external_miner = Arc::new(ExternalMiner::default());
+	let eth = EthClient::new(&client, &sync_provider(), &accounts_provider(), &miner_service(), &external_miner);
+	let io = IoHandler::new();
+	io.add_delegate(eth.to_delegate());
+	let request = |data: &str| format!(r#"{{
+		"jsonrpc": "2.0",
+		"method": "eth_call",
+		"params": [{{
+			"from": "0xb60e8dd61c5d32be8058bb8eb970870f07233155",
+			"gas": "0x100000",
+			"data": "{}"
+		}},
+		"latest"],
+		"id": 1
+	}}"#, data);
+
+	// when
+	// 60 01 60 00 f3 - return(0, 1)
+	let read_only = io.handle_request(&request("0x60016000f3"));
+	// 60 01 60 00 55 - sstore(0, 1)
+	// 60 01 60 00 f3 - return(0, 1)
+	let writing = io.handle_request(&request("0x600160005560016000f3"));
+
+	// then
+	assert_eq!(read_only, Some(r#"{"jsonrpc":"2.0","result":"0x00","id":1}"#.to_owned()));
+	assert_eq!(writing, Some(r#"{"jsonrpc":"2.0","result":"0x","id":1}"#.to_owned()));
+}
+
 #[test]
 fn rpc_eth_estimate_gas() {
 	let tester = EthTester::default();
@@ -418,6 +601,9 @@ fn rpc_eth_estimate_gas() {
 		contracts_created: vec![],
 		output: vec![0x12, 0x34, 0xff],
 		trace: None,
+		vm_trace: None,
+		exception: false,
+		state_diff: None,
 	});
 
 	let request = r#"{
@@ -451,6 +637,9 @@ fn rpc_eth_estimate_gas_default_block() {
 		contracts_created: vec![],
 		output: vec![0x12, 0x34, 0xff],
 		trace: None,
+		vm_trace: None,
+		exception: false,
+		state_diff: None,
 	});
 
 	let request = r#"{
@@ -521,6 +710,85 @@ fn rpc_eth_send_transaction() {
 	assert_eq!(tester.io.handle_request(request.as_ref()), Some(response));
 }
 
+#[test]
+fn rpc_eth_send_transaction_signs_with_chain_id() {
+	let account = TestAccount::new("123");
+	let address = account.address();
+	let secret = account.secret.clone();
+
+	let tester = EthTester::default();
+	tester.client.set_chain_id(Some(61));
+	tester.accounts_provider.accounts.write().unwrap().insert(address.clone(), account);
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", address).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"gas": "0x76c0",
+			"gasPrice": "0x9184e72a000",
+			"value": "0x9184e72a"
+		}],
+		"id": 1
+	}"#;
+
+	let t = Transaction {
+		nonce: U256::zero(),
+		gas_price: U256::from(0x9184e72a000u64),
+		gas: U256::from(0x76c0),
+		action: Action::Call(Address::from_str("d46e8dd67c5d32be8058bb8eb970870f07244567").unwrap()),
+		value: U256::from(0x9184e72au64),
+		data: vec![]
+	}.sign_with_chain_id(&secret, Some(61));
+	assert_eq!(t.chain_id(), Some(61));
+
+	let response = r#"{"jsonrpc":"2.0","result":""#.to_owned() + format!("0x{:?}", t.hash()).as_ref() + r#"","id":1}"#;
+
+	assert_eq!(tester.io.handle_request(request.as_ref()), Some(response));
+}
+
+#[test]
+fn rpc_eth_send_transaction_records_origin() {
+	use ethminer::{MinerService, SubmissionOrigin, SubmissionStats};
+	use origin::with_origin;
+
+	let account = TestAccount::new("123");
+	let address = account.address();
+	let tester = EthTester::default();
+	tester.accounts_provider.accounts.write().unwrap().insert(address.clone(), account);
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", address).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"gas": "0x76c0",
+			"gasPrice": "0x9184e72a000",
+			"value": "0x9184e72a"
+		}],
+		"id": 1
+	}"#;
+	let wallet = SubmissionOrigin::new("dapp:wallet");
+	let ipc = SubmissionOrigin::new("ipc:uid=1000");
+
+	with_origin(wallet.clone(), || tester.io.handle_request(request.as_ref()));
+	let first = tester.miner.imported_transactions.lock().unwrap()[0].hash();
+	tester.miner.last_nonces.write().unwrap().insert(address.clone(), U256::zero());
+	with_origin(ipc.clone(), || tester.io.handle_request(request.as_ref()));
+	tester.io.handle_request(request.as_ref());
+	let second = tester.miner.imported_transactions.lock().unwrap()[1].hash();
+
+	let audit = tester.miner.transaction_audit(&first);
+	assert_eq!(audit.len(), 1);
+	assert_eq!(audit[0].origin, wallet);
+	assert_eq!(tester.miner.transaction_audit(&second)[0].origin, ipc);
+
+	let stats = tester.miner.submission_stats();
+	assert_eq!(stats[&wallet], SubmissionStats { imported: 1, rejected: 0 });
+	assert_eq!(stats[&ipc], SubmissionStats { imported: 1, rejected: 0 });
+	assert_eq!(stats[&SubmissionOrigin::anonymous()], SubmissionStats { imported: 1, rejected: 0 });
+}
+
 #[test]
 fn rpc_eth_sign_and_send_transaction_with_invalid_password() {
 	let account = TestAccount::new("password123");
@@ -703,13 +971,59 @@ fn returns_no_work_if_cant_mine() {
 	eth_tester.client.set_queue_size(10);
 
 	let request = r#"{"jsonrpc": "2.0", "method": "eth_getWork", "params": [], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":["","",""],"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":["","","",""],"id":1}"#;
 
 	assert_eq!(eth_tester.io.handle_request(request), Some(response.to_owned()));
 }
 
-#[ignore]
-// enable once TestMinerService supports the mining API.
+#[test]
+fn returns_work_with_block_number_and_target() {
+	// given
+	let dir = RandomTempPath::new();
+	let tester = EthTester::default();
+	let block = closed_block(&dir);
+	let pow_hash = block.hash();
+	let target = Ethash::difficulty_to_boundary(block.block().header().difficulty());
+	*tester.miner.latest_closed_block.lock().unwrap() = Some(block);
+
+	// when
+	let request = r#"{"jsonrpc": "2.0", "method": "eth_getWork", "params": [], "id": 1}"#;
+	let response = tester.io.handle_request(request);
+
+	// then
+	let expected = format!(r#"{{"jsonrpc":"2.0","result":["0x{:?}","0x{:?}","0x{:?}","0x01"],"id":1}}"#, pow_hash, H256::zero(), target);
+	assert_eq!(response, Some(expected));
+}
+
+#[test]
+fn returns_legacy_work_without_block_number_if_requested() {
+	// given
+	let dir = RandomTempPath::new();
+	let client = blockchain_client();
+	let sync = sync_provider();
+	let ap = accounts_provider();
+	let miner = miner_service();
+	let external_miner = Arc::new(ExternalMiner::default());
+	let eth = EthClient::new(&client, &sync, &ap, &miner, &external_miner)
+		.with_send_block_number_in_get_work(false);
+	let io = IoHandler::new();
+	io.add_delegate(eth.to_delegate());
+	let block = closed_block(&dir);
+	let pow_hash = block.hash();
+	let target = Ethash::difficulty_to_boundary(block.block().header().difficulty());
+	*miner.latest_closed_block.lock().unwrap() = Some(block);
+
+	// when
+	let request = r#"{"jsonrpc": "2.0", "method": "eth_getWork", "params": [], "id": 1}"#;
+	let response = io.handle_request(request);
+
+	// then
+	let expected = format!(r#"{{"jsonrpc":"2.0","result":["0x{:?}","0x{:?}","0x{:?}"],"id":1}}"#, pow_hash, H256::zero(), target);
+	assert_eq!(response, Some(expected));
+	client.set_queue_size(10);
+	assert_eq!(io.handle_request(request), Some(r#"{"jsonrpc":"2.0","result":["","",""],"id":1}"#.to_owned()));
+}
+
 #[test]
 fn returns_error_if_can_mine_and_no_closed_block() {
 	use ethsync::{SyncState};
@@ -722,3 +1036,37 @@ fn returns_error_if_can_mine_and_no_closed_block() {
 
 	assert_eq!(eth_tester.io.handle_request(request), Some(response.to_owned()));
 }
+
+#[test]
+fn rpc_eth_read_only_rejects_send_but_allows_queries() {
+	use read_only::MethodFilter;
+
+	let account = TestAccount::new("123");
+	let address = account.address();
+	let tester = EthTester::default();
+	tester.accounts_provider.accounts.write().unwrap().insert(address.clone(), account);
+	let filter = MethodFilter::default();
+	filter.restrict_to_read_only();
+
+	let request = r#"{
+		"jsonrpc": "2.0",
+		"method": "eth_sendTransaction",
+		"params": [{
+			"from": ""#.to_owned() + format!("0x{:?}", address).as_ref() + r#"",
+			"to": "0xd46e8dd67c5d32be8058bb8eb970870f07244567",
+			"value": "0x9184e72a"
+		}],
+		"id": 1
+	}"#;
+	let rejected = r#"{"jsonrpc":"2.0","error":{"code":-32011,"message":"Method is not available on read-only node.","data":null},"id":1}"#;
+	assert_eq!(filter.handle_request(&tester.io, request.as_ref()), Some(rejected.to_owned()));
+	assert!(tester.miner.imported_transactions.lock().unwrap().is_empty());
+
+	// methods of APIs which weren't enabled are rejected as well
+	let request = r#"{"jsonrpc": "2.0", "method": "personal_newAccount", "params": ["pass"], "id": 1}"#;
+	assert_eq!(filter.handle_request(&tester.io, request), Some(rejected.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "eth_gasPrice", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0x04a817c800","id":1}"#;
+	assert_eq!(filter.handle_request(&tester.io, request), Some(response.to_owned()));
+}
@@ -19,6 +19,7 @@ use std::str::FromStr;
 use jsonrpc_core::IoHandler;
 use v1::{Ethcore, EthcoreClient};
 use ethminer::MinerService;
+use ethcore::client::TestBlockChainClient;
 use v1::tests::helpers::TestMinerService;
 use util::numbers::*;
 use rustc_serialize::hex::FromHex;
@@ -45,8 +46,12 @@ fn settings() -> Arc<NetworkSettings> {
 	})
 }
 
-fn ethcore_client(miner: &Arc<TestMinerService>) -> EthcoreClient<TestMinerService> {
-	EthcoreClient::new(&miner, logger(), settings())
+fn client_service() -> Arc<TestBlockChainClient> {
+	Arc::new(TestBlockChainClient::default())
+}
+
+fn ethcore_client(miner: &Arc<TestMinerService>) -> EthcoreClient<TestBlockChainClient, TestMinerService> {
+	EthcoreClient::new(&client_service(), &miner, logger(), settings())
 }
 
 #[test]
@@ -112,7 +117,7 @@ fn rpc_ethcore_set_min_gas_price() {
 	io.add_delegate(ethcore);
 
 	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setMinGasPrice", "params":["0xcd1722f3947def4cf144679da39c4c32bdc35681"], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0xcd1722f3947def4cf144679da39c4c32bdc35681","id":1}"#;
 
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 	assert_eq!(miner.minimal_gas_price(), U256::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap());
@@ -126,7 +131,7 @@ fn rpc_ethcore_set_gas_floor_target() {
 	io.add_delegate(ethcore);
 
 	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setGasFloorTarget", "params":["0xcd1722f3947def4cf144679da39c4c32bdc35681"], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0xcd1722f3947def4cf144679da39c4c32bdc35681","id":1}"#;
 
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 	assert_eq!(miner.gas_floor_target(), U256::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap());
@@ -140,7 +145,7 @@ fn rpc_ethcore_set_extra_data() {
 	io.add_delegate(ethcore);
 
 	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setExtraData", "params":["0xcd1722f3947def4cf144679da39c4c32bdc35681"], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0xcd1722f3947def4cf144679da39c4c32bdc35681","id":1}"#;
 
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 	assert_eq!(miner.extra_data(), "cd1722f3947def4cf144679da39c4c32bdc35681".from_hex().unwrap());
@@ -154,19 +159,44 @@ fn rpc_ethcore_set_author() {
 	io.add_delegate(ethcore);
 
 	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setAuthor", "params":["0xcd1722f3947def4cf144679da39c4c32bdc35681"], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":"0xcd1722f3947def4cf144679da39c4c32bdc35681","id":1}"#;
 
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 	assert_eq!(miner.author(), Address::from_str("cd1722f3947def4cf144679da39c4c32bdc35681").unwrap());
 }
 
+#[test]
+fn rpc_ethcore_setters_reject_invalid_params() {
+	let miner = miner_service();
+	let ethcore = ethcore_client(&miner).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params","data":null},"id":1}"#;
+
+	let requests = [
+		r#"{"jsonrpc": "2.0", "method": "ethcore_setMinGasPrice", "params":["0xzz"], "id": 1}"#,
+		r#"{"jsonrpc": "2.0", "method": "ethcore_setGasFloorTarget", "params":[], "id": 1}"#,
+		r#"{"jsonrpc": "2.0", "method": "ethcore_setExtraData", "params":["0x000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20"], "id": 1}"#,
+		r#"{"jsonrpc": "2.0", "method": "ethcore_setAuthor", "params":["0xcd1722f3947def4c"], "id": 1}"#,
+		r#"{"jsonrpc": "2.0", "method": "ethcore_setTransactionsLimit", "params":["many"], "id": 1}"#,
+	];
+	for request in requests.iter() {
+		assert_eq!(io.handle_request(request), Some(response.to_owned()));
+	}
+	assert_eq!(miner.minimal_gas_price(), U256::from(20_000_000));
+	assert_eq!(miner.gas_floor_target(), U256::from(12345));
+	assert_eq!(miner.extra_data(), vec![1, 2, 3, 4]);
+	assert_eq!(miner.author(), Address::default());
+	assert_eq!(miner.transactions_limit(), 1024);
+}
+
 #[test]
 fn rpc_ethcore_dev_logs() {
 	let miner = miner_service();
 	let logger = logger();
 	logger.append("a".to_owned());
 	logger.append("b".to_owned());
-	let ethcore = EthcoreClient::new(&miner, logger.clone(), settings()).to_delegate();
+	let ethcore = EthcoreClient::new(&client_service(), &miner, logger.clone(), settings()).to_delegate();
 	let io = IoHandler::new();
 	io.add_delegate(ethcore);
 
@@ -196,7 +226,7 @@ fn rpc_ethcore_set_transactions_limit() {
 	io.add_delegate(ethcore);
 
 	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setTransactionsLimit", "params":[10240240], "id": 1}"#;
-	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":10240240,"id":1}"#;
 
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 	assert_eq!(miner.transactions_limit(), 10_240_240);
@@ -215,6 +245,25 @@ fn rpc_ethcore_transactions_limit() {
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 }
 
+#[test]
+fn rpc_ethcore_set_sealing_enabled() {
+	let miner = miner_service();
+	let ethcore = ethcore_client(&miner).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_setSealingEnabled", "params":[false], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":false,"id":1}"#;
+
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+	assert!(!miner.sealing_enabled());
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_sealingEnabled", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":false,"id":1}"#;
+
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
 #[test]
 fn rpc_ethcore_net_chain() {
 	let miner = miner_service();
@@ -279,3 +328,207 @@ fn rpc_ethcore_node_name() {
 
 	assert_eq!(io.handle_request(request), Some(response.to_owned()));
 }
+
+#[test]
+fn rpc_ethcore_state_stats_unknown_block() {
+	let miner = miner_service();
+	let client = client_service();
+	let ethcore = EthcoreClient::new(&client, &miner, logger(), settings()).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_stateStats", "params":["latest"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_state_stats_not_available_in_safe_api_set() {
+	use read_only::MethodFilter;
+
+	let miner = miner_service();
+	let client = client_service();
+	let ethcore = EthcoreClient::new(&client, &miner, logger(), settings()).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+	let filter = MethodFilter::default();
+	filter.restrict_to_safe();
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_stateStats", "params":["latest"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found","data":null},"id":1}"#;
+
+	assert_eq!(filter.handle_request(&io, request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_state_stats_invalid_sample() {
+	let miner = miner_service();
+	let client = client_service();
+	let ethcore = EthcoreClient::new(&client, &miner, logger(), settings()).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_stateStats", "params":["latest", 1.5], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","error":{"code":-32602,"message":"Invalid params","data":null},"id":1}"#;
+
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_transaction_audit_and_submission_stats() {
+	use ethminer::{SubmissionOrigin, TransactionImportResult, TransactionImportError};
+
+	let miner = miner_service();
+	let ethcore = ethcore_client(&miner).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+	{
+		let mut audit = miner.submission_audit.lock().unwrap();
+		audit.record(H256::from(1), SubmissionOrigin::new("dapp:wallet"), &Ok(TransactionImportResult::Current));
+		audit.record(H256::from(1), SubmissionOrigin::new("ipc:uid=1000"), &Err(TransactionImportError::AlreadyImported));
+		audit.record(H256::from(2), SubmissionOrigin::new("dapp:wallet"), &Ok(TransactionImportResult::Future));
+	}
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionAudit", "params": ["0x0000000000000000000000000000000000000000000000000000000000000001"], "id": 1}"#;
+	let response = io.handle_request(request).unwrap();
+	assert!(response.contains(r#""origin":"dapp:wallet","timestamp""#));
+	assert!(response.contains(r#""origin":"ipc:uid=1000","timestamp""#));
+	assert_eq!(response.matches(r#""error":null"#).count(), 1);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_submissionStats", "params": [], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"dapp:wallet":{"imported":"0x02","rejected":"0x00"},"ipc:uid=1000":{"imported":"0x00","rejected":"0x01"}},"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_uncle_info() {
+	use ethcore::client::{BlockChainClient, EachBlockWith, BlockID, UncleID};
+	use util::hash::FixedHash;
+
+	let miner = miner_service();
+	let client = client_service();
+	client.add_blocks(2, EachBlockWith::Uncle);
+	let ethcore = EthcoreClient::new(&client, &miner, logger(), settings()).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+	let block_hash = client.block_hash(BlockID::Number(1)).unwrap();
+	let uncle = client.uncle(UncleID(BlockID::Number(1), 0)).unwrap();
+
+	let request = format!(r#"{{"jsonrpc": "2.0", "method": "ethcore_uncleInfo", "params": ["0x{}"], "id": 1}}"#, uncle.hash().hex());
+	let response = io.handle_request(&request).unwrap();
+	assert!(response.contains(&format!(r#""hash":"0x{}""#, uncle.hash().hex())));
+	assert!(response.contains(&format!(r#""blockHash":"0x{}""#, block_hash.hex())));
+	assert!(response.contains(r#""blockNumber":"0x01""#));
+	assert!(response.contains(r#""index":"0x00""#));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_uncleInfo", "params": ["0x0000000000000000000000000000000000000000000000000000000000000001"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_transaction_details() {
+	use util::*;
+	use ethcore::transaction::SignedTransaction;
+
+	let miner = miner_service();
+	let ethcore = ethcore_client(&miner).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+	{
+		let tx: SignedTransaction = decode(&FromHex::from_hex("f85f800182520894095e7baea6a6c7c4c2dfeb977efac326af552d870a801ba048b55bfa915ac795c431978d8a6a992b628d557da5ff759b307d495a36649353a0efffd310ac743f371de3b9f7f9cb56c0b28ad43601b4ab949f53faa07bd2c804").unwrap());
+		miner.pending_transactions.lock().unwrap().insert(H256::from(1), tx);
+	}
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionDetails", "params": ["0x0000000000000000000000000000000000000000000000000000000000000001"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"hash":"0x0000000000000000000000000000000000000000000000000000000000000001","origin":"external","insertionBlock":"0x00","state":"pending"},"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_transactionDetails", "params": ["0x0000000000000000000000000000000000000000000000000000000000000002"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_bundle_status() {
+	use ethminer::BundleStatus;
+
+	let miner = miner_service();
+	let ethcore = ethcore_client(&miner).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+	{
+		let mut bundles = miner.bundles.lock().unwrap();
+		bundles.insert(0, BundleStatus::Pending);
+		bundles.insert(1, BundleStatus::Mined { number: 2, hash: H256::from(1) });
+	}
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_bundleStatus", "params": ["0x01"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"status":"mined","blockNumber":"0x02","blockHash":"0x0000000000000000000000000000000000000000000000000000000000000001"},"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_bundleStatus", "params": ["0x00"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"status":"pending","blockNumber":null,"blockHash":null},"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_bundleStatus", "params": ["0x05"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_ban_and_unban_address() {
+	let miner = miner_service();
+	let ethcore = ethcore_client(&miner).to_delegate();
+	let io = IoHandler::new();
+	io.add_delegate(ethcore);
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_banAddress", "params":["0xcd1722f3947def4cf144679da39c4c32bdc35681"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_bannedAddresses", "params":[], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":["0xcd1722f3947def4cf144679da39c4c32bdc35681"],"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_unbanAddress", "params":["0xcd1722f3947def4cf144679da39c4c32bdc35681"], "id": 1}"#;
+	let response = r#"{"jsonrpc":"2.0","result":true,"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+	assert!(miner.banned_addresses().is_empty());
+
+	let response = r#"{"jsonrpc":"2.0","result":false,"id":1}"#;
+	assert_eq!(io.handle_request(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_ethcore_rpc_stats() {
+	use stats::{RpcStats, CountingHandler};
+	use v1::{Web3, Web3Client};
+
+	let miner = miner_service();
+	let rpc_stats = Arc::new(RpcStats::default());
+	let io = IoHandler::new();
+	io.add_delegate(Web3Client::new().to_delegate());
+	io.add_delegate(ethcore_client(&miner).with_rpc_stats(rpc_stats.clone()).to_delegate());
+	let handler = CountingHandler::new(Arc::new(io), rpc_stats);
+
+	handler.handle_request(r#"{"jsonrpc": "2.0", "method": "web3_clientVersion", "params": [], "id": 1}"#).unwrap();
+	handler.handle_request(r#"{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["0x00"], "id": 2}"#).unwrap();
+	handler.handle_request(r#"[
+		{"jsonrpc": "2.0", "method": "web3_sha3", "params": ["0x01"], "id": 3},
+		{"jsonrpc": "2.0", "method": "web3_sha3", "params": [], "id": 4}
+	]"#).unwrap();
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_rpcStats", "params": [], "id": 5}"#;
+	let response = handler.handle_request(request).unwrap();
+	assert!(response.contains(r#""web3_clientVersion":{"calls":"0x01","errors":"0x00","latency":"0x"#), "Unexpected response: {}", response);
+	assert!(response.contains(r#""web3_sha3":{"calls":"0x03","errors":"0x01","latency":"0x"#), "Unexpected response: {}", response);
+	assert!(!response.contains("ethcore_rpcStats"));
+
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_resetRpcStats", "params": [], "id": 6}"#;
+	assert_eq!(handler.handle_request(request), Some(r#"{"jsonrpc":"2.0","result":true,"id":6}"#.to_owned()));
+	let request = r#"{"jsonrpc": "2.0", "method": "ethcore_rpcStats", "params": [], "id": 7}"#;
+	let response = r#"{"jsonrpc":"2.0","result":{"ethcore_resetRpcStats":{"calls":"0x01","errors":"0x00","latency":"0x"#;
+	assert!(handler.handle_request(request).unwrap().starts_with(response));
+}
@@ -18,12 +18,22 @@
 
 use util::{Address, H256, Bytes, U256, FixedHash, Uint};
 use util::standard::*;
+use util::rlp::{UntrustedRlp, View};
+use std::time::{Instant, Duration};
 use ethcore::error::{Error, ExecutionError};
 use ethcore::client::{BlockChainClient, Executed};
 use ethcore::block::{ClosedBlock, IsBlock};
 use ethcore::transaction::SignedTransaction;
 use ethcore::receipt::Receipt;
-use ethminer::{MinerService, MinerStatus, AccountDetails, TransactionImportResult};
+use ethcore::filter::Filter;
+use ethcore::log_entry::LocalizedLogEntry;
+use ethcore::header::BlockNumber;
+use ethcore::gas_price_rule::GasPriceRule;
+use ethcore::gas_limit::GasLimitStrategy;
+use ethminer::{MinerService, MinerStatus, SealingWork, CallOverrides, AccountDetails, TransactionImportResult, TransactionImportError};
+use ethminer::{TransactionOrigin, TransactionDetails, TransactionState};
+use ethminer::{SubmissionOrigin, SubmissionAudit, SubmissionStats, AuditEntry};
+use ethminer::{BundleId, BundleStatus, BundleImportError};
 
 /// Test miner service.
 pub struct TestMinerService {
@@ -33,16 +43,38 @@ pub struct TestMinerService {
 	pub latest_closed_block: Mutex<Option<ClosedBlock>>,
 	/// Pre-existed pending transactions
 	pub pending_transactions: Mutex<HashMap<H256, SignedTransaction>>,
+	/// Pre-existed future transactions
+	pub future_transactions: Mutex<Vec<SignedTransaction>>,
 	/// Pre-existed pending receipts
 	pub pending_receipts: Mutex<BTreeMap<H256, Receipt>>,
 	/// Last nonces.
 	pub last_nonces: RwLock<HashMap<Address, U256>>,
+	/// Audit trail of submitted transactions.
+	pub submission_audit: Mutex<SubmissionAudit>,
+	/// Statuses of imported bundles.
+	pub bundles: Mutex<HashMap<BundleId, BundleStatus>>,
+	/// Banned addresses.
+	pub banned_addresses: RwLock<BTreeSet<Address>>,
+	/// Hashes of blocks sealed with given work (indexed by pow hash).
+	pub sealable_work: Mutex<HashMap<H256, H256>>,
 
 	min_gas_price: RwLock<U256>,
+	gas_price_rule: RwLock<GasPriceRule>,
 	gas_floor_target: RwLock<U256>,
+	gas_ceil_target: RwLock<U256>,
+	gas_limit_strategy: RwLock<GasLimitStrategy>,
+	include_uncles: RwLock<bool>,
+	tx_gas_limit: RwLock<U256>,
 	author: RwLock<Address>,
 	extra_data: RwLock<Bytes>,
 	limit: RwLock<usize>,
+	byte_limit: RwLock<usize>,
+	max_per_sender: RwLock<usize>,
+	max_age: RwLock<BlockNumber>,
+	bundle_max_age: RwLock<BlockNumber>,
+	reseal_min_period: RwLock<Duration>,
+	sealing_enabled: RwLock<bool>,
+	sealing_work_timeout: RwLock<Option<Duration>>,
 }
 
 impl Default for TestMinerService {
@@ -51,13 +83,30 @@ impl Default for TestMinerService {
 			imported_transactions: Mutex::new(Vec::new()),
 			latest_closed_block: Mutex::new(None),
 			pending_transactions: Mutex::new(HashMap::new()),
+			future_transactions: Mutex::new(Vec::new()),
 			pending_receipts: Mutex::new(BTreeMap::new()),
 			last_nonces: RwLock::new(HashMap::new()),
+			submission_audit: Mutex::new(SubmissionAudit::default()),
+			bundles: Mutex::new(HashMap::new()),
+			banned_addresses: RwLock::new(BTreeSet::new()),
+			sealable_work: Mutex::new(HashMap::new()),
 			min_gas_price: RwLock::new(U256::from(20_000_000)),
+			gas_price_rule: RwLock::new(GasPriceRule::default()),
 			gas_floor_target: RwLock::new(U256::from(12345)),
+			gas_ceil_target: RwLock::new(U256::from(12345)),
+			gas_limit_strategy: RwLock::new(GasLimitStrategy::Fixed),
+			include_uncles: RwLock::new(true),
+			tx_gas_limit: RwLock::new(!U256::zero()),
 			author: RwLock::new(Address::zero()),
 			extra_data: RwLock::new(vec![1, 2, 3, 4]),
 			limit: RwLock::new(1024),
+			byte_limit: RwLock::new(usize::max_value()),
+			max_per_sender: RwLock::new(usize::max_value()),
+			max_age: RwLock::new(600),
+			bundle_max_age: RwLock::new(50),
+			reseal_min_period: RwLock::new(Duration::from_millis(0)),
+			sealing_enabled: RwLock::new(true),
+			sealing_work_timeout: RwLock::new(None),
 		}
 	}
 }
@@ -69,7 +118,9 @@ impl MinerService for TestMinerService {
 		MinerStatus {
 			transactions_in_pending_queue: 0,
 			transactions_in_future_queue: 0,
-			transactions_in_pending_block: 1
+			transactions_in_pending_block: 1,
+			transactions_bytes: 0,
+			uncles_in_pending_block: 0,
 		}
 	}
 
@@ -86,6 +137,39 @@ impl MinerService for TestMinerService {
 		*self.gas_floor_target.write().unwrap() = target;
 	}
 
+	fn gas_ceil_target(&self) -> U256 {
+		*self.gas_ceil_target.read().unwrap()
+	}
+
+	fn set_gas_range_target(&self, (floor, ceil): (U256, U256)) {
+		*self.gas_floor_target.write().unwrap() = floor;
+		*self.gas_ceil_target.write().unwrap() = ceil;
+	}
+
+	fn gas_limit_strategy(&self) -> GasLimitStrategy {
+		*self.gas_limit_strategy.read().unwrap()
+	}
+
+	fn set_gas_limit_strategy(&self, strategy: GasLimitStrategy) {
+		*self.gas_limit_strategy.write().unwrap() = strategy;
+	}
+
+	fn include_uncles(&self) -> bool {
+		*self.include_uncles.read().unwrap()
+	}
+
+	fn set_include_uncles(&self, include: bool) {
+		*self.include_uncles.write().unwrap() = include;
+	}
+
+	fn tx_gas_limit(&self) -> U256 {
+		*self.tx_gas_limit.read().unwrap()
+	}
+
+	fn set_tx_gas_limit(&self, limit: U256) {
+		*self.tx_gas_limit.write().unwrap() = limit;
+	}
+
 	fn set_minimal_gas_price(&self, min_gas_price: U256) {
 		*self.min_gas_price.write().unwrap() = min_gas_price;
 	}
@@ -98,6 +182,66 @@ impl MinerService for TestMinerService {
 		*self.limit.read().unwrap()
 	}
 
+	fn set_total_byte_limit(&self, limit: usize) {
+		*self.byte_limit.write().unwrap() = limit;
+	}
+
+	fn total_byte_limit(&self) -> usize {
+		*self.byte_limit.read().unwrap()
+	}
+
+	fn set_max_per_sender(&self, max_per_sender: usize) {
+		*self.max_per_sender.write().unwrap() = max_per_sender;
+	}
+
+	fn max_per_sender(&self) -> usize {
+		*self.max_per_sender.read().unwrap()
+	}
+
+	fn ban_address(&self, address: Address) {
+		self.banned_addresses.write().unwrap().insert(address);
+	}
+
+	fn unban_address(&self, address: &Address) -> bool {
+		self.banned_addresses.write().unwrap().remove(address)
+	}
+
+	fn banned_addresses(&self) -> Vec<Address> {
+		self.banned_addresses.read().unwrap().iter().cloned().collect()
+	}
+
+	fn set_transaction_queue_max_age(&self, max_age: BlockNumber) {
+		*self.max_age.write().unwrap() = max_age;
+	}
+
+	fn transaction_queue_max_age(&self) -> BlockNumber {
+		*self.max_age.read().unwrap()
+	}
+
+	fn reseal_min_period(&self) -> Duration {
+		*self.reseal_min_period.read().unwrap()
+	}
+
+	fn set_reseal_min_period(&self, period: Duration) {
+		*self.reseal_min_period.write().unwrap() = period;
+	}
+
+	fn sealing_enabled(&self) -> bool {
+		*self.sealing_enabled.read().unwrap()
+	}
+
+	fn set_sealing_enabled(&self, enabled: bool) {
+		*self.sealing_enabled.write().unwrap() = enabled;
+	}
+
+	fn sealing_work_timeout(&self) -> Option<Duration> {
+		*self.sealing_work_timeout.read().unwrap()
+	}
+
+	fn set_sealing_work_timeout(&self, timeout: Option<Duration>) {
+		*self.sealing_work_timeout.write().unwrap() = timeout;
+	}
+
 	fn author(&self) -> Address {
 		*self.author.read().unwrap()
 	}
@@ -106,6 +250,14 @@ impl MinerService for TestMinerService {
 		*self.min_gas_price.read().unwrap()
 	}
 
+	fn gas_price_rule(&self) -> GasPriceRule {
+		self.gas_price_rule.read().unwrap().clone()
+	}
+
+	fn set_gas_price_rule(&self, rule: GasPriceRule) {
+		*self.gas_price_rule.write().unwrap() = rule;
+	}
+
 	fn extra_data(&self) -> Bytes {
 		self.extra_data.read().unwrap().clone()
 	}
@@ -115,11 +267,17 @@ impl MinerService for TestMinerService {
 	}
 
 	/// Imports transactions to transaction queue.
-	fn import_transactions<T>(&self, transactions: Vec<SignedTransaction>, _fetch_account: T) ->
-		Vec<Result<TransactionImportResult, Error>>
+	fn import_transactions<T>(&self, _chain: &BlockChainClient, transactions: Vec<SignedTransaction>, origin: Option<SubmissionOrigin>, _fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
 		where T: Fn(&Address) -> AccountDetails {
 		// lets assume that all txs are valid
 		self.imported_transactions.lock().unwrap().extend_from_slice(&transactions);
+		if let Some(origin) = origin {
+			let mut audit = self.submission_audit.lock().unwrap();
+			for t in &transactions {
+				audit.record(t.hash(), origin.clone(), &Ok(TransactionImportResult::Current));
+			}
+		}
 
 		transactions
 			.iter()
@@ -127,14 +285,62 @@ impl MinerService for TestMinerService {
 			.collect()
 	}
 
+	fn import_raw_transactions<T>(&self, _chain: &BlockChainClient, transactions: Vec<Bytes>, _fetch_account: T) ->
+		Vec<Result<TransactionImportResult, TransactionImportError>>
+		where T: Fn(&Address) -> AccountDetails {
+		transactions
+			.iter()
+			.map(|rlp| UntrustedRlp::new(rlp).as_val::<SignedTransaction>().map_err(TransactionImportError::InvalidRlp))
+			.map(|tx| tx.map(|tx| {
+				self.imported_transactions.lock().unwrap().push(tx);
+				TransactionImportResult::Current
+			}))
+			.collect()
+	}
+
 	/// Imports transactions to transaction queue.
-	fn import_own_transaction<T>(&self, _chain: &BlockChainClient, transaction: SignedTransaction, _fetch_account: T) ->
-		Result<TransactionImportResult, Error>
+	fn import_own_transaction<T>(&self, _chain: &BlockChainClient, transaction: SignedTransaction, origin: Option<SubmissionOrigin>, _fetch_account: T) ->
+		Result<TransactionImportResult, TransactionImportError>
 		where T: Fn(&Address) -> AccountDetails {
 		// lets assume that all txs are valid
+		let result = Ok(TransactionImportResult::Current);
+		self.submission_audit.lock().unwrap().record(transaction.hash(), origin.unwrap_or_else(SubmissionOrigin::anonymous), &result);
 		self.imported_transactions.lock().unwrap().push(transaction);
 
-		Ok(TransactionImportResult::Current)
+		result
+	}
+
+	fn import_transaction_bundle<T>(&self, _chain: &BlockChainClient, transactions: Vec<SignedTransaction>, _fetch_account: T) ->
+		Result<BundleId, BundleImportError>
+		where T: Fn(&Address) -> AccountDetails {
+		if transactions.is_empty() {
+			return Err(BundleImportError::Empty);
+		}
+		let mut bundles = self.bundles.lock().unwrap();
+		let id = bundles.len() as BundleId;
+		bundles.insert(id, BundleStatus::Pending);
+		self.imported_transactions.lock().unwrap().extend(transactions);
+		Ok(id)
+	}
+
+	fn bundle_status(&self, id: BundleId) -> Option<BundleStatus> {
+		self.bundles.lock().unwrap().get(&id).cloned()
+	}
+
+	fn bundle_max_age(&self) -> BlockNumber {
+		*self.bundle_max_age.read().unwrap()
+	}
+
+	fn set_bundle_max_age(&self, max_age: BlockNumber) {
+		*self.bundle_max_age.write().unwrap() = max_age;
+	}
+
+	fn transaction_audit(&self, hash: &H256) -> Vec<AuditEntry> {
+		self.submission_audit.lock().unwrap().entries(hash)
+	}
+
+	fn submission_stats(&self) -> BTreeMap<SubmissionOrigin, SubmissionStats> {
+		self.submission_audit.lock().unwrap().stats()
 	}
 
 	/// Returns hashes of transactions currently in pending
@@ -157,8 +363,22 @@ impl MinerService for TestMinerService {
 		unimplemented!();
 	}
 
-	fn map_sealing_work<F, T>(&self, _chain: &BlockChainClient, _f: F) -> Option<T> where F: FnOnce(&ClosedBlock) -> T {
-		unimplemented!();
+	fn map_sealing_work<F, T>(&self, _chain: &BlockChainClient, f: F) -> Option<T> where F: FnOnce(&SealingWork) -> T {
+		self.latest_closed_block.lock().unwrap().as_ref().map(|b| f(&SealingWork {
+			block: b,
+			job_id: 0,
+			pow_hash: b.hash(),
+			number: b.block().header().number(),
+			difficulty: b.block().header().difficulty().clone(),
+		}))
+	}
+
+	fn current_job_id(&self) -> Option<u64> {
+		None
+	}
+
+	fn remove_transaction(&self, hash: &H256) -> Option<SignedTransaction> {
+		self.pending_transactions.lock().unwrap().remove(hash)
 	}
 
 	fn transaction(&self, hash: &H256) -> Option<SignedTransaction> {
@@ -173,25 +393,72 @@ impl MinerService for TestMinerService {
 		self.pending_transactions.lock().unwrap().values().cloned().collect()
 	}
 
+	fn future_transactions(&self) -> Vec<SignedTransaction> {
+		self.future_transactions.lock().unwrap().clone()
+	}
+
+	fn transaction_details(&self, hash: &H256) -> Option<TransactionDetails> {
+		let state = match self.pending_transactions.lock().unwrap().contains_key(hash) {
+			true => TransactionState::Pending,
+			false if self.future_transactions.lock().unwrap().iter().any(|t| &t.hash() == hash) => TransactionState::Future,
+			false => return None,
+		};
+		Some(TransactionDetails {
+			hash: hash.clone(),
+			origin: TransactionOrigin::External,
+			insertion_block: 0,
+			state: state,
+		})
+	}
+
 	fn pending_receipts(&self) -> BTreeMap<H256, Receipt> {
 		self.pending_receipts.lock().unwrap().clone()
 	}
 
+	fn pending_logs(&self, filter: &Filter) -> Vec<LocalizedLogEntry> {
+		self.pending_receipts.lock().unwrap().iter()
+			.enumerate()
+			.flat_map(|(index, (hash, receipt))| receipt.logs.iter()
+				.filter(|log| filter.matches(log))
+				.map(|log| LocalizedLogEntry {
+					entry: log.clone(),
+					block_hash: H256::new(),
+					block_number: 0,
+					transaction_hash: hash.clone(),
+					transaction_index: index,
+					log_index: 0,
+				})
+				.collect::<Vec<_>>())
+			.collect()
+	}
+
 	fn last_nonce(&self, address: &Address) -> Option<U256> {
 		self.last_nonces.read().unwrap().get(address).cloned()
 	}
 
+	fn reserve_nonce(&self, chain: &BlockChainClient, address: &Address) -> U256 {
+		self.last_nonce(address).map_or_else(|| chain.nonce(address), |nonce| nonce + U256::one())
+	}
+
+	fn release_nonce(&self, _address: &Address, _nonce: &U256) {
+		// nonces are not reserved
+	}
+
 	/// Submit `seal` as a valid solution for the header of `pow_hash`.
 	/// Will check the seal, but not actually insert the block into the chain.
-	fn submit_seal(&self, _chain: &BlockChainClient, _pow_hash: H256, _seal: Vec<Bytes>) -> Result<(), Error> {
-		unimplemented!();
+	fn submit_seal(&self, _chain: &BlockChainClient, pow_hash: H256, _seal: Vec<Bytes>, _job_id: Option<u64>) -> Result<H256, Error> {
+		self.sealable_work.lock().unwrap().remove(&pow_hash).ok_or(Error::PowHashInvalid)
 	}
 
 	fn balance(&self, _chain: &BlockChainClient, address: &Address) -> U256 {
 		self.latest_closed_block.lock().unwrap().as_ref().map_or_else(U256::zero, |b| b.block().fields().state.balance(address).clone())
 	}
 
-	fn call(&self, _chain: &BlockChainClient, _t: &SignedTransaction) -> Result<Executed, ExecutionError> {
+	fn call(&self, _chain: &BlockChainClient, _t: &SignedTransaction, _overrides: &CallOverrides, _deadline: Option<Instant>) -> Result<Executed, ExecutionError> {
+		unimplemented!();
+	}
+
+	fn estimate_gas(&self, _chain: &BlockChainClient, _t: &SignedTransaction, _deadline: Option<Instant>) -> Result<U256, ExecutionError> {
 		unimplemented!();
 	}
 
@@ -17,7 +17,7 @@
 //! Test implementation of SyncProvider.
 
 use util::{U256};
-use ethsync::{SyncProvider, SyncStatus, SyncState};
+use ethsync::{SyncProvider, SyncStatus, SyncState, BlockCollectionStatus};
 use std::sync::{RwLock};
 
 /// TestSyncProvider config.
@@ -50,6 +50,9 @@ impl TestSyncProvider {
 				num_peers: config.num_peers,
 				num_active_peers: 0,
 				mem_used: 0,
+				clock_skew: None,
+				clock_skewed: false,
+				download: BlockCollectionStatus::default(),
 			}),
 		}
 	}
@@ -35,6 +35,9 @@ pub trait Eth: Sized + Send + Sync + 'static {
 	/// Returns true if client is actively mining new blocks.
 	fn is_mining(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
+	/// Returns id of the chain transactions are signed for (or null if the chain has no id).
+	fn chain_id(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
 	/// Returns current gas_price.
 	fn gas_price(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
@@ -125,7 +128,8 @@ pub trait Eth: Sized + Send + Sync + 'static {
 	/// Returns logs matching given filter object.
 	fn logs(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
-	/// Returns the hash of the current block, the seedHash, and the boundary condition to be met.
+	/// Returns the hash of the current block, the seedHash, the boundary condition to be met
+	/// and (unless legacy responses are requested) the number of the block.
 	fn work(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
 	/// Used for submitting a proof-of-work solution.
@@ -142,6 +146,7 @@ pub trait Eth: Sized + Send + Sync + 'static {
 		delegate.add_method("eth_hashrate", Eth::hashrate);
 		delegate.add_method("eth_coinbase", Eth::author);
 		delegate.add_method("eth_mining", Eth::is_mining);
+		delegate.add_method("eth_chainId", Eth::chain_id);
 		delegate.add_method("eth_gasPrice", Eth::gas_price);
 		delegate.add_method("eth_accounts", Eth::accounts);
 		delegate.add_method("eth_blockNumber", Eth::block_number);
@@ -21,24 +21,39 @@ use jsonrpc_core::*;
 /// Ethcore-specific rpc interface.
 pub trait Ethcore: Sized + Send + Sync + 'static {
 
-	/// Sets new minimal gas price for mined blocks.
+	/// Sets new minimal gas price for mined blocks. Returns the new value.
 	fn set_min_gas_price(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
-	/// Sets new gas floor target for mined blocks.
+	/// Sets new gas floor target for mined blocks. Returns the new value.
 	fn set_gas_floor_target(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
-	/// Sets new extra data for mined blocks.
+	/// Sets new extra data (at most 32 bytes) for mined blocks. Returns the new value.
 	fn set_extra_data(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
-	/// Sets new author for mined block.
+	/// Sets new author for mined block. Returns the new value.
 	fn set_author(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
-	/// Sets the limits for transaction queue.
+	/// Sets the limits for transaction queue. Returns the new value.
 	fn set_transactions_limit(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
+	/// Enables or disables preparing blocks for sealing. Returns the new value.
+	fn set_sealing_enabled(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Bans address, transactions sent from or to it are dropped from the queue.
+	fn ban_address(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Lifts ban of address.
+	fn unban_address(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns banned addresses.
+	fn banned_addresses(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
 	/// Returns current transactions limit.
 	fn transactions_limit(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
+	/// Returns `true` if preparing blocks for sealing is enabled.
+	fn sealing_enabled(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
 	/// Returns mining extra data.
 	fn extra_data(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
@@ -72,6 +87,31 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 	/// Returns default extra data
 	fn default_extra_data(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
+	/// Returns statistics of the state trie at given block.
+	/// Walks the whole state (or sampled fraction of it), so it's expensive.
+	fn state_stats(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns recorded submissions (with their origins) of transaction with given hash.
+	fn transaction_audit(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns numbers of submitted transactions per origin.
+	fn submission_stats(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns status of transaction bundle with given id (`null` if unknown).
+	fn bundle_status(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns uncle with given hash together with the block which included it.
+	/// Requires uncles index to be enabled.
+	fn uncle_info(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns origin, insertion block and state of queued transaction with given hash (`null` if not queued).
+	fn transaction_details(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Returns numbers of calls, errors and cumulative latency per RPC method.
+	fn rpc_stats(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
+
+	/// Resets RPC methods statistics.
+	fn reset_rpc_stats(&self, _: Params) -> Result<Value, Error> { rpc_unimplemented!() }
 
 	/// Should be used to convert object to io delegate.
 	fn to_delegate(self) -> IoDelegate<Self> {
@@ -81,11 +121,16 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 		delegate.add_method("ethcore_setExtraData", Ethcore::set_extra_data);
 		delegate.add_method("ethcore_setAuthor", Ethcore::set_author);
 		delegate.add_method("ethcore_setTransactionsLimit", Ethcore::set_transactions_limit);
+		delegate.add_method("ethcore_setSealingEnabled", Ethcore::set_sealing_enabled);
+		delegate.add_method("ethcore_banAddress", Ethcore::ban_address);
+		delegate.add_method("ethcore_unbanAddress", Ethcore::unban_address);
+		delegate.add_method("ethcore_resetRpcStats", Ethcore::reset_rpc_stats);
 
 		delegate.add_method("ethcore_extraData", Ethcore::extra_data);
 		delegate.add_method("ethcore_gasFloorTarget", Ethcore::gas_floor_target);
 		delegate.add_method("ethcore_minGasPrice", Ethcore::min_gas_price);
 		delegate.add_method("ethcore_transactionsLimit", Ethcore::transactions_limit);
+		delegate.add_method("ethcore_sealingEnabled", Ethcore::sealing_enabled);
 		delegate.add_method("ethcore_devLogs", Ethcore::dev_logs);
 		delegate.add_method("ethcore_devLogsLevels", Ethcore::dev_logs_levels);
 		delegate.add_method("ethcore_netChain", Ethcore::net_chain);
@@ -94,6 +139,14 @@ pub trait Ethcore: Sized + Send + Sync + 'static {
 		delegate.add_method("ethcore_rpcSettings", Ethcore::rpc_settings);
 		delegate.add_method("ethcore_nodeName", Ethcore::node_name);
 		delegate.add_method("ethcore_defaultExtraData", Ethcore::default_extra_data);
+		delegate.add_method("ethcore_stateStats", Ethcore::state_stats);
+		delegate.add_method("ethcore_transactionAudit", Ethcore::transaction_audit);
+		delegate.add_method("ethcore_submissionStats", Ethcore::submission_stats);
+		delegate.add_method("ethcore_bundleStatus", Ethcore::bundle_status);
+		delegate.add_method("ethcore_bannedAddresses", Ethcore::banned_addresses);
+		delegate.add_method("ethcore_rpcStats", Ethcore::rpc_stats);
+		delegate.add_method("ethcore_uncleInfo", Ethcore::uncle_info);
+		delegate.add_method("ethcore_transactionDetails", Ethcore::transaction_details);
 
 		delegate
 	}
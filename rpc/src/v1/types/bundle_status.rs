@@ -0,0 +1,62 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use util::hash::H256;
+use util::numbers::U256;
+use ethminer::BundleStatus as EthBundleStatus;
+
+/// Status of transaction bundle.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct BundleStatus {
+	/// One of `pending`, `mined` or `expired`.
+	pub status: String,
+	#[serde(rename="blockNumber")]
+	pub block_number: Option<U256>,
+	#[serde(rename="blockHash")]
+	pub block_hash: Option<H256>,
+}
+
+impl From<EthBundleStatus> for BundleStatus {
+	fn from(s: EthBundleStatus) -> BundleStatus {
+		let (status, number, hash) = match s {
+			EthBundleStatus::Pending => ("pending", None, None),
+			EthBundleStatus::Mined { number, hash } => ("mined", Some(U256::from(number)), Some(hash)),
+			EthBundleStatus::Expired => ("expired", None, None),
+		};
+		BundleStatus {
+			status: status.to_owned(),
+			block_number: number,
+			block_hash: hash,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use util::hash::H256;
+	use ethminer::BundleStatus as EthBundleStatus;
+	use super::BundleStatus;
+
+	#[test]
+	fn bundle_status_serialization() {
+		let pending = BundleStatus::from(EthBundleStatus::Pending);
+		let mined = BundleStatus::from(EthBundleStatus::Mined { number: 2, hash: H256::from(1) });
+
+		assert_eq!(serde_json::to_string(&pending).unwrap(), r#"{"status":"pending","blockNumber":null,"blockHash":null}"#);
+		assert_eq!(serde_json::to_string(&mined).unwrap(), r#"{"status":"mined","blockNumber":"0x02","blockHash":"0x0000000000000000000000000000000000000000000000000000000000000001"}"#);
+	}
+}
@@ -0,0 +1,51 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use util::numbers::U256;
+use stats::MethodStats;
+
+/// Statistics of calls of single RPC method.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RpcMethodStats {
+	pub calls: U256,
+	pub errors: U256,
+	/// Cumulative time spent handling the calls in microseconds.
+	pub latency: U256,
+}
+
+impl From<MethodStats> for RpcMethodStats {
+	fn from(s: MethodStats) -> RpcMethodStats {
+		RpcMethodStats {
+			calls: U256::from(s.calls),
+			errors: U256::from(s.errors),
+			latency: U256::from(s.latency),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use stats::MethodStats;
+	use super::*;
+
+	#[test]
+	fn test_serialize_rpc_method_stats() {
+		let s = RpcMethodStats::from(MethodStats { calls: 3, errors: 1, latency: 1000 });
+		let serialized = serde_json::to_string(&s).unwrap();
+		assert_eq!(serialized, r#"{"calls":"0x03","errors":"0x01","latency":"0x03e8"}"#);
+	}
+}
@@ -0,0 +1,100 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use util::hash::{H256, Address};
+use util::numbers::U256;
+use ethcore::client::{StateStats as EthStateStats, AccountStorage as EthAccountStorage};
+
+/// Number of storage slots of an account.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AccountStorage {
+	pub address: Address,
+	pub slots: U256,
+}
+
+impl From<EthAccountStorage> for AccountStorage {
+	fn from(s: EthAccountStorage) -> AccountStorage {
+		AccountStorage {
+			address: s.address,
+			slots: U256::from(s.slots),
+		}
+	}
+}
+
+/// State trie statistics.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StateStats {
+	#[serde(rename="stateRoot")]
+	pub state_root: H256,
+	pub accounts: U256,
+	pub contracts: U256,
+	#[serde(rename="accountsWithStorage")]
+	pub accounts_with_storage: U256,
+	#[serde(rename="storageSlots")]
+	pub storage_slots: U256,
+	/// Number of contracts keyed by upper bound of code size.
+	#[serde(rename="codeSizes")]
+	pub code_sizes: BTreeMap<String, U256>,
+	#[serde(rename="largestStorage")]
+	pub largest_storage: Vec<AccountStorage>,
+	#[serde(rename="unknownAddresses")]
+	pub unknown_addresses: U256,
+	pub sample: f64,
+	pub exact: bool,
+}
+
+impl From<EthStateStats> for StateStats {
+	fn from(s: EthStateStats) -> StateStats {
+		StateStats {
+			exact: s.is_exact(),
+			state_root: s.state_root,
+			accounts: U256::from(s.accounts),
+			contracts: U256::from(s.contracts),
+			accounts_with_storage: U256::from(s.accounts_with_storage),
+			storage_slots: U256::from(s.storage_slots),
+			code_sizes: s.code_sizes.into_iter().map(|(size, count)| (size.to_string(), U256::from(count))).collect(),
+			largest_storage: s.largest_storage.into_iter().map(AccountStorage::from).collect(),
+			unknown_addresses: U256::from(s.unknown_addresses),
+			sample: s.sample,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use util::hash::{H256, Address};
+	use ethcore::client::{StateStats as EthStateStats, AccountStorage as EthAccountStorage};
+	use super::*;
+
+	#[test]
+	fn test_serialize_state_stats() {
+		let s = StateStats::from(EthStateStats {
+			state_root: H256::default(),
+			accounts: 16,
+			contracts: 2,
+			accounts_with_storage: 1,
+			storage_slots: 3,
+			code_sizes: vec![(64, 1), (1024, 1)].into_iter().collect(),
+			largest_storage: vec![EthAccountStorage { address: Address::from(1), slots: 3 }],
+			unknown_addresses: 0,
+			sample: 0.5,
+		});
+		let serialized = serde_json::to_string(&s).unwrap();
+		assert_eq!(serialized, r#"{"stateRoot":"0x0000000000000000000000000000000000000000000000000000000000000000","accounts":"0x10","contracts":"0x02","accountsWithStorage":"0x01","storageSlots":"0x03","codeSizes":{"1024":"0x01","64":"0x01"},"largestStorage":[{"address":"0x0000000000000000000000000000000000000001","slots":"0x03"}],"unknownAddresses":"0x00","sample":0.5,"exact":false}"#);
+	}
+}
@@ -0,0 +1,82 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use util::hash::H256;
+use util::numbers::U256;
+use ethminer::{AuditEntry as EthAuditEntry, SubmissionStats as EthSubmissionStats};
+
+/// Recorded submission of transaction.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct AuditEntry {
+	pub hash: H256,
+	pub origin: String,
+	pub timestamp: U256,
+	pub error: Option<String>,
+}
+
+impl From<EthAuditEntry> for AuditEntry {
+	fn from(e: EthAuditEntry) -> AuditEntry {
+		AuditEntry {
+			hash: e.hash,
+			origin: e.origin.tag().to_owned(),
+			timestamp: U256::from(e.timestamp),
+			error: e.error,
+		}
+	}
+}
+
+/// Submissions of single origin.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SubmissionStats {
+	pub imported: U256,
+	pub rejected: U256,
+}
+
+impl From<EthSubmissionStats> for SubmissionStats {
+	fn from(s: EthSubmissionStats) -> SubmissionStats {
+		SubmissionStats {
+			imported: U256::from(s.imported),
+			rejected: U256::from(s.rejected),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use util::hash::H256;
+	use ethminer::{AuditEntry as EthAuditEntry, SubmissionOrigin, SubmissionStats as EthSubmissionStats};
+	use super::*;
+
+	#[test]
+	fn test_serialize_audit_entry() {
+		let e = AuditEntry::from(EthAuditEntry {
+			hash: H256::default(),
+			origin: SubmissionOrigin::new("dapp:wallet"),
+			timestamp: 16,
+			error: None,
+		});
+		let serialized = serde_json::to_string(&e).unwrap();
+		assert_eq!(serialized, r#"{"hash":"0x0000000000000000000000000000000000000000000000000000000000000000","origin":"dapp:wallet","timestamp":"0x10","error":null}"#);
+	}
+
+	#[test]
+	fn test_serialize_submission_stats() {
+		let s = SubmissionStats::from(EthSubmissionStats { imported: 2, rejected: 1 });
+		let serialized = serde_json::to_string(&s).unwrap();
+		assert_eq!(serialized, r#"{"imported":"0x02","rejected":"0x01"}"#);
+	}
+}
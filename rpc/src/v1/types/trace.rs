@@ -59,12 +59,32 @@ impl From<trace::Call> for Call {
 	}
 }
 
+#[derive(Debug, Serialize)]
+pub struct Suicide {
+	address: Address,
+	#[serde(rename="refundAddress")]
+	refund_address: Address,
+	balance: U256,
+}
+
+impl From<trace::Suicide> for Suicide {
+	fn from(s: trace::Suicide) -> Self {
+		Suicide {
+			address: s.address,
+			refund_address: s.refund_address,
+			balance: s.balance,
+		}
+	}
+}
+
 #[derive(Debug, Serialize)]
 pub enum Action {
 	#[serde(rename="call")]
 	Call(Call),
 	#[serde(rename="create")]
 	Create(Create),
+	#[serde(rename="suicide")]
+	Suicide(Suicide),
 }
 
 impl From<trace::Action> for Action {
@@ -72,6 +92,7 @@ impl From<trace::Action> for Action {
 		match c {
 			trace::Action::Call(call) => Action::Call(Call::from(call)),
 			trace::Action::Create(create) => Action::Create(Create::from(create)),
+			trace::Action::Suicide(suicide) => Action::Suicide(Suicide::from(suicide)),
 		}
 	}
 }
@@ -120,6 +141,8 @@ pub enum Res {
 	FailedCall,
 	#[serde(rename="failedCreate")]
 	FailedCreate,
+	#[serde(rename="none")]
+	None,
 }
 
 impl From<trace::Res> for Res {
@@ -129,6 +152,7 @@ impl From<trace::Res> for Res {
 			trace::Res::Create(create) => Res::Create(CreateResult::from(create)),
 			trace::Res::FailedCall => Res::FailedCall,
 			trace::Res::FailedCreate => Res::FailedCreate,
+			trace::Res::None => Res::None,
 		}
 	}
 }
@@ -210,10 +234,14 @@ mod tests {
 			value: U256::from(6),
 			gas: U256::from(7),
 			init: Bytes::new(vec![0x56, 0x78]),
+		}), Action::Suicide(Suicide {
+			address: Address::from(8),
+			refund_address: Address::from(9),
+			balance: U256::from(10),
 		})];
 
 		let serialized = serde_json::to_string(&actions).unwrap();
-		assert_eq!(serialized, r#"[{"call":{"from":"0x0000000000000000000000000000000000000001","to":"0x0000000000000000000000000000000000000002","value":"0x03","gas":"0x04","input":"0x1234"}},{"create":{"from":"0x0000000000000000000000000000000000000005","value":"0x06","gas":"0x07","init":"0x5678"}}]"#);
+		assert_eq!(serialized, r#"[{"call":{"from":"0x0000000000000000000000000000000000000001","to":"0x0000000000000000000000000000000000000002","value":"0x03","gas":"0x04","input":"0x1234"}},{"create":{"from":"0x0000000000000000000000000000000000000005","value":"0x06","gas":"0x07","init":"0x5678"}},{"suicide":{"address":"0x0000000000000000000000000000000000000008","refundAddress":"0x0000000000000000000000000000000000000009","balance":"0x0a"}}]"#);
 	}
 
 	#[test]
@@ -230,9 +258,10 @@ mod tests {
 			}),
 			Res::FailedCall,
 			Res::FailedCreate,
+			Res::None,
 		];
 
 		let serialized = serde_json::to_string(&results).unwrap();
-		assert_eq!(serialized, r#"[{"call":{"gasUsed":"0x01","output":"0x1234"}},{"create":{"gasUsed":"0x02","code":"0x4556","address":"0x0000000000000000000000000000000000000003"}},{"failedCall":[]},{"failedCreate":[]}]"#);
+		assert_eq!(serialized, r#"[{"call":{"gasUsed":"0x01","output":"0x1234"}},{"create":{"gasUsed":"0x02","code":"0x4556","address":"0x0000000000000000000000000000000000000003"}},{"failedCall":[]},{"failedCreate":[]},{"none":[]}]"#);
 	}
 }
@@ -0,0 +1,76 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use util::hash::H256;
+use util::numbers::U256;
+use ethminer::{TransactionDetails as EthTransactionDetails, TransactionOrigin, TransactionState};
+
+/// Details of transaction kept in the queue.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct TransactionDetails {
+	/// Transaction hash
+	pub hash: H256,
+	/// One of `local`, `external` or `retractedBlock`.
+	pub origin: String,
+	/// Number of the best block when transaction was inserted to the queue
+	#[serde(rename="insertionBlock")]
+	pub insertion_block: U256,
+	/// Either `pending` or `future`.
+	pub state: String,
+}
+
+impl From<EthTransactionDetails> for TransactionDetails {
+	fn from(d: EthTransactionDetails) -> TransactionDetails {
+		let origin = match d.origin {
+			TransactionOrigin::Local => "local",
+			TransactionOrigin::External => "external",
+			TransactionOrigin::RetractedBlock => "retractedBlock",
+		};
+		let state = match d.state {
+			TransactionState::Pending => "pending",
+			TransactionState::Future => "future",
+		};
+		TransactionDetails {
+			hash: d.hash,
+			origin: origin.to_owned(),
+			insertion_block: U256::from(d.insertion_block),
+			state: state.to_owned(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use util::hash::H256;
+	use ethminer::{TransactionDetails as EthTransactionDetails, TransactionOrigin, TransactionState};
+	use super::TransactionDetails;
+
+	#[test]
+	fn transaction_details_serialization() {
+		let details = TransactionDetails::from(EthTransactionDetails {
+			hash: H256::from(1),
+			origin: TransactionOrigin::RetractedBlock,
+			insertion_block: 5,
+			state: TransactionState::Future,
+		});
+
+		assert_eq!(
+			serde_json::to_string(&details).unwrap(),
+			r#"{"hash":"0x0000000000000000000000000000000000000000000000000000000000000001","origin":"retractedBlock","insertionBlock":"0x05","state":"future"}"#
+		);
+	}
+}
@@ -0,0 +1,54 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use util::hash::{Address, H256};
+use util::numbers::U256;
+use ethcore::uncle_info::UncleInfo as EthUncleInfo;
+
+/// Uncle and the block which included it.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct UncleInfo {
+	/// Uncle hash
+	pub hash: H256,
+	/// Uncle number
+	pub number: U256,
+	/// Uncle author
+	pub author: Address,
+	/// Hash of the block which included the uncle
+	#[serde(rename="blockHash")]
+	pub block_hash: H256,
+	/// Number of the block which included the uncle
+	#[serde(rename="blockNumber")]
+	pub block_number: U256,
+	/// Position of the uncle within the including block
+	pub index: U256,
+	/// Reward paid to the uncle author
+	pub reward: U256,
+}
+
+impl From<EthUncleInfo> for UncleInfo {
+	fn from(info: EthUncleInfo) -> UncleInfo {
+		UncleInfo {
+			hash: info.hash,
+			number: U256::from(info.number),
+			author: info.author,
+			block_hash: info.nephew_hash,
+			block_number: U256::from(info.nephew_number),
+			index: U256::from(info.index),
+			reward: info.reward,
+		}
+	}
+}
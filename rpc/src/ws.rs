@@ -0,0 +1,617 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! JSON-RPC over WebSockets.
+//!
+//! Minimal RFC 6455 server: every connection is handled by its own thread (up to `MAX_CONNECTIONS`),
+//! text messages are passed to the `CountingHandler` and responses are sent back as text frames.
+//! Binary messages and extensions are not supported.
+
+use std::{io, str, thread};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use crypto::digest::Digest;
+use crypto::sha1::Sha1;
+use rustc_serialize::base64::{ToBase64, STANDARD};
+use ethminer::SubmissionOrigin;
+use origin;
+use panics::{PanicGuard, PanicHandlerCallback};
+use stats::CountingHandler;
+
+/// GUID appended to the client key in the opening handshake.
+const WS_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Maximal size of the handshake request.
+const MAX_HANDSHAKE_SIZE: usize = 8 * 1024;
+/// Maximal size of a (possibly fragmented) message.
+const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+/// Maximal number of connections served at the same time.
+const MAX_CONNECTIONS: usize = 64;
+/// Time given to the client to complete the opening handshake, in seconds.
+const HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+/// Connections without any frame received for that long are closed, in seconds.
+const IDLE_TIMEOUT_SECS: u64 = 300;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Close status: message type not supported.
+const CLOSE_UNSUPPORTED: u16 = 1003;
+/// Close status: message too big.
+const CLOSE_TOO_BIG: u16 = 1009;
+
+/// Running WebSockets JSON-RPC server.
+pub struct WsServer {
+	addr: SocketAddr,
+	running: Arc<AtomicBool>,
+	panic_handler: PanicHandlerCallback,
+	thread: Option<JoinHandle<()>>,
+}
+
+impl WsServer {
+	/// Starts server asynchronously.
+	///
+	/// Connections with `Origin` header are accepted only if the origin is one of `origins`
+	/// or `origins` contain `*`. Empty list accepts local origins only.
+	pub fn start(addr: &SocketAddr, handler: Arc<CountingHandler>, origins: Vec<String>) -> io::Result<Self> {
+		let listener = try!(TcpListener::bind(addr));
+		let addr = try!(listener.local_addr());
+		let running = Arc::new(AtomicBool::new(true));
+		let panic_handler: PanicHandlerCallback = Arc::new(Mutex::new(None));
+		let origins = Arc::new(origins);
+		let connections = Arc::new(AtomicUsize::new(0));
+
+		let thread = {
+			let running = running.clone();
+			let panic_handler = panic_handler.clone();
+			try!(thread::Builder::new().name("ws-rpc".into()).spawn(move || {
+				for stream in listener.incoming() {
+					if !running.load(Ordering::SeqCst) {
+						break;
+					}
+					let mut stream = match stream {
+						Ok(stream) => stream,
+						Err(e) => {
+							warn!(target: "rpc", "WebSockets: unable to accept connection: {:?}", e);
+							continue;
+						},
+					};
+					let slot = match ConnectionSlot::acquire(&connections) {
+						Some(slot) => slot,
+						None => {
+							trace!(target: "rpc", "WebSockets: too many connections, rejecting");
+							let _ = stream.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+							continue;
+						},
+					};
+					let handler = handler.clone();
+					let origins = origins.clone();
+					let panic_handler = panic_handler.clone();
+					let spawned = thread::Builder::new().name("ws-rpc-connection".into()).spawn(move || {
+						let _slot = slot;
+						let _guard = PanicGuard(panic_handler);
+						if let Err(e) = Connection::new(stream, handler).run(&origins) {
+							trace!(target: "rpc", "WebSockets: connection closed: {:?}", e);
+						}
+					});
+					if let Err(e) = spawned {
+						warn!(target: "rpc", "WebSockets: unable to handle connection: {:?}", e);
+					}
+				}
+			}))
+		};
+
+		Ok(WsServer {
+			addr: addr,
+			running: running,
+			panic_handler: panic_handler,
+			thread: Some(thread),
+		})
+	}
+
+	/// Address the server is listening on.
+	pub fn addr(&self) -> &SocketAddr {
+		&self.addr
+	}
+
+	/// Set callback for panics.
+	pub fn set_panic_handler<F>(&self, handler: F) where F : Fn() -> () + Send + 'static {
+		*self.panic_handler.lock().unwrap() = Some(Box::new(handler));
+	}
+}
+
+impl Drop for WsServer {
+	fn drop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+		// Wake up the listener so it notices the server is stopped.
+		let _ = TcpStream::connect(&self.addr);
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Counts a connection being served, released when dropped.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+	/// Returns `None` if there are already `MAX_CONNECTIONS` connections served.
+	fn acquire(connections: &Arc<AtomicUsize>) -> Option<ConnectionSlot> {
+		if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+			connections.fetch_sub(1, Ordering::SeqCst);
+			return None;
+		}
+		Some(ConnectionSlot(connections.clone()))
+	}
+}
+
+impl Drop for ConnectionSlot {
+	fn drop(&mut self) {
+		self.0.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+/// Computes `Sec-WebSocket-Accept` value for given client key.
+fn accept_key(key: &str) -> String {
+	let mut hasher = Sha1::new();
+	hasher.input_str(&format!("{}{}", key, WS_GUID));
+	let mut digest = [0u8; 20];
+	hasher.result(&mut digest);
+	digest.to_base64(STANDARD)
+}
+
+/// Checks if origin points to the local machine, e.g. `http://localhost:8080`.
+fn is_local_origin(origin: &str) -> bool {
+	let authority = origin.splitn(2, "://").nth(1).unwrap_or(origin);
+	let authority = authority.split('/').next().unwrap_or("");
+	if authority == "[::1]" || authority.starts_with("[::1]:") {
+		return true;
+	}
+	match authority.split(':').next() {
+		Some("localhost") | Some("127.0.0.1") => true,
+		_ => false,
+	}
+}
+
+/// Checks if connection from given origin can be accepted.
+fn is_origin_allowed(origin: Option<&str>, origins: &[String]) -> bool {
+	match origin {
+		// Non-browser clients don't send `Origin`.
+		None => true,
+		// Web pages can open connections to any host, allow only local ones unless configured otherwise.
+		Some(origin) if origins.is_empty() => is_local_origin(origin),
+		Some(origin) => origins.iter().any(|allowed| allowed == "*" || allowed == origin),
+	}
+}
+
+/// Opening handshake request.
+#[derive(Debug, PartialEq)]
+struct Handshake {
+	key: String,
+	origin: Option<String>,
+}
+
+impl Handshake {
+	/// Parses HTTP upgrade request. Returns `None` if it's not a valid WebSocket handshake.
+	fn parse(request: &str) -> Option<Handshake> {
+		let mut lines = request.split("\r\n");
+		let is_get = lines.next().map_or(false, |line| line.starts_with("GET "));
+		if !is_get {
+			return None;
+		}
+
+		let mut key = None;
+		let mut origin = None;
+		let mut upgrade = false;
+		for line in lines.take_while(|line| !line.is_empty()) {
+			let mut parts = line.splitn(2, ':');
+			let name = parts.next().unwrap_or("").trim().to_lowercase();
+			let value = parts.next().unwrap_or("").trim();
+			match name.as_str() {
+				"upgrade" => upgrade = value.to_lowercase() == "websocket",
+				"sec-websocket-key" => key = Some(value.to_owned()),
+				"origin" => origin = Some(value.to_owned()),
+				_ => {},
+			}
+		}
+
+		match (upgrade, key) {
+			(true, Some(key)) => Some(Handshake {
+				key: key,
+				origin: origin,
+			}),
+			_ => None,
+		}
+	}
+}
+
+/// Single frame.
+struct Frame {
+	fin: bool,
+	opcode: u8,
+	payload: Vec<u8>,
+}
+
+fn read_frame<R: Read>(stream: &mut R) -> io::Result<Frame> {
+	let mut header = [0u8; 2];
+	try!(stream.read_exact(&mut header));
+	let fin = header[0] & 0x80 != 0;
+	let opcode = header[0] & 0x0f;
+	let masked = header[1] & 0x80 != 0;
+	let len = match header[1] & 0x7f {
+		126 => {
+			let mut len = [0u8; 2];
+			try!(stream.read_exact(&mut len));
+			(len[0] as usize) << 8 | len[1] as usize
+		},
+		127 => {
+			let mut len = [0u8; 8];
+			try!(stream.read_exact(&mut len));
+			len.iter().fold(0u64, |acc, b| acc << 8 | *b as u64) as usize
+		},
+		len => len as usize,
+	};
+
+	// Clients must mask all frames.
+	if !masked {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Unmasked client frame"));
+	}
+	if len > MAX_MESSAGE_SIZE {
+		return Err(io::Error::new(io::ErrorKind::InvalidData, "Frame too big"));
+	}
+
+	let mut mask = [0u8; 4];
+	try!(stream.read_exact(&mut mask));
+	// Declared length is not trusted, the buffer grows only as the data arrives.
+	let mut payload = Vec::new();
+	try!(stream.by_ref().take(len as u64).read_to_end(&mut payload));
+	if payload.len() != len {
+		return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated frame"));
+	}
+	for (i, byte) in payload.iter_mut().enumerate() {
+		*byte ^= mask[i % 4];
+	}
+
+	Ok(Frame {
+		fin: fin,
+		opcode: opcode,
+		payload: payload,
+	})
+}
+
+/// Encodes (unmasked) server frame.
+fn encode_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+	let mut frame = Vec::with_capacity(payload.len() + 10);
+	frame.push(0x80 | opcode);
+	match payload.len() {
+		len if len < 126 => frame.push(len as u8),
+		len if len <= 0xffff => {
+			frame.push(126);
+			frame.push((len >> 8) as u8);
+			frame.push(len as u8);
+		},
+		len => {
+			frame.push(127);
+			for i in (0..8).rev() {
+				frame.push(((len as u64) >> (i * 8)) as u8);
+			}
+		},
+	}
+	frame.extend_from_slice(payload);
+	frame
+}
+
+fn close_payload(status: u16) -> [u8; 2] {
+	[(status >> 8) as u8, status as u8]
+}
+
+struct Connection {
+	stream: TcpStream,
+	handler: Arc<CountingHandler>,
+	origin: SubmissionOrigin,
+}
+
+impl Connection {
+	fn new(stream: TcpStream, handler: Arc<CountingHandler>) -> Self {
+		Connection {
+			stream: stream,
+			handler: handler,
+			origin: SubmissionOrigin::anonymous(),
+		}
+	}
+
+	fn run(&mut self, origins: &[String]) -> io::Result<()> {
+		try!(self.stream.set_read_timeout(Some(Duration::from_secs(HANDSHAKE_TIMEOUT_SECS))));
+		let request = try!(self.read_handshake());
+		let handshake = match Handshake::parse(&request) {
+			Some(handshake) => handshake,
+			None => {
+				try!(self.stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"));
+				return Ok(());
+			},
+		};
+
+		if !is_origin_allowed(handshake.origin.as_ref().map(|o| o.as_str()), origins) {
+			try!(self.stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"));
+			return Ok(());
+		}
+
+		self.origin = handshake.origin.map_or_else(SubmissionOrigin::anonymous, |origin| SubmissionOrigin::new(format!("ws:{}", origin)));
+		try!(self.stream.write_all(format!(
+			"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+			accept_key(&handshake.key)
+		).as_bytes()));
+
+		try!(self.stream.set_read_timeout(Some(Duration::from_secs(IDLE_TIMEOUT_SECS))));
+		self.serve()
+	}
+
+	fn read_handshake(&mut self) -> io::Result<String> {
+		let mut request = Vec::new();
+		let mut byte = [0u8; 1];
+		while !request.ends_with(b"\r\n\r\n") {
+			if request.len() >= MAX_HANDSHAKE_SIZE {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "Handshake too big"));
+			}
+			try!(self.stream.read_exact(&mut byte));
+			request.push(byte[0]);
+		}
+		String::from_utf8(request).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	fn serve(&mut self) -> io::Result<()> {
+		let mut message: Option<(u8, Vec<u8>)> = None;
+		loop {
+			let frame = try!(read_frame(&mut self.stream));
+			match frame.opcode {
+				OPCODE_PING => try!(self.send(OPCODE_PONG, &frame.payload)),
+				OPCODE_PONG => {},
+				OPCODE_CLOSE => return self.send(OPCODE_CLOSE, &frame.payload),
+				OPCODE_TEXT | OPCODE_BINARY | OPCODE_CONTINUATION => {
+					let (opcode, mut payload) = match (frame.opcode, message.take()) {
+						(OPCODE_CONTINUATION, Some((opcode, payload))) => (opcode, payload),
+						(OPCODE_CONTINUATION, None) => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unexpected continuation frame")),
+						(opcode, _) => (opcode, Vec::new()),
+					};
+					if payload.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+						return self.send(OPCODE_CLOSE, &close_payload(CLOSE_TOO_BIG));
+					}
+					payload.extend_from_slice(&frame.payload);
+					if !frame.fin {
+						message = Some((opcode, payload));
+						continue;
+					}
+					if opcode == OPCODE_BINARY {
+						return self.send(OPCODE_CLOSE, &close_payload(CLOSE_UNSUPPORTED));
+					}
+					try!(self.handle_message(payload));
+				},
+				_ => return Err(io::Error::new(io::ErrorKind::InvalidData, "Unknown opcode")),
+			}
+		}
+	}
+
+	fn handle_message(&mut self, payload: Vec<u8>) -> io::Result<()> {
+		let request = try!(String::from_utf8(payload).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)));
+		let handler = self.handler.clone();
+		let response = origin::with_origin(self.origin.clone(), move || handler.handle_request(&request));
+		match response {
+			Some(response) => self.send(OPCODE_TEXT, response.as_bytes()),
+			None => Ok(()),
+		}
+	}
+
+	fn send(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+		self.stream.write_all(&encode_frame(opcode, payload))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::{Read, Write};
+	use std::net::{SocketAddr, TcpStream};
+	use std::str::FromStr;
+	use std::sync::Arc;
+	use std::sync::atomic::AtomicUsize;
+	use std::time::Duration;
+	use jsonrpc_core::IoHandler;
+	use util::version;
+	use v1::{Web3, Web3Client};
+	use stats::{CountingHandler, RpcStats};
+	use RpcServer;
+	use super::{WsServer, ConnectionSlot, accept_key, is_origin_allowed, encode_frame, read_frame, Handshake, OPCODE_TEXT, MAX_CONNECTIONS};
+
+	fn start(origins: Vec<String>) -> WsServer {
+		let io = IoHandler::new();
+		io.add_delegate(Web3Client::new().to_delegate());
+		let handler = CountingHandler::new(Arc::new(io), Arc::new(RpcStats::default()));
+		WsServer::start(&SocketAddr::from_str("127.0.0.1:0").unwrap(), Arc::new(handler), origins).unwrap()
+	}
+
+	fn connect(server: &WsServer, origin: Option<&str>) -> (TcpStream, String) {
+		let mut stream = TcpStream::connect(server.addr()).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		let origin = origin.map_or_else(String::new, |origin| format!("Origin: {}\r\n", origin));
+		stream.write_all(format!(
+			"GET / HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n{}\r\n",
+			server.addr(), origin
+		).as_bytes()).unwrap();
+
+		let mut response = Vec::new();
+		let mut byte = [0u8; 1];
+		while !response.ends_with(b"\r\n\r\n") && stream.read(&mut byte).unwrap() == 1 {
+			response.push(byte[0]);
+		}
+		(stream, String::from_utf8(response).unwrap())
+	}
+
+	/// Encodes masked client frame.
+	fn client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+		let mask = [1u8, 2, 3, 4];
+		let mut frame = encode_frame(opcode, payload);
+		let header_len = frame.len() - payload.len();
+		frame[1] |= 0x80;
+		let masked = payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]).collect::<Vec<_>>();
+		frame.truncate(header_len);
+		frame.extend_from_slice(&mask);
+		frame.extend_from_slice(&masked);
+		frame
+	}
+
+	/// Reads unmasked server frame.
+	fn server_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+		let mut header = [0u8; 2];
+		stream.read_exact(&mut header).unwrap();
+		assert_eq!(header[1] & 0x80, 0);
+		let len = (header[1] & 0x7f) as usize;
+		assert!(len < 126);
+		let mut payload = vec![0u8; len];
+		stream.read_exact(&mut payload).unwrap();
+		(header[0] & 0x0f, payload)
+	}
+
+	#[test]
+	fn should_compute_accept_key() {
+		// Example from RFC 6455
+		assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+	}
+
+	#[test]
+	fn should_allow_only_local_origins_by_default() {
+		assert!(is_origin_allowed(None, &[]));
+		assert!(is_origin_allowed(Some("http://localhost:8080"), &[]));
+		assert!(is_origin_allowed(Some("http://127.0.0.1"), &[]));
+		assert!(is_origin_allowed(Some("http://[::1]:8080"), &[]));
+		assert!(!is_origin_allowed(Some("http://evil.com"), &[]));
+		assert!(!is_origin_allowed(Some("http://localhost.evil.com"), &[]));
+		assert!(!is_origin_allowed(Some("null"), &[]));
+		assert!(is_origin_allowed(Some("http://evil.com"), &["*".to_owned()]));
+	}
+
+	#[test]
+	fn should_limit_number_of_connections() {
+		// given
+		let connections = Arc::new(AtomicUsize::new(0));
+		let mut slots = (0..MAX_CONNECTIONS).map(|_| ConnectionSlot::acquire(&connections).unwrap()).collect::<Vec<_>>();
+
+		// when
+		let rejected = ConnectionSlot::acquire(&connections);
+		slots.pop();
+		let accepted = ConnectionSlot::acquire(&connections);
+
+		// then
+		assert!(rejected.is_none());
+		assert!(accepted.is_some());
+	}
+
+	#[test]
+	fn should_parse_handshake() {
+		let request = "GET / HTTP/1.1\r\nHost: localhost\r\nupgrade: WebSocket\r\nSec-WebSocket-Key: abc\r\nOrigin: http://parity.io\r\n\r\n";
+		assert_eq!(Handshake::parse(request), Some(Handshake { key: "abc".to_owned(), origin: Some("http://parity.io".to_owned()) }));
+		assert_eq!(Handshake::parse("GET / HTTP/1.1\r\nSec-WebSocket-Key: abc\r\n\r\n"), None);
+		assert_eq!(Handshake::parse("POST / HTTP/1.1\r\nUpgrade: websocket\r\nSec-WebSocket-Key: abc\r\n\r\n"), None);
+	}
+
+	#[test]
+	fn should_decode_masked_frames() {
+		// given
+		let frame = client_frame(OPCODE_TEXT, b"Hello");
+
+		// when
+		let decoded = read_frame(&mut &frame[..]).unwrap();
+
+		// then
+		assert!(decoded.fin);
+		assert_eq!(decoded.opcode, OPCODE_TEXT);
+		assert_eq!(decoded.payload, b"Hello".to_vec());
+		assert!(read_frame(&mut &encode_frame(OPCODE_TEXT, b"Hello")[..]).is_err());
+	}
+
+	#[test]
+	fn should_reject_truncated_frames() {
+		// given
+		// text frame declaring 16MB payload, but carrying 5 bytes only
+		let mut frame = vec![0x80 | OPCODE_TEXT, 0x80 | 127, 0, 0, 0, 0, 1, 0, 0, 0];
+		frame.extend_from_slice(&[1, 2, 3, 4]);
+		frame.extend_from_slice(b"Hello");
+
+		// when
+		let decoded = read_frame(&mut &frame[..]);
+
+		// then
+		assert!(decoded.is_err());
+	}
+
+	#[test]
+	fn should_handle_rpc_calls() {
+		// given
+		let server = start(vec!["http://parity.io".to_owned()]);
+		let (mut stream, handshake) = connect(&server, Some("http://parity.io"));
+		let request = r#"{"jsonrpc": "2.0", "method": "web3_clientVersion", "params": [], "id": 1}"#;
+
+		// when
+		stream.write_all(&client_frame(OPCODE_TEXT, request.as_bytes())).unwrap();
+		let (opcode, payload) = server_frame(&mut stream);
+
+		// then
+		let v = version().to_owned().replace("Parity/", "Parity//");
+		let response = r#"{"jsonrpc":"2.0","result":"VER","id":1}"#.to_owned().replace("VER", v.as_ref());
+		assert!(handshake.starts_with("HTTP/1.1 101"), "Unexpected response: {}", handshake);
+		assert!(handshake.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+		assert_eq!(opcode, OPCODE_TEXT);
+		assert_eq!(String::from_utf8(payload).unwrap(), response);
+	}
+
+	#[test]
+	fn should_reject_not_allowed_origins() {
+		// given
+		let server = start(vec!["http://parity.io".to_owned()]);
+
+		// when
+		let (_, rejected) = connect(&server, Some("http://evil.com"));
+		let (_, no_origin) = connect(&server, None);
+
+		// then
+		assert!(rejected.starts_with("HTTP/1.1 403"), "Unexpected response: {}", rejected);
+		assert!(no_origin.starts_with("HTTP/1.1 101"), "Unexpected response: {}", no_origin);
+	}
+
+	#[test]
+	fn should_reject_restricted_methods_in_read_only_mode() {
+		// given
+		let rpc = RpcServer::new();
+		rpc.restrict_to_read_only();
+		rpc.add_delegate(Web3Client::new().to_delegate());
+		let server = rpc.start_ws(&SocketAddr::from_str("127.0.0.1:0").unwrap(), vec![]).unwrap();
+		let (mut stream, _) = connect(&server, None);
+		let request = r#"{"jsonrpc": "2.0", "method": "eth_sendTransaction", "params": [], "id": 1}"#;
+
+		// when
+		stream.write_all(&client_frame(OPCODE_TEXT, request.as_bytes())).unwrap();
+		let (_, payload) = server_frame(&mut stream);
+
+		// then
+		let rejected = r#"{"jsonrpc":"2.0","error":{"code":-32011,"message":"Method is not available on read-only node.","data":null},"id":1}"#;
+		assert_eq!(String::from_utf8(payload).unwrap(), rejected);
+	}
+}
@@ -14,15 +14,60 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::{cmp, mem};
 use util::*;
 use ethcore::header::{ Header as BlockHeader};
+use ethcore::views::HeaderView;
 
-known_heap_size!(0, HeaderId, SyncBlock);
+known_heap_size!(0, HeaderId);
 
-/// Block data with optional body.
+/// Block data with optional body and receipts.
 struct SyncBlock {
 	header: Bytes,
 	body: Option<Bytes>,
+	receipts: Option<Bytes>,
+}
+
+impl SyncBlock {
+	/// Size of the block data in bytes.
+	fn data_size(&self) -> usize {
+		self.header.len()
+			+ self.body.as_ref().map_or(0, |b| b.len())
+			+ self.receipts.as_ref().map_or(0, |r| r.len())
+	}
+}
+
+impl HeapSizeOf for SyncBlock {
+	fn heap_size_of_children(&self) -> usize {
+		self.header.heap_size_of_children()
+			+ self.body.heap_size_of_children()
+			+ self.receipts.heap_size_of_children()
+	}
+}
+
+/// Downloaded block with receipts, if these were requested.
+pub struct BlockAndReceipts {
+	/// Block RLP.
+	pub block: Bytes,
+	/// Block receipts RLP list.
+	pub receipts: Option<Bytes>,
+}
+
+/// Download progress of `BlockCollection`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BlockCollectionStatus {
+	/// Number of block headers received in current sync round.
+	pub headers_received: usize,
+	/// Number of block bodies received in current sync round.
+	pub bodies_received: usize,
+	/// Number of block bodies received in current sync round which were invalid, duplicate or not matching any header.
+	pub useless_bodies: usize,
+	/// Number of complete blocks ready to be drained.
+	pub ready: usize,
+	/// Number of subchain heads.
+	pub heads: usize,
+	/// Approximate heap size of downloaded block data in bytes.
+	pub heap_size: usize,
 }
 
 /// Used to identify header by transactions and uncles hashes
@@ -45,12 +90,32 @@ pub struct BlockCollection {
 	parents: HashMap<H256, H256>,
 	/// Used to map body to header.
 	header_ids: HashMap<HeaderId, H256>,
+	/// Used to map receipts root to headers.
+	receipt_ids: HashMap<H256, Vec<H256>>,
 	/// First block in `blocks`.
 	head: Option<H256>,
-	/// Set of block header hashes being downloaded
-	downloading_headers: HashSet<H256>,
-	/// Set of block bodies being downloaded identified by block hash.
-	downloading_bodies: HashSet<H256>,
+	/// Block header hashes being downloaded mapped to the peer they were requested from.
+	downloading_headers: HashMap<H256, PeerId>,
+	/// Block bodies being downloaded identified by block hash mapped to the peer they were requested from.
+	downloading_bodies: HashMap<H256, PeerId>,
+	/// Block receipts being downloaded identified by block hash mapped to the peer they were requested from.
+	downloading_receipts: HashMap<H256, PeerId>,
+	/// Whether receipts should be downloaded along with block bodies.
+	need_receipts: bool,
+	/// Maximal size of downloaded block data after which no more headers are requested.
+	memory_limit: Option<usize>,
+	/// Number of block headers received in current sync round.
+	headers_received: usize,
+	/// Number of block bodies received in current sync round.
+	bodies_received: usize,
+	/// Number of invalid, duplicate or unmatched block bodies received in current sync round.
+	useless_bodies: usize,
+	/// Number of complete blocks following `head`.
+	ready: usize,
+	/// Last of the complete blocks following `head`.
+	ready_tail: Option<H256>,
+	/// Size of block data held in `blocks`.
+	data_size: usize,
 }
 
 impl BlockCollection {
@@ -59,52 +124,180 @@ impl BlockCollection {
 		BlockCollection {
 			blocks: HashMap::new(),
 			header_ids: HashMap::new(),
+			receipt_ids: HashMap::new(),
 			heads: Vec::new(),
 			parents: HashMap::new(),
 			head: None,
-			downloading_headers: HashSet::new(),
-			downloading_bodies: HashSet::new(),
+			downloading_headers: HashMap::new(),
+			downloading_bodies: HashMap::new(),
+			downloading_receipts: HashMap::new(),
+			need_receipts: false,
+			memory_limit: None,
+			headers_received: 0,
+			bodies_received: 0,
+			useless_bodies: 0,
+			ready: 0,
+			ready_tail: None,
+			data_size: 0,
 		}
 	}
 
+	/// Set maximal size of downloaded block data. Once it's reached no more headers are
+	/// requested until downloaded blocks are drained.
+	pub fn set_memory_limit(&mut self, limit: usize) {
+		self.memory_limit = Some(limit);
+	}
+
 	/// Clear everything.
 	pub fn clear(&mut self) {
 		self.blocks.clear();
 		self.parents.clear();
 		self.header_ids.clear();
+		self.receipt_ids.clear();
 		self.heads.clear();
 		self.head = None;
 		self.downloading_headers.clear();
 		self.downloading_bodies.clear();
+		self.downloading_receipts.clear();
+		self.headers_received = 0;
+		self.bodies_received = 0;
+		self.useless_bodies = 0;
+		self.ready = 0;
+		self.ready_tail = None;
+		self.data_size = 0;
 	}
 
-	/// Reset collection for a new sync round with given subchain block hashes.
-	pub fn reset_to(&mut self, hashes: Vec<H256>) {
+	/// Clear download state but keep fully downloaded blocks connected to the current `head`
+	/// so that they don't have to be downloaded again when a new sync round starts.
+	pub fn clear_soft(&mut self) {
+		let mut blocks = HashMap::new();
+		let mut parents = HashMap::new();
+		let mut head = self.head;
+		while let Some(h) = head {
+			head = None;
+			if let Some(child) = self.parents.get(&h).cloned() {
+				match self.blocks.remove(&child) {
+					Some(block) if self.is_complete(&block) => {
+						blocks.insert(child.clone(), block);
+						parents.insert(h, child.clone());
+						head = Some(child);
+					},
+					_ => (),
+				}
+			}
+		}
+		let head = self.head;
 		self.clear();
+		if !blocks.is_empty() {
+			trace!(target: "sync", "Preserved {} downloaded blocks, head: {:?}", blocks.len(), head);
+			self.data_size = blocks.values().fold(0, |size, block| size + block.data_size());
+			self.blocks = blocks;
+			self.parents = parents;
+			self.head = head;
+			self.update_ready();
+		}
+	}
+
+	/// Reset collection for a new sync round with given subchain block hashes.
+	/// Receipts are downloaded along with block bodies if `need_receipts` is set.
+	/// Blocks preserved by `clear_soft` are kept only if they are connected to the first subchain head
+	/// and receipts requirement hasn't changed.
+	pub fn reset_to(&mut self, hashes: Vec<H256>, need_receipts: bool) {
+		self.clear_soft();
+		let connected = match (self.head, hashes.first()) {
+			(Some(head), Some(first)) => head == *first && self.need_receipts == need_receipts,
+			_ => false,
+		};
+		self.need_receipts = need_receipts;
+		if connected {
+			trace!(target: "sync", "Resuming download with {} preserved blocks", self.blocks.len());
+		} else {
+			self.clear();
+		}
 		self.heads = hashes;
+		self.update_heads();
 	}
 
 	/// Insert a set of headers into collection and advance subchain head pointers.
-	pub fn insert_headers(&mut self, headers: Vec<Bytes>) {
+	/// Returns the number of headers which were not known before.
+	pub fn insert_headers(&mut self, headers: Vec<Bytes>) -> usize {
+		let mut inserted = 0;
 		for h in headers.into_iter() {
-			if let Err(e) =  self.insert_header(h) {
-				trace!(target: "sync", "Ignored invalid header: {:?}", e);
+			match self.insert_header(h) {
+				Ok(true) => inserted += 1,
+				Ok(false) => (),
+				Err(e) => trace!(target: "sync", "Ignored invalid header: {:?}", e),
 			}
 		}
+		self.headers_received += inserted;
 		self.update_heads();
+		self.update_ready();
+		inserted
 	}
 
 	/// Insert a collection of block bodies for previously downloaded headers.
-	pub fn insert_bodies(&mut self, bodies: Vec<Bytes>) {
+	/// Returns hashes of blocks the bodies were matched with. Invalid bodies, bodies
+	/// not matching any downloaded header and duplicates are ignored and counted as useless.
+	pub fn insert_bodies(&mut self, bodies: Vec<Bytes>) -> Vec<H256> {
+		let mut inserted = Vec::new();
+		let mut useless = 0;
 		for b in bodies.into_iter() {
-			if let Err(e) =  self.insert_body(b) {
-				trace!(target: "sync", "Ignored invalid body: {:?}", e);
+			match self.insert_body(b) {
+				Ok(Some(hash)) => inserted.push(hash),
+				Ok(None) => useless += 1,
+				Err(e) => {
+					trace!(target: "sync", "Ignored invalid body: {:?}", e);
+					useless += 1;
+				},
 			}
 		}
+		self.bodies_received += inserted.len();
+		self.useless_bodies += useless;
+		self.update_ready();
+		inserted
 	}
 
-	/// Returns a set of block hashes that require a body download. The returned set is marked as being downloaded.
-	pub fn needed_bodies(&mut self, count: usize, _ignore_downloading: bool) -> Vec<H256> {
+	/// Insert a collection of block receipts for previously downloaded headers.
+	/// Returns hashes of blocks the receipts were matched with.
+	pub fn insert_receipts(&mut self, receipts: Vec<Bytes>) -> Vec<H256> {
+		if !self.need_receipts {
+			return Vec::new();
+		}
+		let mut inserted = Vec::new();
+		for r in receipts.into_iter() {
+			match self.insert_receipt(r) {
+				Ok(hashes) => inserted.extend(hashes),
+				Err(e) => trace!(target: "sync", "Ignored invalid receipt: {:?}", e),
+			}
+		}
+		self.update_ready();
+		inserted
+	}
+
+	/// Returns a set of block hashes that require a receipts download. The returned set is marked as being downloaded by `peer_id`.
+	pub fn needed_receipts(&mut self, count: usize, _ignore_downloading: bool, peer_id: PeerId) -> Vec<H256> {
+		if self.head.is_none() || !self.need_receipts {
+			return Vec::new();
+		}
+		let mut needed_receipts: Vec<H256> = Vec::new();
+		let mut head = self.head;
+		while head.is_some() && needed_receipts.len() < count {
+			head = self.parents.get(&head.unwrap()).cloned();
+			if let Some(head) = head {
+				match self.blocks.get(&head) {
+					Some(block) if block.receipts.is_none() && !self.downloading_receipts.contains_key(&head) => {
+						needed_receipts.push(head.clone());
+					}
+					_ => (),
+				}
+			}
+		}
+		self.downloading_receipts.extend(needed_receipts.iter().map(|h| (h.clone(), peer_id)));
+		needed_receipts
+	}
+
+	/// Returns a set of block hashes that require a body download. The returned set is marked as being downloaded by `peer_id`.
+	pub fn needed_bodies(&mut self, count: usize, _ignore_downloading: bool, peer_id: PeerId) -> Vec<H256> {
 		if self.head.is_none() {
 			return Vec::new();
 		}
@@ -114,25 +307,36 @@ impl BlockCollection {
 			head = self.parents.get(&head.unwrap()).cloned();
 			if let Some(head) = head {
 				match self.blocks.get(&head) {
-					Some(block) if block.body.is_none() && !self.downloading_bodies.contains(&head) => {
+					Some(block) if block.body.is_none() && !self.downloading_bodies.contains_key(&head) => {
 						needed_bodies.push(head.clone());
 					}
 					_ => (),
 				}
 			}
 		}
-		self.downloading_bodies.extend(needed_bodies.iter());
+		self.downloading_bodies.extend(needed_bodies.iter().map(|h| (h.clone(), peer_id)));
 		needed_bodies
 	}
 
-	/// Returns a set of block hashes that require a header download. The returned set is marked as being downloaded.
-	pub fn needed_headers(&mut self, count: usize, ignore_downloading: bool) -> Option<(H256, usize)> {
+	/// Returns a set of block hashes that require a header download. The returned set is marked as being downloaded by `peer_id`.
+	/// Once the collection is full only the first subchain is requested and only if there are no blocks ready
+	/// to be drained, since the head can't advance without it. Bodies and receipts are still requested in that case,
+	/// since they are required to drain the blocks which are already there.
+	pub fn needed_headers(&mut self, count: usize, ignore_downloading: bool, peer_id: PeerId) -> Option<(H256, usize)> {
+		let heads = match self.is_full() {
+			false => &self.heads[..],
+			true if self.ready == 0 => &self.heads[..cmp::min(1, self.heads.len())],
+			true => {
+				trace!(target: "sync", "Block collection is full ({} bytes), not requesting more headers", self.data_size);
+				return None;
+			},
+		};
 		// find subchain to download
 		let mut download = None;
 		{
-			for h in &self.heads {
-				if ignore_downloading || !self.downloading_headers.contains(h) {
-					self.downloading_headers.insert(h.clone());
+			for h in heads {
+				if ignore_downloading || !self.downloading_headers.contains_key(h) {
+					self.downloading_headers.insert(h.clone(), peer_id);
 					download = Some(h.clone());
 					break;
 				}
@@ -151,22 +355,47 @@ impl BlockCollection {
 		self.downloading_bodies.remove(hash);
 	}
 
-	/// Get a valid chain of blocks ordered in descending order and ready for importing into blockchain.
-	pub fn drain(&mut self) -> Vec<Bytes> {
+	/// Unmark block receipts as being downloaded.
+	pub fn clear_receipt_download(&mut self, hash: &H256) {
+		self.downloading_receipts.remove(hash);
+	}
+
+	/// Unmark all headers and block bodies being downloaded by `peer_id`, so that they can be requested from other peers.
+	pub fn fail_from_peer(&mut self, peer_id: PeerId) {
+		let headers: Vec<_> = self.downloading_headers.iter().filter(|&(_, p)| *p == peer_id).map(|(h, _)| h.clone()).collect();
+		for h in &headers {
+			self.downloading_headers.remove(h);
+		}
+		let bodies: Vec<_> = self.downloading_bodies.iter().filter(|&(_, p)| *p == peer_id).map(|(h, _)| h.clone()).collect();
+		for h in &bodies {
+			self.downloading_bodies.remove(h);
+		}
+		let receipts: Vec<_> = self.downloading_receipts.iter().filter(|&(_, p)| *p == peer_id).map(|(h, _)| h.clone()).collect();
+		for h in &receipts {
+			self.downloading_receipts.remove(h);
+		}
+		trace!(target: "sync", "Peer {} failed; reassigning {} headers, {} bodies and {} receipts", peer_id, headers.len(), bodies.len(), receipts.len());
+	}
+
+	/// Get a valid chain of at most `max` blocks ordered in descending order and ready for importing into blockchain.
+	/// Blocks are returned along with their receipts if these were requested in `reset_to`.
+	/// Subsequent call continues with the first block which wasn't returned.
+	pub fn drain(&mut self, max: usize) -> Vec<BlockAndReceipts> {
 		if self.blocks.is_empty() || self.head.is_none() {
 			return Vec::new();
 		}
 
+		let old_head = self.head;
 		let mut drained = Vec::new();
 		let mut hashes = Vec::new();
 		{
 			let mut blocks = Vec::new();
 			let mut head = self.head;
-			while head.is_some() {
+			while head.is_some() && blocks.len() < max {
 				head = self.parents.get(&head.unwrap()).cloned();
 				if let Some(head) = head {
 					match self.blocks.get(&head) {
-						Some(block) if block.body.is_some() => {
+						Some(block) if self.is_complete(block) => {
 							blocks.push(block);
 							hashes.push(head);
 							self.head = Some(head);
@@ -182,11 +411,35 @@ impl BlockCollection {
 				let body = Rlp::new(block.body.as_ref().unwrap()); // incomplete blocks are filtered out in the loop above
 				block_rlp.append_raw(body.at(0).as_raw(), 1);
 				block_rlp.append_raw(body.at(1).as_raw(), 1);
-				drained.push(block_rlp.out());
+				drained.push(BlockAndReceipts {
+					block: block_rlp.out(),
+					receipts: block.receipts.clone(),
+				});
+			}
+		}
+		// links to drained blocks are not needed anymore, only the new head has to stay linked to its child
+		if let Some((_, linked)) = hashes.split_last() {
+			for h in old_head.iter().chain(linked.iter()) {
+				self.parents.remove(h);
 			}
 		}
 		for h in hashes {
-			self.blocks.remove(&h);
+			if let Some(block) = self.blocks.remove(&h) {
+				self.data_size -= block.data_size();
+				let header = HeaderView::new(&block.header);
+				let header_id = HeaderId {
+					transactions_root: header.transactions_root(),
+					uncles: header.uncles_hash(),
+				};
+				if self.header_ids.get(&header_id) == Some(&h) {
+					self.header_ids.remove(&header_id);
+				}
+			}
+		}
+		// drained blocks are always the complete ones following `head`
+		self.ready = self.ready.saturating_sub(drained.len());
+		if self.ready == 0 {
+			self.ready_tail = None;
 		}
 		trace!("Drained {} blocks, new head :{:?}", drained.len(), self.head);
 		drained
@@ -203,18 +456,79 @@ impl BlockCollection {
 		self.blocks.contains_key(hash)
 	}
 
-	/// Return heap size.
+	/// Return approximate heap size. Downloaded block data is tracked as blocks are inserted and drained,
+	/// size of the indexes is estimated from their capacity so it doesn't require walking them.
 	pub fn heap_size(&self) -> usize {
-		//TODO: other collections
-		self.blocks.heap_size_of_children()
+		let hash = mem::size_of::<H256>();
+		let downloading = mem::size_of::<PeerId>() + hash;
+		self.data_size
+			+ self.heads.capacity() * hash
+			+ self.blocks.capacity() * (hash + mem::size_of::<SyncBlock>())
+			+ self.parents.capacity() * 2 * hash
+			+ self.header_ids.capacity() * (mem::size_of::<HeaderId>() + hash)
+			// every receipts root is shared by at least one block
+			+ self.receipt_ids.capacity() * (hash + mem::size_of::<Vec<H256>>()) + self.receipt_ids.len() * hash
+			+ (self.downloading_headers.capacity() + self.downloading_bodies.capacity() + self.downloading_receipts.capacity()) * downloading
+	}
+
+	/// Check if the collection reached its memory limit.
+	pub fn is_full(&self) -> bool {
+		self.memory_limit.map_or(false, |limit| self.data_size >= limit)
 	}
 
 	/// Check if given block hash is marked as being downloaded.
 	pub fn is_downloading(&self, hash: &H256) -> bool {
-		self.downloading_headers.contains(hash) || self.downloading_bodies.contains(hash)
+		self.downloading_headers.contains_key(hash) || self.downloading_bodies.contains_key(hash) || self.downloading_receipts.contains_key(hash)
 	}
 
-	fn insert_body(&mut self, b: Bytes) -> Result<(), UtilError> {
+	/// Returns download progress.
+	pub fn status(&self) -> BlockCollectionStatus {
+		BlockCollectionStatus {
+			headers_received: self.headers_received,
+			bodies_received: self.bodies_received,
+			useless_bodies: self.useless_bodies,
+			ready: self.ready,
+			heads: self.heads.len(),
+			heap_size: self.data_size,
+		}
+	}
+
+	/// Check if the block has all the data required for importing.
+	fn is_complete(&self, block: &SyncBlock) -> bool {
+		block.body.is_some() && (!self.need_receipts || block.receipts.is_some())
+	}
+
+	fn insert_receipt(&mut self, r: Bytes) -> Result<Vec<H256>, UtilError> {
+		let receipts = UntrustedRlp::new(&r);
+		if !receipts.is_list() {
+			return Err(From::from(DecoderError::RlpExpectedToBeList));
+		}
+		let receipts_root = ordered_trie_root(receipts.iter().map(|r| r.as_raw().to_vec()).collect()); //TODO: get rid of vectors here
+		match self.receipt_ids.remove(&receipts_root) {
+			Some(hashes) => {
+				let mut inserted = Vec::new();
+				for h in hashes {
+					self.downloading_receipts.remove(&h);
+					match self.blocks.get_mut(&h) {
+						Some(ref mut block) => {
+							trace!(target: "sync", "Got receipt {}", h);
+							block.receipts = Some(receipts.as_raw().to_vec());
+							self.data_size += receipts.as_raw().len();
+							inserted.push(h);
+						},
+						None => warn!("Got receipt with no header {}", h),
+					}
+				}
+				Ok(inserted)
+			}
+			None => {
+				trace!(target: "sync", "Ignored unknown/stale block receipt");
+				Ok(Vec::new())
+			}
+		}
+	}
+
+	fn insert_body(&mut self, b: Bytes) -> Result<Option<H256>, UtilError> {
 		let body = UntrustedRlp::new(&b);
 		let tx = try!(body.at(0));
 		let tx_root = ordered_trie_root(tx.iter().map(|r| r.as_raw().to_vec()).collect()); //TODO: get rid of vectors here
@@ -223,28 +537,40 @@ impl BlockCollection {
 			transactions_root: tx_root,
 			uncles: uncles
 		};
-		match self.header_ids.get(&header_id).cloned() {
-			Some(h) => {
+		// Ids of blocks with bodies are kept until the blocks are drained, so that duplicates are recognized.
+		let h = match self.header_ids.get(&header_id).cloned() {
+			Some(h) => h,
+			None => {
+				trace!(target: "sync", "Ignored unknown/stale block body");
+				return Ok(None);
+			}
+		};
+		match self.blocks.get_mut(&h) {
+			Some(ref block) if block.body.is_some() => {
+				trace!(target: "sync", "Ignored duplicate body {}", h);
+				Ok(None)
+			},
+			Some(ref mut block) => {
+				trace!(target: "sync", "Got body {}", h);
+				self.downloading_bodies.remove(&h);
+				block.body = Some(body.as_raw().to_vec());
+				self.data_size += body.as_raw().len();
+				Ok(Some(h))
+			},
+			None => {
+				warn!("Got body with no header {}", h);
 				self.header_ids.remove(&header_id);
 				self.downloading_bodies.remove(&h);
-				match self.blocks.get_mut(&h) {
-					Some(ref mut block) => {
-						trace!(target: "sync", "Got body {}", h);
-						block.body = Some(body.as_raw().to_vec());
-					},
-					None => warn!("Got body with no header {}", h)
-				}
+				Ok(None)
 			}
-			None => trace!(target: "sync", "Ignored unknown/stale block body")
-		};
-		Ok(())
+		}
 	}
 
-	fn insert_header(&mut self, header: Bytes) -> Result<H256, UtilError> {
+	fn insert_header(&mut self, header: Bytes) -> Result<bool, UtilError> {
 		let info: BlockHeader = try!(UntrustedRlp::new(&header).as_val());
 		let hash = info.hash();
 		if self.blocks.contains_key(&hash) {
-			return Ok(hash);
+			return Ok(false);
 		}
 		match self.head {
 			None if hash == self.heads[0] => {
@@ -257,6 +583,7 @@ impl BlockCollection {
 		let mut block = SyncBlock {
 			header: header,
 			body: None,
+			receipts: None,
 		};
 		let header_id = HeaderId {
 			transactions_root: info.transactions_root,
@@ -272,10 +599,33 @@ impl BlockCollection {
 		else {
 			self.header_ids.insert(header_id, hash.clone());
 		}
+		if self.need_receipts {
+			if info.receipts_root == rlp::SHA3_NULL_RLP {
+				// no receipts, just mark as downloaded
+				block.receipts = Some(rlp::EMPTY_LIST_RLP.to_vec());
+			} else {
+				self.receipt_ids.entry(info.receipts_root).or_insert_with(Vec::new).push(hash.clone());
+			}
+		}
 
 		self.parents.insert(info.parent_hash.clone(), hash.clone());
-		self.blocks.insert(hash.clone(), block);
-		Ok(hash)
+		self.data_size += block.data_size();
+		self.blocks.insert(hash, block);
+		Ok(true)
+	}
+
+	// update the number of complete blocks following `head`
+	fn update_ready(&mut self) {
+		let mut tail = self.ready_tail.or(self.head);
+		while let Some(next) = tail.and_then(|t| self.parents.get(&t).cloned()) {
+			let complete = self.blocks.get(&next).map_or(false, |block| self.is_complete(block));
+			if !complete {
+				break;
+			}
+			self.ready += 1;
+			self.ready_tail = Some(next);
+			tail = Some(next);
+		}
 	}
 
 	// update subchain headers
@@ -306,10 +656,11 @@ impl BlockCollection {
 
 #[cfg(test)]
 mod test {
-	use super::BlockCollection;
+	use super::{BlockCollection, BlockCollectionStatus};
 	use ethcore::client::{TestBlockChainClient, EachBlockWith, BlockID, BlockChainClient};
 	use ethcore::views::HeaderView;
-	use ethcore::header::BlockNumber;
+	use ethcore::header::{BlockNumber, Header};
+	use ethcore::receipt::Receipt;
 	use util::*;
 
 	fn is_empty(bc: &BlockCollection) -> bool {
@@ -317,9 +668,15 @@ mod test {
 		bc.blocks.is_empty() &&
 		bc.parents.is_empty() &&
 		bc.header_ids.is_empty() &&
+		bc.receipt_ids.is_empty() &&
 		bc.head.is_none() &&
 		bc.downloading_headers.is_empty() &&
-		bc.downloading_bodies.is_empty()
+		bc.downloading_bodies.is_empty() &&
+		bc.downloading_receipts.is_empty()
+	}
+
+	fn drain(bc: &mut BlockCollection) -> Vec<Bytes> {
+		bc.drain(usize::max_value()).into_iter().map(|b| b.block).collect()
 	}
 
 	#[test]
@@ -329,7 +686,7 @@ mod test {
 		let client = TestBlockChainClient::new();
 		client.add_blocks(100, EachBlockWith::Nothing);
 		let hashes = (0 .. 100).map(|i| (&client as &BlockChainClient).block_hash(BlockID::Number(i)).unwrap()).collect();
-		bc.reset_to(hashes);
+		bc.reset_to(hashes, false);
 		assert!(!is_empty(&bc));
 		bc.clear();
 		assert!(is_empty(&bc));
@@ -346,19 +703,19 @@ mod test {
 		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
 		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
 		let heads: Vec<_> = hashes.iter().enumerate().filter_map(|(i, h)| if i % 20 == 0 { Some(h.clone()) } else { None }).collect();
-		bc.reset_to(heads);
+		bc.reset_to(heads, false);
 		assert!(!bc.is_empty());
 		assert_eq!(hashes[0], bc.heads[0]);
-		assert!(bc.needed_bodies(1, false).is_empty());
+		assert!(bc.needed_bodies(1, false, 0).is_empty());
 		assert!(!bc.contains(&hashes[0]));
 		assert!(!bc.is_downloading(&hashes[0]));
 
-		let (h, n) = bc.needed_headers(6, false).unwrap();
+		let (h, n) = bc.needed_headers(6, false, 0).unwrap();
 		assert!(bc.is_downloading(&hashes[0]));
 		assert_eq!(hashes[0], h);
 		assert_eq!(n, 6);
 		assert_eq!(bc.downloading_headers.len(), 1);
-		assert!(bc.drain().is_empty());
+		assert!(bc.drain(usize::max_value()).is_empty());
 
 		bc.insert_headers(headers[0..6].to_vec());
 		assert_eq!(hashes[5], bc.heads[0]);
@@ -369,25 +726,74 @@ mod test {
 		assert!(!bc.is_downloading(&hashes[0]));
 		assert!(bc.contains(&hashes[0]));
 
-		assert_eq!(&bc.drain()[..], &blocks[0..6]);
+		assert_eq!(&drain(&mut bc)[..], &blocks[0..6]);
 		assert!(!bc.contains(&hashes[0]));
 		assert_eq!(hashes[5], bc.head.unwrap());
 
-		let (h, _) = bc.needed_headers(6, false).unwrap();
+		let (h, _) = bc.needed_headers(6, false, 0).unwrap();
 		assert_eq!(hashes[5], h);
-		let (h, _) = bc.needed_headers(6, false).unwrap();
+		let (h, _) = bc.needed_headers(6, false, 0).unwrap();
 		assert_eq!(hashes[20], h);
 		bc.insert_headers(headers[10..16].to_vec());
-		assert!(bc.drain().is_empty());
+		assert!(bc.drain(usize::max_value()).is_empty());
 		bc.insert_headers(headers[5..10].to_vec());
-		assert_eq!(&bc.drain()[..], &blocks[6..16]);
+		assert_eq!(&drain(&mut bc)[..], &blocks[6..16]);
 		assert_eq!(hashes[15], bc.heads[0]);
 
 		bc.insert_headers(headers[16..].to_vec());
-		bc.drain();
+		bc.drain(usize::max_value());
 		assert!(bc.is_empty());
 	}
 
+	fn prepare_soft_restart() -> (BlockCollection, Vec<Bytes>, Vec<H256>) {
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 200;
+		client.add_blocks(nblocks, EachBlockWith::Nothing);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		let heads: Vec<_> = hashes.iter().enumerate().filter_map(|(i, h)| if i % 20 == 0 { Some(h.clone()) } else { None }).collect();
+		bc.reset_to(heads, false);
+		bc.insert_headers(headers[0..6].to_vec());
+		assert_eq!(&drain(&mut bc)[..], &blocks[0..6]);
+		bc.insert_headers(headers[6..16].to_vec());
+		// incomplete subchain, not connected to the head
+		bc.insert_headers(headers[30..35].to_vec());
+		(bc, blocks, hashes)
+	}
+
+	#[test]
+	fn soft_restart_preserves_downloaded_blocks() {
+		let (mut bc, blocks, hashes) = prepare_soft_restart();
+
+		bc.clear_soft();
+		assert!(bc.heads.is_empty());
+		assert!(bc.downloading_headers.is_empty());
+		assert_eq!(bc.head, Some(hashes[5]));
+		assert!(bc.contains(&hashes[6]));
+		assert!(bc.contains(&hashes[15]));
+		assert!(!bc.contains(&hashes[30]));
+
+		bc.reset_to(vec![hashes[5], hashes[40]], false);
+		assert_eq!(hashes[15], bc.heads[0]);
+		let (h, _) = bc.needed_headers(6, false, 0).unwrap();
+		assert_eq!(hashes[15], h);
+		assert_eq!(&drain(&mut bc)[..], &blocks[6..16]);
+	}
+
+	#[test]
+	fn soft_restart_discards_blocks_if_target_changed() {
+		let (mut bc, _, hashes) = prepare_soft_restart();
+
+		bc.clear_soft();
+		bc.reset_to(vec![hashes[3]], false);
+		assert!(bc.head.is_none());
+		assert!(!bc.contains(&hashes[6]));
+		assert_eq!(bc.heads, vec![hashes[3]]);
+		assert!(bc.drain(usize::max_value()).is_empty());
+	}
+
 	#[test]
 	fn insert_headers_with_gap() {
 		let mut bc = BlockCollection::new();
@@ -399,7 +805,7 @@ mod test {
 		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
 		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
 		let heads: Vec<_> = hashes.iter().enumerate().filter_map(|(i, h)| if i % 20 == 0 { Some(h.clone()) } else { None }).collect();
-		bc.reset_to(heads);
+		bc.reset_to(heads, false);
 
 		bc.insert_headers(headers[2..22].to_vec());
 		assert_eq!(hashes[0], bc.heads[0]);
@@ -409,5 +815,363 @@ mod test {
 		assert!(bc.head.is_some());
 		assert_eq!(hashes[21], bc.heads[0]);
 	}
-}
 
+	#[test]
+	fn failed_peer_downloads_are_needed_again() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 10;
+		client.add_blocks(nblocks, EachBlockWith::Transaction);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		let bodies: Vec<_> = blocks.iter().map(|b| {
+			let rlp = Rlp::new(b);
+			let mut body = RlpStream::new_list(2);
+			body.append_raw(rlp.at(1).as_raw(), 1);
+			body.append_raw(rlp.at(2).as_raw(), 1);
+			body.out()
+		}).collect();
+		bc.reset_to(vec![hashes[0]], false);
+
+		// when
+		let (h, _) = bc.needed_headers(nblocks, false, 1).unwrap();
+		bc.fail_from_peer(1);
+
+		// then
+		assert_eq!(h, hashes[0]);
+		assert!(!bc.is_downloading(&hashes[0]));
+		let (h, _) = bc.needed_headers(nblocks, false, 2).unwrap();
+		assert_eq!(h, hashes[0]);
+		assert_eq!(bc.insert_headers(headers.clone()), nblocks);
+		assert_eq!(bc.insert_headers(headers.clone()), 0);
+
+		// when
+		let first = bc.needed_bodies(3, false, 1);
+		let second = bc.needed_bodies(3, false, 2);
+		bc.fail_from_peer(1);
+
+		// then
+		assert_eq!(first.len(), 3);
+		assert_eq!(second.len(), 3);
+		assert!(first.iter().all(|h| !bc.is_downloading(h)));
+		assert!(second.iter().all(|h| bc.is_downloading(h)));
+		let again = bc.needed_bodies(3, false, 3);
+		assert_eq!(again, first);
+
+		let first_bodies: Vec<_> = first.iter().map(|h| bodies[hashes.iter().position(|x| x == h).unwrap()].clone()).collect();
+		assert_eq!(bc.insert_bodies(first_bodies.clone()), first);
+		assert!(bc.insert_bodies(first_bodies).is_empty());
+		assert!(first.iter().all(|h| !bc.is_downloading(h)));
+	}
+
+	#[test]
+	fn insert_bodies_reports_matched_bodies_only() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let other_client = TestBlockChainClient::new();
+		let nblocks = 5;
+		client.add_blocks(nblocks, EachBlockWith::Transaction);
+		other_client.add_blocks(nblocks, EachBlockWith::Transaction);
+		let body = |c: &TestBlockChainClient, i: usize| {
+			let block = (c as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap();
+			let rlp = Rlp::new(&block);
+			let mut body = RlpStream::new_list(2);
+			body.append_raw(rlp.at(1).as_raw(), 1);
+			body.append_raw(rlp.at(2).as_raw(), 1);
+			body.out()
+		};
+		let headers: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block_header(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		bc.reset_to(vec![hashes[0]], false);
+		bc.insert_headers(headers);
+
+		// when
+		let inserted = bc.insert_bodies(vec![
+			body(&client, 2),
+			body(&other_client, 3),
+			vec![0x01, 0x02],
+			body(&client, 2),
+			body(&client, 4),
+		]);
+
+		// then
+		assert_eq!(inserted, vec![hashes[2], hashes[4]]);
+		assert_eq!(bc.blocks[&hashes[2]].body, Some(body(&client, 2)));
+		assert!(bc.blocks[&hashes[3]].body.is_none());
+		assert_eq!(bc.status().useless_bodies, 3);
+		assert!(bc.insert_bodies(vec![body(&client, 2), body(&client, 4)]).is_empty());
+		assert_eq!(bc.status().useless_bodies, 5);
+		assert_eq!(bc.status().bodies_received, 2);
+	}
+
+	#[test]
+	fn insert_receipts_and_drain_complete_blocks() {
+		// given
+		let mut bc = BlockCollection::new();
+		let nblocks = 6;
+		let receipts: Vec<_> = (0 .. nblocks).map(|i| encode(&Receipt::new(H256::from(i as u64), U256::from(i as u64), vec![])).to_vec()).collect();
+		let receipt_lists: Vec<_> = receipts.iter().map(|r| {
+			let mut list = RlpStream::new_list(1);
+			list.append_raw(r, 1);
+			list.out()
+		}).collect();
+		let mut parent = H256::zero();
+		let headers: Vec<_> = (0 .. nblocks).map(|i| {
+			let mut header = Header::new();
+			header.set_parent_hash(parent.clone());
+			header.set_number(i as BlockNumber);
+			// block 3 has no transactions, so no receipts
+			if i != 3 {
+				header.receipts_root = ordered_trie_root(vec![receipts[i].clone()]);
+			}
+			parent = header.hash();
+			encode(&header).to_vec()
+		}).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		bc.reset_to(vec![hashes[0]], true);
+		bc.insert_headers(headers.clone());
+
+		// when
+		let needed = bc.needed_receipts(nblocks, false, 0);
+
+		// then
+		assert_eq!(needed, vec![hashes[0], hashes[1], hashes[2], hashes[4], hashes[5]]);
+		assert!(bc.needed_bodies(nblocks, false, 0).is_empty());
+		assert!(bc.needed_receipts(nblocks, false, 0).is_empty());
+		assert!(bc.drain(usize::max_value()).is_empty());
+
+		// when
+		let inserted = bc.insert_receipts(vec![receipt_lists[2].clone(), receipt_lists[0].clone()]);
+		let drained = bc.drain(usize::max_value());
+
+		// then
+		assert_eq!(inserted, vec![hashes[2], hashes[0]]);
+		assert_eq!(drained.len(), 1);
+		assert_eq!(Rlp::new(&drained[0].block).at(0).as_raw(), &headers[0][..]);
+		assert_eq!(drained[0].receipts, Some(receipt_lists[0].clone()));
+
+		// when
+		let inserted = bc.insert_receipts(vec![receipt_lists[4].clone(), vec![0x01, 0x02], receipt_lists[1].clone(), receipt_lists[4].clone()]);
+		let drained = bc.drain(usize::max_value());
+
+		// then
+		assert_eq!(inserted, vec![hashes[4], hashes[1]]);
+		assert_eq!(drained.len(), 4);
+		assert_eq!(drained[0].receipts, Some(receipt_lists[1].clone()));
+		assert_eq!(drained[1].receipts, Some(receipt_lists[2].clone()));
+		assert_eq!(drained[2].receipts, Some(rlp::EMPTY_LIST_RLP.to_vec()));
+		assert_eq!(drained[3].receipts, Some(receipt_lists[4].clone()));
+		assert!(bc.is_downloading(&hashes[5]));
+		assert_eq!(bc.head, Some(hashes[4]));
+	}
+
+	#[test]
+	fn stops_requesting_headers_when_full() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 200;
+		client.add_blocks(nblocks, EachBlockWith::Nothing);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		let heads: Vec<_> = hashes.iter().enumerate().filter_map(|(i, h)| if i % 20 == 0 { Some(h.clone()) } else { None }).collect();
+		bc.reset_to(heads, false);
+		bc.set_memory_limit(headers[0..100].iter().fold(0, |size, h| size + h.len()));
+		assert!(!bc.is_full());
+
+		// when
+		let (h, _) = bc.needed_headers(100, false, 0).unwrap();
+		bc.insert_headers(headers[0..100].to_vec());
+		bc.clear_header_download(&h);
+
+		// then
+		assert!(bc.is_full());
+		assert!(bc.needed_headers(100, false, 0).is_none());
+		assert!(!bc.is_downloading(&hashes[99]));
+
+		// when
+		assert_eq!(&drain(&mut bc)[..], &blocks[0..100]);
+
+		// then
+		assert!(!bc.is_full());
+		assert_eq!(bc.parents.len(), 0);
+		let (h, _) = bc.needed_headers(100, false, 0).unwrap();
+		assert_eq!(h, hashes[99]);
+	}
+
+	#[test]
+	fn heap_size_includes_indexes() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 100;
+		client.add_blocks(nblocks, EachBlockWith::Nothing);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		bc.reset_to(vec![hashes[0].clone()], false);
+
+		// when
+		bc.insert_headers(headers[1..].to_vec());
+
+		// then
+		let data_size = bc.status().heap_size;
+		assert!(bc.heap_size() >= data_size + bc.parents.len() * 2 * 32 + bc.header_ids.len() * 32);
+	}
+
+	#[test]
+	fn requests_head_subchain_when_full_and_nothing_is_ready() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 200;
+		client.add_blocks(nblocks, EachBlockWith::Nothing);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		let heads: Vec<_> = hashes.iter().enumerate().filter_map(|(i, h)| if i % 20 == 0 { Some(h.clone()) } else { None }).collect();
+		bc.reset_to(heads, false);
+		bc.set_memory_limit(headers[20..100].iter().fold(0, |size, h| size + h.len()));
+
+		// when
+		bc.insert_headers(headers[20..100].to_vec());
+
+		// then
+		assert!(bc.is_full());
+		assert_eq!(bc.status().ready, 0);
+		let (h, _) = bc.needed_headers(100, false, 0).unwrap();
+		assert_eq!(h, hashes[0]);
+		assert!(bc.needed_headers(100, false, 1).is_none());
+	}
+
+	#[test]
+	fn drain_in_chunks() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 100;
+		client.add_blocks(nblocks, EachBlockWith::Transaction);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		let bodies: Vec<_> = blocks.iter().map(|b| {
+			let rlp = Rlp::new(b);
+			let mut body = RlpStream::new_list(2);
+			body.append_raw(rlp.at(1).as_raw(), 1);
+			body.append_raw(rlp.at(2).as_raw(), 1);
+			body.out()
+		}).collect();
+		bc.reset_to(vec![hashes[0]], false);
+		bc.insert_headers(headers.clone());
+		bc.insert_bodies(bodies[0..50].to_vec());
+
+		// when
+		let mut drained = Vec::new();
+		loop {
+			let chunk = bc.drain(7);
+			assert!(chunk.len() <= 7);
+			if chunk.is_empty() {
+				break;
+			}
+			drained.extend(chunk.into_iter().map(|b| b.block));
+		}
+
+		// then
+		assert_eq!(&drained[..], &blocks[0..50]);
+		assert_eq!(bc.head, Some(hashes[49]));
+
+		// when
+		bc.insert_bodies(bodies[50..].to_vec());
+		loop {
+			let chunk = bc.drain(7);
+			assert!(chunk.len() <= 7);
+			if chunk.is_empty() {
+				break;
+			}
+			drained.extend(chunk.into_iter().map(|b| b.block));
+		}
+
+		// then
+		assert_eq!(drained, blocks);
+		assert_eq!(bc.head, Some(hashes[99]));
+		assert!(bc.blocks.is_empty());
+	}
+
+	#[test]
+	fn status_tracks_download_progress() {
+		// given
+		let mut bc = BlockCollection::new();
+		let client = TestBlockChainClient::new();
+		let nblocks = 10;
+		client.add_blocks(nblocks, EachBlockWith::Transaction);
+		let blocks: Vec<_> = (0 .. nblocks).map(|i| (&client as &BlockChainClient).block(BlockID::Number(i as BlockNumber)).unwrap()).collect();
+		let headers: Vec<_> = blocks.iter().map(|b| Rlp::new(b).at(0).as_raw().to_vec()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+		let bodies: Vec<_> = blocks.iter().map(|b| {
+			let rlp = Rlp::new(b);
+			let mut body = RlpStream::new_list(2);
+			body.append_raw(rlp.at(1).as_raw(), 1);
+			body.append_raw(rlp.at(2).as_raw(), 1);
+			body.out()
+		}).collect();
+		let status = |headers_received, bodies_received, ready, heads, heap_size| BlockCollectionStatus {
+			headers_received: headers_received,
+			bodies_received: bodies_received,
+			useless_bodies: 0,
+			ready: ready,
+			heads: heads,
+			heap_size: heap_size,
+		};
+		bc.reset_to(vec![hashes[0]], false);
+		assert_eq!(bc.status(), status(0, 0, 0, 1, 0));
+
+		// when
+		bc.insert_headers(headers.clone());
+		let headers_size: usize = headers.iter().fold(bodies[0].len(), |size, h| size + h.len());
+
+		// then
+		// genesis has an empty body, so it's complete right away
+		assert_eq!(bc.status(), status(10, 0, 1, 1, headers_size));
+
+		// when
+		bc.insert_bodies(bodies[2..5].to_vec());
+		let bodies_size: usize = bodies[2..5].iter().fold(0, |size, b| size + b.len());
+
+		// then
+		assert_eq!(bc.status(), status(10, 3, 1, 1, headers_size + bodies_size));
+
+		// when
+		bc.insert_bodies(bodies[1..2].to_vec());
+
+		// then
+		assert_eq!(bc.status().ready, 5);
+		assert_eq!(bc.status().bodies_received, 4);
+
+		// when
+		assert_eq!(bc.drain(2).len(), 2);
+
+		// then
+		assert_eq!(bc.status().ready, 3);
+		assert_eq!(bc.status().heap_size, headers[2..].iter().chain(bodies[2..5].iter()).fold(0, |size, b| size + b.len()));
+
+		// when
+		assert_eq!(bc.drain(usize::max_value()).len(), 3);
+		bc.insert_bodies(bodies[5..].to_vec());
+
+		// then
+		assert_eq!(bc.status().ready, 5);
+		assert_eq!(bc.status().bodies_received, 9);
+		assert_eq!(bc.drain(usize::max_value()).len(), 5);
+		assert_eq!(bc.status(), status(10, 9, 0, 1, 0));
+
+		// when
+		bc.clear();
+
+		// then
+		assert_eq!(bc.status(), status(0, 0, 0, 0, 0));
+	}
+}
@@ -90,34 +90,42 @@
 ///
 
 use util::*;
+use util::panics::PanicHandler;
 use std::mem::{replace};
 use ethcore::views::{HeaderView, BlockView};
 use ethcore::header::{BlockNumber, Header as BlockHeader};
 use ethcore::client::{BlockChainClient, BlockStatus, BlockID, BlockChainInfo};
 use ethcore::error::*;
-use ethcore::transaction::SignedTransaction;
 use ethcore::block::Block;
-use ethminer::{Miner, MinerService, AccountDetails};
+use ethcore::receipt::Receipt;
+use ethminer::{Miner, MinerService, AccountDetails, TransactionImportError};
 use io::SyncIo;
 use time;
 use super::SyncConfig;
-use blocks::BlockCollection;
+use blocks::{BlockCollection, BlockCollectionStatus};
+use clock_skew::{ClockSkew, SkewChange};
 
 known_heap_size!(0, PeerInfo);
 
 type PacketDecodeError = DecoderError;
 
 const PROTOCOL_VERSION: u8 = 63u8;
+const PROTOCOL_VERSION_WITHOUT_RECEIPTS: u8 = 62u8;
 const MAX_BODIES_TO_SEND: usize = 256;
+const MAX_BODIES_SIZE_TO_SEND: usize = 2 * 1024 * 1024;
 const MAX_HEADERS_TO_SEND: usize = 512;
 const MAX_NODE_DATA_TO_SEND: usize = 1024;
 const MAX_RECEIPTS_TO_SEND: usize = 1024;
 const MAX_RECEIPTS_HEADERS_TO_SEND: usize = 256;
 const MAX_HEADERS_TO_REQUEST: usize = 256;
 const MAX_BODIES_TO_REQUEST: usize = 64;
+const MAX_RECEIPTS_TO_REQUEST: usize = 128;
 const MIN_PEERS_PROPAGATION: usize = 4;
 const MAX_PEERS_PROPAGATION: usize = 128;
 const MAX_PEER_LAG_PROPAGATION: BlockNumber = 20;
+const MAX_USELESS_BODIES_RESPONSES: usize = 3;
+const MAX_BLOCKS_TO_IMPORT: usize = 512;
+const MAX_DOWNLOADED_RECEIPTS: usize = 1024;
 
 const STATUS_PACKET: u8 = 0x00;
 const NEW_BLOCK_HASHES_PACKET: u8 = 0x01;
@@ -175,6 +183,12 @@ pub struct SyncStatus {
 	pub num_active_peers: usize,
 	/// Heap memory used in bytes
 	pub mem_used: usize,
+	/// Estimated skew of local clock in seconds (positive if local clock is behind peers)
+	pub clock_skew: Option<i64>,
+	/// Estimated clock skew exceeds configured threshold
+	pub clock_skewed: bool,
+	/// Progress of the current blocks download
+	pub download: BlockCollectionStatus,
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
@@ -184,6 +198,8 @@ enum PeerAsking {
 	BlockHeaders,
 	BlockBodies,
 	Heads,
+	Receipts,
+	BlockReceipts,
 }
 
 #[derive(Clone)]
@@ -209,6 +225,8 @@ struct PeerInfo {
 	asking_hash: Option<H256>,
 	/// Request timestamp
 	ask_time: f64,
+	/// Number of consecutive block bodies responses without any useful body
+	useless_bodies: usize,
 }
 
 /// Blockchain sync handler.
@@ -242,6 +260,18 @@ pub struct ChainSync {
 	network_id: U256,
 	/// Miner
 	miner: Arc<Miner>,
+	/// Local clock skew estimator
+	clock_skew: ClockSkew,
+	/// Pause sealing when local clock is skewed
+	pause_sealing_on_clock_skew: bool,
+	/// Serve receipts to peers and advertise eth/63
+	serve_receipts: bool,
+	/// Receipts downloaded from peers by block hash
+	downloaded_receipts: HashMap<H256, Vec<Receipt>>,
+	/// Download receipts along with block bodies
+	download_receipts: bool,
+	/// Handler used to raise alerts
+	panic_handler: Arc<PanicHandler>,
 }
 
 type RlpResponseResult = Result<Option<(PacketId, RlpStream)>, PacketDecodeError>;
@@ -265,16 +295,28 @@ impl ChainSync {
 			_max_download_ahead_blocks: max(MAX_HEADERS_TO_REQUEST, config.max_download_ahead_blocks),
 			network_id: config.network_id,
 			miner: miner,
+			clock_skew: ClockSkew::new(config.clock_skew_threshold),
+			pause_sealing_on_clock_skew: config.pause_sealing_on_clock_skew,
+			serve_receipts: config.serve_receipts,
+			downloaded_receipts: HashMap::new(),
+			download_receipts: config.download_receipts,
+			panic_handler: PanicHandler::new_in_arc(),
 		};
+		sync.blocks.set_memory_limit(config.max_download_memory);
 		sync.reset();
 		sync
 	}
 
+	/// Handler notified about alerts raised by sync.
+	pub fn panic_handler(&self) -> Arc<PanicHandler> {
+		self.panic_handler.clone()
+	}
+
 	/// @returns Synchonization status
 	pub fn status(&self) -> SyncStatus {
 		SyncStatus {
 			state: self.state.clone(),
-			protocol_version: 63,
+			protocol_version: self.protocol_version(),
 			network_id: self.network_id,
 			start_block_number: self.starting_block,
 			last_imported_block_number: Some(self.last_imported_block),
@@ -283,15 +325,29 @@ impl ChainSync {
 			blocks_total: match self.highest_block { Some(x) if x > self.starting_block => x - self.starting_block, _ => 0 },
 			num_peers: self.peers.len(),
 			num_active_peers: self.peers.values().filter(|p| p.asking != PeerAsking::Nothing).count(),
-			mem_used:
-				//  TODO: https://github.com/servo/heapsize/pull/50
-				//+ self.downloading_bodies.heap_size_of_children()
-				//+ self.downloading_headers.heap_size_of_children()
-				self.blocks.heap_size()
-				+ self.peers.heap_size_of_children(),
+			mem_used: self.blocks.heap_size() + self.peers.heap_size_of_children(),
+			clock_skew: self.clock_skew.estimate(),
+			clock_skewed: self.clock_skew.is_skewed(),
+			download: self.blocks.status(),
 		}
 	}
 
+	/// Version of eth protocol advertised to peers.
+	fn protocol_version(&self) -> u8 {
+		if self.serve_receipts { PROTOCOL_VERSION } else { PROTOCOL_VERSION_WITHOUT_RECEIPTS }
+	}
+
+	/// Takes receipts downloaded so far (see `request_receipts`), keyed by block hash.
+	pub fn take_receipts(&mut self) -> HashMap<H256, Vec<Receipt>> {
+		replace(&mut self.downloaded_receipts, HashMap::new())
+	}
+
+	/// Returns best block hash and total difficulty (if known) of the given peer.
+	/// `None` if the peer has no eth session.
+	pub fn peer_chain_info(&self, peer_id: PeerId) -> Option<(H256, Option<U256>)> {
+		self.peers.get(&peer_id).map(|peer| (peer.latest_hash.clone(), peer.difficulty.clone()))
+	}
+
 	/// Abort all sync activity
 	pub fn abort(&mut self, io: &mut SyncIo) {
 		self.restart(io);
@@ -299,16 +355,15 @@ impl ChainSync {
 	}
 
 	#[cfg_attr(feature="dev", allow(for_kv_map))] // Because it's not possible to get `values_mut()`
-	/// Reset sync. Clear all downloaded data but keep the queue
+	/// Reset sync. Clear all download state but keep the queue and already downloaded blocks
 	fn reset(&mut self) {
-		self.blocks.clear();
+		self.blocks.clear_soft();
 		for (_, ref mut p) in &mut self.peers {
 			p.asking_blocks.clear();
 			p.asking_hash = None;
 		}
 		self.syncing_difficulty = From::from(0u64);
 		self.state = SyncState::Idle;
-		self.blocks.clear();
 		self.active_peers = self.peers.keys().cloned().collect();
 	}
 
@@ -339,6 +394,8 @@ impl ChainSync {
 		let chain = io.chain().chain_info();
 		self.last_imported_block = chain.best_block_number;
 		self.last_imported_hash = chain.best_block_hash;
+		// Downloaded blocks can't be trusted anymore
+		self.blocks.clear();
 		self.restart(io);
 	}
 
@@ -355,6 +412,7 @@ impl ChainSync {
 			asking_blocks: Vec::new(),
 			asking_hash: None,
 			ask_time: 0f64,
+			useless_bodies: 0,
 		};
 
 		trace!(target: "sync", "New peer {} (protocol: {}, network: {:?}, difficulty: {:?}, latest:{}, genesis:{})", peer_id, peer.protocol_version, peer.network_id, peer.difficulty, peer.latest_hash, peer.genesis);
@@ -454,13 +512,14 @@ impl ChainSync {
 				} else {
 					// TODO: validate heads better. E.g. check that there is enough distance between blocks.
 					trace!(target: "sync", "Received {} subchain heads, proceeding to download", headers.len());
-					self.blocks.reset_to(hashes);
+					self.blocks.reset_to(hashes, self.download_receipts);
 					self.state = SyncState::Blocks;
 				}
 			},
 			SyncState::Blocks | SyncState::NewBlocks | SyncState::Waiting => {
-				trace!(target: "sync", "Inserted {} headers", headers.len());
-				self.blocks.insert_headers(headers);
+				let count = headers.len();
+				let inserted = self.blocks.insert_headers(headers);
+				trace!(target: "sync", "Inserted {} of {} headers from {}", inserted, count, peer_id);
 			},
 			_ => trace!(target: "sync", "Unexpected headers({}) from  {} ({}), state = {:?}", headers.len(), peer_id, io.peer_info(peer_id), self.state)
 		}
@@ -491,7 +550,95 @@ impl ChainSync {
 			for i in 0..item_count {
 				bodies.push(try!(r.at(i)).as_raw().to_vec());
 			}
-			self.blocks.insert_bodies(bodies);
+			let inserted = self.blocks.insert_bodies(bodies);
+			trace!(target: "sync", "Inserted {} of {} bodies from {}", inserted.len(), item_count, peer_id);
+			let useless_bodies = {
+				let peer = self.peers.get_mut(&peer_id).unwrap();
+				peer.useless_bodies = match inserted.is_empty() {
+					true => peer.useless_bodies + 1,
+					false => 0,
+				};
+				peer.useless_bodies
+			};
+			if useless_bodies >= MAX_USELESS_BODIES_RESPONSES {
+				debug!(target: "sync", "{} -> Deactivating peer after {} useless block bodies responses", peer_id, useless_bodies);
+				self.deactivate_peer(io, peer_id);
+			}
+			self.collect_blocks(io);
+		}
+		self.continue_sync(io);
+		Ok(())
+	}
+
+	/// Called by peer once it has receipts of requested blocks.
+	/// Receipts are matched to the requested block hashes in order and checked against
+	/// receipts root of the block. Receipts of blocks we don't know are ignored.
+	fn on_peer_receipts(&mut self, io: &mut SyncIo, peer_id: PeerId, r: &UntrustedRlp) -> Result<(), PacketDecodeError> {
+		if self.peers.get(&peer_id).unwrap().asking == PeerAsking::BlockReceipts {
+			return self.on_peer_block_receipts(io, peer_id, r);
+		}
+		// leave any other pending request to be cleaned up once the peer is disconnected
+		if self.peers.get(&peer_id).unwrap().asking != PeerAsking::Receipts {
+			debug!(target: "sync", "{} -> Unexpected receipts, disabling peer", peer_id);
+			io.disable_peer(peer_id);
+			return Ok(());
+		}
+		self.reset_peer_asking(peer_id, PeerAsking::Receipts);
+		let requested = replace(&mut self.peers.get_mut(&peer_id).unwrap().asking_blocks, Vec::new());
+		let item_count = r.item_count();
+		trace!(target: "sync", "{} -> Receipts ({} entries)", peer_id, item_count);
+		if item_count > requested.len() {
+			debug!(target: "sync", "{} -> Got {} receipts for {} requested blocks, disabling peer", peer_id, item_count, requested.len());
+			io.disable_peer(peer_id);
+			return Ok(());
+		}
+		let mut receipts = Vec::with_capacity(item_count);
+		for (i, hash) in requested.into_iter().take(item_count).enumerate() {
+			let block_receipts = try!(r.at(i));
+			let receipts_root = ordered_trie_root(block_receipts.iter().map(|r| r.as_raw().to_vec()).collect());
+			match io.chain().block_header(BlockID::Hash(hash.clone())) {
+				Some(ref header) if HeaderView::new(header).receipts_root() != receipts_root => {
+					debug!(target: "sync", "{} -> Receipts of block {} don't match receipts root, disabling peer", peer_id, hash);
+					io.disable_peer(peer_id);
+					return Ok(());
+				},
+				Some(_) => {
+					let block_receipts: Vec<Receipt> = try!(block_receipts.as_val());
+					receipts.push((hash, block_receipts));
+				},
+				None => trace!(target: "sync", "{} -> Ignoring receipts of unknown block {}", peer_id, hash),
+			}
+		}
+		for (hash, block_receipts) in receipts {
+			if self.downloaded_receipts.len() >= MAX_DOWNLOADED_RECEIPTS {
+				trace!(target: "sync", "Too many downloaded receipts, dropping the rest");
+				break;
+			}
+			self.downloaded_receipts.insert(hash, block_receipts);
+		}
+		self.continue_sync(io);
+		Ok(())
+	}
+
+	/// Called by peer once it has receipts of blocks being downloaded.
+	fn on_peer_block_receipts(&mut self, io: &mut SyncIo, peer_id: PeerId, r: &UntrustedRlp) -> Result<(), PacketDecodeError> {
+		self.clear_peer_download(peer_id);
+		self.reset_peer_asking(peer_id, PeerAsking::BlockReceipts);
+		let item_count = r.item_count();
+		trace!(target: "sync", "{} -> Receipts of downloaded blocks ({} entries)", peer_id, item_count);
+		if item_count == 0 {
+			self.deactivate_peer(io, peer_id);
+		}
+		else if self.state != SyncState::Blocks && self.state != SyncState::NewBlocks {
+			trace!(target: "sync", "Ignored unexpected block receipts");
+		}
+		else {
+			let mut receipts = Vec::with_capacity(item_count);
+			for i in 0..item_count {
+				receipts.push(try!(r.at(i)).as_raw().to_vec());
+			}
+			let inserted = self.blocks.insert_receipts(receipts);
+			trace!(target: "sync", "Inserted receipts of {} of {} blocks from {}", inserted.len(), item_count, peer_id);
 			self.collect_blocks(io);
 		}
 		self.continue_sync(io);
@@ -510,6 +657,7 @@ impl ChainSync {
 			return Ok(());
 		}
 		let header: BlockHeader = try!(header_rlp.as_val());
+		self.note_block_timestamp(peer_id, header.timestamp, time::get_time().sec as u64);
 		let mut unknown = false;
 		{
 			let peer = self.peers.get_mut(&peer_id).unwrap();
@@ -602,7 +750,7 @@ impl ChainSync {
 		};
 		if max_height != 0 {
 			trace!(target: "sync", "Downloading blocks for new hashes");
-			self.blocks.reset_to(new_hashes);
+			self.blocks.reset_to(new_hashes, self.download_receipts);
 			self.state = SyncState::NewBlocks;
 			self.sync_peer(io, peer_id, true);
 		}
@@ -615,12 +763,41 @@ impl ChainSync {
 		if self.peers.contains_key(&peer) {
 			debug!(target: "sync", "Disconnected {}", peer);
 			self.clear_peer_download(peer);
+			self.blocks.fail_from_peer(peer);
 			self.peers.remove(&peer);
 			self.active_peers.remove(&peer);
+			let change = self.clock_skew.remove_peer(peer);
+			self.on_clock_skew_change(change);
 			self.continue_sync(io);
 		}
 	}
 
+	/// Updates local clock skew estimate with timestamp of freshly announced block.
+	fn note_block_timestamp(&mut self, peer_id: PeerId, timestamp: u64, local_time: u64) {
+		let change = self.clock_skew.note(peer_id, timestamp, local_time);
+		self.on_clock_skew_change(change);
+	}
+
+	fn on_clock_skew_change(&mut self, change: Option<SkewChange>) {
+		match change {
+			Some(SkewChange::Skewed(skew)) => {
+				self.panic_handler.notify_alert(format!("Local clock is {} peers by ~{}s. Blocks authored by this node are likely to be rejected. Check the system time.",
+					if skew > 0 { "behind" } else { "ahead of" }, skew.abs()));
+				if self.pause_sealing_on_clock_skew {
+					warn!(target: "sync", "Sealing paused until the clock is fixed.");
+					self.miner.set_sealing_paused(true);
+				}
+			},
+			Some(SkewChange::Recovered(skew)) => {
+				info!(target: "sync", "Local clock is in sync with peers again (skew ~{}s).", skew);
+				if self.pause_sealing_on_clock_skew {
+					self.miner.set_sealing_paused(false);
+				}
+			},
+			None => {},
+		}
+	}
+
 	/// Called when a new peer is connected
 	pub fn on_peer_connected(&mut self, io: &mut SyncIo, peer: PeerId) {
 		trace!(target: "sync", "== Connected {}: {}", peer, io.peer_info(peer));
@@ -729,15 +906,29 @@ impl ChainSync {
 		}
 
 		// check to see if we need to download any block bodies first
-		let needed_bodies = self.blocks.needed_bodies(MAX_BODIES_TO_REQUEST, ignore_others);
+		let needed_bodies = self.blocks.needed_bodies(MAX_BODIES_TO_REQUEST, ignore_others, peer_id);
 		if !needed_bodies.is_empty() {
 			replace(&mut self.peers.get_mut(&peer_id).unwrap().asking_blocks, needed_bodies.clone());
 			self.request_bodies(io, peer_id, needed_bodies);
 			return;
 		}
 
+		// then receipts, if these are downloaded too
+		if self.serves_receipts(peer_id) {
+			let needed_receipts = self.blocks.needed_receipts(MAX_RECEIPTS_TO_REQUEST, ignore_others, peer_id);
+			if !needed_receipts.is_empty() {
+				replace(&mut self.peers.get_mut(&peer_id).unwrap().asking_blocks, needed_receipts.clone());
+				self.request_block_receipts(io, peer_id, needed_receipts);
+				return;
+			}
+		}
+
+		if self.blocks.is_full() {
+			trace!(target: "sync", "Downloaded blocks use {} bytes, waiting for them to be imported", self.blocks.heap_size());
+		}
+
 		// find subchain to download
-		if let Some((h, count)) = self.blocks.needed_headers(MAX_HEADERS_TO_REQUEST, ignore_others) {
+		if let Some((h, count)) = self.blocks.needed_headers(MAX_HEADERS_TO_REQUEST, ignore_others, peer_id) {
 			replace(&mut self.peers.get_mut(&peer_id).unwrap().asking_blocks, vec![h.clone()]);
 			self.request_headers_by_hash(io, peer_id, &h, count, 0, false, PeerAsking::BlockHeaders);
 		}
@@ -757,6 +948,11 @@ impl ChainSync {
 					self.blocks.clear_body_download(b);
 				}
 			},
+			PeerAsking::BlockReceipts => {
+				for b in &peer.asking_blocks {
+					self.blocks.clear_receipt_download(b);
+				}
+			},
 			_ => (),
 		}
 		peer.asking_blocks.clear();
@@ -766,9 +962,10 @@ impl ChainSync {
 	fn collect_blocks(&mut self, io: &mut SyncIo) {
 		let mut restart = false;
 		let mut imported = HashSet::new();
-		let blocks = self.blocks.drain();
+		let blocks = self.blocks.drain(MAX_BLOCKS_TO_IMPORT);
 		let count = blocks.len();
-		for block in blocks {
+		for downloaded in blocks.into_iter() {
+			let block = downloaded.block;
 			let number = BlockView::new(&block).header_view().number();
 			let h = BlockView::new(&block).header_view().sha3();
 
@@ -791,6 +988,9 @@ impl ChainSync {
 					self.last_imported_block = number;
 					self.last_imported_hash = h.clone();
 					imported.insert(h.clone());
+					if let Some(ref receipts) = downloaded.receipts {
+						self.keep_receipts(h.clone(), receipts);
+					}
 				},
 				Err(Error::Block(BlockError::UnknownParent(_))) if self.state == SyncState::NewBlocks => {
 					trace!(target: "sync", "Unknown new block parent, restarting sync");
@@ -818,6 +1018,18 @@ impl ChainSync {
 		}
 	}
 
+	/// Keeps receipts of downloaded block until these are taken (see `take_receipts`).
+	fn keep_receipts(&mut self, hash: H256, receipts: &[u8]) {
+		if self.downloaded_receipts.len() >= MAX_DOWNLOADED_RECEIPTS {
+			trace!(target: "sync", "Too many downloaded receipts, dropping receipts of {}", hash);
+			return;
+		}
+		match UntrustedRlp::new(receipts).as_val::<Vec<Receipt>>() {
+			Ok(receipts) => { self.downloaded_receipts.insert(hash, receipts); },
+			Err(e) => debug!(target: "sync", "Bad receipts of block {}: {:?}", hash, e),
+		}
+	}
+
 	/// Request headers from a peer by block hash
 	#[cfg_attr(feature="dev", allow(too_many_arguments))]
 	fn request_headers_by_hash(&mut self, sync: &mut SyncIo, peer_id: PeerId, h: &H256, count: usize, skip: usize, reverse: bool, asking: PeerAsking) {
@@ -840,6 +1052,50 @@ impl ChainSync {
 		self.send_request(sync, peer_id, PeerAsking::BlockBodies, GET_BLOCK_BODIES_PACKET, rlp.out());
 	}
 
+	/// Request receipts of blocks being downloaded from a peer
+	fn request_block_receipts(&mut self, sync: &mut SyncIo, peer_id: PeerId, hashes: Vec<H256>) {
+		let mut rlp = RlpStream::new_list(hashes.len());
+		trace!(target: "sync", "{} <- GetReceipts: {} entries starting from {:?}", peer_id, hashes.len(), hashes.first());
+		for h in hashes {
+			rlp.append(&h);
+		}
+		self.send_request(sync, peer_id, PeerAsking::BlockReceipts, GET_RECEIPTS_PACKET, rlp.out());
+	}
+
+	/// Checks if receipts can be requested from a peer. Session uses the highest version supported by both sides.
+	fn serves_receipts(&self, peer_id: PeerId) -> bool {
+		let our_version = self.protocol_version() as u32;
+		self.peers.get(&peer_id).map_or(false, |peer| min(peer.protocol_version, our_version) >= PROTOCOL_VERSION as u32)
+	}
+
+	/// Request receipts of given blocks from a peer. Peers without eth/63 are never asked.
+	/// The peer has to be idle and downloaded receipts have to be taken (see `take_receipts`) before there are too many.
+	pub fn request_receipts(&mut self, sync: &mut SyncIo, peer_id: PeerId, hashes: Vec<H256>) {
+		if !self.serves_receipts(peer_id) {
+			trace!(target: "sync", "{} does not serve receipts", peer_id);
+			return;
+		}
+		match self.peers.get(&peer_id) {
+			Some(peer) if peer.asking != PeerAsking::Nothing => {
+				trace!(target: "sync", "{} is busy, not asking for receipts", peer_id);
+				return;
+			},
+			Some(_) => (),
+			None => return,
+		}
+		if self.downloaded_receipts.len() >= MAX_DOWNLOADED_RECEIPTS {
+			trace!(target: "sync", "Too many downloaded receipts, not asking for more");
+			return;
+		}
+		let mut rlp = RlpStream::new_list(hashes.len());
+		trace!(target: "sync", "{} <- GetReceipts: {} entries starting from {:?}", peer_id, hashes.len(), hashes.first());
+		for h in &hashes {
+			rlp.append(h);
+		}
+		self.peers.get_mut(&peer_id).unwrap().asking_blocks = hashes;
+		self.send_request(sync, peer_id, PeerAsking::Receipts, GET_RECEIPTS_PACKET, rlp.out());
+	}
+
 	/// Reset peer status after request is complete.
 	fn reset_peer_asking(&mut self, peer_id: PeerId, asking: PeerAsking) -> bool {
 		let peer = self.peers.get_mut(&peer_id).unwrap();
@@ -895,15 +1151,22 @@ impl ChainSync {
 
 		let mut transactions = Vec::with_capacity(item_count);
 		for i in 0..item_count {
-			let tx: SignedTransaction = try!(r.val_at(i));
-			transactions.push(tx);
+			transactions.push(try!(r.at(i)).as_raw().to_vec());
 		}
 		let chain = io.chain();
 		let fetch_account = |a: &Address| AccountDetails {
 			nonce: chain.nonce(a),
 			balance: chain.balance(a),
 		};
-		let _ = self.miner.import_transactions(transactions, fetch_account);
+		let results = self.miner.import_raw_transactions(chain, transactions, fetch_account);
+		let malformed = results.iter().filter(|r| match **r {
+			Err(TransactionImportError::InvalidRlp(_)) => true,
+			_ => false,
+		}).count();
+		if malformed > 0 {
+			debug!(target: "sync", "{} -> {} malformed transactions", peer_id, malformed);
+			io.disable_peer(peer_id);
+		}
 		Ok(())
 	}
 
@@ -911,7 +1174,7 @@ impl ChainSync {
 	fn send_status(&mut self, io: &mut SyncIo) -> Result<(), UtilError> {
 		let mut packet = RlpStream::new_list(5);
 		let chain = io.chain().chain_info();
-		packet.append(&(PROTOCOL_VERSION as u32));
+		packet.append(&(self.protocol_version() as u32));
 		packet.append(&self.network_id);
 		packet.append(&chain.total_difficulty);
 		packet.append(&chain.best_block_hash);
@@ -979,18 +1242,27 @@ impl ChainSync {
 		}
 		trace!(target: "sync", "-> GetBlockBodies: {} entries", count);
 		count = min(count, MAX_BODIES_TO_SEND);
+		let hashes: Vec<H256> = try!((0..count).map(|i| r.val_at::<H256>(i)).collect());
+		let (added, rlp) = ChainSync::rlp_list_within(io.chain().block_bodies(&hashes), MAX_BODIES_SIZE_TO_SEND);
+		trace!(target: "sync", "-> GetBlockBodies: returned {} entries", added);
+		Ok(Some((BLOCK_BODIES_PACKET, rlp)))
+	}
+
+	/// Build RLP list out of known items, in order, until their total size would exceed `max_size` bytes.
+	/// The first known item is always included. Returns the number of included items and the list.
+	fn rlp_list_within(items: Vec<Option<Bytes>>, max_size: usize) -> (usize, RlpStream) {
 		let mut added = 0usize;
 		let mut data = Bytes::new();
-		for i in 0..count {
-			if let Some(mut hdr) = io.chain().block_body(BlockID::Hash(try!(r.val_at::<H256>(i)))) {
-				data.append(&mut hdr);
-				added += 1;
+		for mut item in items.into_iter().filter_map(|i| i) {
+			if added > 0 && data.len() + item.len() > max_size {
+				break;
 			}
+			data.append(&mut item);
+			added += 1;
 		}
 		let mut rlp = RlpStream::new_list(added);
 		rlp.append_raw(&data, added);
-		trace!(target: "sync", "-> GetBlockBodies: returned {} entries", added);
-		Ok(Some((BLOCK_BODIES_PACKET, rlp)))
+		(added, rlp)
 	}
 
 	/// Respond to GetNodeData request
@@ -1021,19 +1293,8 @@ impl ChainSync {
 			return Ok(None);
 		}
 		count = min(count, MAX_RECEIPTS_HEADERS_TO_SEND);
-		let mut added_headers = 0usize;
-		let mut added_receipts = 0usize;
-		let mut data = Bytes::new();
-		for i in 0..count {
-			if let Some(mut receipts_bytes) = io.chain().block_receipts(&try!(rlp.val_at::<H256>(i))) {
-				data.append(&mut receipts_bytes);
-				added_receipts += receipts_bytes.len();
-				added_headers += 1;
-				if added_receipts > MAX_RECEIPTS_TO_SEND { break; }
-			}
-		}
-		let mut rlp_result = RlpStream::new_list(added_headers);
-		rlp_result.append_raw(&data, added_headers);
+		let hashes: Vec<H256> = try!((0..count).map(|i| rlp.val_at::<H256>(i)).collect());
+		let (_, rlp_result) = ChainSync::rlp_list_within(io.chain().block_receipts_list(&hashes), MAX_RECEIPTS_TO_SEND);
 		Ok(Some((RECEIPTS_PACKET, rlp_result)))
 	}
 
@@ -1068,6 +1329,7 @@ impl ChainSync {
 			BLOCK_BODIES_PACKET => self.on_peer_block_bodies(io, peer, &rlp),
 			NEW_BLOCK_PACKET => self.on_peer_new_block(io, peer, &rlp),
 			NEW_BLOCK_HASHES_PACKET => self.on_peer_new_hashes(io, peer, &rlp),
+			RECEIPTS_PACKET => self.on_peer_receipts(io, peer, &rlp),
 
 			GET_BLOCK_BODIES_PACKET => self.return_rlp(io, &rlp,
 				ChainSync::return_block_bodies,
@@ -1077,6 +1339,11 @@ impl ChainSync {
 				ChainSync::return_block_headers,
 				|e| format!("Error sending block headers: {:?}", e)),
 
+			GET_RECEIPTS_PACKET if !self.serve_receipts => {
+				debug!(target: "sync", "{} -> Ignoring receipts request", peer);
+				Ok(())
+			},
+
 			GET_RECEIPTS_PACKET => self.return_rlp(io, &rlp,
 				ChainSync::return_receipts,
 				|e| format!("Error sending receipts: {:?}", e)),
@@ -1274,6 +1541,10 @@ impl ChainSync {
 	/// Maintain other peers. Send out any new blocks and transactions
 	pub fn maintain_sync(&mut self, io: &mut SyncIo) {
 		self.check_resume(io);
+		// import blocks left over after draining at most `MAX_BLOCKS_TO_IMPORT`
+		if self.state == SyncState::Blocks && !io.chain().queue_info().is_full() {
+			self.collect_blocks(io);
+		}
 	}
 
 	/// called when block is imported to chain, updates transactions queue and propagates the blocks
@@ -1302,11 +1573,13 @@ mod tests {
 	use ::SyncConfig;
 	use util::*;
 	use super::{PeerInfo, PeerAsking};
-	use ethcore::views::BlockView;
+	use ethcore::views::{BlockView, HeaderView};
 	use ethcore::header::*;
 	use ethcore::client::*;
 	use ethcore::spec::Spec;
+	use ethcore::receipt::Receipt;
 	use ethminer::{Miner, MinerService};
+	use util::panics::MayPanic;
 
 	fn get_dummy_block(order: u32, parent_hash: H256) -> Bytes {
 		let mut header = Header::new();
@@ -1387,6 +1660,53 @@ mod tests {
 		assert_eq!(1, io.queue.len());
 	}
 
+	#[test]
+	fn return_block_bodies_in_requested_order() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Uncle);
+		let known = vec![
+			(&client as &BlockChainClient).block_hash(BlockID::Number(5)).unwrap(),
+			(&client as &BlockChainClient).block_hash(BlockID::Number(2)).unwrap(),
+		];
+		let expected = client.block_bodies(&known);
+		let mut queue = VecDeque::new();
+		let io = TestIo::new(&mut client, &mut queue, None);
+
+		let mut request = RlpStream::new_list(3);
+		request.append(&known[0]);
+		request.append(&H256::from(0xdeadu64));
+		request.append(&known[1]);
+
+		// when
+		let result = ChainSync::return_block_bodies(&io, &UntrustedRlp::new(&request.out())).unwrap().unwrap();
+
+		// then
+		let response = result.1.out();
+		let rlp = UntrustedRlp::new(&response);
+		assert_eq!(rlp.item_count(), 2);
+		assert_eq!(rlp.at(0).unwrap().as_raw(), &expected[0].clone().unwrap()[..]);
+		assert_eq!(rlp.at(1).unwrap().as_raw(), &expected[1].clone().unwrap()[..]);
+	}
+
+	#[test]
+	fn rlp_list_should_respect_size_limit() {
+		// given
+		let item = |b: u8| Some(vec![0x83, b, b, b]);
+		let items = vec![None, item(1), None, item(2), item(3)];
+
+		// when
+		let (added, rlp) = ChainSync::rlp_list_within(items.clone(), 8);
+		let (added_oversized, rlp_oversized) = ChainSync::rlp_list_within(items, 2);
+
+		// then
+		assert_eq!(added, 2);
+		assert_eq!(rlp.out(), vec![0xc8, 0x83, 1, 1, 1, 0x83, 2, 2, 2]);
+		// the first known item is always returned
+		assert_eq!(added_oversized, 1);
+		assert_eq!(rlp_oversized.out(), vec![0xc4, 0x83, 1, 1, 1]);
+	}
+
 	#[test]
 	fn return_block_headers() {
 		use ethcore::views::HeaderView;
@@ -1481,22 +1801,270 @@ mod tests {
 
 	fn dummy_sync_with_peer(peer_latest_hash: H256, client: &BlockChainClient) -> ChainSync {
 		let mut sync = ChainSync::new(SyncConfig::default(), Miner::new(false, Spec::new_test()), client);
-		sync.peers.insert(0,
-			PeerInfo {
-				protocol_version: 0,
-				genesis: H256::zero(),
-				network_id: U256::zero(),
-				latest_hash: peer_latest_hash,
-				latest_number: None,
-				difficulty: None,
-				asking: PeerAsking::Nothing,
-				asking_blocks: Vec::new(),
-				asking_hash: None,
-				ask_time: 0f64,
-			});
+		sync.peers.insert(0, dummy_peer(peer_latest_hash));
 		sync
 	}
 
+	fn dummy_peer(latest_hash: H256) -> PeerInfo {
+		PeerInfo {
+			protocol_version: 0,
+			genesis: H256::zero(),
+			network_id: U256::zero(),
+			latest_hash: latest_hash,
+			latest_number: None,
+			difficulty: None,
+			asking: PeerAsking::Nothing,
+			asking_blocks: Vec::new(),
+			asking_hash: None,
+			ask_time: 0f64,
+			useless_bodies: 0,
+		}
+	}
+
+	fn receipts_rlp(hashes: &[H256]) -> Bytes {
+		let mut rlp = RlpStream::new_list(hashes.len());
+		for h in hashes {
+			rlp.append(h);
+		}
+		rlp.out()
+	}
+
+	#[test]
+	fn ignores_unsolicited_receipts_and_disables_peer() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, Some(0));
+		let request = receipts_rlp(&[H256::from("ff00000000000000000000000000000000000000000000000000000000000000")]);
+		let receipts = ChainSync::return_receipts(&io, &UntrustedRlp::new(&request)).unwrap().unwrap().1.out();
+
+		// when
+		sync.on_packet(&mut io, 0, super::RECEIPTS_PACKET, &receipts);
+
+		// then
+		assert_eq!(io.disabled_peers, vec![0]);
+		assert!(sync.take_receipts().is_empty());
+	}
+
+	fn receipts_packet(receipts_per_block: &[usize]) -> Bytes {
+		let mut rlp = RlpStream::new_list(receipts_per_block.len());
+		for count in receipts_per_block {
+			rlp.begin_list(*count);
+			for _ in 0..*count {
+				rlp.append(&Receipt::new(H256::zero(), U256::zero(), vec![]));
+			}
+		}
+		rlp.out()
+	}
+
+	#[test]
+	fn verifies_receipts_against_receipts_root() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Nothing);
+		let hashes = vec![
+			client.block_hash(BlockID::Number(5)).unwrap(),
+			client.block_hash(BlockID::Number(6)).unwrap(),
+		];
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		sync.peers.get_mut(&0).unwrap().protocol_version = super::PROTOCOL_VERSION as u32;
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, Some(0));
+		sync.request_receipts(&mut io, 0, hashes.clone());
+
+		// when
+		// the second block has no transactions, so it can't have any receipts
+		sync.on_packet(&mut io, 0, super::RECEIPTS_PACKET, &receipts_packet(&[0, 1]));
+
+		// then
+		assert_eq!(io.disabled_peers, vec![0]);
+		let receipts = sync.take_receipts();
+		assert!(receipts.is_empty());
+	}
+
+	#[test]
+	fn accepts_receipts_matching_receipts_root() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Nothing);
+		let hashes = vec![
+			client.block_hash(BlockID::Number(5)).unwrap(),
+			H256::from("ff00000000000000000000000000000000000000000000000000000000000000"),
+		];
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		sync.peers.get_mut(&0).unwrap().protocol_version = super::PROTOCOL_VERSION as u32;
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, Some(0));
+		sync.request_receipts(&mut io, 0, hashes.clone());
+
+		// when
+		sync.on_packet(&mut io, 0, super::RECEIPTS_PACKET, &receipts_packet(&[0, 1]));
+
+		// then
+		assert!(io.disabled_peers.is_empty());
+		let receipts = sync.take_receipts();
+		assert_eq!(receipts.len(), 1);
+		assert!(receipts[&hashes[0]].is_empty());
+	}
+
+	#[test]
+	fn does_not_request_receipts_if_eth63_is_not_registered() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		sync.serve_receipts = false;
+		sync.peers.get_mut(&0).unwrap().protocol_version = super::PROTOCOL_VERSION as u32;
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, None);
+
+		// when
+		sync.request_receipts(&mut io, 0, vec![H256::from("ff00000000000000000000000000000000000000000000000000000000000000")]);
+
+		// then
+		assert!(io.queue.is_empty());
+		assert_eq!(sync.peers[&0].asking, PeerAsking::Nothing);
+	}
+
+	#[test]
+	fn does_not_request_receipts_from_busy_peer() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		sync.peers.get_mut(&0).unwrap().protocol_version = super::PROTOCOL_VERSION as u32;
+		sync.peers.get_mut(&0).unwrap().asking = PeerAsking::BlockHeaders;
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, None);
+
+		// when
+		sync.request_receipts(&mut io, 0, vec![H256::from("ff00000000000000000000000000000000000000000000000000000000000000")]);
+
+		// then
+		assert!(io.queue.is_empty());
+		assert_eq!(sync.peers[&0].asking, PeerAsking::BlockHeaders);
+		assert!(sync.peers[&0].asking_blocks.is_empty());
+	}
+
+	#[test]
+	fn disables_peer_sending_malformed_transactions() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, Some(0));
+		let mut transactions = RlpStream::new_list(1);
+		transactions.append(&1u8);
+
+		// when
+		sync.on_packet(&mut io, 0, super::TRANSACTIONS_PACKET, &transactions.out());
+
+		// then
+		assert_eq!(io.disabled_peers, vec![0]);
+	}
+
+	#[test]
+	fn ignores_receipts_requests_if_not_serving_receipts() {
+		// given
+		let mut client = TestBlockChainClient::new();
+		let config = SyncConfig { serve_receipts: false, ..SyncConfig::default() };
+		let mut sync = ChainSync::new(config, Miner::new(false, Spec::new_test()), &client);
+		sync.peers.insert(0, dummy_peer(H256::new()));
+		let mut queue = VecDeque::new();
+		let mut io = TestIo::new(&mut client, &mut queue, Some(0));
+		let request = receipts_rlp(&[H256::from("ff00000000000000000000000000000000000000000000000000000000000000")]);
+
+		// when
+		sync.on_packet(&mut io, 0, super::GET_RECEIPTS_PACKET, &request);
+
+		// then
+		assert!(io.queue.is_empty());
+		assert_eq!(sync.status().protocol_version, 62);
+	}
+
+	#[test]
+	fn reports_block_download_progress() {
+		// given
+		let client = TestBlockChainClient::new();
+		client.add_blocks(10, EachBlockWith::Nothing);
+		let mut sync = dummy_sync_with_peer(H256::new(), &client);
+		let headers: Vec<_> = (1..10).map(|i| client.block_header(BlockID::Number(i)).unwrap()).collect();
+		let hashes: Vec<_> = headers.iter().map(|h| HeaderView::new(h).sha3()).collect();
+
+		// when
+		sync.blocks.reset_to(vec![hashes[0].clone()], false);
+		sync.blocks.insert_headers(headers);
+
+		// then
+		let status = sync.status();
+		assert_eq!(status.download, sync.blocks.status());
+		assert_eq!(status.download.headers_received, 9);
+		assert_eq!(status.download.heads, 1);
+	}
+
+	#[test]
+	fn returns_peer_chain_info() {
+		let client = TestBlockChainClient::new();
+		let mut sync = dummy_sync_with_peer(H256::from(42), &client);
+		sync.peers.get_mut(&0).unwrap().difficulty = Some(U256::from(100));
+
+		assert_eq!(sync.peer_chain_info(0), Some((H256::from(42), Some(U256::from(100)))));
+		assert_eq!(sync.peer_chain_info(1), None);
+	}
+
+	#[test]
+	fn reports_clock_skew_and_pauses_sealing() {
+		let client = TestBlockChainClient::new();
+		let config = SyncConfig { pause_sealing_on_clock_skew: true, ..SyncConfig::default() };
+		let mut sync = ChainSync::new(config, Miner::new(false, Spec::new_test()), &client);
+		let alerts = Arc::new(Mutex::new(Vec::new()));
+		let a = alerts.clone();
+		sync.panic_handler().on_alert(move |alert| a.lock().unwrap().push(alert));
+		let now = 1_000_000u64;
+
+		sync.note_block_timestamp(1, now + 120, now);
+		sync.note_block_timestamp(2, now + 118, now);
+		assert_eq!(sync.status().clock_skew, None);
+		assert!(!sync.miner.is_sealing_paused());
+		assert!(alerts.lock().unwrap().is_empty());
+
+		sync.note_block_timestamp(3, now + 121, now);
+		let status = sync.status();
+		assert_eq!(status.clock_skew, Some(120));
+		assert!(status.clock_skewed);
+		assert!(sync.miner.is_sealing_paused());
+		assert_eq!(alerts.lock().unwrap().len(), 1);
+		assert!(alerts.lock().unwrap()[0].starts_with("Local clock is behind peers by ~120s."));
+		assert!(sync.miner.map_sealing_work(&client, |_| ()).is_none());
+
+		for peer in 1..4 {
+			for _ in 0..10 {
+				sync.note_block_timestamp(peer, now, now);
+			}
+		}
+		assert!(!sync.status().clock_skewed);
+		assert!(!sync.miner.is_sealing_paused());
+	}
+
+	#[test]
+	fn ignores_single_peer_with_skewed_timestamps() {
+		let client = TestBlockChainClient::new();
+		let config = SyncConfig { pause_sealing_on_clock_skew: true, ..SyncConfig::default() };
+		let mut sync = ChainSync::new(config, Miner::new(false, Spec::new_test()), &client);
+		let now = 1_000_000u64;
+
+		for peer in 1..4 {
+			sync.note_block_timestamp(peer, now - 1, now);
+		}
+		for _ in 0..20 {
+			sync.note_block_timestamp(4, now + 3000, now);
+		}
+
+		let status = sync.status();
+		assert_eq!(status.clock_skew, Some(-1));
+		assert!(!status.clock_skewed);
+		assert!(!sync.miner.is_sealing_paused());
+	}
+
 	#[test]
 	fn finds_lagging_peers() {
 		let mut client = TestBlockChainClient::new();
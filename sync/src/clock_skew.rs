@@ -0,0 +1,226 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Local clock skew estimation based on timestamps of freshly announced blocks.
+//!
+//! Every peer contributes the median of its recent samples and the estimate is the median
+//! across peers, so a single peer can't move the estimate on its own.
+
+use std::collections::{HashMap, VecDeque};
+use util::network::PeerId;
+
+/// Number of recent samples kept for each peer.
+const SAMPLES_PER_PEER: usize = 8;
+/// Minimal number of peers needed to produce an estimate.
+const MIN_PEERS: usize = 3;
+/// Samples further off than this (in seconds) are ignored altogether.
+const MAX_SAMPLE_DELTA: i64 = 24 * 60 * 60;
+
+/// Change of the skew state reported by `ClockSkew::note`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SkewChange {
+	/// Estimated skew has just exceeded the threshold.
+	Skewed(i64),
+	/// Estimated skew is back within the threshold.
+	Recovered(i64),
+}
+
+/// Estimates skew of the local clock.
+pub struct ClockSkew {
+	/// Recent differences between block timestamp and local receipt time for each peer.
+	samples: HashMap<PeerId, VecDeque<i64>>,
+	/// Maximal tolerated skew in seconds.
+	threshold: u64,
+	/// Whether the skew is currently above threshold.
+	skewed: bool,
+}
+
+impl ClockSkew {
+	/// Creates new estimator with given threshold (in seconds).
+	pub fn new(threshold: u64) -> Self {
+		ClockSkew {
+			samples: HashMap::new(),
+			threshold: threshold,
+			skewed: false,
+		}
+	}
+
+	/// Records timestamp of a block announced by `peer` and received at `local_time` (both in seconds).
+	/// Returns change of skew state if any.
+	pub fn note(&mut self, peer: PeerId, block_timestamp: u64, local_time: u64) -> Option<SkewChange> {
+		let delta = block_timestamp as i64 - local_time as i64;
+		if delta.abs() > MAX_SAMPLE_DELTA {
+			trace!(target: "sync", "Ignoring timestamp sample from {}: {}s off", peer, delta);
+			return None;
+		}
+		{
+			let samples = self.samples.entry(peer).or_insert_with(VecDeque::new);
+			if samples.len() == SAMPLES_PER_PEER {
+				samples.pop_front();
+			}
+			samples.push_back(delta);
+		}
+		self.update()
+	}
+
+	/// Forgets samples of disconnected peer.
+	pub fn remove_peer(&mut self, peer: PeerId) -> Option<SkewChange> {
+		if self.samples.remove(&peer).is_some() {
+			self.update()
+		} else {
+			None
+		}
+	}
+
+	/// Returns estimated skew in seconds (positive if local clock is behind) or `None` if there is not enough data.
+	pub fn estimate(&self) -> Option<i64> {
+		if self.samples.len() < MIN_PEERS {
+			return None;
+		}
+		let per_peer = self.samples.values()
+			.map(|samples| median(samples.iter().cloned().collect()))
+			.collect();
+		Some(median(per_peer))
+	}
+
+	/// Returns `true` if estimated skew exceeds the threshold.
+	pub fn is_skewed(&self) -> bool {
+		self.skewed
+	}
+
+	fn update(&mut self) -> Option<SkewChange> {
+		let estimate = match self.estimate() {
+			Some(estimate) => estimate,
+			None => return None,
+		};
+		let skewed = estimate.abs() as u64 > self.threshold;
+		match (self.skewed, skewed) {
+			(false, true) => {
+				self.skewed = true;
+				Some(SkewChange::Skewed(estimate))
+			},
+			(true, false) => {
+				self.skewed = false;
+				Some(SkewChange::Recovered(estimate))
+			},
+			_ => None,
+		}
+	}
+}
+
+/// Median of non-empty list (lower one for even length).
+fn median(mut values: Vec<i64>) -> i64 {
+	values.sort();
+	values[(values.len() - 1) / 2]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const NOW: u64 = 1_000_000;
+
+	#[test]
+	fn should_not_estimate_with_too_few_peers() {
+		// given
+		let mut skew = ClockSkew::new(30);
+
+		// when
+		skew.note(1, NOW - 100, NOW);
+		skew.note(2, NOW - 100, NOW);
+
+		// then
+		assert_eq!(skew.estimate(), None);
+		assert!(!skew.is_skewed());
+	}
+
+	#[test]
+	fn should_estimate_median_skew() {
+		// given
+		let mut skew = ClockSkew::new(30);
+
+		// when
+		skew.note(1, NOW + 1, NOW);
+		skew.note(1, NOW + 3, NOW);
+		skew.note(1, NOW + 2, NOW);
+		skew.note(2, NOW - 2, NOW);
+		skew.note(3, NOW + 5, NOW);
+
+		// then
+		assert_eq!(skew.estimate(), Some(2));
+		assert!(!skew.is_skewed());
+	}
+
+	#[test]
+	fn should_alert_when_threshold_exceeded_and_recover() {
+		// given
+		let mut skew = ClockSkew::new(30);
+		skew.note(1, NOW - 60, NOW);
+		skew.note(2, NOW - 61, NOW);
+
+		// when
+		let change = skew.note(3, NOW - 59, NOW);
+
+		// then
+		assert_eq!(change, Some(SkewChange::Skewed(-60)));
+		assert!(skew.is_skewed());
+
+		// when clock is fixed
+		for _ in 0..SAMPLES_PER_PEER {
+			skew.note(1, NOW, NOW);
+			skew.note(2, NOW, NOW);
+		}
+
+		// then
+		assert!(!skew.is_skewed());
+		assert_eq!(skew.estimate(), Some(0));
+	}
+
+	#[test]
+	fn should_resist_single_outlier_peer() {
+		// given
+		let mut skew = ClockSkew::new(30);
+		skew.note(1, NOW, NOW);
+		skew.note(2, NOW + 1, NOW);
+		skew.note(3, NOW - 1, NOW);
+
+		// when
+		let mut changes = vec![];
+		for _ in 0..100 {
+			changes.push(skew.note(4, NOW + 3600, NOW));
+		}
+
+		// then
+		assert!(changes.iter().all(Option::is_none));
+		assert!(!skew.is_skewed());
+		assert_eq!(skew.estimate(), Some(0));
+	}
+
+	#[test]
+	fn should_forget_disconnected_peers() {
+		// given
+		let mut skew = ClockSkew::new(30);
+		skew.note(1, NOW, NOW);
+		skew.note(2, NOW, NOW);
+		skew.note(3, NOW, NOW);
+
+		// when
+		skew.remove_peer(3);
+
+		// then
+		assert_eq!(skew.estimate(), None);
+	}
+}
@@ -64,9 +64,10 @@ extern crate heapsize;
 
 use std::ops::*;
 use std::sync::*;
-use util::network::{NetworkProtocolHandler, NetworkService, NetworkContext, PeerId};
+use util::network::{NetworkProtocolHandler, NetworkService, NetworkContext, PeerId, PeerInfo as NetworkPeerInfo};
 use util::TimerToken;
-use util::{U256, ONE_U256};
+use util::panics::{MayPanic, OnPanicListener};
+use util::{H256, U256, ONE_U256};
 use ethcore::client::Client;
 use ethcore::service::SyncMessage;
 use ethminer::Miner;
@@ -75,6 +76,7 @@ use chain::ChainSync;
 
 mod chain;
 mod blocks;
+mod clock_skew;
 mod io;
 
 #[cfg(test)]
@@ -86,6 +88,17 @@ pub struct SyncConfig {
 	pub max_download_ahead_blocks: usize,
 	/// Network ID
 	pub network_id: U256,
+	/// Maximal tolerated skew of local clock (in seconds) as estimated from peers' blocks.
+	pub clock_skew_threshold: u64,
+	/// Stop preparing blocks for sealing while local clock is skewed.
+	pub pause_sealing_on_clock_skew: bool,
+	/// Maximal memory used by blocks being downloaded (in bytes).
+	pub max_download_memory: usize,
+	/// Serve block receipts to peers. eth/63 is advertised only if enabled.
+	pub serve_receipts: bool,
+	/// Download receipts of synced blocks along with their bodies (from eth/63 peers only).
+	/// Receipts of imported blocks are then available through `ChainSync::take_receipts`.
+	pub download_receipts: bool,
 }
 
 impl Default for SyncConfig {
@@ -93,6 +106,11 @@ impl Default for SyncConfig {
 		SyncConfig {
 			max_download_ahead_blocks: 20000,
 			network_id: ONE_U256,
+			clock_skew_threshold: 30,
+			pause_sealing_on_clock_skew: false,
+			max_download_memory: 256 * 1024 * 1024,
+			serve_receipts: true,
+			download_receipts: false,
 		}
 	}
 }
@@ -112,16 +130,29 @@ pub struct EthSync {
 }
 
 pub use self::chain::{SyncStatus, SyncState};
+pub use self::blocks::BlockCollectionStatus;
+
+/// Connected peer information: network session details enriched with the peer's chain state.
+#[derive(Debug, Clone)]
+pub struct PeerInfo {
+	/// Network session information
+	pub network: NetworkPeerInfo,
+	/// Peer best block hash. `None` for peers without eth session.
+	pub head: Option<H256>,
+	/// Peer total difficulty if known.
+	pub difficulty: Option<U256>,
+}
 
 impl EthSync {
 	/// Creates and register protocol with the network service
 	pub fn register(service: &mut NetworkService<SyncMessage>, config: SyncConfig, chain: Arc<Client>, miner: Arc<Miner>) -> Arc<EthSync> {
+		let versions: &[u8] = if config.serve_receipts { &[62u8, 63u8] } else { &[62u8] };
 		let sync = ChainSync::new(config, miner, chain.deref());
 		let sync = Arc::new(EthSync {
 			chain: chain,
 			sync: RwLock::new(sync),
 		});
-		service.register_protocol(sync.clone(), "eth", &[62u8, 63u8]).expect("Error registering eth protocol handler");
+		service.register_protocol(sync.clone(), "eth", versions).expect("Error registering eth protocol handler");
 		sync
 	}
 
@@ -134,6 +165,27 @@ impl EthSync {
 	pub fn restart(&mut self, io: &mut NetworkContext<SyncMessage>) {
 		self.sync.write().unwrap().restart(&mut NetSyncIo::new(io, self.chain.deref()));
 	}
+
+	/// Enriches network information of connected peers (see `NetworkService::peers_info`) with their chain state.
+	pub fn peers_info(&self, peers: Vec<NetworkPeerInfo>) -> Vec<PeerInfo> {
+		let sync = self.sync.read().unwrap();
+		peers.into_iter().map(|peer| {
+			let chain_info = sync.peer_chain_info(peer.peer_id);
+			PeerInfo {
+				network: peer,
+				head: chain_info.as_ref().map(|&(ref head, _)| head.clone()),
+				difficulty: chain_info.and_then(|(_, difficulty)| difficulty),
+			}
+		}).collect()
+	}
+}
+
+impl MayPanic for EthSync {
+	fn on_panic<F>(&self, _closure: F) where F: OnPanicListener {}
+
+	fn on_alert<F>(&self, closure: F) where F: OnPanicListener {
+		self.sync.read().unwrap().panic_handler().on_alert(closure);
+	}
 }
 
 impl SyncProvider for EthSync {
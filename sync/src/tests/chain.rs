@@ -15,9 +15,12 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use util::*;
-use ethcore::client::{BlockChainClient, BlockID, EachBlockWith};
+use ethcore::client::{BlockChainClient, TestBlockChainClient, BlockID, EachBlockWith};
+use ethcore::header::Header as BlockHeader;
+use ethcore::receipt::Receipt;
 use chain::{SyncState};
 use super::helpers::*;
+use SyncConfig;
 
 #[test]
 fn two_peers() {
@@ -191,3 +194,87 @@ fn restart_on_broken_chain() {
 
 	assert_eq!(net.peer(0).chain.chain_info().best_block_number, 5);
 }
+
+#[test]
+fn exchanges_receipts() {
+	::env_logger::init().ok();
+	let mut net = TestNet::new(2);
+	net.peer_mut(0).chain.add_blocks(10, EachBlockWith::Nothing);
+	net.peer_mut(1).chain.add_blocks(10, EachBlockWith::Nothing);
+	net.sync();
+	let hashes = vec![
+		net.peer(0).chain.block_hash(BlockID::Number(5)).unwrap(),
+		net.peer(0).chain.block_hash(BlockID::Number(6)).unwrap(),
+		// unknown block
+		H256::from("ff00000000000000000000000000000000000000000000000000000000000000"),
+	];
+	// blocks without transactions have no receipts
+	net.peer_mut(1).chain.set_block_receipts(hashes[0].clone(), vec![]);
+	net.peer_mut(1).chain.set_block_receipts(hashes[1].clone(), vec![]);
+
+	{
+		let peer = net.peer_mut(0);
+		peer.sync.request_receipts(&mut TestIo::new(&mut peer.chain, &mut peer.queue, None), 1, hashes.clone());
+	}
+	net.sync_until_done();
+
+	let receipts = net.peer_mut(0).sync.take_receipts();
+	assert_eq!(receipts.len(), 2);
+	assert!(receipts[&hashes[0]].is_empty());
+	assert!(receipts[&hashes[1]].is_empty());
+	assert!(net.peer_mut(0).sync.take_receipts().is_empty());
+}
+
+/// Adds blocks with a single receipt each. Returns their hashes.
+fn add_blocks_with_receipts(chain: &TestBlockChainClient, count: usize) -> Vec<H256> {
+	let receipts = vec![Receipt::new(H256::zero(), U256::from(21_000), vec![])];
+	let receipts_root = ordered_trie_root(receipts.iter().map(|r| encode(r).to_vec()).collect());
+	(0..count).map(|_| {
+		let info = chain.chain_info();
+		let mut header = BlockHeader::new();
+		header.number = info.best_block_number + 1;
+		header.parent_hash = info.best_block_hash;
+		header.difficulty = U256::from(header.number);
+		header.receipts_root = receipts_root.clone();
+		let mut block = RlpStream::new_list(3);
+		block.append(&header);
+		block.append_raw(&rlp::EMPTY_LIST_RLP, 1);
+		block.append_raw(&rlp::EMPTY_LIST_RLP, 1);
+		chain.import_block(block.out()).unwrap();
+		chain.set_block_receipts(header.hash(), receipts.clone());
+		header.hash()
+	}).collect()
+}
+
+#[test]
+fn downloads_receipts_along_with_blocks() {
+	::env_logger::init().ok();
+	let mut net = TestNet::new_with_config(2, || SyncConfig { download_receipts: true, ..SyncConfig::default() });
+	net.peer_mut(1).chain.add_blocks(5, EachBlockWith::Nothing);
+	let hashes = add_blocks_with_receipts(&net.peer(1).chain, 5);
+	net.sync();
+
+	assert_eq!(net.peer(0).chain.chain_info().best_block_hash, hashes[4]);
+	let receipts = net.peer_mut(0).sync.take_receipts();
+	// blocks without transactions have no receipts
+	assert_eq!(receipts.len(), 10);
+	for hash in &hashes {
+		assert_eq!(receipts[hash].len(), 1);
+	}
+}
+
+#[test]
+fn does_not_request_receipts_from_peers_without_eth63() {
+	::env_logger::init().ok();
+	let mut net = TestNet::new_with_config(2, || SyncConfig { serve_receipts: false, ..SyncConfig::default() });
+	net.sync();
+	let hashes = vec![H256::from("ff00000000000000000000000000000000000000000000000000000000000000")];
+
+	{
+		let peer = net.peer_mut(0);
+		peer.sync.request_receipts(&mut TestIo::new(&mut peer.chain, &mut peer.queue, None), 1, hashes);
+	}
+
+	assert!(net.done());
+	assert_eq!(net.peer(0).sync.status().protocol_version, 62);
+}
@@ -26,6 +26,7 @@ pub struct TestIo<'p> {
 	pub chain: &'p mut TestBlockChainClient,
 	pub queue: &'p mut VecDeque<TestPacket>,
 	pub sender: Option<PeerId>,
+	pub disabled_peers: Vec<PeerId>,
 }
 
 impl<'p> TestIo<'p> {
@@ -33,13 +34,15 @@ impl<'p> TestIo<'p> {
 		TestIo {
 			chain: chain,
 			queue: queue,
-			sender: sender
+			sender: sender,
+			disabled_peers: Vec::new(),
 		}
 	}
 }
 
 impl<'p> SyncIo for TestIo<'p> {
-	fn disable_peer(&mut self, _peer_id: PeerId) {
+	fn disable_peer(&mut self, peer_id: PeerId) {
+		self.disabled_peers.push(peer_id);
 	}
 
 	fn disconnect_peer(&mut self, _peer_id: PeerId) {
@@ -87,13 +90,17 @@ pub struct TestNet {
 
 impl TestNet {
 	pub fn new(n: usize) -> TestNet {
+		TestNet::new_with_config(n, SyncConfig::default)
+	}
+
+	pub fn new_with_config<F>(n: usize, config: F) -> TestNet where F: Fn() -> SyncConfig {
 		let mut net = TestNet {
 			peers: Vec::new(),
 			started: false,
 		};
 		for _ in 0..n {
 			let chain = TestBlockChainClient::new();
-			let sync = ChainSync::new(SyncConfig::default(), Miner::new(false, Spec::new_test()), &chain);
+			let sync = ChainSync::new(config(), Miner::new(false, Spec::new_test()), &chain);
 			net.peers.push(TestPeer {
 				sync: sync,
 				chain: chain,
@@ -165,6 +172,12 @@ impl TestNet {
 		}
 	}
 
+	pub fn sync_until_done(&mut self) {
+		while !self.done() {
+			self.sync_step();
+		}
+	}
+
 	pub fn done(&self) -> bool {
 		self.peers.iter().all(|p| p.queue.is_empty())
 	}
@@ -51,8 +51,6 @@ pub enum UtilError {
 	Io(io::IoError),
 	/// Error concerning the network address parsing subsystem.
 	AddressParse(::std::net::AddrParseError),
-	/// Error concerning the network address resolution subsystem.
-	AddressResolve(Option<::std::io::Error>),
 	/// Error concerning the hex conversion logic.
 	FromHex(FromHexError),
 	/// Error concerning the database abstraction logic.
@@ -74,8 +72,6 @@ impl fmt::Display for UtilError {
 			UtilError::StdIo(ref err) => f.write_fmt(format_args!("{}", err)),
 			UtilError::Io(ref err) => f.write_fmt(format_args!("{}", err)),
 			UtilError::AddressParse(ref err) => f.write_fmt(format_args!("{}", err)),
-			UtilError::AddressResolve(Some(ref err)) => f.write_fmt(format_args!("{}", err)),
-			UtilError::AddressResolve(_) => f.write_str("Failed to resolve network address."),
 			UtilError::FromHex(ref err) => f.write_fmt(format_args!("{}", err)),
 			UtilError::BaseData(ref err) => f.write_fmt(format_args!("{}", err)),
 			UtilError::Network(ref err) => f.write_fmt(format_args!("{}", err)),
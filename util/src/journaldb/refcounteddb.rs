@@ -47,10 +47,9 @@ const PADDING : [u8; 10] = [ 0u8; 10 ];
 
 impl RefCountedDB {
 	/// Create a new instance given a `backing` database.
-	pub fn new(path: &str) -> RefCountedDB {
-		let opts = DatabaseConfig {
-			prefix_size: Some(12) //use 12 bytes as prefix, this must match account_db prefix
-		};
+	pub fn new(path: &str, config: DatabaseConfig) -> RefCountedDB {
+		// use 12 bytes as prefix, this must match account_db prefix
+		let opts = config.prefix(12);
 		let backing = Database::open(&opts, path).unwrap_or_else(|e| {
 			panic!("Error opening state db: {}", e);
 		});
@@ -80,7 +79,7 @@ impl RefCountedDB {
 	fn new_temp() -> RefCountedDB {
 		let mut dir = env::temp_dir();
 		dir.push(H32::random().hex());
-		Self::new(dir.to_str().unwrap())
+		Self::new(dir.to_str().unwrap(), DatabaseConfig::default())
 	}
 }
 
@@ -201,6 +200,7 @@ mod tests {
 
 	use common::*;
 	use super::*;
+	use kvdb::DatabaseConfig;
 	use super::super::traits::JournalDB;
 	use hashdb::*;
 
@@ -17,8 +17,8 @@
 //! Key-Value store abstraction with `RocksDB` backend.
 
 use std::default::Default;
-use rocksdb::{DB, Writable, WriteBatch, IteratorMode, DBVector, DBIterator,
-	IndexType, Options, DBCompactionStyle, BlockBasedOptions, Direction};
+use rocksdb::{DB, Writable, WriteBatch, WriteOptions, IteratorMode, DBVector, DBIterator,
+	IndexType, Options, DBCompactionStyle, BlockBasedOptions, Direction, Snapshot, DBCFHandle};
 
 /// Write transaction. Batches a sequence of put/delete operations for efficiency.
 pub struct DBTransaction {
@@ -46,12 +46,89 @@ impl DBTransaction {
 	pub fn delete(&self, key: &[u8]) -> Result<(), String> {
 		self.batch.delete(key)
 	}
+
+	/// Insert a key-value pair into column `col` of `db` (`None` for the default column).
+	/// Any existing value will be overwritten upon write.
+	pub fn put_in(&self, db: &Database, col: Option<u32>, key: &[u8], value: &[u8]) -> Result<(), String> {
+		match col {
+			None => self.batch.put(key, value),
+			Some(col) => self.batch.put_cf(db.cfs[col as usize], key, value),
+		}
+	}
+}
+
+/// Compaction profile for the database settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompactionProfile {
+	/// Profile suited for solid state drives.
+	SSD,
+	/// Profile suited for rotational disks: bigger files and less frequent compactions.
+	HDD,
+}
+
+impl Default for CompactionProfile {
+	fn default() -> Self {
+		CompactionProfile::SSD
+	}
+}
+
+impl CompactionProfile {
+	/// Target size of L1 files in bytes.
+	fn target_file_size(&self) -> u64 {
+		match *self {
+			CompactionProfile::SSD => 32 * 1024 * 1024,
+			CompactionProfile::HDD => 192 * 1024 * 1024,
+		}
+	}
+
+	/// Maximal number of concurrent background compactions.
+	fn background_compactions(&self) -> i32 {
+		match *self {
+			CompactionProfile::SSD => 4,
+			CompactionProfile::HDD => 1,
+		}
+	}
 }
 
 /// Database configuration
+#[derive(Debug, Clone, PartialEq)]
 pub struct DatabaseConfig {
 	/// Optional prefix size in bytes. Allows lookup by partial key.
-	pub prefix_size: Option<usize>
+	pub prefix_size: Option<usize>,
+	/// Block cache size in MB. RocksDB default is used if not set.
+	pub cache_size: Option<usize>,
+	/// Compaction profile.
+	pub compaction: CompactionProfile,
+	/// Use write-ahead log. Disabling it speeds up writes, but recent writes may be lost on crash.
+	pub wal: bool,
+	/// Number of column families. `None` for a database without columns.
+	pub columns: Option<u32>,
+}
+
+impl Default for DatabaseConfig {
+	fn default() -> Self {
+		DatabaseConfig {
+			prefix_size: None,
+			cache_size: None,
+			compaction: CompactionProfile::default(),
+			wal: true,
+			columns: None,
+		}
+	}
+}
+
+impl DatabaseConfig {
+	/// Returns the config with given prefix size.
+	pub fn prefix(mut self, prefix_size: usize) -> Self {
+		self.prefix_size = Some(prefix_size);
+		self
+	}
+
+	/// Returns the config with given number of columns.
+	pub fn columns(mut self, columns: Option<u32>) -> Self {
+		self.columns = columns;
+		self
+	}
 }
 
 /// Database iterator
@@ -67,15 +144,30 @@ impl<'a> Iterator for DatabaseIterator {
 	}
 }
 
+/// Consistent read-only view of the database at the moment the snapshot was taken.
+pub struct DatabaseSnapshot<'a> {
+	snapshot: Snapshot<'a>,
+}
+
+impl<'a> DatabaseSnapshot<'a> {
+	/// Get value by key.
+	pub fn get(&self, key: &[u8]) -> Result<Option<DBVector>, String> {
+		self.snapshot.get(key)
+	}
+}
+
 /// Key-Value database.
 pub struct Database {
 	db: DB,
+	write_opts: WriteOptions,
+	config: DatabaseConfig,
+	cfs: Vec<DBCFHandle>,
 }
 
 impl Database {
 	/// Open database with default settings.
 	pub fn open_default(path: &str) -> Result<Database, String> {
-		Database::open(&DatabaseConfig { prefix_size: None }, path)
+		Database::open(&DatabaseConfig::default(), path)
 	}
 
 	/// Open database file. Creates if it does not exist.
@@ -85,6 +177,11 @@ impl Database {
 		opts.create_if_missing(true);
 		opts.set_use_fsync(false);
 		opts.set_compaction_style(DBCompactionStyle::DBUniversalCompaction);
+		opts.set_target_file_size_base(config.compaction.target_file_size());
+		opts.set_max_background_compactions(config.compaction.background_compactions());
+		if let Some(cache_size) = config.cache_size {
+			opts.set_block_cache_size_mb(cache_size as u64);
+		}
 		/*
 		opts.set_bytes_per_sync(8388608);
 		opts.set_disable_data_sync(false);
@@ -109,8 +206,40 @@ impl Database {
 			opts.set_block_based_table_factory(&block_opts);
 			opts.set_prefix_extractor_fixed_size(size);
 		}
-		let db = try!(DB::open(&opts, path));
-		Ok(Database { db: db })
+		let mut write_opts = WriteOptions::new();
+		write_opts.disable_wal(!config.wal);
+
+		let (db, cfs) = match config.columns {
+			Some(columns) => {
+				let names = (0..columns).map(|c| format!("col{}", c)).collect::<Vec<_>>();
+				let names = names.iter().map(|name| name.as_str()).collect::<Vec<_>>();
+				let db = match DB::open_cf(&opts, path, &names) {
+					Ok(db) => db,
+					// column families of a new database have to be created first
+					Err(_) => {
+						let mut db = try!(DB::open(&opts, path));
+						for name in &names {
+							try!(db.create_cf(name, &opts));
+						}
+						db
+					},
+				};
+				let cfs = names.iter().map(|name| *db.cf_handle(name).expect("Column family was opened or created above; qed")).collect();
+				(db, cfs)
+			},
+			None => (try!(DB::open(&opts, path)), Vec::new()),
+		};
+		Ok(Database {
+			db: db,
+			write_opts: write_opts,
+			config: config.clone(),
+			cfs: cfs,
+		})
+	}
+
+	/// Returns configuration the database was opened with.
+	pub fn config(&self) -> &DatabaseConfig {
+		&self.config
 	}
 
 	/// Insert a key-value pair in the transaction. Any existing value value will be overwritten.
@@ -125,7 +254,7 @@ impl Database {
 
 	/// Commit transaction to database.
 	pub fn write(&self, tr: DBTransaction) -> Result<(), String> {
-		self.db.write(tr.batch)
+		self.db.write_opt(tr.batch, &self.write_opts)
 	}
 
 	/// Get value by key.
@@ -152,6 +281,21 @@ impl Database {
 	pub fn iter(&self) -> DatabaseIterator {
 		DatabaseIterator { iter: self.db.iterator(IteratorMode::Start) }
 	}
+
+	/// Iterator over entries of column `col` (`None` for the default column).
+	pub fn iter_column(&self, col: Option<u32>) -> DatabaseIterator {
+		match col {
+			None => self.iter(),
+			Some(col) => DatabaseIterator {
+				iter: self.db.iterator_cf(self.cfs[col as usize], IteratorMode::Start).expect("Column handles are valid while the database is open; qed"),
+			},
+		}
+	}
+
+	/// Take a snapshot of the database to read multiple values consistently.
+	pub fn snapshot(&self) -> DatabaseSnapshot {
+		DatabaseSnapshot { snapshot: self.db.snapshot() }
+	}
 }
 
 #[cfg(test)]
@@ -198,15 +342,72 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn snapshot_does_not_see_later_writes() {
+		// given
+		let path = RandomTempPath::create_dir();
+		let db = Database::open_default(path.as_path().to_str().unwrap()).unwrap();
+		let key1 = H256::from(1);
+		let key2 = H256::from(2);
+		db.put(&key1, b"cat").unwrap();
+
+		// when
+		let snapshot = db.snapshot();
+		db.put(&key1, b"dog").unwrap();
+		db.put(&key2, b"elephant").unwrap();
+
+		// then
+		assert_eq!(snapshot.get(&key1).unwrap().unwrap().deref(), b"cat");
+		assert!(snapshot.get(&key2).unwrap().is_none());
+		assert_eq!(db.get(&key1).unwrap().unwrap().deref(), b"dog");
+	}
+
 	#[test]
 	fn kvdb() {
 		let path = RandomTempPath::create_dir();
 		let smoke = Database::open_default(path.as_path().to_str().unwrap()).unwrap();
 		assert!(smoke.is_empty());
-		test_db(&DatabaseConfig { prefix_size: None });
-		test_db(&DatabaseConfig { prefix_size: Some(1) });
-		test_db(&DatabaseConfig { prefix_size: Some(8) });
-		test_db(&DatabaseConfig { prefix_size: Some(32) });
+		test_db(&DatabaseConfig::default());
+		test_db(&DatabaseConfig::default().prefix(1));
+		test_db(&DatabaseConfig::default().prefix(8));
+		test_db(&DatabaseConfig::default().prefix(32));
+	}
+
+	#[test]
+	fn kvdb_with_columns() {
+		let path = RandomTempPath::create_dir();
+		let config = DatabaseConfig::default().columns(Some(4));
+		{
+			let db = Database::open(&config, path.as_path().to_str().unwrap()).unwrap();
+			db.put(&H256::from(1), b"cat").unwrap();
+		}
+
+		// reopening requires the same columns
+		let db = Database::open(&config, path.as_path().to_str().unwrap()).unwrap();
+		assert_eq!(db.get(&H256::from(1)).unwrap().unwrap().deref(), b"cat");
+
+		let transaction = DBTransaction::new();
+		transaction.put_in(&db, Some(2), &H256::from(2), b"dog").unwrap();
+		db.write(transaction).unwrap();
+		assert_eq!(db.iter_column(Some(2)).count(), 1);
+		assert_eq!(db.iter_column(Some(1)).count(), 0);
+		assert_eq!(db.iter_column(None).count(), 1);
+	}
+
+	#[test]
+	fn tuned_kvdb() {
+		let config = DatabaseConfig {
+			prefix_size: None,
+			cache_size: Some(64),
+			compaction: CompactionProfile::HDD,
+			wal: false,
+			columns: None,
+		};
+		test_db(&config);
+
+		let path = RandomTempPath::create_dir();
+		let db = Database::open(&config, path.as_path().to_str().unwrap()).unwrap();
+		assert_eq!(db.config(), &config);
 	}
 }
 
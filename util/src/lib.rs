@@ -154,6 +154,7 @@ pub mod panics;
 pub mod keys;
 pub mod table;
 pub mod network_settings;
+pub mod server_pool;
 pub mod path;
 
 pub use common::*;
@@ -20,15 +20,125 @@ use std::collections::BTreeMap;
 use kvdb::{Database, DBTransaction};
 use migration::{Destination, Error};
 
-impl Destination for Database {
+/// Single column of a database as migration destination.
+pub struct DatabaseColumn<'a> {
+	db: &'a Database,
+	column: Option<u32>,
+}
+
+impl<'a> DatabaseColumn<'a> {
+	/// Creates destination writing to column `column` of `db` (`None` for the default column).
+	pub fn new(db: &'a Database, column: Option<u32>) -> Self {
+		DatabaseColumn {
+			db: db,
+			column: column,
+		}
+	}
+}
+
+impl<'a> Destination for DatabaseColumn<'a> {
 	fn commit(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>) -> Result<(), Error> {
 		let transaction = DBTransaction::new();
 
 		for keypair in &batch {
-			try!(transaction.put(&keypair.0, &keypair.1).map_err(Error::Custom))
+			try!(transaction.put_in(self.db, self.column, &keypair.0, &keypair.1).map_err(Error::Custom))
+		}
+
+		self.db.write(transaction).map_err(Error::Custom)
+	}
+
+	fn commit_chunked(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>, chunk_size: usize, progress: &mut FnMut(usize)) -> Result<(), Error> {
+		let mut committed = 0;
+		let mut transaction = DBTransaction::new();
+		let mut pending = 0;
+
+		for (key, value) in &batch {
+			try!(transaction.put_in(self.db, self.column, key, value).map_err(|e| Error::PartialCommit(committed, e)));
+			pending += 1;
+			if pending == chunk_size {
+				try!(self.db.write(transaction).map_err(|e| Error::PartialCommit(committed, e)));
+				committed += pending;
+				progress(committed);
+				transaction = DBTransaction::new();
+				pending = 0;
+			}
+		}
+
+		if pending > 0 {
+			try!(self.db.write(transaction).map_err(|e| Error::PartialCommit(committed, e)));
+			progress(committed + pending);
+		}
+		Ok(())
+	}
+}
+
+impl Destination for Database {
+	fn commit(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>) -> Result<(), Error> {
+		DatabaseColumn::new(self, None).commit(batch)
+	}
+
+	fn commit_chunked(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>, chunk_size: usize, progress: &mut FnMut(usize)) -> Result<(), Error> {
+		DatabaseColumn::new(self, None).commit_chunked(batch, chunk_size, progress)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+	use devtools::RandomTempPath;
+	use kvdb::Database;
+	use migration::{Destination, Manager, Config, Migration};
+
+	struct AppendMigration;
+
+	impl Migration for AppendMigration {
+		fn version(&self) -> u32 {
+			1
+		}
+
+		fn simple_migrate(&self, key: Vec<u8>, mut value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
+			value.push(0xff);
+			Some((key, value))
 		}
+	}
+
+	fn key(i: u32) -> Vec<u8> {
+		vec![(i >> 24) as u8, (i >> 16) as u8, (i >> 8) as u8, i as u8]
+	}
+
+	#[test]
+	fn commits_batch_in_chunks() {
+		// given
+		let path = RandomTempPath::create_dir();
+		let mut db = Database::open_default(path.as_path().to_str().unwrap()).unwrap();
+		let batch = (0..10).map(|i| (key(i), vec![])).collect::<BTreeMap<_, _>>();
+		let mut reported = vec![];
+
+		// when
+		db.commit_chunked(batch, 4, &mut |done| reported.push(done)).unwrap();
+
+		// then
+		assert_eq!(reported, vec![4, 8, 10]);
+		assert_eq!(db.iter().count(), 10);
+	}
+
+	#[test]
+	fn migrates_database_in_chunks() {
+		// given
+		let path = RandomTempPath::create_dir();
+		let mut db = Database::open_default(path.as_path().to_str().unwrap()).unwrap();
+		let source = (0..100_000).map(|i| (key(i), vec![1u8]));
+		let mut manager = Manager::new(Config { batch_size: 40_000, chunk_size: 16 * 1024 });
+		manager.add_migration(AppendMigration).unwrap();
+		let mut reported = vec![];
+
+		// when
+		manager.execute_entries_with_progress(source, 0, &mut db, &mut |done| reported.push(done)).unwrap();
 
-		self.write(transaction).map_err(Error::Custom)
+		// then
+		assert_eq!(reported, vec![16_384, 32_768, 40_000, 56_384, 72_768, 80_000, 96_384, 100_000]);
+		assert_eq!(db.iter().count(), 100_000);
+		assert_eq!(db.get(&key(99_999)).unwrap().unwrap().to_vec(), vec![1u8, 0xff]);
 	}
 }
 
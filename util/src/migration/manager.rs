@@ -16,8 +16,12 @@
 
 //! Migration manager
 
+use std::{fs, io};
 use std::collections::BTreeMap;
-use migration::{Migration, Destination};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use kvdb::{Database, DatabaseConfig};
+use migration::{Migration, Destination, DatabaseColumn, Manifest};
 
 /// Migration error.
 #[derive(Debug)]
@@ -28,18 +32,31 @@ pub enum Error {
 	MigrationImpossible,
 	/// Custom error.
 	Custom(String),
+	/// Commit failed (with given error) after given number of entries had already been committed.
+	PartialCommit(usize, String),
+	/// Filesystem error.
+	Io(io::Error),
+}
+
+impl From<io::Error> for Error {
+	fn from(err: io::Error) -> Self {
+		Error::Io(err)
+	}
 }
 
 /// Migration config.
 pub struct Config {
 	/// Defines how many elements should be migrated at once.
 	pub batch_size: usize,
+	/// Maximal number of elements committed to destination in single transaction.
+	pub chunk_size: usize,
 }
 
 impl Default for Config {
 	fn default() -> Self {
 		Config {
-			batch_size: 1024,
+			batch_size: 64 * 1024,
+			chunk_size: 32 * 1024,
 		}
 	}
 }
@@ -72,8 +89,102 @@ impl Manager {
 		}
 	}
 
-	/// Performs migration to destination.
-	pub fn execute<D>(&self, db_iter: D, version: u32, destination: &mut Destination) -> Result<(), Error> where
+	/// Migrates database at `db_path` to `target_version`, one migration at a time.
+	///
+	/// Each migration writes into a temporary database next to the original one, which replaces the original
+	/// (together with its manifest) once the migration is complete. Leftovers of an interrupted migration
+	/// (see `is_interrupted`) are cleaned up and the migration is restarted.
+	/// The temporary database is created with columns of the migration (see `Migration::columns`),
+	/// entries of every column are migrated into the same column of the temporary database.
+	pub fn execute(&self, db_path: &Path, target_version: u32) -> Result<(), Error> {
+		self.execute_with_progress(db_path, target_version, &mut |_| {})
+	}
+
+	/// Migrates database at `db_path` to `target_version` (see `execute`).
+	/// `progress` is called with number of entries committed so far by the migration which is currently running.
+	pub fn execute_with_progress(&self, db_path: &Path, target_version: u32, progress: &mut FnMut(usize)) -> Result<(), Error> {
+		try!(Self::recover(db_path));
+		if !db_path.exists() {
+			return Ok(());
+		}
+
+		let manifest = try!(Manifest::load(db_path));
+		if manifest.version == target_version {
+			return Ok(());
+		}
+		let migrations = try!(self.migrations_from(manifest.version).ok_or(Error::MigrationImpossible));
+		let migrations = match migrations.iter().position(|m| m.version() == target_version) {
+			Some(last) => &migrations[..last + 1],
+			None => return Err(Error::MigrationImpossible),
+		};
+
+		let temp_path = sibling_path(db_path, ".migration");
+		let mut columns = manifest.columns;
+		for (i, migration) in migrations.iter().enumerate() {
+			trace!(target: "migration", "Migrating {:?} to version {}", db_path, migration.version());
+			{
+				let source_config = DatabaseConfig::default().columns(columns);
+				let destination_config = DatabaseConfig::default().columns(migration.columns());
+				let source = try!(Database::open(&source_config, try!(path_str(db_path))).map_err(Error::Custom));
+				let destination = try!(Database::open(&destination_config, try!(path_str(&temp_path))).map_err(Error::Custom));
+				let mut done = 0;
+				for column in column_ids(columns) {
+					if column.map_or(false, |c| c >= migration.columns().unwrap_or(0)) {
+						return Err(Error::Custom(format!("Migration to version {} drops column {:?}", migration.version(), column)));
+					}
+					let entries = source.iter_column(column).map(|(key, value)| (key.into_vec(), value.into_vec()));
+					let offset = done;
+					done += try!(self.migrate(&migrations[i..i + 1], entries, &mut DatabaseColumn::new(&destination, column), &mut |n| progress(offset + n)));
+				}
+			}
+			columns = migration.columns();
+			try!(Manifest { version: migration.version(), columns: columns }.save(&temp_path));
+			try!(Self::replace(db_path, &temp_path));
+		}
+		Ok(())
+	}
+
+	/// Returns true if migration of database at `db_path` has been interrupted.
+	pub fn is_interrupted(db_path: &Path) -> bool {
+		sibling_path(db_path, ".migration").exists() || sibling_path(db_path, ".backup").exists()
+	}
+
+	/// Restores database at `db_path` to consistent state after an interrupted migration.
+	fn recover(db_path: &Path) -> Result<(), Error> {
+		let backup_path = sibling_path(db_path, ".backup");
+		let temp_path = sibling_path(db_path, ".migration");
+		if backup_path.exists() {
+			match db_path.exists() {
+				// interrupted after the migrated database had replaced the original one
+				true => try!(fs::remove_dir_all(&backup_path)),
+				false => try!(fs::rename(&backup_path, db_path)),
+			}
+		}
+		if temp_path.exists() {
+			warn!(target: "migration", "Restarting interrupted migration of {:?}", db_path);
+			try!(fs::remove_dir_all(&temp_path));
+		}
+		Ok(())
+	}
+
+	/// Replaces database at `db_path` with the migrated one.
+	fn replace(db_path: &Path, migrated_path: &Path) -> Result<(), Error> {
+		let backup_path = sibling_path(db_path, ".backup");
+		try!(fs::rename(db_path, &backup_path));
+		try!(fs::rename(migrated_path, db_path));
+		try!(fs::remove_dir_all(&backup_path));
+		Ok(())
+	}
+
+	/// Performs migration of given entries to destination.
+	pub fn execute_entries<D>(&self, db_iter: D, version: u32, destination: &mut Destination) -> Result<(), Error> where
+		D: Iterator<Item = (Vec<u8>, Vec<u8>)> {
+		self.execute_entries_with_progress(db_iter, version, destination, &mut |_| {})
+	}
+
+	/// Performs migration of given entries to destination. `progress` is called with total number of entries committed so far.
+	/// On `Error::PartialCommit` the number of entries committed before the failure is returned.
+	pub fn execute_entries_with_progress<D>(&self, db_iter: D, version: u32, destination: &mut Destination, progress: &mut FnMut(usize)) -> Result<(), Error> where
 		D: Iterator<Item = (Vec<u8>, Vec<u8>)> {
 
 		if self.is_latest_version(version) {
@@ -81,8 +192,14 @@ impl Manager {
 		}
 
 		let migrations = try!(self.migrations_from(version).ok_or(Error::MigrationImpossible));
+		self.migrate(migrations, db_iter, destination, progress).map(|_| ())
+	}
 
+	/// Migrates given entries to destination, returns number of committed entries.
+	fn migrate<D>(&self, migrations: &[Box<Migration>], db_iter: D, destination: &mut Destination, progress: &mut FnMut(usize)) -> Result<usize, Error> where
+		D: Iterator<Item = (Vec<u8>, Vec<u8>)> {
 		let mut batch: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+		let mut committed = 0;
 
 		for keypair in db_iter {
 			let migrated = migrations.iter().fold(Some(keypair), |migrated, migration| {
@@ -94,14 +211,23 @@ impl Manager {
 			}
 
 			if batch.len() == self.config.batch_size {
-				try!(destination.commit(batch));
+				committed = try!(self.commit(destination, batch, committed, progress));
 				batch = BTreeMap::new();
 			}
 		}
 
-		try!(destination.commit(batch));
+		self.commit(destination, batch, committed, progress)
+	}
 
-		Ok(())
+	/// Commits the batch, returns total number of committed entries.
+	fn commit(&self, destination: &mut Destination, batch: BTreeMap<Vec<u8>, Vec<u8>>, committed: usize, progress: &mut FnMut(usize)) -> Result<usize, Error> {
+		let len = batch.len();
+		try!(destination.commit_chunked(batch, self.config.chunk_size, &mut |done| progress(committed + done)).map_err(|e| match e {
+			Error::PartialCommit(done, e) => Error::PartialCommit(committed + done, e),
+			Error::Custom(e) => Error::PartialCommit(committed, e),
+			e => e,
+		}));
+		Ok(committed + len)
 	}
 
 	/// Returns true if given string is equal to latest known version.
@@ -119,3 +245,20 @@ impl Manager {
 	}
 }
 
+/// Returns ids of all columns of a database with `columns` columns, starting with the default column.
+fn column_ids(columns: Option<u32>) -> Vec<Option<u32>> {
+	let mut ids = vec![None];
+	ids.extend((0..columns.unwrap_or(0)).map(Some));
+	ids
+}
+
+/// Returns path of a directory next to `path` with `suffix` appended to its name.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+	let mut name = path.file_name().map_or_else(OsString::new, |name| name.to_owned());
+	name.push(suffix);
+	path.with_file_name(name)
+}
+
+fn path_str(path: &Path) -> Result<&str, Error> {
+	path.to_str().ok_or_else(|| Error::Custom(format!("Invalid database path: {:?}", path)))
+}
@@ -0,0 +1,81 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database manifest: version and column configuration of the database.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use rustc_serialize::json::Json;
+use migration::Error;
+
+/// Name of the manifest file stored in the database directory.
+const MANIFEST_FILE: &'static str = "manifest.json";
+
+/// Version and column configuration of the database.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Manifest {
+	/// Database version.
+	pub version: u32,
+	/// Number of columns. `None` for a database without columns.
+	pub columns: Option<u32>,
+}
+
+impl Manifest {
+	/// Loads manifest of the database at `db_path`. Databases without manifest have version 0 and no columns.
+	pub fn load(db_path: &Path) -> Result<Manifest, Error> {
+		let mut file = match fs::File::open(db_path.join(MANIFEST_FILE)) {
+			Ok(file) => file,
+			Err(_) => return Ok(Manifest::default()),
+		};
+		let mut buf = String::new();
+		try!(file.read_to_string(&mut buf));
+		let json = try!(Json::from_str(&buf).map_err(|e| Error::Custom(format!("Invalid manifest: {:?}", e))));
+		let version = try!(json.find("version").and_then(Json::as_u64).ok_or_else(|| Error::Custom("Manifest without version".into())));
+		Ok(Manifest {
+			version: version as u32,
+			columns: json.find("columns").and_then(Json::as_u64).map(|c| c as u32),
+		})
+	}
+
+	/// Stores the manifest in the database directory at `db_path`.
+	pub fn save(&self, db_path: &Path) -> Result<(), Error> {
+		let columns = self.columns.map_or_else(|| "null".to_owned(), |c| c.to_string());
+		let mut file = try!(fs::File::create(db_path.join(MANIFEST_FILE)));
+		try!(file.write_all(format!("{{ \"version\": {}, \"columns\": {} }}\n", self.version, columns).as_bytes()));
+		try!(file.sync_all());
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use devtools::RandomTempPath;
+	use super::Manifest;
+
+	#[test]
+	fn manifest_save_load() {
+		let path = RandomTempPath::create_dir();
+		assert_eq!(Manifest::load(path.as_path()).unwrap(), Manifest { version: 0, columns: None });
+
+		let manifest = Manifest { version: 3, columns: Some(4) };
+		manifest.save(path.as_path()).unwrap();
+		assert_eq!(Manifest::load(path.as_path()).unwrap(), manifest);
+
+		Manifest { version: 4, columns: None }.save(path.as_path()).unwrap();
+		assert_eq!(Manifest::load(path.as_path()).unwrap(), Manifest { version: 4, columns: None });
+	}
+}
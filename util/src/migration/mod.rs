@@ -18,17 +18,22 @@
 
 mod db_impl;
 mod manager;
+mod manifest;
 
 #[cfg(test)]
 mod tests;
 
 pub use self::manager::{Error, Config, Manager};
+pub use self::db_impl::DatabaseColumn;
+pub use self::manifest::Manifest;
 use std::collections::BTreeMap;
 
 /// Single migration.
 pub trait Migration: 'static {
 	/// Version of database after the migration.
 	fn version(&self) -> u32;
+	/// Number of columns of database after the migration. `None` if the database has no columns.
+	fn columns(&self) -> Option<u32> { None }
 	/// Should migrate existing object to new database.
 	/// Returns `None` if the object does not exist in new version of database.
 	fn simple_migrate(&self, key: Vec<u8>, value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)>;
@@ -38,4 +43,14 @@ pub trait Migration: 'static {
 pub trait Destination {
 	/// Called on destination to commit batch of migrated entries.
 	fn commit(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>) -> Result<(), Error>;
+
+	/// Commits batch of migrated entries in chunks of at most `chunk_size` entries.
+	/// `progress` is called with number of entries of the batch committed so far after each chunk.
+	/// Fails with `Error::PartialCommit` if some of the chunks have already been committed.
+	fn commit_chunked(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>, _chunk_size: usize, progress: &mut FnMut(usize)) -> Result<(), Error> {
+		let len = batch.len();
+		try!(self.commit(batch));
+		progress(len);
+		Ok(())
+	}
 }
@@ -15,7 +15,10 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::BTreeMap;
-use migration::{Error, Destination, Migration, Manager, Config};
+use std::path::Path;
+use devtools::RandomTempPath;
+use kvdb::{Database, DatabaseConfig, DBTransaction};
+use migration::{Error, Destination, Migration, Manager, Config, Manifest};
 
 impl Destination for BTreeMap<Vec<u8>, Vec<u8>> {
 	fn commit(&mut self, batch: BTreeMap<Vec<u8>, Vec<u8>>) -> Result<(), Error> {
@@ -47,11 +50,32 @@ impl Migration for Migration1 {
 		2
 	}
 
+	fn columns(&self) -> Option<u32> {
+		Some(4)
+	}
+
 	fn simple_migrate(&self, key: Vec<u8>, _value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
 		Some((key, vec![]))
 	}
 }
 
+struct Migration2;
+
+impl Migration for Migration2 {
+	fn version(&self) -> u32 {
+		3
+	}
+
+	fn columns(&self) -> Option<u32> {
+		Some(4)
+	}
+
+	fn simple_migrate(&self, key: Vec<u8>, mut value: Vec<u8>) -> Option<(Vec<u8>, Vec<u8>)> {
+		value.push(0x33);
+		Some((key, value))
+	}
+}
+
 #[test]
 fn one_simple_migration() {
 	let mut manager = Manager::new(Config::default());
@@ -65,7 +89,7 @@ fn one_simple_migration() {
 
 	let mut result = BTreeMap::new();
 	manager.add_migration(Migration0).unwrap();
-	manager.execute(db, 0, &mut result).unwrap();
+	manager.execute_entries(db, 0, &mut result).unwrap();
 	assert_eq!(expected_db, result);
 }
 
@@ -78,7 +102,7 @@ fn no_migration_needed() {
 
 	let mut result = BTreeMap::new();
 	manager.add_migration(Migration0).unwrap();
-	manager.execute(db, 1, &mut result).unwrap();
+	manager.execute_entries(db, 1, &mut result).unwrap();
 	assert!(result.is_empty());
 }
 
@@ -96,7 +120,7 @@ fn multiple_migrations() {
 	let mut result = BTreeMap::new();
 	manager.add_migration(Migration0).unwrap();
 	manager.add_migration(Migration1).unwrap();
-	manager.execute(db, 0, &mut result).unwrap();
+	manager.execute_entries(db, 0, &mut result).unwrap();
 	assert_eq!(expected_db, result);
 }
 
@@ -114,6 +138,133 @@ fn second_migration() {
 	let mut result = BTreeMap::new();
 	manager.add_migration(Migration0).unwrap();
 	manager.add_migration(Migration1).unwrap();
-	manager.execute(db, 1, &mut result).unwrap();
+	manager.execute_entries(db, 1, &mut result).unwrap();
 	assert_eq!(expected_db, result);
 }
+
+fn create_db(path: &Path, entries: &[(&[u8], &[u8])]) {
+	let db = Database::open_default(path.to_str().unwrap()).unwrap();
+	for &(key, value) in entries {
+		db.put(key, value).unwrap();
+	}
+}
+
+fn db_contents(path: &Path) -> BTreeMap<Vec<u8>, Vec<u8>> {
+	let config = DatabaseConfig::default().columns(Manifest::load(path).unwrap().columns);
+	let db = Database::open(&config, path.to_str().unwrap()).unwrap();
+	let contents = db.iter().map(|(key, value)| (key.into_vec(), value.into_vec())).collect();
+	contents
+}
+
+#[test]
+fn chained_migrations_on_disk() {
+	let dir = RandomTempPath::create_dir();
+	let db_path = dir.as_path().join("db");
+	create_db(&db_path, &[(&[1u8], &[1u8]), (&[2u8], &[2u8])]);
+
+	let expected_keys = vec![vec![1u8, 0x11], vec![2, 0x11]];
+	let expected_values = vec![vec![], vec![]];
+	let expected_db = expected_keys.into_iter().zip(expected_values.into_iter()).collect::<BTreeMap<_, _>>();
+
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(Migration0).unwrap();
+	manager.add_migration(Migration1).unwrap();
+	manager.execute(&db_path, 2).unwrap();
+
+	assert_eq!(Manifest::load(&db_path).unwrap(), Manifest { version: 2, columns: Some(4) });
+	assert_eq!(db_contents(&db_path), expected_db);
+	assert!(!Manager::is_interrupted(&db_path));
+
+	// already migrated
+	manager.execute(&db_path, 2).unwrap();
+	assert_eq!(db_contents(&db_path), expected_db);
+}
+
+#[test]
+fn migrates_database_on_disk_in_chunks() {
+	// given
+	let dir = RandomTempPath::create_dir();
+	let db_path = dir.as_path().join("db");
+	let entries = (0..7u8).map(|i| ([i], [i])).collect::<Vec<_>>();
+	create_db(&db_path, &entries.iter().map(|&(ref key, ref value)| (&key[..], &value[..])).collect::<Vec<_>>());
+	let mut manager = Manager::new(Config { batch_size: 5, chunk_size: 2 });
+	manager.add_migration(Migration0).unwrap();
+	let mut reported = vec![];
+
+	// when
+	manager.execute_with_progress(&db_path, 1, &mut |done| reported.push(done)).unwrap();
+
+	// then
+	assert_eq!(reported, vec![2, 4, 5, 7]);
+	let expected_db = (0..7u8).map(|i| (vec![i, 0x11], vec![i, 0x22])).collect::<BTreeMap<_, _>>();
+	assert_eq!(db_contents(&db_path), expected_db);
+}
+
+#[test]
+fn migrates_every_column() {
+	// given
+	let dir = RandomTempPath::create_dir();
+	let db_path = dir.as_path().join("db");
+	{
+		let config = DatabaseConfig::default().columns(Some(4));
+		let db = Database::open(&config, db_path.to_str().unwrap()).unwrap();
+		let transaction = DBTransaction::new();
+		transaction.put_in(&db, None, &[1u8], &[1u8]).unwrap();
+		transaction.put_in(&db, Some(1), &[2u8], &[2u8]).unwrap();
+		transaction.put_in(&db, Some(3), &[3u8], &[3u8]).unwrap();
+		db.write(transaction).unwrap();
+	}
+	Manifest { version: 2, columns: Some(4) }.save(&db_path).unwrap();
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(Migration0).unwrap();
+	manager.add_migration(Migration1).unwrap();
+	manager.add_migration(Migration2).unwrap();
+
+	// when
+	manager.execute(&db_path, 3).unwrap();
+
+	// then
+	let config = DatabaseConfig::default().columns(Some(4));
+	let db = Database::open(&config, db_path.to_str().unwrap()).unwrap();
+	let column = |col| db.iter_column(col).map(|(key, value)| (key.into_vec(), value.into_vec())).collect::<Vec<_>>();
+	assert_eq!(column(None), vec![(vec![1u8], vec![1u8, 0x33])]);
+	assert_eq!(column(Some(0)), vec![]);
+	assert_eq!(column(Some(1)), vec![(vec![2u8], vec![2u8, 0x33])]);
+	assert_eq!(column(Some(3)), vec![(vec![3u8], vec![3u8, 0x33])]);
+}
+
+#[test]
+fn restarts_interrupted_migration() {
+	let dir = RandomTempPath::create_dir();
+	let db_path = dir.as_path().join("db");
+	let leftover_path = dir.as_path().join("db.migration");
+	create_db(&db_path, &[(&[1u8], &[1u8])]);
+	Manifest { version: 1, columns: None }.save(&db_path).unwrap();
+	create_db(&leftover_path, &[(&[9u8], &[9u8])]);
+	assert!(Manager::is_interrupted(&db_path));
+
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(Migration0).unwrap();
+	manager.add_migration(Migration1).unwrap();
+	manager.execute(&db_path, 2).unwrap();
+
+	assert!(!Manager::is_interrupted(&db_path));
+	assert!(!leftover_path.exists());
+	assert_eq!(Manifest::load(&db_path).unwrap(), Manifest { version: 2, columns: Some(4) });
+	assert_eq!(db_contents(&db_path), vec![(vec![1u8], vec![])].into_iter().collect());
+}
+
+#[test]
+fn migration_to_unknown_version_is_impossible() {
+	let dir = RandomTempPath::create_dir();
+	let db_path = dir.as_path().join("db");
+	create_db(&db_path, &[(&[1u8], &[1u8])]);
+
+	let mut manager = Manager::new(Config::default());
+	manager.add_migration(Migration0).unwrap();
+	match manager.execute(&db_path, 2) {
+		Err(Error::MigrationImpossible) => {},
+		other => panic!("Unexpected result: {:?}", other),
+	}
+	assert_eq!(Manifest::load(&db_path).unwrap().version, 0);
+}
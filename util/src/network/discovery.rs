@@ -15,9 +15,11 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use bytes::Bytes;
-use std::net::SocketAddr;
+use std::net::{SocketAddr, IpAddr};
 use std::collections::{HashSet, HashMap, BTreeMap, VecDeque};
 use std::mem;
+use std::cmp;
+use std::sync::Arc;
 use std::default::Default;
 use mio::*;
 use mio::udp::*;
@@ -28,6 +30,7 @@ use crypto::*;
 use rlp::*;
 use network::node_table::*;
 use network::error::NetworkError;
+use network::stats::NetworkStats;
 use io::StreamToken;
 
 use network::PROTOCOL_VERSION;
@@ -47,6 +50,11 @@ const PACKET_NEIGHBOURS: u8 = 4;
 
 const PING_TIMEOUT_MS: u64 = 300;
 
+/// Default number of packets per second accepted from a single IP address.
+pub const DEFAULT_PACKET_RATE: u32 = 50;
+/// Default number of packets accepted from a single IP address in a burst.
+pub const DEFAULT_PACKET_BURST: u32 = 100;
+
 #[derive(Clone, Debug)]
 pub struct NodeEntry {
 	pub id: NodeId,
@@ -60,6 +68,8 @@ pub struct BucketEntry {
 
 struct NodeBucket {
 	nodes: VecDeque<BucketEntry>, //sorted by last active
+	/// Nodes waiting to replace the least active node once it fails to answer a ping.
+	pending: VecDeque<NodeEntry>,
 }
 
 impl Default for NodeBucket {
@@ -71,7 +81,29 @@ impl Default for NodeBucket {
 impl NodeBucket {
 	fn new() -> Self {
 		NodeBucket {
-			nodes: VecDeque::new()
+			nodes: VecDeque::new(),
+			pending: VecDeque::new(),
+		}
+	}
+}
+
+/// Token bucket limiting packets received from a single IP address.
+struct PacketAllowance {
+	tokens: f64,
+	updated: u64,
+}
+
+impl PacketAllowance {
+	/// Refills the tokens at `rate` per second (up to `burst`) and takes one if available.
+	fn take(&mut self, now: u64, rate: u32, burst: u32) -> bool {
+		let elapsed = now.saturating_sub(self.updated) as f64 / 1_000_000_000f64;
+		self.tokens = (self.tokens + elapsed * rate as f64).min(burst as f64);
+		self.updated = now;
+		if self.tokens >= 1f64 {
+			self.tokens -= 1f64;
+			true
+		} else {
+			false
 		}
 	}
 }
@@ -93,6 +125,13 @@ pub struct Discovery {
 	node_buckets: Vec<NodeBucket>,
 	send_queue: VecDeque<Datagramm>,
 	check_timestamps: bool,
+	/// Packets per second and burst size accepted from a single IP address. Zero rate disables the limit.
+	packet_rate: u32,
+	packet_burst: u32,
+	allowances: HashMap<IpAddr, PacketAllowance>,
+	stats: Arc<NetworkStats>,
+	/// Returns current time in nanoseconds.
+	clock: Box<Fn() -> u64 + Send>,
 }
 
 pub struct TableUpdates {
@@ -115,9 +154,28 @@ impl Discovery {
 			udp_socket: socket,
 			send_queue: VecDeque::new(),
 			check_timestamps: true,
+			packet_rate: DEFAULT_PACKET_RATE,
+			packet_burst: DEFAULT_PACKET_BURST,
+			allowances: HashMap::new(),
+			stats: Arc::new(NetworkStats::default()),
+			clock: Box::new(time::precise_time_ns),
 		}
 	}
 
+	/// Limits packets accepted from a single IP address to `rate` per second with bursts of `burst` packets.
+	/// Excess packets are dropped. Zero `rate` disables the limit.
+	pub fn with_rate_limit(mut self, rate: u32, burst: u32) -> Self {
+		self.packet_rate = rate;
+		self.packet_burst = cmp::max(burst, 1);
+		self
+	}
+
+	/// Reports dropped packets to given statistics.
+	pub fn with_stats(mut self, stats: Arc<NetworkStats>) -> Self {
+		self.stats = stats;
+		self
+	}
+
 	/// Add a new node to discovery table. Pings the node.
 	pub fn add_node(&mut self, e: NodeEntry) {
 		let endpoint = e.endpoint.clone();
@@ -125,6 +183,22 @@ impl Discovery {
 		self.ping(&endpoint);
 	}
 
+	/// Add a node to the table after discovery has started. Pings the node and restarts
+	/// the lookup, so the node is queried immediately.
+	pub fn seed_node(&mut self, e: NodeEntry) {
+		self.add_node(e);
+		self.start();
+		self.discover();
+	}
+
+	/// Remove a node from the table. Returns `false` if the node is not known.
+	pub fn remove_node(&mut self, id: &NodeId) -> bool {
+		let bucket = self.node_buckets.get_mut(Discovery::distance(&self.id, id) as usize).unwrap();
+		let len = bucket.nodes.len();
+		bucket.nodes.retain(|n| &n.address.id != id);
+		bucket.nodes.len() != len
+	}
+
 	/// Add a list of known nodes to the table.
 	pub fn init_node_list(&mut self, mut nodes: Vec<NodeEntry>) {
 		for n in nodes.drain(..) {
@@ -132,8 +206,11 @@ impl Discovery {
 		}
 	}
 
+	/// Inserts or updates a node. If the bucket is full the new node is kept pending and
+	/// the least active node is pinged; the new node replaces it only if it doesn't answer.
 	fn update_node(&mut self, e: NodeEntry) {
 		trace!(target: "discovery", "Inserting {:?}", &e);
+		let now = (self.clock)();
 		let ping = {
 			let mut bucket = self.node_buckets.get_mut(Discovery::distance(&self.id, &e.id) as usize).unwrap();
 			let updated = if let Some(node) = bucket.nodes.iter_mut().find(|n| n.address.id == e.id) {
@@ -142,25 +219,81 @@ impl Discovery {
 				true
 			} else { false };
 
-			if !updated {
+			if updated {
+				None
+			} else if bucket.nodes.len() < BUCKET_SIZE {
 				bucket.nodes.push_front(BucketEntry { address: e, timeout: None });
+				None
+			} else {
+				if !bucket.pending.iter().any(|n| n.id == e.id) {
+					if bucket.pending.len() >= BUCKET_SIZE {
+						bucket.pending.pop_front();
+					}
+					bucket.pending.push_back(e);
+				}
+				Discovery::ping_least_active(bucket, now)
 			}
-
-			if bucket.nodes.len() > BUCKET_SIZE {
-				//ping least active node
-				bucket.nodes.back_mut().unwrap().timeout = Some(time::precise_time_ns());
-				Some(bucket.nodes.back().unwrap().address.endpoint.clone())
-			} else { None }
 		};
 		if let Some(endpoint) = ping {
 			self.ping(&endpoint);
 		}
 	}
 
+	/// Marks the least active node of the bucket as being pinged, unless it already is.
+	/// Returns the endpoint to ping.
+	fn ping_least_active(bucket: &mut NodeBucket, now: u64) -> Option<NodeEndpoint> {
+		match bucket.nodes.back_mut() {
+			Some(node) => if node.timeout.is_none() {
+				node.timeout = Some(now);
+				Some(node.address.endpoint.clone())
+			} else { None },
+			None => None,
+		}
+	}
+
 	fn clear_ping(&mut self, id: &NodeId) {
-		let mut bucket = self.node_buckets.get_mut(Discovery::distance(&self.id, &id) as usize).unwrap();
-		if let Some(node) = bucket.nodes.iter_mut().find(|n| &n.address.id == id) {
-			node.timeout = None;
+		let now = (self.clock)();
+		let ping = {
+			let mut bucket = self.node_buckets.get_mut(Discovery::distance(&self.id, &id) as usize).unwrap();
+			let position = bucket.nodes.iter().position(|n| &n.address.id == id);
+			match position.and_then(|i| bucket.nodes.remove(i)) {
+				Some(mut node) => {
+					let was_pinged = node.timeout.take().is_some();
+					bucket.nodes.push_front(node);
+					if was_pinged && bucket.pending.pop_front().is_some() && !bucket.pending.is_empty() {
+						// the node is alive, so the replacement is discarded; try the next least active one
+						Discovery::ping_least_active(bucket, now)
+					} else { None }
+				},
+				None => None,
+			}
+		};
+		if let Some(endpoint) = ping {
+			self.ping(&endpoint);
+		}
+	}
+
+	/// Returns `false` if packet from `from` exceeds the rate limit and should be dropped.
+	fn accept_packet(&mut self, from: &SocketAddr) -> bool {
+		if self.packet_rate == 0 {
+			return true;
+		}
+		let now = (self.clock)();
+		let burst = self.packet_burst;
+		let allowance = self.allowances.entry(from.ip()).or_insert_with(|| PacketAllowance { tokens: burst as f64, updated: now });
+		allowance.take(now, self.packet_rate, burst)
+	}
+
+	/// Forgets IP addresses whose allowance has been fully replenished.
+	fn prune_allowances(&mut self) {
+		let now = (self.clock)();
+		let (rate, burst) = (self.packet_rate as f64, self.packet_burst as f64);
+		let full = self.allowances.iter()
+			.filter(|&(_, a)| a.tokens + now.saturating_sub(a.updated) as f64 / 1_000_000_000f64 * rate >= burst)
+			.map(|(ip, _)| ip.clone())
+			.collect::<Vec<_>>();
+		for ip in full {
+			self.allowances.remove(&ip);
 		}
 	}
 
@@ -324,6 +457,12 @@ impl Discovery {
 	}
 
 	fn on_packet(&mut self, packet: &[u8], from: SocketAddr) -> Result<Option<TableUpdates>, NetworkError> {
+		if !self.accept_packet(&from) {
+			trace!(target: "discovery", "Dropping packet from {:?}, rate limit exceeded", &from);
+			self.stats.inc_discovery_dropped();
+			return Ok(None);
+		}
+
 		// validate packet
 		if packet.len() < 32 + 65 + 4 + 1 {
 			return Err(NetworkError::BadProtocol);
@@ -457,8 +596,9 @@ impl Discovery {
 	}
 
 	fn check_expired(&mut self, force: bool) -> HashSet<NodeId> {
-		let now = time::precise_time_ns();
+		let now = (self.clock)();
 		let mut removed: HashSet<NodeId> = HashSet::new();
+		let mut pings = Vec::new();
 		for bucket in &mut self.node_buckets {
 			bucket.nodes.retain(|node| {
 				if let Some(timeout) = node.timeout {
@@ -472,12 +612,26 @@ impl Discovery {
 					}
 				} else { true }
 			});
+			// replace evicted nodes with pending ones
+			while bucket.nodes.len() < BUCKET_SIZE {
+				match bucket.pending.pop_front() {
+					Some(e) => bucket.nodes.push_front(BucketEntry { address: e, timeout: None }),
+					None => break,
+				}
+			}
+			if !bucket.pending.is_empty() {
+				pings.extend(Discovery::ping_least_active(bucket, now));
+			}
+		}
+		for endpoint in pings {
+			self.ping(&endpoint);
 		}
 		removed
 	}
 
 	pub fn round(&mut self) -> Option<TableUpdates> {
 		let removed = self.check_expired(false);
+		self.prune_allowances();
 		self.discover();
 		if !removed.is_empty() {
 			Some(TableUpdates { added: HashMap::new(), removed: removed })
@@ -511,7 +665,33 @@ mod tests {
 	use network::node_table::*;
 	use crypto::KeyPair;
 	use std::str::FromStr;
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::collections::HashMap;
 	use rustc_serialize::hex::FromHex;
+	use super::BUCKET_SIZE;
+
+	/// Makes `discovery` use time (in nanoseconds) returned by the result.
+	fn fake_clock(discovery: &mut Discovery) -> Arc<AtomicUsize> {
+		let now = Arc::new(AtomicUsize::new(1_000_000_000));
+		let clock = now.clone();
+		discovery.clock = Box::new(move || clock.load(Ordering::SeqCst) as u64);
+		now
+	}
+
+	/// Returns index of a bucket and `count` random node ids falling into it.
+	fn bucket_candidates(discovery: &Discovery, count: usize) -> (usize, Vec<NodeId>) {
+		let mut buckets: HashMap<usize, Vec<NodeId>> = HashMap::new();
+		loop {
+			let id = NodeId::random();
+			let index = Discovery::distance(&discovery.id, &id) as usize;
+			let ids = buckets.entry(index).or_insert_with(Vec::new);
+			ids.push(id);
+			if ids.len() == count {
+				return (index, ids.clone());
+			}
+		}
+	}
 
 	#[test]
 	fn find_node() {
@@ -565,6 +745,29 @@ mod tests {
 		assert_eq!(Discovery::nearest_node_entries(&NodeId::new(), &discovery2.node_buckets).len(), 3)
 	}
 
+	#[test]
+	fn seeded_node_is_used_in_lookups() {
+		let key = KeyPair::create().unwrap();
+		let ep = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40448").unwrap(), udp_port: 40448 };
+		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0);
+		discovery.round();
+		assert!(discovery.send_queue.is_empty());
+
+		let node = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@127.0.0.1:7772").unwrap();
+		discovery.seed_node(NodeEntry { id: node.id.clone(), endpoint: node.endpoint.clone() });
+
+		let packets: Vec<u8> = discovery.send_queue.iter()
+			.filter(|d| d.address == node.endpoint.udp_address())
+			.map(|d| d.payload[32 + 65])
+			.collect();
+		assert_eq!(packets, vec![PACKET_PING, PACKET_FIND_NODE]);
+		assert!(discovery.discovery_nodes.contains(&node.id));
+
+		assert!(discovery.remove_node(&node.id));
+		assert!(!discovery.remove_node(&node.id));
+		assert!(Discovery::nearest_node_entries(&NodeId::new(), &discovery.node_buckets).is_empty());
+	}
+
 	#[test]
 	fn removes_expired() {
 		let key = KeyPair::create().unwrap();
@@ -578,6 +781,86 @@ mod tests {
 		assert!(removed > 0);
 	}
 
+	#[test]
+	fn drops_packets_exceeding_rate_limit() {
+		// given
+		let key = KeyPair::create().unwrap();
+		let ep = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40449").unwrap(), udp_port: 40449 };
+		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0).with_rate_limit(1, 2);
+		let now = fake_clock(&mut discovery);
+		let from = SocketAddr::from_str("99.99.99.99:40445").unwrap();
+		let other = SocketAddr::from_str("99.99.99.98:40445").unwrap();
+		let packet = vec![0u8; 10];
+
+		// when
+		let first = discovery.on_packet(&packet, from.clone());
+		let second = discovery.on_packet(&packet, from.clone());
+		let third = discovery.on_packet(&packet, from.clone());
+		let from_other = discovery.on_packet(&packet, other.clone());
+
+		// then
+		// packets which are processed are rejected as malformed, dropped ones are silently ignored
+		assert!(first.is_err());
+		assert!(second.is_err());
+		assert!(third.unwrap().is_none());
+		assert!(from_other.is_err());
+		assert_eq!(discovery.stats.discovery_dropped(), 1);
+
+		// when
+		now.fetch_add(1_000_000_000, Ordering::SeqCst);
+
+		// then
+		assert!(discovery.on_packet(&packet, from.clone()).is_err());
+		assert!(discovery.on_packet(&packet, from.clone()).unwrap().is_none());
+		assert_eq!(discovery.stats.discovery_dropped(), 2);
+	}
+
+	#[test]
+	fn pings_least_active_node_before_replacing_it() {
+		// given
+		let key = KeyPair::create().unwrap();
+		let ep = NodeEndpoint { address: SocketAddr::from_str("127.0.0.1:40450").unwrap(), udp_port: 40450 };
+		let mut discovery = Discovery::new(&key, ep.address.clone(), ep.clone(), 0);
+		let now = fake_clock(&mut discovery);
+		let (bucket, ids) = bucket_candidates(&discovery, BUCKET_SIZE + 1);
+		let entry = |id: &NodeId| NodeEntry { id: id.clone(), endpoint: ep.clone() };
+		for id in &ids[..BUCKET_SIZE] {
+			discovery.update_node(entry(id));
+		}
+		let newcomer = ids[BUCKET_SIZE].clone();
+		let contains = |discovery: &Discovery, id: &NodeId| discovery.node_buckets[bucket].nodes.iter().any(|n| &n.address.id == id);
+
+		// when
+		discovery.update_node(entry(&newcomer));
+
+		// then
+		assert_eq!(discovery.node_buckets[bucket].nodes.len(), BUCKET_SIZE);
+		assert!(!contains(&discovery, &newcomer));
+		assert_eq!(discovery.node_buckets[bucket].nodes.back().unwrap().address.id, ids[0]);
+		assert!(discovery.node_buckets[bucket].nodes.back().unwrap().timeout.is_some());
+
+		// when the least active node answers
+		discovery.clear_ping(&ids[0]);
+
+		// then it's kept and the newcomer is discarded
+		assert!(contains(&discovery, &ids[0]));
+		assert!(!contains(&discovery, &newcomer));
+		assert!(discovery.node_buckets[bucket].pending.is_empty());
+		assert!(discovery.check_expired(false).is_empty());
+
+		// when the least active node doesn't answer
+		discovery.update_node(entry(&newcomer));
+		assert!(discovery.check_expired(false).is_empty());
+		now.fetch_add(4_000_000_000, Ordering::SeqCst);
+		let removed = discovery.check_expired(false);
+
+		// then it's replaced with the newcomer
+		assert_eq!(removed.into_iter().collect::<Vec<_>>(), vec![ids[1].clone()]);
+		assert!(contains(&discovery, &newcomer));
+		assert_eq!(discovery.node_buckets[bucket].nodes.len(), BUCKET_SIZE);
+		assert!(discovery.node_buckets[bucket].pending.is_empty());
+	}
+
 	#[test]
 	fn packets() {
 		let key = KeyPair::create().unwrap();
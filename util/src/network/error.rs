@@ -18,6 +18,9 @@ use io::IoError;
 use crypto::CryptoError;
 use rlp::*;
 use std::fmt;
+use std::error::Error;
+use std::net::SocketAddr;
+use network::host::PeerId;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DisconnectReason
@@ -92,10 +95,41 @@ pub enum NetworkError {
 	Expired,
 	/// Peer not found.
 	PeerNotFound,
+	/// Peer is banned.
+	PeerBanned,
+	/// Peer has no common capabilities or uses incompatible protocol version.
+	UselessPeer,
 	/// Peer is diconnected.
 	Disconnect(DisconnectReason),
 	/// Socket IO error.
 	Io(IoError),
+	/// Listen address is already in use.
+	AddressInUse(SocketAddr),
+	/// Network address can't be resolved.
+	AddressResolve(Option<::std::io::Error>),
+	/// Invalid node id or enode URL.
+	InvalidNodeId,
+}
+
+impl NetworkError {
+	/// Returns reason which should be sent to the peer in disconnect packet when the session fails with this error.
+	/// `None` means no packet should be sent, e.g. because the session is not established, the peer
+	/// has already disconnected or the error is not caused by the peer.
+	pub fn to_disconnect_reason(&self) -> Option<DisconnectReason> {
+		use self::NetworkError::*;
+
+		match *self {
+			BadProtocol => Some(DisconnectReason::BadProtocol),
+			PeerBanned | UselessPeer => Some(DisconnectReason::UselessPeer),
+			Io(_) => Some(DisconnectReason::TCPError),
+			Auth | Expired | PeerNotFound | Disconnect(_) | AddressInUse(_) | AddressResolve(_) | InvalidNodeId => None,
+		}
+	}
+
+	/// Attributes this error to given peer.
+	pub fn with_peer(self, peer_id: PeerId) -> PeerError {
+		PeerError::new(Some(peer_id), self)
+	}
 }
 
 impl fmt::Display for NetworkError {
@@ -107,14 +141,91 @@ impl fmt::Display for NetworkError {
 			BadProtocol => "Bad protocol".into(),
 			Expired => "Expired message".into(),
 			PeerNotFound => "Peer not found".into(),
+			PeerBanned => "Peer is banned".into(),
+			UselessPeer => "Useless peer".into(),
 			Disconnect(ref reason) => format!("Peer disconnected: {}", reason),
 			Io(ref err) => format!("Socket I/O error: {}", err),
+			AddressInUse(ref address) => format!("Address already in use: {}", address),
+			AddressResolve(Some(ref err)) => format!("Failed to resolve network address: {}", err),
+			AddressResolve(None) => "Failed to resolve network address".into(),
+			InvalidNodeId => "Invalid node id".into(),
 		};
 
 		f.write_fmt(format_args!("Network error ({})", msg))
 	}
 }
 
+impl Error for NetworkError {
+	fn description(&self) -> &str {
+		use self::NetworkError::*;
+
+		match *self {
+			Auth => "Authentication failure",
+			BadProtocol => "Bad protocol",
+			Expired => "Expired message",
+			PeerNotFound => "Peer not found",
+			PeerBanned => "Peer is banned",
+			UselessPeer => "Useless peer",
+			Disconnect(_) => "Peer disconnected",
+			Io(_) => "Socket I/O error",
+			AddressInUse(_) => "Address already in use",
+			AddressResolve(_) => "Failed to resolve network address",
+			InvalidNodeId => "Invalid node id",
+		}
+	}
+
+	fn cause(&self) -> Option<&Error> {
+		match *self {
+			NetworkError::AddressResolve(Some(ref err)) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+/// Network error together with the peer which caused it.
+#[derive(Debug)]
+pub struct PeerError {
+	/// Peer which caused the error, if known.
+	pub peer_id: Option<PeerId>,
+	/// The error.
+	pub error: NetworkError,
+}
+
+impl PeerError {
+	/// Creates new error caused by given peer.
+	pub fn new(peer_id: Option<PeerId>, error: NetworkError) -> PeerError {
+		PeerError {
+			peer_id: peer_id,
+			error: error,
+		}
+	}
+}
+
+impl fmt::Display for PeerError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.peer_id {
+			Some(peer) => f.write_fmt(format_args!("{} caused by peer {}", self.error, peer)),
+			None => self.error.fmt(f),
+		}
+	}
+}
+
+impl Error for PeerError {
+	fn description(&self) -> &str {
+		self.error.description()
+	}
+
+	fn cause(&self) -> Option<&Error> {
+		Some(&self.error)
+	}
+}
+
+impl From<NetworkError> for PeerError {
+	fn from(err: NetworkError) -> PeerError {
+		PeerError::new(None, err)
+	}
+}
+
 impl From<DecoderError> for NetworkError {
 	fn from(_err: DecoderError) -> NetworkError {
 		NetworkError::Auth
@@ -152,3 +263,42 @@ fn test_errors() {
 		_ => panic!("Unexpeceted error"),
 	}
 }
+
+#[test]
+fn test_disconnect_reasons() {
+	use std::io;
+	use std::str::FromStr;
+
+	let cases = vec![
+		(NetworkError::Auth, None),
+		(NetworkError::BadProtocol, Some(DisconnectReason::BadProtocol)),
+		(NetworkError::Expired, None),
+		(NetworkError::PeerNotFound, None),
+		(NetworkError::PeerBanned, Some(DisconnectReason::UselessPeer)),
+		(NetworkError::UselessPeer, Some(DisconnectReason::UselessPeer)),
+		(NetworkError::Disconnect(DisconnectReason::TooManyPeers), None),
+		(NetworkError::Io(IoError::Mio(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))), Some(DisconnectReason::TCPError)),
+		(NetworkError::AddressInUse(SocketAddr::from_str("127.0.0.1:30303").unwrap()), None),
+		(NetworkError::AddressResolve(None), None),
+		(NetworkError::InvalidNodeId, None),
+	];
+
+	for (error, reason) in cases {
+		assert_eq!(error.to_disconnect_reason(), reason, "Unexpected reason of {:?}", error);
+	}
+}
+
+#[test]
+fn test_peer_errors() {
+	use std::io;
+
+	let resolve = NetworkError::AddressResolve(Some(io::Error::new(io::ErrorKind::Other, "no such host")));
+	assert_eq!(format!("{}", resolve), "Network error (Failed to resolve network address: no such host)");
+	assert!(resolve.cause().is_some());
+
+	let error = NetworkError::BadProtocol.with_peer(5);
+	assert_eq!(error.peer_id, Some(5));
+	assert_eq!(error.description(), "Bad protocol");
+	assert_eq!(format!("{}", error), "Network error (Bad protocol) caused by peer 5");
+	assert_eq!(format!("{}", PeerError::from(NetworkError::Expired)), "Network error (Expired message)");
+}
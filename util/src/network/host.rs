@@ -15,7 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::net::{SocketAddr};
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet};
 use std::str::{FromStr};
 use std::sync::*;
 use std::ops::*;
@@ -24,6 +24,7 @@ use std::path::{Path, PathBuf};
 use std::io::{Read, Write};
 use std::default::Default;
 use std::fs;
+use std::time::{Duration, Instant};
 use mio::*;
 use mio::tcp::*;
 use hash::*;
@@ -32,14 +33,15 @@ use crypto::*;
 use sha3::Hashable;
 use rlp::*;
 use network::handshake::Handshake;
-use network::session::{Session, SessionData};
+use network::session::{Session, SessionData, PeerInfo};
 use error::*;
 use io::*;
 use network::{NetworkProtocolHandler, PROTOCOL_VERSION};
 use network::node_table::*;
 use network::stats::NetworkStats;
-use network::error::{NetworkError, DisconnectReason};
-use network::discovery::{Discovery, TableUpdates, NodeEntry};
+use network::error::{NetworkError, PeerError, DisconnectReason};
+use network::discovery::{Discovery, TableUpdates, NodeEntry, DEFAULT_PACKET_RATE, DEFAULT_PACKET_BURST};
+use network::reputation::{Reputation, BannedNode};
 use network::ip_utils::{map_external_address, select_public_address};
 
 type Slab<T> = ::slab::Slab<T, usize>;
@@ -73,6 +75,10 @@ pub struct NetworkConfiguration {
 	pub use_secret: Option<Secret>,
 	/// Number of connected peers to maintain
 	pub ideal_peers: u32,
+	/// Discovery packets per second accepted from a single IP address, 0 disables the limit
+	pub discovery_packet_rate: u32,
+	/// Discovery packets accepted from a single IP address in a burst
+	pub discovery_packet_burst: u32,
 }
 
 impl Default for NetworkConfiguration {
@@ -95,6 +101,8 @@ impl NetworkConfiguration {
 			boot_nodes: Vec::new(),
 			use_secret: None,
 			ideal_peers: 25,
+			discovery_packet_rate: DEFAULT_PACKET_RATE,
+			discovery_packet_burst: DEFAULT_PACKET_BURST,
 		}
 	}
 
@@ -305,6 +313,15 @@ impl HostInfo {
 	}
 }
 
+/// Policy for peers which are not reserved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NonReservedPeerMode {
+	/// Accept non-reserved peers up to the peer limit.
+	Accept,
+	/// Connect to reserved peers only.
+	Deny,
+}
+
 type SharedSession = Arc<Mutex<Session>>;
 type SharedHandshake = Arc<Mutex<Handshake>>;
 
@@ -327,6 +344,9 @@ pub struct Host<Message> where Message: Send + Sync + Clone {
 	timer_counter: RwLock<usize>,
 	stats: Arc<NetworkStats>,
 	pinned_nodes: Vec<NodeId>,
+	reserved_nodes: RwLock<HashSet<NodeId>>,
+	non_reserved_mode: RwLock<NonReservedPeerMode>,
+	reputation: RwLock<Reputation>,
 }
 
 impl<Message> Host<Message> where Message: Send + Sync + Clone {
@@ -352,7 +372,10 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		};
 		let path = config.config_path.clone();
 		// Setup the server socket
-		let tcp_listener = try!(TcpListener::bind(&listen_address));
+		let tcp_listener = try!(TcpListener::bind(&listen_address).map_err(|e| match e.kind() {
+			::std::io::ErrorKind::AddrInUse => UtilError::Network(NetworkError::AddressInUse(listen_address)),
+			_ => UtilError::StdIo(e),
+		}));
 		listen_address = SocketAddr::new(listen_address.ip(), try!(tcp_listener.local_addr()).port());
 		let udp_port = config.udp_port.unwrap_or(listen_address.port());
 		let local_endpoint = NodeEndpoint { address: listen_address, udp_port: udp_port };
@@ -378,6 +401,9 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 			timer_counter: RwLock::new(USER_TIMER),
 			stats: Arc::new(NetworkStats::default()),
 			pinned_nodes: Vec::new(),
+			reserved_nodes: RwLock::new(HashSet::new()),
+			non_reserved_mode: RwLock::new(NonReservedPeerMode::Accept),
+			reputation: RwLock::new(Reputation::default()),
 		};
 
 		let boot_nodes = host.info.read().unwrap().config.boot_nodes.clone();
@@ -405,6 +431,96 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		}
 	}
 
+	/// Adds a boot node at runtime. The node is inserted into the discovery table
+	/// and a new lookup round is started right away.
+	pub fn add_boot_node(&self, id: &str) -> Result<(), NetworkError> {
+		let n = try!(Node::from_enode_url(id));
+		let entry = NodeEntry { endpoint: n.endpoint.clone(), id: n.id.clone() };
+		{
+			let mut info = self.info.write().unwrap();
+			let boot_nodes = &mut info.config.boot_nodes;
+			if !boot_nodes.iter().any(|b| b == id) {
+				boot_nodes.push(id.to_owned());
+			}
+		}
+		self.nodes.write().unwrap().add_node(n);
+		if let Some(ref mut discovery) = *self.discovery.lock().unwrap().deref_mut() {
+			discovery.seed_node(entry);
+		}
+		Ok(())
+	}
+
+	/// Removes a boot node from the list, from the node table and from the discovery table.
+	/// Reserved nodes are kept in the node table, so they are still dialed.
+	pub fn remove_boot_node(&self, id: &str) -> Result<(), NetworkError> {
+		let n = try!(Node::from_enode_url(id));
+		self.info.write().unwrap().config.boot_nodes.retain(|b| Node::from_enode_url(b).map_or(true, |b| b.id != n.id));
+		if !self.reserved_nodes.read().unwrap().contains(&n.id) {
+			self.nodes.write().unwrap().remove(&n.id);
+		}
+		if let Some(ref mut discovery) = *self.discovery.lock().unwrap().deref_mut() {
+			discovery.remove_node(&n.id);
+		}
+		Ok(())
+	}
+
+	/// Returns enode URLs of current boot nodes.
+	pub fn boot_nodes(&self) -> Vec<String> {
+		self.info.read().unwrap().config.boot_nodes.clone()
+	}
+
+	/// Adds a node which is kept connected regardless of the peer limit.
+	/// Reserved nodes are redialed on every maintenance round and don't count towards `ideal_peers`.
+	pub fn add_reserved_node(&self, id: &str) -> Result<(), NetworkError> {
+		let n = try!(Node::from_enode_url(id));
+		let entry = NodeEntry { endpoint: n.endpoint.clone(), id: n.id.clone() };
+		self.reserved_nodes.write().unwrap().insert(n.id.clone());
+		self.nodes.write().unwrap().add_node(n);
+		if let Some(ref mut discovery) = *self.discovery.lock().unwrap().deref_mut() {
+			discovery.add_node(entry);
+		}
+		Ok(())
+	}
+
+	/// Makes the node a regular peer again. Its session is dropped if it exceeds the peer limit.
+	pub fn remove_reserved_node(&self, id: &str) -> Result<(), NetworkError> {
+		let n = try!(Node::from_enode_url(id));
+		self.reserved_nodes.write().unwrap().remove(&n.id);
+		Ok(())
+	}
+
+	/// Sets policy for non-reserved peers. In `Deny` mode connections with non-reserved peers
+	/// are dropped with `TooManyPeers` reason.
+	pub fn set_non_reserved_mode(&self, mode: NonReservedPeerMode) {
+		*self.non_reserved_mode.write().unwrap() = mode;
+	}
+
+	/// Bans node for given time. Banned nodes are not dialed and their connections are dropped.
+	pub fn ban_node(&self, id: &NodeId, duration: Duration) {
+		self.reputation.write().unwrap().ban(id, duration, Instant::now());
+	}
+
+	/// Lifts the ban of given node. Returns `false` if the node is not known.
+	pub fn unban_node(&self, id: &NodeId) -> bool {
+		self.reputation.write().unwrap().unban(id)
+	}
+
+	/// Returns currently banned nodes.
+	pub fn banned_nodes(&self) -> Vec<BannedNode> {
+		self.reputation.read().unwrap().banned(Instant::now())
+	}
+
+	fn is_banned(&self, id: &NodeId) -> bool {
+		self.reputation.read().unwrap().is_banned(id, Instant::now())
+	}
+
+	/// Returns information about connected peers.
+	pub fn peers_info(&self) -> Vec<PeerInfo> {
+		// Sessions are locked one at a time, the sessions list is not locked while collecting the info.
+		let sessions: Vec<SharedSession> = self.sessions.read().unwrap().iter().cloned().collect();
+		sessions.iter().filter_map(|s| s.lock().unwrap().peer_info()).collect()
+	}
+
 	pub fn client_version(&self) -> String {
 		self.info.read().unwrap().client_version.clone()
 	}
@@ -452,7 +568,9 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		let discovery = {
 			let info = self.info.read().unwrap();
 			if info.config.discovery_enabled && !info.config.pin {
-				Some(Discovery::new(&info.keys, public_endpoint.address.clone(), public_endpoint, DISCOVERY))
+				Some(Discovery::new(&info.keys, public_endpoint.address.clone(), public_endpoint, DISCOVERY)
+					.with_rate_limit(info.config.discovery_packet_rate, info.config.discovery_packet_burst)
+					.with_stats(self.stats.clone()))
 			} else { None }
 		};
 
@@ -473,9 +591,24 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 
 	fn maintain_network(&self, io: &IoContext<NetworkIoMessage<Message>>) {
 		self.keep_alive(io);
+		self.reputation.write().unwrap().prune(Instant::now());
+		self.drop_banned_peers(io);
+		self.drop_excess_peers(io);
 		self.connect_peers(io);
 	}
 
+	/// Maximal number of sessions with non-reserved peers.
+	fn max_non_reserved_peers(&self) -> usize {
+		match *self.non_reserved_mode.read().unwrap() {
+			NonReservedPeerMode::Accept => self.info.read().unwrap().config.ideal_peers as usize,
+			NonReservedPeerMode::Deny => 0,
+		}
+	}
+
+	fn non_reserved_session_count(&self, reserved_nodes: &HashSet<NodeId>) -> usize {
+		self.sessions.read().unwrap().iter().filter(|s| !reserved_nodes.contains(s.lock().unwrap().id())).count()
+	}
+
 	fn have_session(&self, id: &NodeId) -> bool {
 		self.sessions.read().unwrap().iter().any(|e| e.lock().unwrap().info.id.eq(&id))
 	}
@@ -507,16 +640,49 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		}
 	}
 
+	/// Disconnects banned peers.
+	fn drop_banned_peers(&self, io: &IoContext<NetworkIoMessage<Message>>) {
+		let mut to_kill = Vec::new();
+		for s in self.sessions.read().unwrap().iter() {
+			let mut s = s.lock().unwrap();
+			if !s.expired() && self.is_banned(s.id()) {
+				s.disconnect(DisconnectReason::UselessPeer);
+				to_kill.push(s.token());
+			}
+		}
+		for token in to_kill {
+			trace!(target: "network", "Dropping banned peer {}", token);
+			self.kill_connection(token, io, false);
+		}
+	}
+
+	/// Disconnects non-reserved peers exceeding the peer limit.
+	fn drop_excess_peers(&self, io: &IoContext<NetworkIoMessage<Message>>) {
+		let reserved_nodes = self.reserved_nodes.read().unwrap().clone();
+		let max_peers = self.max_non_reserved_peers();
+		let mut non_reserved = Vec::new();
+		for s in self.sessions.read().unwrap().iter() {
+			let s = s.lock().unwrap();
+			if !s.expired() && !reserved_nodes.contains(s.id()) {
+				non_reserved.push(s.token());
+			}
+		}
+		for token in non_reserved.into_iter().skip(max_peers) {
+			let session = { self.sessions.read().unwrap().get(token).cloned() };
+			if let Some(session) = session {
+				session.lock().unwrap().disconnect(DisconnectReason::TooManyPeers);
+			}
+			trace!(target: "network", "Dropping excess peer {}", token);
+			self.kill_connection(token, io, false);
+		}
+	}
+
 	fn connect_peers(&self, io: &IoContext<NetworkIoMessage<Message>>) {
 		if self.info.read().unwrap().deref().capabilities.is_empty() {
 			return;
 		}
-		let ideal_peers = { self.info.read().unwrap().deref().config.ideal_peers };
 		let pin = { self.info.read().unwrap().deref().config.pin };
-		let session_count = self.session_count();
-		if session_count >= ideal_peers as usize {
-			return;
-		}
+		let reserved_nodes = self.reserved_nodes.read().unwrap().clone();
 
 		let handshake_count = self.handshake_count();
 		// allow 16 slots for incoming connections
@@ -525,8 +691,13 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 			return;
 		}
 
-		let nodes = if pin { self.pinned_nodes.clone() } else { self.nodes.read().unwrap().nodes() };
-		for id in nodes.iter().filter(|ref id| !self.have_session(id) && !self.connecting_to(id))
+		// Reserved nodes are dialed first, regardless of the peer limit.
+		let mut nodes: Vec<NodeId> = reserved_nodes.iter().cloned().collect();
+		if self.non_reserved_session_count(&reserved_nodes) < self.max_non_reserved_peers() {
+			let others = if pin { self.pinned_nodes.clone() } else { self.nodes.read().unwrap().nodes() };
+			nodes.extend(others.into_iter().filter(|id| !reserved_nodes.contains(id)));
+		}
+		for id in nodes.iter().filter(|ref id| !self.have_session(id) && !self.connecting_to(id) && !self.is_banned(id))
 			.take(min(MAX_HANDSHAKES_PER_ROUND, handshake_limit - handshake_count)) {
 			self.connect_peer(&id, io);
 		}
@@ -544,6 +715,10 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 			trace!(target: "network", "Aborted connect. Node already connecting.");
 			return;
 		}
+		if self.is_banned(id) {
+			trace!(target: "network", "Aborted connect. Node is banned.");
+			return;
+		}
 
 		let socket = {
 			let address = {
@@ -604,7 +779,10 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		if let Some(handshake) = handshake {
 			let mut h = handshake.lock().unwrap();
 			if let Err(e) = h.writable(io, &self.info.read().unwrap()) {
-				trace!(target: "network", "Handshake write error: {}: {:?}", token, e);
+				match peer_error(token, e) {
+					Ok(e) => trace!(target: "network", "Handshake write error: {}", e),
+					Err(e) => trace!(target: "network", "Handshake write error: {}: {:?}", token, e),
+				}
 			}
 		}
 	}
@@ -614,7 +792,10 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		if let Some(session) = session {
 			let mut s = session.lock().unwrap();
 			if let Err(e) = s.writable(io, &self.info.read().unwrap()) {
-				trace!(target: "network", "Session write error: {}: {:?}", token, e);
+				match peer_error(token, e) {
+					Ok(e) => trace!(target: "network", "Session write error: {}", e),
+					Err(e) => trace!(target: "network", "Session write error: {}: {:?}", token, e),
+				}
 			}
 			if s.done() {
 				io.deregister_stream(token).expect("Error deregistering stream");
@@ -636,7 +817,10 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		if let Some(handshake) = handshake {
 			let mut h = handshake.lock().unwrap();
 			if let Err(e) = h.readable(io, &self.info.read().unwrap()) {
-				debug!(target: "network", "Handshake read error: {}: {:?}", token, e);
+				match peer_error(token, e) {
+					Ok(e) => debug!(target: "network", "Handshake read error: {}", e),
+					Err(e) => debug!(target: "network", "Handshake read error: {}: {:?}", token, e),
+				}
 				kill = true;
 			}
 			if h.done() {
@@ -647,7 +831,9 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 			self.kill_connection(token, io, true);
 			return;
 		} else if create_session {
-			self.start_session(token, io);
+			if let Err(e) = self.start_session(token, io) {
+				trace!(target: "network", "Session refused: {}", e);
+			}
 			return;
 		}
 		io.update_registration(token).unwrap_or_else(|e| debug!(target: "network", "Token registration error: {:?}", e));
@@ -662,13 +848,22 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 			let mut s = session.lock().unwrap();
 			match s.readable(io, &self.info.read().unwrap()) {
 				Err(e) => {
-					trace!(target: "network", "Session read error: {}:{} ({:?}) {:?}", token, s.id(), s.remote_addr(), e);
-					match e {
-						UtilError::Network(NetworkError::Disconnect(DisconnectReason::UselessPeer)) |
-						UtilError::Network(NetworkError::Disconnect(DisconnectReason::IncompatibleProtocol)) => {
-							self.nodes.write().unwrap().mark_as_useless(s.id());
-						}
-						_ => (),
+					match peer_error(token, e) {
+						Ok(e) => {
+							trace!(target: "network", "Session read error: {} ({:?}): {}", s.id(), s.remote_addr(), e);
+							if self.reputation.write().unwrap().note_error(s.id(), &e.error, Instant::now()) {
+								debug!(target: "network", "Banned node {} after session error: {}", s.id(), e);
+							}
+							match e.error {
+								NetworkError::UselessPeer |
+								NetworkError::Disconnect(DisconnectReason::UselessPeer) |
+								NetworkError::Disconnect(DisconnectReason::IncompatibleProtocol) => {
+									self.nodes.write().unwrap().mark_as_useless(s.id());
+								}
+								_ => (),
+							}
+						},
+						Err(e) => trace!(target: "network", "Session read error: {}:{} ({:?}) {:?}", token, s.id(), s.remote_addr(), e),
 					}
 					kill = true;
 				},
@@ -706,17 +901,17 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		io.update_registration(token).unwrap_or_else(|e| debug!(target: "network", "Token registration error: {:?}", e));
 	}
 
-	fn start_session(&self, token: StreamToken, io: &IoContext<NetworkIoMessage<Message>>) {
+	fn start_session(&self, token: StreamToken, io: &IoContext<NetworkIoMessage<Message>>) -> Result<(), PeerError> {
 		let mut handshakes = self.handshakes.write().unwrap();
 		if handshakes.get(token).is_none() {
-			return;
+			return Ok(());
 		}
 
 		// turn a handshake into a session
 		let mut sessions = self.sessions.write().unwrap();
 		let mut h = handshakes.get_mut(token).unwrap().lock().unwrap();
 		if h.expired {
-			return;
+			return Ok(());
 		}
 		io.deregister_stream(token).expect("Error deleting handshake registration");
 		h.set_expired();
@@ -725,15 +920,22 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 			Ok(s) => s,
 			Err(e) => {
 				debug!(target: "network", "Session creation error: {:?}", e);
-				return;
+				return Ok(());
 			}
 		};
+		if self.is_banned(session.id()) {
+			trace!(target: "network", "Rejecting banned node {}", session.id());
+			session.disconnect(DisconnectReason::UselessPeer);
+			return Err(NetworkError::PeerBanned.with_peer(token));
+		}
 		if !originated {
-			let session_count = sessions.count();
-			let ideal_peers = { self.info.read().unwrap().deref().config.ideal_peers };
-			if session_count >= ideal_peers as usize {
-				session.disconnect(DisconnectReason::TooManyPeers);
-				return;
+			let reserved_nodes = self.reserved_nodes.read().unwrap();
+			if !reserved_nodes.contains(session.id()) {
+				let session_count = sessions.iter().filter(|s| !reserved_nodes.contains(s.lock().unwrap().id())).count();
+				if session_count >= self.max_non_reserved_peers() {
+					session.disconnect(DisconnectReason::TooManyPeers);
+					return Ok(());
+				}
 			}
 		}
 		let result = sessions.insert_with(move |session_token| {
@@ -757,6 +959,7 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		if result.is_none() {
 			warn!("Max sessions reached");
 		}
+		Ok(())
 	}
 
 	fn connection_timeout(&self, token: StreamToken, io: &IoContext<NetworkIoMessage<Message>>) {
@@ -796,6 +999,11 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 						}
 						s.set_expired();
 						failure_id = Some(s.id().clone());
+						if let Some(reason) = s.disconnect_reason() {
+							if self.reputation.write().unwrap().note_disconnect(s.id(), &reason, Instant::now()) {
+								debug!(target: "network", "Banned node {} after disconnect: {:?}", s.id(), reason);
+							}
+						}
 					}
 					deregister = remote || s.done();
 				}
@@ -818,7 +1026,12 @@ impl<Message> Host<Message> where Message: Send + Sync + Clone {
 		}
 	}
 
-	fn update_nodes(&self, io: &IoContext<NetworkIoMessage<Message>>, node_changes: TableUpdates) {
+	fn update_nodes(&self, io: &IoContext<NetworkIoMessage<Message>>, mut node_changes: TableUpdates) {
+		// Reserved nodes are never removed.
+		{
+			let reserved_nodes = self.reserved_nodes.read().unwrap();
+			node_changes.removed = node_changes.removed.into_iter().filter(|id| !reserved_nodes.contains(id)).collect();
+		}
 		let mut to_remove: Vec<PeerId> = Vec::new();
 		{
 			{
@@ -965,7 +1178,7 @@ impl<Message> IoHandler<NetworkIoMessage<Message>> for Host<Message> where Messa
 			NetworkIoMessage::DisablePeer(ref peer) => {
 				let session = { self.sessions.read().unwrap().get(*peer).cloned() };
 				if let Some(session) = session {
-					session.lock().unwrap().disconnect(DisconnectReason::DisconnectRequested);
+					session.lock().unwrap().disconnect(DisconnectReason::UselessPeer);
 					self.nodes.write().unwrap().mark_as_useless(session.lock().unwrap().id());
 				}
 				trace!(target: "network", "Disabling peer {}", peer);
@@ -1041,6 +1254,16 @@ impl<Message> IoHandler<NetworkIoMessage<Message>> for Host<Message> where Messa
 	}
 }
 
+/// Attributes handshake or session failure to the peer on given token.
+/// Errors which are not caused by the peer are returned unchanged.
+fn peer_error(token: StreamToken, e: UtilError) -> Result<PeerError, UtilError> {
+	match e {
+		UtilError::Network(e) => Ok(e.with_peer(token)),
+		UtilError::Decoder(_) => Ok(NetworkError::BadProtocol.with_peer(token)),
+		e => Err(e),
+	}
+}
+
 fn save_key(path: &Path, key: &Secret) {
 	let mut path_buf = PathBuf::from(path);
 	if let Err(e) = fs::create_dir_all(path_buf.as_path()) {
@@ -1097,6 +1320,17 @@ fn key_save_load() {
 	assert_eq!(key, r.unwrap());
 }
 
+#[test]
+fn attributes_session_errors_to_peer() {
+	let e = peer_error(3, UtilError::Decoder(DecoderError::RlpIsTooShort)).unwrap();
+	assert_eq!(e.peer_id, Some(3));
+	assert_eq!(format!("{}", e), "Bad protocol caused by peer 3");
+
+	let e = peer_error(4, UtilError::Network(NetworkError::PeerBanned)).unwrap();
+	assert_eq!(format!("{}", e), "Peer is banned caused by peer 4");
+
+	assert!(peer_error(5, UtilError::BadSize).is_err());
+}
 
 #[test]
 fn host_client_url() {
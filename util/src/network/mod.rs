@@ -74,6 +74,7 @@ mod error;
 mod node_table;
 mod stats;
 mod ip_utils;
+mod reputation;
 
 #[cfg(test)]
 mod tests;
@@ -84,9 +85,12 @@ pub use network::host::NetworkContext;
 pub use network::service::NetworkService;
 pub use network::host::NetworkIoMessage;
 pub use network::host::NetworkIoMessage::User as UserMessage;
-pub use network::error::NetworkError;
+pub use network::error::{NetworkError, PeerError};
 pub use network::host::NetworkConfiguration;
+pub use network::host::NonReservedPeerMode;
+pub use network::session::{PeerInfo, PeerCapabilityInfo};
 pub use network::stats::NetworkStats;
+pub use network::reputation::BannedNode;
 
 use io::TimerToken;
 pub use network::node_table::is_valid_node_url;
@@ -29,6 +29,7 @@ use hash::*;
 use rlp::*;
 use time::Tm;
 use error::*;
+use network::error::NetworkError;
 use network::discovery::{TableUpdates, NodeEntry};
 use network::ip_utils::*;
 pub use rustc_serialize::json::Json;
@@ -117,8 +118,8 @@ impl FromStr for NodeEndpoint {
 				address: a,
 				udp_port: a.port()
 			}),
-			Ok(_) => Err(UtilError::AddressResolve(None)),
-			Err(e) => Err(UtilError::AddressResolve(Some(e)))
+			Ok(_) => Err(From::from(NetworkError::AddressResolve(None))),
+			Err(e) => Err(From::from(NetworkError::AddressResolve(Some(e))))
 		}
 	}
 }
@@ -147,6 +148,17 @@ impl Node {
 			last_attempted: None,
 		}
 	}
+
+	/// Parses enode URL. Unlike `from_str` plain addresses without node id are rejected.
+	pub fn from_enode_url(url: &str) -> Result<Node, NetworkError> {
+		if !url.starts_with("enode://") || url.len() <= 136 || url.as_bytes()[136] != b'@' {
+			return Err(NetworkError::InvalidNodeId);
+		}
+		Node::from_str(url).map_err(|e| match e {
+			UtilError::Network(e) => e,
+			_ => NetworkError::InvalidNodeId,
+		})
+	}
 }
 
 impl Display for Node {
@@ -230,6 +242,12 @@ impl NodeTable {
 		self.nodes.values().map(|n| NodeEntry { endpoint: n.endpoint.clone(), id: n.id.clone() }).collect()
 	}
 
+	/// Removes the node from the table. Returns `false` if it wasn't there.
+	pub fn remove(&mut self, id: &NodeId) -> bool {
+		self.useless_nodes.remove(id);
+		self.nodes.remove(id).is_some()
+	}
+
 	/// Get particular node
 	pub fn get_mut(&mut self, id: &NodeId) -> Option<&mut Node> {
 		self.nodes.get_mut(id)
@@ -357,6 +375,7 @@ mod tests {
 	use std::net::*;
 	use hash::*;
 	use devtools::*;
+	use network::error::NetworkError;
 
 	#[test]
 	fn endpoint_parse() {
@@ -385,6 +404,19 @@ mod tests {
 			node.id);
 	}
 
+	#[test]
+	fn enode_url_parse() {
+		assert!(Node::from_enode_url("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").is_ok());
+		match Node::from_enode_url("22.99.55.44:7770") {
+			Err(NetworkError::InvalidNodeId) => {},
+			other => panic!("Unexpected result: {:?}", other.map(|n| n.id)),
+		}
+		match Node::from_enode_url("enode://x979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770") {
+			Err(NetworkError::InvalidNodeId) => {},
+			other => panic!("Unexpected result: {:?}", other.map(|n| n.id)),
+		}
+	}
+
 	#[test]
 	fn table_failure_order() {
 		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
@@ -408,6 +440,21 @@ mod tests {
 		assert_eq!(r[2][..], id1[..]);
 	}
 
+	#[test]
+	fn table_remove() {
+		let node1 = Node::from_str("enode://a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let node2 = Node::from_str("enode://b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c@22.99.55.44:7770").unwrap();
+		let id1 = H512::from_str("a979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let id2 = H512::from_str("b979fb575495b8d6db44f750317d0f4622bf4c2aa3365d6af7c284339968eef29b69ad0dce72a4d8db5ebb4968de0e3bec910127f134779fbcb0cb6d3331163c").unwrap();
+		let mut table = NodeTable::new(None);
+		table.add_node(node1);
+		table.add_node(node2);
+
+		assert!(table.remove(&id1));
+		assert!(!table.remove(&id1));
+		assert_eq!(table.nodes(), vec![id2]);
+	}
+
 	#[test]
 	fn table_save_load() {
 		let temp_path = RandomTempPath::create_dir();
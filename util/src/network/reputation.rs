@@ -0,0 +1,290 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Node reputation tracking.
+//!
+//! Misbehaving nodes collect penalty points. Once the points reach `BAN_THRESHOLD`
+//! the node is banned for a period which grows with each consecutive ban.
+//! Penalty points decay over time and nodes which behaved for long enough are forgotten.
+
+use std::cmp::min;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use network::error::{NetworkError, DisconnectReason};
+use network::node_table::NodeId;
+
+/// Number of penalty points after which the node gets banned.
+const BAN_THRESHOLD: u32 = 100;
+/// Durations (in seconds) of consecutive bans. The last one is used for all further bans.
+const BAN_BACKOFF: [u64; 4] = [5, 30, 5 * 60, 30 * 60];
+/// Number of seconds after which a single penalty point is forgiven.
+const PENALTY_DECAY_SECS: u64 = 10;
+/// Number of seconds after the last ban expired after which the node is forgotten (if it has no penalty points).
+const BAN_MEMORY_SECS: u64 = 24 * 60 * 60;
+
+/// Currently banned node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BannedNode {
+	/// Node id
+	pub id: NodeId,
+	/// Time left until the ban expires
+	pub remaining: Duration,
+	/// Number of penalty bans of this node so far
+	pub bans: usize,
+}
+
+struct NodeReputation {
+	penalty: u32,
+	/// Time of the last change of `penalty`.
+	penalized_at: Instant,
+	bans: usize,
+	banned_until: Option<Instant>,
+}
+
+impl NodeReputation {
+	fn new(now: Instant) -> Self {
+		NodeReputation {
+			penalty: 0,
+			penalized_at: now,
+			bans: 0,
+			banned_until: None,
+		}
+	}
+
+	/// Returns penalty points left at given time.
+	fn penalty_at(&self, now: Instant) -> u32 {
+		if now <= self.penalized_at {
+			return self.penalty;
+		}
+		let forgiven = now.duration_since(self.penalized_at).as_secs() / PENALTY_DECAY_SECS;
+		self.penalty.saturating_sub(min(forgiven, u32::max_value() as u64) as u32)
+	}
+
+	/// Checks if the node can be forgotten at given time.
+	fn is_forgotten(&self, now: Instant) -> bool {
+		self.penalty_at(now) == 0
+			&& self.banned_until.map_or(true, |until| until + Duration::from_secs(BAN_MEMORY_SECS) <= now)
+	}
+}
+
+/// Penalty points and bans of known nodes.
+#[derive(Default)]
+pub struct Reputation {
+	nodes: HashMap<NodeId, NodeReputation>,
+}
+
+/// Returns penalty for being disconnected by the peer (or disconnecting it) with given reason.
+fn disconnect_penalty(reason: &DisconnectReason) -> u32 {
+	match *reason {
+		DisconnectReason::BadProtocol |
+		DisconnectReason::NullIdentity |
+		DisconnectReason::UnexpectedIdentity |
+		DisconnectReason::LocalIdentity => BAN_THRESHOLD,
+		DisconnectReason::UselessPeer |
+		DisconnectReason::IncompatibleProtocol => BAN_THRESHOLD / 2,
+		DisconnectReason::PingTimeout |
+		DisconnectReason::Unknown => BAN_THRESHOLD / 5,
+		DisconnectReason::TCPError => BAN_THRESHOLD / 10,
+		DisconnectReason::DisconnectRequested |
+		DisconnectReason::TooManyPeers |
+		DisconnectReason::DuplicatePeer |
+		DisconnectReason::ClientQuit => 0,
+	}
+}
+
+/// Returns penalty for session failing with given error.
+fn error_penalty(error: &NetworkError) -> u32 {
+	match *error {
+		NetworkError::Disconnect(ref reason) => disconnect_penalty(reason),
+		NetworkError::UselessPeer => disconnect_penalty(&DisconnectReason::UselessPeer),
+		NetworkError::BadProtocol | NetworkError::Auth => BAN_THRESHOLD,
+		NetworkError::Io(_) => BAN_THRESHOLD / 10,
+		NetworkError::Expired |
+		NetworkError::PeerNotFound |
+		NetworkError::PeerBanned |
+		NetworkError::AddressInUse(_) |
+		NetworkError::AddressResolve(_) |
+		NetworkError::InvalidNodeId => 0,
+	}
+}
+
+impl Reputation {
+	/// Penalizes node for session failure. Returns `true` if the node got banned.
+	pub fn note_error(&mut self, id: &NodeId, error: &NetworkError, now: Instant) -> bool {
+		self.penalize(id, error_penalty(error), now)
+	}
+
+	/// Penalizes node for disconnect with given reason. Returns `true` if the node got banned.
+	pub fn note_disconnect(&mut self, id: &NodeId, reason: &DisconnectReason, now: Instant) -> bool {
+		self.penalize(id, disconnect_penalty(reason), now)
+	}
+
+	fn penalize(&mut self, id: &NodeId, penalty: u32, now: Instant) -> bool {
+		if penalty == 0 || self.is_banned(id, now) {
+			return false;
+		}
+		let node = self.nodes.entry(id.clone()).or_insert_with(|| NodeReputation::new(now));
+		node.penalty = node.penalty_at(now) + penalty;
+		node.penalized_at = now;
+		if node.penalty < BAN_THRESHOLD {
+			return false;
+		}
+		let backoff = BAN_BACKOFF[min(node.bans, BAN_BACKOFF.len() - 1)];
+		trace!(target: "network", "Banning node {} for {}s", id, backoff);
+		node.penalty = 0;
+		node.bans += 1;
+		node.banned_until = Some(now + Duration::from_secs(backoff));
+		true
+	}
+
+	/// Bans node for given time. Doesn't affect the backoff of penalty bans.
+	pub fn ban(&mut self, id: &NodeId, duration: Duration, now: Instant) {
+		self.nodes.entry(id.clone()).or_insert_with(|| NodeReputation::new(now)).banned_until = Some(now + duration);
+	}
+
+	/// Forgets nodes which are not banned, have no penalty points left and weren't banned recently.
+	pub fn prune(&mut self, now: Instant) {
+		let forgotten: Vec<_> = self.nodes.iter().filter(|&(_, node)| node.is_forgotten(now)).map(|(id, _)| id.clone()).collect();
+		for id in forgotten {
+			self.nodes.remove(&id);
+		}
+	}
+
+	/// Lifts the ban and forgets all penalties of the node. Returns `false` if the node wasn't known.
+	pub fn unban(&mut self, id: &NodeId) -> bool {
+		self.nodes.remove(id).is_some()
+	}
+
+	/// Checks if the node is banned at given time.
+	pub fn is_banned(&self, id: &NodeId, now: Instant) -> bool {
+		self.nodes.get(id).and_then(|node| node.banned_until).map_or(false, |until| until > now)
+	}
+
+	/// Returns nodes banned at given time.
+	pub fn banned(&self, now: Instant) -> Vec<BannedNode> {
+		self.nodes.iter()
+			.filter_map(|(id, node)| match node.banned_until {
+				Some(until) if until > now => Some(BannedNode {
+					id: id.clone(),
+					remaining: until.duration_since(now),
+					bans: node.bans,
+				}),
+				_ => None,
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::{PENALTY_DECAY_SECS, BAN_MEMORY_SECS, BAN_THRESHOLD, BAN_BACKOFF};
+	use std::time::{Duration, Instant};
+	use hash::H512;
+	use network::error::{NetworkError, DisconnectReason};
+
+	#[test]
+	fn bans_with_exponential_backoff() {
+		// given
+		let id = H512::from(1);
+		let mut reputation = Reputation::default();
+		let mut now = Instant::now();
+
+		// when
+		assert!(!reputation.note_disconnect(&id, &DisconnectReason::UselessPeer, now));
+		assert!(reputation.note_disconnect(&id, &DisconnectReason::UselessPeer, now));
+
+		// then
+		for &backoff in &[5, 30, 300, 1800, 1800] {
+			assert!(reputation.is_banned(&id, now));
+			assert!(reputation.is_banned(&id, now + Duration::from_secs(backoff - 1)));
+			assert!(!reputation.note_error(&id, &NetworkError::BadProtocol, now), "Banned node can't be banned again");
+			now = now + Duration::from_secs(backoff);
+			assert!(!reputation.is_banned(&id, now));
+			assert!(reputation.note_error(&id, &NetworkError::BadProtocol, now));
+		}
+	}
+
+	#[test]
+	fn penalties_decay_over_time() {
+		// given
+		let id = H512::from(1);
+		let mut reputation = Reputation::default();
+		let now = Instant::now();
+		assert!(!reputation.note_disconnect(&id, &DisconnectReason::UselessPeer, now));
+
+		// when
+		let later = now + Duration::from_secs(PENALTY_DECAY_SECS);
+
+		// then
+		assert!(!reputation.note_disconnect(&id, &DisconnectReason::UselessPeer, later));
+		assert!(reputation.note_disconnect(&id, &DisconnectReason::PingTimeout, later));
+	}
+
+	#[test]
+	fn prunes_forgotten_nodes() {
+		// given
+		let (id1, id2, id3) = (H512::from(1), H512::from(2), H512::from(3));
+		let mut reputation = Reputation::default();
+		let now = Instant::now();
+		reputation.note_disconnect(&id1, &DisconnectReason::PingTimeout, now);
+		reputation.note_error(&id2, &NetworkError::BadProtocol, now);
+		reputation.note_disconnect(&id3, &DisconnectReason::UselessPeer, now);
+		let decayed = now + Duration::from_secs(PENALTY_DECAY_SECS * (BAN_THRESHOLD / 5) as u64);
+
+		// when
+		reputation.prune(decayed);
+
+		// then
+		assert_eq!(reputation.nodes.len(), 2);
+		assert!(!reputation.nodes.contains_key(&id1));
+		reputation.prune(now + Duration::from_secs(BAN_MEMORY_SECS + BAN_BACKOFF[0]));
+		assert!(reputation.nodes.is_empty());
+	}
+
+	#[test]
+	fn ignores_harmless_disconnects() {
+		let id = H512::from(1);
+		let mut reputation = Reputation::default();
+		let now = Instant::now();
+
+		for _ in 0..100 {
+			assert!(!reputation.note_disconnect(&id, &DisconnectReason::TooManyPeers, now));
+			assert!(!reputation.note_disconnect(&id, &DisconnectReason::ClientQuit, now));
+		}
+		assert!(!reputation.is_banned(&id, now));
+		assert!(reputation.banned(now).is_empty());
+	}
+
+	#[test]
+	fn manual_bans() {
+		// given
+		let (id1, id2) = (H512::from(1), H512::from(2));
+		let mut reputation = Reputation::default();
+		let now = Instant::now();
+
+		// when
+		reputation.ban(&id1, Duration::from_secs(60), now);
+		reputation.ban(&id2, Duration::from_secs(10), now);
+		let banned = reputation.banned(now + Duration::from_secs(20));
+
+		// then
+		assert_eq!(banned, vec![BannedNode { id: id1.clone(), remaining: Duration::from_secs(40), bans: 0 }]);
+		assert!(reputation.unban(&id1));
+		assert!(!reputation.unban(&H512::from(3)));
+		assert!(!reputation.is_banned(&id1, now));
+	}
+}
@@ -15,11 +15,14 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::*;
+use std::time::Duration;
 use error::*;
 use panics::*;
-use network::{NetworkProtocolHandler, NetworkConfiguration};
+use network::{NetworkProtocolHandler, NetworkConfiguration, PeerInfo};
 use network::error::{NetworkError};
-use network::host::{Host, NetworkIoMessage, ProtocolId};
+use network::host::{Host, NetworkIoMessage, ProtocolId, NonReservedPeerMode};
+use network::node_table::NodeId;
+use network::reputation::BannedNode;
 use network::stats::{NetworkStats};
 use io::*;
 
@@ -87,6 +90,56 @@ impl<Message> NetworkService<Message> where Message: Send + Sync + Clone + 'stat
 	pub fn local_url(&self) -> String {
 		self.host.local_url()
 	}
+
+	/// Returns information about connected peers.
+	pub fn peers_info(&self) -> Vec<PeerInfo> {
+		self.host.peers_info()
+	}
+
+	/// Adds a peer (given by enode URL) which is kept connected regardless of the peer limit.
+	pub fn add_reserved_peer(&self, peer: &str) -> Result<(), NetworkError> {
+		self.host.add_reserved_node(peer)
+	}
+
+	/// Adds a boot node and starts a discovery lookup including it.
+	pub fn add_boot_node(&self, enode: &str) -> Result<(), NetworkError> {
+		self.host.add_boot_node(enode)
+	}
+
+	/// Removes a boot node from the boot list and the discovery table.
+	pub fn remove_boot_node(&self, enode: &str) -> Result<(), NetworkError> {
+		self.host.remove_boot_node(enode)
+	}
+
+	/// Returns enode URLs of current boot nodes.
+	pub fn boot_nodes(&self) -> Vec<String> {
+		self.host.boot_nodes()
+	}
+
+	/// Removes a peer from the reserved peers list.
+	pub fn remove_reserved_peer(&self, peer: &str) -> Result<(), NetworkError> {
+		self.host.remove_reserved_node(peer)
+	}
+
+	/// Sets policy for peers which are not reserved.
+	pub fn set_non_reserved_mode(&self, mode: NonReservedPeerMode) {
+		self.host.set_non_reserved_mode(mode)
+	}
+
+	/// Bans node for given time. The node is not dialed and its connections are dropped until the ban expires.
+	pub fn ban_node(&self, id: &NodeId, duration: Duration) {
+		self.host.ban_node(id, duration)
+	}
+
+	/// Lifts the ban of given node and forgets its penalties. Returns `false` if the node is not known.
+	pub fn unban_node(&self, id: &NodeId) -> bool {
+		self.host.unban_node(id)
+	}
+
+	/// Returns currently banned nodes.
+	pub fn banned_nodes(&self) -> Vec<BannedNode> {
+		self.host.banned_nodes()
+	}
 }
 
 impl<Message> MayPanic for NetworkService<Message> where Message: Send + Sync + Clone + 'static {
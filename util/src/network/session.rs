@@ -42,6 +42,8 @@ pub struct Session {
 	had_hello: bool,
 	/// Session is no longer active flag.
 	expired: bool,
+	/// Reason of disconnecting the peer, if it was disconnected by this side.
+	disconnect_reason: Option<DisconnectReason>,
 	ping_time_ns: u64,
 	pong_time_ns: Option<u64>,
 }
@@ -72,16 +74,46 @@ pub struct SessionInfo {
 	pub protocol_version: u32,
 	/// Peer protocol capabilities
 	capabilities: Vec<SessionCapabilityInfo>,
+	/// All capabilities advertised by the peer
+	pub peer_capabilities: Vec<PeerCapabilityInfo>,
 	/// Peer ping delay in milliseconds
 	pub ping_ms: Option<u64>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PeerCapabilityInfo {
 	pub protocol: String,
 	pub version: u8,
 }
 
+/// Snapshot of connected peer information.
+#[derive(Debug, Clone)]
+pub struct PeerInfo {
+	/// Session id
+	pub peer_id: PeerId,
+	/// Peer public key
+	pub id: NodeId,
+	/// Peer client ID as reported in Hello packet
+	pub client_version: String,
+	/// Remote address of the connection
+	pub remote_address: Option<SocketAddr>,
+	/// Peer RLPx protocol version
+	pub protocol_version: u32,
+	/// All capabilities advertised by the peer
+	pub capabilities: Vec<PeerCapabilityInfo>,
+	/// Protocols negotiated with the peer (highest mutually supported version of each)
+	pub protocols: Vec<PeerCapabilityInfo>,
+	/// Peer ping delay in milliseconds
+	pub ping_ms: Option<u64>,
+}
+
+impl PeerInfo {
+	/// Returns negotiated version of given protocol.
+	pub fn negotiated_version(&self, protocol: &str) -> Option<u8> {
+		self.protocols.iter().find(|c| c.protocol == protocol).map(|c| c.version)
+	}
+}
+
 impl Decodable for PeerCapabilityInfo {
 	fn decode<D>(decoder: &D) -> Result<Self, DecoderError> where D: Decoder {
 		let c = decoder.as_rlp();
@@ -123,11 +155,13 @@ impl Session {
 				client_version: String::new(),
 				protocol_version: 0,
 				capabilities: Vec::new(),
+				peer_capabilities: Vec::new(),
 				ping_ms: None,
 			},
 			ping_time_ns: 0,
 			pong_time_ns: None,
 			expired: false,
+			disconnect_reason: None,
 		};
 		try!(session.write_hello(host));
 		try!(session.send_ping());
@@ -168,13 +202,30 @@ impl Session {
 		self.connection.remote_addr()
 	}
 
+	/// Returns information about the peer or `None` if the session is not ready.
+	pub fn peer_info(&self) -> Option<PeerInfo> {
+		if !self.is_ready() || self.expired() {
+			return None;
+		}
+		Some(PeerInfo {
+			peer_id: self.token(),
+			id: self.info.id.clone(),
+			client_version: self.info.client_version.clone(),
+			remote_address: self.remote_addr().ok(),
+			protocol_version: self.info.protocol_version,
+			capabilities: self.info.peer_capabilities.clone(),
+			protocols: self.info.capabilities.iter().map(|c| PeerCapabilityInfo { protocol: c.protocol.to_owned(), version: c.version }).collect(),
+			ping_ms: self.info.ping_ms,
+		})
+	}
+
 	/// Readable IO handler. Returns packet data if available.
 	pub fn readable<Message>(&mut self, io: &IoContext<Message>, host: &HostInfo) -> Result<SessionData, UtilError>  where Message: Send + Sync + Clone {
 		if self.expired() {
 			return Ok(SessionData::None)
 		}
 		match try!(self.connection.readable(io)) {
-			Some(data) => Ok(try!(self.read_packet(data, host))),
+			Some(data) => self.read_packet(data, host).map_err(|e| self.disconnect_on_error(e)),
 			None => Ok(SessionData::None)
 		}
 	}
@@ -357,14 +408,16 @@ impl Session {
 		}
 		trace!(target: "network", "Hello: {} v{} {} {:?}", client_version, protocol, id, caps);
 		self.info.client_version = client_version;
+		self.info.protocol_version = protocol;
 		self.info.capabilities = caps;
+		self.info.peer_capabilities = peer_caps;
 		if self.info.capabilities.is_empty() {
 			trace!(target: "network", "No common capabilities with peer.");
-			return Err(From::from(self.disconnect(DisconnectReason::UselessPeer)));
+			return Err(From::from(NetworkError::UselessPeer));
 		}
 		if protocol != host.protocol_version {
 			trace!(target: "network", "Peer protocol version mismatch: {}", protocol);
-			return Err(From::from(self.disconnect(DisconnectReason::UselessPeer)));
+			return Err(From::from(NetworkError::UselessPeer));
 		}
 		self.had_hello = true;
 		Ok(())
@@ -384,6 +437,16 @@ impl Session {
 
 	/// Disconnect this session
 	pub fn disconnect(&mut self, reason: DisconnectReason) -> NetworkError {
+		self.disconnect_reason = Some(reason);
+		self.send_disconnect(reason)
+	}
+
+	/// Returns reason the peer was disconnected with by `disconnect`.
+	pub fn disconnect_reason(&self) -> Option<DisconnectReason> {
+		self.disconnect_reason
+	}
+
+	fn send_disconnect(&mut self, reason: DisconnectReason) -> NetworkError {
 		let mut rlp = RlpStream::new();
 		rlp.append(&(PACKET_DISCONNECT as u32));
 		rlp.begin_list(1);
@@ -392,6 +455,20 @@ impl Session {
 		NetworkError::Disconnect(reason)
 	}
 
+	/// Notifies the peer about protocol error (if the error maps to a disconnect reason).
+	fn disconnect_on_error(&mut self, error: UtilError) -> UtilError {
+		let reason = match error {
+			UtilError::Network(ref e) => e.to_disconnect_reason(),
+			UtilError::Decoder(_) => Some(DisconnectReason::BadProtocol),
+			_ => None,
+		};
+		// errors are accounted for by the host, the reason is not recorded
+		if let Some(reason) = reason {
+			self.send_disconnect(reason);
+		}
+		error
+	}
+
 	fn prepare(packet_id: u8) -> Result<RlpStream, UtilError> {
 		let mut rlp = RlpStream::new();
 		rlp.append(&(packet_id as u32));
@@ -26,6 +26,8 @@ pub struct NetworkStats {
 	send: AtomicUsize,
 	/// Total number of sessions created
 	sessions: AtomicUsize,
+	/// Discovery packets dropped because of the rate limit
+	discovery_dropped: AtomicUsize,
 }
 
 impl NetworkStats {
@@ -47,6 +49,12 @@ impl NetworkStats {
 		self.sessions.fetch_add(1, Ordering::Relaxed);
 	}
 
+	/// Increase number of dropped discovery packets.
+	#[inline]
+	pub fn inc_discovery_dropped(&self) {
+		self.discovery_dropped.fetch_add(1, Ordering::Relaxed);
+	}
+
 	/// Get bytes sent.
 	#[inline]
 	pub fn send(&self) -> usize {
@@ -65,12 +73,19 @@ impl NetworkStats {
 		self.sessions.load(Ordering::Relaxed)
 	}
 
+	/// Get number of discovery packets dropped because of the rate limit.
+	#[inline]
+	pub fn discovery_dropped(&self) -> usize {
+		self.discovery_dropped.load(Ordering::Relaxed)
+	}
+
 	#[cfg(test)]
 	pub fn new() -> NetworkStats {
 		NetworkStats {
 			recv: AtomicUsize::new(0),
 			send: AtomicUsize::new(0),
 			sessions: AtomicUsize::new(0),
+			discovery_dropped: AtomicUsize::new(0),
 		}
 	}
 }
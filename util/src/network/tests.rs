@@ -15,6 +15,7 @@
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::{channel, Sender, Receiver};
 use std::thread;
 use std::time::*;
 use common::*;
@@ -27,6 +28,14 @@ pub struct TestProtocol {
 	pub packet: Mutex<Bytes>,
 	pub got_timeout: AtomicBool,
 	pub got_disconnect: AtomicBool,
+	events: Mutex<Option<Sender<TestEvent>>>,
+}
+
+/// Events reported to the channel returned by `TestProtocol::events`.
+#[derive(Debug, PartialEq)]
+pub enum TestEvent {
+	Packet,
+	Disconnected,
 }
 
 #[derive(Clone)]
@@ -41,6 +50,7 @@ impl TestProtocol {
 			got_timeout: AtomicBool::new(false), 
 			got_disconnect: AtomicBool::new(false), 
 			drop_session: drop_session,
+			events: Mutex::new(None),
 		}
 	}
 	/// Creates and register protocol with the network service
@@ -61,6 +71,19 @@ impl TestProtocol {
 	pub fn got_disconnect(&self) -> bool {
 		self.got_disconnect.load(AtomicOrdering::Relaxed)
 	}
+
+	/// Returns channel receiving events of this handler from now on.
+	pub fn events(&self) -> Receiver<TestEvent> {
+		let (tx, rx) = channel();
+		*self.events.lock().unwrap() = Some(tx);
+		rx
+	}
+
+	fn notify(&self, event: TestEvent) {
+		if let Some(ref tx) = *self.events.lock().unwrap() {
+			let _ = tx.send(event);
+		}
+	}
 }
 
 impl NetworkProtocolHandler<TestProtocolMessage> for TestProtocol {
@@ -71,6 +94,7 @@ impl NetworkProtocolHandler<TestProtocolMessage> for TestProtocol {
 	fn read(&self, _io: &NetworkContext<TestProtocolMessage>, _peer: &PeerId, packet_id: u8, data: &[u8]) {
 		assert_eq!(packet_id, 33);
 		self.packet.lock().unwrap().extend(data);
+		self.notify(TestEvent::Packet);
 	}
 
 	fn connected(&self, io: &NetworkContext<TestProtocolMessage>, peer: &PeerId) {
@@ -84,6 +108,7 @@ impl NetworkProtocolHandler<TestProtocolMessage> for TestProtocol {
 
 	fn disconnected(&self, _io: &NetworkContext<TestProtocolMessage>, _peer: &PeerId) {
 		self.got_disconnect.store(true, AtomicOrdering::Relaxed);
+		self.notify(TestEvent::Disconnected);
 	}
 
 	/// Timer function called after a timeout created with `NetworkContext::timeout`.
@@ -150,3 +175,88 @@ fn net_timeout() {
 		thread::sleep(Duration::from_millis(50));
 	}
 }
+
+#[test]
+fn net_reserved_peer_survives_peer_limit() {
+	let mut service1 = NetworkService::<TestProtocolMessage>::start(NetworkConfiguration::new_local()).unwrap();
+	let handler1 = TestProtocol::register(&mut service1, false);
+	let events1 = handler1.events();
+	let key2 = KeyPair::create().unwrap();
+	let mut config2 = NetworkConfiguration::new_local();
+	config2.use_secret = Some(key2.secret().clone());
+	let mut service2 = NetworkService::<TestProtocolMessage>::start(config2).unwrap();
+	let handler2 = TestProtocol::register(&mut service2, false);
+	service1.add_reserved_peer(&service2.local_url()).unwrap();
+	let mut config3 = NetworkConfiguration::new_local();
+	config3.boot_nodes = vec![ service1.local_url() ];
+	let mut service3 = NetworkService::<TestProtocolMessage>::start(config3).unwrap();
+	let _handler3 = TestProtocol::register(&mut service3, false);
+	// both peers said hello
+	assert_eq!(events1.recv().unwrap(), TestEvent::Packet);
+	assert_eq!(events1.recv().unwrap(), TestEvent::Packet);
+
+	// when
+	service1.set_non_reserved_mode(NonReservedPeerMode::Deny);
+	// the next maintenance round drops the non-reserved peer
+	assert_eq!(events1.recv().unwrap(), TestEvent::Disconnected);
+
+	// then
+	assert!(service1.peers_info().iter().any(|p| p.id == *key2.public()));
+	assert!(!handler2.got_disconnect());
+}
+
+#[test]
+fn net_reserved_peer_requires_enode_url() {
+	let service = NetworkService::<TestProtocolMessage>::start(NetworkConfiguration::new_local()).unwrap();
+	match service.add_reserved_peer("127.0.0.1:30303") {
+		Err(NetworkError::InvalidNodeId) => {},
+		other => panic!("Unexpected result: {:?}", other),
+	}
+	assert!(service.remove_reserved_peer("enode://invalid").is_err());
+}
+
+#[test]
+fn net_boot_nodes_can_be_changed_at_runtime() {
+	let service1 = NetworkService::<TestProtocolMessage>::start(NetworkConfiguration::new_local()).unwrap();
+	let service2 = NetworkService::<TestProtocolMessage>::start(NetworkConfiguration::new_local()).unwrap();
+	assert!(service1.boot_nodes().is_empty());
+
+	service1.add_boot_node(&service2.local_url()).unwrap();
+	service1.add_boot_node(&service2.local_url()).unwrap();
+	assert_eq!(service1.boot_nodes(), vec![service2.local_url()]);
+	match service1.add_boot_node("127.0.0.1:30303") {
+		Err(NetworkError::InvalidNodeId) => {},
+		other => panic!("Unexpected result: {:?}", other),
+	}
+
+	service1.remove_boot_node(&service2.local_url()).unwrap();
+	assert!(service1.boot_nodes().is_empty());
+}
+
+#[test]
+fn net_peers_info() {
+	let key1 = KeyPair::create().unwrap();
+	let mut config1 = NetworkConfiguration::new_local();
+	config1.use_secret = Some(key1.secret().clone());
+	let mut service1 = NetworkService::<TestProtocolMessage>::start(config1).unwrap();
+	let handler1 = TestProtocol::register(&mut service1, false);
+	let mut config2 = NetworkConfiguration::new_local();
+	config2.boot_nodes = vec![ service1.local_url() ];
+	let mut service2 = NetworkService::<TestProtocolMessage>::start(config2).unwrap();
+	let handler2 = TestProtocol::register(&mut service2, false);
+	while !(handler1.got_packet() && handler2.got_packet()) {
+		thread::sleep(Duration::from_millis(50));
+	}
+
+	let peers = service2.peers_info();
+	let peer = peers.iter().find(|p| p.id == *key1.public()).expect("Peer should be connected");
+	assert!(peer.client_version.contains("Parity"));
+	assert_eq!(peer.protocol_version, 4);
+	assert_eq!(peer.capabilities, vec![
+		PeerCapabilityInfo { protocol: "test".to_owned(), version: 42 },
+		PeerCapabilityInfo { protocol: "test".to_owned(), version: 43 },
+	]);
+	assert_eq!(peer.negotiated_version("test"), Some(43));
+	assert_eq!(peer.negotiated_version("eth"), None);
+	assert_eq!(peer.remote_address.map(|a| format!("{}", a.ip())), Some("127.0.0.1".to_owned()));
+}
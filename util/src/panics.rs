@@ -38,6 +38,10 @@ pub trait ForwardPanic {
 pub trait MayPanic {
 	/// `closure` will be invoked whenever panic in thread is caught
 	fn on_panic<F>(&self, closure: F) where F: OnPanicListener;
+
+	/// `closure` will be invoked with every alert raised. Alerts report conditions
+	/// the operator should act on, but unlike panics they are not fatal.
+	fn on_alert<F>(&self, _closure: F) where F: OnPanicListener {}
 }
 
 struct PanicGuard<'a> {
@@ -54,7 +58,8 @@ impl<'a> Drop for PanicGuard<'a> {
 
 /// Structure that allows to catch panics and notify listeners
 pub struct PanicHandler {
-	listeners: Mutex<Vec<Box<OnPanicListener>>>
+	listeners: Mutex<Vec<Box<OnPanicListener>>>,
+	alert_listeners: Mutex<Vec<Box<OnPanicListener>>>,
 }
 
 impl Default for PanicHandler {
@@ -72,7 +77,8 @@ impl PanicHandler {
 	/// Creates new `PanicHandler`
 	pub fn new() -> Self {
 		PanicHandler {
-			listeners: Mutex::new(vec![])
+			listeners: Mutex::new(vec![]),
+			alert_listeners: Mutex::new(vec![]),
 		}
 	}
 
@@ -93,18 +99,32 @@ impl PanicHandler {
 			listener.call(&r);
 		}
 	}
+
+	/// Notifies all alert listeners.
+	pub fn notify_alert(&self, r: String) {
+		let mut listeners = self.alert_listeners.lock().unwrap();
+		for listener in listeners.deref_mut() {
+			listener.call(&r);
+		}
+	}
 }
 
 impl MayPanic for PanicHandler {
 	fn on_panic<F>(&self, closure: F) where F: OnPanicListener {
 		self.listeners.lock().unwrap().push(Box::new(closure));
 	}
+
+	fn on_alert<F>(&self, closure: F) where F: OnPanicListener {
+		self.alert_listeners.lock().unwrap().push(Box::new(closure));
+	}
 }
 
 impl ForwardPanic for Arc<PanicHandler> {
 	fn forward_from<S>(&self, child: &S) where S : MayPanic {
 		let p = self.clone();
 		child.on_panic(move |t| p.notify_all(t));
+		let p = self.clone();
+		child.on_alert(move |t| p.notify_alert(t));
 	}
 }
 
@@ -189,3 +209,25 @@ use std::sync::RwLock;
 	// then
 	assert!(invocations.read().unwrap()[0] == "Panic!");
 }
+
+#[test]
+fn should_forward_alerts_without_notifying_panic_listeners() {
+	use std::sync::RwLock;
+	// given
+	let alerts = Arc::new(RwLock::new(vec![]));
+	let panics = Arc::new(RwLock::new(vec![]));
+	let (a, p) = (alerts.clone(), panics.clone());
+	let handler = PanicHandler::new_in_arc();
+	handler.on_alert(move |t| a.write().unwrap().push(t));
+	handler.on_panic(move |t| p.write().unwrap().push(t));
+
+	let child = PanicHandler::new();
+	handler.forward_from(&child);
+
+	// when
+	child.notify_alert("Clock skewed".to_owned());
+
+	// then
+	assert_eq!(*alerts.read().unwrap(), vec!["Clock skewed".to_owned()]);
+	assert!(panics.read().unwrap().is_empty());
+}
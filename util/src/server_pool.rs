@@ -0,0 +1,565 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Worker pool shared by HTTP servers.
+//!
+//! Every server registers with the pool and gets a `ServerPoolHandle`. A request is admitted while
+//! its server is below `server_limit` and the pool as a whole is below its combined limit
+//! (`threads + queue_size`). Each server has `reserved` requests and worker threads that other
+//! servers can't take, so a single busy server can't starve the rest.
+
+use std::fmt;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, Condvar};
+use std::thread::{self, JoinHandle};
+
+/// Shared server pool configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerPoolConfig {
+	/// Number of worker threads.
+	pub threads: usize,
+	/// Number of requests that may wait for a free worker, shared by all servers.
+	pub queue_size: usize,
+	/// Maximal number of requests of a single server admitted at the same time.
+	pub server_limit: usize,
+	/// Number of requests and worker threads reserved for every server.
+	pub reserved: usize,
+}
+
+impl Default for ServerPoolConfig {
+	fn default() -> Self {
+		ServerPoolConfig {
+			threads: 4,
+			queue_size: 64,
+			server_limit: 48,
+			reserved: 1,
+		}
+	}
+}
+
+/// Reason of request rejection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PoolError {
+	/// Server or the whole pool is saturated.
+	Saturated,
+	/// Pool is shutting down.
+	ShuttingDown,
+}
+
+impl fmt::Display for PoolError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			PoolError::Saturated => write!(f, "Server pool is saturated"),
+			PoolError::ShuttingDown => write!(f, "Server pool is shutting down"),
+		}
+	}
+}
+
+/// Utilization of a single server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerStatus {
+	/// Server name.
+	pub name: String,
+	/// Number of requests currently admitted (running, queued or being handled by the listener).
+	pub in_flight: usize,
+	/// Number of requests waiting for a worker.
+	pub queued: usize,
+	/// Number of requests served so far.
+	pub served: u64,
+	/// Number of requests rejected so far.
+	pub rejected: u64,
+}
+
+/// Utilization of the pool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerPoolStatus {
+	/// Number of worker threads.
+	pub threads: usize,
+	/// Number of worker threads running a request.
+	pub busy: usize,
+	/// Status of every registered server.
+	pub servers: Vec<ServerStatus>,
+}
+
+impl ServerPoolStatus {
+	/// Total number of admitted requests.
+	pub fn in_flight(&self) -> usize {
+		self.servers.iter().fold(0, |acc, s| acc + s.in_flight)
+	}
+
+	/// Total number of rejected requests.
+	pub fn rejected(&self) -> u64 {
+		self.servers.iter().fold(0, |acc, s| acc + s.rejected)
+	}
+}
+
+type Job = Box<FnMut() + Send>;
+
+fn boxed<F>(job: F) -> Job where F: FnOnce() + Send + 'static {
+	let mut job = Some(job);
+	Box::new(move || if let Some(job) = job.take() { job() })
+}
+
+struct ServerState {
+	name: String,
+	queue: VecDeque<Job>,
+	running: usize,
+	tickets: usize,
+	served: u64,
+	rejected: u64,
+}
+
+impl ServerState {
+	fn in_flight(&self) -> usize {
+		self.queue.len() + self.running + self.tickets
+	}
+}
+
+struct State {
+	servers: Vec<ServerState>,
+	/// Server checked first when looking for the next job.
+	next: usize,
+	shutdown: bool,
+}
+
+struct Shared {
+	config: ServerPoolConfig,
+	state: Mutex<State>,
+	work_ready: Condvar,
+}
+
+impl Shared {
+	fn lock(&self) -> MutexGuard<State> {
+		self.state.lock().unwrap()
+	}
+
+	/// Checks if request of given server can be admitted, noting the rejection otherwise.
+	fn admit(&self, state: &mut State, server: usize) -> Result<(), PoolError> {
+		if state.shutdown {
+			return Err(PoolError::ShuttingDown);
+		}
+		let reserved = self.config.reserved;
+		let total = state.servers.iter().fold(0, |acc, s| acc + s.in_flight());
+		let reserved_for_others = state.servers.iter().enumerate()
+			.filter(|&(i, _)| i != server)
+			.fold(0, |acc, (_, s)| acc + reserved.saturating_sub(s.in_flight()));
+		let in_flight = state.servers[server].in_flight();
+		let capacity = self.config.threads + self.config.queue_size;
+
+		if in_flight < reserved || (in_flight < self.config.server_limit && total + reserved_for_others < capacity) {
+			Ok(())
+		} else {
+			state.servers[server].rejected += 1;
+			Err(PoolError::Saturated)
+		}
+	}
+
+	/// Returns index of the server to take the next job from.
+	/// Servers are visited in turns and never take worker threads reserved for others.
+	fn next_job(&self, state: &State) -> Option<usize> {
+		let reserved = self.config.reserved;
+		let count = state.servers.len();
+		let running = state.servers.iter().fold(0, |acc, s| acc + s.running);
+		(0..count).map(|i| (state.next + i) % count).find(|&i| {
+			let server = &state.servers[i];
+			let reserved_for_others = state.servers.iter().enumerate()
+				.filter(|&(j, _)| j != i)
+				.fold(0, |acc, (_, s)| acc + reserved.saturating_sub(s.running));
+			!server.queue.is_empty() && (server.running < reserved || running + reserved_for_others < self.config.threads)
+		})
+	}
+
+	/// Blocks until there is a job to run. Returns `None` when the pool is shutting down.
+	fn take_job(&self) -> Option<(usize, Job)> {
+		let mut state = self.lock();
+		loop {
+			if state.shutdown {
+				return None;
+			}
+			if let Some(i) = self.next_job(&state) {
+				state.next = (i + 1) % state.servers.len();
+				let server = &mut state.servers[i];
+				let job = server.queue.pop_front().expect("next_job only returns servers with queued jobs; qed");
+				server.running += 1;
+				return Some((i, job));
+			}
+			state = self.work_ready.wait(state).unwrap();
+		}
+	}
+
+	fn finish_job(&self, server: usize) {
+		{
+			let mut state = self.lock();
+			let server = &mut state.servers[server];
+			server.running -= 1;
+			server.served += 1;
+		}
+		// Released thread may be usable by a different server now.
+		self.work_ready.notify_all();
+	}
+}
+
+/// Worker pool shared by several servers.
+pub struct ServerPool {
+	shared: Arc<Shared>,
+	workers: Vec<JoinHandle<()>>,
+}
+
+impl ServerPool {
+	/// Creates new pool and starts its worker threads.
+	pub fn new(config: ServerPoolConfig) -> Self {
+		let shared = Arc::new(Shared {
+			config: config,
+			state: Mutex::new(State {
+				servers: Vec::new(),
+				next: 0,
+				shutdown: false,
+			}),
+			work_ready: Condvar::new(),
+		});
+		let workers = (0..shared.config.threads).map(|i| {
+			let shared = shared.clone();
+			thread::Builder::new()
+				.name(format!("Server Worker #{}", i))
+				.spawn(move || {
+					while let Some((server, mut job)) = shared.take_job() {
+						job();
+						shared.finish_job(server);
+					}
+				})
+				.expect("Error creating server pool worker thread")
+		}).collect();
+
+		ServerPool {
+			shared: shared,
+			workers: workers,
+		}
+	}
+
+	/// Registers new server and returns handle used to submit its requests.
+	pub fn register(&self, name: &str) -> ServerPoolHandle {
+		let mut state = self.shared.lock();
+		state.servers.push(ServerState {
+			name: name.to_owned(),
+			queue: VecDeque::new(),
+			running: 0,
+			tickets: 0,
+			served: 0,
+			rejected: 0,
+		});
+		ServerPoolHandle {
+			shared: self.shared.clone(),
+			server: state.servers.len() - 1,
+		}
+	}
+
+	/// Returns current utilization of the pool.
+	pub fn status(&self) -> ServerPoolStatus {
+		let state = self.shared.lock();
+		ServerPoolStatus {
+			threads: self.shared.config.threads,
+			busy: state.servers.iter().fold(0, |acc, s| acc + s.running),
+			servers: state.servers.iter().map(|s| ServerStatus {
+				name: s.name.clone(),
+				in_flight: s.in_flight(),
+				queued: s.queue.len(),
+				served: s.served,
+				rejected: s.rejected,
+			}).collect(),
+		}
+	}
+}
+
+impl Drop for ServerPool {
+	fn drop(&mut self) {
+		self.shared.lock().shutdown = true;
+		self.shared.work_ready.notify_all();
+		for worker in self.workers.drain(..) {
+			worker.join().unwrap_or_else(|_| warn!("Server pool worker panicked."));
+		}
+	}
+}
+
+/// Handle of a server registered in `ServerPool`.
+#[derive(Clone)]
+pub struct ServerPoolHandle {
+	shared: Arc<Shared>,
+	server: usize,
+}
+
+impl ServerPoolHandle {
+	/// Queues request to be run on the pool threads.
+	pub fn execute<F>(&self, job: F) -> Result<(), PoolError> where F: FnOnce() + Send + 'static {
+		{
+			let mut state = self.shared.lock();
+			try!(self.shared.admit(&mut state, self.server));
+			state.servers[self.server].queue.push_back(boxed(job));
+		}
+		self.shared.work_ready.notify_all();
+		Ok(())
+	}
+
+	/// Admits request handled outside of the pool threads (e.g. by an asynchronous listener).
+	/// The request counts towards the limits until returned ticket is dropped.
+	pub fn acquire(&self) -> Result<PoolTicket, PoolError> {
+		let mut state = self.shared.lock();
+		try!(self.shared.admit(&mut state, self.server));
+		state.servers[self.server].tickets += 1;
+		Ok(PoolTicket {
+			shared: self.shared.clone(),
+			server: self.server,
+			dispatched: false,
+		})
+	}
+}
+
+/// Request admitted with `ServerPoolHandle::acquire`.
+pub struct PoolTicket {
+	shared: Arc<Shared>,
+	server: usize,
+	dispatched: bool,
+}
+
+impl PoolTicket {
+	/// Queues already admitted request to be run on the pool threads.
+	/// The request counts towards the limits until `job` finishes. If the pool is shutting down `job` is run in place.
+	pub fn execute<F>(mut self, job: F) where F: FnOnce() + Send + 'static {
+		let shared = self.shared.clone();
+		let job = {
+			let mut state = shared.lock();
+			match state.shutdown {
+				true => Some(job),
+				false => {
+					let server = &mut state.servers[self.server];
+					server.tickets -= 1;
+					server.queue.push_back(boxed(job));
+					self.dispatched = true;
+					None
+				},
+			}
+		};
+		match job {
+			Some(job) => job(),
+			None => shared.work_ready.notify_all(),
+		}
+	}
+}
+
+impl Drop for PoolTicket {
+	fn drop(&mut self) {
+		if self.dispatched {
+			return;
+		}
+		let mut state = self.shared.lock();
+		let server = &mut state.servers[self.server];
+		server.tickets -= 1;
+		server.served += 1;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::sync::mpsc::{channel, Receiver};
+	use std::time::Duration;
+	use std::thread;
+	use super::*;
+
+	fn config() -> ServerPoolConfig {
+		ServerPoolConfig {
+			threads: 2,
+			queue_size: 2,
+			server_limit: 4,
+			reserved: 1,
+		}
+	}
+
+	/// Submits request blocking until `release` is signalled. Returns receiver notified when request starts.
+	fn slow_request(handle: &ServerPoolHandle, release: Receiver<()>) -> Result<Receiver<()>, PoolError> {
+		let (started_tx, started) = channel();
+		handle.execute(move || {
+			started_tx.send(()).unwrap();
+			let _ = release.recv();
+		}).map(|_| started)
+	}
+
+	fn wait_for(rx: &Receiver<()>) -> bool {
+		for _ in 0..500 {
+			if rx.try_recv().is_ok() {
+				return true;
+			}
+			thread::sleep(Duration::from_millis(10));
+		}
+		false
+	}
+
+	#[test]
+	fn should_run_requests() {
+		// given
+		let pool = ServerPool::new(ServerPoolConfig::default());
+		let handle = pool.register("rpc");
+		let (tx, rx) = channel();
+
+		// when
+		for i in 0..10 {
+			let tx = tx.clone();
+			handle.execute(move || tx.send(i).unwrap()).unwrap();
+		}
+
+		// then
+		let mut results: Vec<_> = (0..10).map(|_| rx.recv().unwrap()).collect();
+		results.sort();
+		assert_eq!(results, (0..10).collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn should_run_requests_concurrently() {
+		// given
+		let pool = ServerPool::new(config());
+		let rpc = pool.register("rpc");
+		let running = Arc::new(AtomicUsize::new(0));
+		let (tx, rx) = channel();
+
+		// when
+		for _ in 0..2 {
+			let running = running.clone();
+			let tx = tx.clone();
+			rpc.execute(move || {
+				running.fetch_add(1, Ordering::SeqCst);
+				// Sees the other request only if both are running at the same time.
+				let met = (0..500).any(|_| {
+					thread::sleep(Duration::from_millis(10));
+					running.load(Ordering::SeqCst) == 2
+				});
+				tx.send(met).unwrap();
+			}).unwrap();
+		}
+
+		// then
+		assert!(rx.recv().unwrap());
+		assert!(rx.recv().unwrap());
+	}
+
+	#[test]
+	fn should_run_admitted_request_on_pool_thread() {
+		// given
+		let pool = ServerPool::new(config());
+		let rpc = pool.register("rpc");
+		let ticket = rpc.acquire().unwrap();
+		let (release_tx, release) = channel::<()>();
+		let (tx, rx) = channel();
+
+		// when
+		ticket.execute(move || {
+			let _ = release.recv();
+			tx.send(thread::current().name().map(str::to_owned)).unwrap();
+		});
+
+		// then
+		assert_eq!(pool.status().servers[0].in_flight, 1);
+		drop(release_tx);
+		let worker = rx.recv().unwrap().expect("Pool threads are named");
+		assert!(worker.starts_with("Server Worker"), "Unexpected thread: {}", worker);
+	}
+
+	#[test]
+	fn should_keep_reserved_capacity_for_other_server() {
+		// given
+		let pool = ServerPool::new(config());
+		let rpc = pool.register("rpc");
+		let dapps = pool.register("dapps");
+		let mut releases = vec![];
+		let mut started = vec![];
+		for _ in 0..3 {
+			let (release_tx, release) = channel();
+			started.push(slow_request(&rpc, release).unwrap());
+			releases.push(release_tx);
+		}
+
+		// when
+		let (_release_tx, release) = channel();
+		let rejected = slow_request(&rpc, release);
+
+		// then
+		assert!(wait_for(&started[0]));
+		assert_eq!(rejected.err(), Some(PoolError::Saturated));
+
+		// when
+		let (served_tx, served) = channel();
+		dapps.execute(move || served_tx.send(()).unwrap()).unwrap();
+
+		// then
+		assert!(wait_for(&served), "Reserved worker should serve the other server.");
+		let status = pool.status();
+		assert_eq!(status.servers[0].in_flight, 3);
+		assert_eq!(status.servers[0].queued, 2);
+		assert_eq!(status.servers[0].rejected, 1);
+
+		drop(releases);
+	}
+
+	#[test]
+	fn should_reject_past_combined_limit() {
+		// given
+		let pool = ServerPool::new(config());
+		let rpc = pool.register("rpc");
+		let dapps = pool.register("dapps");
+		let mut releases = vec![];
+		for _ in 0..3 {
+			let (release_tx, release) = channel();
+			slow_request(&rpc, release).unwrap();
+			releases.push(release_tx);
+		}
+
+		// when
+		let ticket = dapps.acquire().unwrap();
+		let rejected_dapps = dapps.acquire().err();
+		let rejected_rpc = rpc.acquire().err();
+
+		// then
+		assert_eq!(rejected_dapps, Some(PoolError::Saturated));
+		assert_eq!(rejected_rpc, Some(PoolError::Saturated));
+		assert_eq!(pool.status().in_flight(), 4);
+		assert_eq!(pool.status().rejected(), 2);
+
+		// when
+		drop(ticket);
+
+		// then
+		assert!(dapps.acquire().is_ok());
+		drop(releases);
+	}
+
+	#[test]
+	fn should_limit_requests_of_single_server() {
+		// given
+		let pool = ServerPool::new(ServerPoolConfig {
+			threads: 1,
+			queue_size: 10,
+			server_limit: 2,
+			reserved: 0,
+		});
+		let rpc = pool.register("rpc");
+
+		// when
+		let _first = rpc.acquire().unwrap();
+		let _second = rpc.acquire().unwrap();
+
+		// then
+		assert_eq!(rpc.acquire().err(), Some(PoolError::Saturated));
+	}
+}
@@ -14,13 +14,21 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::str;
 use std::sync::Arc;
+use std::collections::BTreeMap;
+use hyper::{server, Decoder, Encoder, Next};
+use hyper::net::HttpStream;
+use hyper::status::StatusCode;
+use hyper::uri::RequestUri;
+use ethcore_rpc::stats::RpcStats;
 use endpoint::{Endpoint, Endpoints, Handler, EndpointPath};
 
-use api::response::as_json;
+use api::response::{as_json, as_json_with_status, as_cached_json};
 
 pub struct RestApi {
 	endpoints: Arc<Endpoints>,
+	rpc_stats: Arc<RpcStats>,
 }
 
 #[derive(Debug, PartialEq, Serialize)]
@@ -34,13 +42,52 @@ struct App {
 	pub icon_url: String,
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+struct ApiError {
+	code: String,
+	title: String,
+	detail: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Ping {
+	status: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct MethodStats {
+	calls: usize,
+	errors: usize,
+	latency: usize,
+}
+
 impl RestApi {
-	pub fn new(endpoints: Arc<Endpoints>) -> Box<Endpoint> {
+	pub fn new(endpoints: Arc<Endpoints>, rpc_stats: Arc<RpcStats>) -> Box<Endpoint> {
 		Box::new(RestApi {
-			endpoints: endpoints
+			endpoints: endpoints,
+			rpc_stats: rpc_stats,
 		})
 	}
+}
+
+impl Endpoint for RestApi {
+	fn to_handler(&self, _path: EndpointPath) -> Box<Handler> {
+		Box::new(RestApiRouter {
+			endpoints: self.endpoints.clone(),
+			rpc_stats: self.rpc_stats.clone(),
+			handler: None,
+		})
+	}
+}
+
+/// Dispatches request to the API method.
+struct RestApiRouter {
+	endpoints: Arc<Endpoints>,
+	rpc_stats: Arc<RpcStats>,
+	handler: Option<Box<Handler>>,
+}
 
+impl RestApiRouter {
 	fn list_apps(&self) -> Vec<App> {
 		self.endpoints.iter().filter_map(|(ref k, ref e)| {
 			e.info().map(|ref info| App {
@@ -53,11 +100,90 @@ impl RestApi {
 			})
 		}).collect()
 	}
+
+	fn rpc_stats(&self) -> BTreeMap<String, MethodStats> {
+		self.rpc_stats.stats().into_iter().map(|(method, stats)| (method, MethodStats {
+			calls: stats.calls,
+			errors: stats.errors,
+			latency: stats.latency,
+		})).collect()
+	}
+
+	fn resolve(&self, path: &str, if_none_match: Option<&str>) -> Box<Handler> {
+		let (path, query) = split_query(path);
+		let callback = query.and_then(jsonp_callback);
+		let mut parts = path.split('/').filter(|part| !part.is_empty()).skip(1);
+
+		match (parts.next(), parts.next()) {
+			// Listing on the API root is kept for backward compatibility.
+			(None, _) | (Some("apps"), None) => as_cached_json(&self.list_apps(), callback, if_none_match),
+			(Some("ping"), None) => as_json(&Ping { status: "ok".to_owned() }),
+			(Some("stats"), None) => as_json(&self.rpc_stats()),
+			_ => as_json_with_status(&ApiError {
+				code: "404".to_owned(),
+				title: "Not Found".to_owned(),
+				detail: format!("Resource {} does not exist.", path),
+			}, StatusCode::NotFound, Vec::new()),
+		}
+	}
 }
 
-impl Endpoint for RestApi {
-	fn to_handler(&self, _path: EndpointPath) -> Box<Handler> {
-		as_json(&self.list_apps())
+fn split_query(path: &str) -> (&str, Option<&str>) {
+	let mut parts = path.splitn(2, '?');
+	(parts.next().unwrap_or(""), parts.next())
+}
+
+/// Extracts JSONP callback name from the query. Only valid identifiers are accepted.
+fn jsonp_callback(query: &str) -> Option<&str> {
+	query.split('&')
+		.filter_map(|param| {
+			let mut kv = param.splitn(2, '=');
+			match (kv.next(), kv.next()) {
+				(Some("callback"), Some(value)) => Some(value),
+				_ => None,
+			}
+		})
+		.find(|callback| !callback.is_empty() && callback.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '$'))
+}
+
+impl server::Handler<HttpStream> for RestApiRouter {
+	fn on_request(&mut self, request: server::Request) -> Next {
+		let mut handler = {
+			let path = match *request.uri() {
+				RequestUri::AbsolutePath(ref path) => path.clone(),
+				RequestUri::AbsoluteUri(ref url) => match url.query() {
+					Some(query) => format!("{}?{}", url.path(), query),
+					None => url.path().to_owned(),
+				},
+				_ => String::new(),
+			};
+			let if_none_match = request.headers().get_raw("If-None-Match")
+				.and_then(|values| values.first())
+				.and_then(|value| str::from_utf8(value).ok());
+			self.resolve(&path, if_none_match)
+		};
+		let next = handler.on_request(request);
+		self.handler = Some(handler);
+		next
+	}
+
+	fn on_request_readable(&mut self, decoder: &mut Decoder<HttpStream>) -> Next {
+		self.handler.as_mut().expect("on_request is always called first").on_request_readable(decoder)
+	}
+
+	fn on_response(&mut self, response: &mut server::Response) -> Next {
+		self.handler.as_mut().expect("on_request is always called first").on_response(response)
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		self.handler.as_mut().expect("on_request is always called first").on_response_writable(encoder)
 	}
 }
 
+#[test]
+fn should_extract_jsonp_callback() {
+	assert_eq!(split_query("/api/apps?callback=cb"), ("/api/apps", Some("callback=cb")));
+	assert_eq!(jsonp_callback("a=1&callback=parity.apps_1"), Some("parity.apps_1"));
+	assert_eq!(jsonp_callback("callback=alert(1)"), None);
+	assert_eq!(jsonp_callback("callback="), None);
+}
@@ -14,10 +14,54 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use hyper::status::StatusCode;
 use serde::Serialize;
 use serde_json;
+use util::sha3::Hashable;
 use endpoint::{ContentHandler, Handler};
 
 pub fn as_json<T : Serialize>(val: &T) -> Box<Handler> {
-	Box::new(ContentHandler::new(serde_json::to_string(val).unwrap(), "application/json".to_owned()))
+	as_json_with_status(val, StatusCode::Ok, Vec::new())
+}
+
+/// Responds with JSON, given status and additional headers.
+pub fn as_json_with_status<T : Serialize>(val: &T, status: StatusCode, headers: Vec<(&'static str, String)>) -> Box<Handler> {
+	Box::new(ContentHandler::with_status(serde_json::to_string(val).unwrap(), "application/json".to_owned(), status, headers))
+}
+
+/// Responds with JSON (or JSONP if `callback` is given) which can be cached by the client.
+/// Returns `304 Not Modified` if `if_none_match` contains the `ETag` of the content.
+pub fn as_cached_json<T : Serialize>(val: &T, callback: Option<&str>, if_none_match: Option<&str>) -> Box<Handler> {
+	let json = serde_json::to_string(val).unwrap();
+	let (content, mimetype) = match callback {
+		Some(callback) => (format!("{}({});", callback, json), "application/javascript"),
+		None => (json, "application/json"),
+	};
+	let etag = etag(&content);
+	let headers = vec![("ETag", etag.clone())];
+
+	match if_none_match.map_or(false, |tags| etag_matches(tags, &etag)) {
+		true => Box::new(ContentHandler::with_status(String::new(), mimetype.to_owned(), StatusCode::NotModified, headers)),
+		false => Box::new(ContentHandler::with_status(content, mimetype.to_owned(), StatusCode::Ok, headers)),
+	}
+}
+
+/// Computes (strong) `ETag` of given content.
+fn etag(content: &str) -> String {
+	format!("\"{}\"", content.sha3().hex())
+}
+
+/// Checks if value of `If-None-Match` header matches given `ETag`.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+	if_none_match.split(',').map(|tag| tag.trim()).any(|tag| tag == "*" || tag == etag || tag.trim_left_matches("W/") == etag)
+}
+
+#[test]
+fn should_match_etags() {
+	let tag = etag("content");
+	assert!(etag_matches(&tag, &tag));
+	assert!(etag_matches("*", &tag));
+	assert!(etag_matches(&format!("\"other\", W/{}", tag), &tag));
+	assert!(!etag_matches("\"other\"", &tag));
+	assert!(tag != etag("other content"));
 }
@@ -16,7 +16,6 @@
 
 use endpoint::{Endpoints, Endpoint};
 use page::PageEndpoint;
-use proxypac::ProxyPac;
 use parity_webapp::WebApp;
 
 extern crate parity_status;
@@ -27,6 +26,7 @@ pub const DAPPS_DOMAIN : &'static str = ".parity";
 pub const RPC_PATH : &'static str =  "rpc";
 pub const API_PATH : &'static str =  "api";
 pub const UTILS_PATH : &'static str =  "parity-utils";
+pub const PROXY_PATH : &'static str =  "proxy";
 
 pub fn main_page() -> &'static str {
 	"/home/"
@@ -36,9 +36,13 @@ pub fn utils() -> Box<Endpoint> {
 	Box::new(PageEndpoint::with_prefix(parity_idmanager::App::default(), UTILS_PATH.to_owned()))
 }
 
+/// Returns true if given endpoint name is used by built-in endpoints.
+pub fn is_reserved(name: &str) -> bool {
+	[RPC_PATH, API_PATH, UTILS_PATH, PROXY_PATH].contains(&name) || all_endpoints().contains_key(name)
+}
+
 pub fn all_endpoints() -> Endpoints {
 	let mut pages = Endpoints::new();
-	pages.insert("proxy".to_owned(), ProxyPac::boxed());
 
 	insert::<parity_status::App>(&mut pages, "status");
 	insert::<parity_status::App>(&mut pages, "parity");
@@ -19,46 +19,101 @@
 use hyper::status::StatusCode;
 use hyper::{header, server, Decoder, Encoder, Next};
 use hyper::net::HttpStream;
+use ethcore_rpc::pooled::Dispatcher;
 
 use std::io::Write;
+use std::sync::Arc;
 use std::collections::HashMap;
 
+/// Details of the request routed to an endpoint.
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct EndpointPath {
+	/// Name of the endpoint (dapp) the request is routed to.
 	pub app_id: String,
+	/// Requested host.
 	pub host: String,
+	/// Requested port.
 	pub port: u16,
 }
 
+/// Endpoint (dapp) details listed by the API.
 #[derive(Debug, PartialEq)]
 pub struct EndpointInfo {
+	/// Dapp name.
 	pub name: String,
+	/// Dapp description.
 	pub description: String,
+	/// Dapp version.
 	pub version: String,
+	/// Dapp author.
 	pub author: String,
+	/// Dapp icon url.
 	pub icon_url: String,
 }
 
+/// Creates handlers for requests routed to the endpoint.
 pub trait Endpoint : Send + Sync {
+	/// Endpoint details, endpoints without details are not listed by the API.
 	fn info(&self) -> Option<EndpointInfo> { None }
 
+	/// Returns handler of a single request.
 	fn to_handler(&self, path: EndpointPath) -> Box<server::Handler<HttpStream>>;
+
+	/// Returns handler of a single request admitted into the server pool.
+	/// Endpoints doing blocking work should run it through `dispatcher`.
+	fn to_pooled_handler(&self, path: EndpointPath, _dispatcher: Dispatcher) -> Box<server::Handler<HttpStream>> {
+		self.to_handler(path)
+	}
 }
 
 pub type Endpoints = HashMap<String, Box<Endpoint>>;
+/// Request handler.
 pub type Handler = server::Handler<HttpStream>;
 
+/// Endpoint which can be shared between multiple servers.
+pub struct SharedEndpoint(Arc<Box<Endpoint>>);
+
+impl SharedEndpoint {
+	/// Wraps shared endpoint so it can be put into `Endpoints`.
+	pub fn new(endpoint: Arc<Box<Endpoint>>) -> Box<Endpoint> {
+		Box::new(SharedEndpoint(endpoint))
+	}
+}
+
+impl Endpoint for SharedEndpoint {
+	fn info(&self) -> Option<EndpointInfo> {
+		self.0.info()
+	}
+
+	fn to_handler(&self, path: EndpointPath) -> Box<server::Handler<HttpStream>> {
+		self.0.to_handler(path)
+	}
+
+	fn to_pooled_handler(&self, path: EndpointPath, dispatcher: Dispatcher) -> Box<server::Handler<HttpStream>> {
+		self.0.to_pooled_handler(path, dispatcher)
+	}
+}
+
 pub struct ContentHandler {
 	content: String,
 	mimetype: String,
+	status: StatusCode,
+	headers: Vec<(&'static str, String)>,
 	write_pos: usize,
 }
 
 impl ContentHandler {
 	pub fn new(content: String, mimetype: String) -> Self {
+		Self::with_status(content, mimetype, StatusCode::Ok, Vec::new())
+	}
+
+	/// Creates handler responding with given status and additional (raw) headers.
+	pub fn with_status(content: String, mimetype: String, status: StatusCode, headers: Vec<(&'static str, String)>) -> Self {
 		ContentHandler {
 			content: content,
 			mimetype: mimetype,
+			status: status,
+			headers: headers,
 			write_pos: 0
 		}
 	}
@@ -74,8 +129,12 @@ impl server::Handler<HttpStream> for ContentHandler {
 	}
 
 	fn on_response(&mut self, res: &mut server::Response) -> Next {
-		res.set_status(StatusCode::Ok);
+		res.set_status(self.status);
 		res.headers_mut().set(header::ContentType(self.mimetype.parse().unwrap()));
+		res.headers_mut().set(header::ContentLength(self.content.len() as u64));
+		for &(name, ref value) in &self.headers {
+			res.headers_mut().set_raw(name, vec![value.as_bytes().to_vec()]);
+		}
 		Next::write()
 	}
 
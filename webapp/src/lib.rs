@@ -47,11 +47,16 @@
 extern crate log;
 extern crate url;
 extern crate hyper;
+extern crate flate2;
 extern crate serde;
 extern crate serde_json;
 extern crate jsonrpc_core;
 extern crate jsonrpc_http_server;
+extern crate ethcore_rpc;
+extern crate ethcore_util as util;
 extern crate parity_webapp;
+#[cfg(test)]
+extern crate ethcore_devtools as devtools;
 
 mod endpoint;
 mod apps;
@@ -63,74 +68,241 @@ mod proxypac;
 
 use std::sync::{Arc, Mutex};
 use std::net::SocketAddr;
+use std::path::Path;
 use std::collections::HashMap;
 use jsonrpc_core::{IoHandler, IoDelegate};
 use router::auth::{Authorization, NoAuth, HttpBasicAuth};
+use endpoint::{Endpoints, SharedEndpoint};
+use ethcore_rpc::pooled::PooledHandler;
+use ethcore_rpc::stats::RpcStats;
+use ethcore_rpc::read_only::{MethodFilter, FilteredHandler};
+use util::server_pool::ServerPoolHandle;
 
-static DAPPS_DOMAIN : &'static str = ".parity";
+pub use endpoint::{Endpoint, EndpointPath, EndpointInfo, Handler};
 
 /// Webapps HTTP+RPC server build.
 pub struct ServerBuilder {
 	handler: Arc<IoHandler>,
+	filter: Arc<MethodFilter>,
+	pool: Option<ServerPoolHandle>,
+	endpoints: HashMap<String, Arc<Box<Endpoint>>>,
+	allowed_hosts: Option<Vec<String>>,
+	cors_domains: Vec<String>,
+	dapps_domain: String,
+	proxy_target: Option<String>,
+	rpc_stats: Arc<RpcStats>,
 }
 
 impl ServerBuilder {
 	/// Construct new webapps
 	pub fn new() -> Self {
 		ServerBuilder {
-			handler: Arc::new(IoHandler::new())
+			handler: Arc::new(IoHandler::new()),
+			filter: Arc::new(MethodFilter::default()),
+			pool: None,
+			endpoints: HashMap::new(),
+			allowed_hosts: None,
+			cors_domains: Vec::new(),
+			dapps_domain: apps::DAPPS_DOMAIN.to_owned(),
+			proxy_target: None,
+			rpc_stats: Arc::new(RpcStats::default()),
 		}
 	}
 
+	/// Serve dapps under given domain (`.parity` by default), e.g. `wallet.parity`.
+	pub fn with_dapps_domain(&mut self, domain: &str) {
+		self.dapps_domain = match domain.starts_with('.') {
+			true => domain.to_owned(),
+			false => format!(".{}", domain),
+		};
+	}
+
+	/// Proxy address (`host:port`) returned in the PAC file.
+	/// By default it's the address the PAC file was requested from.
+	pub fn with_proxy_target(&mut self, target: &str) {
+		self.proxy_target = Some(target.to_owned());
+	}
+
+	/// Statistics of RPC calls reported by `/api/stats`.
+	pub fn with_rpc_stats(&mut self, stats: Arc<RpcStats>) {
+		self.rpc_stats = stats;
+	}
+
+	/// Accept only requests with one of given `Host` headers (`host` or `host:port`), others get `403`.
+	/// `None` or a list containing `*` accepts any host. Dapps domains are always accepted.
+	pub fn allowed_hosts(&mut self, hosts: Option<Vec<String>>) {
+		self.allowed_hosts = hosts;
+	}
+
+	/// Echo only given origins in `Access-Control-Allow-Origin` header of RPC responses.
+	/// Empty list keeps the default `null` origin, `*` allows any origin.
+	pub fn cors_domains(&mut self, domains: Vec<String>) {
+		self.cors_domains = domains;
+	}
+
+	/// Add custom endpoint (dapp) available under `name` (both as a path prefix and a `.parity` subdomain).
+	/// Fails if the name is already used by a built-in or previously added endpoint.
+	pub fn add_endpoint(&mut self, name: &str, endpoint: Box<Endpoint>) -> Result<(), EndpointError> {
+		if apps::is_reserved(name) || self.endpoints.contains_key(name) {
+			return Err(EndpointError::AlreadyExists(name.to_owned()));
+		}
+		self.endpoints.insert(name.to_owned(), Arc::new(endpoint));
+		Ok(())
+	}
+
+	/// Add endpoint serving files from given local directory under `name`.
+	pub fn add_static_endpoint<P: AsRef<Path>>(&mut self, name: &str, path: P) -> Result<(), EndpointError> {
+		self.add_endpoint(name, Box::new(page::LocalPageEndpoint::new(path.as_ref().to_path_buf())))
+	}
+
+	/// Admit requests into shared server pool.
+	pub fn set_server_pool(&mut self, pool: ServerPoolHandle) {
+		self.pool = Some(pool);
+	}
+
 	/// Add io delegate.
 	pub fn add_delegate<D>(&self, delegate: IoDelegate<D>) where D: Send + Sync + 'static {
 		self.handler.add_delegate(delegate);
 	}
 
+	/// Reject all RPC methods which mutate state of the node or touch keys.
+	/// Applies to methods of delegates added later as well.
+	pub fn restrict_to_read_only(&self) {
+		self.filter.restrict_to_read_only();
+	}
+
+	fn filtered_handler(&self) -> Arc<FilteredHandler> {
+		Arc::new(FilteredHandler::new(self.handler.clone(), self.filter.clone()))
+	}
+
 	/// Asynchronously start server with no authentication,
 	/// returns result with `Server` handle on success or an error.
 	pub fn start_unsecure_http(&self, addr: &SocketAddr) -> Result<Server, ServerError> {
-		Server::start_http(addr, NoAuth, self.handler.clone())
+		self.start_unsecure_http_multi(&[*addr])
+	}
+
+	/// Asynchronously start server with no authentication listening on all given addresses,
+	/// returns result with `Server` handle on success or an error.
+	/// If any of the addresses can't be bound the server is not started at all.
+	pub fn start_unsecure_http_multi(&self, addrs: &[SocketAddr]) -> Result<Server, ServerError> {
+		Server::start_http(addrs, NoAuth, self.filtered_handler(), self.pool.clone(), self.all_endpoints(), self.options())
 	}
 
 	/// Asynchronously start server with `HTTP Basic Authentication`,
 	/// return result with `Server` handle on success or an error.
 	pub fn start_basic_auth_http(&self, addr: &SocketAddr, username: &str, password: &str) -> Result<Server, ServerError> {
-		Server::start_http(addr, HttpBasicAuth::single_user(username, password), self.handler.clone())
+		Server::start_http(
+			&[*addr],
+			HttpBasicAuth::single_user(username, password),
+			self.filtered_handler(),
+			self.pool.clone(),
+			self.all_endpoints(),
+			self.options(),
+		)
+	}
+
+	/// Asynchronously start server with `HTTP Basic Authentication` accepting any of given users
+	/// (`(username, password)` pairs), return result with `Server` handle on success or an error.
+	pub fn start_basic_auth_http_multi(&self, addr: &SocketAddr, users: Vec<(String, String)>) -> Result<Server, ServerError> {
+		Server::start_http(
+			&[*addr],
+			HttpBasicAuth::multiple_users(users.into_iter().collect()),
+			self.filtered_handler(),
+			self.pool.clone(),
+			self.all_endpoints(),
+			self.options(),
+		)
+	}
+
+	fn options(&self) -> ServerOptions {
+		ServerOptions {
+			allowed_hosts: self.allowed_hosts.clone(),
+			cors_domains: self.cors_domains.clone(),
+			dapps_domain: self.dapps_domain.clone(),
+			proxy_target: self.proxy_target.clone(),
+			rpc_stats: self.rpc_stats.clone(),
+		}
+	}
+
+	/// Built-in endpoints merged with the custom ones.
+	fn all_endpoints(&self) -> Endpoints {
+		let mut endpoints = apps::all_endpoints();
+		for (name, endpoint) in &self.endpoints {
+			endpoints.insert(name.clone(), SharedEndpoint::new(endpoint.clone()));
+		}
+		endpoints
 	}
 }
 
+/// Request handling configuration.
+struct ServerOptions {
+	allowed_hosts: Option<Vec<String>>,
+	cors_domains: Vec<String>,
+	dapps_domain: String,
+	proxy_target: Option<String>,
+	rpc_stats: Arc<RpcStats>,
+}
+
 /// Webapps HTTP server.
 pub struct Server {
-	server: Option<hyper::server::Listening>,
+	servers: Vec<hyper::server::Listening>,
 	panic_handler: Arc<Mutex<Option<Box<Fn() -> () + Send>>>>,
 }
 
 impl Server {
-	fn start_http<A: Authorization + 'static>(addr: &SocketAddr, authorization: A, handler: Arc<IoHandler>) -> Result<Server, ServerError> {
+	/// Starts server listening on all `addrs`. If `pool` is given requests are admitted into the shared server pool
+	/// and rejected with `503` when it's saturated.
+	fn start_http<A: Authorization + 'static>(
+		addrs: &[SocketAddr],
+		authorization: A,
+		handler: Arc<FilteredHandler>,
+		pool: Option<ServerPoolHandle>,
+		endpoints: Endpoints,
+		options: ServerOptions,
+	) -> Result<Server, ServerError> {
 		let panic_handler = Arc::new(Mutex::new(None));
 		let authorization = Arc::new(authorization);
-		let endpoints = Arc::new(apps::all_endpoints());
+		let endpoints = Arc::new(endpoints);
 		let special = Arc::new({
 			let mut special = HashMap::new();
-			special.insert(router::SpecialEndpoint::Rpc, rpc::rpc(handler, panic_handler.clone()));
-			special.insert(router::SpecialEndpoint::Api, api::RestApi::new(endpoints.clone()));
+			special.insert(router::SpecialEndpoint::Rpc, rpc::rpc(handler, panic_handler.clone(), options.cors_domains));
+			special.insert(router::SpecialEndpoint::Api, api::RestApi::new(endpoints.clone(), options.rpc_stats.clone()));
 			special.insert(router::SpecialEndpoint::Utils, apps::utils());
+			special.insert(router::SpecialEndpoint::ProxyPac, proxypac::ProxyPac::boxed(endpoints.clone(), options.dapps_domain.clone(), options.proxy_target));
 			special
 		});
 
-		try!(hyper::Server::http(addr))
-			.handle(move |_| router::Router::new(
-				apps::main_page(),
-				endpoints.clone(),
-				special.clone(),
-				authorization.clone(),
-			))
-			.map(|l| Server {
-				server: Some(l),
-				panic_handler: panic_handler,
-			})
-			.map_err(ServerError::from)
+		// Already bound listeners are closed when `server` is dropped on error.
+		let mut server = Server {
+			servers: Vec::with_capacity(addrs.len()),
+			panic_handler: panic_handler,
+		};
+		for addr in addrs {
+			let pool = pool.clone();
+			let endpoints = endpoints.clone();
+			let special = special.clone();
+			let authorization = authorization.clone();
+			let allowed_hosts = options.allowed_hosts.clone();
+			let dapps_domain = options.dapps_domain.clone();
+			let listening = try!(hyper::Server::http(addr)
+				.and_then(|http| http.handle(move |control| PooledHandler::new(pool.as_ref(), control, |dispatcher| router::Router::new(
+					apps::main_page(),
+					endpoints.clone(),
+					special.clone(),
+					authorization.clone(),
+					allowed_hosts.clone(),
+					dapps_domain.clone(),
+					dispatcher,
+				))))
+				.map_err(|e| ServerError::from(e).with_address(addr)));
+			server.servers.push(listening);
+		}
+		Ok(server)
+	}
+
+	/// Returns addresses the server is listening on.
+	pub fn addrs(&self) -> Vec<SocketAddr> {
+		self.servers.iter().map(|server| server.socket).collect()
 	}
 
 	/// Set callback for panics.
@@ -141,7 +313,9 @@ impl Server {
 
 impl Drop for Server {
 	fn drop(&mut self) {
-		self.server.take().unwrap().close()
+		for server in self.servers.drain(..) {
+			server.close();
+		}
 	}
 }
 
@@ -154,6 +328,19 @@ pub enum ServerError {
 	Other(hyper::error::Error),
 }
 
+impl ServerError {
+	/// Adds address which failed to be bound to the error message.
+	fn with_address(self, addr: &SocketAddr) -> Self {
+		match self {
+			ServerError::IoError(e) => ServerError::IoError(std::io::Error::new(e.kind(), format!("{}: {}", addr, e))),
+			e => {
+				warn!(target: "webapp", "Unable to listen on {}: {:?}", addr, e);
+				e
+			},
+		}
+	}
+}
+
 impl From<hyper::error::Error> for ServerError {
 	fn from(err: hyper::error::Error) -> Self {
 		match err {
@@ -162,3 +349,381 @@ impl From<hyper::error::Error> for ServerError {
 		}
 	}
 }
+
+/// Custom endpoint registration error
+#[derive(Debug, PartialEq)]
+pub enum EndpointError {
+	/// Endpoint with given name is already registered or the name is used by a built-in endpoint.
+	AlreadyExists(String),
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs;
+	use std::io::{Read, Write};
+	use std::net::{SocketAddr, TcpStream, TcpListener};
+	use std::str::FromStr;
+	use std::sync::Arc;
+	use std::time::Duration;
+	use serde_json::{self, Value};
+	use flate2::read::GzDecoder;
+	use parity_webapp::{WebApp, File, Info};
+	use ethcore_rpc::stats::RpcStats;
+	use devtools::RandomTempPath;
+	use page::PageEndpoint;
+	use super::{ServerBuilder, ServerError, EndpointError};
+
+	const SCRIPT: &'static [u8] = b"function hello() { return 'hello'; }\nfunction hello() { return 'hello'; }\nfunction hello() { return 'hello'; }\n";
+
+	struct ScriptApp {
+		script: File,
+	}
+
+	impl Default for ScriptApp {
+		fn default() -> Self {
+			ScriptApp {
+				script: File {
+					path: "app.js",
+					content: SCRIPT,
+					content_type: "application/javascript",
+				},
+			}
+		}
+	}
+
+	impl WebApp for ScriptApp {
+		fn file(&self, path: &str) -> Option<&File> {
+			match path {
+				"app.js" => Some(&self.script),
+				_ => None,
+			}
+		}
+
+		fn info(&self) -> Info {
+			Info {
+				name: "Script".to_owned(),
+				version: "1.0".to_owned(),
+				author: "Parity".to_owned(),
+				description: "Serves a script".to_owned(),
+				icon_url: "icon.png".to_owned(),
+			}
+		}
+	}
+
+	/// Address for a test server to listen on, the port is chosen by the OS.
+	fn any_port() -> SocketAddr {
+		SocketAddr::from_str("127.0.0.1:0").unwrap()
+	}
+
+	/// Sends raw request and returns response head and body.
+	fn raw_request_bytes(addr: &SocketAddr, request: &str) -> (String, Vec<u8>) {
+		let mut stream = TcpStream::connect(addr).unwrap();
+		stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+		stream.write_all(request.as_bytes()).unwrap();
+		let mut response = Vec::new();
+		let _ = stream.read_to_end(&mut response);
+		let head_len = response.windows(4).position(|w| w == b"\r\n\r\n").map_or(response.len(), |pos| pos + 4);
+		let body = response.split_off(head_len);
+		(String::from_utf8_lossy(&response).into_owned(), body)
+	}
+
+	fn raw_request(addr: &SocketAddr, request: &str) -> String {
+		let (head, body) = raw_request_bytes(addr, request);
+		head + &String::from_utf8_lossy(&body)
+	}
+
+	fn request(addr: &SocketAddr, host: &str, path: &str) -> String {
+		raw_request(addr, &format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host))
+	}
+
+	fn rpc_request(addr: &SocketAddr, host: &str, origin: &str) -> String {
+		let body = r#"{"jsonrpc":"2.0","method":"web3_clientVersion","params":[],"id":1}"#;
+		raw_request(addr, &format!(
+			"POST /rpc/ HTTP/1.1\r\nHost: {}\r\nOrigin: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			host, origin, body.len(), body
+		))
+	}
+
+	fn response_header<'a>(response: &'a str, name: &str) -> Option<&'a str> {
+		let prefix = format!("{}:", name.to_lowercase());
+		response.lines()
+			.take_while(|line| !line.is_empty())
+			.find(|line| line.to_lowercase().starts_with(&prefix))
+			.map(|line| line[prefix.len()..].trim())
+	}
+
+	fn allow_origin(response: &str) -> Option<&str> {
+		response_header(response, "Access-Control-Allow-Origin")
+	}
+
+	fn response_body(response: &str) -> &str {
+		response.splitn(2, "\r\n\r\n").nth(1).unwrap_or("")
+	}
+
+	#[test]
+	fn should_reject_endpoints_with_reserved_names() {
+		// given
+		let dir = RandomTempPath::create_dir();
+		let mut builder = ServerBuilder::new();
+		builder.add_static_endpoint("local", dir.as_path()).unwrap();
+
+		// when
+		let builtin = builder.add_static_endpoint("status", dir.as_path());
+		let special = builder.add_static_endpoint("rpc", dir.as_path());
+		let duplicate = builder.add_static_endpoint("local", dir.as_path());
+
+		// then
+		assert_eq!(builtin, Err(EndpointError::AlreadyExists("status".to_owned())));
+		assert_eq!(special, Err(EndpointError::AlreadyExists("rpc".to_owned())));
+		assert_eq!(duplicate, Err(EndpointError::AlreadyExists("local".to_owned())));
+	}
+
+	#[test]
+	fn should_serve_static_endpoint_by_path_and_host() {
+		// given
+		let dir = RandomTempPath::create_dir();
+		fs::File::create(dir.as_path().join("index.html")).unwrap().write_all(b"Hello from local dapp").unwrap();
+		let mut builder = ServerBuilder::new();
+		builder.add_static_endpoint("local", dir.as_path()).unwrap();
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+
+		// when
+		let by_path = request(&addr, &addr.to_string(), "/local/index.html");
+		let by_host = request(&addr, "local.parity", "/index.html");
+		let missing = request(&addr, "local.parity", "/missing.html");
+
+		// then
+		assert!(by_path.starts_with("HTTP/1.1 200"), "Unexpected response: {}", by_path);
+		assert!(by_path.ends_with("Hello from local dapp"));
+		assert!(by_host.starts_with("HTTP/1.1 200"), "Unexpected response: {}", by_host);
+		assert!(by_host.ends_with("Hello from local dapp"));
+		assert!(missing.starts_with("HTTP/1.1 404"), "Unexpected response: {}", missing);
+	}
+
+	#[test]
+	fn should_reject_requests_with_not_allowed_host() {
+		// given
+		let mut builder = ServerBuilder::new();
+		builder.allowed_hosts(Some(vec!["127.0.0.1:8080".to_owned()]));
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+
+		// when
+		let allowed = request(&addr, "127.0.0.1:8080", "/status/");
+		let dapps = request(&addr, "status.parity", "/");
+		let rejected = request(&addr, "evil.com:8080", "/status/");
+
+		// then
+		assert!(allowed.starts_with("HTTP/1.1 200"), "Unexpected response: {}", allowed);
+		assert!(dapps.starts_with("HTTP/1.1 200"), "Unexpected response: {}", dapps);
+		assert!(rejected.starts_with("HTTP/1.1 403"), "Unexpected response: {}", rejected);
+	}
+
+	#[test]
+	fn should_accept_any_host_by_default() {
+		// given
+		let mut builder = ServerBuilder::new();
+		builder.allowed_hosts(Some(vec!["*".to_owned()]));
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let default_server = ServerBuilder::new().start_unsecure_http(&any_port()).unwrap();
+
+		// when
+		let wildcard = request(&server.addrs()[0], "evil.com:8080", "/status/");
+		let default = request(&default_server.addrs()[0], "evil.com:8080", "/status/");
+
+		// then
+		assert!(wildcard.starts_with("HTTP/1.1 200"), "Unexpected response: {}", wildcard);
+		assert!(default.starts_with("HTTP/1.1 200"), "Unexpected response: {}", default);
+	}
+
+	#[test]
+	fn should_echo_only_configured_cors_domains() {
+		// given
+		let mut builder = ServerBuilder::new();
+		builder.cors_domains(vec!["http://parity.io".to_owned()]);
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+		let default_server = ServerBuilder::new().start_unsecure_http(&any_port()).unwrap();
+		let default_addr = default_server.addrs()[0];
+		let mut wildcard = ServerBuilder::new();
+		wildcard.cors_domains(vec!["*".to_owned()]);
+		let wildcard_server = wildcard.start_unsecure_http(&any_port()).unwrap();
+		let wildcard_addr = wildcard_server.addrs()[0];
+
+		// when
+		let allowed = rpc_request(&addr, &addr.to_string(), "http://parity.io");
+		let rejected = rpc_request(&addr, &addr.to_string(), "http://evil.com");
+		let default = rpc_request(&default_addr, &default_addr.to_string(), "http://evil.com");
+		let any = rpc_request(&wildcard_addr, &wildcard_addr.to_string(), "http://evil.com");
+
+		// then
+		assert!(allowed.starts_with("HTTP/1.1 200"), "Unexpected response: {}", allowed);
+		assert_eq!(allow_origin(&allowed), Some("http://parity.io"));
+		assert_eq!(allow_origin(&rejected), None);
+		assert_eq!(allow_origin(&default), Some("null"));
+		assert_eq!(allow_origin(&any), Some("*"));
+	}
+
+	#[test]
+	fn should_serve_the_same_endpoints_on_all_addresses() {
+		// given
+		let server = ServerBuilder::new().start_unsecure_http_multi(&[any_port(), any_port()]).unwrap();
+		let addrs = server.addrs();
+		assert_eq!(addrs.len(), 2);
+
+		// when
+		let first = request(&addrs[0], &addrs[0].to_string(), "/status/");
+		let second = request(&addrs[1], &addrs[1].to_string(), "/status/");
+
+		// then
+		assert!(first.starts_with("HTTP/1.1 200"), "Unexpected response: {}", first);
+		assert!(second.starts_with("HTTP/1.1 200"), "Unexpected response: {}", second);
+	}
+
+	#[test]
+	fn should_release_bound_addresses_if_any_fails() {
+		// given
+		let server = ServerBuilder::new().start_unsecure_http(&any_port()).unwrap();
+		let taken = server.addrs()[0];
+		// the listener is dropped right away, leaving the port free
+		let free = TcpListener::bind(any_port()).unwrap().local_addr().unwrap();
+
+		// when
+		let result = ServerBuilder::new().start_unsecure_http_multi(&[free, taken]);
+
+		// then
+		match result {
+			Err(ServerError::IoError(e)) => assert!(format!("{}", e).contains(&taken.to_string())),
+			_ => panic!("Expected binding to fail."),
+		}
+		assert!(ServerBuilder::new().start_unsecure_http(&free).is_ok());
+	}
+
+	#[test]
+	fn should_list_apps_with_etag() {
+		// given
+		let server = ServerBuilder::new().start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+
+		// when
+		let apps = request(&addr, &addr.to_string(), "/api/apps");
+		let etag = response_header(&apps, "ETag").unwrap().to_owned();
+		let cached = raw_request(&addr, &format!(
+			"GET /api/apps HTTP/1.1\r\nHost: {}\r\nIf-None-Match: {}\r\nConnection: close\r\n\r\n", addr, etag
+		));
+		let jsonp = request(&addr, &addr.to_string(), "/api/apps?callback=parity");
+
+		// then
+		assert!(apps.starts_with("HTTP/1.1 200"), "Unexpected response: {}", apps);
+		let apps: Value = serde_json::from_str(response_body(&apps)).unwrap();
+		let status = apps.as_array().unwrap().iter()
+			.map(|app| app.as_object().unwrap())
+			.find(|app| app["id"].as_string() == Some("status"))
+			.expect("Status app should be listed.");
+		for key in &["name", "description", "version", "author", "iconUrl"] {
+			assert!(status.contains_key(*key), "Missing {}", key);
+		}
+		assert!(cached.starts_with("HTTP/1.1 304"), "Unexpected response: {}", cached);
+		assert!(response_body(&jsonp).starts_with("parity(["), "Unexpected response: {}", jsonp);
+		assert!(response_header(&jsonp, "ETag") != Some(etag.as_str()));
+	}
+
+	#[test]
+	fn should_respond_to_ping_and_unknown_api_paths() {
+		// given
+		let server = ServerBuilder::new().start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+
+		// when
+		let ping = request(&addr, &addr.to_string(), "/api/ping");
+		let unknown = request(&addr, &addr.to_string(), "/api/unknown");
+
+		// then
+		assert!(ping.starts_with("HTTP/1.1 200"), "Unexpected response: {}", ping);
+		assert_eq!(response_body(&ping), r#"{"status":"ok"}"#);
+		assert!(unknown.starts_with("HTTP/1.1 404"), "Unexpected response: {}", unknown);
+		let error: Value = serde_json::from_str(response_body(&unknown)).unwrap();
+		assert_eq!(error.as_object().unwrap()["code"].as_string(), Some("404"));
+	}
+
+	#[test]
+	fn should_generate_proxy_pac_with_custom_endpoints() {
+		// given
+		let dir = RandomTempPath::create_dir();
+		let mut builder = ServerBuilder::new();
+		builder.with_dapps_domain("dapp");
+		builder.add_static_endpoint("local", dir.as_path()).unwrap();
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+
+		// when
+		let pac = request(&addr, &addr.to_string(), "/proxy/proxy.pac");
+		let by_host = request(&addr, "status.dapp", "/");
+
+		// then
+		assert!(pac.starts_with("HTTP/1.1 200"), "Unexpected response: {}", pac);
+		assert!(pac.contains(r#"if (host == "local.dapp")"#), "Unexpected response: {}", pac);
+		assert!(pac.contains(r#"if (host == "status.dapp")"#), "Unexpected response: {}", pac);
+		assert!(pac.contains(&format!("PROXY {}", addr)), "Unexpected response: {}", pac);
+		assert!(by_host.starts_with("HTTP/1.1 200"), "Unexpected response: {}", by_host);
+	}
+
+	#[test]
+	fn should_report_rpc_stats() {
+		// given
+		let stats = Arc::new(RpcStats::default());
+		stats.record("eth_call", false, Duration::from_millis(2));
+		stats.record("eth_call", true, Duration::from_millis(1));
+		let mut builder = ServerBuilder::new();
+		builder.with_rpc_stats(stats.clone());
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+
+		// when
+		let response = request(&addr, &addr.to_string(), "/api/stats");
+		stats.reset();
+		let after_reset = request(&addr, &addr.to_string(), "/api/stats");
+
+		// then
+		assert!(response.starts_with("HTTP/1.1 200"), "Unexpected response: {}", response);
+		assert_eq!(response_body(&response), r#"{"eth_call":{"calls":2,"errors":1,"latency":3000}}"#);
+		assert_eq!(response_body(&after_reset), "{}");
+	}
+
+	#[test]
+	fn should_serve_gzipped_pages_only_if_accepted() {
+		// given
+		let mut builder = ServerBuilder::new();
+		builder.add_endpoint("script", Box::new(PageEndpoint::new(ScriptApp::default()))).unwrap();
+		let server = builder.start_unsecure_http(&any_port()).unwrap();
+		let addr = server.addrs()[0];
+		let get = |headers: &str| raw_request_bytes(&addr, &format!(
+			"GET /script/app.js HTTP/1.1\r\nHost: {}\r\n{}Connection: close\r\n\r\n", addr, headers
+		));
+
+		// when
+		let (plain_head, plain_body) = get("");
+		let (gzip_head, gzip_body) = get("Accept-Encoding: deflate, gzip\r\n");
+		let (refused_head, refused_body) = get("Accept-Encoding: gzip;q=0\r\n");
+		let (range_head, range_body) = get("Accept-Encoding: gzip\r\nRange: bytes=0-9\r\n");
+
+		// then
+		assert!(plain_head.starts_with("HTTP/1.1 200"), "Unexpected response: {}", plain_head);
+		assert_eq!(response_header(&plain_head, "Content-Encoding"), None);
+		assert_eq!(&plain_body[..], SCRIPT);
+
+		assert!(gzip_head.starts_with("HTTP/1.1 200"), "Unexpected response: {}", gzip_head);
+		assert_eq!(response_header(&gzip_head, "Content-Encoding"), Some("gzip"));
+		assert_eq!(response_header(&gzip_head, "Content-Length"), Some(&*gzip_body.len().to_string()));
+		assert!(gzip_body.len() < SCRIPT.len());
+		let mut decompressed = Vec::new();
+		GzDecoder::new(&gzip_body[..]).unwrap().read_to_end(&mut decompressed).unwrap();
+		assert_eq!(&decompressed[..], SCRIPT);
+
+		assert_eq!(response_header(&refused_head, "Content-Encoding"), None);
+		assert_eq!(&refused_body[..], SCRIPT);
+		assert_eq!(response_header(&range_head, "Content-Encoding"), None);
+		assert_eq!(&range_body[..], SCRIPT);
+	}
+}
@@ -0,0 +1,161 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Gzip content encoding of served files.
+
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use hyper::header::Headers;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+/// Default limit of compressed content kept in memory by a single endpoint.
+pub const DEFAULT_GZIP_CACHE_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Default)]
+struct Cached {
+	size: usize,
+	files: HashMap<String, Arc<Vec<u8>>>,
+}
+
+/// Compressed variants of served files keyed by their path.
+///
+/// Files are compressed on first request. Variants which don't fit into the size limit
+/// are still compressed and served, but not kept around.
+pub struct GzipCache {
+	max_size: usize,
+	cached: Mutex<Cached>,
+}
+
+impl GzipCache {
+	/// Creates new cache holding at most `max_size` bytes of compressed content.
+	pub fn new(max_size: usize) -> Self {
+		GzipCache {
+			max_size: max_size,
+			cached: Mutex::new(Cached::default()),
+		}
+	}
+
+	/// Returns compressed `content` of the file at `path`, compressing it if it's not cached yet.
+	pub fn get_or_compress(&self, path: &str, content: &[u8]) -> io::Result<Arc<Vec<u8>>> {
+		if let Some(compressed) = self.cached.lock().unwrap().files.get(path) {
+			return Ok(compressed.clone());
+		}
+
+		let compressed = Arc::new(try!(compress(content)));
+		let mut cached = self.cached.lock().unwrap();
+		if !cached.files.contains_key(path) && cached.size + compressed.len() <= self.max_size {
+			cached.size += compressed.len();
+			cached.files.insert(path.to_owned(), compressed.clone());
+		}
+		Ok(compressed)
+	}
+
+	/// Total size of cached compressed content.
+	pub fn size(&self) -> usize {
+		self.cached.lock().unwrap().size
+	}
+}
+
+impl Default for GzipCache {
+	fn default() -> Self {
+		GzipCache::new(DEFAULT_GZIP_CACHE_SIZE)
+	}
+}
+
+fn compress(content: &[u8]) -> io::Result<Vec<u8>> {
+	let mut encoder = GzEncoder::new(Vec::new(), Compression::Default);
+	try!(encoder.write_all(content));
+	encoder.finish()
+}
+
+/// Returns true if gzip is listed in `Accept-Encoding` values with non-zero quality.
+fn accepts_gzip(accept_encoding: &str) -> bool {
+	accept_encoding.split(',').any(|value| {
+		let mut parts = value.split(';').map(str::trim);
+		let coding = parts.next().unwrap_or("");
+		let quality = parts
+			.find(|param| param.starts_with("q="))
+			.and_then(|param| param[2..].parse::<f32>().ok())
+			.unwrap_or(1.0);
+		(coding.to_lowercase() == "gzip" || coding == "*") && quality > 0.0
+	})
+}
+
+/// Returns true if response to a request with given headers can be gzip-encoded.
+///
+/// Range requests are always answered with identity encoding.
+pub fn should_compress(headers: &Headers) -> bool {
+	if headers.get_raw("Range").is_some() {
+		return false;
+	}
+	headers.get_raw("Accept-Encoding").map_or(false, |values| {
+		values.iter().any(|value| accepts_gzip(&String::from_utf8_lossy(value)))
+	})
+}
+
+/// Returns true if content of given type is worth compressing.
+pub fn is_compressible(content_type: &str) -> bool {
+	content_type.starts_with("text/")
+		|| content_type.starts_with("application/javascript")
+		|| content_type.starts_with("application/json")
+		|| content_type.starts_with("image/svg+xml")
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Read;
+	use flate2::read::GzDecoder;
+	use super::{GzipCache, accepts_gzip, is_compressible};
+
+	#[test]
+	fn should_negotiate_gzip() {
+		assert!(accepts_gzip("gzip"));
+		assert!(accepts_gzip("deflate, GZIP;q=0.5"));
+		assert!(accepts_gzip("*"));
+		assert!(!accepts_gzip("gzip;q=0"));
+		assert!(!accepts_gzip("deflate, br"));
+		assert!(!accepts_gzip(""));
+	}
+
+	#[test]
+	fn should_compress_text_content_only() {
+		assert!(is_compressible("text/html"));
+		assert!(is_compressible("application/javascript"));
+		assert!(!is_compressible("image/png"));
+	}
+
+	#[test]
+	fn should_cache_compressed_content_up_to_the_limit() {
+		// given
+		let content = b"var x = 1;var x = 1;var x = 1;var x = 1;var x = 1;var x = 1;";
+		let cache = GzipCache::new(50);
+
+		// when
+		let first = cache.get_or_compress("app.js", content).unwrap();
+		let second = cache.get_or_compress("app.js", content).unwrap();
+		let other = cache.get_or_compress("other.js", content).unwrap();
+
+		// then
+		let mut decompressed = Vec::new();
+		GzDecoder::new(&first[..]).unwrap().read_to_end(&mut decompressed).unwrap();
+		assert_eq!(&decompressed[..], &content[..]);
+		assert_eq!(first, second);
+		assert_eq!(first, other);
+		assert_eq!(cache.size(), first.len());
+	}
+}
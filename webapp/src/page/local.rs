@@ -0,0 +1,159 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf, Component};
+use hyper::uri::RequestUri;
+use hyper::server;
+use hyper::header;
+use hyper::status::StatusCode;
+use hyper::net::HttpStream;
+use hyper::{Decoder, Encoder, Next};
+use endpoint::{Endpoint, EndpointPath};
+
+/// Serves files from a local directory.
+pub struct LocalPageEndpoint {
+	path: PathBuf,
+}
+
+impl LocalPageEndpoint {
+	/// Creates new endpoint serving files from given directory.
+	pub fn new(path: PathBuf) -> Self {
+		LocalPageEndpoint {
+			path: path,
+		}
+	}
+}
+
+impl Endpoint for LocalPageEndpoint {
+	fn to_handler(&self, path: EndpointPath) -> Box<server::Handler<HttpStream>> {
+		Box::new(LocalPageHandler {
+			root: self.path.clone(),
+			path: path,
+			file: None,
+			write_pos: 0,
+		})
+	}
+}
+
+struct LocalFile {
+	content: Vec<u8>,
+	content_type: &'static str,
+}
+
+struct LocalPageHandler {
+	root: PathBuf,
+	path: EndpointPath,
+	file: Option<LocalFile>,
+	write_pos: usize,
+}
+
+impl LocalPageHandler {
+	fn read_file(&self, path: &str) -> Option<LocalFile> {
+		let file_path = Path::new(path);
+		// Don't allow escaping the root directory
+		let escapes = file_path.components().any(|c| match c {
+			Component::Normal(_) => false,
+			_ => true,
+		});
+		if escapes {
+			return None;
+		}
+		let file_path = self.root.join(file_path);
+		let mut content = Vec::new();
+		match fs::File::open(&file_path).and_then(|mut f| f.read_to_end(&mut content)) {
+			Ok(_) => Some(LocalFile {
+				content: content,
+				content_type: content_type(&file_path),
+			}),
+			Err(e) => {
+				trace!(target: "webapp", "Unable to read {:?}: {:?}", file_path, e);
+				None
+			},
+		}
+	}
+}
+
+/// Guesses content type of the file from its extension.
+fn content_type(path: &Path) -> &'static str {
+	match path.extension().and_then(|e| e.to_str()) {
+		Some("html") | Some("htm") => "text/html",
+		Some("js") => "application/javascript",
+		Some("css") => "text/css",
+		Some("json") => "application/json",
+		Some("txt") => "text/plain",
+		Some("png") => "image/png",
+		Some("jpg") | Some("jpeg") => "image/jpeg",
+		Some("gif") => "image/gif",
+		Some("svg") => "image/svg+xml",
+		Some("ico") => "image/x-icon",
+		_ => "application/octet-stream",
+	}
+}
+
+impl server::Handler<HttpStream> for LocalPageHandler {
+	fn on_request(&mut self, req: server::Request) -> Next {
+		let path = match *req.uri() {
+			RequestUri::AbsolutePath(ref path) => Some(super::extract_path(&self.path.app_id, path)),
+			RequestUri::AbsoluteUri(ref url) => Some(super::extract_path(&self.path.app_id, url.path())),
+			_ => None,
+		};
+		self.file = path.and_then(|path| self.read_file(&path));
+		Next::write()
+	}
+
+	fn on_request_readable(&mut self, _decoder: &mut Decoder<HttpStream>) -> Next {
+		Next::write()
+	}
+
+	fn on_response(&mut self, res: &mut server::Response) -> Next {
+		if let Some(ref f) = self.file {
+			res.set_status(StatusCode::Ok);
+			res.headers_mut().set(header::ContentType(f.content_type.parse().unwrap()));
+			res.headers_mut().set(header::ContentLength(f.content.len() as u64));
+			Next::write()
+		} else {
+			res.set_status(StatusCode::NotFound);
+			Next::write()
+		}
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		let (wrote, res) = match self.file {
+			None => (None, Next::end()),
+			Some(ref f) if self.write_pos == f.content.len() => (None, Next::end()),
+			Some(ref f) => match encoder.write(&f.content[self.write_pos..]) {
+				Ok(bytes) => (Some(bytes), Next::write()),
+				Err(e) => match e.kind() {
+					::std::io::ErrorKind::WouldBlock => (None, Next::write()),
+					_ => (None, Next::end())
+				},
+			}
+		};
+		if let Some(bytes) = wrote {
+			self.write_pos += bytes;
+		}
+		res
+	}
+}
+
+#[test]
+fn should_guess_content_type() {
+	assert_eq!(content_type(Path::new("index.html")), "text/html");
+	assert_eq!(content_type(Path::new("js/app.js")), "application/javascript");
+	assert_eq!(content_type(Path::new("data")), "application/octet-stream");
+}
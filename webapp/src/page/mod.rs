@@ -25,11 +25,19 @@ use hyper::{Decoder, Encoder, Next};
 use endpoint::{Endpoint, EndpointInfo, EndpointPath};
 use parity_webapp::{WebApp, Info};
 
+mod local;
+mod gzip;
+
+pub use self::local::LocalPageEndpoint;
+use self::gzip::GzipCache;
+
 pub struct PageEndpoint<T : WebApp + 'static> {
 	/// Content of the files
 	pub app: Arc<T>,
 	/// Prefix to strip from the path (when `None` deducted from `app_id`)
 	pub prefix: Option<String>,
+	/// Gzip-compressed variants of the files
+	gzip_cache: Arc<GzipCache>,
 }
 
 impl<T: WebApp + 'static> PageEndpoint<T> {
@@ -37,6 +45,7 @@ impl<T: WebApp + 'static> PageEndpoint<T> {
 		PageEndpoint {
 			app: Arc::new(app),
 			prefix: None,
+			gzip_cache: Arc::new(GzipCache::default()),
 		}
 	}
 
@@ -44,6 +53,7 @@ impl<T: WebApp + 'static> PageEndpoint<T> {
 		PageEndpoint {
 			app: Arc::new(app),
 			prefix: Some(prefix),
+			gzip_cache: Arc::new(GzipCache::default()),
 		}
 	}
 }
@@ -60,6 +70,9 @@ impl<T: WebApp> Endpoint for PageEndpoint<T> {
 			prefix: self.prefix.clone(),
 			path: path,
 			file: None,
+			gzip_cache: self.gzip_cache.clone(),
+			accepts_gzip: false,
+			gzipped: None,
 			write_pos: 0,
 		})
 	}
@@ -82,26 +95,48 @@ struct PageHandler<T: WebApp + 'static> {
 	prefix: Option<String>,
 	path: EndpointPath,
 	file: Option<String>,
+	gzip_cache: Arc<GzipCache>,
+	accepts_gzip: bool,
+	gzipped: Option<Arc<Vec<u8>>>,
 	write_pos: usize,
 }
 
 impl<T: WebApp + 'static> PageHandler<T> {
 	fn extract_path(&self, path: &str) -> String {
-		let app_id = &self.path.app_id;
-		let prefix = "/".to_owned() + self.prefix.as_ref().unwrap_or(app_id);
-		let prefix_with_slash = prefix.clone() + "/";
-		let query_pos = path.find('?').unwrap_or_else(|| path.len());
-
-		// Index file support
-		match path == "/" || path == &prefix || path == &prefix_with_slash {
-			true => "index.html".to_owned(),
-			false => if path.starts_with(&prefix_with_slash) {
-				path[prefix_with_slash.len()..query_pos].to_owned()
-			} else if path.starts_with("/") {
-				path[1..query_pos].to_owned()
-			} else {
-				path[0..query_pos].to_owned()
-			}
+		extract_path(self.prefix.as_ref().unwrap_or(&self.path.app_id), path)
+	}
+
+	/// Returns compressed content of the file if it's worth sending instead of the original.
+	fn compressed_content(&self, path: &str, content: &[u8], content_type: &str) -> Option<Arc<Vec<u8>>> {
+		if !self.accepts_gzip || !gzip::is_compressible(content_type) {
+			return None;
+		}
+		match self.gzip_cache.get_or_compress(path, content) {
+			Ok(ref compressed) if compressed.len() >= content.len() => None,
+			Ok(compressed) => Some(compressed),
+			Err(e) => {
+				warn!(target: "webapp", "Unable to compress {}: {:?}", path, e);
+				None
+			},
+		}
+	}
+}
+
+/// Returns path of the requested file relative to the application root.
+fn extract_path(prefix: &str, path: &str) -> String {
+	let prefix = "/".to_owned() + prefix;
+	let prefix_with_slash = prefix.clone() + "/";
+	let query_pos = path.find('?').unwrap_or_else(|| path.len());
+
+	// Index file support
+	match path == "/" || path == &prefix || path == &prefix_with_slash {
+		true => "index.html".to_owned(),
+		false => if path.starts_with(&prefix_with_slash) {
+			path[prefix_with_slash.len()..query_pos].to_owned()
+		} else if path.starts_with("/") {
+			path[1..query_pos].to_owned()
+		} else {
+			path[0..query_pos].to_owned()
 		}
 	}
 }
@@ -117,6 +152,7 @@ impl<T: WebApp + 'static> server::Handler<HttpStream> for PageHandler<T> {
 			},
 			_ => None,
 		};
+		self.accepts_gzip = gzip::should_compress(req.headers());
 		Next::write()
 	}
 
@@ -125,9 +161,21 @@ impl<T: WebApp + 'static> server::Handler<HttpStream> for PageHandler<T> {
 	}
 
 	fn on_response(&mut self, res: &mut server::Response) -> Next {
-		if let Some(f) = self.file.as_ref().and_then(|f| self.app.file(f)) {
+		let app = self.app.clone();
+		if let Some((path, f)) = self.file.as_ref().and_then(|path| app.file(path).map(|f| (path, f))) {
+			self.gzipped = self.compressed_content(path, &f.content, &f.content_type);
 			res.set_status(StatusCode::Ok);
 			res.headers_mut().set(header::ContentType(f.content_type.parse().unwrap()));
+			res.headers_mut().set_raw("Vary", vec![b"Accept-Encoding".to_vec()]);
+			match self.gzipped {
+				Some(ref compressed) => {
+					res.headers_mut().set(header::ContentEncoding(vec![header::Encoding::Gzip]));
+					res.headers_mut().set(header::ContentLength(compressed.len() as u64));
+				},
+				None => {
+					res.headers_mut().set(header::ContentLength(f.content.len() as u64));
+				},
+			}
 			Next::write()
 		} else {
 			res.set_status(StatusCode::NotFound);
@@ -137,11 +185,14 @@ impl<T: WebApp + 'static> server::Handler<HttpStream> for PageHandler<T> {
 
 	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
 		let (wrote, res) = {
-			let file = self.file.as_ref().and_then(|f| self.app.file(f));
-			match file {
+			let content = match self.gzipped {
+				Some(ref compressed) => Some(&compressed[..]),
+				None => self.file.as_ref().and_then(|f| self.app.file(f)).map(|f| &f.content[..]),
+			};
+			match content {
 				None => (None, Next::end()),
-				Some(f) if self.write_pos == f.content.len() => (None, Next::end()),
-				Some(f) => match encoder.write(&f.content[self.write_pos..]) {
+				Some(content) if self.write_pos == content.len() => (None, Next::end()),
+				Some(content) => match encoder.write(&content[self.write_pos..]) {
 					Ok(bytes) => (Some(bytes), Next::write()),
 					Err(e) => match e.kind() {
 						::std::io::ErrorKind::WouldBlock => (None, Next::write()),
@@ -191,6 +242,9 @@ fn should_extract_path_with_appid() {
 			port: 8080
 		},
 		file: None,
+		gzip_cache: Arc::new(GzipCache::default()),
+		accepts_gzip: false,
+		gzipped: None,
 		write_pos: 0,
 	};
 
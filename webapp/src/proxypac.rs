@@ -16,33 +16,110 @@
 
 //! Serving ProxyPac file
 
-use endpoint::{Endpoint, Handler, ContentHandler, EndpointPath};
-use apps::DAPPS_DOMAIN;
+use std::sync::Arc;
+use endpoint::{Endpoint, Endpoints, Handler, ContentHandler, EndpointPath};
 
-pub struct ProxyPac;
+/// Generates PAC file routing all known dapps domains through the server.
+pub struct ProxyPac {
+	endpoints: Arc<Endpoints>,
+	dapps_domain: String,
+	proxy_target: Option<String>,
+}
 
 impl ProxyPac {
-	pub fn boxed() -> Box<Endpoint> {
-		Box::new(ProxyPac)
+	/// Creates PAC endpoint. Unless `proxy_target` is given, requests are proxied
+	/// through the host and port the PAC file was requested from.
+	pub fn boxed(endpoints: Arc<Endpoints>, dapps_domain: String, proxy_target: Option<String>) -> Box<Endpoint> {
+		Box::new(ProxyPac {
+			endpoints: endpoints,
+			dapps_domain: dapps_domain,
+			proxy_target: proxy_target,
+		})
 	}
-}
 
-impl Endpoint for ProxyPac {
-	fn to_handler(&self, path: EndpointPath) -> Box<Handler> {
-		let content = format!(
+	fn content(&self, path: &EndpointPath) -> String {
+		let proxy = self.proxy_target.clone().unwrap_or_else(|| format!("{}:{}", path.host, path.port));
+		let mut apps = self.endpoints.keys().collect::<Vec<_>>();
+		apps.sort();
+		let apps = apps.into_iter()
+			.map(|app| format!(
+r#"	if (host == "{0}{1}")
+	{{
+		return "PROXY {2}";
+	}}
+"#,
+				app, self.dapps_domain, proxy))
+			.collect::<Vec<_>>()
+			.join("\n");
+
+		format!(
 r#"
 function FindProxyForURL(url, host) {{
-	if (shExpMatch(host, "*{0}"))
+{0}
+	if (shExpMatch(host, "*{1}"))
 	{{
-		return "PROXY {1}:{2}";
+		return "PROXY {2}";
 	}}
 
 	return "DIRECT";
 }}
 "#,
-			DAPPS_DOMAIN, path.host, path.port);
-		Box::new(ContentHandler::new(content, "application/javascript".to_owned()))
+			apps, self.dapps_domain, proxy)
 	}
 }
 
+impl Endpoint for ProxyPac {
+	fn to_handler(&self, path: EndpointPath) -> Box<Handler> {
+		Box::new(ContentHandler::new(self.content(&path), "application/javascript".to_owned()))
+	}
+}
 
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use endpoint::{Endpoints, EndpointPath};
+	use page::LocalPageEndpoint;
+	use super::ProxyPac;
+
+	fn pac(proxy_target: Option<String>) -> ProxyPac {
+		let mut endpoints = Endpoints::new();
+		endpoints.insert("local".to_owned(), Box::new(LocalPageEndpoint::new(".".into())));
+		ProxyPac {
+			endpoints: Arc::new(endpoints),
+			dapps_domain: ".dapp".to_owned(),
+			proxy_target: proxy_target,
+		}
+	}
+
+	#[test]
+	fn should_proxy_registered_endpoints_through_requested_host() {
+		// given
+		let pac = pac(None);
+		let path = EndpointPath {
+			app_id: "proxy".to_owned(),
+			host: "127.0.0.1".to_owned(),
+			port: 8080,
+		};
+
+		// when
+		let content = pac.content(&path);
+
+		// then
+		assert!(content.contains(r#"if (host == "local.dapp")"#), "Unexpected content: {}", content);
+		assert!(content.contains(r#"shExpMatch(host, "*.dapp")"#));
+		assert!(content.contains("PROXY 127.0.0.1:8080"));
+	}
+
+	#[test]
+	fn should_use_configured_proxy_target() {
+		// given
+		let pac = pac(Some("10.0.0.1:8000".to_owned()));
+
+		// when
+		let content = pac.content(&EndpointPath::default());
+
+		// then
+		assert!(content.contains("PROXY 10.0.0.1:8000"));
+		assert!(!content.contains("PROXY :0"));
+	}
+}
@@ -21,6 +21,8 @@ use std::collections::HashMap;
 use hyper::{header, server, Decoder, Encoder, Next};
 use hyper::net::HttpStream;
 use hyper::status::StatusCode;
+use util::hash::{H256, FixedHash};
+use util::sha3::Hashable;
 
 /// Authorization result
 pub enum Authorized {
@@ -36,9 +38,40 @@ pub trait Authorization : Send + Sync {
 	fn is_authorized(&self, req: &server::Request)-> Authorized;
 }
 
+/// Salted hash of the password.
+struct HashedPassword {
+	salt: H256,
+	hash: H256,
+}
+
+impl HashedPassword {
+	fn new(password: &str) -> Self {
+		let salt = H256::random();
+		HashedPassword {
+			hash: hash_password(&salt, password),
+			salt: salt,
+		}
+	}
+
+	/// Checks if given password matches. Hashes are compared in constant time.
+	fn matches(&self, password: &str) -> bool {
+		constant_time_eq(&hash_password(&self.salt, password), &self.hash)
+	}
+}
+
+fn hash_password(salt: &H256, password: &str) -> H256 {
+	let mut data = salt.to_vec();
+	data.extend_from_slice(password.as_bytes());
+	data.sha3()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// HTTP Basic Authorization handler
 pub struct HttpBasicAuth {
-	users: HashMap<String, String>,
+	users: HashMap<String, HashedPassword>,
 }
 
 /// No-authorization implementation (authorization disabled)
@@ -52,7 +85,7 @@ impl Authorization for NoAuth {
 
 impl Authorization for HttpBasicAuth {
 	fn is_authorized(&self, req: &server::Request) -> Authorized {
-		let auth = self.check_auth(&req);
+		let auth = self.check_auth(req.headers());
 
 		match auth {
 			Access::Denied => {
@@ -68,7 +101,7 @@ impl Authorization for HttpBasicAuth {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 enum Access {
 	Granted,
 	Denied,
@@ -80,17 +113,25 @@ impl HttpBasicAuth {
 	pub fn single_user(username: &str, password: &str) -> Self {
 		let mut users = HashMap::new();
 		users.insert(username.to_owned(), password.to_owned());
+		Self::multiple_users(users)
+	}
+
+	/// Creates `HttpBasicAuth` instance with given users (username to password map).
+	/// Only salted hashes of the passwords are kept.
+	pub fn multiple_users(users: HashMap<String, String>) -> Self {
 		HttpBasicAuth {
-			users: users
+			users: users.into_iter()
+				.map(|(username, password)| (username, HashedPassword::new(&password)))
+				.collect(),
 		}
 	}
 
 	fn is_authorized(&self, username: &str, password: &str) -> bool {
-		self.users.get(&username.to_owned()).map_or(false, |pass| pass == password)
+		self.users.get(username).map_or(false, |hashed| hashed.matches(password))
 	}
 
-	fn check_auth(&self, req: &server::Request) -> Access {
-		match req.headers().get::<header::Authorization<header::Basic>>() {
+	fn check_auth(&self, headers: &header::Headers) -> Access {
+		match headers.get::<header::Authorization<header::Basic>>() {
 			Some(&header::Authorization(
 				header::Basic { ref username, password: Some(ref password) }
 			)) if self.is_authorized(username, password) => Access::Granted,
@@ -115,6 +156,7 @@ impl server::Handler<HttpStream> for UnauthorizedHandler {
 
 	fn on_response(&mut self, res: &mut server::Response) -> Next {
 		res.set_status(StatusCode::Unauthorized);
+		res.headers_mut().set_raw("WWW-Authenticate", vec![b"Basic realm=\"Parity\"".to_vec()]);
 		Next::write()
 	}
 
@@ -159,3 +201,67 @@ impl server::Handler<HttpStream> for AuthRequiredHandler {
 		Next::end()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+	use hyper::header::{Headers, Authorization, Basic};
+	use super::{HttpBasicAuth, Access};
+
+	fn auth() -> HttpBasicAuth {
+		let mut users = HashMap::new();
+		users.insert("alice".to_owned(), "secret".to_owned());
+		users.insert("bob".to_owned(), "password".to_owned());
+		HttpBasicAuth::multiple_users(users)
+	}
+
+	fn basic(username: &str, password: &str) -> Headers {
+		let mut headers = Headers::new();
+		headers.set(Authorization(Basic { username: username.to_owned(), password: Some(password.to_owned()) }));
+		headers
+	}
+
+	#[test]
+	fn should_grant_access_to_any_configured_user() {
+		// given
+		let auth = auth();
+
+		// then
+		assert_eq!(auth.check_auth(&basic("alice", "secret")), Access::Granted);
+		assert_eq!(auth.check_auth(&basic("bob", "password")), Access::Granted);
+	}
+
+	#[test]
+	fn should_deny_wrong_password_and_unknown_user() {
+		// given
+		let auth = auth();
+
+		// then
+		assert_eq!(auth.check_auth(&basic("bob", "secret")), Access::Denied);
+		assert_eq!(auth.check_auth(&basic("carol", "secret")), Access::Denied);
+	}
+
+	#[test]
+	fn should_require_auth_when_header_is_missing_or_malformed() {
+		// given
+		let auth = auth();
+		let mut malformed = Headers::new();
+		malformed.set_raw("Authorization", vec![b"Basic !!!not-base64".to_vec()]);
+
+		// then
+		assert_eq!(auth.check_auth(&Headers::new()), Access::AuthRequired);
+		assert_eq!(auth.check_auth(&malformed), Access::AuthRequired);
+	}
+
+	#[test]
+	fn should_not_store_plaintext_passwords() {
+		// given
+		let auth = HttpBasicAuth::single_user("alice", "secret");
+		let other = HttpBasicAuth::single_user("alice", "secret");
+
+		// then
+		assert!(auth.users["alice"].hash != other.users["alice"].hash);
+		assert!(auth.is_authorized("alice", "secret"));
+		assert!(other.is_authorized("alice", "secret"));
+	}
+}
@@ -0,0 +1,103 @@
+// Copyright 2015, 2016 Ethcore (UK) Ltd.
+// This file is part of Parity.
+
+// Parity is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Parity is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Parity.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of the `Host` header (protection against DNS rebinding).
+
+use std::io::Write;
+use hyper::{header, server, Decoder, Encoder, Next};
+use hyper::net::HttpStream;
+use hyper::status::StatusCode;
+
+/// Checks if `Host` header of the request is one of `allowed_hosts`.
+///
+/// `None` or a list containing `*` allows any host. Hosts in `dapps_domain` are always allowed.
+pub fn is_valid(req: &server::Request, allowed_hosts: &Option<Vec<String>>, dapps_domain: &str) -> bool {
+	match *allowed_hosts {
+		None => true,
+		Some(ref hosts) => match req.headers().get::<header::Host>() {
+			Some(host) => is_allowed(&host.hostname, host.port, hosts, dapps_domain),
+			None => false,
+		},
+	}
+}
+
+fn is_allowed(hostname: &str, port: Option<u16>, allowed_hosts: &[String], dapps_domain: &str) -> bool {
+	if hostname.ends_with(dapps_domain) {
+		return true;
+	}
+	let host_with_port = port.map(|port| format!("{}:{}", hostname, port));
+	allowed_hosts.iter().any(|allowed| {
+		allowed == "*" || allowed == hostname || host_with_port.as_ref().map_or(false, |host| host == allowed)
+	})
+}
+
+pub struct HostInvalidHandler {
+	write_pos: usize,
+}
+
+impl HostInvalidHandler {
+	pub fn new() -> Box<Self> {
+		Box::new(HostInvalidHandler {
+			write_pos: 0,
+		})
+	}
+}
+
+impl server::Handler<HttpStream> for HostInvalidHandler {
+	fn on_request(&mut self, _request: server::Request) -> Next {
+		Next::write()
+	}
+
+	fn on_request_readable(&mut self, _decoder: &mut Decoder<HttpStream>) -> Next {
+		Next::write()
+	}
+
+	fn on_response(&mut self, res: &mut server::Response) -> Next {
+		res.set_status(StatusCode::Forbidden);
+		Next::write()
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		let response = "Provided Host header is not whitelisted.".as_bytes();
+
+		if self.write_pos == response.len() {
+			return Next::end();
+		}
+
+		match encoder.write(&response[self.write_pos..]) {
+			Ok(bytes) => {
+				self.write_pos += bytes;
+				Next::write()
+			},
+			Err(e) => match e.kind() {
+				::std::io::ErrorKind::WouldBlock => Next::write(),
+				_ => Next::end()
+			},
+		}
+	}
+}
+
+#[test]
+fn should_allow_only_whitelisted_hosts() {
+	let hosts = vec!["localhost".to_owned(), "127.0.0.1:8080".to_owned()];
+
+	assert!(is_allowed("localhost", Some(8080), &hosts, ".parity"));
+	assert!(is_allowed("127.0.0.1", Some(8080), &hosts, ".parity"));
+	assert!(is_allowed("wallet.parity", None, &hosts, ".parity"));
+	assert!(!is_allowed("127.0.0.1", Some(8081), &hosts, ".parity"));
+	assert!(!is_allowed("evil.com", Some(8080), &hosts, ".parity"));
+	assert!(is_allowed("evil.com", Some(8080), &["*".to_owned()], ".parity"));
+}
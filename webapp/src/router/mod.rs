@@ -19,9 +19,9 @@
 
 mod url;
 mod redirect;
+mod host_validation;
 pub mod auth;
 
-use DAPPS_DOMAIN;
 use std::sync::Arc;
 use std::collections::HashMap;
 use url::Host;
@@ -29,11 +29,13 @@ use hyper;
 use hyper::{server, uri, header};
 use hyper::{Next, Encoder, Decoder};
 use hyper::net::HttpStream;
+use ethcore_rpc::pooled::Dispatcher;
 use apps;
-use endpoint::{Endpoint, Endpoints, EndpointPath};
+use endpoint::{Endpoint, Endpoints, EndpointPath, Handler};
 use self::url::Url;
 use self::auth::{Authorization, Authorized};
 use self::redirect::Redirection;
+use self::host_validation::HostInvalidHandler;
 
 /// Special endpoints are accessible on every domain (every dapp)
 #[derive(Debug, PartialEq, Hash, Eq)]
@@ -41,6 +43,7 @@ pub enum SpecialEndpoint {
 	Rpc,
 	Api,
 	Utils,
+	ProxyPac,
 	None,
 }
 
@@ -49,12 +52,29 @@ pub struct Router<A: Authorization + 'static> {
 	endpoints: Arc<Endpoints>,
 	special: Arc<HashMap<SpecialEndpoint, Box<Endpoint>>>,
 	authorization: Arc<A>,
+	allowed_hosts: Option<Vec<String>>,
+	dapps_domain: String,
+	dispatcher: Option<Dispatcher>,
 	handler: Box<server::Handler<HttpStream>>,
 }
 
+/// Returns handler of the endpoint, running its blocking part through `dispatcher`.
+fn endpoint_handler(endpoint: &Endpoint, path: EndpointPath, dispatcher: Option<Dispatcher>) -> Box<Handler> {
+	match dispatcher {
+		Some(dispatcher) => endpoint.to_pooled_handler(path, dispatcher),
+		None => endpoint.to_handler(path),
+	}
+}
+
 impl<A: Authorization + 'static> server::Handler<HttpStream> for Router<A> {
 
 	fn on_request(&mut self, req: server::Request) -> Next {
+		// Reject requests with unknown Host header
+		if !host_validation::is_valid(&req, &self.allowed_hosts, &self.dapps_domain) {
+			self.handler = HostInvalidHandler::new();
+			return self.handler.on_request(req);
+		}
+
 		// Check authorization
 		let auth = self.authorization.is_authorized(&req);
 
@@ -63,16 +83,17 @@ impl<A: Authorization + 'static> server::Handler<HttpStream> for Router<A> {
 			Authorized::No(handler) => handler,
 			Authorized::Yes => {
 				let url = extract_url(&req);
-				let endpoint = extract_endpoint(&url);
+				let endpoint = extract_endpoint(&url, &self.dapps_domain);
+				let dispatcher = self.dispatcher.take();
 
 				match endpoint {
 					// First check special endpoints
 					(ref path, ref endpoint) if self.special.contains_key(endpoint) => {
-						self.special.get(endpoint).unwrap().to_handler(path.clone().unwrap_or_default())
+						endpoint_handler(&**self.special.get(endpoint).unwrap(), path.clone().unwrap_or_default(), dispatcher)
 					},
 					// Then delegate to dapp
 					(Some(ref path), _) if self.endpoints.contains_key(&path.app_id) => {
-						self.endpoints.get(&path.app_id).unwrap().to_handler(path.clone())
+						endpoint_handler(&**self.endpoints.get(&path.app_id).unwrap(), path.clone(), dispatcher)
 					},
 					// Redirection to main page
 					_ if *req.method() == hyper::method::Method::Get => {
@@ -80,7 +101,7 @@ impl<A: Authorization + 'static> server::Handler<HttpStream> for Router<A> {
 					},
 					// RPC by default
 					_ => {
-						self.special.get(&SpecialEndpoint::Rpc).unwrap().to_handler(EndpointPath::default())
+						endpoint_handler(&**self.special.get(&SpecialEndpoint::Rpc).unwrap(), EndpointPath::default(), dispatcher)
 					}
 				}
 			}
@@ -111,7 +132,10 @@ impl<A: Authorization> Router<A> {
 		main_page: &'static str,
 		endpoints: Arc<Endpoints>,
 		special: Arc<HashMap<SpecialEndpoint, Box<Endpoint>>>,
-		authorization: Arc<A>) -> Self {
+		authorization: Arc<A>,
+		allowed_hosts: Option<Vec<String>>,
+		dapps_domain: String,
+		dispatcher: Dispatcher) -> Self {
 
 		let handler = special.get(&SpecialEndpoint::Rpc).unwrap().to_handler(EndpointPath::default());
 		Router {
@@ -119,6 +143,9 @@ impl<A: Authorization> Router<A> {
 			endpoints: endpoints,
 			special: special,
 			authorization: authorization,
+			allowed_hosts: allowed_hosts,
+			dapps_domain: dapps_domain,
+			dispatcher: Some(dispatcher),
 			handler: handler,
 		}
 	}
@@ -150,7 +177,7 @@ fn extract_url(req: &server::Request) -> Option<Url> {
 	}
 }
 
-fn extract_endpoint(url: &Option<Url>) -> (Option<EndpointPath>, SpecialEndpoint) {
+fn extract_endpoint(url: &Option<Url>, dapps_domain: &str) -> (Option<EndpointPath>, SpecialEndpoint) {
 	fn special_endpoint(url: &Url) -> SpecialEndpoint {
 		if url.path.len() <= 1 {
 			return SpecialEndpoint::None;
@@ -160,14 +187,15 @@ fn extract_endpoint(url: &Option<Url>) -> (Option<EndpointPath>, SpecialEndpoint
 			apps::RPC_PATH => SpecialEndpoint::Rpc,
 			apps::API_PATH => SpecialEndpoint::Api,
 			apps::UTILS_PATH => SpecialEndpoint::Utils,
+			apps::PROXY_PATH => SpecialEndpoint::ProxyPac,
 			_ => SpecialEndpoint::None,
 		}
 	}
 
 	match *url {
 		Some(ref url) => match url.host {
-			Host::Domain(ref domain) if domain.ends_with(DAPPS_DOMAIN) => {
-				let len = domain.len() - DAPPS_DOMAIN.len();
+			Host::Domain(ref domain) if domain.ends_with(dapps_domain) => {
+				let len = domain.len() - dapps_domain.len();
 				let id = domain[0..len].to_owned();
 
 				(Some(EndpointPath {
@@ -192,11 +220,11 @@ fn extract_endpoint(url: &Option<Url>) -> (Option<EndpointPath>, SpecialEndpoint
 
 #[test]
 fn should_extract_endpoint() {
-	assert_eq!(extract_endpoint(&None), (None, SpecialEndpoint::None));
+	assert_eq!(extract_endpoint(&None, apps::DAPPS_DOMAIN), (None, SpecialEndpoint::None));
 
 	// With path prefix
 	assert_eq!(
-		extract_endpoint(&Url::parse("http://localhost:8080/status/index.html").ok()),
+		extract_endpoint(&Url::parse("http://localhost:8080/status/index.html").ok(), apps::DAPPS_DOMAIN),
 		(Some(EndpointPath {
 			app_id: "status".to_owned(),
 			host: "localhost".to_owned(),
@@ -206,7 +234,7 @@ fn should_extract_endpoint() {
 
 	// With path prefix
 	assert_eq!(
-		extract_endpoint(&Url::parse("http://localhost:8080/rpc/").ok()),
+		extract_endpoint(&Url::parse("http://localhost:8080/rpc/").ok(), apps::DAPPS_DOMAIN),
 		(Some(EndpointPath {
 			app_id: "rpc".to_owned(),
 			host: "localhost".to_owned(),
@@ -215,7 +243,7 @@ fn should_extract_endpoint() {
 	);
 
 	assert_eq!(
-		extract_endpoint(&Url::parse("http://my.status.parity/parity-utils/inject.js").ok()),
+		extract_endpoint(&Url::parse("http://my.status.parity/parity-utils/inject.js").ok(), apps::DAPPS_DOMAIN),
 		(Some(EndpointPath {
 			app_id: "my.status".to_owned(),
 			host: "my.status.parity".to_owned(),
@@ -225,7 +253,7 @@ fn should_extract_endpoint() {
 
 	// By Subdomain
 	assert_eq!(
-		extract_endpoint(&Url::parse("http://my.status.parity/test.html").ok()),
+		extract_endpoint(&Url::parse("http://my.status.parity/test.html").ok(), apps::DAPPS_DOMAIN),
 		(Some(EndpointPath {
 			app_id: "my.status".to_owned(),
 			host: "my.status.parity".to_owned(),
@@ -235,7 +263,7 @@ fn should_extract_endpoint() {
 
 	// RPC by subdomain
 	assert_eq!(
-		extract_endpoint(&Url::parse("http://my.status.parity/rpc/").ok()),
+		extract_endpoint(&Url::parse("http://my.status.parity/rpc/").ok(), apps::DAPPS_DOMAIN),
 		(Some(EndpointPath {
 			app_id: "my.status".to_owned(),
 			host: "my.status.parity".to_owned(),
@@ -243,9 +271,29 @@ fn should_extract_endpoint() {
 		}), SpecialEndpoint::Rpc)
 	);
 
+	// By custom domain
+	assert_eq!(
+		extract_endpoint(&Url::parse("http://status.dapp/test.html").ok(), ".dapp"),
+		(Some(EndpointPath {
+			app_id: "status".to_owned(),
+			host: "status.dapp".to_owned(),
+			port: 80,
+		}), SpecialEndpoint::None)
+	);
+
+	// Proxy PAC
+	assert_eq!(
+		extract_endpoint(&Url::parse("http://localhost:8080/proxy/proxy.pac").ok(), apps::DAPPS_DOMAIN),
+		(Some(EndpointPath {
+			app_id: "proxy".to_owned(),
+			host: "localhost".to_owned(),
+			port: 8080,
+		}), SpecialEndpoint::ProxyPac)
+	);
+
 	// API by subdomain
 	assert_eq!(
-		extract_endpoint(&Url::parse("http://my.status.parity/api/").ok()),
+		extract_endpoint(&Url::parse("http://my.status.parity/api/").ok(), apps::DAPPS_DOMAIN),
 		(Some(EndpointPath {
 			app_id: "my.status".to_owned(),
 			host: "my.status.parity".to_owned(),
@@ -14,28 +14,182 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::{io, mem, str};
+use std::io::{Read, Write};
 use std::sync::{Arc, Mutex};
-use jsonrpc_core::IoHandler;
-use jsonrpc_http_server::{ServerHandler, PanicHandler, AccessControlAllowOrigin};
+use hyper::{header, server, Decoder, Encoder, Next};
+use hyper::method::Method;
+use hyper::net::HttpStream;
+use hyper::status::StatusCode;
+use jsonrpc_http_server::AccessControlAllowOrigin;
+use ethcore_rpc::origin::OriginHandler;
+use ethcore_rpc::pooled::Dispatcher;
+use ethcore_rpc::read_only::FilteredHandler;
+use ethcore_rpc::panics::PanicGuard;
 use endpoint::{Endpoint, EndpointPath, Handler};
 
-pub fn rpc(handler: Arc<IoHandler>, panic_handler: Arc<Mutex<Option<Box<Fn() -> () + Send>>>>) -> Box<Endpoint> {
+pub fn rpc(
+	handler: Arc<FilteredHandler>,
+	panic_handler: Arc<Mutex<Option<Box<Fn() -> () + Send>>>>,
+	cors_domains: Vec<String>,
+) -> Box<Endpoint> {
 	Box::new(RpcEndpoint {
 		handler: handler,
 		panic_handler: panic_handler,
-		cors_domain: Some(AccessControlAllowOrigin::Null)
+		cors_domains: Arc::new(cors_domains),
 	})
 }
 
 struct RpcEndpoint {
-	handler: Arc<IoHandler>,
+	handler: Arc<FilteredHandler>,
 	panic_handler: Arc<Mutex<Option<Box<Fn() -> () + Send>>>>,
-	cors_domain: Option<AccessControlAllowOrigin>,
+	cors_domains: Arc<Vec<String>>,
+}
+
+impl RpcEndpoint {
+	fn handler(&self, dispatcher: Option<Dispatcher>) -> Box<Handler> {
+		Box::new(OriginHandler::new(RpcHandler {
+			handler: self.handler.clone(),
+			panic_handler: self.panic_handler.clone(),
+			cors_domains: self.cors_domains.clone(),
+			dispatcher: dispatcher,
+			status: StatusCode::Ok,
+			cors: None,
+			request: Vec::new(),
+			result: Arc::new(Mutex::new(Vec::new())),
+			response: Vec::new(),
+			write_pos: 0,
+		}, true))
+	}
 }
 
 impl Endpoint for RpcEndpoint {
 	fn to_handler(&self, _path: EndpointPath) -> Box<Handler> {
-		let panic_handler = PanicHandler { handler: self.panic_handler.clone() };
-		Box::new(ServerHandler::new(self.handler.clone(), self.cors_domain.clone(), panic_handler))
+		self.handler(None)
+	}
+
+	fn to_pooled_handler(&self, _path: EndpointPath, dispatcher: Dispatcher) -> Box<Handler> {
+		self.handler(Some(dispatcher))
+	}
+}
+
+/// Returns value of `Access-Control-Allow-Origin` header for request coming from given `origin`.
+///
+/// Empty list of domains keeps the permissive default (`null`), `*` allows any origin.
+/// Otherwise only configured origins are echoed back.
+fn cors_header(cors_domains: &[String], origin: Option<&str>) -> Option<AccessControlAllowOrigin> {
+	if cors_domains.is_empty() {
+		return Some(AccessControlAllowOrigin::Null);
 	}
+	if cors_domains.iter().any(|domain| domain == "*") {
+		return Some(AccessControlAllowOrigin::Any);
+	}
+	origin
+		.and_then(|origin| cors_domains.iter().find(|domain| *domain == origin))
+		.map(|domain| AccessControlAllowOrigin::Value(domain.clone()))
+}
+
+/// Handles single JSON-RPC request. Calls are run through the `Dispatcher` (if any).
+struct RpcHandler {
+	handler: Arc<FilteredHandler>,
+	panic_handler: Arc<Mutex<Option<Box<Fn() -> () + Send>>>>,
+	cors_domains: Arc<Vec<String>>,
+	dispatcher: Option<Dispatcher>,
+	status: StatusCode,
+	cors: Option<AccessControlAllowOrigin>,
+	request: Vec<u8>,
+	result: Arc<Mutex<Vec<u8>>>,
+	response: Vec<u8>,
+	write_pos: usize,
+}
+
+impl server::Handler<HttpStream> for RpcHandler {
+	fn on_request(&mut self, request: server::Request) -> Next {
+		let origin = request.headers().get_raw("Origin")
+			.and_then(|values| values.first())
+			.and_then(|value| str::from_utf8(value).ok());
+		self.cors = cors_header(&self.cors_domains, origin);
+
+		match *request.method() {
+			Method::Post => Next::read(),
+			Method::Options => Next::write(),
+			_ => {
+				self.status = StatusCode::MethodNotAllowed;
+				Next::write()
+			},
+		}
+	}
+
+	fn on_request_readable(&mut self, decoder: &mut Decoder<HttpStream>) -> Next {
+		let mut buf = [0u8; 4096];
+		loop {
+			match decoder.read(&mut buf) {
+				Ok(0) => break,
+				Ok(read) => self.request.extend_from_slice(&buf[..read]),
+				Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Next::read(),
+				Err(e) => {
+					debug!(target: "webapp", "Unable to read RPC request: {:?}", e);
+					return Next::end();
+				},
+			}
+		}
+
+		let handler = self.handler.clone();
+		let panic_handler = self.panic_handler.clone();
+		let request = String::from_utf8_lossy(&mem::replace(&mut self.request, Vec::new())).into_owned();
+		let result = self.result.clone();
+		let job = move || {
+			let _guard = PanicGuard(panic_handler);
+			*result.lock().unwrap() = handler.handle_request(&request).map_or_else(Vec::new, String::into_bytes);
+		};
+		match self.dispatcher {
+			Some(ref mut dispatcher) => dispatcher.dispatch(job),
+			None => {
+				job();
+				Next::write()
+			},
+		}
+	}
+
+	fn on_response(&mut self, response: &mut server::Response) -> Next {
+		self.response = mem::replace(&mut *self.result.lock().unwrap(), Vec::new());
+		response.set_status(self.status);
+		if let Some(ref cors) = self.cors {
+			response.headers_mut().set(cors.clone());
+			response.headers_mut().set_raw("Access-Control-Allow-Methods", vec![b"OPTIONS, POST".to_vec()]);
+			response.headers_mut().set_raw("Access-Control-Allow-Headers", vec![b"Content-Type".to_vec()]);
+		}
+		response.headers_mut().set(header::ContentType("application/json".parse().unwrap()));
+		response.headers_mut().set(header::ContentLength(self.response.len() as u64));
+		Next::write()
+	}
+
+	fn on_response_writable(&mut self, encoder: &mut Encoder<HttpStream>) -> Next {
+		if self.write_pos == self.response.len() {
+			return Next::end();
+		}
+		match encoder.write(&self.response[self.write_pos..]) {
+			Ok(bytes) => {
+				self.write_pos += bytes;
+				Next::write()
+			},
+			Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Next::write(),
+			Err(_) => Next::end(),
+		}
+	}
+}
+
+#[test]
+fn should_echo_only_configured_origins() {
+	let domains = vec!["http://parity.io".to_owned()];
+	let is_value = |cors: Option<AccessControlAllowOrigin>, expected: &str| match cors {
+		Some(AccessControlAllowOrigin::Value(ref value)) => value == expected,
+		_ => false,
+	};
+
+	assert!(match cors_header(&[], Some("http://evil.com")) { Some(AccessControlAllowOrigin::Null) => true, _ => false });
+	assert!(match cors_header(&["*".to_owned()], Some("http://evil.com")) { Some(AccessControlAllowOrigin::Any) => true, _ => false });
+	assert!(is_value(cors_header(&domains, Some("http://parity.io")), "http://parity.io"));
+	assert!(cors_header(&domains, Some("http://evil.com")).is_none());
+	assert!(cors_header(&domains, None).is_none());
 }